@@ -0,0 +1,165 @@
+#ifndef EKIDEN_H
+#define EKIDEN_H
+
+/* Generated with cbindgen; regenerate with
+ * `cbindgen --config cbindgen.toml --output include/ekiden.h` */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Status returned by functions that succeed
+#define EKIDEN_OK 0
+
+// Status returned by functions that fail; see `ekiden_last_error`
+#define EKIDEN_ERROR -1
+
+// Event stream channel kinds
+typedef enum EkidenChannel {
+  // Orderbook snapshots and updates for a market address
+  EKIDEN_CHANNEL_ORDERBOOK,
+  // Public trades for a market address
+  EKIDEN_CHANNEL_TRADES,
+  // Order, position and balance updates for a user address
+  EKIDEN_CHANNEL_USER,
+} EkidenChannel;
+
+// Opaque client handle
+typedef struct EkidenHandle EkidenHandle;
+
+// Opaque event subscription handle
+typedef struct EkidenSubscription EkidenSubscription;
+
+// Callback receiving each event as JSON; the string is only valid during the call
+typedef void (*EkidenEventCallback)(const char *event_json, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Get the last error message on this thread, or NULL if none
+//
+// The pointer stays valid until the next failing call on the same thread.
+const char *ekiden_last_error(void);
+
+// Free a string returned by this library
+//
+// # Safety
+//
+// `s` must be NULL or a pointer returned by this library that was not freed yet.
+void ekiden_string_free(char *s);
+
+// Create a client for `base_url` (NULL for production), optionally with a private key
+//
+// Returns NULL on failure.
+//
+// # Safety
+//
+// `base_url` and `private_key` must each be NULL or a valid NUL-terminated string.
+EkidenHandle *ekiden_client_new(const char *base_url, const char *private_key);
+
+// Destroy a client handle
+//
+// # Safety
+//
+// `handle` must be NULL or a handle from `ekiden_client_new` that was not freed yet.
+// Subscriptions created from it must be released first.
+void ekiden_client_free(EkidenHandle *handle);
+
+// Authorize with the configured private key
+//
+// # Safety
+//
+// `handle` must be a live handle from `ekiden_client_new`.
+int32_t ekiden_authorize(const EkidenHandle *handle);
+
+// Get all markets as a JSON array
+//
+// # Safety
+//
+// `handle` must be a live handle from `ekiden_client_new`.
+char *ekiden_get_markets(const EkidenHandle *handle);
+
+// Get orders on a market as a JSON array
+//
+// # Safety
+//
+// `handle` must be a live handle and `market_addr` a valid NUL-terminated string.
+char *ekiden_get_orders(const EkidenHandle *handle, const char *market_addr);
+
+// Get the user's positions as a JSON array
+//
+// # Safety
+//
+// `handle` must be a live handle from `ekiden_client_new`.
+char *ekiden_get_positions(const EkidenHandle *handle);
+
+// Get the user's portfolio as a JSON object
+//
+// # Safety
+//
+// `handle` must be a live handle from `ekiden_client_new`.
+char *ekiden_get_portfolio(const EkidenHandle *handle);
+
+// Get the most recent candles for a market as a JSON array
+//
+// # Safety
+//
+// `handle` must be a live handle; `market_addr` and `interval` valid NUL-terminated strings.
+char *ekiden_get_candles(const EkidenHandle *handle,
+                         const char *market_addr,
+                         const char *interval,
+                         uint32_t limit);
+
+// Sign a JSON array of actions and send them as one intent
+//
+// Returns the intent response as JSON.
+//
+// # Safety
+//
+// `handle` must be a live handle and `actions_json` a valid NUL-terminated string.
+char *ekiden_send_actions(const EkidenHandle *handle, const char *actions_json);
+
+// Connect the client's WebSocket
+//
+// # Safety
+//
+// `handle` must be a live handle from `ekiden_client_new`.
+int32_t ekiden_connect_websocket(const EkidenHandle *handle);
+
+// Subscribe to a channel, invoking `callback` on a runtime thread for every event
+//
+// Returns NULL on failure. The WebSocket must be connected first.
+//
+// # Safety
+//
+// `handle` must be a live handle and `addr` a valid NUL-terminated string.
+// `user_data` is passed back to `callback` untouched and must stay valid, and
+// safe to use from another thread, until `ekiden_unsubscribe` returns.
+// Subscribing to the same channel twice shares one gateway subscription, so
+// unsubscribing one of them ends both.
+EkidenSubscription *ekiden_subscribe(const EkidenHandle *handle,
+                                     EkidenChannel channel,
+                                     const char *addr,
+                                     EkidenEventCallback callback,
+                                     void *user_data);
+
+// Stop a subscription and unsubscribe its channel on the gateway
+//
+// Returns once no callback for the subscription is running or will run, so
+// its `user_data` may be freed afterwards. The subscription is released even
+// if the gateway unsubscribe fails.
+//
+// # Safety
+//
+// `subscription` must be NULL or a pointer from `ekiden_subscribe` that was not released yet,
+// and its client handle must still be alive. It must not be called from the
+// subscription's callback.
+int32_t ekiden_unsubscribe(EkidenSubscription *subscription);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* EKIDEN_H */
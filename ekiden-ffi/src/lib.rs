@@ -0,0 +1,491 @@
+//! C bindings for the Ekiden Rust SDK.
+//!
+//! Every client handle owns a Tokio runtime, so calls are blocking from the
+//! caller's point of view. Functions returning `char*` hand back JSON encoded
+//! with the SDK's field names; release those strings with [`ekiden_string_free`].
+//! On failure they return NULL (or `EKIDEN_ERROR`) and the message is available
+//! from [`ekiden_last_error`] on the calling thread.
+//!
+//! The C header lives in `include/ekiden.h` and is generated with
+//! `cbindgen --config cbindgen.toml --output include/ekiden.h`.
+
+use ekiden_rust_sdk::ws::channels;
+use ekiden_rust_sdk::{
+    ActionPayload, EkidenClient, EkidenConfig, ListMarketsParams, ListOrdersParams, Pagination,
+    WsEvent,
+};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// Status returned by functions that succeed
+pub const EKIDEN_OK: i32 = 0;
+/// Status returned by functions that fail; see `ekiden_last_error`
+pub const EKIDEN_ERROR: i32 = -1;
+
+/// Opaque client handle
+pub struct EkidenHandle {
+    runtime: Runtime,
+    client: EkidenClient,
+}
+
+/// Opaque event subscription handle
+pub struct EkidenSubscription {
+    handle: *const EkidenHandle,
+    channel: String,
+    task: JoinHandle<()>,
+}
+
+/// Event stream channel kinds
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EkidenChannel {
+    /// Orderbook snapshots and updates for a market address
+    Orderbook,
+    /// Public trades for a market address
+    Trades,
+    /// Order, position and balance updates for a user address
+    User,
+}
+
+/// Callback receiving each event as JSON; the string is only valid during the call
+pub type EkidenEventCallback = extern "C" fn(event_json: *const c_char, user_data: *mut c_void);
+
+struct UserData(*mut c_void);
+
+// The caller guarantees `user_data` may be used from the runtime's threads
+unsafe impl Send for UserData {}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error<S: ToString>(message: S) {
+    let message = CString::new(message.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Run `f`, recording any error or panic as the thread's last error
+fn guard<T, F>(f: F) -> Option<T>
+where
+    F: FnOnce() -> Result<T, String>,
+{
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(message)) => {
+            set_last_error(message);
+            None
+        }
+        Err(_) => {
+            set_last_error("panic in ekiden-ffi");
+            None
+        }
+    }
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, String> {
+    if ptr.is_null() {
+        return Err(format!("{} must not be NULL", name));
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| format!("{} is not valid UTF-8", name))
+}
+
+unsafe fn handle_arg<'a>(handle: *const EkidenHandle) -> Result<&'a EkidenHandle, String> {
+    handle
+        .as_ref()
+        .ok_or_else(|| "client handle must not be NULL".to_string())
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<*mut c_char, String> {
+    let json = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    CString::new(json)
+        .map(CString::into_raw)
+        .map_err(|e| e.to_string())
+}
+
+/// Run an SDK query on the handle's runtime and return the result as JSON
+unsafe fn query<T, F, Fut>(handle: *const EkidenHandle, f: F) -> *mut c_char
+where
+    T: Serialize,
+    F: FnOnce(EkidenClient) -> Fut,
+    Fut: std::future::Future<Output = ekiden_rust_sdk::Result<T>>,
+{
+    guard(|| {
+        let handle = handle_arg(handle)?;
+        let value = handle
+            .runtime
+            .block_on(f(handle.client.clone()))
+            .map_err(|e| e.to_string())?;
+        to_json(&value)
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Get the last error message on this thread, or NULL if none
+///
+/// The pointer stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn ekiden_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+/// Free a string returned by this library
+///
+/// # Safety
+///
+/// `s` must be NULL or a pointer returned by this library that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Create a client for `base_url` (NULL for production), optionally with a private key
+///
+/// Returns NULL on failure.
+///
+/// # Safety
+///
+/// `base_url` and `private_key` must each be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_client_new(
+    base_url: *const c_char,
+    private_key: *const c_char,
+) -> *mut EkidenHandle {
+    guard(|| {
+        let config = if base_url.is_null() {
+            EkidenConfig::production()
+        } else {
+            EkidenConfig::new(str_arg(base_url, "base_url")?)
+        }
+        .map_err(|e| e.to_string())?;
+
+        let runtime = Runtime::new().map_err(|e| e.to_string())?;
+        let client = EkidenClient::new(config).map_err(|e| e.to_string())?;
+        if !private_key.is_null() {
+            let private_key = str_arg(private_key, "private_key")?;
+            runtime
+                .block_on(client.set_private_key(private_key))
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(Box::into_raw(Box::new(EkidenHandle { runtime, client })))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Destroy a client handle
+///
+/// # Safety
+///
+/// `handle` must be NULL or a handle from `ekiden_client_new` that was not freed yet.
+/// Subscriptions created from it must be released first.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_client_free(handle: *mut EkidenHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Authorize with the configured private key
+///
+/// # Safety
+///
+/// `handle` must be a live handle from `ekiden_client_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_authorize(handle: *const EkidenHandle) -> i32 {
+    guard(|| {
+        let handle = handle_arg(handle)?;
+        handle
+            .runtime
+            .block_on(handle.client.authorize())
+            .map_err(|e| e.to_string())
+    })
+    .map_or(EKIDEN_ERROR, |_| EKIDEN_OK)
+}
+
+/// Get all markets as a JSON array
+///
+/// # Safety
+///
+/// `handle` must be a live handle from `ekiden_client_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_get_markets(handle: *const EkidenHandle) -> *mut c_char {
+    query(handle, |client| async move {
+        client.get_markets(ListMarketsParams::default()).await
+    })
+}
+
+/// Get orders on a market as a JSON array
+///
+/// # Safety
+///
+/// `handle` must be a live handle and `market_addr` a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_get_orders(
+    handle: *const EkidenHandle,
+    market_addr: *const c_char,
+) -> *mut c_char {
+    let market_addr = match guard(|| str_arg(market_addr, "market_addr")) {
+        Some(market_addr) => market_addr.to_string(),
+        None => return ptr::null_mut(),
+    };
+    query(handle, |client| async move {
+        let params = ListOrdersParams {
+            market_addr,
+            side: None,
+            pagination: Pagination::default(),
+        };
+        client.get_orders(params).await
+    })
+}
+
+/// Get the user's positions as a JSON array
+///
+/// # Safety
+///
+/// `handle` must be a live handle from `ekiden_client_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_get_positions(handle: *const EkidenHandle) -> *mut c_char {
+    query(handle, |client| async move {
+        client.get_all_user_positions().await
+    })
+}
+
+/// Get the user's portfolio as a JSON object
+///
+/// # Safety
+///
+/// `handle` must be a live handle from `ekiden_client_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_get_portfolio(handle: *const EkidenHandle) -> *mut c_char {
+    query(
+        handle,
+        |client| async move { client.get_user_portfolio().await },
+    )
+}
+
+/// Get the most recent candles for a market as a JSON array
+///
+/// # Safety
+///
+/// `handle` must be a live handle; `market_addr` and `interval` valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_get_candles(
+    handle: *const EkidenHandle,
+    market_addr: *const c_char,
+    interval: *const c_char,
+    limit: u32,
+) -> *mut c_char {
+    let args = guard(|| {
+        Ok((
+            str_arg(market_addr, "market_addr")?.to_string(),
+            str_arg(interval, "interval")?.to_string(),
+        ))
+    });
+    let Some((market_addr, interval)) = args else {
+        return ptr::null_mut();
+    };
+    query(handle, |client| async move {
+        client
+            .get_recent_candles(&market_addr, &interval, Some(limit))
+            .await
+    })
+}
+
+/// Sign a JSON array of actions and send them as one intent
+///
+/// Returns the intent response as JSON.
+///
+/// # Safety
+///
+/// `handle` must be a live handle and `actions_json` a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_send_actions(
+    handle: *const EkidenHandle,
+    actions_json: *const c_char,
+) -> *mut c_char {
+    let actions = guard(|| {
+        serde_json::from_str::<Vec<ActionPayload>>(str_arg(actions_json, "actions_json")?)
+            .map_err(|e| format!("invalid actions: {}", e))
+    });
+    let Some(actions) = actions else {
+        return ptr::null_mut();
+    };
+    query(handle, |client| async move {
+        client.send_actions(actions).await
+    })
+}
+
+/// Connect the client's WebSocket
+///
+/// # Safety
+///
+/// `handle` must be a live handle from `ekiden_client_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_connect_websocket(handle: *const EkidenHandle) -> i32 {
+    guard(|| {
+        let handle = handle_arg(handle)?;
+        handle
+            .runtime
+            .block_on(handle.client.connect_websocket())
+            .map_err(|e| e.to_string())
+    })
+    .map_or(EKIDEN_ERROR, |_| EKIDEN_OK)
+}
+
+/// Subscribe to a channel, invoking `callback` on a runtime thread for every event
+///
+/// Returns NULL on failure. The WebSocket must be connected first.
+///
+/// # Safety
+///
+/// `handle` must be a live handle and `addr` a valid NUL-terminated string.
+/// `user_data` is passed back to `callback` untouched and must stay valid, and
+/// safe to use from another thread, until `ekiden_unsubscribe` returns.
+/// Subscribing to the same channel twice shares one gateway subscription, so
+/// unsubscribing one of them ends both.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_subscribe(
+    handle: *const EkidenHandle,
+    channel: EkidenChannel,
+    addr: *const c_char,
+    callback: EkidenEventCallback,
+    user_data: *mut c_void,
+) -> *mut EkidenSubscription {
+    let user_data = UserData(user_data);
+    guard(move || {
+        let handle = handle_arg(handle)?;
+        let addr = str_arg(addr, "addr")?;
+        let client = &handle.client;
+        let channel_name = match channel {
+            EkidenChannel::Orderbook => channels::orderbook(addr),
+            EkidenChannel::Trades => channels::trades(addr),
+            EkidenChannel::User => channels::user(addr),
+        };
+        let mut receiver = handle
+            .runtime
+            .block_on(async {
+                match channel {
+                    EkidenChannel::Orderbook => client.subscribe_orderbook(addr).await,
+                    EkidenChannel::Trades => client.subscribe_trades(addr).await,
+                    EkidenChannel::User => client.subscribe_user(addr).await,
+                }
+            })
+            .map_err(|e| e.to_string())?;
+
+        let task = handle.runtime.spawn(async move {
+            let user_data = user_data;
+            loop {
+                let event: WsEvent = match receiver.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                let Ok(json) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if let Ok(json) = CString::new(json) {
+                    callback(json.as_ptr(), user_data.0);
+                }
+            }
+        });
+
+        Ok(Box::into_raw(Box::new(EkidenSubscription {
+            handle,
+            channel: channel_name,
+            task,
+        })))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Stop a subscription and unsubscribe its channel on the gateway
+///
+/// Returns once no callback for the subscription is running or will run, so
+/// its `user_data` may be freed afterwards. The subscription is released even
+/// if the gateway unsubscribe fails.
+///
+/// # Safety
+///
+/// `subscription` must be NULL or a pointer from `ekiden_subscribe` that was not released yet,
+/// and its client handle must still be alive. It must not be called from the
+/// subscription's callback.
+#[no_mangle]
+pub unsafe extern "C" fn ekiden_unsubscribe(subscription: *mut EkidenSubscription) -> i32 {
+    if subscription.is_null() {
+        return EKIDEN_OK;
+    }
+    let EkidenSubscription {
+        handle,
+        channel,
+        task,
+    } = *Box::from_raw(subscription);
+    guard(|| {
+        let handle = handle_arg(handle)?;
+        task.abort();
+        handle.runtime.block_on(async {
+            // An aborted task may be mid-callback until it is joined
+            let _ = task.await;
+            handle
+                .client
+                .unsubscribe(&channel)
+                .await
+                .map_err(|e| e.to_string())
+        })
+    })
+    .map_or(EKIDEN_ERROR, |_| EKIDEN_OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> String {
+        let message = ekiden_last_error();
+        assert!(!message.is_null());
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_null_handle_sets_last_error() {
+        let markets = unsafe { ekiden_get_markets(ptr::null()) };
+        assert!(markets.is_null());
+        assert!(last_error().contains("handle"));
+    }
+
+    #[test]
+    fn test_invalid_base_url() {
+        let url = CString::new("not a url").unwrap();
+        let handle = unsafe { ekiden_client_new(url.as_ptr(), ptr::null()) };
+        assert!(handle.is_null());
+        assert!(!last_error().is_empty());
+    }
+
+    #[test]
+    fn test_client_lifecycle() {
+        let url = CString::new("http://localhost:3010/api/v1").unwrap();
+        let handle = unsafe { ekiden_client_new(url.as_ptr(), ptr::null()) };
+        assert!(!handle.is_null());
+
+        let actions = CString::new("not json").unwrap();
+        let response = unsafe { ekiden_send_actions(handle, actions.as_ptr()) };
+        assert!(response.is_null());
+        assert!(last_error().starts_with("invalid actions"));
+
+        unsafe {
+            ekiden_client_free(handle);
+            ekiden_string_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Basis monitoring between mark, oracle and index prices.
+//!
+//! [`BasisMonitor`] keeps the latest basis per market and broadcasts a
+//! [`BasisAlert`] whenever a market crosses one of the configured thresholds,
+//! and again once it returns within them.
+
+use crate::client::EkidenClient;
+use crate::error::Result;
+use crate::types::{ListMarketsParams, MarketResponse};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Thresholds that trigger basis alerts
+#[derive(Debug, Clone)]
+pub struct BasisThresholds {
+    /// Maximum absolute mark/oracle basis, in basis points
+    pub max_basis_bps: f64,
+    /// Maximum absolute funding-implied APR, as a fraction (0.5 = 50%)
+    pub max_funding_apr: f64,
+    /// Interval between funding payments, used to annualize funding rates
+    pub funding_interval: Duration,
+}
+
+impl Default for BasisThresholds {
+    fn default() -> Self {
+        Self {
+            max_basis_bps: 50.0,
+            max_funding_apr: 1.0,
+            funding_interval: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// Latest basis state of a market
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasisSnapshot {
+    pub market_addr: String,
+    pub symbol: String,
+    pub mark_price: u64,
+    pub oracle_price: u64,
+    pub index_price: Option<u64>,
+    /// Mark versus oracle, in basis points
+    pub basis_bps: f64,
+    /// Mark versus index, in basis points
+    pub index_basis_bps: Option<f64>,
+    /// Latest funding rate per funding interval
+    pub funding_rate: Option<f64>,
+    /// Funding rate annualized over the funding interval
+    pub funding_apr: Option<f64>,
+}
+
+/// Alert emitted when a market crosses a threshold
+#[derive(Debug, Clone, PartialEq)]
+pub enum BasisAlert {
+    BasisExceeded {
+        market_addr: String,
+        basis_bps: f64,
+        threshold_bps: f64,
+    },
+    BasisRecovered {
+        market_addr: String,
+        basis_bps: f64,
+    },
+    FundingExceeded {
+        market_addr: String,
+        funding_apr: f64,
+        threshold_apr: f64,
+    },
+    FundingRecovered {
+        market_addr: String,
+        funding_apr: f64,
+    },
+}
+
+#[derive(Debug, Default)]
+struct MarketBasis {
+    snapshot: Option<BasisSnapshot>,
+    index_price: Option<u64>,
+    funding_rate: Option<f64>,
+    basis_breached: bool,
+    funding_breached: bool,
+}
+
+/// Tracks basis and funding per market and emits threshold alerts
+#[derive(Debug)]
+pub struct BasisMonitor {
+    thresholds: BasisThresholds,
+    markets: HashMap<String, MarketBasis>,
+    alerts: broadcast::Sender<BasisAlert>,
+}
+
+fn bps(price: u64, reference: u64) -> Option<f64> {
+    if reference == 0 {
+        return None;
+    }
+    Some((price as f64 - reference as f64) / reference as f64 * 10_000.0)
+}
+
+impl BasisMonitor {
+    /// Create a monitor with the given thresholds
+    pub fn new(thresholds: BasisThresholds) -> Self {
+        let (alerts, _) = broadcast::channel(100);
+        Self {
+            thresholds,
+            markets: HashMap::new(),
+            alerts,
+        }
+    }
+
+    /// Subscribe to threshold alerts
+    pub fn subscribe(&self) -> broadcast::Receiver<BasisAlert> {
+        self.alerts.subscribe()
+    }
+
+    /// Get the latest snapshot for a market
+    pub fn snapshot(&self, market_addr: &str) -> Option<&BasisSnapshot> {
+        self.markets.get(market_addr)?.snapshot.as_ref()
+    }
+
+    /// Get the latest snapshots of all markets
+    pub fn snapshots(&self) -> Vec<BasisSnapshot> {
+        self.markets
+            .values()
+            .filter_map(|market| market.snapshot.clone())
+            .collect()
+    }
+
+    /// Annualize a per-interval funding rate
+    pub fn funding_apr(&self, funding_rate: f64) -> f64 {
+        let year = 365.0 * 24.0 * 60.0 * 60.0;
+        let interval = self.thresholds.funding_interval.as_secs_f64().max(1.0);
+        funding_rate * year / interval
+    }
+
+    /// Set an external index price for a market
+    pub fn update_index(&mut self, market_addr: &str, index_price: u64) -> Vec<BasisAlert> {
+        self.markets
+            .entry(market_addr.to_string())
+            .or_default()
+            .index_price = Some(index_price);
+        self.recompute(market_addr)
+    }
+
+    /// Set the latest funding rate for a market
+    pub fn update_funding(&mut self, market_addr: &str, funding_rate: f64) -> Vec<BasisAlert> {
+        self.markets
+            .entry(market_addr.to_string())
+            .or_default()
+            .funding_rate = Some(funding_rate);
+        self.recompute(market_addr)
+    }
+
+    /// Update mark and oracle prices from market info
+    pub fn update_market(&mut self, market: &MarketResponse) -> Vec<BasisAlert> {
+        let entry = self.markets.entry(market.addr.clone()).or_default();
+        let snapshot = entry.snapshot.get_or_insert_with(|| BasisSnapshot {
+            market_addr: market.addr.clone(),
+            symbol: market.symbol.clone(),
+            mark_price: 0,
+            oracle_price: 0,
+            index_price: None,
+            basis_bps: 0.0,
+            index_basis_bps: None,
+            funding_rate: None,
+            funding_apr: None,
+        });
+        snapshot.mark_price = market.mark_price;
+        snapshot.oracle_price = market.oracle_price;
+        self.recompute(&market.addr)
+    }
+
+    /// Fetch all markets and their current funding rates and update the monitor
+    pub async fn refresh(&mut self, client: &EkidenClient) -> Result<Vec<BasisAlert>> {
+        let markets = client.get_markets(ListMarketsParams::default()).await?;
+        let mut alerts = Vec::new();
+        for market in &markets {
+            if market.addr.is_empty() {
+                continue;
+            }
+            if let Some(rate) = client.get_current_funding_rate(&market.addr).await? {
+                self.markets
+                    .entry(market.addr.clone())
+                    .or_default()
+                    .funding_rate = Some(rate.funding_rate);
+            }
+            alerts.extend(self.update_market(market));
+        }
+        Ok(alerts)
+    }
+
+    fn recompute(&mut self, market_addr: &str) -> Vec<BasisAlert> {
+        let funding_apr = self
+            .markets
+            .get(market_addr)
+            .and_then(|market| market.funding_rate)
+            .map(|rate| self.funding_apr(rate));
+        let Some(market) = self.markets.get_mut(market_addr) else {
+            return Vec::new();
+        };
+        let Some(snapshot) = market.snapshot.as_mut() else {
+            return Vec::new();
+        };
+
+        snapshot.index_price = market.index_price;
+        snapshot.basis_bps = bps(snapshot.mark_price, snapshot.oracle_price).unwrap_or(0.0);
+        snapshot.index_basis_bps = market
+            .index_price
+            .and_then(|index| bps(snapshot.mark_price, index));
+        snapshot.funding_rate = market.funding_rate;
+        snapshot.funding_apr = funding_apr;
+
+        let mut alerts = Vec::new();
+        let basis_bps = snapshot.basis_bps;
+        let basis_breached = basis_bps.abs() > self.thresholds.max_basis_bps;
+        if basis_breached != market.basis_breached {
+            market.basis_breached = basis_breached;
+            alerts.push(if basis_breached {
+                BasisAlert::BasisExceeded {
+                    market_addr: market_addr.to_string(),
+                    basis_bps,
+                    threshold_bps: self.thresholds.max_basis_bps,
+                }
+            } else {
+                BasisAlert::BasisRecovered {
+                    market_addr: market_addr.to_string(),
+                    basis_bps,
+                }
+            });
+        }
+
+        if let Some(funding_apr) = funding_apr {
+            let funding_breached = funding_apr.abs() > self.thresholds.max_funding_apr;
+            if funding_breached != market.funding_breached {
+                market.funding_breached = funding_breached;
+                alerts.push(if funding_breached {
+                    BasisAlert::FundingExceeded {
+                        market_addr: market_addr.to_string(),
+                        funding_apr,
+                        threshold_apr: self.thresholds.max_funding_apr,
+                    }
+                } else {
+                    BasisAlert::FundingRecovered {
+                        market_addr: market_addr.to_string(),
+                        funding_apr,
+                    }
+                });
+            }
+        }
+
+        for alert in &alerts {
+            // No subscribers is not an error for a monitor
+            let _ = self.alerts.send(alert.clone());
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(mark_price: u64, oracle_price: u64) -> MarketResponse {
+        MarketResponse {
+            mark_price,
+            oracle_price,
+            ..crate::test_utils::market()
+        }
+    }
+
+    #[test]
+    fn test_basis_alerts_on_crossing() {
+        let mut monitor = BasisMonitor::new(BasisThresholds::default());
+        let mut rx = monitor.subscribe();
+
+        assert!(monitor.update_market(&market(10_010, 10_000)).is_empty());
+        assert_eq!(monitor.snapshot("0x1").unwrap().basis_bps, 10.0);
+
+        let alerts = monitor.update_market(&market(10_100, 10_000));
+        assert!(matches!(alerts[..], [BasisAlert::BasisExceeded { .. }]));
+        // Staying beyond the threshold does not alert again
+        assert!(monitor.update_market(&market(10_200, 10_000)).is_empty());
+
+        let alerts = monitor.update_market(&market(10_000, 10_000));
+        assert!(matches!(alerts[..], [BasisAlert::BasisRecovered { .. }]));
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            BasisAlert::BasisExceeded { .. }
+        ));
+    }
+
+    #[test]
+    fn test_funding_apr() {
+        let mut monitor = BasisMonitor::new(BasisThresholds::default());
+        monitor.update_market(&market(10_000, 10_000));
+
+        let alerts = monitor.update_funding("0x1", 0.0002);
+        let apr = monitor.snapshot("0x1").unwrap().funding_apr.unwrap();
+        assert!((apr - 0.0002 * 24.0 * 365.0).abs() < 1e-9);
+        assert!(matches!(alerts[..], [BasisAlert::FundingExceeded { .. }]));
+    }
+}
@@ -0,0 +1,177 @@
+//! Interval VWAP/TWAP benchmarks over fills.
+//!
+//! Timestamps are compared as-is, so `start` and `end` must use the same unit
+//! as the fills returned by the gateway. Intervals are half-open: `[start, end)`.
+
+use crate::types::{FillResponse, OrderSide};
+
+/// Volume-weighted average price of the fills within `[start, end)`
+pub fn vwap(fills: &[FillResponse], start: u64, end: u64) -> Option<f64> {
+    let (notional, volume) = fills
+        .iter()
+        .filter(|fill| fill.timestamp >= start && fill.timestamp < end)
+        .fold((0u128, 0u128), |(notional, volume), fill| {
+            (
+                notional + fill.price as u128 * fill.size as u128,
+                volume + fill.size as u128,
+            )
+        });
+
+    if volume == 0 {
+        return None;
+    }
+    Some(notional as f64 / volume as f64)
+}
+
+/// Time-weighted average price over `[start, end)`.
+///
+/// Each trade price is held until the next trade. The last trade before
+/// `start`, if any, sets the price at the start of the interval; otherwise
+/// the average starts at the first trade inside it.
+pub fn twap(fills: &[FillResponse], start: u64, end: u64) -> Option<f64> {
+    if end <= start {
+        return None;
+    }
+
+    let mut sorted: Vec<&FillResponse> = fills.iter().filter(|fill| fill.timestamp < end).collect();
+    sorted.sort_by_key(|fill| (fill.timestamp, fill.seq));
+
+    let mut current: Option<(u64, u64)> = None;
+    let mut weighted = 0f64;
+    let mut elapsed = 0u64;
+
+    for fill in sorted {
+        let at = fill.timestamp.max(start);
+        if let Some((since, price)) = current {
+            let held = at - since;
+            weighted += price as f64 * held as f64;
+            elapsed += held;
+        }
+        current = Some((at, fill.price));
+    }
+
+    let (since, price) = current?;
+    let held = end - since;
+    weighted += price as f64 * held as f64;
+    elapsed += held;
+
+    if elapsed == 0 {
+        return None;
+    }
+    Some(weighted / elapsed as f64)
+}
+
+/// Select the fills in which `user_addr` was the taker or the maker
+pub fn user_fills<'a>(fills: &'a [FillResponse], user_addr: &str) -> Vec<&'a FillResponse> {
+    fills
+        .iter()
+        .filter(|fill| {
+            fill.taker_addr.eq_ignore_ascii_case(user_addr)
+                || fill.maker_addr.eq_ignore_ascii_case(user_addr)
+        })
+        .collect()
+}
+
+/// Execution quality of a set of own fills against the market
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionBenchmark {
+    /// Total executed size
+    pub filled_size: u64,
+    /// Size-weighted average price of the own fills
+    pub avg_price: f64,
+    /// Market VWAP over the execution window
+    pub market_vwap: Option<f64>,
+    /// Market TWAP over the execution window
+    pub market_twap: Option<f64>,
+    /// Cost versus the market VWAP in basis points, positive when worse
+    pub vs_vwap_bps: Option<f64>,
+    /// Cost versus the market TWAP in basis points, positive when worse
+    pub vs_twap_bps: Option<f64>,
+}
+
+/// Benchmark own fills against market fills over the execution window.
+///
+/// The window spans from the first to just after the last own fill. `side`
+/// is the direction of the execution and decides the sign of the cost.
+pub fn benchmark_execution(
+    own_fills: &[FillResponse],
+    market_fills: &[FillResponse],
+    side: OrderSide,
+) -> Option<ExecutionBenchmark> {
+    let start = own_fills.iter().map(|fill| fill.timestamp).min()?;
+    let end = own_fills.iter().map(|fill| fill.timestamp).max()? + 1;
+    let avg_price = vwap(own_fills, start, end)?;
+    let filled_size = own_fills.iter().map(|fill| fill.size).sum();
+
+    let market_vwap = vwap(market_fills, start, end);
+    let market_twap = twap(market_fills, start, end);
+    let cost_bps = |benchmark: f64| {
+        let diff = match side {
+            OrderSide::Buy => avg_price - benchmark,
+            OrderSide::Sell => benchmark - avg_price,
+        };
+        diff / benchmark * 10_000.0
+    };
+
+    Some(ExecutionBenchmark {
+        filled_size,
+        avg_price,
+        market_vwap,
+        market_twap,
+        vs_vwap_bps: market_vwap.map(cost_bps),
+        vs_twap_bps: market_twap.map(cost_bps),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(timestamp: u64, price: u64, size: u64, taker: &str) -> FillResponse {
+        FillResponse {
+            sid: timestamp.to_string(),
+            price,
+            size,
+            side: "buy".to_string(),
+            taker_addr: taker.to_string(),
+            maker_addr: "0xmaker".to_string(),
+            market_addr: "0x1".to_string(),
+            seq: timestamp,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_vwap() {
+        let fills = vec![
+            fill(0, 100, 1, "0xa"),
+            fill(5, 110, 3, "0xb"),
+            fill(20, 500, 1, "0xa"),
+        ];
+        assert_eq!(vwap(&fills, 0, 10), Some(107.5));
+        assert_eq!(vwap(&fills, 10, 20), None);
+    }
+
+    #[test]
+    fn test_twap_holds_prices_between_trades() {
+        let fills = vec![fill(0, 100, 1, "0xa"), fill(5, 110, 100, "0xb")];
+        // 100 for 5 units of time, then 110 for 5
+        assert_eq!(twap(&fills, 0, 10), Some(105.0));
+        // Price before the window carries into it
+        assert_eq!(twap(&fills, 2, 10), Some((100.0 * 3.0 + 110.0 * 5.0) / 8.0));
+        assert_eq!(twap(&[], 0, 10), None);
+    }
+
+    #[test]
+    fn test_benchmark_execution() {
+        let market = vec![fill(0, 100, 1, "0xb"), fill(10, 100, 1, "0xb")];
+        let own = vec![fill(0, 101, 1, "0xa"), fill(10, 101, 1, "0xa")];
+
+        let result = benchmark_execution(&own, &market, OrderSide::Buy).unwrap();
+        assert_eq!(result.filled_size, 2);
+        assert_eq!(result.avg_price, 101.0);
+        assert_eq!(result.market_vwap, Some(100.0));
+        assert_eq!(result.vs_vwap_bps, Some(100.0));
+        assert_eq!(user_fills(&own, "0xA").len(), 2);
+    }
+}
@@ -0,0 +1,301 @@
+//! Candle normalization into gap-free decimal OHLCV series.
+//!
+//! Illiquid markets only get a candle for intervals with trades, so
+//! `get_candles` can return sparse series. [`CandleNormalizer`] checks that
+//! timestamps increase on the interval grid, fills every missing interval with
+//! a flat bar at the previous close and zero volume, and scales prices and
+//! volumes by the market's decimals.
+
+use crate::error::{EkidenError, Result};
+use crate::types::{CandleResponse, MarketResponse};
+
+/// Default limit on filled-in bars per series
+pub const MAX_FILLED_BARS: usize = 100_000;
+
+/// Length of a candle interval such as `"1m"` or `"4h"`, in milliseconds
+pub fn interval_millis(interval: &str) -> Option<u64> {
+    let unit_start = interval.char_indices().last()?.0;
+    let (value, unit) = interval.split_at(unit_start);
+    let value: u64 = value.parse().ok()?;
+    let unit_ms = match unit {
+        "m" => 60_000,
+        "h" => 60 * 60_000,
+        "d" => 24 * 60 * 60_000,
+        "w" => 7 * 24 * 60 * 60_000,
+        _ => return None,
+    };
+    match value.checked_mul(unit_ms)? {
+        0 => None,
+        millis => Some(millis),
+    }
+}
+
+/// A decimal OHLCV bar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ohlcv {
+    /// Bar open time, in milliseconds
+    pub timestamp: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    /// Whether the bar was filled in for a missing interval
+    pub filled: bool,
+}
+
+/// A gap-free candle series on a fixed interval
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandleSeries {
+    pub interval: String,
+    pub interval_ms: u64,
+    pub bars: Vec<Ohlcv>,
+}
+
+impl CandleSeries {
+    /// Get the number of bars
+    pub fn len(&self) -> usize {
+        self.bars.len()
+    }
+
+    /// Check if the series has no bars
+    pub fn is_empty(&self) -> bool {
+        self.bars.is_empty()
+    }
+
+    /// Get the number of filled-in bars
+    pub fn filled(&self) -> usize {
+        self.bars.iter().filter(|bar| bar.filled).count()
+    }
+
+    /// Iterate over closing prices
+    pub fn closes(&self) -> impl Iterator<Item = f64> + '_ {
+        self.bars.iter().map(|bar| bar.close)
+    }
+}
+
+/// Turns sparse gateway candles into a [`CandleSeries`]
+#[derive(Debug, Clone)]
+pub struct CandleNormalizer {
+    interval: String,
+    interval_ms: u64,
+    price_decimals: u8,
+    size_decimals: u8,
+    end_time: Option<u64>,
+    max_filled: usize,
+}
+
+impl CandleNormalizer {
+    /// Create a normalizer with explicit price and size decimals
+    pub fn new(interval: &str, price_decimals: u8, size_decimals: u8) -> Result<Self> {
+        let interval_ms = interval_millis(interval).ok_or_else(|| {
+            EkidenError::validation(format!("Invalid candle interval: {}", interval))
+        })?;
+        Ok(Self {
+            interval: interval.to_string(),
+            interval_ms,
+            price_decimals,
+            size_decimals,
+            end_time: None,
+            max_filled: MAX_FILLED_BARS,
+        })
+    }
+
+    /// Create a normalizer using a market's quote and base decimals
+    pub fn for_market(market: &MarketResponse, interval: &str) -> Result<Self> {
+        Self::new(interval, market.quote_decimals, market.base_decimals)
+    }
+
+    /// Carry the last close forward up to this time, in milliseconds
+    pub fn with_end_time(mut self, end_time: u64) -> Self {
+        self.end_time = Some(end_time);
+        self
+    }
+
+    /// Limit the bars filled in per series, [`MAX_FILLED_BARS`] by default
+    ///
+    /// A series needing more is rejected rather than allocating without bound.
+    pub fn with_max_filled(mut self, max_filled: usize) -> Self {
+        self.max_filled = max_filled;
+        self
+    }
+
+    /// Validate, gap-fill and scale a candle series
+    pub fn normalize(&self, candles: &[CandleResponse]) -> Result<CandleSeries> {
+        let price_unit = 10f64.powi(self.price_decimals as i32);
+        let size_unit = 10f64.powi(self.size_decimals as i32);
+        let mut bars: Vec<Ohlcv> = Vec::with_capacity(candles.len());
+        let mut filled = 0;
+
+        for candle in candles {
+            if candle.timestamp % self.interval_ms != 0 {
+                return Err(EkidenError::validation(format!(
+                    "Candle at {} is not aligned to the {} interval",
+                    candle.timestamp, self.interval
+                )));
+            }
+            if let Some(last) = bars.last() {
+                if candle.timestamp <= last.timestamp {
+                    return Err(EkidenError::validation(format!(
+                        "Candle timestamps are not increasing: {} after {}",
+                        candle.timestamp, last.timestamp
+                    )));
+                }
+                self.fill(&mut bars, &mut filled, candle.timestamp)?;
+            }
+            bars.push(Ohlcv {
+                timestamp: candle.timestamp,
+                open: candle.open as f64 / price_unit,
+                high: candle.high as f64 / price_unit,
+                low: candle.low as f64 / price_unit,
+                close: candle.close as f64 / price_unit,
+                volume: candle.volume as f64 / size_unit,
+                filled: false,
+            });
+        }
+
+        if let Some(end_time) = self.end_time {
+            // Bars through the one containing `end_time`, or to the end of time
+            let until = (end_time / self.interval_ms + 1)
+                .checked_mul(self.interval_ms)
+                .unwrap_or(u64::MAX);
+            self.fill(&mut bars, &mut filled, until)?;
+        }
+
+        Ok(CandleSeries {
+            interval: self.interval.clone(),
+            interval_ms: self.interval_ms,
+            bars,
+        })
+    }
+
+    /// Add flat bars at the last close for every interval before `until`
+    ///
+    /// `filled` counts the bars added so far, which may not exceed the limit.
+    fn fill(&self, bars: &mut Vec<Ohlcv>, filled: &mut usize, until: u64) -> Result<()> {
+        let Some(&last) = bars.last() else {
+            return Ok(());
+        };
+        let missing = until.saturating_sub(last.timestamp).saturating_sub(1) / self.interval_ms;
+        let total = usize::try_from(missing)
+            .ok()
+            .and_then(|missing| filled.checked_add(missing))
+            .filter(|total| *total <= self.max_filled)
+            .ok_or_else(|| {
+                EkidenError::validation(format!(
+                    "Filling candles after {} would exceed {} filled bars",
+                    last.timestamp, self.max_filled
+                ))
+            })?;
+        *filled = total;
+        let mut next = last.timestamp.checked_add(self.interval_ms);
+        while let Some(timestamp) = next.filter(|timestamp| *timestamp < until) {
+            bars.push(Ohlcv {
+                timestamp,
+                open: last.close,
+                high: last.close,
+                low: last.close,
+                close: last.close,
+                volume: 0.0,
+                filled: true,
+            });
+            next = timestamp.checked_add(self.interval_ms);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 60_000;
+
+    fn candle(timestamp: u64, close: u64) -> CandleResponse {
+        CandleResponse {
+            market_addr: "0x1".to_string(),
+            timestamp,
+            open: close,
+            high: close + 100,
+            low: close - 100,
+            close,
+            volume: 250,
+            interval: "1m".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_interval_millis() {
+        assert_eq!(interval_millis("1m"), Some(MINUTE));
+        assert_eq!(interval_millis("4h"), Some(240 * MINUTE));
+        assert_eq!(interval_millis("1d"), Some(1_440 * MINUTE));
+        assert_eq!(interval_millis("0m"), None);
+        assert_eq!(interval_millis("1y"), None);
+        assert_eq!(interval_millis(""), None);
+    }
+
+    #[test]
+    fn test_fills_gaps_with_previous_close() {
+        let normalizer = CandleNormalizer::new("1m", 2, 1).unwrap();
+        let series = normalizer
+            .normalize(&[candle(0, 10_000), candle(3 * MINUTE, 10_500)])
+            .unwrap();
+
+        assert_eq!(series.len(), 4);
+        assert_eq!(series.filled(), 2);
+        assert_eq!(
+            series.closes().collect::<Vec<_>>(),
+            vec![100.0, 100.0, 100.0, 105.0]
+        );
+        assert_eq!(series.bars[0].volume, 25.0);
+        assert_eq!(series.bars[1].timestamp, MINUTE);
+        assert_eq!(series.bars[1].high, 100.0);
+        assert_eq!(series.bars[1].volume, 0.0);
+    }
+
+    #[test]
+    fn test_carries_forward_to_end_time() {
+        let normalizer = CandleNormalizer::new("1m", 2, 1)
+            .unwrap()
+            .with_end_time(2 * MINUTE + 30_000);
+        let series = normalizer.normalize(&[candle(0, 10_000)]).unwrap();
+
+        assert_eq!(series.len(), 3);
+        assert_eq!(series.bars[2].timestamp, 2 * MINUTE);
+        assert!(series.bars[2].filled);
+    }
+
+    #[test]
+    fn test_rejects_unordered_or_misaligned_candles() {
+        let normalizer = CandleNormalizer::new("1m", 2, 1).unwrap();
+        assert!(normalizer
+            .normalize(&[candle(MINUTE, 10_000), candle(MINUTE, 10_000)])
+            .is_err());
+        assert!(normalizer
+            .normalize(&[candle(2 * MINUTE, 10_000), candle(MINUTE, 10_000)])
+            .is_err());
+        assert!(normalizer.normalize(&[candle(30_000, 10_000)]).is_err());
+    }
+
+    #[test]
+    fn test_caps_filled_bars() {
+        let normalizer = CandleNormalizer::new("1m", 2, 1)
+            .unwrap()
+            .with_max_filled(2);
+        assert_eq!(
+            normalizer
+                .normalize(&[candle(0, 10_000), candle(3 * MINUTE, 10_500)])
+                .unwrap()
+                .filled(),
+            2
+        );
+        assert!(normalizer
+            .normalize(&[candle(0, 10_000), candle(4 * MINUTE, 10_500)])
+            .is_err());
+        assert!(CandleNormalizer::new("1m", 2, 1)
+            .unwrap()
+            .with_end_time(u64::MAX)
+            .normalize(&[candle(0, 10_000)])
+            .is_err());
+    }
+}
@@ -0,0 +1,15 @@
+//! Analytics helpers built on top of gateway market data.
+
+pub mod basis;
+pub mod benchmark;
+pub mod candles;
+pub mod rolling;
+pub mod sizing;
+pub mod volatility;
+
+pub use basis::{BasisAlert, BasisMonitor, BasisSnapshot, BasisThresholds};
+pub use benchmark::{benchmark_execution, twap, user_fills, vwap, ExecutionBenchmark};
+pub use candles::{interval_millis, CandleNormalizer, CandleSeries, Ohlcv, MAX_FILLED_BARS};
+pub use rolling::{rolling, rolling_timed, RollingSnapshot, RollingStats, Window};
+pub use sizing::{kelly_fraction, PositionSizer};
+pub use volatility::{annualize, periods_per_year, RollingVolatility};
@@ -0,0 +1,298 @@
+//! Windowed rolling statistics over numeric streams.
+//!
+//! [`RollingStats`] maintains mean, standard deviation, min and max over a
+//! count or time window in amortized constant time per sample. The stream
+//! helpers apply it to any numeric stream, such as the trade prices or mids
+//! derived from WebSocket events.
+
+use crate::orderbook::Orderbook;
+use crate::types::WsEvent;
+use futures_util::{future, Stream, StreamExt};
+use std::collections::VecDeque;
+use tokio::sync::broadcast;
+
+/// Window over which rolling statistics are computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    /// The last `n` samples
+    Count(usize),
+    /// Samples whose timestamp is within this span of the newest one
+    Time(u64),
+}
+
+/// Point-in-time view of rolling statistics
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RollingSnapshot {
+    pub count: usize,
+    pub last: f64,
+    pub mean: f64,
+    pub std: Option<f64>,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Rolling mean, standard deviation, min and max over a window
+#[derive(Debug, Clone)]
+pub struct RollingStats {
+    window: Window,
+    samples: VecDeque<(u64, u64, f64)>,
+    mins: VecDeque<(u64, f64)>,
+    maxs: VecDeque<(u64, f64)>,
+    sum: f64,
+    sum_sq: f64,
+    next_id: u64,
+}
+
+impl RollingStats {
+    /// Create rolling statistics over the given window
+    pub fn new(window: Window) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+            mins: VecDeque::new(),
+            maxs: VecDeque::new(),
+            sum: 0.0,
+            sum_sq: 0.0,
+            next_id: 0,
+        }
+    }
+
+    /// Create rolling statistics over the last `n` samples
+    pub fn count(n: usize) -> Self {
+        Self::new(Window::Count(n.max(1)))
+    }
+
+    /// Create rolling statistics over a time span, in timestamp units
+    pub fn time(span: u64) -> Self {
+        Self::new(Window::Time(span))
+    }
+
+    /// Get the window
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    /// Add a sample; count windows ignore the timestamp
+    pub fn push(&mut self, value: f64) {
+        let timestamp = self.samples.back().map(|s| s.1).unwrap_or_default();
+        self.push_at(timestamp, value);
+    }
+
+    /// Add a sample observed at `timestamp`
+    pub fn push_at(&mut self, timestamp: u64, value: f64) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.samples.push_back((id, timestamp, value));
+        self.sum += value;
+        self.sum_sq += value * value;
+
+        while self.mins.back().is_some_and(|(_, v)| *v >= value) {
+            self.mins.pop_back();
+        }
+        self.mins.push_back((id, value));
+        while self.maxs.back().is_some_and(|(_, v)| *v <= value) {
+            self.maxs.pop_back();
+        }
+        self.maxs.push_back((id, value));
+
+        self.evict(timestamp);
+    }
+
+    /// Drop samples that fell out of a time window as of `now`
+    pub fn evict(&mut self, now: u64) {
+        loop {
+            let expired = match (self.window, self.samples.front()) {
+                (Window::Count(n), Some(_)) => self.samples.len() > n,
+                (Window::Time(span), Some((_, timestamp, _))) => {
+                    now.saturating_sub(*timestamp) > span
+                }
+                (_, None) => false,
+            };
+            if !expired {
+                break;
+            }
+
+            let (id, _, value) = self.samples.pop_front().unwrap();
+            self.sum -= value;
+            self.sum_sq -= value * value;
+            if self.mins.front().is_some_and(|(min_id, _)| *min_id == id) {
+                self.mins.pop_front();
+            }
+            if self.maxs.front().is_some_and(|(max_id, _)| *max_id == id) {
+                self.maxs.pop_front();
+            }
+        }
+    }
+
+    /// Get the number of samples in the window
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Check if the window is empty
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Get the most recent sample
+    pub fn last(&self) -> Option<f64> {
+        self.samples.back().map(|(_, _, value)| *value)
+    }
+
+    /// Get the mean of the window
+    pub fn mean(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.sum / self.samples.len() as f64)
+    }
+
+    /// Get the sample variance of the window
+    pub fn variance(&self) -> Option<f64> {
+        let n = self.samples.len() as f64;
+        if n < 2.0 {
+            return None;
+        }
+        let mean = self.sum / n;
+        // Clamp tiny negative values caused by floating point cancellation
+        Some(((self.sum_sq - n * mean * mean) / (n - 1.0)).max(0.0))
+    }
+
+    /// Get the sample standard deviation of the window
+    pub fn std(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+
+    /// Get the minimum of the window
+    pub fn min(&self) -> Option<f64> {
+        self.mins.front().map(|(_, value)| *value)
+    }
+
+    /// Get the maximum of the window
+    pub fn max(&self) -> Option<f64> {
+        self.maxs.front().map(|(_, value)| *value)
+    }
+
+    /// Get all statistics at once
+    pub fn snapshot(&self) -> Option<RollingSnapshot> {
+        Some(RollingSnapshot {
+            count: self.len(),
+            last: self.last()?,
+            mean: self.mean()?,
+            std: self.std(),
+            min: self.min()?,
+            max: self.max()?,
+        })
+    }
+}
+
+/// Apply rolling statistics over the last `n` values of a stream
+pub fn rolling<S>(stream: S, n: usize) -> impl Stream<Item = RollingSnapshot>
+where
+    S: Stream<Item = f64>,
+{
+    stream.scan(RollingStats::count(n), |stats, value| {
+        stats.push(value);
+        future::ready(stats.snapshot())
+    })
+}
+
+/// Apply rolling statistics over a time span to a stream of `(timestamp, value)`
+pub fn rolling_timed<S>(stream: S, span: u64) -> impl Stream<Item = RollingSnapshot>
+where
+    S: Stream<Item = (u64, f64)>,
+{
+    stream.scan(RollingStats::time(span), |stats, (timestamp, value)| {
+        stats.push_at(timestamp, value);
+        future::ready(stats.snapshot())
+    })
+}
+
+/// Turn an event receiver into a stream of `(timestamp, price)` trades.
+///
+/// The stream ends when the channel closes; lagged events are skipped.
+pub fn trade_prices(mut receiver: broadcast::Receiver<WsEvent>) -> impl Stream<Item = (u64, f64)> {
+    async_stream::stream! {
+        loop {
+            match receiver.recv().await {
+                Ok(WsEvent::Trade { price, timestamp, .. }) => yield (timestamp, price as f64),
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+/// Turn an orderbook event receiver into a stream of `(timestamp, mid)` prices
+pub fn mid_prices(
+    mut receiver: broadcast::Receiver<WsEvent>,
+    market_addr: &str,
+) -> impl Stream<Item = (u64, f64)> {
+    let mut book = Orderbook::new(market_addr);
+    async_stream::stream! {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if book.apply_event(&event) {
+                        if let Some(mid) = book.mid_price() {
+                            yield (book.timestamp(), mid);
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_window() {
+        let mut stats = RollingStats::count(3);
+        for value in [1.0, 5.0, 3.0, 2.0] {
+            stats.push(value);
+        }
+
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.mean(), Some(10.0 / 3.0));
+        assert_eq!(stats.min(), Some(2.0));
+        assert_eq!(stats.max(), Some(5.0));
+        let expected = ((5.0f64 - 10.0 / 3.0).powi(2)
+            + (3.0f64 - 10.0 / 3.0).powi(2)
+            + (2.0f64 - 10.0 / 3.0).powi(2))
+            / 2.0;
+        assert!((stats.variance().unwrap() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_time_window_evicts_old_samples() {
+        let mut stats = RollingStats::time(10);
+        stats.push_at(0, 9.0);
+        stats.push_at(5, 1.0);
+        stats.push_at(12, 4.0);
+
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats.max(), Some(4.0));
+        assert_eq!(stats.min(), Some(1.0));
+
+        stats.evict(30);
+        assert!(stats.is_empty());
+        assert!(stats.snapshot().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rolling_stream() {
+        let values = futures_util::stream::iter(vec![1.0, 2.0, 3.0]);
+        let snapshots: Vec<RollingSnapshot> = rolling(values, 2).collect().await;
+
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(snapshots[2].mean, 2.5);
+        assert_eq!(snapshots[2].min, 2.0);
+        assert_eq!(snapshots[0].std, None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Position sizing helpers.
+//!
+//! Sizes are returned in base units. Prices are in quote units per whole base
+//! token, so the notional of `size` at `price` is
+//! `price * size / 10^base_decimals` quote units. Every result is capped by the
+//! market's maximum leverage and rounded down to a multiple of the market's
+//! `min_order_size` so it can be submitted as-is.
+
+use crate::error::{EkidenError, Result};
+use crate::types::{MarketResponse, PortfolioResponse};
+
+/// Kelly fraction `p - (1 - p) / b`, clamped to `[0, cap]`
+pub fn kelly_fraction(win_probability: f64, win_loss_ratio: f64, cap: f64) -> f64 {
+    if win_loss_ratio <= 0.0 {
+        return 0.0;
+    }
+    let fraction = win_probability - (1.0 - win_probability) / win_loss_ratio;
+    fraction.clamp(0.0, cap.max(0.0))
+}
+
+/// Turns account equity and market specs into exchange-valid order sizes
+#[derive(Debug, Clone)]
+pub struct PositionSizer {
+    equity: u64,
+    base_unit: f64,
+    min_order_size: u64,
+    max_leverage: u32,
+}
+
+impl PositionSizer {
+    /// Create a sizer for a market with the given account equity in quote units
+    pub fn new(equity: u64, market: &MarketResponse) -> Self {
+        Self {
+            equity,
+            base_unit: 10f64.powi(market.base_decimals as i32),
+            min_order_size: market.min_order_size.max(1),
+            max_leverage: market.max_leverage.max(1),
+        }
+    }
+
+    /// Create a sizer using the total value of the portfolio as equity
+    pub fn from_portfolio(portfolio: &PortfolioResponse, market: &MarketResponse) -> Self {
+        Self::new(portfolio.summary.total_value, market)
+    }
+
+    /// Get the equity used for sizing
+    pub fn equity(&self) -> u64 {
+        self.equity
+    }
+
+    /// Size an order whose notional is `fraction` of equity
+    pub fn fixed_fractional(&self, fraction: f64, price: u64) -> Result<u64> {
+        self.size_for_notional(self.equity as f64 * fraction, price)
+    }
+
+    /// Size an order so that hitting `stop` loses `risk_fraction` of equity
+    pub fn fixed_risk(&self, risk_fraction: f64, entry: u64, stop: u64) -> Result<u64> {
+        let distance = entry.abs_diff(stop);
+        if distance == 0 {
+            return Err(EkidenError::validation("Stop must differ from entry price"));
+        }
+        let risk = self.equity as f64 * risk_fraction;
+        let size = risk / distance as f64 * self.base_unit;
+        self.validate(size, entry)
+    }
+
+    /// Size an order so the position contributes `target_volatility` to equity,
+    /// given the asset's volatility over the same horizon
+    pub fn volatility_target(
+        &self,
+        target_volatility: f64,
+        asset_volatility: f64,
+        price: u64,
+    ) -> Result<u64> {
+        if asset_volatility <= 0.0 {
+            return Err(EkidenError::validation("Asset volatility must be positive"));
+        }
+        let notional = self.equity as f64 * target_volatility / asset_volatility;
+        self.size_for_notional(notional, price)
+    }
+
+    /// Size an order using the Kelly fraction capped at `cap`
+    pub fn kelly(
+        &self,
+        win_probability: f64,
+        win_loss_ratio: f64,
+        cap: f64,
+        price: u64,
+    ) -> Result<u64> {
+        let fraction = kelly_fraction(win_probability, win_loss_ratio, cap);
+        self.fixed_fractional(fraction, price)
+    }
+
+    fn size_for_notional(&self, notional: f64, price: u64) -> Result<u64> {
+        if price == 0 {
+            return Err(EkidenError::validation("Price must be positive"));
+        }
+        self.validate(notional / price as f64 * self.base_unit, price)
+    }
+
+    /// Cap by leverage and round down to a multiple of the minimum order size
+    fn validate(&self, size: f64, price: u64) -> Result<u64> {
+        if !size.is_finite() || size <= 0.0 {
+            return Err(EkidenError::validation(
+                "Computed order size is not positive",
+            ));
+        }
+
+        let max_notional = self.equity as f64 * self.max_leverage as f64;
+        let max_size = max_notional / price.max(1) as f64 * self.base_unit;
+        let size = size.min(max_size).floor() as u64;
+        let size = size - size % self.min_order_size;
+
+        if size == 0 {
+            return Err(EkidenError::validation(format!(
+                "Computed order size is below the minimum of {}",
+                self.min_order_size
+            )));
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(min_order_size: u64, max_leverage: u32) -> MarketResponse {
+        MarketResponse {
+            base_decimals: 2,
+            min_order_size,
+            max_leverage,
+            ..crate::test_utils::market()
+        }
+    }
+
+    #[test]
+    fn test_fixed_fractional_rounds_to_min_size() {
+        let sizer = PositionSizer::new(10_000, &market(5, 10));
+        // 10% of 10_000 at price 100 is 10 whole tokens = 1_000 base units
+        assert_eq!(sizer.fixed_fractional(0.1, 100).unwrap(), 1_000);
+        assert_eq!(sizer.fixed_fractional(0.1, 300).unwrap(), 330);
+    }
+
+    #[test]
+    fn test_leverage_cap() {
+        let sizer = PositionSizer::new(1_000, &market(1, 2));
+        // 10x equity is capped at 2x: 2_000 / 100 = 20 tokens
+        assert_eq!(sizer.fixed_fractional(10.0, 100).unwrap(), 2_000);
+    }
+
+    #[test]
+    fn test_fixed_risk_and_volatility_target() {
+        let sizer = PositionSizer::new(10_000, &market(1, 100));
+        // Risk 1% = 100 quote units over a 10 unit stop = 10 tokens
+        assert_eq!(sizer.fixed_risk(0.01, 100, 90).unwrap(), 1_000);
+        assert!(sizer.fixed_risk(0.01, 100, 100).is_err());
+        // Target 10% vol with a 50% vol asset = 20% of equity
+        assert_eq!(sizer.volatility_target(0.1, 0.5, 100).unwrap(), 2_000);
+    }
+
+    #[test]
+    fn test_kelly() {
+        assert!((kelly_fraction(0.6, 1.0, 1.0) - 0.2).abs() < 1e-12);
+        assert_eq!(kelly_fraction(0.6, 1.0, 0.1), 0.1);
+        assert_eq!(kelly_fraction(0.3, 1.0, 1.0), 0.0);
+
+        let sizer = PositionSizer::new(10_000, &market(1, 10));
+        assert!(sizer.kelly(0.3, 1.0, 0.5, 100).is_err());
+    }
+
+    #[test]
+    fn test_below_min_size_is_rejected() {
+        let sizer = PositionSizer::new(100, &market(1_000, 10));
+        assert!(sizer.fixed_fractional(0.01, 100).is_err());
+    }
+}
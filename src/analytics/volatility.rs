@@ -0,0 +1,266 @@
+//! Realized volatility estimators.
+//!
+//! Estimates are per bar and not annualized; use [`annualize`] with
+//! [`periods_per_year`] to scale them to yearly figures.
+
+use super::rolling::RollingStats;
+use crate::types::{CandleResponse, WsEvent};
+
+/// Close-to-close volatility: sample standard deviation of log returns
+pub fn close_to_close(candles: &[CandleResponse]) -> Option<f64> {
+    let closes: Vec<f64> = candles.iter().map(|candle| candle.close as f64).collect();
+    close_to_close_from_prices(&closes)
+}
+
+/// Parkinson volatility estimated from the high/low range of each candle
+pub fn parkinson(candles: &[CandleResponse]) -> Option<f64> {
+    let ranges: Vec<f64> = candles
+        .iter()
+        .filter_map(|candle| log_range(candle.high as f64, candle.low as f64))
+        .collect();
+    parkinson_from_ranges(&ranges)
+}
+
+/// Scale a per-bar volatility to a yearly one
+pub fn annualize(volatility: f64, periods_per_year: f64) -> f64 {
+    volatility * periods_per_year.sqrt()
+}
+
+/// Number of bars per year for a candle interval such as `"1m"` or `"4h"`
+pub fn periods_per_year(interval: &str) -> Option<f64> {
+    const MINUTES_PER_YEAR: f64 = 365.0 * 24.0 * 60.0;
+    let unit_start = interval.char_indices().last()?.0;
+    let (value, unit) = interval.split_at(unit_start);
+    let value: f64 = value.parse().ok()?;
+    let minutes = match unit {
+        "m" => value,
+        "h" => value * 60.0,
+        "d" => value * 24.0 * 60.0,
+        "w" => value * 7.0 * 24.0 * 60.0,
+        _ => return None,
+    };
+    if minutes <= 0.0 {
+        return None;
+    }
+    Some(MINUTES_PER_YEAR / minutes)
+}
+
+fn close_to_close_from_prices(closes: &[f64]) -> Option<f64> {
+    let mut returns = RollingStats::count(closes.len());
+    for pair in closes.windows(2) {
+        if let Some(r) = log_return(pair[0], pair[1]) {
+            returns.push(r);
+        }
+    }
+    returns.std()
+}
+
+fn parkinson_from_ranges(ranges: &[f64]) -> Option<f64> {
+    let mut squared = RollingStats::count(ranges.len());
+    for range in ranges {
+        squared.push(range * range);
+    }
+    parkinson_from_mean_square(squared.mean()?)
+}
+
+fn parkinson_from_mean_square(mean_square: f64) -> Option<f64> {
+    Some((mean_square / (4.0 * std::f64::consts::LN_2)).sqrt())
+}
+
+fn log_return(from: f64, to: f64) -> Option<f64> {
+    if from <= 0.0 || to <= 0.0 {
+        return None;
+    }
+    Some((to / from).ln())
+}
+
+fn log_range(high: f64, low: f64) -> Option<f64> {
+    if low <= 0.0 || high < low {
+        return None;
+    }
+    Some((high / low).ln())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bar {
+    bucket: u64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+/// Rolling realized volatility over the last `window` bars.
+///
+/// Bars come either from candles or from the live trade stream, which is
+/// bucketed into bars of `bar_interval` timestamp units.
+#[derive(Debug, Clone)]
+pub struct RollingVolatility {
+    bar_interval: u64,
+    returns: RollingStats,
+    squared_ranges: RollingStats,
+    last_close: Option<f64>,
+    current: Option<Bar>,
+}
+
+impl RollingVolatility {
+    /// Create an estimator over `window` bars of `bar_interval` timestamp units
+    pub fn new(window: usize, bar_interval: u64) -> Self {
+        let window = window.max(2);
+        Self {
+            bar_interval: bar_interval.max(1),
+            returns: RollingStats::count(window),
+            squared_ranges: RollingStats::count(window),
+            last_close: None,
+            current: None,
+        }
+    }
+
+    /// Get the number of completed bars in the window
+    pub fn len(&self) -> usize {
+        self.squared_ranges.len()
+    }
+
+    /// Check if no bars have completed yet
+    pub fn is_empty(&self) -> bool {
+        self.squared_ranges.is_empty()
+    }
+
+    /// Add a completed bar from its high, low and close
+    pub fn push_bar(&mut self, high: f64, low: f64, close: f64) {
+        if let Some(r) = self.last_close.and_then(|last| log_return(last, close)) {
+            self.returns.push(r);
+        }
+        self.last_close = Some(close);
+        if let Some(range) = log_range(high, low) {
+            self.squared_ranges.push(range * range);
+        }
+    }
+
+    /// Add a completed candle
+    pub fn push_candle(&mut self, candle: &CandleResponse) {
+        self.push_bar(candle.high as f64, candle.low as f64, candle.close as f64);
+    }
+
+    /// Add a trade; a bar is completed when a trade falls into a later bucket
+    pub fn on_trade(&mut self, timestamp: u64, price: u64) {
+        let bucket = timestamp / self.bar_interval;
+        let price = price as f64;
+
+        match self.current.as_mut() {
+            Some(bar) if bar.bucket == bucket => {
+                bar.high = bar.high.max(price);
+                bar.low = bar.low.min(price);
+                bar.close = price;
+            }
+            Some(bar) if bucket < bar.bucket => {
+                // Late trade for an already completed bar
+            }
+            _ => {
+                if let Some(bar) = self.current.take() {
+                    self.push_bar(bar.high, bar.low, bar.close);
+                }
+                self.current = Some(Bar {
+                    bucket,
+                    high: price,
+                    low: price,
+                    close: price,
+                });
+            }
+        }
+    }
+
+    /// Feed a WebSocket event; only trades are used
+    pub fn on_event(&mut self, event: &WsEvent) {
+        if let WsEvent::Trade {
+            price, timestamp, ..
+        } = event
+        {
+            self.on_trade(*timestamp, *price);
+        }
+    }
+
+    /// Close-to-close volatility over the window
+    pub fn close_to_close(&self) -> Option<f64> {
+        self.returns.std()
+    }
+
+    /// Parkinson volatility over the window
+    pub fn parkinson(&self) -> Option<f64> {
+        parkinson_from_mean_square(self.squared_ranges.mean()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(high: u64, low: u64, close: u64) -> CandleResponse {
+        CandleResponse {
+            market_addr: "0x1".to_string(),
+            timestamp: 0,
+            open: close,
+            high,
+            low,
+            close,
+            volume: 0,
+            interval: "1m".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_close_to_close() {
+        let candles = vec![
+            candle(100, 100, 100),
+            candle(110, 110, 110),
+            candle(99, 99, 99),
+        ];
+        let r1 = (110.0f64 / 100.0).ln();
+        let r2 = (99.0f64 / 110.0).ln();
+        let mean = (r1 + r2) / 2.0;
+        let expected = ((r1 - mean).powi(2) + (r2 - mean).powi(2)).sqrt();
+
+        assert!((close_to_close(&candles).unwrap() - expected).abs() < 1e-12);
+        assert!(close_to_close(&candles[..2]).is_none());
+    }
+
+    #[test]
+    fn test_parkinson() {
+        let candles = vec![candle(110, 100, 105)];
+        let range = (110.0f64 / 100.0).ln();
+        let expected = (range * range / (4.0 * std::f64::consts::LN_2)).sqrt();
+
+        assert!((parkinson(&candles).unwrap() - expected).abs() < 1e-12);
+        assert!(parkinson(&[]).is_none());
+    }
+
+    #[test]
+    fn test_periods_per_year() {
+        assert_eq!(periods_per_year("1d"), Some(365.0));
+        assert_eq!(periods_per_year("1h"), Some(365.0 * 24.0));
+        assert_eq!(periods_per_year("x"), None);
+        assert_eq!(periods_per_year(""), None);
+    }
+
+    #[test]
+    fn test_rolling_window_from_trades() {
+        let mut vol = RollingVolatility::new(2, 10);
+        vol.on_trade(0, 100);
+        vol.on_trade(5, 102);
+        assert!(vol.is_empty());
+
+        vol.on_trade(10, 101);
+        vol.on_trade(20, 103);
+        vol.on_trade(30, 100);
+        vol.on_trade(40, 100);
+
+        // Completed bars: [100..102], [101], [103], [100]; window keeps the last 2
+        assert_eq!(vol.len(), 2);
+        let closes = [101.0f64, 103.0, 100.0];
+        let r1 = (closes[1] / closes[0]).ln();
+        let r2 = (closes[2] / closes[1]).ln();
+        let mean = (r1 + r2) / 2.0;
+        let expected = ((r1 - mean).powi(2) + (r2 - mean).powi(2)).sqrt();
+        assert!((vol.close_to_close().unwrap() - expected).abs() < 1e-12);
+        assert_eq!(vol.parkinson(), Some(0.0));
+    }
+}
@@ -172,6 +172,30 @@ impl VaultContract {
         Ok(txn_hash.to_string())
     }
 
+    /// Look up a transaction once; unknown transactions are pending
+    pub async fn transaction_status(&self, txn_hash: &str) -> TransactionStatus {
+        match self
+            .client
+            .get_transaction_by_hash(txn_hash.to_string())
+            .await
+        {
+            Ok(resp) => match resp.inner().get("success").and_then(|s| s.as_bool()) {
+                Some(true) => TransactionStatus::Confirmed,
+                Some(false) => {
+                    let vm_status = resp
+                        .inner()
+                        .get("vm_status")
+                        .and_then(|s| s.as_str())
+                        .unwrap_or("unknown");
+                    TransactionStatus::Failed(vm_status.to_string())
+                }
+                None => TransactionStatus::Pending,
+            },
+            // Not found, let's wait
+            Err(_) => TransactionStatus::Pending,
+        }
+    }
+
     pub async fn wait_for_transaction(
         &self,
         txn_hash: &str,
@@ -185,26 +209,7 @@ impl VaultContract {
                 }
             }
 
-            let status = match self
-                .client
-                .get_transaction_by_hash(txn_hash.to_string())
-                .await
-            {
-                Ok(resp) => match resp.inner().get("success") {
-                    Some(success) => {
-                        if success.as_bool().unwrap() {
-                            TransactionStatus::Confirmed
-                        } else {
-                            let vm_status =
-                                resp.inner().get("vm_status").unwrap().as_str().unwrap();
-                            TransactionStatus::Failed(vm_status.to_string())
-                        }
-                    }
-                    _ => TransactionStatus::Pending,
-                },
-                // Not found, let's wait
-                Err(_) => TransactionStatus::Pending,
-            };
+            let status = self.transaction_status(txn_hash).await;
 
             println!("TX_STATUS: {:?}", status);
             match status {
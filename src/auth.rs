@@ -1,14 +1,43 @@
 use crate::error::{EkidenError, Result};
-use crate::types::{AuthorizeParams, AuthorizeResponse};
-use crate::utils::{format, KeyPair};
+use crate::types::{ActionPayload, AuthorizeParams, AuthorizeResponse, SendIntentParams};
+use crate::utils::{format, Crypto, KeyPair, Redacted};
+
+/// Build the message signed for an intent
+///
+/// The message is the [canonical JSON](format::canonical_json) of the action
+/// list, or of `{"actions":[...],"nonce":n}` when the intent carries a nonce.
+pub fn intent_message(actions: &[ActionPayload], nonce: Option<u64>) -> Result<Vec<u8>> {
+    let message = match nonce {
+        Some(nonce) => {
+            format::canonical_json(&serde_json::json!({ "actions": actions, "nonce": nonce }))?
+        }
+        None => format::canonical_json(&actions)?,
+    };
+    Ok(message.into_bytes())
+}
+
+/// Check an intent's signature against the signer's public key
+pub fn verify_intent(params: &SendIntentParams, public_key: &str) -> Result<bool> {
+    let message = intent_message(&params.actions, params.nonce)?;
+    Crypto::verify_signature(&message, &params.signature, public_key)
+}
 
 /// Authentication manager for the Ekiden client
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Auth {
     key_pair: Option<KeyPair>,
     token: Option<String>,
 }
 
+impl std::fmt::Debug for Auth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Auth")
+            .field("key_pair", &self.key_pair)
+            .field("token", &self.token.as_deref().map(Redacted))
+            .finish()
+    }
+}
+
 impl Auth {
     /// Create a new authentication manager
     pub fn new() -> Self {
@@ -99,6 +128,11 @@ impl Auth {
         self.sign_message(json_str.as_bytes())
     }
 
+    /// Sign an intent's actions and nonce, see [`intent_message`]
+    pub fn sign_intent(&self, actions: &[ActionPayload], nonce: Option<u64>) -> Result<String> {
+        self.sign_message(&intent_message(actions, nonce)?)
+    }
+
     /// Generate a bearer token header value
     pub fn bearer_token(&self) -> Option<String> {
         self.token.as_ref().map(|token| format!("Bearer {}", token))
@@ -195,6 +229,41 @@ mod tests {
     use super::*;
     use crate::utils::KeyPair;
 
+    #[test]
+    fn test_debug_redacts_token() {
+        let auth = Auth::new()
+            .with_key_pair(KeyPair::generate())
+            .with_token("secret-session-token");
+        let debug = format!("{:?}", auth);
+        assert!(!debug.contains("secret-session-token"));
+        assert!(debug.contains("<redacted "));
+    }
+
+    #[test]
+    fn test_intent_signature_verifies() {
+        let key_pair = KeyPair::generate();
+        let public_key = key_pair.public_key();
+        let auth = Auth::new().with_key_pair(key_pair);
+        let actions = vec![ActionPayload::new(
+            "cancel_order",
+            serde_json::json!({ "sid": "1", "market_addr": "0x2" }),
+        )];
+
+        let mut params = SendIntentParams {
+            signature: auth.sign_intent(&actions, Some(7)).unwrap(),
+            actions,
+            nonce: Some(7),
+        };
+        assert!(verify_intent(&params, &public_key).unwrap());
+
+        // The signature covers the nonce and every action field
+        params.nonce = Some(8);
+        assert!(!verify_intent(&params, &public_key).unwrap());
+        params.nonce = Some(7);
+        params.actions[0].data["sid"] = serde_json::json!("2");
+        assert!(!verify_intent(&params, &public_key).unwrap());
+    }
+
     #[test]
     fn test_auth_creation() {
         let auth = Auth::new();
@@ -0,0 +1,222 @@
+//! Concurrent execution of many read queries.
+//!
+//! A [`Batch`] runs [`BatchQuery`] values with bounded parallelism and returns
+//! their results in input order, so a snapshot across many markets does not
+//! need a hand-rolled `join_all`. Queries go through the client's rate limiter
+//! and interceptors like any other request:
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient, markets: Vec<String>) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::batch::{BatchQuery, BatchResponse};
+//! use ekiden_rust_sdk::types::{ListFillsParams, Pagination};
+//!
+//! let queries = markets.iter().flat_map(|market| {
+//!     [
+//!         BatchQuery::orderbook(market, Some(10)),
+//!         BatchQuery::Fills(ListFillsParams {
+//!             market_addr: market.clone(),
+//!             pagination: Pagination::new(50, 0),
+//!         }),
+//!     ]
+//! });
+//! for result in client.batch().with_concurrency(16).queries(queries).run().await {
+//!     match result? {
+//!         BatchResponse::Orderbook(book) => println!("{}: {} bids", book.market_addr, book.bids.len()),
+//!         BatchResponse::Fills(fills) => println!("{} fills", fills.len()),
+//!         _ => {}
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::EkidenClient;
+use crate::error::Result;
+use crate::types::*;
+use futures_util::stream::{self, StreamExt};
+use std::collections::HashMap;
+
+/// Default number of queries a batch keeps in flight
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+
+/// A read query that can run as part of a [`Batch`]
+#[derive(Debug, Clone)]
+pub enum BatchQuery {
+    Markets(ListMarketsParams),
+    Orderbook {
+        market_addr: String,
+        depth: Option<u32>,
+    },
+    Orders(ListOrdersParams),
+    Fills(ListFillsParams),
+    Candles(ListCandlesParams),
+    FundingRates(ListFundingRatesParams),
+    Positions(ListPositionsParams),
+    /// Any other GET endpoint, returned as raw JSON
+    Json {
+        path: String,
+        query: HashMap<String, String>,
+        auth_required: bool,
+    },
+}
+
+impl BatchQuery {
+    /// Query an orderbook snapshot
+    pub fn orderbook<S: Into<String>>(market_addr: S, depth: Option<u32>) -> Self {
+        BatchQuery::Orderbook {
+            market_addr: market_addr.into(),
+            depth,
+        }
+    }
+
+    async fn run(self, client: &EkidenClient) -> Result<BatchResponse> {
+        Ok(match self {
+            BatchQuery::Markets(params) => {
+                BatchResponse::Markets(client.get_markets(params).await?)
+            }
+            BatchQuery::Orderbook { market_addr, depth } => {
+                BatchResponse::Orderbook(client.get_orderbook(&market_addr, depth).await?)
+            }
+            BatchQuery::Orders(params) => BatchResponse::Orders(client.get_orders(params).await?),
+            BatchQuery::Fills(params) => BatchResponse::Fills(client.get_fills(params).await?),
+            BatchQuery::Candles(params) => {
+                BatchResponse::Candles(client.get_candles(params).await?)
+            }
+            BatchQuery::FundingRates(params) => {
+                BatchResponse::FundingRates(client.get_funding_rates(params).await?)
+            }
+            BatchQuery::Positions(params) => {
+                BatchResponse::Positions(client.get_user_positions(params).await?)
+            }
+            BatchQuery::Json {
+                path,
+                query,
+                auth_required,
+            } => {
+                let mut config = RequestConfig::get().with_query(query);
+                if auth_required {
+                    config = config.with_auth();
+                }
+                BatchResponse::Json(client.request_json(&path, config).await?)
+            }
+        })
+    }
+}
+
+/// Result of a [`BatchQuery`], in the variant of the same name
+#[derive(Debug, Clone)]
+pub enum BatchResponse {
+    Markets(Vec<MarketResponse>),
+    Orderbook(OrderbookResponse),
+    Orders(Vec<OrderResponse>),
+    Fills(Vec<FillResponse>),
+    Candles(Vec<CandleResponse>),
+    FundingRates(Vec<FundingRateResponse>),
+    Positions(Vec<PositionResponse>),
+    Json(serde_json::Value),
+}
+
+/// Queries to run concurrently against one client
+#[derive(Debug)]
+pub struct Batch<'a> {
+    client: &'a EkidenClient,
+    queries: Vec<BatchQuery>,
+    concurrency: usize,
+}
+
+impl<'a> Batch<'a> {
+    /// Create an empty batch
+    pub fn new(client: &'a EkidenClient) -> Self {
+        Self {
+            client,
+            queries: Vec::new(),
+            concurrency: DEFAULT_BATCH_CONCURRENCY,
+        }
+    }
+
+    /// Set how many queries may be in flight at once
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Add a query
+    pub fn query(mut self, query: BatchQuery) -> Self {
+        self.queries.push(query);
+        self
+    }
+
+    /// Add several queries
+    pub fn queries<I: IntoIterator<Item = BatchQuery>>(mut self, queries: I) -> Self {
+        self.queries.extend(queries);
+        self
+    }
+
+    /// Get the number of queries
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Check if the batch has no queries
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    /// Run every query and return the results in the order they were added
+    ///
+    /// A failed query does not stop the others.
+    pub async fn run(self) -> Vec<Result<BatchResponse>> {
+        let client = self.client;
+        stream::iter(self.queries.into_iter().map(|query| query.run(client)))
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use crate::EkidenClientBuilder;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_results_keep_input_order() {
+        let transport = Arc::new(MockTransport::new());
+        transport
+            .respond_json("market_info", &serde_json::json!([]))
+            .unwrap();
+        transport
+            .respond_json("announcements", &serde_json::json!([{ "id": 1 }]))
+            .unwrap();
+        transport.respond_once_status("status", 503, "maintenance");
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(transport.clone())
+            .build()
+            .await
+            .unwrap();
+
+        let json = |path: &str| BatchQuery::Json {
+            path: path.to_string(),
+            query: HashMap::new(),
+            auth_required: false,
+        };
+        let results = client
+            .batch()
+            .with_concurrency(2)
+            .query(json("announcements"))
+            .query(json("status"))
+            .query(BatchQuery::Markets(ListMarketsParams::default()))
+            .run()
+            .await;
+
+        assert_eq!(results.len(), 3);
+        assert!(matches!(&results[0], Ok(BatchResponse::Json(value)) if value[0]["id"] == 1));
+        assert!(results[1].is_err());
+        assert!(matches!(&results[2], Ok(BatchResponse::Markets(markets)) if markets.is_empty()));
+        assert_eq!(transport.requests().len(), 3);
+    }
+}
@@ -0,0 +1,406 @@
+//! Subcommand implementations.
+
+use crate::output::{OutputFormat, Table};
+use crate::settings::Settings;
+use crate::trade::{self, TradeSummary};
+use ekiden_rust_sdk::utils::format;
+use ekiden_rust_sdk::{
+    ActionPayload, EkidenClient, EkidenError, IntentSimulation, ListMarketsParams, MarketResponse,
+    OrderSide, OrderType, Orderbook, Result, SendIntentResponse,
+};
+use std::time::Duration;
+
+/// Resolve a market given either its address or its symbol
+async fn resolve_market(client: &EkidenClient, market: &str) -> Result<String> {
+    if format::validate_address(market).is_ok() {
+        return Ok(market.to_string());
+    }
+    client
+        .get_market_by_symbol(market)
+        .await?
+        .map(|m| m.addr)
+        .filter(|addr| !addr.is_empty())
+        .ok_or_else(|| EkidenError::validation(format!("Unknown market: {}", market)))
+}
+
+/// Fetch a market given either its address or its symbol
+async fn fetch_market(client: &EkidenClient, market: &str) -> Result<MarketResponse> {
+    let found = if format::validate_address(market).is_ok() {
+        client.get_market_by_address(market).await?.map(|mut m| {
+            if m.addr.is_empty() {
+                m.addr = market.to_string();
+            }
+            m
+        })
+    } else {
+        client
+            .get_market_by_symbol(market)
+            .await?
+            .filter(|m| !m.addr.is_empty())
+    };
+    found.ok_or_else(|| EkidenError::validation(format!("Unknown market: {}", market)))
+}
+
+/// List markets, optionally filtered by symbol
+pub async fn markets(settings: &Settings, symbol: Option<String>) -> Result<()> {
+    let client = settings.client().await?;
+    let params = ListMarketsParams {
+        symbol,
+        ..Default::default()
+    };
+    let markets = client.get_markets(params).await?;
+
+    settings.output.print_rows(
+        &[
+            "symbol",
+            "addr",
+            "mark_price",
+            "oracle_price",
+            "open_interest",
+            "max_leverage",
+        ],
+        &markets,
+    )
+}
+
+/// Print the top of the book from the first WebSocket snapshot
+pub async fn orderbook(
+    settings: &Settings,
+    market: &str,
+    depth: usize,
+    timeout: Duration,
+) -> Result<()> {
+    let client = settings.client().await?;
+    let market_addr = resolve_market(&client, market).await?;
+    client.connect_websocket().await?;
+    let mut receiver = client.subscribe_orderbook(&market_addr).await?;
+
+    let mut book = Orderbook::new(market_addr.as_str());
+    tokio::time::timeout(timeout, async {
+        while let Ok(event) = receiver.recv().await {
+            book.apply_event(&event);
+            if !book.is_empty() {
+                break;
+            }
+        }
+    })
+    .await
+    .map_err(|_| EkidenError::Timeout)?;
+    client.disconnect_websocket().await?;
+
+    let (bids, asks) = book.depth(depth);
+    if settings.output == OutputFormat::Json {
+        return settings.output.print_record(&serde_json::json!({
+            "market_addr": market_addr,
+            "timestamp": book.timestamp(),
+            "bids": bids,
+            "asks": asks,
+        }));
+    }
+
+    let mut table = Table::new(vec!["bid_size", "bid", "ask", "ask_size"]);
+    for i in 0..bids.len().max(asks.len()) {
+        let bid = bids.get(i);
+        let ask = asks.get(i);
+        table.row(vec![
+            bid.map(|l| l.size.to_string()).unwrap_or_default(),
+            bid.map(|l| l.price.to_string()).unwrap_or_default(),
+            ask.map(|l| l.price.to_string()).unwrap_or_default(),
+            ask.map(|l| l.size.to_string()).unwrap_or_default(),
+        ]);
+    }
+    settings.output.print_table(&table);
+    Ok(())
+}
+
+/// Print recent candles for a market
+pub async fn candles(settings: &Settings, market: &str, interval: &str, limit: u32) -> Result<()> {
+    let client = settings.client().await?;
+    let market_addr = resolve_market(&client, market).await?;
+    let candles = client
+        .get_recent_candles(&market_addr, interval, Some(limit))
+        .await?;
+
+    settings.output.print_rows(
+        &["timestamp", "open", "high", "low", "close", "volume"],
+        &candles,
+    )
+}
+
+/// Print the portfolio summary and positions
+///
+/// CSV output holds only the positions so the file stays a single table.
+pub async fn portfolio(settings: &Settings) -> Result<()> {
+    let client = settings.authenticated_client().await?;
+    let portfolio = client.get_user_portfolio().await?;
+    let columns = [
+        "symbol",
+        "side",
+        "size",
+        "entry_price",
+        "mark_price",
+        "unrealized_pnl",
+    ];
+    match settings.output {
+        OutputFormat::Json => settings.output.print_record(&portfolio),
+        OutputFormat::Csv => settings.output.print_rows(&columns, &portfolio.positions),
+        OutputFormat::Table => {
+            settings.output.print_record(&portfolio.summary)?;
+            if !portfolio.positions.is_empty() {
+                println!();
+                settings.output.print_rows(&columns, &portfolio.positions)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Print open positions, optionally for a single market
+pub async fn positions(settings: &Settings, market: Option<&str>) -> Result<()> {
+    let client = settings.authenticated_client().await?;
+    let positions = match market {
+        Some(market) => {
+            let market_addr = resolve_market(&client, market).await?;
+            client.get_user_positions_by_market(&market_addr).await?
+        }
+        None => client.get_all_user_positions().await?,
+    };
+
+    settings.output.print_rows(
+        &[
+            "market_addr",
+            "side",
+            "size",
+            "entry_price",
+            "mark_price",
+            "unrealized_pnl",
+            "liquidation_price",
+        ],
+        &positions,
+    )
+}
+
+/// Print deposits for a user
+pub async fn deposits(settings: &Settings, user_addr: Option<String>) -> Result<()> {
+    let client = settings.client().await?;
+    let user_addr = match user_addr {
+        Some(user_addr) => user_addr,
+        None => settings.user_addr()?,
+    };
+    let deposits = client.get_user_deposits(&user_addr).await?;
+
+    settings.output.print_rows(
+        &["version", "asset_addr", "amount", "status", "tx_hash"],
+        &deposits,
+    )
+}
+
+/// Options shared by trading commands
+pub struct Execution {
+    /// Skip the confirmation prompt
+    pub yes: bool,
+    /// Simulate the intent and print its expected outcome without sending it
+    pub dry_run: bool,
+}
+
+impl Execution {
+    async fn submit(
+        &self,
+        settings: &Settings,
+        client: &EkidenClient,
+        actions: Vec<ActionPayload>,
+    ) -> Result<()> {
+        if self.dry_run {
+            let simulation = client.simulate_actions(actions).await?;
+            print_simulation(settings.output, &simulation)?;
+            eprintln!("Dry run: intent simulated but not sent");
+            return Ok(());
+        }
+        if !trade::confirm(self.yes)? {
+            eprintln!("Aborted");
+            return Ok(());
+        }
+        print_intent(settings.output, &client.send_actions(actions).await?)
+    }
+}
+
+fn print_intent(output: OutputFormat, response: &SendIntentResponse) -> Result<()> {
+    if output == OutputFormat::Json {
+        return output.print_record(response);
+    }
+    let rows: Vec<_> = response
+        .outputs
+        .iter()
+        .map(|o| {
+            serde_json::json!({
+                "seq": response.seq,
+                "status": response.status,
+                "action_type": o.action_type,
+                "sid": o.sid(),
+            })
+        })
+        .collect();
+    output.print_rows(&["seq", "status", "action_type", "sid"], &rows)
+}
+
+fn print_simulation(output: OutputFormat, simulation: &IntentSimulation) -> Result<()> {
+    if output == OutputFormat::Json {
+        return output.print_record(simulation);
+    }
+    let rows: Vec<_> = simulation
+        .outputs
+        .iter()
+        .map(|o| {
+            serde_json::json!({
+                "action_type": o.action_type,
+                "filled_size": o.filled_size(),
+                "average_price": o.average_price(),
+                "fee": o.fee,
+                "error": o.error,
+            })
+        })
+        .collect();
+    output.print_rows(
+        &[
+            "action_type",
+            "filled_size",
+            "average_price",
+            "fee",
+            "error",
+        ],
+        &rows,
+    )?;
+    eprintln!(
+        "{}: total fee {}, margin required {}",
+        if simulation.accepted {
+            "Would be accepted"
+        } else {
+            "Would be rejected"
+        },
+        simulation.total_fee,
+        simulation.margin_required
+    );
+    Ok(())
+}
+
+/// Leverage for the summary: explicit flag, else the account setting for the market
+async fn leverage_for(
+    client: &EkidenClient,
+    market_addr: &str,
+    leverage: Option<u64>,
+) -> Result<u64> {
+    match leverage {
+        Some(leverage) => Ok(leverage),
+        None => Ok(client.get_user_leverage(market_addr).await?.leverage),
+    }
+}
+
+/// Parameters for `order place`
+pub struct PlaceOrder {
+    pub market: String,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    pub size: u64,
+    pub price: Option<u64>,
+    pub leverage: Option<u64>,
+    pub fee_bps: f64,
+}
+
+/// Place an order after showing a pre-trade summary
+pub async fn place_order(
+    settings: &Settings,
+    order: PlaceOrder,
+    execution: Execution,
+) -> Result<()> {
+    let client = settings.authenticated_client().await?;
+    let market = fetch_market(&client, &order.market).await?;
+    let price = match (order.order_type, order.price) {
+        (_, Some(price)) => price,
+        (OrderType::Market, None) => market.mark_price,
+        (OrderType::Limit, None) => {
+            return Err(EkidenError::validation("Limit orders require --price"))
+        }
+    };
+    let leverage = leverage_for(&client, &market.addr, order.leverage).await?;
+
+    let summary = TradeSummary::estimate(
+        &market,
+        order.side,
+        order.order_type,
+        price,
+        order.size,
+        leverage,
+        order.fee_bps,
+    );
+    settings.output.print_record(&summary)?;
+
+    let action = ActionPayload::place_order(
+        &market.addr,
+        order.side,
+        order.order_type,
+        price,
+        order.size,
+    );
+    execution.submit(settings, &client, vec![action]).await
+}
+
+/// Cancel an order by sid
+pub async fn cancel_order(
+    settings: &Settings,
+    market: &str,
+    sid: &str,
+    execution: Execution,
+) -> Result<()> {
+    let client = settings.authenticated_client().await?;
+    let market_addr = resolve_market(&client, market).await?;
+    eprintln!("Cancel order {} on {}", sid, market_addr);
+
+    let action = ActionPayload::cancel_order(&market_addr, sid);
+    execution.submit(settings, &client, vec![action]).await
+}
+
+/// Close the open position on a market with an opposite market order
+pub async fn close_position(
+    settings: &Settings,
+    market: &str,
+    fee_bps: f64,
+    execution: Execution,
+) -> Result<()> {
+    let client = settings.authenticated_client().await?;
+    let market = fetch_market(&client, market).await?;
+    let position = client
+        .get_user_positions_by_market(&market.addr)
+        .await?
+        .into_iter()
+        .find(|p| p.size > 0)
+        .ok_or_else(|| EkidenError::PositionNotFound {
+            market_addr: Some(market.addr.clone()),
+        })?;
+
+    let side = if position.signed_size() < 0 {
+        OrderSide::Buy
+    } else {
+        OrderSide::Sell
+    };
+    let mut summary = TradeSummary::estimate(
+        &market,
+        side,
+        OrderType::Market,
+        market.mark_price,
+        position.size,
+        position.leverage,
+        fee_bps,
+    );
+    summary.liquidation_price = None;
+    summary.unrealized_pnl = Some(position.unrealized_pnl);
+    settings.output.print_record(&summary)?;
+
+    let action = ActionPayload::place_order(
+        &market.addr,
+        side,
+        OrderType::Market,
+        market.mark_price,
+        position.size,
+    );
+    execution.submit(settings, &client, vec![action]).await
+}
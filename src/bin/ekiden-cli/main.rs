@@ -0,0 +1,258 @@
+//! Command line interface for quick operational checks against the Ekiden Gateway.
+//!
+//! Install with `cargo install ekiden-rust-sdk --features cli`. Settings are read
+//! from an optional config file (`--config` or `EKIDEN_CONFIG`) and `EKIDEN_*`
+//! environment variables such as `EKIDEN_ENV`, `EKIDEN_BASE_URL` and
+//! `EKIDEN_PRIVATE_KEY`.
+
+mod commands;
+mod output;
+mod settings;
+mod trade;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use commands::{Execution, PlaceOrder};
+use ekiden_rust_sdk::{OrderSide, OrderType};
+use output::OutputFormat;
+use settings::Settings;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(
+    name = "ekiden-cli",
+    version,
+    about = "Ekiden Gateway command line interface"
+)]
+struct Cli {
+    /// Path to a TOML, YAML or JSON settings file
+    #[arg(long, global = true, env = "EKIDEN_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Environment: production, staging, testnet or local
+    #[arg(long, global = true)]
+    env: Option<String>,
+
+    /// Output format
+    #[arg(long, short, global = true, value_enum)]
+    output: Option<OutputFormat>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List markets
+    Markets {
+        /// Filter by symbol
+        #[arg(long)]
+        symbol: Option<String>,
+    },
+    /// Show the top of the orderbook
+    Orderbook {
+        /// Market address or symbol
+        market: String,
+        /// Number of levels per side
+        #[arg(long, default_value_t = 10)]
+        depth: usize,
+        /// Seconds to wait for a snapshot
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+    /// Show recent candles
+    Candles {
+        /// Market address or symbol
+        market: String,
+        /// Candle interval (1m, 5m, 15m, 1h, 4h, 1d)
+        #[arg(long, default_value = "1h")]
+        interval: String,
+        /// Number of candles
+        #[arg(long, default_value_t = 24)]
+        limit: u32,
+    },
+    /// Show the portfolio summary (requires credentials)
+    Portfolio,
+    /// Show open positions (requires credentials)
+    Positions {
+        /// Market address or symbol
+        #[arg(long)]
+        market: Option<String>,
+    },
+    /// Show deposits
+    Deposits {
+        /// User address, defaults to the configured account
+        #[arg(long)]
+        user: Option<String>,
+    },
+    /// Place or cancel orders (requires credentials)
+    #[command(subcommand)]
+    Order(OrderCommand),
+    /// Close the open position on a market with a market order (requires credentials)
+    ClosePosition {
+        /// Market address or symbol
+        market: String,
+        /// Taker fee in basis points used for the fee estimate
+        #[arg(long, default_value_t = 5.0)]
+        fee_bps: f64,
+        #[command(flatten)]
+        execution: ExecutionArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum OrderCommand {
+    /// Place an order
+    Place {
+        /// Market address or symbol
+        market: String,
+        #[arg(long, value_enum)]
+        side: Side,
+        /// Order size in base units
+        #[arg(long)]
+        size: u64,
+        /// Limit price; market orders default to the mark price
+        #[arg(long)]
+        price: Option<u64>,
+        #[arg(long = "type", value_enum, default_value_t = Kind::Limit)]
+        order_type: Kind,
+        /// Leverage for the liquidation estimate, defaults to the account setting
+        #[arg(long)]
+        leverage: Option<u64>,
+        /// Taker fee in basis points used for the fee estimate
+        #[arg(long, default_value_t = 5.0)]
+        fee_bps: f64,
+        #[command(flatten)]
+        execution: ExecutionArgs,
+    },
+    /// Cancel an order
+    Cancel {
+        /// Market address or symbol
+        market: String,
+        /// Order sid
+        sid: String,
+        #[command(flatten)]
+        execution: ExecutionArgs,
+    },
+}
+
+#[derive(Args)]
+struct ExecutionArgs {
+    /// Skip the confirmation prompt
+    #[arg(long, short)]
+    yes: bool,
+    /// Simulate the intent and print its expected fills, fees and rejections
+    /// without sending it
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl From<ExecutionArgs> for Execution {
+    fn from(args: ExecutionArgs) -> Self {
+        Self {
+            yes: args.yes,
+            dry_run: args.dry_run,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Side {
+    Buy,
+    Sell,
+}
+
+impl From<Side> for OrderSide {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Buy => OrderSide::Buy,
+            Side::Sell => OrderSide::Sell,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Kind {
+    Limit,
+    Market,
+}
+
+impl From<Kind> for OrderType {
+    fn from(kind: Kind) -> Self {
+        match kind {
+            Kind::Limit => OrderType::Limit,
+            Kind::Market => OrderType::Market,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> ekiden_rust_sdk::Result<()> {
+    let mut settings = Settings::load(cli.config.as_deref())?;
+    if cli.env.is_some() {
+        settings.env = cli.env;
+    }
+    if let Some(output) = cli.output {
+        settings.output = output;
+    }
+
+    match cli.command {
+        Command::Markets { symbol } => commands::markets(&settings, symbol).await,
+        Command::Orderbook {
+            market,
+            depth,
+            timeout,
+        } => commands::orderbook(&settings, &market, depth, Duration::from_secs(timeout)).await,
+        Command::Candles {
+            market,
+            interval,
+            limit,
+        } => commands::candles(&settings, &market, &interval, limit).await,
+        Command::Portfolio => commands::portfolio(&settings).await,
+        Command::Positions { market } => commands::positions(&settings, market.as_deref()).await,
+        Command::Deposits { user } => commands::deposits(&settings, user).await,
+        Command::Order(OrderCommand::Place {
+            market,
+            side,
+            size,
+            price,
+            order_type,
+            leverage,
+            fee_bps,
+            execution,
+        }) => {
+            let order = PlaceOrder {
+                market,
+                side: side.into(),
+                order_type: order_type.into(),
+                size,
+                price,
+                leverage,
+                fee_bps,
+            };
+            commands::place_order(&settings, order, execution.into()).await
+        }
+        Command::Order(OrderCommand::Cancel {
+            market,
+            sid,
+            execution,
+        }) => commands::cancel_order(&settings, &market, &sid, execution.into()).await,
+        Command::ClosePosition {
+            market,
+            fee_bps,
+            execution,
+        } => commands::close_position(&settings, &market, fee_bps, execution.into()).await,
+    }
+}
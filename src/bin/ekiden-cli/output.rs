@@ -0,0 +1,195 @@
+//! Rendering of command results as tables, JSON or CSV.
+//!
+//! JSON output serializes the SDK types directly, so field names match the
+//! gateway API. Table and CSV output select columns by those same names.
+
+use clap::ValueEnum;
+use ekiden_rust_sdk::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Output format selected with `--output`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    /// Print a list of records, using `columns` as field names for table and CSV output
+    pub fn print_rows<T: Serialize>(self, columns: &[&'static str], rows: &[T]) -> Result<()> {
+        if self == OutputFormat::Json {
+            println!("{}", serde_json::to_string_pretty(rows)?);
+            return Ok(());
+        }
+        let mut table = Table::new(columns.to_vec());
+        for row in rows {
+            let value = serde_json::to_value(row)?;
+            table.row(columns.iter().map(|c| cell(&value[*c])).collect());
+        }
+        self.print_table(&table);
+        Ok(())
+    }
+
+    /// Print a single record as `field,value` pairs for table and CSV output
+    ///
+    /// Fields are listed in alphabetical order.
+    pub fn print_record<T: Serialize>(self, record: &T) -> Result<()> {
+        let value = serde_json::to_value(record)?;
+        if self == OutputFormat::Json {
+            println!("{}", serde_json::to_string_pretty(&value)?);
+            return Ok(());
+        }
+        let mut table = Table::new(vec!["field", "value"]);
+        if let Value::Object(fields) = &value {
+            for (field, value) in fields {
+                table.row(vec![field.clone(), cell(value)]);
+            }
+        }
+        self.print_table(&table);
+        Ok(())
+    }
+
+    /// Print a prebuilt table; JSON output falls back to an array of objects
+    pub fn print_table(self, table: &Table) {
+        match self {
+            OutputFormat::Table => println!("{}", table.render()),
+            OutputFormat::Csv => println!("{}", table.render_csv()),
+            OutputFormat::Json => {
+                let rows: Vec<Value> = table
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        Value::Object(
+                            table
+                                .headers
+                                .iter()
+                                .zip(row)
+                                .map(|(h, c)| (h.to_string(), Value::String(c.clone())))
+                                .collect(),
+                        )
+                    })
+                    .collect();
+                println!("{:#}", Value::Array(rows));
+            }
+        }
+    }
+}
+
+/// Render a JSON value as a single cell
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// A table of string cells with a header row
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Create an empty table with the given headers
+    pub fn new(headers: Vec<&'static str>) -> Self {
+        Self {
+            headers,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Append a row; missing cells render empty
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    /// Render with columns padded to their widest cell
+    pub fn render(&self) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.len()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let line = |cells: Vec<&str>| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        };
+
+        let mut out = vec![line(self.headers.clone())];
+        for row in &self.rows {
+            let mut cells: Vec<&str> = row.iter().map(String::as_str).collect();
+            cells.resize(self.headers.len(), "");
+            out.push(line(cells));
+        }
+        out.join("\n")
+    }
+
+    /// Render as RFC 4180 CSV with a header line
+    pub fn render_csv(&self) -> String {
+        let mut out = vec![csv_line(self.headers.iter().copied())];
+        for row in &self.rows {
+            let cells = row
+                .iter()
+                .map(String::as_str)
+                .chain(std::iter::repeat(""))
+                .take(self.headers.len());
+            out.push(csv_line(cells));
+        }
+        out.join("\n")
+    }
+}
+
+fn csv_line<'a>(cells: impl Iterator<Item = &'a str>) -> String {
+    cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_pads_columns() {
+        let mut table = Table::new(vec!["symbol", "price"]);
+        table.row(vec!["BTC-USD".to_string(), "100".to_string()]);
+        table.row(vec!["ETH".to_string()]);
+
+        assert_eq!(table.render(), "symbol   price\nBTC-USD  100\nETH");
+    }
+
+    #[test]
+    fn test_render_csv_escapes() {
+        let mut table = Table::new(vec!["name", "note"]);
+        table.row(vec!["a,b".to_string(), "say \"hi\"".to_string()]);
+
+        assert_eq!(table.render_csv(), "name,note\n\"a,b\",\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_cells_from_json() {
+        assert_eq!(cell(&Value::Null), "");
+        assert_eq!(cell(&serde_json::json!("x")), "x");
+        assert_eq!(cell(&serde_json::json!(42)), "42");
+    }
+}
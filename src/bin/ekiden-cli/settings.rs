@@ -0,0 +1,101 @@
+//! CLI settings loaded from an optional config file and `EKIDEN_*` environment variables.
+
+use crate::output::OutputFormat;
+use ekiden_rust_sdk::{
+    Crypto, EkidenClient, EkidenClientBuilder, EkidenConfig, EkidenError, KeyPair, Result,
+};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Connection and credential settings
+#[derive(Default, Deserialize)]
+pub struct Settings {
+    /// Named environment: production, staging, testnet or local
+    pub env: Option<String>,
+    /// API base URL, overrides `env`
+    pub base_url: Option<String>,
+    /// WebSocket URL, derived from the base URL when unset
+    pub ws_url: Option<String>,
+    /// Hex-encoded Ed25519 private key
+    pub private_key: Option<String>,
+    /// Pre-issued bearer token
+    pub token: Option<String>,
+    /// User address for user-scoped queries
+    pub user_addr: Option<String>,
+    /// Default output format
+    #[serde(default)]
+    pub output: OutputFormat,
+}
+
+impl Settings {
+    /// Load settings from a file (TOML, YAML or JSON) overlaid with `EKIDEN_*` variables
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut builder = config::Config::builder();
+        if let Some(path) = path {
+            builder = builder.add_source(config::File::from(path));
+        }
+        builder
+            .add_source(config::Environment::with_prefix("EKIDEN"))
+            .build()
+            .and_then(|c| c.try_deserialize())
+            .map_err(|e| EkidenError::config(format!("Failed to load settings: {}", e)))
+    }
+
+    fn sdk_config(&self) -> Result<EkidenConfig> {
+        let config = match (&self.base_url, self.env.as_deref()) {
+            (Some(base_url), _) => EkidenConfig::new(base_url)?,
+            (None, Some("production")) | (None, None) => EkidenConfig::production()?,
+            (None, Some("staging")) => EkidenConfig::staging()?,
+            (None, Some("testnet")) => EkidenConfig::testnet()?,
+            (None, Some("local")) => EkidenConfig::local()?,
+            (None, Some(other)) => {
+                return Err(EkidenError::config(format!(
+                    "Unknown environment: {}",
+                    other
+                )))
+            }
+        };
+        match &self.ws_url {
+            Some(ws_url) => config.with_ws_url(ws_url),
+            None => Ok(config),
+        }
+    }
+
+    /// Build a client with any configured credentials
+    pub async fn client(&self) -> Result<EkidenClient> {
+        let mut builder = EkidenClientBuilder::new().config(self.sdk_config()?);
+        if let Some(private_key) = &self.private_key {
+            builder = builder.private_key(private_key);
+        }
+        if let Some(token) = &self.token {
+            builder = builder.token(token);
+        }
+        builder.build().await
+    }
+
+    /// Build a client and make sure it holds a bearer token
+    pub async fn authenticated_client(&self) -> Result<EkidenClient> {
+        let client = self.client().await?;
+        if !client.is_authenticated().await {
+            if self.private_key.is_none() {
+                return Err(EkidenError::auth(
+                    "Set EKIDEN_PRIVATE_KEY or EKIDEN_TOKEN for this command",
+                ));
+            }
+            client.authorize().await?;
+        }
+        Ok(client)
+    }
+
+    /// Resolve the user address from settings or the configured key
+    pub fn user_addr(&self) -> Result<String> {
+        if let Some(user_addr) = &self.user_addr {
+            return Ok(user_addr.clone());
+        }
+        let private_key = self.private_key.as_deref().ok_or_else(|| {
+            EkidenError::config("Set EKIDEN_USER_ADDR or EKIDEN_PRIVATE_KEY for this command")
+        })?;
+        let key_pair = KeyPair::from_private_key(private_key)?;
+        Crypto::generate_address_from_public_key(&key_pair.public_key())
+    }
+}
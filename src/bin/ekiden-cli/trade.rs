@@ -0,0 +1,103 @@
+//! Pre-trade summaries and confirmation prompts for trading commands.
+
+use ekiden_rust_sdk::{MarketResponse, OrderSide, OrderType, Result};
+use serde::Serialize;
+use std::io::{BufRead, Write};
+
+/// Estimated economics of an order before it is sent
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeSummary {
+    pub symbol: String,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    pub price: u64,
+    pub size: u64,
+    pub leverage: u64,
+    pub notional: f64,
+    pub fee: f64,
+    pub liquidation_price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unrealized_pnl: Option<i64>,
+}
+
+impl TradeSummary {
+    /// Estimate notional, fee and liquidation price for an order on `market`
+    pub fn estimate(
+        market: &MarketResponse,
+        side: OrderSide,
+        order_type: OrderType,
+        price: u64,
+        size: u64,
+        leverage: u64,
+        fee_bps: f64,
+    ) -> Self {
+        let base_unit = 10f64.powi(market.base_decimals as i32);
+        let notional = price as f64 * size as f64 / base_unit;
+        Self {
+            symbol: market.symbol.clone(),
+            side,
+            order_type,
+            price,
+            size,
+            leverage,
+            notional,
+            fee: notional * fee_bps / 10_000.0,
+            liquidation_price: liquidation_price(
+                side,
+                price as f64,
+                leverage,
+                market.maintenance_margin_ratio,
+            ),
+            unrealized_pnl: None,
+        }
+    }
+}
+
+/// Isolated-margin liquidation estimate, `None` when the position cannot be liquidated
+pub fn liquidation_price(
+    side: OrderSide,
+    entry: f64,
+    leverage: u64,
+    maintenance_margin_ratio: f64,
+) -> Option<f64> {
+    if leverage == 0 {
+        return None;
+    }
+    let initial = 1.0 / leverage as f64;
+    match side {
+        OrderSide::Buy => {
+            let price = entry * (1.0 - initial + maintenance_margin_ratio);
+            (price > 0.0).then_some(price)
+        }
+        OrderSide::Sell => Some(entry * (1.0 + initial - maintenance_margin_ratio)),
+    }
+}
+
+/// Ask for confirmation on stdin unless `yes` was passed
+pub fn confirm(yes: bool) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+    eprint!("Proceed? [y/N] ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes" | "YES"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_liquidation_price() {
+        let long = liquidation_price(OrderSide::Buy, 100.0, 10, 0.05).unwrap();
+        assert!((long - 95.0).abs() < 1e-9);
+
+        let short = liquidation_price(OrderSide::Sell, 100.0, 10, 0.05).unwrap();
+        assert!((short - 105.0).abs() < 1e-9);
+
+        assert!(liquidation_price(OrderSide::Buy, 100.0, 1, 0.05).is_some());
+        assert!(liquidation_price(OrderSide::Buy, 100.0, 0, 0.05).is_none());
+    }
+}
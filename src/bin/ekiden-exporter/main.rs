@@ -0,0 +1,383 @@
+//! Prometheus exporter for Ekiden market and account metrics.
+//!
+//! Install with `cargo install ekiden-rust-sdk --features exporter`. The exporter
+//! subscribes to the orderbook and trades of each configured market and, when a
+//! user address or private key is given, to the user channel. With credentials
+//! it also polls the portfolio for equity and margin. Everything is served on
+//! `/metrics` for Prometheus to scrape, along with the client's request and
+//! WebSocket metrics.
+
+mod metrics;
+
+use axum::{extract::State as AxumState, routing::get, Router};
+use clap::Parser;
+use ekiden_rust_sdk::metrics::PrometheusRecorder;
+use ekiden_rust_sdk::utils::format;
+use ekiden_rust_sdk::{
+    Crypto, EkidenClient, EkidenClientBuilder, EkidenConfig, EkidenError, KeyPair, Orderbook,
+    Result, WsEvent,
+};
+use metrics::{MarketMetrics, State};
+use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, RwLock};
+use tracing::{info, warn};
+
+#[derive(Parser)]
+#[command(
+    name = "ekiden-exporter",
+    version,
+    about = "Prometheus exporter for the Ekiden Gateway"
+)]
+struct Args {
+    /// Address to serve /metrics on
+    #[arg(long, env = "EKIDEN_EXPORTER_BIND", default_value = "127.0.0.1:9464")]
+    bind: SocketAddr,
+
+    /// Market addresses or symbols to watch, comma separated
+    #[arg(long, env = "EKIDEN_MARKETS", value_delimiter = ',', required = true)]
+    markets: Vec<String>,
+
+    /// Environment: production, staging, testnet or local
+    #[arg(long, env = "EKIDEN_ENV")]
+    env: Option<String>,
+
+    /// API base URL, overrides --env
+    #[arg(long, env = "EKIDEN_BASE_URL")]
+    base_url: Option<String>,
+
+    /// WebSocket URL, derived from the base URL when unset
+    #[arg(long, env = "EKIDEN_WS_URL")]
+    ws_url: Option<String>,
+
+    /// Hex-encoded Ed25519 private key, enables account metrics
+    #[arg(long, env = "EKIDEN_PRIVATE_KEY", hide_env_values = true)]
+    private_key: Option<String>,
+
+    /// Pre-issued bearer token, enables account metrics
+    #[arg(long, env = "EKIDEN_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    /// User address for the user channel, derived from the key when unset
+    #[arg(long, env = "EKIDEN_USER_ADDR")]
+    user_addr: Option<String>,
+
+    /// Seconds between REST polls of market info and the portfolio
+    #[arg(long, default_value_t = 15)]
+    poll_interval: u64,
+
+    /// Seconds between WebSocket health checks
+    #[arg(long, default_value_t = 5)]
+    health_interval: u64,
+}
+
+impl Args {
+    fn sdk_config(&self) -> Result<EkidenConfig> {
+        let config = match (&self.base_url, self.env.as_deref()) {
+            (Some(base_url), _) => EkidenConfig::new(base_url)?,
+            (None, Some("production")) | (None, None) => EkidenConfig::production()?,
+            (None, Some("staging")) => EkidenConfig::staging()?,
+            (None, Some("testnet")) => EkidenConfig::testnet()?,
+            (None, Some("local")) => EkidenConfig::local()?,
+            (None, Some(other)) => {
+                return Err(EkidenError::config(format!(
+                    "Unknown environment: {}",
+                    other
+                )))
+            }
+        };
+        match &self.ws_url {
+            Some(ws_url) => config.with_ws_url(ws_url),
+            None => Ok(config),
+        }
+    }
+
+    fn user_addr(&self) -> Result<Option<String>> {
+        if let Some(user_addr) = &self.user_addr {
+            return Ok(Some(user_addr.clone()));
+        }
+        match &self.private_key {
+            Some(private_key) => {
+                let key_pair = KeyPair::from_private_key(private_key)?;
+                Crypto::generate_address_from_public_key(&key_pair.public_key()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+type Shared = Arc<RwLock<State>>;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt::init();
+    let args = Args::parse();
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<()> {
+    let recorder = Arc::new(PrometheusRecorder::new());
+    let mut builder = EkidenClientBuilder::new()
+        .config(args.sdk_config()?)
+        .metrics(recorder.clone());
+    if let Some(private_key) = &args.private_key {
+        builder = builder.private_key(private_key);
+    }
+    if let Some(token) = &args.token {
+        builder = builder.token(token);
+    }
+    let client = Arc::new(builder.build().await?);
+    let has_credentials = args.private_key.is_some() || args.token.is_some();
+    if has_credentials && !client.is_authenticated().await {
+        client.authorize().await?;
+    }
+    let user_addr = args.user_addr()?;
+
+    let state: Shared = Arc::default();
+    let mut market_addrs = Vec::new();
+    for market in &args.markets {
+        let found = if format::validate_address(market).is_ok() {
+            client.get_market_by_address(market).await?.map(|mut m| {
+                if m.addr.is_empty() {
+                    m.addr = market.to_string();
+                }
+                m
+            })
+        } else {
+            client.get_market_by_symbol(market).await?
+        };
+        let market = found
+            .filter(|m| !m.addr.is_empty())
+            .ok_or_else(|| EkidenError::validation(format!("Unknown market: {}", market)))?;
+        state
+            .write()
+            .await
+            .markets
+            .insert(market.addr.clone(), MarketMetrics::new(&market));
+        market_addrs.push(market.addr);
+    }
+
+    client.connect_websocket().await?;
+    subscribe_all(&client, &state, &market_addrs, user_addr.as_deref()).await?;
+    state.write().await.ws_connected = true;
+
+    tokio::spawn(poll(
+        client.clone(),
+        state.clone(),
+        market_addrs.clone(),
+        has_credentials,
+        Duration::from_secs(args.poll_interval.max(1)),
+    ));
+    tokio::spawn(health(
+        client,
+        state.clone(),
+        market_addrs,
+        user_addr,
+        Duration::from_secs(args.health_interval.max(1)),
+    ));
+
+    let app = Router::new()
+        .route("/metrics", get(render))
+        .with_state((state, recorder));
+    let listener = tokio::net::TcpListener::bind(args.bind).await?;
+    info!("Serving metrics on http://{}/metrics", args.bind);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Render market and account metrics followed by the client's own request metrics
+async fn render(
+    AxumState((state, recorder)): AxumState<(Shared, Arc<PrometheusRecorder>)>,
+) -> String {
+    let mut body = state.read().await.render();
+    body.push_str(&recorder.render());
+    body
+}
+
+/// Subscribe to every configured channel and spawn a pump per subscription
+///
+/// Pumps end when their channel closes, which happens on resubscription.
+async fn subscribe_all(
+    client: &EkidenClient,
+    state: &Shared,
+    market_addrs: &[String],
+    user_addr: Option<&str>,
+) -> Result<()> {
+    for market_addr in market_addrs {
+        let receiver = client.subscribe_orderbook(market_addr).await?;
+        tokio::spawn(pump(state.clone(), receiver, Some(market_addr.clone())));
+        let receiver = client.subscribe_trades(market_addr).await?;
+        tokio::spawn(pump(state.clone(), receiver, None));
+    }
+    if let Some(user_addr) = user_addr {
+        let receiver = client.subscribe_user(user_addr).await?;
+        tokio::spawn(pump(state.clone(), receiver, None));
+    }
+    Ok(())
+}
+
+/// Apply events to the state; `book` names the market whose orderbook this channel carries
+async fn pump(state: Shared, mut receiver: broadcast::Receiver<WsEvent>, book: Option<String>) {
+    let mut orderbook = book.map(Orderbook::new);
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Exporter lagged, skipped {} events", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+
+        let mut state = state.write().await;
+        state.ws_last_event = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_millis() as u64);
+        *state.ws_events.entry(event_type(&event)).or_default() += 1;
+
+        match event {
+            WsEvent::OrderbookSnapshot { .. } | WsEvent::OrderbookUpdate { .. } => {
+                if let Some(orderbook) = orderbook.as_mut() {
+                    if orderbook.apply_event(&event) {
+                        if let Some(market) = state.markets.get_mut(orderbook.market_addr()) {
+                            market.update_book(orderbook);
+                        }
+                    }
+                }
+            }
+            WsEvent::Trade {
+                market_addr, price, ..
+            } => {
+                if let Some(market) = state.markets.get_mut(&market_addr) {
+                    market.last_trade_price = Some(price);
+                }
+            }
+            WsEvent::PositionUpdate { position } => state.update_position(position),
+            WsEvent::OrderUpdate { .. }
+            | WsEvent::BalanceUpdate { .. }
+            | WsEvent::Announcement { .. }
+            | WsEvent::Candle { .. }
+            | WsEvent::Ticker { .. }
+            | WsEvent::UserFill { .. }
+            | WsEvent::Liquidation { .. }
+            | WsEvent::Resync { .. } => {}
+        }
+    }
+}
+
+fn event_type(event: &WsEvent) -> &'static str {
+    match event {
+        WsEvent::OrderbookSnapshot { .. } => "orderbook_snapshot",
+        WsEvent::OrderbookUpdate { .. } => "orderbook_update",
+        WsEvent::Trade { .. } => "trade",
+        WsEvent::OrderUpdate { .. } => "order_update",
+        WsEvent::PositionUpdate { .. } => "position_update",
+        WsEvent::BalanceUpdate { .. } => "balance_update",
+        WsEvent::Announcement { .. } => "announcement",
+        WsEvent::Candle { .. } => "candle",
+        WsEvent::Ticker { .. } => "ticker",
+        WsEvent::UserFill { .. } => "user_fill",
+        WsEvent::Liquidation { .. } => "liquidation",
+        WsEvent::Resync { .. } => "resync",
+    }
+}
+
+/// Poll market info and, with credentials, positions and the portfolio
+async fn poll(
+    client: Arc<EkidenClient>,
+    state: Shared,
+    market_addrs: Vec<String>,
+    has_credentials: bool,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let mut failed = false;
+
+        for market_addr in &market_addrs {
+            match client.get_market_by_address(market_addr).await {
+                Ok(Some(market)) => {
+                    if let Some(metrics) = state.write().await.markets.get_mut(market_addr) {
+                        metrics.update_market(&market);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("Failed to poll market {}: {}", market_addr, e);
+                    failed = true;
+                }
+            }
+        }
+
+        if has_credentials {
+            match client.get_user_portfolio().await {
+                Ok(portfolio) => state.write().await.portfolio = Some(portfolio.summary),
+                Err(e) => {
+                    warn!("Failed to poll portfolio: {}", e);
+                    failed = true;
+                }
+            }
+            match client.get_all_user_positions().await {
+                Ok(positions) => {
+                    let mut state = state.write().await;
+                    state.positions.clear();
+                    for position in positions {
+                        state.update_position(position);
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to poll positions: {}", e);
+                    failed = true;
+                }
+            }
+        }
+
+        if failed {
+            state.write().await.poll_errors += 1;
+        }
+    }
+}
+
+/// Track WebSocket health, reconnecting and resubscribing after a drop
+async fn health(
+    client: Arc<EkidenClient>,
+    state: Shared,
+    market_addrs: Vec<String>,
+    user_addr: Option<String>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        if client.is_websocket_connected().await {
+            state.write().await.ws_connected = true;
+            continue;
+        }
+
+        warn!("WebSocket disconnected, reconnecting");
+        {
+            let mut state = state.write().await;
+            state.ws_connected = false;
+            state.ws_reconnects += 1;
+        }
+        let reconnected = async {
+            client.connect_websocket().await?;
+            subscribe_all(&client, &state, &market_addrs, user_addr.as_deref()).await
+        }
+        .await;
+        match reconnected {
+            Ok(()) => state.write().await.ws_connected = true,
+            Err(e) => warn!("Reconnect failed: {}", e),
+        }
+    }
+}
@@ -0,0 +1,407 @@
+//! Exporter state and Prometheus text rendering.
+
+use ekiden_rust_sdk::{MarketResponse, Orderbook, PortfolioSummary, PositionResponse};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Latest values for one market
+#[derive(Debug, Clone)]
+pub struct MarketMetrics {
+    pub symbol: String,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    pub best_bid: Option<u64>,
+    pub best_ask: Option<u64>,
+    pub bid_volume: u64,
+    pub ask_volume: u64,
+    pub last_trade_price: Option<u64>,
+    pub mark_price: u64,
+    pub oracle_price: u64,
+    pub open_interest: u64,
+}
+
+impl MarketMetrics {
+    pub fn new(market: &MarketResponse) -> Self {
+        Self {
+            symbol: market.symbol.clone(),
+            base_decimals: market.base_decimals,
+            quote_decimals: market.quote_decimals,
+            best_bid: None,
+            best_ask: None,
+            bid_volume: 0,
+            ask_volume: 0,
+            last_trade_price: None,
+            mark_price: market.mark_price,
+            oracle_price: market.oracle_price,
+            open_interest: market.open_interest,
+        }
+    }
+
+    /// Refresh REST-sourced prices
+    pub fn update_market(&mut self, market: &MarketResponse) {
+        self.mark_price = market.mark_price;
+        self.oracle_price = market.oracle_price;
+        self.open_interest = market.open_interest;
+    }
+
+    /// Refresh top-of-book values from a local orderbook
+    pub fn update_book(&mut self, book: &Orderbook) {
+        self.best_bid = book.best_bid().map(|l| l.price);
+        self.best_ask = book.best_ask().map(|l| l.price);
+        self.bid_volume = book.bid_volume();
+        self.ask_volume = book.ask_volume();
+    }
+
+    fn price(&self, value: u64) -> f64 {
+        value as f64 / 10f64.powi(self.quote_decimals as i32)
+    }
+
+    fn size(&self, value: u64) -> f64 {
+        value as f64 / 10f64.powi(self.base_decimals as i32)
+    }
+}
+
+/// Everything exposed on `/metrics`
+#[derive(Debug, Default)]
+pub struct State {
+    /// Keyed by market address
+    pub markets: BTreeMap<String, MarketMetrics>,
+    /// Open positions keyed by market address
+    pub positions: BTreeMap<String, PositionResponse>,
+    pub portfolio: Option<PortfolioSummary>,
+    pub ws_connected: bool,
+    pub ws_reconnects: u64,
+    /// Events received keyed by event type
+    pub ws_events: BTreeMap<&'static str, u64>,
+    /// Unix time in milliseconds of the last WebSocket event
+    pub ws_last_event: Option<u64>,
+    pub poll_errors: u64,
+}
+
+impl State {
+    /// Record or clear a position from a REST or WebSocket update
+    pub fn update_position(&mut self, position: PositionResponse) {
+        if position.size == 0 {
+            self.positions.remove(&position.market_addr);
+        } else {
+            self.positions
+                .insert(position.market_addr.clone(), position);
+        }
+    }
+
+    /// Render the state in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = MetricWriter::default();
+
+        let markets = || self.markets.iter();
+        let book_gauges: [(&str, &str, fn(&MarketMetrics) -> Option<f64>); 9] = [
+            ("ekiden_best_bid_price", "Best bid price", |m| {
+                m.best_bid.map(|p| m.price(p))
+            }),
+            ("ekiden_best_ask_price", "Best ask price", |m| {
+                m.best_ask.map(|p| m.price(p))
+            }),
+            (
+                "ekiden_mid_price",
+                "Mid price between best bid and ask",
+                |m| Some((m.price(m.best_bid?) + m.price(m.best_ask?)) / 2.0),
+            ),
+            ("ekiden_spread", "Best ask minus best bid", |m| {
+                Some(m.price(m.best_ask?.checked_sub(m.best_bid?)?))
+            }),
+            (
+                "ekiden_spread_bps",
+                "Spread relative to mid in basis points",
+                |m| {
+                    let (bid, ask) = (m.best_bid? as f64, m.best_ask? as f64);
+                    Some((ask - bid) / ((ask + bid) / 2.0) * 10_000.0)
+                },
+            ),
+            (
+                "ekiden_book_volume",
+                "Resting size on both sides of the book",
+                |m| Some(m.size(m.bid_volume + m.ask_volume)),
+            ),
+            (
+                "ekiden_last_trade_price",
+                "Price of the last public trade",
+                |m| m.last_trade_price.map(|p| m.price(p)),
+            ),
+            ("ekiden_mark_price", "Mark price", |m| {
+                Some(m.price(m.mark_price))
+            }),
+            (
+                "ekiden_open_interest",
+                "Open interest in base tokens",
+                |m| Some(m.size(m.open_interest)),
+            ),
+        ];
+        for (name, help, value) in book_gauges {
+            out.family(name, help, "gauge");
+            for (addr, market) in markets() {
+                if let Some(v) = value(market) {
+                    out.sample(name, &[("market", addr), ("symbol", &market.symbol)], v);
+                }
+            }
+        }
+
+        out.family("ekiden_oracle_price", "Oracle price", "gauge");
+        for (addr, market) in markets() {
+            out.sample(
+                "ekiden_oracle_price",
+                &[("market", addr), ("symbol", &market.symbol)],
+                market.price(market.oracle_price),
+            );
+        }
+
+        let position_gauges: [(&str, &str, fn(&PositionResponse, &MarketMetrics) -> f64); 5] = [
+            (
+                "ekiden_position_size",
+                "Position size in base tokens, negative when short",
+                |p, m| {
+                    let size = m.size(p.size);
+                    if p.signed_size() < 0 {
+                        -size
+                    } else {
+                        size
+                    }
+                },
+            ),
+            (
+                "ekiden_position_entry_price",
+                "Position entry price",
+                |p, m| m.price(p.entry_price),
+            ),
+            (
+                "ekiden_position_liquidation_price",
+                "Position liquidation price",
+                |p, m| m.price(p.liquidation_price),
+            ),
+            (
+                "ekiden_position_unrealized_pnl",
+                "Position unrealized PnL in quote units",
+                |p, m| p.unrealized_pnl as f64 / 10f64.powi(m.quote_decimals as i32),
+            ),
+            ("ekiden_position_leverage", "Position leverage", |p, _| {
+                p.leverage as f64
+            }),
+        ];
+        for (name, help, value) in position_gauges {
+            out.family(name, help, "gauge");
+            for (addr, position) in &self.positions {
+                let market = self
+                    .markets
+                    .get(addr)
+                    .cloned()
+                    .unwrap_or_else(|| unscaled(addr));
+                out.sample(
+                    name,
+                    &[("market", addr), ("symbol", &market.symbol)],
+                    value(position, &market),
+                );
+            }
+        }
+
+        if let Some(portfolio) = &self.portfolio {
+            let summary: [(&str, &str, f64); 5] = [
+                (
+                    "ekiden_equity",
+                    "Total account value in collateral base units",
+                    portfolio.total_value as f64,
+                ),
+                (
+                    "ekiden_available_balance",
+                    "Available balance in collateral base units",
+                    portfolio.available_balance as f64,
+                ),
+                (
+                    "ekiden_margin_used",
+                    "Margin in use in collateral base units",
+                    portfolio.margin_used as f64,
+                ),
+                (
+                    "ekiden_margin_available",
+                    "Margin available in collateral base units",
+                    portfolio.margin_available as f64,
+                ),
+                (
+                    "ekiden_unrealized_pnl",
+                    "Total unrealized PnL in collateral base units",
+                    portfolio.unrealized_pnl as f64,
+                ),
+            ];
+            for (name, help, value) in summary {
+                out.family(name, help, "gauge");
+                out.sample(name, &[], value);
+            }
+        }
+
+        out.family(
+            "ekiden_ws_up",
+            "Whether the WebSocket is connected",
+            "gauge",
+        );
+        out.sample(
+            "ekiden_ws_up",
+            &[],
+            if self.ws_connected { 1.0 } else { 0.0 },
+        );
+        out.family(
+            "ekiden_ws_reconnects_total",
+            "WebSocket reconnects",
+            "counter",
+        );
+        out.sample("ekiden_ws_reconnects_total", &[], self.ws_reconnects as f64);
+        out.family(
+            "ekiden_ws_events_total",
+            "WebSocket events received",
+            "counter",
+        );
+        for (event_type, count) in &self.ws_events {
+            out.sample(
+                "ekiden_ws_events_total",
+                &[("type", event_type)],
+                *count as f64,
+            );
+        }
+        if let Some(last) = self.ws_last_event {
+            out.family(
+                "ekiden_ws_last_event_timestamp_seconds",
+                "Unix time of the last WebSocket event",
+                "gauge",
+            );
+            out.sample(
+                "ekiden_ws_last_event_timestamp_seconds",
+                &[],
+                last as f64 / 1000.0,
+            );
+        }
+        out.family("ekiden_poll_errors_total", "Failed REST polls", "counter");
+        out.sample("ekiden_poll_errors_total", &[], self.poll_errors as f64);
+
+        out.0
+    }
+}
+
+/// Metrics for a position whose market metadata was never loaded
+fn unscaled(addr: &str) -> MarketMetrics {
+    MarketMetrics {
+        symbol: addr.to_string(),
+        base_decimals: 0,
+        quote_decimals: 0,
+        best_bid: None,
+        best_ask: None,
+        bid_volume: 0,
+        ask_volume: 0,
+        last_trade_price: None,
+        mark_price: 0,
+        oracle_price: 0,
+        open_interest: 0,
+    }
+}
+
+#[derive(Default)]
+struct MetricWriter(String);
+
+impl MetricWriter {
+    fn family(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.0, "# HELP {} {}", name, help);
+        let _ = writeln!(self.0, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.0.push_str(name);
+        if !labels.is_empty() {
+            self.0.push('{');
+            for (i, (key, value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.0.push(',');
+                }
+                let _ = write!(self.0, "{}=\"{}\"", key, escape_label(value));
+            }
+            self.0.push('}');
+        }
+        let _ = writeln!(self.0, " {}", value);
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market() -> MarketMetrics {
+        MarketMetrics {
+            symbol: "BTC-USD".to_string(),
+            base_decimals: 3,
+            quote_decimals: 2,
+            best_bid: Some(6_499_000),
+            best_ask: Some(6_501_000),
+            bid_volume: 1_500,
+            ask_volume: 500,
+            last_trade_price: None,
+            mark_price: 6_500_000,
+            oracle_price: 6_500_500,
+            open_interest: 0,
+        }
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
+    #[test]
+    fn test_render_market() {
+        let mut state = State::default();
+        state.markets.insert("0x1".to_string(), market());
+        state.ws_connected = true;
+        let text = state.render();
+
+        assert!(text.contains("# TYPE ekiden_mid_price gauge\n"));
+        assert!(text.contains("ekiden_mid_price{market=\"0x1\",symbol=\"BTC-USD\"} 65000\n"));
+        assert!(text.contains("ekiden_spread{market=\"0x1\",symbol=\"BTC-USD\"} 20\n"));
+        assert!(text.contains("ekiden_book_volume{market=\"0x1\",symbol=\"BTC-USD\"} 2\n"));
+        assert!(!text.contains("ekiden_last_trade_price{"));
+        assert!(text.contains("ekiden_ws_up 1\n"));
+        assert!(!text.contains("ekiden_equity"));
+    }
+
+    #[test]
+    fn test_closed_position_is_removed() {
+        let position: PositionResponse = serde_json::from_value(serde_json::json!({
+            "market_addr": "0x1",
+            "user_addr": "0x2",
+            "side": "SHORT",
+            "size": 2500,
+            "entry_price": 6_400_000,
+            "mark_price": 6_500_000,
+            "unrealized_pnl": -25_000,
+            "margin": 0,
+            "leverage": 5,
+            "liquidation_price": 7_000_000,
+            "created_at": "",
+            "updated_at": ""
+        }))
+        .unwrap();
+
+        let mut state = State::default();
+        state.markets.insert("0x1".to_string(), market());
+        state.update_position(position.clone());
+        assert!(state
+            .render()
+            .contains("ekiden_position_size{market=\"0x1\",symbol=\"BTC-USD\"} -2.5\n"));
+
+        state.update_position(PositionResponse {
+            size: 0,
+            ..position
+        });
+        assert!(state.positions.is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+//! Synchronous wrapper around the async client.
+//!
+//! [`EkidenClient`] owns a small tokio runtime and blocks the calling thread
+//! on each REST call, for code that is not async:
+//!
+//! ```rust,no_run
+//! # fn run() -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::blocking::EkidenClient;
+//! use ekiden_rust_sdk::EkidenConfig;
+//!
+//! let client = EkidenClient::new(EkidenConfig::production()?)?;
+//! client.set_token("eyJ...");
+//! let portfolio = client.get_user_portfolio()?;
+//! let btc = client.get_market_by_symbol("BTC-USD")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Methods must not be called from inside an async runtime, where blocking
+//! would stall it; tokio panics if they are. WebSocket subscriptions are not
+//! mirrored; use the async client through [`EkidenClient::inner`] for those.
+//! Dropping the client is fine anywhere, including inside a runtime.
+
+use crate::client::{BulkOutcome, EkidenClient as AsyncClient};
+use crate::config::EkidenConfig;
+use crate::error::Result;
+use crate::types::*;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// Blocking Ekiden REST client
+#[derive(Debug, Clone)]
+pub struct EkidenClient {
+    inner: AsyncClient,
+    runtime: Arc<SharedRuntime>,
+}
+
+/// Runtime shared by a client and its clones
+///
+/// Dropping a runtime waits for its workers, which panics inside an async
+/// context, so the last clone shuts it down in the background instead.
+#[derive(Debug)]
+struct SharedRuntime(Option<Runtime>);
+
+impl Drop for SharedRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+macro_rules! blocking {
+    ($(
+        $(#[$meta:meta])*
+        fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;
+    )*) => {
+        $(
+            $(#[$meta])*
+            pub fn $name(&self $(, $arg: $ty)*) -> $ret {
+                self.block_on(self.inner.$name($($arg),*))
+            }
+        )*
+    };
+}
+
+impl EkidenClient {
+    /// Create a client with the given configuration
+    pub fn new(config: EkidenConfig) -> Result<Self> {
+        Self::from_async(AsyncClient::new(config)?)
+    }
+
+    /// Wrap an async client, e.g. one built with `EkidenClientBuilder`
+    pub fn from_async(inner: AsyncClient) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("ekiden-blocking")
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            inner,
+            runtime: Arc::new(SharedRuntime(Some(runtime))),
+        })
+    }
+
+    /// Get the wrapped async client
+    pub fn inner(&self) -> &AsyncClient {
+        &self.inner
+    }
+
+    /// Get a client whose requests use these timeout, retry and header overrides
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        Self {
+            inner: self.inner.with_options(options),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Drop cached market metadata so the next lookup hits the gateway
+    pub fn invalidate_market_cache(&self) {
+        self.inner.invalidate_market_cache()
+    }
+
+    /// Get the health of the primary and backup endpoints
+    pub fn endpoint_health(&self) -> Vec<crate::failover::EndpointHealth> {
+        self.inner.endpoint_health()
+    }
+
+    /// Get a clock corrected by the estimated skew to the gateway
+    pub fn server_clock(&self) -> Arc<dyn crate::clock::Clock> {
+        self.inner.server_clock()
+    }
+
+    /// Milliseconds the gateway's clock is ahead of the local one
+    pub fn clock_skew_millis(&self) -> i64 {
+        self.inner.clock_skew_millis()
+    }
+
+    /// Set user leverage for several markets concurrently
+    pub fn set_leverage_bulk<I, S>(&self, leverages: I) -> BulkOutcome<LeverageResponse>
+    where
+        I: IntoIterator<Item = (S, u64)>,
+        S: AsRef<str>,
+    {
+        self.block_on(self.inner.set_leverage_bulk(leverages))
+    }
+
+    /// Set the authentication token
+    pub fn set_token(&self, token: &str) {
+        self.block_on(self.inner.set_token(token))
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime
+            .0
+            .as_ref()
+            .expect("runtime is only taken on drop")
+            .block_on(future)
+    }
+
+    blocking! {
+        /// Set the private key for signing operations
+        fn set_private_key(&self, private_key: &str) -> Result<()>;
+        /// Get the authentication token
+        fn token(&self) -> Option<String>;
+        /// Get the public key
+        fn public_key(&self) -> Option<String>;
+        /// Check if the client is authenticated
+        fn is_authenticated(&self) -> bool;
+        /// Authorize with the API using the private key
+        fn authorize(&self) -> Result<AuthorizeResponse>;
+        /// Cancel in-flight requests and background jobs and close the WebSocket
+        fn shutdown(&self) -> Result<()>;
+
+        /// Get market information
+        fn get_markets(&self, params: ListMarketsParams) -> Result<Vec<MarketResponse>>;
+        /// Get a specific market by address
+        fn get_market_by_address(&self, market_addr: &str) -> Result<Option<MarketResponse>>;
+        /// Get a specific market by symbol
+        fn get_market_by_symbol(&self, symbol: &str) -> Result<Option<MarketResponse>>;
+        /// Get an orderbook snapshot for a market
+        fn get_orderbook(&self, market_addr: &str, depth: Option<u32>) -> Result<OrderbookResponse>;
+
+        /// Get orders for a market
+        fn get_orders(&self, params: ListOrdersParams) -> Result<Vec<OrderResponse>>;
+        /// Get orders for a specific market and side
+        fn get_orders_by_side(
+            &self,
+            market_addr: &str,
+            side: OrderSide,
+            pagination: Option<Pagination>
+        ) -> Result<Vec<OrderResponse>>;
+        /// Get fills (trades) for a market
+        fn get_fills(&self, params: ListFillsParams) -> Result<Vec<FillResponse>>;
+        /// Get recent fills for a market
+        fn get_recent_fills(&self, market_addr: &str, limit: Option<u32>) -> Result<Vec<FillResponse>>;
+
+        /// Get user vaults
+        fn get_user_vaults(&self, params: ListVaultsParams) -> Result<Vec<VaultResponse>>;
+        /// Get all user vaults
+        fn get_all_user_vaults(&self) -> Result<Vec<VaultResponse>>;
+        /// Get user positions
+        fn get_user_positions(&self, params: ListPositionsParams) -> Result<Vec<PositionResponse>>;
+        /// Get user positions for a specific market
+        fn get_user_positions_by_market(&self, market_addr: &str) -> Result<Vec<PositionResponse>>;
+        /// Get all user positions
+        fn get_all_user_positions(&self) -> Result<Vec<PositionResponse>>;
+        /// Get user leverage for a market
+        fn get_user_leverage(&self, market_addr: &str) -> Result<LeverageResponse>;
+        /// Set user leverage for a market
+        fn set_user_leverage(&self, market_addr: &str, leverage: u64) -> Result<LeverageResponse>;
+        /// Get user leverage for every listed market
+        fn get_all_user_leverage(&self) -> Result<BulkOutcome<LeverageResponse>>;
+        /// Get user portfolio
+        fn get_user_portfolio(&self) -> Result<PortfolioResponse>;
+        /// Get account tier, fees, referral status and API limits
+        fn get_account_info(&self) -> Result<AccountInfoResponse>;
+
+        /// Send an intent (execute actions)
+        fn send_intent(&self, params: SendIntentParams) -> Result<SendIntentResponse>;
+        /// Sign a batch of actions with the configured key pair and send them as one intent
+        fn send_actions(&self, actions: Vec<ActionPayload>) -> Result<SendIntentResponse>;
+        /// Get the status and outputs of an intent by its seq
+        fn get_intent(&self, seq: u64) -> Result<SendIntentResponse>;
+        /// Wait until an intent is final and return its outputs
+        fn wait_intent(&self, seq: u64, timeout: std::time::Duration) -> Result<SendIntentResponse>;
+        /// Ask the gateway to check an intent without executing it
+        fn simulate_intent(&self, params: SendIntentParams) -> Result<IntentSimulation>;
+        /// Sign a batch of actions and simulate them as one intent
+        fn simulate_actions(&self, actions: Vec<ActionPayload>) -> Result<IntentSimulation>;
+
+        /// Get deposits
+        fn get_deposits(&self, params: ListDepositsParams) -> Result<Vec<DepositResponse>>;
+        /// Get user deposits
+        fn get_user_deposits(&self, user_addr: &str) -> Result<Vec<DepositResponse>>;
+        /// Get withdrawals
+        fn get_withdrawals(&self, params: ListWithdrawsParams) -> Result<Vec<WithdrawResponse>>;
+        /// Get user withdrawals
+        fn get_user_withdrawals(&self, user_addr: &str) -> Result<Vec<WithdrawResponse>>;
+
+        /// Get candlestick data
+        fn get_candles(&self, params: ListCandlesParams) -> Result<Vec<CandleResponse>>;
+        /// Get recent candles for a market
+        fn get_recent_candles(
+            &self,
+            market_addr: &str,
+            interval: &str,
+            limit: Option<u32>
+        ) -> Result<Vec<CandleResponse>>;
+        /// Get funding rates
+        fn get_funding_rates(&self, params: ListFundingRatesParams) -> Result<Vec<FundingRateResponse>>;
+        /// Get current funding rate for a market
+        fn get_current_funding_rate(&self, market_addr: &str) -> Result<Option<FundingRateResponse>>;
+        /// Get the gateway status and the health of its components
+        fn get_status(&self) -> Result<StatusResponse>;
+        /// Check that the gateway answers and return the round trip time
+        fn ping(&self) -> Result<std::time::Duration>;
+        /// Check gateway and component health
+        fn health(&self) -> Result<HealthReport>;
+        /// Get the gateway's time and update the estimated clock skew
+        fn get_server_time(&self) -> Result<ServerTime>;
+        /// Get gateway announcements
+        fn get_announcements(&self, params: ListAnnouncementsParams) -> Result<Vec<AnnouncementResponse>>;
+
+        /// Send a request to an arbitrary API path and return the raw JSON body
+        fn request_json(&self, path: &str, config: RequestConfig) -> Result<serde_json::Value>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use crate::EkidenClientBuilder;
+
+    #[test]
+    fn test_blocking_calls() {
+        let transport = Arc::new(MockTransport::new());
+        transport
+            .respond_json("market_info", &serde_json::json!([]))
+            .unwrap();
+        let inner = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(
+                EkidenClientBuilder::new()
+                    .local()
+                    .unwrap()
+                    .transport(transport.clone())
+                    .build(),
+            )
+            .unwrap();
+        let client = EkidenClient::from_async(inner).unwrap();
+
+        assert!(client
+            .get_markets(ListMarketsParams::default())
+            .unwrap()
+            .is_empty());
+        assert!(client.get_market_by_symbol("BTC-USD").unwrap().is_none());
+        assert!(!client.is_authenticated());
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_drop_inside_async_context() {
+        let client = EkidenClient::new(EkidenConfig::local().unwrap()).unwrap();
+        drop(client.clone());
+        drop(client);
+    }
+}
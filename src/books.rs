@@ -0,0 +1,335 @@
+//! Local orderbooks for many markets, kept current from the WebSocket.
+//!
+//! [`OrderbookManager`] subscribes to the orderbook channel of each tracked
+//! market, applies snapshots and updates to an [`Orderbook`] per market and
+//! publishes a [`BookChange`] after every change:
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::books::OrderbookManager;
+//!
+//! let market = "0x1111111111111111111111111111111111111111";
+//! client.connect_websocket().await?;
+//! let books = OrderbookManager::new();
+//! books.track(&client, market).await?;
+//!
+//! let mut changes = books.subscribe();
+//! while let Ok(change) = changes.recv().await {
+//!     let (bids, asks) = books.depth(&change.market_addr, 5).unwrap_or_default();
+//!     println!("{} mid {:?} top bids {:?} asks {:?}", change.market_addr, books.mid_price(market), bids, asks);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Events can also be fed in directly with [`OrderbookManager::apply`], e.g.
+//! from a recorded session.
+
+use crate::client::EkidenClient;
+use crate::error::Result;
+use crate::orderbook::Orderbook;
+use crate::types::{OrderbookLevel, WsEvent};
+use crate::utils::format;
+use crate::ws::channels;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// What changed in a book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookChangeKind {
+    /// The book was replaced by a snapshot
+    Snapshot,
+    /// Levels were updated
+    Update,
+    /// Events were lost; updates are ignored until the next snapshot
+    Resync,
+}
+
+/// Notification that a market's book changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookChange {
+    pub market_addr: String,
+    pub kind: BookChangeKind,
+    /// Timestamp of the book after the change
+    pub timestamp: u64,
+    pub best_bid: Option<OrderbookLevel>,
+    pub best_ask: Option<OrderbookLevel>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    books: RwLock<HashMap<String, Orderbook>>,
+    changes: broadcast::Sender<BookChange>,
+    tasks: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        for (_, task) in self.tasks.get_mut().unwrap().drain() {
+            task.abort();
+        }
+    }
+}
+
+/// Sorted L2 books for several markets; clones share the books
+#[derive(Debug, Clone)]
+pub struct OrderbookManager {
+    inner: Arc<Inner>,
+}
+
+impl Default for OrderbookManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn key(market_addr: &str) -> String {
+    format::normalize_address(market_addr).unwrap_or_else(|_| market_addr.to_string())
+}
+
+impl OrderbookManager {
+    /// Create a manager with no books
+    pub fn new() -> Self {
+        let (changes, _) = broadcast::channel(1000);
+        Self {
+            inner: Arc::new(Inner {
+                books: RwLock::new(HashMap::new()),
+                changes,
+                tasks: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Subscribe to a market's orderbook and keep its book current
+    ///
+    /// Tracking stops with [`untrack`](Self::untrack), when the last clone of
+    /// the manager is dropped, or when the client shuts down. If the manager
+    /// falls behind the channel, the book is marked as awaiting a snapshot, a
+    /// [`BookChangeKind::Resync`] is published and a new snapshot requested.
+    pub async fn track(&self, client: &EkidenClient, market_addr: &str) -> Result<()> {
+        let mut events = client.subscribe_orderbook(market_addr).await?;
+        let manager = Arc::downgrade(&self.inner);
+        let channel = channels::orderbook(market_addr);
+        let resync_client = client.clone();
+        let task = client.spawn_job(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        let Some(inner) = manager.upgrade() else {
+                            return;
+                        };
+                        OrderbookManager { inner }.apply(&event);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!(
+                            "Orderbook manager lagged on {}, missed {}; resyncing",
+                            channel, missed
+                        );
+                        let Some(inner) = manager.upgrade() else {
+                            return;
+                        };
+                        // Lost updates leave the book wrong until a snapshot;
+                        // the lag carries no sequence numbers to report
+                        OrderbookManager { inner }.apply(&WsEvent::Resync {
+                            channel: channel.clone(),
+                            expected: 0,
+                            received: 0,
+                        });
+                        if let Err(e) = resync_client.resubscribe(&channel).await {
+                            warn!("Failed to request a snapshot for {}: {}", channel, e);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+        if let Some(previous) = self
+            .inner
+            .tasks
+            .lock()
+            .unwrap()
+            .insert(key(market_addr), task)
+        {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    /// Stop tracking a market and forget its book
+    pub async fn untrack(&self, client: &EkidenClient, market_addr: &str) -> Result<()> {
+        let key = key(market_addr);
+        if let Some(task) = self.inner.tasks.lock().unwrap().remove(&key) {
+            task.abort();
+        }
+        self.inner.books.write().unwrap().remove(&key);
+        client.unsubscribe(&channels::orderbook(market_addr)).await
+    }
+
+    /// Apply an orderbook event, returning `true` if a book changed
+    ///
+    /// Other events are ignored.
+    pub fn apply(&self, event: &WsEvent) -> bool {
+        let (market_addr, kind) = match event {
+            WsEvent::OrderbookSnapshot { market_addr, .. } => {
+                (market_addr.as_str(), BookChangeKind::Snapshot)
+            }
+            WsEvent::OrderbookUpdate { market_addr, .. } => {
+                (market_addr.as_str(), BookChangeKind::Update)
+            }
+            WsEvent::Resync { channel, .. } if channel.starts_with("orderbook/") => {
+                match channels::market_addr(channel) {
+                    Some(market_addr) => (market_addr, BookChangeKind::Resync),
+                    None => return false,
+                }
+            }
+            _ => return false,
+        };
+        let change = {
+            let mut books = self.inner.books.write().unwrap();
+            let book = match kind {
+                BookChangeKind::Resync => match books.get_mut(&key(market_addr)) {
+                    Some(book) => book,
+                    None => return false,
+                },
+                _ => books
+                    .entry(key(market_addr))
+                    .or_insert_with(|| Orderbook::new(market_addr)),
+            };
+            let changed = book.apply_event(event);
+            if !changed && kind != BookChangeKind::Resync {
+                return false;
+            }
+            BookChange {
+                market_addr: book.market_addr().to_string(),
+                kind,
+                timestamp: book.timestamp(),
+                best_bid: book.best_bid(),
+                best_ask: book.best_ask(),
+            }
+        };
+        let _ = self.inner.changes.send(change);
+        true
+    }
+
+    /// Subscribe to book changes
+    pub fn subscribe(&self) -> broadcast::Receiver<BookChange> {
+        self.inner.changes.subscribe()
+    }
+
+    /// Get the markets with a book, sorted
+    pub fn markets(&self) -> Vec<String> {
+        let mut markets: Vec<String> = self
+            .inner
+            .books
+            .read()
+            .unwrap()
+            .values()
+            .map(|book| book.market_addr().to_string())
+            .collect();
+        markets.sort_unstable();
+        markets
+    }
+
+    /// Get a copy of a market's book
+    pub fn book(&self, market_addr: &str) -> Option<Orderbook> {
+        self.with_book(market_addr, Orderbook::clone)
+    }
+
+    /// Get the best bid of a market
+    pub fn best_bid(&self, market_addr: &str) -> Option<OrderbookLevel> {
+        self.with_book(market_addr, Orderbook::best_bid).flatten()
+    }
+
+    /// Get the best ask of a market
+    pub fn best_ask(&self, market_addr: &str) -> Option<OrderbookLevel> {
+        self.with_book(market_addr, Orderbook::best_ask).flatten()
+    }
+
+    /// Get the mid price of a market
+    pub fn mid_price(&self, market_addr: &str) -> Option<f64> {
+        self.with_book(market_addr, Orderbook::mid_price).flatten()
+    }
+
+    /// Get the top `levels` levels of each side of a market as `(bids, asks)`
+    pub fn depth(
+        &self,
+        market_addr: &str,
+        levels: usize,
+    ) -> Option<(Vec<OrderbookLevel>, Vec<OrderbookLevel>)> {
+        self.with_book(market_addr, |book| book.depth(levels))
+    }
+
+    fn with_book<T>(&self, market_addr: &str, f: impl FnOnce(&Orderbook) -> T) -> Option<T> {
+        self.inner
+            .books
+            .read()
+            .unwrap()
+            .get(&key(market_addr))
+            .map(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKET: &str = "0x1111111111111111111111111111111111111111";
+
+    fn level(price: u64, size: u64) -> OrderbookLevel {
+        OrderbookLevel { price, size }
+    }
+
+    #[test]
+    fn test_maintains_books_and_notifies() {
+        let books = OrderbookManager::new();
+        let mut changes = books.subscribe();
+        assert!(books.best_bid(MARKET).is_none());
+
+        assert!(books.apply(&WsEvent::OrderbookSnapshot {
+            market_addr: MARKET.to_string(),
+            bids: vec![level(99, 1), level(100, 2)],
+            asks: vec![level(101, 3), level(103, 1)],
+            timestamp: 1,
+        }));
+        assert!(books.apply(&WsEvent::OrderbookUpdate {
+            market_addr: MARKET.to_string(),
+            bids: vec![level(100, 0)],
+            asks: vec![level(102, 4)],
+            timestamp: 2,
+        }));
+        assert!(!books.apply(&WsEvent::Trade {
+            market_addr: MARKET.to_string(),
+            price: 100,
+            size: 1,
+            side: "buy".to_string(),
+            timestamp: 3,
+        }));
+
+        assert_eq!(books.markets(), vec![MARKET.to_string()]);
+        assert_eq!(books.best_bid(MARKET), Some(level(99, 1)));
+        assert_eq!(books.best_ask(MARKET), Some(level(101, 3)));
+        assert_eq!(books.mid_price(MARKET), Some(100.0));
+        assert_eq!(
+            books.depth(MARKET, 2),
+            Some((vec![level(99, 1)], vec![level(101, 3), level(102, 4)]))
+        );
+
+        assert_eq!(changes.try_recv().unwrap().kind, BookChangeKind::Snapshot);
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.kind, BookChangeKind::Update);
+        assert_eq!(change.timestamp, 2);
+        assert_eq!(change.best_bid, Some(level(99, 1)));
+        assert!(changes.try_recv().is_err());
+
+        assert!(books.apply(&WsEvent::Resync {
+            channel: channels::orderbook(MARKET),
+            expected: 3,
+            received: 5,
+        }));
+        assert_eq!(changes.try_recv().unwrap().kind, BookChangeKind::Resync);
+        assert!(books.book(MARKET).unwrap().is_awaiting_snapshot());
+    }
+}
@@ -0,0 +1,409 @@
+//! Entry orders protected by a linked take-profit and stop-loss.
+//!
+//! A [`BracketOrder`] places its entry, and once the [`OrderTracker`] reports
+//! it closed, a reduce-only take-profit limit order and a reduce-only
+//! stop-market trigger on the opposite side, both sized to the quantity the
+//! entry filled. An entry cancelled after filling in part is protected too.
+//! The two exits are one-cancels-the-other: when either fills or is
+//! cancelled, the sibling is cancelled.
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::bracket::BracketOrder;
+//! use ekiden_rust_sdk::tracker::{OrderTracker, OrderTrackerConfig};
+//! use ekiden_rust_sdk::OrderSide;
+//! use std::sync::Arc;
+//!
+//! let market = "0x1111111111111111111111111111111111111111";
+//! let tracker = Arc::new(OrderTracker::start(client.clone(), OrderTrackerConfig::default()).await?);
+//! let bracket = BracketOrder::limit(market, OrderSide::Buy, 100_000, 63_000_000_000)
+//!     .with_take_profit(66_000_000_000)
+//!     .with_stop_loss(61_000_000_000)
+//!     .submit(&client, tracker)
+//!     .await?;
+//! println!("Bracket closed: {:?}", bracket.wait().await);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The stop-loss is a native trigger order, so the gateway must support them.
+
+use crate::client::EkidenClient;
+use crate::error::{EkidenError, Result};
+use crate::tracker::{self, OrderTracker};
+use crate::types::{OrderOptions, OrderResponse, OrderSide, TriggerOrder, TriggerType};
+use crate::utils::format;
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// An entry order with a take-profit and a stop-loss
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BracketOrder {
+    pub market_addr: String,
+    pub side: OrderSide,
+    pub size: u64,
+    /// Limit price of the entry, or `None` for a market entry
+    pub entry_price: Option<u64>,
+    /// Price of the take-profit limit order
+    pub take_profit: u64,
+    /// Trigger price of the stop-loss
+    pub stop_loss: u64,
+    /// Price the stop-loss is compared against
+    pub trigger_type: TriggerType,
+    /// Options of the entry order
+    pub options: OrderOptions,
+}
+
+impl BracketOrder {
+    /// Create a bracket entering with a limit order
+    pub fn limit(market_addr: &str, side: OrderSide, size: u64, price: u64) -> Self {
+        Self::new(market_addr, side, size, Some(price))
+    }
+
+    /// Create a bracket entering with a market order
+    pub fn market(market_addr: &str, side: OrderSide, size: u64) -> Self {
+        Self::new(market_addr, side, size, None)
+    }
+
+    fn new(market_addr: &str, side: OrderSide, size: u64, entry_price: Option<u64>) -> Self {
+        Self {
+            market_addr: market_addr.to_string(),
+            side,
+            size,
+            entry_price,
+            take_profit: 0,
+            stop_loss: 0,
+            trigger_type: TriggerType::Mark,
+            options: OrderOptions::new(),
+        }
+    }
+
+    /// Set the price of the take-profit limit order
+    pub fn with_take_profit(mut self, price: u64) -> Self {
+        self.take_profit = price;
+        self
+    }
+
+    /// Set the trigger price of the stop-loss
+    pub fn with_stop_loss(mut self, trigger_price: u64) -> Self {
+        self.stop_loss = trigger_price;
+        self
+    }
+
+    /// Set the price the stop-loss is compared against
+    pub fn with_trigger_type(mut self, trigger_type: TriggerType) -> Self {
+        self.trigger_type = trigger_type;
+        self
+    }
+
+    /// Set the options of the entry order
+    pub fn with_options(mut self, options: OrderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Check that both exits are set and on the right sides of the entry
+    pub fn validate(&self) -> Result<()> {
+        if self.size == 0 || self.take_profit == 0 || self.stop_loss == 0 {
+            return Err(EkidenError::validation(
+                "Bracket size, take-profit and stop-loss must be greater than zero",
+            ));
+        }
+        let entry = self.entry_price;
+        let ordered = match self.side {
+            OrderSide::Buy => {
+                self.stop_loss < self.take_profit
+                    && entry.map_or(true, |p| self.stop_loss < p && p < self.take_profit)
+            }
+            OrderSide::Sell => {
+                self.take_profit < self.stop_loss
+                    && entry.map_or(true, |p| self.take_profit < p && p < self.stop_loss)
+            }
+        };
+        if !ordered {
+            return Err(EkidenError::validation(
+                "Take-profit and stop-loss must lie on opposite sides of the entry",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Place the entry and manage the exits in the background
+    ///
+    /// The bracket is managed until it closes, the returned handle is
+    /// cancelled, or the client shuts down.
+    pub async fn submit(
+        self,
+        client: &EkidenClient,
+        tracker: Arc<OrderTracker>,
+    ) -> Result<BracketHandle> {
+        self.validate()?;
+        let order = BracketOrder {
+            market_addr: format::normalize_address(&self.market_addr)?,
+            ..self
+        };
+        // Lets reconciliation find the legs if their updates are lost
+        tracker.track_market(&order.market_addr)?;
+        let entry = match order.entry_price {
+            Some(price) => {
+                client
+                    .place_limit_order(
+                        &order.market_addr,
+                        order.side,
+                        price,
+                        order.size,
+                        order.options.clone(),
+                    )
+                    .await?
+            }
+            None => {
+                client
+                    .place_market_order(
+                        &order.market_addr,
+                        order.side,
+                        order.size,
+                        order.options.clone(),
+                    )
+                    .await?
+            }
+        };
+
+        let legs = BracketLegs {
+            entry: entry.sid,
+            take_profit: None,
+            stop_loss: None,
+        };
+        let (status, _) = watch::channel((BracketStatus::Entering, legs));
+        let status = Arc::new(status);
+        let runner = Runner {
+            client: client.clone(),
+            tracker,
+            order: order.clone(),
+            status: status.clone(),
+        };
+        Ok(BracketHandle {
+            client: client.clone(),
+            order,
+            status,
+            task: client.spawn_job(runner.run()),
+        })
+    }
+}
+
+/// Where a bracket is in its lifecycle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BracketStatus {
+    /// Waiting for the entry to close
+    Entering,
+    /// Entry filled, take-profit and stop-loss in place
+    Protected,
+    /// The take-profit filled and the stop-loss was cancelled
+    TookProfit,
+    /// The stop-loss filled and the take-profit was cancelled
+    StoppedOut,
+    /// The entry closed without filling at all, an exit closed without
+    /// filling, or the bracket was cancelled
+    Cancelled,
+    /// An exit could not be placed, or a leg closed with an unknown outcome;
+    /// legs already placed are left as they are
+    Failed(String),
+}
+
+impl BracketStatus {
+    /// Whether the bracket is no longer managed
+    pub fn is_closed(&self) -> bool {
+        !matches!(self, BracketStatus::Entering | BracketStatus::Protected)
+    }
+}
+
+/// Sids of a bracket's orders
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BracketLegs {
+    pub entry: String,
+    pub take_profit: Option<String>,
+    pub stop_loss: Option<String>,
+}
+
+type StatusSender = Arc<watch::Sender<(BracketStatus, BracketLegs)>>;
+
+/// A submitted bracket, managed in the background
+///
+/// Dropping the handle stops the management but leaves the orders in place.
+#[derive(Debug)]
+pub struct BracketHandle {
+    client: EkidenClient,
+    order: BracketOrder,
+    status: StatusSender,
+    task: JoinHandle<()>,
+}
+
+impl BracketHandle {
+    /// Get the bracket's current status
+    pub fn status(&self) -> BracketStatus {
+        self.status.borrow().0.clone()
+    }
+
+    /// Get the sids of the orders placed so far
+    pub fn legs(&self) -> BracketLegs {
+        self.status.borrow().1.clone()
+    }
+
+    /// Wait until the bracket closes and return how it closed
+    pub async fn wait(&self) -> BracketStatus {
+        let mut status = self.status.subscribe();
+        let closed = status.wait_for(|(status, _)| status.is_closed()).await;
+        match closed {
+            Ok(closed) => closed.0.clone(),
+            Err(_) => self.status(),
+        }
+    }
+
+    /// Stop managing the bracket and cancel its open orders
+    pub async fn cancel(&self) -> Result<()> {
+        self.task.abort();
+        let legs = self.legs();
+        let open = [Some(legs.entry), legs.take_profit, legs.stop_loss];
+        let mut result = Ok(());
+        for sid in open.into_iter().flatten() {
+            if let Err(e) = self
+                .client
+                .cancel_order(&self.order.market_addr, &sid)
+                .await
+            {
+                // Legs that already closed cannot be cancelled
+                if !matches!(e, EkidenError::OrderRejected { .. }) {
+                    result = Err(e);
+                }
+            }
+        }
+        self.status
+            .send_modify(|(status, _)| *status = BracketStatus::Cancelled);
+        result
+    }
+}
+
+impl Drop for BracketHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Runner {
+    client: EkidenClient,
+    tracker: Arc<OrderTracker>,
+    order: BracketOrder,
+    status: StatusSender,
+}
+
+impl Runner {
+    async fn run(self) {
+        let status = self.manage().await;
+        self.status.send_modify(|(current, _)| *current = status);
+    }
+
+    async fn manage(&self) -> BracketStatus {
+        let entry = self.status.borrow().1.entry.clone();
+        let entry = match self.tracker.wait_closed(&entry).await {
+            Ok(entry) => entry,
+            Err(e) => return BracketStatus::Failed(e.to_string()),
+        };
+        if tracker::is_missing(&entry) {
+            return BracketStatus::Failed(format!("entry {} closed unobserved", entry.sid));
+        }
+        let order = &self.order;
+        let filled = filled_size(order.size, &entry);
+        if filled == 0 {
+            return BracketStatus::Cancelled;
+        }
+
+        let exit_side = match order.side {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        };
+        let take_profit = match self
+            .client
+            .place_limit_order(
+                &order.market_addr,
+                exit_side,
+                order.take_profit,
+                filled,
+                OrderOptions::new().with_reduce_only(true),
+            )
+            .await
+        {
+            Ok(placed) => placed.sid,
+            Err(e) => return BracketStatus::Failed(format!("take-profit: {}", e)),
+        };
+        self.status
+            .send_modify(|(_, legs)| legs.take_profit = Some(take_profit.clone()));
+
+        let stop =
+            TriggerOrder::stop_market(&order.market_addr, exit_side, filled, order.stop_loss)
+                .with_trigger_type(order.trigger_type)
+                .with_reduce_only(true);
+        let stop_loss = match self.client.place_trigger_order(stop).await {
+            Ok(placed) => placed.sid,
+            Err(e) => return BracketStatus::Failed(format!("stop-loss: {}", e)),
+        };
+        self.status.send_modify(|(status, legs)| {
+            legs.stop_loss = Some(stop_loss.clone());
+            *status = BracketStatus::Protected;
+        });
+
+        let (closed, sibling, filled_status) = tokio::select! {
+            closed = self.tracker.wait_closed(&take_profit) => {
+                (closed, &stop_loss, BracketStatus::TookProfit)
+            }
+            closed = self.tracker.wait_closed(&stop_loss) => {
+                (closed, &take_profit, BracketStatus::StoppedOut)
+            }
+        };
+        let status = match closed {
+            Ok(exit) if exit.status.eq_ignore_ascii_case("filled") => filled_status,
+            Ok(exit) if tracker::is_missing(&exit) => {
+                return BracketStatus::Failed(format!("exit {} closed unobserved", exit.sid))
+            }
+            Ok(_) => BracketStatus::Cancelled,
+            Err(e) => return BracketStatus::Failed(e.to_string()),
+        };
+        if let Err(e) = self.client.cancel_order(&order.market_addr, sibling).await {
+            warn!("Failed to cancel bracket order {}: {}", sibling, e);
+        }
+        status
+    }
+}
+
+/// Quantity an entry of `size` filled by the time it closed
+///
+/// The closed order's own `size` is the quantity left unfilled.
+fn filled_size(size: u64, entry: &OrderResponse) -> u64 {
+    if entry.status.eq_ignore_ascii_case("filled") {
+        size
+    } else {
+        size.saturating_sub(entry.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exits_must_straddle_entry() {
+        let market = "0x1111111111111111111111111111111111111111";
+        let long = BracketOrder::limit(market, OrderSide::Buy, 10, 100)
+            .with_take_profit(110)
+            .with_stop_loss(90);
+        assert!(long.validate().is_ok());
+        assert!(long.clone().with_stop_loss(105).validate().is_err());
+        assert!(long.clone().with_take_profit(0).validate().is_err());
+
+        let short = BracketOrder::market(market, OrderSide::Sell, 10)
+            .with_take_profit(90)
+            .with_stop_loss(110);
+        assert!(short.validate().is_ok());
+        assert!(short.with_take_profit(120).validate().is_err());
+    }
+}
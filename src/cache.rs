@@ -0,0 +1,122 @@
+//! Time-limited cache for market metadata.
+//!
+//! Markets change rarely but are looked up constantly to resolve symbols,
+//! addresses and decimals. With a market cache TTL on
+//! [`EkidenConfig`](crate::EkidenConfig), `get_markets` and the lookups built
+//! on it answer repeated queries from memory until the entry expires:
+//!
+//! ```rust,no_run
+//! # async fn run() -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::{EkidenClient, EkidenConfig};
+//! use std::time::Duration;
+//!
+//! let config = EkidenConfig::production()?.with_market_cache(Duration::from_secs(60));
+//! let client = EkidenClient::new(config)?;
+//! let btc = client.get_market_by_symbol("BTC-USD").await?; // HTTP request
+//! let btc = client.get_market_by_symbol("BTC-USD").await?; // cached
+//! client.invalidate_market_cache();
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::clock::Clock;
+use crate::types::MarketResponse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Market lists keyed by their query, each kept for a fixed time
+#[derive(Debug)]
+pub struct MarketCache {
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+    entries: Mutex<HashMap<String, (Instant, Vec<MarketResponse>)>>,
+}
+
+impl MarketCache {
+    /// Create an empty cache whose entries expire after `ttl`
+    pub fn new(ttl: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            ttl,
+            clock,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the markets cached for a query, unless expired
+    pub fn get(&self, query: &HashMap<String, String>) -> Option<Vec<MarketResponse>> {
+        let now = self.clock.instant();
+        let mut entries = self.entries.lock().unwrap();
+        let key = Self::key(query);
+        match entries.get(&key) {
+            Some((stored, markets)) if now.saturating_duration_since(*stored) < self.ttl => {
+                Some(markets.clone())
+            }
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Store the markets returned for a query
+    pub fn insert(&self, query: &HashMap<String, String>, markets: Vec<MarketResponse>) {
+        let now = self.clock.instant();
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(Self::key(query), (now, markets));
+    }
+
+    /// Drop every entry
+    pub fn invalidate(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Get the number of entries, including expired ones not yet evicted
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Check if the cache has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn key(query: &HashMap<String, String>) -> String {
+        let mut pairs: Vec<_> = query.iter().collect();
+        pairs.sort();
+        pairs
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn test_entries_expire_after_ttl() {
+        let clock = ManualClock::default();
+        let cache = MarketCache::new(Duration::from_secs(60), Arc::new(clock.clone()));
+        let query = HashMap::from([("symbol".to_string(), "BTC-USD".to_string())]);
+
+        assert!(cache.get(&query).is_none());
+        cache.insert(&query, Vec::new());
+        assert!(cache.get(&query).is_some());
+        assert!(cache.get(&HashMap::new()).is_none());
+
+        clock.advance(Duration::from_secs(60));
+        assert!(cache.get(&query).is_none());
+        assert!(cache.is_empty());
+
+        cache.insert(&query, Vec::new());
+        cache.invalidate();
+        assert!(cache.get(&query).is_none());
+    }
+}
@@ -0,0 +1,425 @@
+//! Fault injection for the HTTP and WebSocket transports.
+//!
+//! Enabled with the `chaos` feature. A [`Chaos`] wraps any
+//! [`HttpTransport`] or [`WsConnector`] and injects latency, dropped requests,
+//! dropped or reordered WebSocket frames and mid-stream disconnects, so a
+//! strategy can be exercised against an unreliable network before going live:
+//!
+//! ```rust,no_run
+//! # async fn run() -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::chaos::{Chaos, ChaosConfig};
+//! use ekiden_rust_sdk::transport::{ReqwestTransport, TungsteniteConnector};
+//! use ekiden_rust_sdk::{EkidenClientBuilder, EkidenConfig};
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! let chaos = Chaos::new(
+//!     ChaosConfig::new(42)
+//!         .with_latency(Duration::from_millis(20), Duration::from_millis(250))
+//!         .with_http_drop_rate(0.05)
+//!         .with_ws_reorder_rate(0.02)
+//!         .with_ws_disconnect_rate(0.001),
+//! );
+//! let transport = Arc::new(ReqwestTransport::new(&EkidenConfig::default())?);
+//! let client = EkidenClientBuilder::new()
+//!     .transport(chaos.wrap_transport(transport))
+//!     .ws_connector(chaos.wrap_connector(Arc::new(TungsteniteConnector)))
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! All faults are drawn from one RNG seeded by [`ChaosConfig::seed`], so the
+//! same seed and the same sequence of calls reproduce the same failures.
+
+use crate::clock::{self, Clock};
+use crate::error::{EkidenError, Result};
+use crate::transport::{
+    HttpRequest, HttpResponse, HttpTransport, WsConnector, WsFrameSink, WsFrameStream,
+};
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::debug;
+use url::Url;
+
+/// Fault probabilities and latency bounds
+#[derive(Debug, Clone)]
+pub struct ChaosConfig {
+    /// Seed for the fault RNG
+    pub seed: u64,
+    /// Minimum latency added to each request, connect and received frame
+    pub min_latency: Duration,
+    /// Maximum latency added to each request, connect and received frame
+    pub max_latency: Duration,
+    /// Probability an HTTP request fails with a network error
+    pub http_drop_rate: f64,
+    /// Probability a WebSocket connect attempt fails
+    pub ws_connect_failure_rate: f64,
+    /// Probability a received WebSocket frame is silently dropped
+    pub ws_drop_rate: f64,
+    /// Probability a received WebSocket frame is delivered after the next one
+    pub ws_reorder_rate: f64,
+    /// Probability the connection is cut before a received frame
+    pub ws_disconnect_rate: f64,
+}
+
+impl ChaosConfig {
+    /// Create a config injecting no faults
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            min_latency: Duration::ZERO,
+            max_latency: Duration::ZERO,
+            http_drop_rate: 0.0,
+            ws_connect_failure_rate: 0.0,
+            ws_drop_rate: 0.0,
+            ws_reorder_rate: 0.0,
+            ws_disconnect_rate: 0.0,
+        }
+    }
+
+    /// Add a uniformly distributed latency between `min` and `max`
+    pub fn with_latency(mut self, min: Duration, max: Duration) -> Self {
+        self.min_latency = min;
+        self.max_latency = max.max(min);
+        self
+    }
+
+    /// Set the probability an HTTP request fails
+    pub fn with_http_drop_rate(mut self, rate: f64) -> Self {
+        self.http_drop_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the probability a WebSocket connect attempt fails
+    pub fn with_ws_connect_failure_rate(mut self, rate: f64) -> Self {
+        self.ws_connect_failure_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the probability a received WebSocket frame is dropped
+    pub fn with_ws_drop_rate(mut self, rate: f64) -> Self {
+        self.ws_drop_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the probability a received WebSocket frame is reordered
+    pub fn with_ws_reorder_rate(mut self, rate: f64) -> Self {
+        self.ws_reorder_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the probability the WebSocket connection is cut per received frame
+    pub fn with_ws_disconnect_rate(mut self, rate: f64) -> Self {
+        self.ws_disconnect_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+}
+
+/// What happens to a received WebSocket frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameFault {
+    Deliver,
+    Drop,
+    Reorder,
+    Disconnect,
+}
+
+struct Inner {
+    config: ChaosConfig,
+    rng: Mutex<StdRng>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Inner {
+    fn roll(&self, rate: f64) -> bool {
+        rate > 0.0 && self.rng.lock().unwrap().gen_bool(rate)
+    }
+
+    fn latency(&self) -> Duration {
+        let (min, max) = (self.config.min_latency, self.config.max_latency);
+        if max <= min {
+            return min;
+        }
+        let extra = self
+            .rng
+            .lock()
+            .unwrap()
+            .gen_range(0..=(max - min).as_micros() as u64);
+        min + Duration::from_micros(extra)
+    }
+
+    async fn delay(&self) {
+        let latency = self.latency();
+        if !latency.is_zero() {
+            self.clock.sleep(latency).await;
+        }
+    }
+
+    fn frame_fault(&self) -> FrameFault {
+        if self.roll(self.config.ws_disconnect_rate) {
+            FrameFault::Disconnect
+        } else if self.roll(self.config.ws_drop_rate) {
+            FrameFault::Drop
+        } else if self.roll(self.config.ws_reorder_rate) {
+            FrameFault::Reorder
+        } else {
+            FrameFault::Deliver
+        }
+    }
+}
+
+/// Seeded fault injector for transports
+#[derive(Clone)]
+pub struct Chaos {
+    inner: Arc<Inner>,
+}
+
+impl fmt::Debug for Chaos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Chaos")
+            .field("config", &self.inner.config)
+            .finish()
+    }
+}
+
+impl Chaos {
+    /// Create an injector from a config
+    pub fn new(config: ChaosConfig) -> Self {
+        Self::with_clock(config, clock::system())
+    }
+
+    /// Create an injector that waits on a custom clock
+    pub fn with_clock(config: ChaosConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                rng: Mutex::new(StdRng::seed_from_u64(config.seed)),
+                config,
+                clock,
+            }),
+        }
+    }
+
+    /// Get the config
+    pub fn config(&self) -> &ChaosConfig {
+        &self.inner.config
+    }
+
+    /// Wrap an HTTP transport
+    pub fn wrap_transport(&self, transport: Arc<dyn HttpTransport>) -> Arc<dyn HttpTransport> {
+        Arc::new(ChaosTransport {
+            chaos: self.clone(),
+            inner: transport,
+        })
+    }
+
+    /// Wrap a WebSocket connector
+    pub fn wrap_connector(&self, connector: Arc<dyn WsConnector>) -> Arc<dyn WsConnector> {
+        Arc::new(ChaosConnector {
+            chaos: self.clone(),
+            inner: connector,
+        })
+    }
+
+    fn wrap_stream(&self, mut stream: WsFrameStream) -> WsFrameStream {
+        let inner = self.inner.clone();
+        let stream = async_stream::stream! {
+            let mut held: Option<Message> = None;
+            while let Some(item) = stream.next().await {
+                let message = match item {
+                    Ok(message @ (Message::Text(_) | Message::Binary(_))) => message,
+                    other => {
+                        yield other;
+                        continue;
+                    }
+                };
+
+                inner.delay().await;
+                match inner.frame_fault() {
+                    FrameFault::Disconnect => {
+                        debug!("Chaos: cutting WebSocket connection");
+                        yield Err(EkidenError::ConnectionClosed);
+                        return;
+                    }
+                    FrameFault::Drop => {
+                        debug!("Chaos: dropping WebSocket frame");
+                    }
+                    FrameFault::Reorder if held.is_none() => {
+                        debug!("Chaos: holding back WebSocket frame");
+                        held = Some(message);
+                    }
+                    _ => {
+                        yield Ok(message);
+                        if let Some(message) = held.take() {
+                            yield Ok(message);
+                        }
+                    }
+                }
+            }
+            if let Some(message) = held.take() {
+                yield Ok(message);
+            }
+        };
+        stream.boxed()
+    }
+}
+
+#[derive(Debug)]
+struct ChaosTransport {
+    chaos: Chaos,
+    inner: Arc<dyn HttpTransport>,
+}
+
+impl HttpTransport for ChaosTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            let inner = &self.chaos.inner;
+            inner.delay().await;
+            if inner.roll(inner.config.http_drop_rate) {
+                // Half of the drops lose the response after the gateway has
+                // acted on the request, which is the harder case to handle.
+                let after_send = inner.rng.lock().unwrap().gen_bool(0.5);
+                if after_send {
+                    let _ = self.inner.send(request).await;
+                    debug!("Chaos: dropping HTTP response");
+                    return Err(EkidenError::network("chaos: response lost"));
+                }
+                debug!("Chaos: dropping HTTP request");
+                return Err(EkidenError::network("chaos: request dropped"));
+            }
+            self.inner.send(request).await
+        })
+    }
+}
+
+#[derive(Debug)]
+struct ChaosConnector {
+    chaos: Chaos,
+    inner: Arc<dyn WsConnector>,
+}
+
+impl WsConnector for ChaosConnector {
+    fn connect<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, Result<(WsFrameSink, WsFrameStream)>> {
+        Box::pin(async move {
+            let inner = &self.chaos.inner;
+            inner.delay().await;
+            if inner.roll(inner.config.ws_connect_failure_rate) {
+                return Err(EkidenError::WebSocket(
+                    "Failed to connect: chaos: connection refused".to_string(),
+                ));
+            }
+            let (sink, stream) = self.inner.connect(url).await?;
+            Ok((sink, self.chaos.wrap_stream(stream)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::SinkExt;
+
+    #[derive(Debug)]
+    struct StubTransport;
+
+    impl HttpTransport for StubTransport {
+        fn send(&self, _request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+            Box::pin(async {
+                Ok(HttpResponse {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: b"{}".to_vec(),
+                })
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct StubConnector(usize);
+
+    impl WsConnector for StubConnector {
+        fn connect<'a>(
+            &'a self,
+            _url: &'a Url,
+        ) -> BoxFuture<'a, Result<(WsFrameSink, WsFrameStream)>> {
+            let frames: Vec<Result<Message>> = (0..self.0)
+                .map(|i| Ok(Message::Text(i.to_string().into())))
+                .collect();
+            Box::pin(async move {
+                let sink: WsFrameSink = Box::pin(
+                    futures_util::sink::drain()
+                        .sink_map_err(|e: std::convert::Infallible| -> EkidenError { match e {} }),
+                );
+                Ok((sink, futures_util::stream::iter(frames).boxed()))
+            })
+        }
+    }
+
+    async fn received(chaos: &Chaos, frames: usize) -> Vec<String> {
+        let url = Url::parse("ws://localhost/ws").unwrap();
+        let connector = chaos.wrap_connector(Arc::new(StubConnector(frames)));
+        let (_sink, stream) = connector.connect(&url).await.unwrap();
+        stream
+            .map(|frame| match frame {
+                Ok(Message::Text(text)) => text.as_str().to_string(),
+                Ok(_) => unreachable!(),
+                Err(_) => "closed".to_string(),
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_frame_faults_are_reproducible() {
+        let config = ChaosConfig::new(7)
+            .with_ws_drop_rate(0.2)
+            .with_ws_reorder_rate(0.2);
+        let first = received(&Chaos::new(config.clone()), 50).await;
+        let second = received(&Chaos::new(config), 50).await;
+        assert_eq!(first, second);
+        assert!(first.len() < 50);
+
+        let mut sorted: Vec<u32> = first.iter().map(|f| f.parse().unwrap()).collect();
+        assert_ne!(first, {
+            let mut in_order = sorted.clone();
+            in_order.sort_unstable();
+            in_order.iter().map(u32::to_string).collect::<Vec<_>>()
+        });
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), first.len());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_ends_stream() {
+        let chaos = Chaos::new(ChaosConfig::new(1).with_ws_disconnect_rate(1.0));
+        assert_eq!(received(&chaos, 3).await, vec!["closed"]);
+    }
+
+    #[tokio::test]
+    async fn test_http_drops() {
+        let request = HttpRequest {
+            method: reqwest::Method::GET,
+            url: Url::parse("http://localhost/api/v1/market/market_info").unwrap(),
+            headers: Vec::new(),
+            body: None,
+            timeout: None,
+        };
+        let transport = Chaos::new(ChaosConfig::new(3).with_http_drop_rate(1.0))
+            .wrap_transport(Arc::new(StubTransport));
+        assert!(matches!(
+            transport.send(request.clone()).await,
+            Err(EkidenError::Network(_))
+        ));
+
+        let transport = Chaos::new(ChaosConfig::new(3)).wrap_transport(Arc::new(StubTransport));
+        assert_eq!(transport.send(request).await.unwrap().status, 200);
+    }
+}
@@ -1,45 +1,416 @@
 use crate::auth::Auth;
-use crate::config::EkidenConfig;
-use crate::error::{EkidenError, Result};
+use crate::batch::Batch;
+use crate::cache::MarketCache;
+use crate::clock::{self, Clock, SkewedClock};
+use crate::cloid::ClientOrderIds;
+use crate::codec::{self, WireFormat};
+use crate::config::{EkidenConfig, ProxyConfig};
+use crate::error::{ApiErrorBody, ApiErrorCode, EkidenError, Result};
+use crate::failover::{self, EndpointHealth, Endpoints};
+use crate::interceptor::{Interceptors, RequestContext, RequestInterceptor};
+use crate::logging;
+use crate::metrics::{route_template, MetricsRecorder, RequestMetrics};
+use crate::pool::WsPool;
+use crate::ratelimit::{EndpointClass, RateLimiter, RateLimiterConfig};
+use crate::streams::{
+    FillStream, MultiStream, OrderbookStream, TradeStream, TypedStream, UserEvent, UserEventStream,
+};
+use crate::tls::TlsConfig;
+use crate::transport::{
+    default_ws_connector, HttpRequest, HttpResponse, HttpTransport, ReqwestTransport, WsConnector,
+};
 use crate::types::*;
-use crate::utils::format;
-use crate::ws::WebSocketClient;
-use reqwest::{Client, Response};
+use crate::utils::{format, Crypto, Redacted};
+use crate::ws::{
+    channels, Channel, EventStream, LatencyStats, SnapshotSource, SubscriptionInfo,
+    SubscriptionOptions, WebSocketClient,
+};
+use futures_util::stream::{self, Stream, StreamExt};
 use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
-use tracing::{debug, error, info};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn, Instrument, Span};
+use url::Url;
+
+/// What the client was doing when an error surfaced
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ErrorContext {
+    /// API path for REST calls, or the WebSocket operation such as `ws_subscribe`
+    pub operation: String,
+    /// HTTP method for REST calls
+    pub method: Option<reqwest::Method>,
+    /// WebSocket channel, when one was involved
+    pub channel: Option<String>,
+}
+
+impl ErrorContext {
+    fn rest(path: &str, method: reqwest::Method) -> Self {
+        Self {
+            operation: path.to_string(),
+            method: Some(method),
+            channel: None,
+        }
+    }
+
+    fn ws(operation: &str, channel: Option<&str>) -> Self {
+        Self {
+            operation: operation.to_string(),
+            method: None,
+            channel: channel.map(str::to_string),
+        }
+    }
+}
+
+/// Callback invoked with every error the client returns
+pub type ErrorHook = Arc<dyn Fn(&EkidenError, &ErrorContext) + Send + Sync>;
+
+#[derive(Clone, Default)]
+struct ErrorHooks(Vec<ErrorHook>);
+
+impl std::fmt::Debug for ErrorHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} hook(s)", self.0.len())
+    }
+}
+
+/// Maximum number of requests a bulk call keeps in flight
+const BULK_CONCURRENCY: usize = 8;
+
+/// How often [`EkidenClient::wait_intent`] polls a pending intent
+const INTENT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Outcome of a bulk call whose items succeed or fail independently
+#[derive(Debug)]
+pub struct BulkOutcome<T> {
+    /// Results of the items that succeeded, in request order
+    pub succeeded: Vec<T>,
+    /// Items that failed, keyed by market address or order sid
+    pub failed: Vec<(String, EkidenError)>,
+}
+
+impl<T> BulkOutcome<T> {
+    /// Whether every item succeeded
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Get the results, or the first failure if any item failed
+    pub fn into_result(self) -> Result<Vec<T>> {
+        match self.failed.into_iter().next() {
+            Some((_, error)) => Err(error),
+            None => Ok(self.succeeded),
+        }
+    }
+
+    fn collect(results: Vec<(String, Result<T>)>) -> Self {
+        let mut outcome = Self {
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+        };
+        for (key, result) in results {
+            match result {
+                Ok(value) => outcome.succeeded.push(value),
+                Err(error) => outcome.failed.push((key, error)),
+            }
+        }
+        outcome
+    }
+}
+
+/// Whether an action failed because the gateway does not support it
+///
+/// Only the gateway's `unsupported_action` code counts: free-text reasons such
+/// as an unsupported tick size, and bare 404s, are ordinary failures.
+pub(crate) fn action_unsupported(error: &EkidenError) -> bool {
+    error.api_code() == Some(ApiErrorCode::UnsupportedAction)
+}
+
+/// Map the first failed output of an intent to an error
+///
+/// Outputs with the `unsupported_action` code become an
+/// [`EkidenError::Api`] carrying that code, so [`action_unsupported`] can tell
+/// them from ordinary rejections.
+fn output_error(outputs: &[IntentOutput]) -> Option<EkidenError> {
+    let output = outputs.iter().find(|output| output.error().is_some())?;
+    let reason = output.error().unwrap_or_default().to_string();
+    Some(match output.error_code() {
+        Some(ApiErrorCode::UnsupportedAction) => EkidenError::Api {
+            status: 501,
+            message: reason.clone(),
+            body: Some(Box::new(ApiErrorBody {
+                code: Some(ApiErrorCode::UnsupportedAction),
+                message: Some(reason),
+                details: None,
+            })),
+        },
+        _ => EkidenError::OrderRejected { reason },
+    })
+}
+
+/// Yield the items of successive pages until one comes back short or empty
+fn paginate<'a, T, F, Fut>(
+    mut pagination: Pagination,
+    mut fetch: F,
+) -> impl Stream<Item = Result<T>> + 'a
+where
+    T: 'a,
+    F: FnMut(Pagination) -> Fut + 'a,
+    Fut: Future<Output = Result<Vec<T>>> + 'a,
+{
+    async_stream::try_stream! {
+        loop {
+            let page = fetch(pagination.clone()).await?;
+            let received = page.len();
+            for item in page {
+                yield item;
+            }
+            if !pagination.advance(received) {
+                break;
+            }
+        }
+    }
+}
+
+/// Market a request refers to, from its query or JSON body
+fn request_market_addr(config: &RequestConfig) -> Option<&str> {
+    config
+        .query
+        .as_ref()
+        .and_then(|query| query.get("market_addr"))
+        .map(String::as_str)
+        .or_else(|| config.body.as_ref()?.get("market_addr")?.as_str())
+}
 
 /// Main Ekiden client for interacting with the API and WebSocket
 #[derive(Debug, Clone)]
 pub struct EkidenClient {
     config: EkidenConfig,
-    http_client: Client,
+    transport: Arc<dyn HttpTransport>,
     auth: Arc<RwLock<Auth>>,
-    ws_client: Option<Arc<RwLock<WebSocketClient>>>,
+    ws_pool: Option<Arc<RwLock<WsPool>>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    market_cache: Option<Arc<MarketCache>>,
+    interceptors: Interceptors,
+    error_hooks: ErrorHooks,
+    options: RequestOptions,
+    shutdown: CancellationToken,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    endpoints: Arc<Endpoints>,
+    /// Clock for retry and polling delays, shared with the client's components
+    clock: Arc<dyn Clock>,
+    server_clock: Arc<SkewedClock>,
+    /// Set once the gateway rejects `amend_order`, so amends cancel and replace
+    amend_unsupported: Arc<AtomicBool>,
+    client_orders: ClientOrderIds,
+    /// Next intent nonce, fetched on first use; held while an intent is sent
+    intent_nonce: Arc<tokio::sync::Mutex<Option<u64>>>,
 }
 
 impl EkidenClient {
     /// Create a new Ekiden client with the given configuration
     pub fn new(config: EkidenConfig) -> Result<Self> {
-        let http_client = Client::builder()
-            .timeout(config.timeout)
-            .user_agent(&config.user_agent)
-            .build()?;
+        let transport = Arc::new(ReqwestTransport::new(&config)?);
+        let ws_connector = default_ws_connector(&config)?;
+        Ok(Self::with_transport(config, transport, ws_connector))
+    }
+
+    /// Create a client sending requests through custom transports
+    pub fn with_transport(
+        config: EkidenConfig,
+        transport: Arc<dyn HttpTransport>,
+        ws_connector: Arc<dyn WsConnector>,
+    ) -> Self {
+        Self::assemble(
+            config,
+            transport,
+            ws_connector,
+            CancellationToken::new(),
+            None,
+            clock::system(),
+        )
+    }
 
-        let ws_client = Some(Arc::new(RwLock::new(WebSocketClient::new(
-            config.websocket_url().clone(),
-        ))));
+    fn assemble(
+        config: EkidenConfig,
+        transport: Arc<dyn HttpTransport>,
+        ws_connector: Arc<dyn WsConnector>,
+        shutdown: CancellationToken,
+        metrics: Option<Arc<dyn MetricsRecorder>>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        let endpoints = Arc::new(Endpoints::new(&config, clock.clone()));
+        let shards = (0..config.ws_connections.max(1))
+            .map(|_| {
+                let mut shard = WebSocketClient::new(config.websocket_url().clone())
+                    .with_endpoints(endpoints.clone())
+                    .with_connector(ws_connector.clone())
+                    .with_control_timeout(config.timeout)
+                    .with_reconnect(config.reconnect)
+                    .with_heartbeat(config.heartbeat)
+                    .with_clock(clock.clone())
+                    .with_cancellation_token(shutdown.clone());
+                if let Some(metrics) = &metrics {
+                    shard = shard.with_metrics(metrics.clone());
+                }
+                shard
+            })
+            .collect();
+        let ws_pool = WsPool::new(shards)
+            .ok()
+            .map(|pool| Arc::new(RwLock::new(pool)));
+        let rate_limiter = config
+            .rate_limiter
+            .as_ref()
+            .map(|limits| Arc::new(RateLimiter::new(limits, clock.clone())));
+        let market_cache = config
+            .market_cache_ttl
+            .map(|ttl| Arc::new(MarketCache::new(ttl, clock.clone())));
 
-        Ok(Self {
+        Self {
             config,
-            http_client,
+            transport,
             auth: Arc::new(RwLock::new(Auth::new())),
-            ws_client,
+            ws_pool,
+            rate_limiter,
+            market_cache,
+            interceptors: Interceptors::default(),
+            error_hooks: ErrorHooks::default(),
+            options: RequestOptions::default(),
+            shutdown,
+            metrics,
+            endpoints,
+            server_clock: Arc::new(SkewedClock::new(clock.clone())),
+            clock,
+            amend_unsupported: Arc::new(AtomicBool::new(false)),
+            client_orders: ClientOrderIds::new(),
+            intent_nonce: Arc::new(tokio::sync::Mutex::new(None)),
+        }
+    }
+
+    /// Register a callback invoked with every error the client returns
+    ///
+    /// Hooks run synchronously on the calling task, so they should hand off
+    /// anything slow, e.g. by spawning. Clones made after registration share
+    /// the hook.
+    pub fn on_error<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&EkidenError, &ErrorContext) + Send + Sync + 'static,
+    {
+        self.error_hooks.0.push(Arc::new(hook));
+        self
+    }
+
+    /// Register an interceptor that sees every REST request and its outcome
+    ///
+    /// Clones made after registration share the interceptor.
+    pub fn with_interceptor<I>(mut self, interceptor: I) -> Self
+    where
+        I: RequestInterceptor + 'static,
+    {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Get a client whose REST requests use these timeout, retry and header overrides
+    ///
+    /// The copy shares authentication, the WebSocket connection and rate
+    /// limits with this client:
+    ///
+    /// ```rust,no_run
+    /// # async fn run(client: ekiden_rust_sdk::EkidenClient, params: ekiden_rust_sdk::types::ListCandlesParams) -> ekiden_rust_sdk::Result<()> {
+    /// use ekiden_rust_sdk::types::{RequestOptions, RetryPolicy};
+    /// use std::time::Duration;
+    ///
+    /// let backfill = RequestOptions::new()
+    ///     .with_timeout(Duration::from_secs(60))
+    ///     .with_retry(RetryPolicy::new(3, Duration::from_secs(1)));
+    /// let candles = client.with_options(backfill).get_candles(params).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        let mut client = self.clone();
+        client.options = options;
+        client
+    }
+
+    /// Get the token that is cancelled when the client shuts down
+    ///
+    /// Work tied to the client's lifetime can select on
+    /// [`CancellationToken::cancelled`] to stop with it.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Check if [`shutdown`](Self::shutdown) has been called on this client or a clone
+    pub fn is_shut_down(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    /// Cancel in-flight requests and background jobs and close the WebSocket
+    ///
+    /// Pending and later REST calls fail with [`EkidenError::Cancelled`],
+    /// the WebSocket reader stops and the connection is closed once writes
+    /// already under way have finished. Watchdogs, data feeds and submission
+    /// queues started from the client stop as well. The shutdown applies to
+    /// every clone of the client:
+    ///
+    /// ```rust,no_run
+    /// # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+    /// tokio::signal::ctrl_c().await?;
+    /// client.shutdown().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn shutdown(&self) -> Result<()> {
+        self.shutdown.cancel();
+        if let Some(ws_pool) = &self.ws_pool {
+            let result = ws_pool.write().await.shutdown().await;
+            self.observe(result, || ErrorContext::ws("ws_shutdown", None))?;
+        }
+        info!("Client shut down");
+        Ok(())
+    }
+
+    /// Spawn a background job that is dropped when the client shuts down
+    pub(crate) fn spawn_job<F>(&self, job: F) -> JoinHandle<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = shutdown.cancelled() => {}
+                _ = job => {}
+            }
         })
     }
 
+    /// Report an error found before a request was sent to the hooks and return it
+    fn invalid<T>(&self, error: EkidenError, path: &str, method: reqwest::Method) -> Result<T> {
+        self.observe(Err(error), || ErrorContext::rest(path, method))
+    }
+
+    /// Pass an error result through the registered hooks
+    fn observe<T>(&self, result: Result<T>, context: impl FnOnce() -> ErrorContext) -> Result<T> {
+        if let Err(error) = &result {
+            if !self.error_hooks.0.is_empty() {
+                let context = context();
+                for hook in &self.error_hooks.0 {
+                    hook(error, &context);
+                }
+            }
+        }
+        result
+    }
+
     /// Create a client with default configuration
     pub fn default_config() -> Result<Self> {
         Self::new(EkidenConfig::default())
@@ -88,13 +459,27 @@ impl EkidenClient {
         self.auth.read().await.is_authenticated()
     }
 
+    /// Get the account address derived from the configured key pair
+    pub async fn address(&self) -> Result<Option<String>> {
+        match self.public_key().await {
+            Some(public_key) => {
+                let address = Crypto::generate_address_from_public_key(&public_key)?;
+                format::normalize_address(&address).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
     // ===== Authentication =====
 
     /// Authenticate with the API using the configured private key
     pub async fn authorize(&self) -> Result<AuthorizeResponse> {
         let auth_params = {
             let auth = self.auth.read().await;
-            auth.generate_authorize_params()?
+            let params = auth.generate_authorize_params();
+            self.observe(params, || {
+                ErrorContext::rest("authorize", reqwest::Method::POST)
+            })?
         };
 
         let response: AuthorizeResponse = self
@@ -114,16 +499,44 @@ impl EkidenClient {
     // ===== Market Endpoints =====
 
     /// Get market information
+    ///
+    /// Served from the market cache while fresh, if one is configured.
     pub async fn get_markets(&self, params: ListMarketsParams) -> Result<Vec<MarketResponse>> {
-        let config = RequestConfig::get().with_query(params.to_query_params());
-        self.request("market_info", config).await
+        let query = params.to_query_params();
+        if let Some(markets) = self
+            .market_cache
+            .as_ref()
+            .and_then(|cache| cache.get(&query))
+        {
+            debug!("Serving markets from cache");
+            return Ok(markets);
+        }
+        let config = RequestConfig::get().with_query(query.clone());
+        let markets: Vec<MarketResponse> = self.request("market_info", config).await?;
+        if let Some(cache) = &self.market_cache {
+            cache.insert(&query, markets.clone());
+        }
+        Ok(markets)
+    }
+
+    /// Get the health of the primary and backup endpoints
+    pub fn endpoint_health(&self) -> Vec<EndpointHealth> {
+        self.endpoints.health()
+    }
+
+    /// Drop cached market metadata so the next lookup hits the gateway
+    pub fn invalidate_market_cache(&self) {
+        if let Some(cache) = &self.market_cache {
+            cache.invalidate();
+        }
     }
 
     /// Get a specific market by address
     pub async fn get_market_by_address(&self, market_addr: &str) -> Result<Option<MarketResponse>> {
-        format::validate_address(market_addr)?;
+        let market_addr = format::normalize_address(market_addr)
+            .or_else(|e| self.invalid(e, "market_info", reqwest::Method::GET))?;
         let params = ListMarketsParams {
-            market_addr: Some(market_addr.to_string()),
+            market_addr: Some(market_addr),
             symbol: None,
             pagination: Pagination::default(),
         };
@@ -142,11 +555,27 @@ impl EkidenClient {
         Ok(markets.into_iter().next())
     }
 
+    /// Get an orderbook snapshot for a market
+    pub async fn get_orderbook(
+        &self,
+        market_addr: &str,
+        depth: Option<u32>,
+    ) -> Result<OrderbookResponse> {
+        let params = GetOrderbookParams {
+            market_addr: format::normalize_address(market_addr)
+                .or_else(|e| self.invalid(e, "orderbook", reqwest::Method::GET))?,
+            depth,
+        };
+        let config = RequestConfig::get().with_query(params.to_query_params());
+        self.request("orderbook", config).await
+    }
+
     // ===== Order Endpoints =====
 
     /// Get orders for a market
-    pub async fn get_orders(&self, params: ListOrdersParams) -> Result<Vec<OrderResponse>> {
-        format::validate_address(&params.market_addr)?;
+    pub async fn get_orders(&self, mut params: ListOrdersParams) -> Result<Vec<OrderResponse>> {
+        params.market_addr = format::normalize_address(&params.market_addr)
+            .or_else(|e| self.invalid(e, "orders", reqwest::Method::GET))?;
         let config = RequestConfig::get().with_query(params.to_query_params());
         self.request("orders", config).await
     }
@@ -172,8 +601,9 @@ impl EkidenClient {
     // ===== Fill Endpoints =====
 
     /// Get fills (trades) for a market
-    pub async fn get_fills(&self, params: ListFillsParams) -> Result<Vec<FillResponse>> {
-        format::validate_address(&params.market_addr)?;
+    pub async fn get_fills(&self, mut params: ListFillsParams) -> Result<Vec<FillResponse>> {
+        params.market_addr = format::normalize_address(&params.market_addr)
+            .or_else(|e| self.invalid(e, "fills", reqwest::Method::GET))?;
         let config = RequestConfig::get().with_query(params.to_query_params());
         self.request("fills", config).await
     }
@@ -230,9 +660,10 @@ impl EkidenClient {
         &self,
         market_addr: &str,
     ) -> Result<Vec<PositionResponse>> {
-        format::validate_address(market_addr)?;
+        let market_addr = format::normalize_address(market_addr)
+            .or_else(|e| self.invalid(e, "user/positions", reqwest::Method::GET))?;
         let params = ListPositionsParams {
-            market_addr: Some(market_addr.to_string()),
+            market_addr: Some(market_addr),
             pagination: Pagination::default(),
         };
         self.get_user_positions(params).await
@@ -249,10 +680,9 @@ impl EkidenClient {
 
     /// Get user leverage for a market
     pub async fn get_user_leverage(&self, market_addr: &str) -> Result<LeverageResponse> {
-        format::validate_address(market_addr)?;
-        let params = GetUserLeverageParams {
-            market_addr: market_addr.to_string(),
-        };
+        let market_addr = format::normalize_address(market_addr)
+            .or_else(|e| self.invalid(e, "user/leverage", reqwest::Method::GET))?;
+        let params = GetUserLeverageParams { market_addr };
         let config = RequestConfig::get()
             .with_query(params.to_query_params())
             .with_auth();
@@ -265,27 +695,663 @@ impl EkidenClient {
         market_addr: &str,
         leverage: u64,
     ) -> Result<LeverageResponse> {
-        format::validate_address(market_addr)?;
+        let market_addr = format::normalize_address(market_addr)
+            .or_else(|e| self.invalid(e, "user/leverage", reqwest::Method::POST))?;
         let params = SetUserLeverageParams {
-            market_addr: market_addr.to_string(),
+            market_addr,
             leverage,
         };
         let config = RequestConfig::post(&params)?.with_auth();
         self.request("user/leverage", config).await
     }
 
+    /// Set user leverage for several markets concurrently
+    ///
+    /// Each market is updated independently; failures are reported per market
+    /// instead of aborting the rest of the batch.
+    pub async fn set_leverage_bulk<I, S>(&self, leverages: I) -> BulkOutcome<LeverageResponse>
+    where
+        I: IntoIterator<Item = (S, u64)>,
+        S: AsRef<str>,
+    {
+        let requests = leverages.into_iter().map(|(market_addr, leverage)| {
+            let market_addr = market_addr.as_ref().to_string();
+            async move {
+                let result = self.set_user_leverage(&market_addr, leverage).await;
+                (market_addr, result)
+            }
+        });
+        BulkOutcome::collect(
+            stream::iter(requests)
+                .buffered(BULK_CONCURRENCY)
+                .collect()
+                .await,
+        )
+    }
+
+    /// Get user leverage for every listed market
+    ///
+    /// Fails only if the market list cannot be fetched or lists a market
+    /// without an address; per-market failures are reported in the outcome.
+    pub async fn get_all_user_leverage(&self) -> Result<BulkOutcome<LeverageResponse>> {
+        let markets = self.listed_market_addrs().await?;
+        let requests = markets.into_iter().map(|market_addr| async move {
+            let result = self.get_user_leverage(&market_addr).await;
+            (market_addr, result)
+        });
+        Ok(BulkOutcome::collect(
+            stream::iter(requests)
+                .buffered(BULK_CONCURRENCY)
+                .collect()
+                .await,
+        ))
+    }
+
+    /// Get the address of every listed market
+    ///
+    /// A market listed without an address is a validation error naming it,
+    /// rather than a blank address that matches nothing.
+    async fn listed_market_addrs(&self) -> Result<Vec<String>> {
+        let markets = self.get_markets(ListMarketsParams::default()).await?;
+        markets
+            .into_iter()
+            .map(|market| {
+                if market.addr.is_empty() {
+                    let error = EkidenError::validation(format!(
+                        "Market {} was listed without an address",
+                        market.symbol
+                    ));
+                    return self.invalid(error, "market_info", reqwest::Method::GET);
+                }
+                Ok(market.addr)
+            })
+            .collect()
+    }
+
     /// Get user portfolio
     pub async fn get_user_portfolio(&self) -> Result<PortfolioResponse> {
         let config = RequestConfig::get().with_auth();
         self.request("user/portfolio", config).await
     }
 
+    /// Get account tier, fees, referral status and API limits
+    pub async fn get_account_info(&self) -> Result<AccountInfoResponse> {
+        let config = RequestConfig::get().with_auth();
+        self.request("user/account", config).await
+    }
+
     /// Send an intent (execute actions)
     pub async fn send_intent(&self, params: SendIntentParams) -> Result<SendIntentResponse> {
         let config = RequestConfig::post(&params)?.with_auth();
         self.request("user/intent", config).await
     }
 
+    /// Get the status and outputs of an intent by its seq
+    pub async fn get_intent(&self, seq: u64) -> Result<SendIntentResponse> {
+        let config = RequestConfig::get().with_auth();
+        let path = format!("user/intent/{}", seq);
+        self.request_at("user/intent/{seq}", &path, config).await
+    }
+
+    /// Wait until an intent is final and return its outputs
+    ///
+    /// The intent is polled until the gateway reports a final status. Fails
+    /// with [`EkidenError::Timeout`] if it is still pending after `timeout`,
+    /// and with [`EkidenError::OrderRejected`] if it was not executed.
+    ///
+    /// ```rust,no_run
+    /// # async fn run(client: ekiden_rust_sdk::EkidenClient, actions: Vec<ekiden_rust_sdk::ActionPayload>) -> ekiden_rust_sdk::Result<()> {
+    /// let sent = client.send_actions(actions).await?;
+    /// let done = client.wait_intent(sent.seq, std::time::Duration::from_secs(10)).await?;
+    /// let sids: Vec<_> = done.outputs.iter().filter_map(|output| output.sid()).collect();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn wait_intent(&self, seq: u64, timeout: Duration) -> Result<SendIntentResponse> {
+        let poll = async {
+            loop {
+                let intent = self.get_intent(seq).await?;
+                if intent.is_final() {
+                    return Ok::<_, EkidenError>(intent);
+                }
+                self.clock.sleep(INTENT_POLL_INTERVAL).await;
+            }
+        };
+        // The deadline runs on the same clock as the polling
+        let intent = tokio::select! {
+            intent = poll => intent?,
+            _ = self.clock.sleep(timeout) => return Err(EkidenError::Timeout),
+        };
+        if !intent.is_success() {
+            let errors: Vec<_> = intent.outputs.iter().filter_map(|o| o.error()).collect();
+            let reason = if errors.is_empty() {
+                format!("Intent {} {}", seq, intent.status)
+            } else {
+                errors.join("; ")
+            };
+            return Err(EkidenError::OrderRejected { reason });
+        }
+        Ok(intent)
+    }
+
+    /// Ask the gateway to check an intent without executing it
+    ///
+    /// Margin, balances and price bands are checked against the current
+    /// state and the expected fills and fees returned. A rejection is
+    /// reported in the simulation rather than as an error; see
+    /// [`IntentSimulation::into_result`].
+    pub async fn simulate_intent(&self, params: SendIntentParams) -> Result<IntentSimulation> {
+        let config = RequestConfig::post(&params)?.with_auth();
+        self.request("user/intent/simulate", config).await
+    }
+
+    /// Sign a batch of actions and simulate them as one intent
+    ///
+    /// Simulations do not use up intent nonces, so none is signed.
+    pub async fn simulate_actions(&self, actions: Vec<ActionPayload>) -> Result<IntentSimulation> {
+        let signature = {
+            let auth = self.auth.read().await;
+            auth.sign_intent(&actions, None)?
+        };
+        self.simulate_intent(SendIntentParams {
+            actions,
+            signature,
+            nonce: None,
+        })
+        .await
+    }
+
+    /// Get the next intent nonce the gateway expects from the user
+    pub async fn get_intent_nonce(&self) -> Result<u64> {
+        let response: NonceResponse = self
+            .request("user/nonce", RequestConfig::get().with_auth())
+            .await?;
+        Ok(response.nonce)
+    }
+
+    /// Sign a batch of actions with the configured key pair and send them as one intent
+    ///
+    /// The signature is over the canonical form described in
+    /// [`auth::intent_message`](crate::auth::intent_message).
+    ///
+    /// With [`EkidenConfig::with_intent_nonces`], the intent carries the next
+    /// nonce, which is signed along with the actions. Nonces are cached and
+    /// intents from all clones of the client are sent one at a time, so they
+    /// reach the gateway in order; after an `invalid_nonce` rejection the
+    /// nonce is fetched again and the intent resent once.
+    ///
+    /// Nonced intents are sent without transport retries: a retry of an intent
+    /// that landed would be rejected as a nonce conflict and then resent under
+    /// a new nonce. A timeout or server error is returned instead, and the
+    /// intent may or may not have executed.
+    pub async fn send_actions(&self, actions: Vec<ActionPayload>) -> Result<SendIntentResponse> {
+        if !self.config.intent_nonces {
+            let signature = {
+                let auth = self.auth.read().await;
+                auth.sign_intent(&actions, None)?
+            };
+            return self
+                .send_intent(SendIntentParams {
+                    actions,
+                    signature,
+                    nonce: None,
+                })
+                .await;
+        }
+
+        let mut next_nonce = self.intent_nonce.lock().await;
+        let mut resynced = false;
+        loop {
+            let nonce = match *next_nonce {
+                Some(nonce) => nonce,
+                None => self.get_intent_nonce().await?,
+            };
+            let signature = {
+                let auth = self.auth.read().await;
+                auth.sign_intent(&actions, Some(nonce))?
+            };
+            let params = SendIntentParams {
+                actions: actions.clone(),
+                signature,
+                nonce: Some(nonce),
+            };
+            let config = RequestConfig::post(&params)?
+                .with_auth()
+                .with_options(RequestOptions::new().with_retry(RetryPolicy::none()));
+            match self.request("user/intent", config).await {
+                Ok(response) => {
+                    *next_nonce = Some(nonce + 1);
+                    return Ok(response);
+                }
+                Err(e) => {
+                    // Whether a failed intent used its nonce is unknown; ask again.
+                    // Only an `invalid_nonce` rejection is known not to have executed.
+                    *next_nonce = None;
+                    if resynced || e.api_code() != Some(ApiErrorCode::InvalidNonce) {
+                        return Err(e);
+                    }
+                    debug!("Intent nonce {} rejected, resyncing", nonce);
+                    resynced = true;
+                }
+            }
+        }
+    }
+
+    /// Place a limit order and return the sid the gateway assigned
+    pub async fn place_limit_order(
+        &self,
+        market_addr: &str,
+        side: OrderSide,
+        price: u64,
+        size: u64,
+        options: OrderOptions,
+    ) -> Result<PlacedOrder> {
+        if price == 0 {
+            return self.invalid(
+                EkidenError::validation("Limit price must be greater than zero"),
+                "user/intent",
+                reqwest::Method::POST,
+            );
+        }
+        let market_addr = format::normalize_address(market_addr)
+            .or_else(|e| self.invalid(e, "user/intent", reqwest::Method::POST))?;
+        let order = IntentAction::limit_order(&market_addr, side, price, size);
+        self.place_order(order, options).await
+    }
+
+    /// Place a market order filling no worse than the mark price moved by
+    /// [`OrderOptions::slippage_bps`]
+    pub async fn place_market_order(
+        &self,
+        market_addr: &str,
+        side: OrderSide,
+        size: u64,
+        options: OrderOptions,
+    ) -> Result<PlacedOrder> {
+        if options.slippage_bps > 10_000 {
+            return self.invalid(
+                EkidenError::validation("Slippage must be at most 10000 basis points"),
+                "user/intent",
+                reqwest::Method::POST,
+            );
+        }
+        let Some(market) = self.get_market_by_address(market_addr).await? else {
+            return self.invalid(
+                EkidenError::validation(format!("Unknown market: {}", market_addr)),
+                "user/intent",
+                reqwest::Method::POST,
+            );
+        };
+        let slippage = market.mark_price as u128 * options.slippage_bps as u128 / 10_000;
+        let worst_price = match side {
+            OrderSide::Buy => (market.mark_price as u128 + slippage).min(u64::MAX as u128) as u64,
+            OrderSide::Sell => (market.mark_price as u128).saturating_sub(slippage) as u64,
+        };
+        let market_addr = format::normalize_address(market_addr)?;
+        let order = IntentAction::market_order(&market_addr, side, size, worst_price);
+        self.place_order(order, options).await
+    }
+
+    /// Send an order with the actions its options call for, and read back its sid
+    async fn place_order(&self, order: IntentAction, options: OrderOptions) -> Result<PlacedOrder> {
+        let order = order
+            .with_time_in_force(options.time_in_force)
+            .with_post_only(options.post_only)
+            .with_reduce_only(options.reduce_only);
+        let order = match &options.client_order_id {
+            Some(id) => order.with_client_order_id(id),
+            None => order,
+        };
+        let IntentAction::PlaceOrder {
+            market_addr,
+            side,
+            order_type,
+            price,
+            size,
+            time_in_force,
+            post_only,
+            client_order_id,
+            ..
+        } = order.clone()
+        else {
+            return self.invalid(
+                EkidenError::validation("Expected a place_order action"),
+                "user/intent",
+                reqwest::Method::POST,
+            );
+        };
+        if size == 0 {
+            return self.invalid(
+                EkidenError::validation("Order size must be greater than zero"),
+                "user/intent",
+                reqwest::Method::POST,
+            );
+        }
+        if post_only && (order_type == OrderType::Market || time_in_force != TimeInForce::Gtc) {
+            return self.invalid(
+                EkidenError::validation(
+                    "Post-only orders must be good-till-cancelled limit orders",
+                ),
+                "user/intent",
+                reqwest::Method::POST,
+            );
+        }
+
+        let mut actions = Vec::with_capacity(2);
+        if let Some(leverage) = options.leverage {
+            actions.push(IntentAction::set_leverage(&market_addr, leverage).into());
+        }
+        actions.push(order.into());
+        if let Some(id) = &client_order_id {
+            self.client_orders
+                .track(id, &market_addr)
+                .or_else(|e| self.invalid(e, "user/intent", reqwest::Method::POST))?;
+        }
+        let placed = self.send_actions(actions).await.and_then(|response| {
+            let sid = response
+                .outputs
+                .iter()
+                .filter(|output| output.action_type == "place_order")
+                .find_map(IntentOutput::sid)
+                .ok_or_else(|| EkidenError::general("Intent accepted without an order sid"))?
+                .to_string();
+            Ok(PlacedOrder {
+                sid,
+                seq: response.seq,
+                market_addr,
+                side,
+                order_type,
+                price,
+                size,
+                client_order_id: client_order_id.clone(),
+                status: response.status,
+            })
+        });
+        if let Some(id) = &client_order_id {
+            match &placed {
+                Ok(order) => self.client_orders.resolve(id, &order.sid),
+                // Keep ids whose order may still have reached the gateway
+                Err(e) if !e.is_retryable() => self.client_orders.forget(id),
+                Err(_) => {}
+            }
+        }
+        placed
+    }
+
+    /// Place a trigger order held by the gateway until its trigger price is reached
+    ///
+    /// Gateways without native triggers reject the action with the
+    /// `unsupported_action` code; a
+    /// [`TriggerMonitor`](crate::triggers::TriggerMonitor) can fire the order
+    /// from the client instead.
+    pub async fn place_trigger_order(&self, order: TriggerOrder) -> Result<PlacedTrigger> {
+        order
+            .validate()
+            .or_else(|e| self.invalid(e, "user/intent", reqwest::Method::POST))?;
+        let order = TriggerOrder {
+            market_addr: format::normalize_address(&order.market_addr)
+                .or_else(|e| self.invalid(e, "user/intent", reqwest::Method::POST))?,
+            ..order
+        };
+        let action = IntentAction::PlaceTriggerOrder(order.clone());
+        let response = self.send_actions(vec![action.into()]).await?;
+        if let Some(error) = output_error(&response.outputs) {
+            return Err(error);
+        }
+        let sid = response
+            .outputs
+            .iter()
+            .filter(|output| output.action_type == "place_trigger_order")
+            .find_map(IntentOutput::sid)
+            .ok_or_else(|| EkidenError::general("Intent accepted without an order sid"))?
+            .to_string();
+        Ok(PlacedTrigger {
+            sid,
+            seq: response.seq,
+            order,
+            status: response.status,
+        })
+    }
+
+    /// Get the orders placed with a client order id and their sids
+    ///
+    /// Sids are recorded from intent responses; start
+    /// [`track_client_orders`](Self::track_client_orders) to also reconcile
+    /// them, and order statuses, from the user channel.
+    pub fn client_orders(&self) -> &ClientOrderIds {
+        &self.client_orders
+    }
+
+    /// Cancel an order by its sid
+    pub async fn cancel_order(&self, market_addr: &str, sid: &str) -> Result<()> {
+        let market_addr = format::normalize_address(market_addr)
+            .or_else(|e| self.invalid(e, "user/intent", reqwest::Method::POST))?;
+        let action = IntentAction::cancel_order(&market_addr, sid);
+        let response = self.send_actions(vec![action.into()]).await?;
+        match response.outputs.iter().find_map(IntentOutput::error) {
+            Some(reason) => Err(EkidenError::OrderRejected {
+                reason: reason.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Change the price and size of an open order
+    ///
+    /// Uses the gateway's native amend, which keeps the order's sid and, where
+    /// the gateway allows, its queue priority. If the gateway does not support
+    /// amends, the order is cancelled and replaced in one intent instead, and
+    /// later amends go straight to cancel-replace, which loses queue priority
+    /// and gives the order a new sid. The replacement keeps the original's time
+    /// in force and post-only and reduce-only flags; orders whose flags the
+    /// gateway does not report are refused rather than replaced without them.
+    pub async fn amend_order(
+        &self,
+        market_addr: &str,
+        sid: &str,
+        new_price: u64,
+        new_size: u64,
+    ) -> Result<AmendedOrder> {
+        if new_price == 0 || new_size == 0 {
+            return self.invalid(
+                EkidenError::validation("Amended price and size must be greater than zero"),
+                "user/intent",
+                reqwest::Method::POST,
+            );
+        }
+        let market_addr = format::normalize_address(market_addr)
+            .or_else(|e| self.invalid(e, "user/intent", reqwest::Method::POST))?;
+        if !self.amend_unsupported.load(Ordering::Relaxed) {
+            match self
+                .native_amend(&market_addr, sid, new_price, new_size)
+                .await
+            {
+                Err(e) if action_unsupported(&e) => {
+                    debug!("Gateway does not support amends, cancelling and replacing");
+                    self.amend_unsupported.store(true, Ordering::Relaxed);
+                }
+                result => return result,
+            }
+        }
+        self.cancel_replace(&market_addr, sid, new_price, new_size)
+            .await
+    }
+
+    /// Amend an order in place
+    async fn native_amend(
+        &self,
+        market_addr: &str,
+        sid: &str,
+        price: u64,
+        size: u64,
+    ) -> Result<AmendedOrder> {
+        let action = IntentAction::amend_order(market_addr, sid, price, size);
+        let response = self.send_actions(vec![action.into()]).await?;
+        if let Some(error) = output_error(&response.outputs) {
+            return Err(error);
+        }
+        Ok(AmendedOrder {
+            sid: sid.to_string(),
+            seq: response.seq,
+            market_addr: market_addr.to_string(),
+            price,
+            size,
+            replaced: false,
+            status: response.status,
+        })
+    }
+
+    /// Cancel an order and place its replacement in one intent
+    async fn cancel_replace(
+        &self,
+        market_addr: &str,
+        sid: &str,
+        price: u64,
+        size: u64,
+    ) -> Result<AmendedOrder> {
+        let order = self.open_order(market_addr, sid).await?;
+        let (Some(post_only), Some(reduce_only)) = (order.post_only, order.reduce_only) else {
+            return self.invalid(
+                EkidenError::validation(format!(
+                    "Order {} does not report its post-only and reduce-only flags; cancel and place it explicitly",
+                    sid
+                )),
+                "user/intent",
+                reqwest::Method::POST,
+            );
+        };
+        let side = serde_json::from_value(serde_json::Value::String(order.side.to_lowercase()))?;
+        let order_type =
+            serde_json::from_value(serde_json::Value::String(order.order_type.to_lowercase()))?;
+        let client_order_id = order.client_order_id;
+        let replacement = IntentAction::PlaceOrder {
+            market_addr: market_addr.to_string(),
+            side,
+            order_type,
+            price,
+            size,
+            time_in_force: order.time_in_force.unwrap_or_default(),
+            post_only,
+            reduce_only,
+            client_order_id: client_order_id.clone(),
+        };
+        let actions = vec![
+            IntentAction::cancel_order(market_addr, sid).into(),
+            replacement.into(),
+        ];
+        let response = self.send_actions(actions).await?;
+        if let Some(reason) = response.outputs.iter().find_map(IntentOutput::error) {
+            return Err(EkidenError::OrderRejected {
+                reason: reason.to_string(),
+            });
+        }
+        let sid = response
+            .outputs
+            .iter()
+            .filter(|output| output.action_type == "place_order")
+            .find_map(IntentOutput::sid)
+            .ok_or_else(|| EkidenError::general("Intent accepted without an order sid"))?
+            .to_string();
+        if let Some(id) = &client_order_id {
+            self.client_orders.resolve(id, &sid);
+        }
+        Ok(AmendedOrder {
+            sid,
+            seq: response.seq,
+            market_addr: market_addr.to_string(),
+            price,
+            size,
+            replaced: true,
+            status: response.status,
+        })
+    }
+
+    /// Find one of the user's open orders
+    async fn open_order(&self, market_addr: &str, sid: &str) -> Result<OrderResponse> {
+        self.get_open_orders(Some(market_addr))
+            .await?
+            .into_iter()
+            .find(|order| order.sid == sid)
+            .ok_or_else(|| EkidenError::OrderRejected {
+                reason: format!("No open order {}", sid),
+            })
+    }
+
+    /// Get the user's open orders on one market, or on every listed market
+    pub async fn get_open_orders(&self, market_addr: Option<&str>) -> Result<Vec<OrderResponse>> {
+        let user_addr = self
+            .address()
+            .await?
+            .ok_or_else(|| EkidenError::auth("A key pair is required to find the user's orders"))?;
+        let markets = match market_addr {
+            Some(market_addr) => vec![format::normalize_address(market_addr)
+                .or_else(|e| self.invalid(e, "orders", reqwest::Method::GET))?],
+            None => self.listed_market_addrs().await?,
+        };
+
+        let mut open = Vec::new();
+        for market_addr in markets {
+            let orders: Vec<Result<OrderResponse>> = self
+                .get_orders_stream(ListOrdersParams {
+                    market_addr,
+                    side: None,
+                    pagination: Pagination::default(),
+                })
+                .collect()
+                .await;
+            for order in orders {
+                let order = order?;
+                let owned = format::normalize_address(&order.user_addr)
+                    .map_or(false, |owner| owner == user_addr);
+                if owned && order.is_open() {
+                    open.push(order);
+                }
+            }
+        }
+        Ok(open)
+    }
+
+    /// Cancel every open order of the user in one intent, on one market or on all
+    ///
+    /// The intent succeeds or fails as a whole. Returns the cancelled sids.
+    pub async fn cancel_all_orders(&self, market_addr: Option<&str>) -> Result<Vec<String>> {
+        let orders = self.get_open_orders(market_addr).await?;
+        if orders.is_empty() {
+            return Ok(Vec::new());
+        }
+        let actions = orders
+            .iter()
+            .map(|order| IntentAction::cancel_order(&order.market_addr, &order.sid).into())
+            .collect();
+        let response = self.send_actions(actions).await?;
+        if let Some(reason) = response.outputs.iter().find_map(IntentOutput::error) {
+            return Err(EkidenError::OrderRejected {
+                reason: reason.to_string(),
+            });
+        }
+        Ok(orders.into_iter().map(|order| order.sid).collect())
+    }
+
+    /// Cancel every open order of the user with one intent per order
+    ///
+    /// Keeps going when a cancel fails; failures are reported per order sid.
+    pub async fn cancel_all_orders_best_effort(
+        &self,
+        market_addr: Option<&str>,
+    ) -> Result<BulkOutcome<String>> {
+        let orders = self.get_open_orders(market_addr).await?;
+        let requests = orders.into_iter().map(|order| async move {
+            let result = self.cancel_order(&order.market_addr, &order.sid).await;
+            (order.sid.clone(), result.map(|()| order.sid))
+        });
+        Ok(BulkOutcome::collect(
+            stream::iter(requests)
+                .buffered(BULK_CONCURRENCY)
+                .collect()
+                .await,
+        ))
+    }
+
     // ===== Deposit/Withdrawal Endpoints =====
 
     /// Get deposits
@@ -296,9 +1362,10 @@ impl EkidenClient {
 
     /// Get user deposits
     pub async fn get_user_deposits(&self, user_addr: &str) -> Result<Vec<DepositResponse>> {
-        format::validate_address(user_addr)?;
+        let user_addr = format::normalize_address(user_addr)
+            .or_else(|e| self.invalid(e, "deposits", reqwest::Method::GET))?;
         let params = ListDepositsParams {
-            user_addr: Some(user_addr.to_string()),
+            user_addr: Some(user_addr),
             vault_addr: None,
             asset_addr: None,
             start_version: None,
@@ -319,9 +1386,10 @@ impl EkidenClient {
 
     /// Get user withdrawals
     pub async fn get_user_withdrawals(&self, user_addr: &str) -> Result<Vec<WithdrawResponse>> {
-        format::validate_address(user_addr)?;
+        let user_addr = format::normalize_address(user_addr)
+            .or_else(|e| self.invalid(e, "withdraws", reqwest::Method::GET))?;
         let params = ListWithdrawsParams {
-            user_addr: Some(user_addr.to_string()),
+            user_addr: Some(user_addr),
             vault_addr: None,
             asset_addr: None,
             start_version: None,
@@ -334,8 +1402,9 @@ impl EkidenClient {
     // ===== Candle Endpoints =====
 
     /// Get candlestick data
-    pub async fn get_candles(&self, params: ListCandlesParams) -> Result<Vec<CandleResponse>> {
-        format::validate_address(&params.market_addr)?;
+    pub async fn get_candles(&self, mut params: ListCandlesParams) -> Result<Vec<CandleResponse>> {
+        params.market_addr = format::normalize_address(&params.market_addr)
+            .or_else(|e| self.invalid(e, "candles", reqwest::Method::GET))?;
         let config = RequestConfig::get().with_query(params.to_query_params());
         self.request("candles", config).await
     }
@@ -367,9 +1436,10 @@ impl EkidenClient {
     /// Get funding rates
     pub async fn get_funding_rates(
         &self,
-        params: ListFundingRatesParams,
+        mut params: ListFundingRatesParams,
     ) -> Result<Vec<FundingRateResponse>> {
-        format::validate_address(&params.market_addr)?;
+        params.market_addr = format::normalize_address(&params.market_addr)
+            .or_else(|e| self.invalid(e, "funding_rate", reqwest::Method::GET))?;
         let config = RequestConfig::get().with_query(params.to_query_params());
         self.request("funding_rate", config).await
     }
@@ -394,158 +1464,930 @@ impl EkidenClient {
         Ok(rates.into_iter().next())
     }
 
-    // ===== WebSocket Methods =====
+    // ===== Announcement Endpoints =====
 
-    /// Connect to WebSocket
-    pub async fn connect_websocket(&self) -> Result<()> {
-        if let Some(ws_client) = &self.ws_client {
-            let mut client = ws_client.write().await;
-            client.connect().await?;
-            info!("WebSocket connected");
-        }
-        Ok(())
+    /// Get gateway announcements
+    pub async fn get_announcements(
+        &self,
+        params: ListAnnouncementsParams,
+    ) -> Result<Vec<AnnouncementResponse>> {
+        let config = RequestConfig::get().with_query(params.to_query_params());
+        self.request("announcements", config).await
     }
 
-    /// Disconnect from WebSocket
-    pub async fn disconnect_websocket(&self) -> Result<()> {
-        if let Some(ws_client) = &self.ws_client {
-            let mut client = ws_client.write().await;
-            client.disconnect().await?;
-            info!("WebSocket disconnected");
-        }
-        Ok(())
+    // ===== Status Endpoints =====
+
+    /// Get the gateway status and the health of its components
+    pub async fn get_status(&self) -> Result<StatusResponse> {
+        self.request("status", RequestConfig::get()).await
     }
 
-    /// Check if WebSocket is connected
-    pub async fn is_websocket_connected(&self) -> bool {
-        if let Some(ws_client) = &self.ws_client {
-            let client = ws_client.read().await;
-            client.is_connected().await
-        } else {
-            false
-        }
+    /// Check that the gateway answers and return the round trip time
+    pub async fn ping(&self) -> Result<Duration> {
+        let started_at = Instant::now();
+        self.request_json("status", RequestConfig::get()).await?;
+        Ok(started_at.elapsed())
     }
 
-    /// Subscribe to orderbook updates
-    pub async fn subscribe_orderbook(
-        &self,
-        market_addr: &str,
-    ) -> Result<tokio::sync::broadcast::Receiver<WsEvent>> {
-        format::validate_address(market_addr)?;
-        if let Some(ws_client) = &self.ws_client {
-            let client = ws_client.read().await;
-            client.subscribe_orderbook(market_addr).await
-        } else {
-            Err(EkidenError::config("WebSocket client not available"))
-        }
+    /// Check gateway and component health, e.g. to gate readiness probes
+    ///
+    /// ```rust,no_run
+    /// # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+    /// let report = client.health().await?;
+    /// if !report.is_ready() {
+    ///     eprintln!("degraded: {:?} ({:?})", report.unhealthy_components(), report.latency);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn health(&self) -> Result<HealthReport> {
+        let started_at = Instant::now();
+        let status = self.get_status().await?;
+        let latency = started_at.elapsed();
+        Ok(HealthReport {
+            latency,
+            status,
+            websocket_connected: self.is_websocket_connected().await,
+        })
     }
 
-    /// Subscribe to trade updates
-    pub async fn subscribe_trades(
-        &self,
-        market_addr: &str,
-    ) -> Result<tokio::sync::broadcast::Receiver<WsEvent>> {
-        format::validate_address(market_addr)?;
-        if let Some(ws_client) = &self.ws_client {
-            let client = ws_client.read().await;
-            client.subscribe_trades(market_addr).await
-        } else {
-            Err(EkidenError::config("WebSocket client not available"))
-        }
+    /// Get the gateway's time and update the estimated clock skew
+    pub async fn get_server_time(&self) -> Result<ServerTime> {
+        let local = self.server_clock.local();
+        let sent_at = local.unix_millis();
+        let time: ServerTime = self.request("time", RequestConfig::get()).await?;
+        let received_at = local.unix_millis();
+        let offset = self
+            .server_clock
+            .observe(time.timestamp, sent_at, received_at);
+        debug!("Clock skew to gateway: {}ms", offset);
+        Ok(time)
     }
 
-    /// Subscribe to user updates
+    /// Get the clock the client and the components started from it wait with
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Get a clock corrected by the estimated skew to the gateway
+    ///
+    /// Use it for timestamps the gateway checks that the application sets
+    /// itself. The SDK's own signing never reads the clock: authorization
+    /// signs a fixed message and intents are ordered by nonce, so the skew
+    /// does not affect them. It reads the local time until
+    /// [`get_server_time`] or [`start_time_sync`] has measured the skew.
+    ///
+    /// [`get_server_time`]: Self::get_server_time
+    /// [`start_time_sync`]: Self::start_time_sync
+    pub fn server_clock(&self) -> Arc<dyn Clock> {
+        self.server_clock.clone()
+    }
+
+    /// Milliseconds the gateway's clock is ahead of the local one
+    pub fn clock_skew_millis(&self) -> i64 {
+        self.server_clock.offset_millis()
+    }
+
+    /// Measure the clock skew now and again every `interval` until shutdown
+    ///
+    /// Fails with a validation error if `interval` is zero.
+    ///
+    /// ```rust,no_run
+    /// # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+    /// use ekiden_rust_sdk::clock::Clock;
+    ///
+    /// client.start_time_sync(std::time::Duration::from_secs(300))?;
+    /// let now = client.server_clock().unix_millis();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn start_time_sync(&self, interval: Duration) -> Result<JoinHandle<()>> {
+        if interval.is_zero() {
+            return self.invalid(
+                EkidenError::validation("Time sync interval must be greater than zero"),
+                "time",
+                reqwest::Method::GET,
+            );
+        }
+        let client = self.clone();
+        Ok(self.spawn_job(async move {
+            loop {
+                if let Err(e) = client.get_server_time().await {
+                    warn!("Failed to sync with gateway time: {}", e);
+                }
+                client.clock.sleep(interval).await;
+            }
+        }))
+    }
+
+    // ===== Paginated Streams =====
+
+    /// Stream every market, fetching further pages as needed
+    pub fn get_markets_stream(
+        &self,
+        params: ListMarketsParams,
+    ) -> impl Stream<Item = Result<MarketResponse>> + '_ {
+        paginate(params.pagination.clone(), move |pagination| {
+            self.get_markets(ListMarketsParams {
+                pagination,
+                ..params.clone()
+            })
+        })
+    }
+
+    /// Stream every order for a market, fetching further pages as needed
+    pub fn get_orders_stream(
+        &self,
+        params: ListOrdersParams,
+    ) -> impl Stream<Item = Result<OrderResponse>> + '_ {
+        paginate(params.pagination.clone(), move |pagination| {
+            self.get_orders(ListOrdersParams {
+                pagination,
+                ..params.clone()
+            })
+        })
+    }
+
+    /// Stream every fill for a market, fetching further pages as needed
+    pub fn get_fills_stream(
+        &self,
+        params: ListFillsParams,
+    ) -> impl Stream<Item = Result<FillResponse>> + '_ {
+        paginate(params.pagination.clone(), move |pagination| {
+            self.get_fills(ListFillsParams {
+                pagination,
+                ..params.clone()
+            })
+        })
+    }
+
+    /// Stream every user vault, fetching further pages as needed
+    pub fn get_user_vaults_stream(
+        &self,
+        params: ListVaultsParams,
+    ) -> impl Stream<Item = Result<VaultResponse>> + '_ {
+        paginate(params.pagination.clone(), move |pagination| {
+            self.get_user_vaults(ListVaultsParams { pagination })
+        })
+    }
+
+    /// Stream every user position, fetching further pages as needed
+    pub fn get_user_positions_stream(
+        &self,
+        params: ListPositionsParams,
+    ) -> impl Stream<Item = Result<PositionResponse>> + '_ {
+        paginate(params.pagination.clone(), move |pagination| {
+            self.get_user_positions(ListPositionsParams {
+                pagination,
+                ..params.clone()
+            })
+        })
+    }
+
+    /// Stream every deposit, fetching further pages as needed
+    pub fn get_deposits_stream(
+        &self,
+        params: ListDepositsParams,
+    ) -> impl Stream<Item = Result<DepositResponse>> + '_ {
+        paginate(params.pagination.clone(), move |pagination| {
+            self.get_deposits(ListDepositsParams {
+                pagination,
+                ..params.clone()
+            })
+        })
+    }
+
+    /// Stream every withdrawal, fetching further pages as needed
+    pub fn get_withdrawals_stream(
+        &self,
+        params: ListWithdrawsParams,
+    ) -> impl Stream<Item = Result<WithdrawResponse>> + '_ {
+        paginate(params.pagination.clone(), move |pagination| {
+            self.get_withdrawals(ListWithdrawsParams {
+                pagination,
+                ..params.clone()
+            })
+        })
+    }
+
+    /// Stream every candle in a range, fetching further pages as needed
+    pub fn get_candles_stream(
+        &self,
+        params: ListCandlesParams,
+    ) -> impl Stream<Item = Result<CandleResponse>> + '_ {
+        paginate(params.pagination.clone(), move |pagination| {
+            self.get_candles(ListCandlesParams {
+                pagination,
+                ..params.clone()
+            })
+        })
+    }
+
+    /// Stream every funding rate in a range, fetching further pages as needed
+    pub fn get_funding_rates_stream(
+        &self,
+        params: ListFundingRatesParams,
+    ) -> impl Stream<Item = Result<FundingRateResponse>> + '_ {
+        paginate(params.pagination.clone(), move |pagination| {
+            self.get_funding_rates(ListFundingRatesParams {
+                pagination,
+                ..params.clone()
+            })
+        })
+    }
+
+    /// Stream every announcement, fetching further pages as needed
+    pub fn get_announcements_stream(
+        &self,
+        params: ListAnnouncementsParams,
+    ) -> impl Stream<Item = Result<AnnouncementResponse>> + '_ {
+        paginate(params.pagination.clone(), move |pagination| {
+            self.get_announcements(ListAnnouncementsParams {
+                pagination,
+                ..params.clone()
+            })
+        })
+    }
+
+    // ===== Batch Requests =====
+
+    /// Start a batch of read queries run concurrently, see [`Batch`]
+    pub fn batch(&self) -> Batch<'_> {
+        Batch::new(self)
+    }
+
+    // ===== Raw Requests =====
+
+    /// Send a request to an arbitrary API path and return the raw JSON body
+    pub async fn request_json(
+        &self,
+        path: &str,
+        config: RequestConfig,
+    ) -> Result<serde_json::Value> {
+        self.request(path, config).await
+    }
+
+    // ===== WebSocket Methods =====
+
+    /// Connect to WebSocket
+    pub async fn connect_websocket(&self) -> Result<()> {
+        if let Some(ws_pool) = &self.ws_pool {
+            // The source must not hold the pool, or the client would keep itself alive
+            let snapshots = self.config.rest_snapshots.then(|| {
+                Arc::new(EkidenClient {
+                    ws_pool: None,
+                    ..self.clone()
+                }) as Arc<dyn SnapshotSource>
+            });
+            let mut pool = ws_pool.write().await;
+            for client in pool.shards_mut() {
+                client.set_snapshot_source(snapshots.clone());
+                let result = self.connect_with_failover(client).await;
+                self.observe(result, || ErrorContext::ws("ws_connect", None))?;
+            }
+            info!("WebSocket connected");
+        }
+        Ok(())
+    }
+
+    /// Disconnect from WebSocket
+    pub async fn disconnect_websocket(&self) -> Result<()> {
+        if let Some(ws_pool) = &self.ws_pool {
+            let mut pool = ws_pool.write().await;
+            let result = pool.disconnect().await;
+            self.observe(result, || ErrorContext::ws("ws_disconnect", None))?;
+            info!("WebSocket disconnected");
+        }
+        Ok(())
+    }
+
+    /// Check if WebSocket is connected
+    pub async fn is_websocket_connected(&self) -> bool {
+        if let Some(ws_pool) = &self.ws_pool {
+            let pool = ws_pool.read().await;
+            pool.is_connected().await
+        } else {
+            false
+        }
+    }
+
+    /// Get WebSocket ping round-trip stats, `None` before the first pong
+    ///
+    /// With several connections the slowest one by p99 is reported. A rising
+    /// p99 is a sign to reconnect or move to another endpoint:
+    ///
+    /// ```rust,no_run
+    /// # async fn run(client: ekiden_rust_sdk::EkidenClient) {
+    /// if let Some(latency) = client.ws_latency().await {
+    ///     println!("last {:?} avg {:?} p99 {:?}", latency.last, latency.avg, latency.p99);
+    /// }
+    /// # }
+    /// ```
+    pub async fn ws_latency(&self) -> Option<LatencyStats> {
+        self.ws_pool.as_ref()?.read().await.latency()
+    }
+
+    /// Watch WebSocket status transitions instead of polling
+    ///
+    /// ```rust,no_run
+    /// # async fn run(client: ekiden_rust_sdk::EkidenClient) {
+    /// use ekiden_rust_sdk::ws::ConnectionStatus;
+    ///
+    /// let mut status = client.websocket_status_stream().await;
+    /// while status.changed().await.is_ok() {
+    ///     if let ConnectionStatus::Failed(reason) = &*status.borrow_and_update() {
+    ///         eprintln!("WebSocket failed: {}", reason);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn websocket_status_stream(
+        &self,
+    ) -> tokio::sync::watch::Receiver<crate::ws::ConnectionStatus> {
+        match &self.ws_pool {
+            Some(ws_pool) => ws_pool.read().await.status_stream(),
+            None => tokio::sync::watch::channel(crate::ws::ConnectionStatus::Disconnected).1,
+        }
+    }
+
+    /// Subscribe to orderbook updates
+    pub async fn subscribe_orderbook(
+        &self,
+        market_addr: &str,
+    ) -> Result<tokio::sync::broadcast::Receiver<WsEvent>> {
+        let channel = channels::orderbook(market_addr);
+        let result = self.subscribe_channel(&channel, market_addr).await;
+        self.observe(result, || ErrorContext::ws("ws_subscribe", Some(&channel)))
+    }
+
+    /// Subscribe to trade updates
+    pub async fn subscribe_trades(
+        &self,
+        market_addr: &str,
+    ) -> Result<tokio::sync::broadcast::Receiver<WsEvent>> {
+        let channel = channels::trades(market_addr);
+        let result = self.subscribe_channel(&channel, market_addr).await;
+        self.observe(result, || ErrorContext::ws("ws_subscribe", Some(&channel)))
+    }
+
+    /// Subscribe to live candle updates for a market and interval such as `"1m"`
+    pub async fn subscribe_candles(
+        &self,
+        market_addr: &str,
+        interval: &str,
+    ) -> Result<tokio::sync::broadcast::Receiver<WsEvent>> {
+        let channel = channels::candles(market_addr, interval);
+        let result = match crate::analytics::candles::interval_millis(interval) {
+            Some(_) => self.subscribe_channel(&channel, market_addr).await,
+            None => Err(EkidenError::validation(format!(
+                "Invalid candle interval: {}",
+                interval
+            ))),
+        };
+        self.observe(result, || ErrorContext::ws("ws_subscribe", Some(&channel)))
+    }
+
+    /// Subscribe to mark price, oracle price, 24h volume and funding updates
+    pub async fn subscribe_ticker(
+        &self,
+        market_addr: &str,
+    ) -> Result<tokio::sync::broadcast::Receiver<WsEvent>> {
+        let channel = channels::ticker(market_addr);
+        let result = self.subscribe_channel(&channel, market_addr).await;
+        self.observe(result, || ErrorContext::ws("ws_subscribe", Some(&channel)))
+    }
+
+    /// Subscribe to liquidations on a market
+    pub async fn subscribe_liquidations(
+        &self,
+        market_addr: &str,
+    ) -> Result<tokio::sync::broadcast::Receiver<WsEvent>> {
+        let channel = channels::liquidations(market_addr);
+        let result = self.subscribe_channel(&channel, market_addr).await;
+        self.observe(result, || ErrorContext::ws("ws_subscribe", Some(&channel)))
+    }
+
+    /// Subscribe to user updates
     pub async fn subscribe_user(
         &self,
         user_addr: &str,
     ) -> Result<tokio::sync::broadcast::Receiver<WsEvent>> {
-        format::validate_address(user_addr)?;
-        if let Some(ws_client) = &self.ws_client {
-            let client = ws_client.read().await;
-            client.subscribe_user(user_addr).await
-        } else {
-            Err(EkidenError::config("WebSocket client not available"))
+        let channel = channels::user(user_addr);
+        let result = self.subscribe_channel(&channel, user_addr).await;
+        self.observe(result, || ErrorContext::ws("ws_subscribe", Some(&channel)))
+    }
+
+    /// Subscribe to gateway announcements (maintenance, listings, system messages)
+    pub async fn subscribe_announcements(
+        &self,
+    ) -> Result<tokio::sync::broadcast::Receiver<WsEvent>> {
+        let channel = channels::announcements();
+        let result = match &self.ws_pool {
+            Some(ws_pool) => ws_pool.read().await.subscribe(&channel).await,
+            None => Err(EkidenError::config("WebSocket client not available")),
+        };
+        self.observe(result, || ErrorContext::ws("ws_subscribe", Some(&channel)))
+    }
+
+    /// Subscribe to a market's orderbook as typed snapshots and updates
+    pub async fn subscribe_orderbook_stream(&self, market_addr: &str) -> Result<OrderbookStream> {
+        let receiver = self.subscribe_orderbook(market_addr).await?;
+        Ok(TypedStream::new(EventStream::new(
+            receiver,
+            channels::orderbook(market_addr),
+        )))
+    }
+
+    /// Subscribe to a market's trades as typed events
+    pub async fn subscribe_trade_stream(&self, market_addr: &str) -> Result<TradeStream> {
+        let receiver = self.subscribe_trades(market_addr).await?;
+        Ok(TypedStream::new(EventStream::new(
+            receiver,
+            channels::trades(market_addr),
+        )))
+    }
+
+    /// Subscribe to a user's order, position and balance updates as typed events
+    pub async fn subscribe_user_stream(&self, user_addr: &str) -> Result<UserEventStream> {
+        let receiver = self.subscribe_user(user_addr).await?;
+        Ok(TypedStream::new(EventStream::new(
+            receiver,
+            channels::user(user_addr),
+        )))
+    }
+
+    /// Subscribe to a user's fills only, as typed events
+    ///
+    /// Shares the user channel with [`subscribe_user`](Self::subscribe_user).
+    pub async fn subscribe_user_fills(&self, user_addr: &str) -> Result<FillStream> {
+        let receiver = self.subscribe_user(user_addr).await?;
+        Ok(TypedStream::new(EventStream::new(
+            receiver,
+            channels::user(user_addr),
+        )))
+    }
+
+    /// Reconcile [`client_orders`](Self::client_orders) with a user's order
+    /// updates until shutdown
+    ///
+    /// After lost events, the user's open orders are refetched over REST.
+    pub async fn track_client_orders(&self, user_addr: &str) -> Result<JoinHandle<()>> {
+        let mut stream = self.subscribe_user_stream(user_addr).await?;
+        let client = self.clone();
+        Ok(self.spawn_job(async move {
+            loop {
+                match stream.recv().await {
+                    Ok(UserEvent::Order(order)) => client.client_orders.apply(&order),
+                    Ok(UserEvent::Resync { .. }) => match client.get_open_orders(None).await {
+                        Ok(orders) => orders
+                            .iter()
+                            .for_each(|order| client.client_orders.apply(order)),
+                        Err(e) => warn!("Failed to refetch orders after resync: {}", e),
+                    },
+                    Ok(_) => {}
+                    Err(e) => {
+                        debug!("Client order tracking stopped: {}", e);
+                        break;
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Subscribe to several channels and receive their events as one stream
+    ///
+    /// Fails on the first channel that cannot be subscribed; channels
+    /// subscribed before it are released when the stream is dropped.
+    pub async fn subscribe_many(&self, channels: &[Channel]) -> Result<MultiStream> {
+        let mut streams = Vec::with_capacity(channels.len());
+        for channel in channels {
+            let receiver = match channel {
+                Channel::Orderbook(market_addr) => self.subscribe_orderbook(market_addr).await?,
+                Channel::Trades(market_addr) => self.subscribe_trades(market_addr).await?,
+                Channel::Candles(market_addr, interval) => {
+                    self.subscribe_candles(market_addr, interval).await?
+                }
+                Channel::Ticker(market_addr) => self.subscribe_ticker(market_addr).await?,
+                Channel::Liquidations(market_addr) => {
+                    self.subscribe_liquidations(market_addr).await?
+                }
+                Channel::User(user_addr) => self.subscribe_user(user_addr).await?,
+                Channel::Announcements => self.subscribe_announcements().await?,
+            };
+            streams.push((channel.clone(), EventStream::new(receiver, channel.name())));
         }
+        Ok(MultiStream::new(streams))
+    }
+
+    /// Subscribe to a channel with a chosen buffer size and lag policy
+    pub async fn subscribe_with(
+        &self,
+        channel: &str,
+        options: SubscriptionOptions,
+    ) -> Result<EventStream> {
+        let result = match &self.ws_pool {
+            Some(ws_pool) => ws_pool.read().await.subscribe_with(channel, options).await,
+            None => Err(EkidenError::config("WebSocket client not available")),
+        };
+        self.observe(result, || ErrorContext::ws("ws_subscribe", Some(channel)))
     }
 
     /// Unsubscribe from a channel
     pub async fn unsubscribe(&self, channel: &str) -> Result<()> {
-        if let Some(ws_client) = &self.ws_client {
-            let client = ws_client.read().await;
-            client.unsubscribe(channel).await
-        } else {
-            Err(EkidenError::config("WebSocket client not available"))
+        let result = match &self.ws_pool {
+            Some(ws_pool) => ws_pool.read().await.unsubscribe(channel).await,
+            None => Err(EkidenError::config("WebSocket client not available")),
+        };
+        self.observe(result, || ErrorContext::ws("ws_unsubscribe", Some(channel)))
+    }
+
+    /// Unsubscribe from every channel, e.g. before tearing down a strategy
+    pub async fn unsubscribe_all(&self) -> Result<()> {
+        let result = match &self.ws_pool {
+            Some(ws_pool) => ws_pool.read().await.unsubscribe_all().await,
+            None => Err(EkidenError::config("WebSocket client not available")),
+        };
+        self.observe(result, || ErrorContext::ws("ws_unsubscribe", None))
+    }
+
+    /// Get every subscribed channel
+    pub async fn active_subscriptions(&self) -> Vec<String> {
+        match &self.ws_pool {
+            Some(ws_pool) => ws_pool.read().await.active_subscriptions().await,
+            None => Vec::new(),
+        }
+    }
+
+    /// Get every subscription with its parsed channel and receiver count
+    ///
+    /// ```rust,no_run
+    /// # async fn run(client: ekiden_rust_sdk::EkidenClient) {
+    /// use ekiden_rust_sdk::ws::Channel;
+    ///
+    /// for subscription in client.subscriptions().await {
+    ///     if let Some(Channel::Candles(market_addr, interval)) = &subscription.kind {
+    ///         println!("{} {} candles, {} receivers", market_addr, interval, subscription.subscribers);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn subscriptions(&self) -> Vec<SubscriptionInfo> {
+        match &self.ws_pool {
+            Some(ws_pool) => ws_pool.read().await.subscriptions().await,
+            None => Vec::new(),
+        }
+    }
+
+    /// Get the number of receivers attached to a channel
+    pub async fn subscriber_count(&self, channel: &str) -> usize {
+        match &self.ws_pool {
+            Some(ws_pool) => ws_pool.read().await.subscriber_count(channel).await,
+            None => 0,
+        }
+    }
+
+    /// Repeat the subscription request for a channel, keeping its receivers
+    pub async fn resubscribe(&self, channel: &str) -> Result<()> {
+        let result = match &self.ws_pool {
+            Some(ws_pool) => ws_pool.read().await.resubscribe(channel).await,
+            None => Err(EkidenError::config("WebSocket client not available")),
+        };
+        self.observe(result, || ErrorContext::ws("ws_resubscribe", Some(channel)))
+    }
+
+    /// Get the time of the last event per subscribed channel
+    pub async fn channel_activity(&self) -> HashMap<String, Instant> {
+        match &self.ws_pool {
+            Some(ws_pool) => ws_pool.read().await.channel_activity().await,
+            None => HashMap::new(),
         }
     }
 
     // ===== Private Helper Methods =====
 
-    /// Make an HTTP request to the API
+    /// Subscribe to a channel keyed by a validated address
+    async fn subscribe_channel(
+        &self,
+        channel: &str,
+        addr: &str,
+    ) -> Result<tokio::sync::broadcast::Receiver<WsEvent>> {
+        format::validate_address(addr)?;
+        match &self.ws_pool {
+            Some(ws_pool) => ws_pool.read().await.subscribe(channel).await,
+            None => Err(EkidenError::config("WebSocket client not available")),
+        }
+    }
+
+    /// Connect the WebSocket, moving on to the next endpoint when one fails
+    async fn connect_with_failover(&self, client: &mut WebSocketClient) -> Result<()> {
+        let mut attempts = 1;
+        loop {
+            let index = self.endpoints.active();
+            client.set_url(self.endpoints.websocket_url(index).clone());
+            match client.connect().await {
+                Ok(()) => {
+                    self.endpoints.report_success(index);
+                    return Ok(());
+                }
+                Err(EkidenError::Cancelled) => return Err(EkidenError::Cancelled),
+                Err(e) => {
+                    self.endpoints.report_failure(index);
+                    if attempts >= self.endpoints.len() {
+                        return Err(e);
+                    }
+                    attempts += 1;
+                }
+            }
+        }
+    }
+
+    /// Make an HTTP request to the API, reporting failures to the error hooks
     async fn request<T>(&self, path: &str, config: RequestConfig) -> Result<T>
     where
         T: DeserializeOwned,
     {
-        let url = self.config.api_url(path);
-        let mut request = self.http_client.request(config.method, &url);
+        self.request_at(&route_template(path), path, config).await
+    }
 
-        // Add query parameters
+    /// Make an HTTP request to a path with ids in it, e.g. `user/intent/7`
+    ///
+    /// `route` is the path's template, e.g. `user/intent/{seq}`, used where
+    /// a label must not grow with every id.
+    async fn request_at<T>(&self, route: &str, path: &str, config: RequestConfig) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let method = config.method.clone();
+        let span = tracing::info_span!(
+            "ekiden.request",
+            endpoint = route,
+            method = %method,
+            market_addr = tracing::field::Empty,
+            status = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        if let Some(market_addr) = request_market_addr(&config) {
+            span.record("market_addr", market_addr);
+        }
+        let send = async {
+            match config.options.retry.or(self.options.retry) {
+                Some(retry) => self.send_with_retry(route, path, config, retry).await,
+                None => self.send_request(route, path, config).await,
+            }
+        };
+        let result = tokio::select! {
+            biased;
+            _ = self.shutdown.cancelled() => Err(EkidenError::Cancelled),
+            result = send.instrument(span.clone()) => result,
+        };
+        if let Err(e) = &result {
+            span.record("error", e.kind().as_str());
+        }
+        self.observe(result, || ErrorContext::rest(path, method))
+    }
+
+    /// Send a request, retrying retryable failures with backoff
+    async fn send_with_retry<T>(
+        &self,
+        route: &str,
+        path: &str,
+        config: RequestConfig,
+        retry: RetryPolicy,
+    ) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let mut result = self.send_request(route, path, config.clone()).await;
+        for attempt in 0..retry.max_retries {
+            let delay = match &result {
+                Err(e) if e.is_retryable() => e
+                    .retry_after()
+                    .unwrap_or_default()
+                    .max(retry.backoff(attempt)),
+                _ => break,
+            };
+            debug!("Retrying {} in {:?}", path, delay);
+            self.clock.sleep(delay).await;
+            result = self.send_request(route, path, config.clone()).await;
+        }
+        result
+    }
+
+    /// Make an HTTP request to the API
+    async fn send_request<T>(&self, route: &str, path: &str, config: RequestConfig) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let mut endpoint = self.endpoints.active();
+        let mut url = Url::parse(&self.endpoints.api_url(endpoint, path))?;
+
+        // Add query parameters, sorted so identical requests share a URL
         if let Some(query) = &config.query {
-            request = request.query(query);
+            let mut pairs: Vec<_> = query.iter().collect();
+            pairs.sort();
+            url.query_pairs_mut().extend_pairs(pairs);
+        }
+
+        // Add headers, letting the request options override them
+        let options = self.options.merge(&config.options);
+        let mut headers: Vec<(String, String)> = config.headers.into_iter().collect();
+        for (name, value) in options.headers {
+            headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+            headers.push((name, value));
         }
 
-        // Add headers
-        for (key, value) in &config.headers {
-            request = request.header(key, value);
+        // Negotiate the response encoding
+        if self.config.wire_format != WireFormat::Json {
+            headers.push((
+                "Accept".to_string(),
+                self.config.wire_format.accept_header(),
+            ));
         }
 
         // Add authentication headers if required
         if config.auth_required {
             let auth = self.auth.read().await;
             auth.ensure_authenticated()?;
-            let auth_headers = auth.auth_headers();
-            for (key, value) in auth_headers {
-                request = request.header(key, value);
-            }
+            headers.extend(auth.auth_headers());
         }
 
         // Add body for POST/PUT requests
-        if let Some(body) = &config.body {
-            request = request.json(body);
+        let body = match &config.body {
+            Some(body) => {
+                headers.push(("Content-Type".to_string(), "application/json".to_string()));
+                Some(serde_json::to_vec(body)?)
+            }
+            None => None,
+        };
+
+        // Propagate the trace context of the current span
+        #[cfg(feature = "otel")]
+        crate::otel::inject_context(&mut headers);
+
+        // Wait for the client-side rate limits
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter
+                .acquire(EndpointClass::classify(path, config.auth_required))
+                .await;
         }
 
-        // Execute the request
-        let response = request.send().await?;
-        self.handle_response(response).await
+        let mut request = HttpRequest {
+            method: config.method,
+            url,
+            headers,
+            body,
+            timeout: options.timeout,
+        };
+
+        // Execute the request, letting interceptors see both sides
+        let context = if self.interceptors.is_empty() {
+            None
+        } else {
+            let context = RequestContext::new(path, config.auth_required);
+            self.interceptors.before_request(&mut request, &context)?;
+            Some(context)
+        };
+
+        // Fail over to the next endpoint while connections fail
+        let mut attempts = 1;
+        let response = loop {
+            let response = self.dispatch(route, &request, context.as_ref()).await;
+            match &response {
+                Err(e) if failover::should_fail_over(e, &request.method) => {
+                    self.endpoints.report_failure(endpoint);
+                    if attempts >= self.endpoints.len() {
+                        break response;
+                    }
+                    attempts += 1;
+                    endpoint = self.endpoints.active();
+                    let mut url = Url::parse(&self.endpoints.api_url(endpoint, path))?;
+                    url.set_query(request.url.query());
+                    request.url = url;
+                }
+                Err(_) => break response,
+                Ok(_) => {
+                    self.endpoints.report_success(endpoint);
+                    break response;
+                }
+            }
+        };
+        self.handle_response(response?)
+    }
+
+    /// Send a request through the transport, reporting it to interceptors and metrics
+    async fn dispatch(
+        &self,
+        route: &str,
+        request: &HttpRequest,
+        context: Option<&RequestContext>,
+    ) -> Result<HttpResponse> {
+        let request_bytes = request.body.as_ref().map_or(0, Vec::len);
+        if self.config.enable_logging {
+            logging::log_request(request);
+        }
+        let started_at = Instant::now();
+        let response = match context {
+            None => self.transport.send(request.clone()).await,
+            Some(context) => {
+                let response = self.transport.send(request.clone()).await;
+                self.interceptors
+                    .after_response(request, &response, context);
+                response
+            }
+        };
+        if self.config.enable_logging {
+            logging::log_response(request, &response, started_at.elapsed());
+        }
+        if let Ok(response) = &response {
+            Span::current().record("status", response.status);
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.record_request(&RequestMetrics {
+                endpoint: route.to_string(),
+                method: request.method.clone(),
+                status: response.as_ref().ok().map(|r| r.status),
+                error: response.as_ref().err().map(EkidenError::kind),
+                latency: started_at.elapsed(),
+                request_bytes,
+                response_bytes: response.as_ref().map_or(0, |r| r.body.len()),
+            });
+        }
+        response
     }
 
     /// Handle HTTP response and convert to the desired type
-    async fn handle_response<T>(&self, response: Response) -> Result<T>
+    fn handle_response<T>(&self, response: HttpResponse) -> Result<T>
     where
         T: DeserializeOwned,
     {
-        let status = response.status();
-
-        if status.is_success() {
-            let text = response.text().await?;
-            debug!("API response: {}", text);
-            serde_json::from_str(&text).map_err(EkidenError::Json)
+        let body = codec::decompress(response.header("content-encoding"), &response.body);
+        if response.is_success() {
+            let body = body?;
+            let format = WireFormat::from_content_type(response.header("content-type"));
+            if format == WireFormat::Json {
+                debug!("API response: {}", logging::redact_body(&body));
+            } else {
+                debug!("API response: {} bytes of {:?}", body.len(), format);
+            }
+            format.decode(&body)
+        } else if response.status == 429 {
+            // An HTTP date in `Retry-After` is in the gateway's time
+            let error = EkidenError::rate_limit_at(
+                response.header("retry-after"),
+                response.header("x-ratelimit-scope"),
+                self.server_clock.now(),
+            );
+            error!("{}", error);
+            Err(error)
         } else {
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            error!("API error {}: {}", status, error_text);
-            Err(EkidenError::api(status.as_u16(), error_text))
+            // Keep the status of an error even if its body cannot be inflated
+            let body = body.unwrap_or(std::borrow::Cow::Borrowed(&response.body));
+            let error = EkidenError::from_response_body(response.status, &body);
+            error!("{}", error);
+            Err(error)
         }
     }
 }
 
+/// Resumes orderbook channels from the REST orderbook
+impl SnapshotSource for EkidenClient {
+    fn orderbook_snapshot<'a>(
+        &'a self,
+        market_addr: &'a str,
+    ) -> futures_util::future::BoxFuture<'a, Result<WsEvent>> {
+        Box::pin(async move { Ok(self.get_orderbook(market_addr, None).await?.into()) })
+    }
+}
+
 /// Builder for creating configured Ekiden clients
-#[derive(Debug)]
 pub struct EkidenClientBuilder {
     config: EkidenConfig,
     private_key: Option<String>,
     token: Option<String>,
+    transport: Option<Arc<dyn HttpTransport>>,
+    ws_connector: Option<Arc<dyn WsConnector>>,
+    interceptors: Interceptors,
+    error_hooks: ErrorHooks,
+    cancellation_token: Option<CancellationToken>,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    clock: Option<Arc<dyn Clock>>,
+}
+
+impl std::fmt::Debug for EkidenClientBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EkidenClientBuilder")
+            .field("config", &self.config)
+            .field("private_key", &self.private_key.as_deref().map(Redacted))
+            .field("token", &self.token.as_deref().map(Redacted))
+            .field("transport", &self.transport)
+            .field("ws_connector", &self.ws_connector)
+            .field("interceptors", &self.interceptors)
+            .field("error_hooks", &self.error_hooks)
+            .field("cancellation_token", &self.cancellation_token)
+            .field("metrics", &self.metrics)
+            .field("clock", &self.clock)
+            .finish()
+    }
 }
 
 impl EkidenClientBuilder {
@@ -555,6 +2397,13 @@ impl EkidenClientBuilder {
             config: EkidenConfig::default(),
             private_key: None,
             token: None,
+            transport: None,
+            ws_connector: None,
+            interceptors: Interceptors::default(),
+            error_hooks: ErrorHooks::default(),
+            cancellation_token: None,
+            metrics: None,
+            clock: None,
         }
     }
 
@@ -612,15 +2461,123 @@ impl EkidenClientBuilder {
         self
     }
 
+    /// Set the preferred REST response encoding
+    pub fn wire_format(mut self, wire_format: WireFormat) -> Self {
+        self.config = self.config.with_wire_format(wire_format);
+        self
+    }
+
+    /// Apply client-side rate limits to REST requests
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiterConfig) -> Self {
+        self.config = self.config.with_rate_limiter(rate_limiter);
+        self
+    }
+
+    /// Route REST requests through a proxy
+    ///
+    /// WebSocket connections are still made directly; see
+    /// [`EkidenConfig::with_proxy`].
+    pub fn proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.config = self.config.with_proxy(proxy);
+        self
+    }
+
+    /// Use custom TLS roots, client identity or backend
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.config = self.config.with_tls(tls);
+        self
+    }
+
+    /// Cache market metadata for `ttl`
+    pub fn market_cache(mut self, ttl: Duration) -> Self {
+        self.config = self.config.with_market_cache(ttl);
+        self
+    }
+
     /// Enable logging
     pub fn with_logging(mut self, enable: bool) -> Self {
         self.config = self.config.with_logging(enable);
         self
     }
 
+    /// Send REST requests through a custom transport
+    pub fn transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Open WebSocket connections through a custom connector
+    pub fn ws_connector(mut self, ws_connector: Arc<dyn WsConnector>) -> Self {
+        self.ws_connector = Some(ws_connector);
+        self
+    }
+
+    /// Register an interceptor that sees every REST request and its outcome
+    pub fn interceptor<I>(mut self, interceptor: I) -> Self
+    where
+        I: RequestInterceptor + 'static,
+    {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Register a callback invoked with every error the client returns
+    pub fn on_error<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&EkidenError, &ErrorContext) + Send + Sync + 'static,
+    {
+        self.error_hooks.0.push(Arc::new(hook));
+        self
+    }
+
+    /// Cancel the client's requests and background jobs when `token` is cancelled
+    ///
+    /// Useful to tie the client to a service-wide shutdown signal. Call
+    /// [`EkidenClient::shutdown`] as well to close the WebSocket cleanly. The
+    /// client uses a child of the token, so its own shutdown does not cancel
+    /// `token`.
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Report REST requests and WebSocket traffic to a metrics recorder
+    pub fn metrics(mut self, metrics: Arc<dyn MetricsRecorder>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Use a custom clock for retry, polling, reconnect and rate limit delays
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
     /// Build the client
     pub async fn build(self) -> Result<EkidenClient> {
-        let client = EkidenClient::new(self.config)?;
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => Arc::new(ReqwestTransport::new(&self.config)?),
+        };
+        let ws_connector = match self.ws_connector {
+            Some(ws_connector) => ws_connector,
+            None => default_ws_connector(&self.config)?,
+        };
+        let shutdown = match &self.cancellation_token {
+            Some(token) => token.child_token(),
+            None => CancellationToken::new(),
+        };
+        let clock = self.clock.unwrap_or_else(clock::system);
+        let mut client = EkidenClient::assemble(
+            self.config,
+            transport,
+            ws_connector,
+            shutdown,
+            self.metrics,
+            clock,
+        );
+        client.interceptors = self.interceptors;
+        client.error_hooks = self.error_hooks;
 
         // Set private key if provided
         if let Some(private_key) = self.private_key {
@@ -652,6 +2609,7 @@ impl Default for EkidenClientBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ErrorKind;
 
     #[tokio::test]
     async fn test_client_creation() {
@@ -671,4 +2629,645 @@ mod tests {
 
         assert!(!client.is_authenticated().await);
     }
+
+    #[test]
+    fn test_builder_debug_is_redacted() {
+        let builder = EkidenClientBuilder::new()
+            .private_key("0xdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef")
+            .token("secret-session-token");
+        let debug = format!("{:?}", builder);
+        assert!(!debug.contains("deadbeef"));
+        assert!(!debug.contains("secret-session-token"));
+    }
+
+    #[derive(Debug)]
+    struct UnavailableTransport;
+
+    impl HttpTransport for UnavailableTransport {
+        fn send(
+            &self,
+            _request: HttpRequest,
+        ) -> futures_util::future::BoxFuture<'_, Result<HttpResponse>> {
+            Box::pin(async {
+                Ok(HttpResponse {
+                    status: 503,
+                    headers: Vec::new(),
+                    body: br#"{"message":"maintenance"}"#.to_vec(),
+                })
+            })
+        }
+    }
+
+    /// Transport whose requests never complete
+    #[derive(Debug)]
+    struct HangingTransport;
+
+    impl HttpTransport for HangingTransport {
+        fn send(
+            &self,
+            _request: HttpRequest,
+        ) -> futures_util::future::BoxFuture<'_, Result<HttpResponse>> {
+            Box::pin(futures_util::future::pending())
+        }
+    }
+
+    /// Transport timing out on every host except `backup`
+    #[derive(Debug, Default)]
+    struct FlakyPrimaryTransport {
+        hosts: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl HttpTransport for FlakyPrimaryTransport {
+        fn send(
+            &self,
+            request: HttpRequest,
+        ) -> futures_util::future::BoxFuture<'_, Result<HttpResponse>> {
+            let host = request.url.host_str().unwrap_or_default().to_string();
+            self.hosts.lock().unwrap().push(host.clone());
+            Box::pin(async move {
+                if host == "backup" {
+                    HttpResponse::json(200, &serde_json::json!([]))
+                } else {
+                    Err(EkidenError::Timeout)
+                }
+            })
+        }
+    }
+
+    /// Transport answering leverage updates, rejecting leverage above 20x
+    #[derive(Debug)]
+    struct LeverageTransport;
+
+    impl HttpTransport for LeverageTransport {
+        fn send(
+            &self,
+            request: HttpRequest,
+        ) -> futures_util::future::BoxFuture<'_, Result<HttpResponse>> {
+            Box::pin(async move {
+                let params: SetUserLeverageParams =
+                    serde_json::from_slice(request.body.as_deref().unwrap_or_default())?;
+                let (status, body) = if params.leverage > 20 {
+                    (400, serde_json::json!({ "message": "leverage too high" }))
+                } else {
+                    (
+                        200,
+                        serde_json::json!({
+                            "market_addr": params.market_addr,
+                            "user_addr": "0x0",
+                            "leverage": params.leverage,
+                            "created_at": "",
+                            "updated_at": "",
+                        }),
+                    )
+                };
+                Ok(HttpResponse {
+                    status,
+                    headers: Vec::new(),
+                    body: serde_json::to_vec(&body)?,
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_leverage_bulk_reports_partial_failures() {
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(Arc::new(LeverageTransport))
+            .token("session")
+            .build()
+            .await
+            .unwrap();
+        let btc = "0x1111111111111111111111111111111111111111";
+        let eth = "0x2222222222222222222222222222222222222222";
+
+        let outcome = client
+            .set_leverage_bulk([(btc, 10), (eth, 50), ("not-an-address", 5)])
+            .await;
+
+        assert!(!outcome.is_complete());
+        assert_eq!(outcome.succeeded.len(), 1);
+        assert_eq!(outcome.succeeded[0].market_addr, btc);
+        assert_eq!(outcome.succeeded[0].leverage, 10);
+        let failed: Vec<_> = outcome
+            .failed
+            .iter()
+            .map(|(m, e)| (m.as_str(), e.kind()))
+            .collect();
+        assert_eq!(
+            failed,
+            vec![
+                (eth, ErrorKind::Rejected),
+                ("not-an-address", ErrorKind::Validation)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_on_error_sees_every_surfaced_error() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = seen.clone();
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(Arc::new(UnavailableTransport))
+            .on_error(move |error, context| {
+                recorded.lock().unwrap().push((
+                    error.kind(),
+                    context.operation.clone(),
+                    context.method.clone(),
+                ));
+            })
+            .build()
+            .await
+            .unwrap();
+
+        assert!(client
+            .get_markets(ListMarketsParams::default())
+            .await
+            .is_err());
+        assert!(client.authorize().await.is_err());
+        assert!(client.unsubscribe("trades/0x00").await.is_err());
+        assert!(client.cancel_order("not-an-address", "1").await.is_err());
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 4);
+        assert_eq!(seen[0].0, ErrorKind::Server);
+        assert_eq!(seen[0].2, Some(reqwest::Method::GET));
+        assert_eq!(seen[1].0, ErrorKind::Auth);
+        assert_eq!(seen[1].1, "authorize");
+        assert_eq!(seen[2].1, "ws_unsubscribe");
+        assert_eq!(seen[3].0, ErrorKind::Validation);
+        assert_eq!(seen[3].1, "user/intent");
+    }
+
+    /// Interceptor recording the org header and status of each call
+    #[derive(Debug, Default)]
+    struct AuditLog {
+        entries: std::sync::Mutex<Vec<(String, Option<String>, Option<u16>)>>,
+    }
+
+    impl RequestInterceptor for Arc<AuditLog> {
+        fn after_response(
+            &self,
+            request: &HttpRequest,
+            response: &Result<HttpResponse>,
+            context: &RequestContext,
+        ) {
+            let org = request
+                .headers
+                .iter()
+                .find(|(name, _)| name == "X-Org")
+                .map(|(_, value)| value.clone());
+            let status = response.as_ref().ok().map(|response| response.status);
+            self.entries
+                .lock()
+                .unwrap()
+                .push((context.path.clone(), org, status));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_wrap_requests() {
+        let audit = Arc::new(AuditLog::default());
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(Arc::new(UnavailableTransport))
+            .interceptor(crate::interceptor::HeaderInjector::new().with_header("X-Org", "desk-1"))
+            .interceptor(audit.clone())
+            .build()
+            .await
+            .unwrap();
+
+        let result = client.get_markets(ListMarketsParams::default()).await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::Server);
+        assert_eq!(
+            *audit.entries.lock().unwrap(),
+            vec![(
+                "market_info".to_string(),
+                Some("desk-1".to_string()),
+                Some(503)
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_walks_pages_until_short_page() {
+        const MARKET: &str = "0x1111111111111111111111111111111111111111";
+        let transport = Arc::new(crate::transport::MockTransport::new());
+        let candles = |from: u64, count: u64| {
+            (from..from + count)
+                .map(|i| {
+                    serde_json::json!({
+                        "market_addr": MARKET,
+                        "timestamp": i * 60_000,
+                        "open": 1, "high": 1, "low": 1, "close": 1, "volume": 1,
+                        "interval": "1m",
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        transport.respond_once("candles", HttpResponse::json(200, &candles(0, 2)).unwrap());
+        transport.respond_once("candles", HttpResponse::json(200, &candles(2, 1)).unwrap());
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(transport.clone())
+            .build()
+            .await
+            .unwrap();
+
+        let params = ListCandlesParams {
+            market_addr: MARKET.to_string(),
+            interval: "1m".to_string(),
+            start_time: None,
+            end_time: None,
+            pagination: Pagination::new(2, 0),
+        };
+        let timestamps: Vec<u64> = client
+            .get_candles_stream(params)
+            .map(|candle| candle.unwrap().timestamp)
+            .collect()
+            .await;
+
+        assert_eq!(timestamps, vec![0, 60_000, 120_000]);
+        let offsets: Vec<String> = transport
+            .requests_to("candles")
+            .iter()
+            .filter_map(|request| {
+                request
+                    .url
+                    .query_pairs()
+                    .find(|(key, _)| key == "offset")
+                    .map(|(_, value)| value.into_owned())
+            })
+            .collect();
+        assert_eq!(offsets, vec!["0", "2"]);
+    }
+
+    #[test]
+    fn test_pagination_advance() {
+        let mut pagination = Pagination::new(10, 0);
+        assert!(pagination.advance(10));
+        assert_eq!(pagination.offset, Some(10));
+        assert!(!pagination.advance(3));
+
+        let mut pagination = Pagination::with_page(1, 10);
+        assert!(pagination.advance(10));
+        assert_eq!(pagination.page, Some(2));
+        assert!(!pagination.advance(0));
+    }
+
+    #[tokio::test]
+    async fn test_request_options_override_timeout_retry_and_headers() {
+        let transport = Arc::new(crate::transport::MockTransport::new());
+        transport.respond_once_status("market_info", 503, "maintenance");
+        transport
+            .respond_json("market_info", &serde_json::json!([]))
+            .unwrap();
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(transport.clone())
+            .build()
+            .await
+            .unwrap();
+
+        let options = RequestOptions::new()
+            .with_timeout(Duration::from_secs(2))
+            .with_retry(RetryPolicy::new(2, Duration::ZERO))
+            .with_header("X-Desk", "mm");
+        let markets = client
+            .with_options(options)
+            .get_markets(ListMarketsParams::default())
+            .await
+            .unwrap();
+        assert!(markets.is_empty());
+
+        let requests = transport.requests_to("market_info");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].timeout, Some(Duration::from_secs(2)));
+        assert!(requests[1]
+            .headers
+            .contains(&("X-Desk".to_string(), "mm".to_string())));
+
+        // The original client keeps its defaults
+        transport.respond_once_status("market_info", 503, "maintenance");
+        assert!(client
+            .get_markets(ListMarketsParams::default())
+            .await
+            .is_err());
+        assert_eq!(transport.requests_to("market_info")[2].timeout, None);
+    }
+
+    #[tokio::test]
+    async fn test_compressed_responses_are_decoded() {
+        use std::io::Write;
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(b"[]").unwrap();
+        let transport = Arc::new(crate::transport::MockTransport::new());
+        transport.respond(
+            "market_info",
+            HttpResponse {
+                status: 200,
+                headers: vec![
+                    ("content-type".to_string(), "application/json".to_string()),
+                    ("content-encoding".to_string(), "gzip".to_string()),
+                ],
+                body: gzip.finish().unwrap(),
+            },
+        );
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(transport)
+            .build()
+            .await
+            .unwrap();
+
+        let markets = client
+            .get_markets(ListMarketsParams::default())
+            .await
+            .unwrap();
+        assert!(markets.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_market_cache_avoids_repeat_requests() {
+        let transport = Arc::new(crate::transport::MockTransport::new());
+        transport
+            .respond_json("market_info", &serde_json::json!([]))
+            .unwrap();
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .market_cache(Duration::from_secs(60))
+            .transport(transport.clone())
+            .build()
+            .await
+            .unwrap();
+
+        client.get_market_by_symbol("BTC-USD").await.unwrap();
+        client.get_market_by_symbol("BTC-USD").await.unwrap();
+        client
+            .get_markets(ListMarketsParams::default())
+            .await
+            .unwrap();
+        assert_eq!(transport.requests_to("market_info").len(), 2);
+
+        client.invalidate_market_cache();
+        client.get_market_by_symbol("BTC-USD").await.unwrap();
+        assert_eq!(transport.requests_to("market_info").len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_cancels_in_flight_requests() {
+        let parent = CancellationToken::new();
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(Arc::new(HangingTransport))
+            .cancellation_token(parent.clone())
+            .build()
+            .await
+            .unwrap();
+
+        let pending = tokio::spawn({
+            let client = client.clone();
+            async move { client.get_markets(ListMarketsParams::default()).await }
+        });
+        tokio::task::yield_now().await;
+        client.shutdown().await.unwrap();
+
+        let result = pending.await.unwrap();
+        assert!(matches!(result, Err(EkidenError::Cancelled)));
+        assert!(client.is_shut_down());
+        assert!(!parent.is_cancelled());
+        assert!(matches!(
+            client.connect_websocket().await,
+            Err(EkidenError::Cancelled)
+        ));
+
+        // Cancelling the parent token shuts down clients built from it
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(Arc::new(HangingTransport))
+            .cancellation_token(parent.clone())
+            .build()
+            .await
+            .unwrap();
+        parent.cancel();
+        assert!(client
+            .get_markets(ListMarketsParams::default())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_metrics_recorder_sees_requests() {
+        let transport = Arc::new(crate::transport::MockTransport::new());
+        transport
+            .respond_json("market_info", &serde_json::json!([]))
+            .unwrap();
+        transport.respond_once_status("announcements", 503, "maintenance");
+        let recorder = Arc::new(crate::metrics::PrometheusRecorder::new());
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(transport.clone())
+            .metrics(recorder.clone())
+            .build()
+            .await
+            .unwrap();
+
+        client
+            .get_markets(ListMarketsParams::default())
+            .await
+            .unwrap();
+        assert!(client
+            .get_announcements(ListAnnouncementsParams::default())
+            .await
+            .is_err());
+
+        let text = recorder.render();
+        assert!(text.contains(
+            r#"ekiden_client_requests_total{endpoint="market_info",method="GET",status="200"} 1"#
+        ));
+        assert!(text.contains(
+            r#"ekiden_client_requests_total{endpoint="announcements",method="GET",status="503"} 1"#
+        ));
+        assert!(text.contains(r#"ekiden_client_response_bytes_total{endpoint="market_info"} 2"#));
+
+        // Paths with ids are recorded by their template
+        transport
+            .respond_json(
+                "user/intent/7",
+                &serde_json::json!({ "seq": 7, "status": "success", "outputs": [] }),
+            )
+            .unwrap();
+        client.set_token("token").await;
+        client.get_intent(7).await.unwrap();
+        let text = recorder.render();
+        assert!(text.contains(r#"endpoint="user/intent/{seq}""#));
+        assert!(!text.contains("user/intent/7"));
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_to_backup_endpoint() {
+        let transport = Arc::new(FlakyPrimaryTransport::default());
+        let config = EkidenConfig::new("http://primary/api/v1")
+            .unwrap()
+            .with_backup_url("http://backup/api/v1")
+            .unwrap();
+        let client = EkidenClientBuilder::new()
+            .config(config)
+            .transport(transport.clone())
+            .build()
+            .await
+            .unwrap();
+
+        client
+            .get_markets(ListMarketsParams::default())
+            .await
+            .unwrap();
+        client
+            .get_markets(ListMarketsParams::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            *transport.hosts.lock().unwrap(),
+            vec!["primary", "backup", "backup"]
+        );
+
+        let health = client.endpoint_health();
+        assert_eq!(health[0].consecutive_failures, 1);
+        assert!(health[1].active);
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_components() {
+        let transport = Arc::new(crate::transport::MockTransport::new());
+        transport
+            .respond_json(
+                "status",
+                &serde_json::json!({
+                    "status": "ok",
+                    "version": "1.4.2",
+                    "components": {
+                        "matching_engine": { "status": "ok" },
+                        "database": { "status": "degraded", "message": "replica lag" },
+                    },
+                }),
+            )
+            .unwrap();
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(transport.clone())
+            .build()
+            .await
+            .unwrap();
+
+        let report = client.health().await.unwrap();
+        assert!(!report.is_ready());
+        assert_eq!(report.unhealthy_components(), vec!["database"]);
+        assert_eq!(report.status.version.as_deref(), Some("1.4.2"));
+        assert!(!report.websocket_connected);
+
+        client.ping().await.unwrap();
+        transport.respond_once_status("status", 503, "maintenance");
+        assert!(client.ping().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_server_time_sets_clock_skew() {
+        let transport = Arc::new(crate::transport::MockTransport::new());
+        let server_now = crate::clock::SystemClock.unix_millis() + 3_600_000;
+        transport
+            .respond_json("time", &serde_json::json!({ "timestamp": server_now }))
+            .unwrap();
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(transport.clone())
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(client.clock_skew_millis(), 0);
+        assert!(matches!(
+            client.start_time_sync(Duration::ZERO),
+            Err(EkidenError::Validation(_))
+        ));
+
+        let time = client.get_server_time().await.unwrap();
+        assert_eq!(time.timestamp, server_now);
+        let skew = client.clock_skew_millis();
+        assert!((3_590_000..=3_600_000).contains(&skew), "skew {}", skew);
+        let ahead = client.server_clock().unix_millis() as i64
+            - crate::clock::SystemClock.unix_millis() as i64;
+        assert!((ahead - skew).abs() < 10_000);
+    }
+
+    #[tokio::test]
+    async fn test_market_without_address_is_rejected() {
+        let transport = Arc::new(crate::transport::MockTransport::new());
+        let market = MarketResponse {
+            addr: String::new(),
+            ..crate::test_utils::market()
+        };
+        transport
+            .respond_json("market_info", &serde_json::json!([market]))
+            .unwrap();
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(transport.clone())
+            .build()
+            .await
+            .unwrap();
+        client.set_token("token").await;
+
+        let err = client.get_all_user_leverage().await.unwrap_err();
+        assert!(matches!(err, EkidenError::Validation(ref message) if message.contains("BTC-USD")));
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_wait_intent_times_out_on_client_clock() {
+        let transport = Arc::new(crate::transport::MockTransport::new());
+        transport
+            .respond_json(
+                "user/intent/7",
+                &serde_json::json!({ "seq": 7, "status": "pending", "outputs": [] }),
+            )
+            .unwrap();
+        let clock = Arc::new(crate::clock::ManualClock::default());
+        let client = EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(transport.clone())
+            .clock(clock.clone())
+            .build()
+            .await
+            .unwrap();
+        client.set_token("token").await;
+
+        let timeout = INTENT_POLL_INTERVAL / 2;
+        let wait = client.wait_intent(7, timeout);
+        let drive = async {
+            // Both the deadline and the next poll are waiting on the clock
+            while clock.pending_sleeps() < 2 {
+                tokio::task::yield_now().await;
+            }
+            clock.advance(timeout);
+        };
+        let (result, ()) = tokio::join!(wait, drive);
+
+        assert!(matches!(result, Err(EkidenError::Timeout)));
+        assert_eq!(transport.requests().len(), 1);
+    }
 }
@@ -0,0 +1,257 @@
+//! Injectable time source.
+//!
+//! Components that read the time or wait take an `Arc<dyn Clock>`, defaulting
+//! to [`SystemClock`]. Tests pass a [`ManualClock`] instead and move time
+//! forward explicitly with [`ManualClock::advance`], which also wakes any
+//! pending [`Clock::sleep`] whose deadline has passed.
+//!
+//! [`SkewedClock`] corrects a local clock by an offset estimated from the
+//! gateway's time, for timestamps the gateway checks against its own clock.
+
+use futures_util::future::BoxFuture;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+
+/// Source of wall-clock time, monotonic time and delays
+pub trait Clock: Send + Sync + Debug {
+    /// Current wall-clock time
+    fn now(&self) -> SystemTime;
+
+    /// Current monotonic instant
+    fn instant(&self) -> Instant;
+
+    /// Wait for `duration`
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+    /// Milliseconds since the Unix epoch
+    fn unix_millis(&self) -> u64 {
+        self.now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default()
+    }
+}
+
+/// The real clock, sleeping with tokio
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Get the default clock
+pub fn system() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+#[derive(Debug)]
+struct ManualState {
+    now: SystemTime,
+    instant: Instant,
+    sleepers: Vec<(Instant, oneshot::Sender<()>)>,
+}
+
+/// A clock that only moves when told to
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    state: Arc<Mutex<ManualState>>,
+}
+
+impl ManualClock {
+    /// Create a clock reading `now`
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ManualState {
+                now,
+                instant: Instant::now(),
+                sleepers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Create a clock reading `millis` since the Unix epoch
+    pub fn from_unix_millis(millis: u64) -> Self {
+        Self::new(UNIX_EPOCH + Duration::from_millis(millis))
+    }
+
+    /// Move time forward, waking sleepers whose deadline has passed
+    pub fn advance(&self, duration: Duration) {
+        let woken = {
+            let mut state = self.state.lock().unwrap();
+            state.now += duration;
+            state.instant += duration;
+            let instant = state.instant;
+            let (due, pending) = state
+                .sleepers
+                .drain(..)
+                .partition::<Vec<_>, _>(|(deadline, _)| *deadline <= instant);
+            state.sleepers = pending;
+            due
+        };
+        for (_, waker) in woken {
+            let _ = waker.send(());
+        }
+    }
+
+    /// Number of sleeps still waiting
+    pub fn pending_sleeps(&self) -> usize {
+        self.state.lock().unwrap().sleepers.len()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(SystemTime::now())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        self.state.lock().unwrap().now
+    }
+
+    fn instant(&self) -> Instant {
+        self.state.lock().unwrap().instant
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        if duration.is_zero() {
+            return Box::pin(std::future::ready(()));
+        }
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut state = self.state.lock().unwrap();
+            let deadline = state.instant + duration;
+            state.sleepers.push((deadline, tx));
+        }
+        Box::pin(async move {
+            let _ = rx.await;
+        })
+    }
+}
+
+/// A clock shifted by the estimated offset to the gateway's clock
+///
+/// The offset is estimated NTP-style: the server reading is assumed to have
+/// been taken halfway through the round trip.
+#[derive(Debug)]
+pub struct SkewedClock {
+    inner: Arc<dyn Clock>,
+    offset_millis: AtomicI64,
+}
+
+impl SkewedClock {
+    /// Wrap `inner` with no offset
+    pub fn new(inner: Arc<dyn Clock>) -> Self {
+        Self {
+            inner,
+            offset_millis: AtomicI64::new(0),
+        }
+    }
+
+    /// Get the local clock being corrected
+    pub fn local(&self) -> &Arc<dyn Clock> {
+        &self.inner
+    }
+
+    /// Milliseconds the gateway's clock is ahead of the local one
+    pub fn offset_millis(&self) -> i64 {
+        self.offset_millis.load(Ordering::Relaxed)
+    }
+
+    /// Set the offset directly
+    pub fn set_offset_millis(&self, offset: i64) {
+        self.offset_millis.store(offset, Ordering::Relaxed);
+    }
+
+    /// Update the offset from a server reading taken between two local readings
+    ///
+    /// Returns the new offset in milliseconds.
+    pub fn observe(&self, server_millis: u64, sent_millis: u64, received_millis: u64) -> i64 {
+        let midpoint = (sent_millis as i128 + received_millis as i128) / 2;
+        let offset = (server_millis as i128 - midpoint) as i64;
+        self.set_offset_millis(offset);
+        offset
+    }
+}
+
+impl Clock for SkewedClock {
+    fn now(&self) -> SystemTime {
+        let now = self.inner.now();
+        let offset = self.offset_millis();
+        let shift = Duration::from_millis(offset.unsigned_abs());
+        if offset >= 0 {
+            now + shift
+        } else {
+            now.checked_sub(shift).unwrap_or(UNIX_EPOCH)
+        }
+    }
+
+    fn instant(&self) -> Instant {
+        self.inner.instant()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        self.inner.sleep(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    #[test]
+    fn test_manual_clock_advances() {
+        let clock = ManualClock::from_unix_millis(1_000);
+        let start = clock.instant();
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(clock.unix_millis(), 1_250);
+        assert_eq!(clock.instant() - start, Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_manual_sleep_wakes_on_advance() {
+        let clock = ManualClock::default();
+        let mut sleep = clock.sleep(Duration::from_secs(10));
+        assert!((&mut sleep).now_or_never().is_none());
+
+        clock.advance(Duration::from_secs(9));
+        assert!((&mut sleep).now_or_never().is_none());
+        assert_eq!(clock.pending_sleeps(), 1);
+
+        clock.advance(Duration::from_secs(1));
+        assert!(sleep.now_or_never().is_some());
+        assert_eq!(clock.pending_sleeps(), 0);
+    }
+
+    #[test]
+    fn test_skewed_clock_applies_offset() {
+        let local = ManualClock::from_unix_millis(10_000);
+        let clock = SkewedClock::new(Arc::new(local.clone()));
+        assert_eq!(clock.unix_millis(), 10_000);
+
+        // Request sent at 10_000, answered at 10_200 with the server 5s behind
+        assert_eq!(clock.observe(5_100, 10_000, 10_200), -5_000);
+        assert_eq!(clock.unix_millis(), 5_000);
+
+        local.advance(Duration::from_millis(500));
+        assert_eq!(clock.unix_millis(), 5_500);
+        assert_eq!(clock.observe(20_600, 10_500, 10_500), 10_100);
+        assert_eq!(clock.unix_millis(), 20_600);
+    }
+}
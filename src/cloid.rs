@@ -0,0 +1,267 @@
+//! Client order ids and the sids they resolve to.
+//!
+//! The gateway assigns an order's sid only when the intent is accepted. Orders
+//! placed with [`OrderOptions::with_client_order_id`](crate::OrderOptions::with_client_order_id)
+//! are tracked by that id from the moment they are sent; the sid is filled in
+//! from the intent response, or from the user channel's order updates if they
+//! arrive first:
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::{OrderOptions, OrderSide};
+//!
+//! let user_addr = client.address().await?.expect("key pair");
+//! client.track_client_orders(&user_addr).await?;
+//! let options = OrderOptions::new().with_client_order_id("grid-7");
+//! client
+//!     .place_limit_order("0x123", OrderSide::Buy, 63_000_000_000, 100_000, options)
+//!     .await?;
+//! let sid = client.client_orders().sid("grid-7");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{EkidenError, Result};
+use crate::types::{OrderResponse, OrderState};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// An order tracked by its client order id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientOrder {
+    pub client_order_id: String,
+    pub market_addr: String,
+    /// Order id assigned by the gateway, once known
+    pub sid: Option<String>,
+    /// Last order status seen, `None` until the gateway reports one
+    pub status: Option<String>,
+}
+
+impl ClientOrder {
+    /// Check if the order may still be waiting for, or resting on, the book
+    pub fn is_open(&self) -> bool {
+        self.status
+            .as_deref()
+            .map_or(true, |status| OrderState::parse(status).is_open())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    orders: HashMap<String, ClientOrder>,
+    by_sid: HashMap<String, String>,
+}
+
+/// Map from client order ids to gateway sids, shared by clones
+#[derive(Debug, Clone, Default)]
+pub struct ClientOrderIds {
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl ClientOrderIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking an order about to be sent
+    ///
+    /// Fails if an open order already uses `client_order_id`.
+    pub fn track(&self, client_order_id: &str, market_addr: &str) -> Result<()> {
+        let mut entries = self.lock();
+        if entries
+            .orders
+            .get(client_order_id)
+            .is_some_and(ClientOrder::is_open)
+        {
+            return Err(EkidenError::validation(format!(
+                "Client order id {} is already in use",
+                client_order_id
+            )));
+        }
+        if let Some(sid) = entries
+            .orders
+            .get(client_order_id)
+            .and_then(|order| order.sid.clone())
+        {
+            entries.by_sid.remove(&sid);
+        }
+        entries.orders.insert(
+            client_order_id.to_string(),
+            ClientOrder {
+                client_order_id: client_order_id.to_string(),
+                market_addr: market_addr.to_string(),
+                sid: None,
+                status: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Record the sid the gateway assigned to a tracked order
+    ///
+    /// A new sid, as given to a replaced order, supersedes the previous one.
+    pub fn resolve(&self, client_order_id: &str, sid: &str) {
+        let mut entries = self.lock();
+        let Entries { orders, by_sid } = &mut *entries;
+        let Some(order) = orders.get_mut(client_order_id) else {
+            return;
+        };
+        if let Some(previous) = order.sid.replace(sid.to_string()) {
+            by_sid.remove(&previous);
+        }
+        by_sid.insert(sid.to_string(), client_order_id.to_string());
+    }
+
+    /// Stop tracking an order, e.g. one whose intent was rejected
+    pub fn forget(&self, client_order_id: &str) {
+        let mut entries = self.lock();
+        if let Some(sid) = entries
+            .orders
+            .remove(client_order_id)
+            .and_then(|order| order.sid)
+        {
+            entries.by_sid.remove(&sid);
+        }
+    }
+
+    /// Reconcile with an order update from the gateway
+    ///
+    /// Updates carrying a client order id resolve it, even if the order was
+    /// placed elsewhere; other updates refresh the status of known sids.
+    pub fn apply(&self, order: &OrderResponse) {
+        let mut entries = self.lock();
+        let Entries { orders, by_sid } = &mut *entries;
+        let Some(client_order_id) = order
+            .client_order_id
+            .clone()
+            .or_else(|| by_sid.get(&order.sid).cloned())
+        else {
+            return;
+        };
+        let tracked = orders
+            .entry(client_order_id.clone())
+            .or_insert_with(|| ClientOrder {
+                client_order_id: client_order_id.clone(),
+                market_addr: order.market_addr.clone(),
+                sid: None,
+                status: None,
+            });
+        if tracked.sid.as_deref() != Some(order.sid.as_str()) {
+            if tracked.sid.is_some() && !order.is_open() {
+                // The order this one replaced closing
+                return;
+            }
+            if let Some(previous) = tracked.sid.replace(order.sid.clone()) {
+                by_sid.remove(&previous);
+            }
+            by_sid.insert(order.sid.clone(), client_order_id);
+        }
+        tracked.status = Some(order.status.clone());
+    }
+
+    /// Get a tracked order
+    pub fn get(&self, client_order_id: &str) -> Option<ClientOrder> {
+        self.lock().orders.get(client_order_id).cloned()
+    }
+
+    /// Get the sid of a tracked order, if the gateway has assigned one
+    pub fn sid(&self, client_order_id: &str) -> Option<String> {
+        self.lock()
+            .orders
+            .get(client_order_id)
+            .and_then(|order| order.sid.clone())
+    }
+
+    /// Get the client order id of a sid
+    pub fn client_order_id(&self, sid: &str) -> Option<String> {
+        self.lock().by_sid.get(sid).cloned()
+    }
+
+    /// Drop orders that are filled, cancelled or otherwise closed
+    pub fn prune(&self) {
+        let mut entries = self.lock();
+        let Entries { orders, by_sid } = &mut *entries;
+        orders.retain(|_, order| {
+            let open = order.is_open();
+            if !open {
+                if let Some(sid) = &order.sid {
+                    by_sid.remove(sid);
+                }
+            }
+            open
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(sid: &str, status: &str, client_order_id: Option<&str>) -> OrderResponse {
+        OrderResponse {
+            sid: sid.to_string(),
+            side: "buy".to_string(),
+            size: 1,
+            price: 1,
+            leverage: 1,
+            order_type: "limit".to_string(),
+            status: status.to_string(),
+            user_addr: "0x1".to_string(),
+            market_addr: "0x2".to_string(),
+            seq: 1,
+            timestamp: 0,
+            client_order_id: client_order_id.map(str::to_string),
+            time_in_force: None,
+            post_only: None,
+            reduce_only: None,
+        }
+    }
+
+    #[test]
+    fn test_update_resolves_before_response() {
+        let ids = ClientOrderIds::new();
+        ids.track("a", "0x2").unwrap();
+        assert_eq!(ids.sid("a"), None);
+        assert!(ids.track("a", "0x2").is_err());
+
+        ids.apply(&update("s1", "placed", Some("a")));
+        assert_eq!(ids.sid("a").as_deref(), Some("s1"));
+        ids.resolve("a", "s1");
+        assert_eq!(ids.client_order_id("s1").as_deref(), Some("a"));
+
+        // Updates without the id are matched by sid
+        ids.apply(&update("s1", "filled", None));
+        assert_eq!(ids.get("a").unwrap().status.as_deref(), Some("filled"));
+        ids.prune();
+        assert!(ids.is_empty());
+        assert_eq!(ids.client_order_id("s1"), None);
+        ids.track("a", "0x2").unwrap();
+    }
+
+    #[test]
+    fn test_replacement_sid_supersedes_old_one() {
+        let ids = ClientOrderIds::new();
+        ids.track("a", "0x2").unwrap();
+        ids.resolve("a", "s1");
+        ids.resolve("a", "s2");
+        assert_eq!(ids.client_order_id("s1"), None);
+        assert_eq!(ids.client_order_id("s2").as_deref(), Some("a"));
+
+        ids.apply(&update("s1", "cancelled", None));
+        ids.apply(&update("s1", "cancelled", Some("a")));
+        assert_eq!(ids.sid("a").as_deref(), Some("s2"));
+        assert!(ids.get("a").unwrap().is_open());
+    }
+}
@@ -0,0 +1,213 @@
+//! REST response wire formats.
+//!
+//! The client always understands JSON. With the `msgpack` or `cbor` feature it
+//! can ask the gateway for a binary encoding through the `Accept` header and
+//! decodes whatever the response's `Content-Type` says, so gateways that only
+//! speak JSON keep working unchanged.
+//!
+//! Bodies compressed with gzip or brotli are inflated by [`decompress`]
+//! according to their `Content-Encoding` before decoding.
+
+use crate::error::{EkidenError, Result};
+use serde::de::DeserializeOwned;
+use std::borrow::Cow;
+use std::io::Read;
+
+/// Encoding requested for REST responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WireFormat {
+    #[default]
+    Json,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl WireFormat {
+    /// Media types of this format, the canonical one first
+    pub fn media_types(&self) -> &'static [&'static str] {
+        match self {
+            WireFormat::Json => &["application/json"],
+            #[cfg(feature = "msgpack")]
+            WireFormat::MessagePack => &[
+                "application/msgpack",
+                "application/x-msgpack",
+                "application/vnd.msgpack",
+            ],
+            #[cfg(feature = "cbor")]
+            WireFormat::Cbor => &["application/cbor"],
+        }
+    }
+
+    /// `Accept` header value preferring this format with JSON as fallback
+    pub fn accept_header(&self) -> String {
+        if *self == WireFormat::Json {
+            return "application/json".to_string();
+        }
+        format!("{}, application/json;q=0.9", self.media_types()[0])
+    }
+
+    /// Detect the format of a response from its `Content-Type`, defaulting to JSON
+    pub fn from_content_type(content_type: Option<&str>) -> Self {
+        let media_type = content_type
+            .and_then(|ct| ct.split(';').next())
+            .map(|ct| ct.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        [
+            #[cfg(feature = "msgpack")]
+            WireFormat::MessagePack,
+            #[cfg(feature = "cbor")]
+            WireFormat::Cbor,
+        ]
+        .into_iter()
+        .find(|format| format.media_types().contains(&media_type.as_str()))
+        .unwrap_or_default()
+    }
+
+    /// Decode a response body in this format
+    pub fn decode<T: DeserializeOwned>(&self, body: &[u8]) -> Result<T> {
+        match self {
+            WireFormat::Json => serde_json::from_slice(body).map_err(EkidenError::Json),
+            #[cfg(feature = "msgpack")]
+            WireFormat::MessagePack => rmp_serde::from_slice(body)
+                .map_err(|e| EkidenError::general(format!("MessagePack decoding error: {}", e))),
+            #[cfg(feature = "cbor")]
+            WireFormat::Cbor => ciborium::from_reader(body)
+                .map_err(|e| EkidenError::general(format!("CBOR decoding error: {}", e))),
+        }
+    }
+}
+
+/// Largest body [`decompress`] inflates to, in bytes
+pub const MAX_DECOMPRESSED_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Undo the `Content-Encoding` of a response body
+///
+/// Bodies without an encoding, or already decoded by the transport, are
+/// returned as they are. Unsupported encodings, and bodies inflating past
+/// [`MAX_DECOMPRESSED_SIZE`], are an error.
+pub fn decompress<'a>(content_encoding: Option<&str>, body: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    decompress_at_most(content_encoding, body, MAX_DECOMPRESSED_SIZE)
+}
+
+fn decompress_at_most<'a>(
+    content_encoding: Option<&str>,
+    body: &'a [u8],
+    limit: u64,
+) -> Result<Cow<'a, [u8]>> {
+    let encoding = content_encoding
+        .map(|encoding| encoding.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let mut decoded = Vec::new();
+    // One byte past the limit tells a body at the limit from a larger one
+    let result = match encoding.as_str() {
+        "" | "identity" => return Ok(Cow::Borrowed(body)),
+        "gzip" | "x-gzip" => flate2::read::GzDecoder::new(body)
+            .take(limit + 1)
+            .read_to_end(&mut decoded),
+        "br" => brotli::Decompressor::new(body, 4096)
+            .take(limit + 1)
+            .read_to_end(&mut decoded),
+        other => {
+            return Err(EkidenError::general(format!(
+                "Unsupported content encoding: {}",
+                other
+            )))
+        }
+    };
+    result.map_err(|e| {
+        EkidenError::general(format!("Failed to decompress {} body: {}", encoding, e))
+    })?;
+    if decoded.len() as u64 > limit {
+        return Err(EkidenError::general(format!(
+            "Decompressed {} body exceeds {} bytes",
+            encoding, limit
+        )));
+    }
+    Ok(Cow::Owned(decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_decompress() {
+        let body = br#"[{"close":6500000}]"#;
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(body).unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert_eq!(decompress(Some("gzip"), &gzip).unwrap().as_ref(), body);
+
+        let mut brotli = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+        brotli.write_all(body).unwrap();
+        let brotli = brotli.into_inner();
+        assert_eq!(decompress(Some("BR"), &brotli).unwrap().as_ref(), body);
+
+        assert!(matches!(decompress(None, body).unwrap(), Cow::Borrowed(_)));
+        assert!(decompress(Some("gzip"), body).is_err());
+        assert!(decompress(Some("zstd"), body).is_err());
+    }
+
+    #[test]
+    fn test_decompress_caps_output() {
+        let body = vec![0u8; 1024];
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&body).unwrap();
+        let gzip = gzip.finish().unwrap();
+
+        assert_eq!(
+            decompress_at_most(Some("gzip"), &gzip, 1024).unwrap().len(),
+            1024
+        );
+        assert!(decompress_at_most(Some("gzip"), &gzip, 1023).is_err());
+    }
+
+    #[test]
+    fn test_unknown_content_type_falls_back_to_json() {
+        assert_eq!(WireFormat::from_content_type(None), WireFormat::Json);
+        assert_eq!(
+            WireFormat::from_content_type(Some("text/plain")),
+            WireFormat::Json
+        );
+        let value: Vec<u64> =
+            WireFormat::from_content_type(Some("application/json; charset=utf-8"))
+                .decode(b"[1,2,3]")
+                .unwrap();
+        assert_eq!(value, vec![1, 2, 3]);
+        assert_eq!(WireFormat::Json.accept_header(), "application/json");
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_round_trip() {
+        let format = WireFormat::from_content_type(Some("application/msgpack"));
+        assert_eq!(format, WireFormat::MessagePack);
+        assert_eq!(
+            format.accept_header(),
+            "application/msgpack, application/json;q=0.9"
+        );
+
+        let market = serde_json::json!({ "symbol": "BTC-USD", "mark_price": 6500000u64 });
+        let bytes = rmp_serde::to_vec_named(&market).unwrap();
+        let decoded: serde_json::Value = format.decode(&bytes).unwrap();
+        assert_eq!(decoded, market);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_round_trip() {
+        let format = WireFormat::from_content_type(Some("application/cbor"));
+        assert_eq!(format, WireFormat::Cbor);
+
+        let market = serde_json::json!({ "symbol": "BTC-USD", "mark_price": 6500000u64 });
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&market, &mut bytes).unwrap();
+        let decoded: serde_json::Value = format.decode(&bytes).unwrap();
+        assert_eq!(decoded, market);
+    }
+}
@@ -1,4 +1,10 @@
+use crate::codec::WireFormat;
 use crate::error::{EkidenError, Result};
+use crate::failover::DEFAULT_FAILBACK_INTERVAL;
+use crate::ratelimit::RateLimiterConfig;
+use crate::tls::TlsConfig;
+use crate::utils::Redacted;
+use crate::ws::{HeartbeatConfig, ReconnectConfig};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use url::Url;
@@ -18,10 +24,38 @@ pub struct EkidenConfig {
     pub max_retries: u32,
     /// Retry delay
     pub retry_delay: Duration,
-    /// Whether to enable request/response logging
+    /// Whether to log requests and responses, with secrets redacted; see [`crate::logging`]
     pub enable_logging: bool,
     /// API version
     pub api_version: String,
+    /// Preferred encoding for REST responses
+    pub wire_format: WireFormat,
+    /// Client-side rate limits for REST requests, disabled when `None`
+    pub rate_limiter: Option<RateLimiterConfig>,
+    /// Proxy for REST requests; the `HTTP(S)_PROXY` variables apply when `None`
+    ///
+    /// WebSocket connections never use it.
+    pub proxy: Option<ProxyConfig>,
+    /// Custom roots and client identity for REST and WebSocket connections
+    pub tls: Option<TlsConfig>,
+    /// Ask for gzip or brotli compressed REST responses
+    pub compression: bool,
+    /// How long market metadata is cached, disabled when `None`
+    pub market_cache_ttl: Option<Duration>,
+    /// API base URLs to fail over to, in order, when `base_url` is unreachable
+    pub backup_urls: Vec<Url>,
+    /// How long to stay on a backup before trying `base_url` again
+    pub failback_interval: Duration,
+    /// WebSocket reconnection after the connection drops, disabled when `None`
+    pub reconnect: Option<ReconnectConfig>,
+    /// WebSocket keepalive pings, disabled when `None`
+    pub heartbeat: Option<HeartbeatConfig>,
+    /// WebSocket connections that channels are spread across; see [`crate::pool`]
+    pub ws_connections: usize,
+    /// Fetch orderbook snapshots over REST when resuming WebSocket channels
+    pub rest_snapshots: bool,
+    /// Number intents with a per-account nonce, for gateways that require one
+    pub intent_nonces: bool,
 }
 
 impl Default for EkidenConfig {
@@ -35,6 +69,19 @@ impl Default for EkidenConfig {
             retry_delay: Duration::from_millis(1000),
             enable_logging: false,
             api_version: "v1".to_string(),
+            wire_format: WireFormat::default(),
+            rate_limiter: None,
+            proxy: None,
+            tls: None,
+            compression: true,
+            market_cache_ttl: None,
+            backup_urls: Vec::new(),
+            failback_interval: DEFAULT_FAILBACK_INTERVAL,
+            reconnect: Some(ReconnectConfig::default()),
+            heartbeat: Some(HeartbeatConfig::default()),
+            ws_connections: 1,
+            rest_snapshots: true,
+            intent_nonces: false,
         }
     }
 }
@@ -102,7 +149,7 @@ impl EkidenConfig {
         self
     }
 
-    /// Enable or disable logging
+    /// Enable or disable request/response logging
     pub fn with_logging(mut self, enable_logging: bool) -> Self {
         self.enable_logging = enable_logging;
         self
@@ -114,8 +161,107 @@ impl EkidenConfig {
         self
     }
 
+    /// Request REST responses in a binary encoding, falling back to JSON
+    /// when the gateway does not offer it
+    pub fn with_wire_format(mut self, wire_format: WireFormat) -> Self {
+        self.wire_format = wire_format;
+        self
+    }
+
+    /// Make REST requests wait for client-side rate limits instead of failing with 429s
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiterConfig) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Route REST requests through a proxy
+    ///
+    /// WebSocket connections are still made directly; tunnel them with a
+    /// custom [`WsConnector`](crate::transport::WsConnector) if needed.
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Use custom TLS roots, client identity or backend
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Enable or disable compressed REST responses
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Cache `get_markets` results for `ttl`
+    pub fn with_market_cache(mut self, ttl: Duration) -> Self {
+        self.market_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Add a backup API base URL, tried after the primary and earlier backups
+    ///
+    /// Its WebSocket URL is derived the same way as for [`EkidenConfig::new`].
+    pub fn with_backup_url<S: AsRef<str>>(mut self, backup_url: S) -> Result<Self> {
+        let backup_url = Url::parse(backup_url.as_ref())?;
+        Self::derive_ws_url(&backup_url)?;
+        self.backup_urls.push(backup_url);
+        Ok(self)
+    }
+
+    /// Set how long to stay on a backup before trying the primary again
+    pub fn with_failback_interval(mut self, failback_interval: Duration) -> Self {
+        self.failback_interval = failback_interval;
+        self
+    }
+
+    /// Set how the WebSocket reconnects after the connection drops
+    pub fn with_reconnect(mut self, reconnect: ReconnectConfig) -> Self {
+        self.reconnect = Some(reconnect);
+        self
+    }
+
+    /// Leave the WebSocket disconnected when the connection drops
+    pub fn without_reconnect(mut self) -> Self {
+        self.reconnect = None;
+        self
+    }
+
+    /// Set how often the WebSocket is pinged and how long a pong may take
+    pub fn with_heartbeat(mut self, heartbeat: HeartbeatConfig) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// Send no keepalive pings
+    pub fn without_heartbeat(mut self) -> Self {
+        self.heartbeat = None;
+        self
+    }
+
+    /// Resume orderbook channels only from snapshots sent over the WebSocket
+    pub fn without_rest_snapshots(mut self) -> Self {
+        self.rest_snapshots = false;
+        self
+    }
+
+    /// Attach a nonce to every intent sent with
+    /// [`send_actions`](crate::EkidenClient::send_actions)
+    pub fn with_intent_nonces(mut self) -> Self {
+        self.intent_nonces = true;
+        self
+    }
+
+    /// Spread WebSocket channels across `connections` sockets, at least one
+    pub fn with_ws_connections(mut self, connections: usize) -> Self {
+        self.ws_connections = connections.max(1);
+        self
+    }
+
     /// Derive WebSocket URL from HTTP URL
-    fn derive_ws_url(base_url: &Url) -> Result<Url> {
+    pub(crate) fn derive_ws_url(base_url: &Url) -> Result<Url> {
         let mut ws_url = base_url.clone();
 
         // Convert HTTP(S) scheme to WS(S)
@@ -141,7 +287,12 @@ impl EkidenConfig {
 
     /// Get the full API URL for a given path
     pub fn api_url(&self, path: &str) -> String {
-        let mut url = self.base_url.clone();
+        Self::join_api_path(&self.base_url, path)
+    }
+
+    /// Append an API path to a base URL
+    pub(crate) fn join_api_path(base_url: &Url, path: &str) -> String {
+        let mut url = base_url.clone();
         let current_path = url.path().trim_end_matches('/');
         let new_path = format!("{}/{}", current_path, path.trim_start_matches('/'));
         url.set_path(&new_path);
@@ -154,6 +305,82 @@ impl EkidenConfig {
     }
 }
 
+/// HTTP or HTTPS proxy for REST requests
+///
+/// Only REST requests go through it. WebSocket connections bypass it.
+#[derive(Clone, PartialEq)]
+pub struct ProxyConfig {
+    /// Proxy URL, e.g. `http://proxy.internal:3128`
+    pub url: Url,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Hosts, domains or IP ranges reached directly, e.g. `localhost` or `.internal`
+    pub no_proxy: Vec<String>,
+}
+
+impl std::fmt::Debug for ProxyConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut url = self.url.clone();
+        if url.password().is_some() {
+            let _ = url.set_password(Some("redacted"));
+        }
+        f.debug_struct("ProxyConfig")
+            .field("url", &url.as_str())
+            .field("username", &self.username)
+            .field("password", &self.password.as_deref().map(Redacted))
+            .field("no_proxy", &self.no_proxy)
+            .finish()
+    }
+}
+
+impl ProxyConfig {
+    /// Create a proxy configuration from an `http` or `https` URL
+    pub fn new<S: AsRef<str>>(url: S) -> Result<Self> {
+        let url = Url::parse(url.as_ref())?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(EkidenError::config(format!(
+                "Unsupported proxy scheme {}, expected http or https",
+                url.scheme()
+            )));
+        }
+        Ok(Self {
+            url,
+            username: None,
+            password: None,
+            no_proxy: Vec::new(),
+        })
+    }
+
+    /// Authenticate to the proxy with basic auth
+    pub fn with_basic_auth<U: Into<String>, P: Into<String>>(
+        mut self,
+        username: U,
+        password: P,
+    ) -> Self {
+        self.username = Some(username.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Bypass the proxy for a host, domain suffix or CIDR range
+    pub fn with_no_proxy<S: Into<String>>(mut self, host: S) -> Self {
+        self.no_proxy.push(host.into());
+        self
+    }
+
+    /// Build the reqwest proxy
+    pub(crate) fn to_reqwest(&self) -> Result<reqwest::Proxy> {
+        let mut proxy = reqwest::Proxy::all(self.url.as_str())?;
+        if let Some(username) = &self.username {
+            proxy = proxy.basic_auth(username, self.password.as_deref().unwrap_or_default());
+        }
+        if !self.no_proxy.is_empty() {
+            proxy = proxy.no_proxy(reqwest::NoProxy::from_string(&self.no_proxy.join(",")));
+        }
+        Ok(proxy)
+    }
+}
+
 /// Environment-specific configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Environment {
@@ -190,6 +417,22 @@ impl Environment {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_proxy_config() {
+        let proxy = ProxyConfig::new("http://proxy.internal:3128")
+            .unwrap()
+            .with_basic_auth("desk", "hunter2")
+            .with_no_proxy("localhost")
+            .with_no_proxy(".internal");
+        assert!(proxy.to_reqwest().is_ok());
+        assert!(!format!("{:?}", proxy).contains("hunter2"));
+
+        let config = EkidenConfig::default().with_proxy(proxy.clone());
+        assert_eq!(config.proxy, Some(proxy));
+
+        assert!(ProxyConfig::new("socks5://proxy.internal:1080").is_err());
+    }
+
     #[test]
     fn test_config_creation() {
         let config = EkidenConfig::default();
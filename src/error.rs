@@ -1,8 +1,21 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, EkidenError>;
 
+/// Longest error message kept from a response body, in characters
+const MAX_ERROR_MESSAGE_CHARS: usize = 1024;
+
+/// Longest unknown error code kept from a response body, in characters
+const MAX_ERROR_CODE_CHARS: usize = 64;
+
+/// Errors returned by the SDK
+///
+/// New variants may be added in minor releases, so matches need a wildcard
+/// arm. Code that only cares about the category of a failure should match on
+/// [`EkidenError::kind`], whose categories are stable.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum EkidenError {
     #[error("HTTP request failed: {0}")]
     Http(#[from] reqwest::Error),
@@ -23,10 +36,17 @@ pub enum EkidenError {
     Config(String),
 
     #[error("Cryptography error: {0}")]
-    Crypto(String),
+    Crypto(#[from] CryptoError),
 
+    /// Fields may be added, so match with `..` and build with [`EkidenError::api`]
     #[error("API error: {status} - {message}")]
-    Api { status: u16, message: String },
+    #[non_exhaustive]
+    Api {
+        status: u16,
+        message: String,
+        /// Parsed JSON error payload, if the body was one
+        body: Option<Box<ApiErrorBody>>,
+    },
 
     #[error("Network error: {0}")]
     Network(String),
@@ -49,14 +69,341 @@ pub enum EkidenError {
     #[error("Connection closed")]
     ConnectionClosed,
 
-    #[error("Rate limit exceeded")]
-    RateLimit,
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    #[error("Event stream on {channel} lagged, missed {missed} events")]
+    Lagged {
+        /// Number of events dropped because the receiver fell behind
+        missed: u64,
+        /// Channel the events were published on
+        channel: String,
+    },
+
+    #[error(
+        "Rate limit exceeded{}",
+        .retry_after.map(|d| format!(", retry after {:?}", d)).unwrap_or_default()
+    )]
+    RateLimit {
+        /// How long to wait before retrying, if the gateway said
+        retry_after: Option<Duration>,
+        /// What the limit applies to, if the gateway said
+        scope: Option<RateLimitScope>,
+    },
 
     #[error("Aptos error: {0}")]
     Aptos(String),
+
+    #[error("Risk limit breached: {0}")]
+    RiskLimit(String),
+
+    #[error("Insufficient margin: required {required}, available {available}")]
+    InsufficientMargin { required: u64, available: u64 },
+
+    #[error("Order rejected: {reason}")]
+    OrderRejected { reason: String },
+
+    #[error(
+        "Position not found{}",
+        .market_addr.as_ref().map(|m| format!(" in market {}", m)).unwrap_or_default()
+    )]
+    PositionNotFound { market_addr: Option<String> },
+
+    #[error(
+        "Market closed{}",
+        .market_addr.as_ref().map(|m| format!(": {}", m)).unwrap_or_default()
+    )]
+    MarketClosed { market_addr: Option<String> },
+}
+
+/// Failures handling keys and signatures
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CryptoError {
+    #[error("Invalid private key: {0}")]
+    InvalidPrivateKey(String),
+
+    #[error("Invalid public key: {0}")]
+    InvalidPublicKey(String),
+
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("Failed to encode {0}")]
+    Encoding(String),
+
+    /// A failure described only by its message
+    #[error("{0}")]
+    Other(String),
+}
+
+/// What a rate limit is counted against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RateLimitScope {
+    /// The caller's IP address
+    Ip,
+    /// The authentication token
+    Token,
+    /// A single endpoint
+    Endpoint,
+}
+
+impl RateLimitScope {
+    /// Parse a scope header value, case-insensitively
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ip" => Some(RateLimitScope::Ip),
+            "token" | "user" | "account" => Some(RateLimitScope::Token),
+            "endpoint" | "route" => Some(RateLimitScope::Endpoint),
+            _ => None,
+        }
+    }
+}
+
+/// Error code reported by the gateway in a JSON error payload
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ApiErrorCode {
+    InsufficientMargin,
+    OrderRejected,
+    PositionNotFound,
+    MarketClosed,
+    MarketPaused,
+    InvalidNonce,
+    InvalidSignature,
+    /// The gateway does not support the requested action
+    UnsupportedAction,
+    /// A code the SDK does not know, lowercased
+    Other(String),
+}
+
+impl ApiErrorCode {
+    /// Parse a gateway error code, case-insensitively
+    pub fn parse(code: &str) -> Self {
+        match code.trim().to_ascii_lowercase().as_str() {
+            "insufficient_margin" => ApiErrorCode::InsufficientMargin,
+            "order_rejected" => ApiErrorCode::OrderRejected,
+            "position_not_found" => ApiErrorCode::PositionNotFound,
+            "market_closed" => ApiErrorCode::MarketClosed,
+            "market_paused" => ApiErrorCode::MarketPaused,
+            "invalid_nonce" => ApiErrorCode::InvalidNonce,
+            "invalid_signature" => ApiErrorCode::InvalidSignature,
+            "unsupported_action" => ApiErrorCode::UnsupportedAction,
+            other => ApiErrorCode::Other(truncate_chars(other.to_string(), MAX_ERROR_CODE_CHARS)),
+        }
+    }
+
+    /// Gateway name of the code
+    pub fn as_str(&self) -> &str {
+        match self {
+            ApiErrorCode::InsufficientMargin => "insufficient_margin",
+            ApiErrorCode::OrderRejected => "order_rejected",
+            ApiErrorCode::PositionNotFound => "position_not_found",
+            ApiErrorCode::MarketClosed => "market_closed",
+            ApiErrorCode::MarketPaused => "market_paused",
+            ApiErrorCode::InvalidNonce => "invalid_nonce",
+            ApiErrorCode::InvalidSignature => "invalid_signature",
+            ApiErrorCode::UnsupportedAction => "unsupported_action",
+            ApiErrorCode::Other(code) => code,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// JSON error payload returned by the gateway
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiErrorBody {
+    pub code: Option<ApiErrorCode>,
+    /// The `message` or `error` string, truncated to a bounded length
+    pub message: Option<String>,
+    /// The `details` value, if any
+    pub details: Option<serde_json::Value>,
+}
+
+impl ApiErrorBody {
+    /// Read a payload from a JSON value; `None` unless it is an object
+    pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+        let object = value.as_object()?;
+        Some(Self {
+            code: object
+                .get("code")
+                .and_then(|code| code.as_str())
+                .map(ApiErrorCode::parse),
+            message: ["message", "error"].iter().find_map(|key| {
+                object
+                    .get(*key)?
+                    .as_str()
+                    .map(|message| truncate_chars(message.to_string(), MAX_ERROR_MESSAGE_CHARS))
+            }),
+            details: object.get("details").cloned(),
+        })
+    }
+}
+
+/// Broad category of an [`EkidenError`]
+///
+/// Existing categories and their [`ErrorKind::as_str`] names are stable:
+/// new error variants are mapped onto them rather than changing them. A new
+/// category may still be added, so matches need a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The request could not be sent or the connection failed
+    Network,
+    /// The operation did not complete in time
+    Timeout,
+    /// The connection was closed
+    ConnectionClosed,
+    /// The gateway is throttling requests
+    RateLimited,
+    /// The gateway failed or is temporarily unavailable (5xx)
+    Server,
+    /// The gateway rejected the request (4xx)
+    Rejected,
+    /// Credentials are missing, invalid or expired
+    Auth,
+    /// Input failed local validation
+    Validation,
+    /// A response or message could not be decoded
+    Decode,
+    /// The client is misconfigured
+    Config,
+    /// Signing or key handling failed
+    Crypto,
+    /// An on-chain operation failed
+    Chain,
+    /// A risk limit blocked the request
+    RiskLimit,
+    /// A subscriber fell behind and events were dropped
+    Lagged,
+    /// The client was shut down before the operation completed
+    Cancelled,
+    /// Anything else
+    Other,
+}
+
+impl ErrorKind {
+    /// Stable snake_case name, e.g. for metrics labels or logs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Network => "network",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::ConnectionClosed => "connection_closed",
+            ErrorKind::RateLimited => "rate_limited",
+            ErrorKind::Server => "server",
+            ErrorKind::Rejected => "rejected",
+            ErrorKind::Auth => "auth",
+            ErrorKind::Validation => "validation",
+            ErrorKind::Decode => "decode",
+            ErrorKind::Config => "config",
+            ErrorKind::Crypto => "crypto",
+            ErrorKind::Chain => "chain",
+            ErrorKind::RiskLimit => "risk_limit",
+            ErrorKind::Lagged => "lagged",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::Other => "other",
+        }
+    }
+
+    /// Classify an HTTP status code
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ErrorKind::Auth,
+            408 => ErrorKind::Timeout,
+            429 => ErrorKind::RateLimited,
+            501 => ErrorKind::Rejected,
+            500..=599 => ErrorKind::Server,
+            400..=499 => ErrorKind::Rejected,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Whether failures of this kind are transient
+    ///
+    /// A transient failure may succeed if the same operation is retried
+    /// later. Callers still have to decide whether a retry is safe, e.g. a
+    /// timed-out order submission may have reached the gateway.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Network
+                | ErrorKind::Timeout
+                | ErrorKind::ConnectionClosed
+                | ErrorKind::RateLimited
+                | ErrorKind::Server
+        )
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 impl EkidenError {
+    /// Get the category of this error
+    pub fn kind(&self) -> ErrorKind {
+        use tokio_tungstenite::tungstenite::Error as WsError;
+
+        match self {
+            EkidenError::Http(e) if e.is_timeout() => ErrorKind::Timeout,
+            EkidenError::Http(e) if e.is_decode() => ErrorKind::Decode,
+            EkidenError::Http(e) if e.is_builder() => ErrorKind::Config,
+            EkidenError::Http(e) => e.status().map_or(ErrorKind::Network, |status| {
+                ErrorKind::from_status(status.as_u16())
+            }),
+            EkidenError::WebSocket(_) | EkidenError::Network(_) => ErrorKind::Network,
+            EkidenError::Tungstenite(e) => match e {
+                WsError::ConnectionClosed | WsError::AlreadyClosed => ErrorKind::ConnectionClosed,
+                WsError::Utf8(_) | WsError::Capacity(_) => ErrorKind::Decode,
+                WsError::Url(_) => ErrorKind::Config,
+                WsError::Http(response) => ErrorKind::from_status(response.status().as_u16()),
+                _ => ErrorKind::Network,
+            },
+            EkidenError::Json(_) => ErrorKind::Decode,
+            EkidenError::Auth(_) => ErrorKind::Auth,
+            EkidenError::Config(_) | EkidenError::UrlParse(_) => ErrorKind::Config,
+            EkidenError::Crypto(_) => ErrorKind::Crypto,
+            EkidenError::Api { status, .. } => ErrorKind::from_status(*status),
+            EkidenError::Validation(_) => ErrorKind::Validation,
+            EkidenError::Io(e) => match e.kind() {
+                std::io::ErrorKind::TimedOut => ErrorKind::Timeout,
+                std::io::ErrorKind::ConnectionRefused
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::NotConnected
+                | std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::UnexpectedEof
+                | std::io::ErrorKind::Interrupted => ErrorKind::Network,
+                _ => ErrorKind::Other,
+            },
+            EkidenError::General(_) => ErrorKind::Other,
+            EkidenError::Timeout => ErrorKind::Timeout,
+            EkidenError::ConnectionClosed => ErrorKind::ConnectionClosed,
+            EkidenError::Cancelled => ErrorKind::Cancelled,
+            EkidenError::Lagged { .. } => ErrorKind::Lagged,
+            EkidenError::RateLimit { .. } => ErrorKind::RateLimited,
+            EkidenError::Aptos(_) => ErrorKind::Chain,
+            EkidenError::RiskLimit(_) => ErrorKind::RiskLimit,
+            EkidenError::InsufficientMargin { .. }
+            | EkidenError::OrderRejected { .. }
+            | EkidenError::PositionNotFound { .. }
+            | EkidenError::MarketClosed { .. } => ErrorKind::Rejected,
+        }
+    }
+
+    /// Whether retrying the failed operation may succeed
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+
     pub fn auth<S: Into<String>>(msg: S) -> Self {
         Self::Auth(msg.into())
     }
@@ -65,12 +412,131 @@ impl EkidenError {
         Self::Config(msg.into())
     }
 
+    #[deprecated(note = "build a typed `CryptoError` and convert it with `From` instead")]
     pub fn crypto<S: Into<String>>(msg: S) -> Self {
-        Self::Crypto(msg.into())
+        Self::Crypto(CryptoError::Other(msg.into()))
     }
 
     pub fn api(status: u16, message: String) -> Self {
-        Self::Api { status, message }
+        Self::Api {
+            status,
+            message,
+            body: None,
+        }
+    }
+
+    /// Build an error from an untrusted response body
+    ///
+    /// A JSON body whose `code` names a known trading failure maps to the
+    /// matching variant. Anything else becomes [`EkidenError::Api`] with the
+    /// `message` or `error` string of a JSON body, or the raw text, truncated
+    /// to a bounded length, and the parsed [`ApiErrorBody`]. Never panics.
+    pub fn from_response_body(status: u16, body: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(body);
+        let value = serde_json::from_str::<serde_json::Value>(&text).ok();
+        let parsed = value.as_ref().and_then(ApiErrorBody::from_value);
+        let message = parsed
+            .as_ref()
+            .and_then(|parsed| parsed.message.clone())
+            .unwrap_or_else(|| truncate_chars(text.trim().to_string(), MAX_ERROR_MESSAGE_CHARS));
+
+        match value
+            .as_ref()
+            .and_then(|v| Self::from_error_code(v, &message))
+        {
+            Some(error) => error,
+            None => Self::Api {
+                status,
+                message,
+                body: parsed.map(Box::new),
+            },
+        }
+    }
+
+    /// Get the parsed gateway error payload of an API error
+    pub fn api_body(&self) -> Option<&ApiErrorBody> {
+        match self {
+            Self::Api { body, .. } => body.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Get the gateway error code, including for errors mapped to domain variants
+    pub fn api_code(&self) -> Option<ApiErrorCode> {
+        match self {
+            Self::Api { body, .. } => body.as_ref()?.code.clone(),
+            Self::InsufficientMargin { .. } => Some(ApiErrorCode::InsufficientMargin),
+            Self::OrderRejected { .. } => Some(ApiErrorCode::OrderRejected),
+            Self::PositionNotFound { .. } => Some(ApiErrorCode::PositionNotFound),
+            Self::MarketClosed { .. } => Some(ApiErrorCode::MarketClosed),
+            _ => None,
+        }
+    }
+
+    /// Map a gateway error `code` to a domain variant
+    fn from_error_code(value: &serde_json::Value, message: &str) -> Option<Self> {
+        let field_str = |key: &str| {
+            value
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(|s| truncate_chars(s.to_string(), MAX_ERROR_MESSAGE_CHARS))
+        };
+        let field_u64 = |key: &str| match value.get(key)? {
+            serde_json::Value::String(s) => s.parse().ok(),
+            v => v.as_u64(),
+        };
+
+        match ApiErrorCode::parse(value.get("code")?.as_str()?) {
+            ApiErrorCode::InsufficientMargin => Some(Self::InsufficientMargin {
+                required: field_u64("required")?,
+                available: field_u64("available")?,
+            }),
+            ApiErrorCode::OrderRejected => Some(Self::OrderRejected {
+                reason: field_str("reason").unwrap_or_else(|| message.to_string()),
+            }),
+            ApiErrorCode::PositionNotFound => Some(Self::PositionNotFound {
+                market_addr: field_str("market_addr"),
+            }),
+            ApiErrorCode::MarketClosed => Some(Self::MarketClosed {
+                market_addr: field_str("market_addr"),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Build a rate limit error from `Retry-After` and scope header values
+    ///
+    /// `retry_after` is a delay in (possibly fractional) seconds or an HTTP
+    /// date, which is measured from the system clock; see
+    /// [`rate_limit_at`](Self::rate_limit_at).
+    pub fn rate_limit(retry_after: Option<&str>, scope: Option<&str>) -> Self {
+        Self::rate_limit_at(retry_after, scope, SystemTime::now())
+    }
+
+    /// Build a rate limit error, measuring an HTTP date `Retry-After` from `now`
+    ///
+    /// Dates in the past mean retrying right away. Malformed values are ignored.
+    pub fn rate_limit_at(retry_after: Option<&str>, scope: Option<&str>, now: SystemTime) -> Self {
+        let retry_after = retry_after
+            .map(str::trim)
+            .and_then(|value| match value.parse::<f64>() {
+                Ok(secs) => Duration::try_from_secs_f64(secs).ok(),
+                Err(_) => {
+                    parse_http_date(value).map(|date| date.duration_since(now).unwrap_or_default())
+                }
+            });
+        Self::RateLimit {
+            retry_after,
+            scope: scope.and_then(RateLimitScope::parse),
+        }
+    }
+
+    /// Get the delay requested by a rate limit error
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimit { retry_after, .. } => *retry_after,
+            _ => None,
+        }
     }
 
     pub fn network<S: Into<String>>(msg: S) -> Self {
@@ -88,3 +554,214 @@ impl EkidenError {
         Self::Aptos(msg.into())
     }
 }
+
+/// Truncate to at most `max` characters, marking the cut with an ellipsis
+fn truncate_chars(mut text: String, max: usize) -> String {
+    if let Some((cut, _)) = text.char_indices().nth(max) {
+        text.truncate(cut);
+        text.push('…');
+    }
+    text
+}
+
+/// Parse an HTTP date in its preferred IMF-fixdate form,
+/// e.g. `Wed, 21 Oct 2015 07:28:00 GMT`
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [weekday, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    if !weekday.ends_with(',') {
+        return None;
+    }
+    let day: u64 = day.parse().ok().filter(|day| (1..=31).contains(day))?;
+    let month = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ]
+    .iter()
+    .position(|name| name == month)? as u64
+        + 1;
+    let year: u64 = year.parse().ok().filter(|year| *year >= 1970)?;
+    let clock: Vec<u64> = time
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let [hour, minute, second] = clock.as_slice() else {
+        return None;
+    };
+    if *hour > 23 || *minute > 59 || *second > 60 {
+        return None;
+    }
+
+    // Days since the epoch of a proleptic Gregorian date, with years starting
+    // in March so the leap day comes last
+    let shifted = if month <= 2 { year - 1 } else { year };
+    let (era, year_of_era) = (shifted / 400, shifted % 400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146_097 + day_of_era).checked_sub(719_468)?;
+
+    let secs = days
+        .checked_mul(86_400)?
+        .checked_add(hour * 3_600 + minute * 60 + second)?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_classification() {
+        assert!(EkidenError::Timeout.is_retryable());
+        assert!(EkidenError::rate_limit(None, None).is_retryable());
+        assert!(EkidenError::network("reset").is_retryable());
+        assert!(EkidenError::api(503, "maintenance".to_string()).is_retryable());
+        assert!(EkidenError::api(429, "slow down".to_string()).is_retryable());
+
+        assert!(!EkidenError::api(400, "bad price".to_string()).is_retryable());
+        assert!(!EkidenError::validation("bad address").is_retryable());
+        assert!(!EkidenError::RiskLimit("paused".to_string()).is_retryable());
+        assert!(!EkidenError::Cancelled.is_retryable());
+        assert_eq!(
+            EkidenError::api(401, "expired".to_string()).kind(),
+            ErrorKind::Auth
+        );
+        assert_eq!(
+            EkidenError::Io(std::io::ErrorKind::ConnectionReset.into()).kind(),
+            ErrorKind::Network
+        );
+        assert_eq!(ErrorKind::RateLimited.to_string(), "rate_limited");
+    }
+
+    #[test]
+    fn test_error_body_parsing() {
+        let err = EkidenError::from_response_body(503, br#"{"error":"maintenance"}"#);
+        assert!(
+            matches!(err, EkidenError::Api { status: 503, ref message, .. } if message == "maintenance")
+        );
+
+        let err = EkidenError::from_response_body(502, b"\xff\xfe bad gateway ");
+        assert!(
+            matches!(err, EkidenError::Api { ref message, .. } if message.ends_with("bad gateway"))
+        );
+
+        let long = "é".repeat(5000);
+        match EkidenError::from_response_body(500, long.as_bytes()) {
+            EkidenError::Api { message, .. } => {
+                assert_eq!(message.chars().count(), MAX_ERROR_MESSAGE_CHARS + 1)
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_domain_errors_from_body() {
+        let err = EkidenError::from_response_body(
+            400,
+            br#"{"code":"insufficient_margin","required":"1500","available":1200}"#,
+        );
+        assert!(matches!(
+            err,
+            EkidenError::InsufficientMargin {
+                required: 1500,
+                available: 1200
+            }
+        ));
+
+        let err = EkidenError::from_response_body(
+            400,
+            br#"{"code":"ORDER_REJECTED","message":"post-only would cross"}"#,
+        );
+        assert!(
+            matches!(err, EkidenError::OrderRejected { ref reason } if reason == "post-only would cross")
+        );
+
+        let err = EkidenError::from_response_body(409, br#"{"code":"market_closed"}"#);
+        assert!(matches!(
+            err,
+            EkidenError::MarketClosed { market_addr: None }
+        ));
+        assert!(!err.is_retryable());
+
+        // Unknown codes and incomplete payloads stay generic
+        let err = EkidenError::from_response_body(400, br#"{"code":"insufficient_margin"}"#);
+        assert!(matches!(err, EkidenError::Api { status: 400, .. }));
+        assert_eq!(err.api_code(), Some(ApiErrorCode::InsufficientMargin));
+    }
+
+    #[test]
+    fn test_api_error_body() {
+        let err = EkidenError::from_response_body(
+            409,
+            br#"{"code":"MARKET_PAUSED","message":"BTC-USD paused","details":{"until":1700000000}}"#,
+        );
+        assert_eq!(err.to_string(), "API error: 409 - BTC-USD paused");
+        assert_eq!(err.api_code(), Some(ApiErrorCode::MarketPaused));
+        let body = err.api_body().unwrap();
+        assert_eq!(
+            body.details,
+            Some(serde_json::json!({ "until": 1700000000 }))
+        );
+
+        let err = EkidenError::from_response_body(400, br#"{"code":"nonce_reused"}"#);
+        assert_eq!(
+            err.api_code(),
+            Some(ApiErrorCode::Other("nonce_reused".to_string()))
+        );
+
+        let err = EkidenError::from_response_body(502, b"bad gateway");
+        assert!(err.api_body().is_none());
+        assert_eq!(err.api_code(), None);
+    }
+
+    #[test]
+    fn test_rate_limit_headers() {
+        let err = EkidenError::rate_limit(Some("1.5"), Some("Token"));
+        assert_eq!(err.retry_after(), Some(Duration::from_millis(1500)));
+        assert!(matches!(
+            err,
+            EkidenError::RateLimit {
+                scope: Some(RateLimitScope::Token),
+                ..
+            }
+        ));
+        assert_eq!(err.to_string(), "Rate limit exceeded, retry after 1.5s");
+
+        let date = Some("Wed, 21 Oct 2015 07:28:00 GMT");
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let err = EkidenError::rate_limit_at(date, Some("bogus"), at(1_445_412_470));
+        assert!(matches!(
+            err,
+            EkidenError::RateLimit {
+                retry_after: Some(_),
+                scope: None
+            }
+        ));
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(10)));
+        assert_eq!(
+            EkidenError::rate_limit_at(date, None, at(1_445_412_490)).retry_after(),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            EkidenError::rate_limit(Some("Wed, 32 Oct 2015 07:28:00 GMT"), None).retry_after(),
+            None
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT"),
+            Some(at(1_709_164_800))
+        );
+        assert_eq!(
+            EkidenError::rate_limit(Some("-1"), None).retry_after(),
+            None
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_crypto_constructor() {
+        let err = EkidenError::crypto("bad key");
+        assert_eq!(err.kind(), ErrorKind::Crypto);
+        assert_eq!(err.to_string(), "Cryptography error: bad key");
+    }
+}
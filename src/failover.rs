@@ -0,0 +1,234 @@
+//! Failover between a primary gateway and backups.
+//!
+//! With backup URLs on [`EkidenConfig`], the client sends requests to the
+//! first healthy endpoint. A connection failure, or a timeout of a request
+//! that is safe to repeat, marks the endpoint unhealthy and the request is
+//! retried on the next one. While a backup is active the primary is tried
+//! again every failback interval:
+//!
+//! ```rust,no_run
+//! # async fn run() -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::{EkidenClient, EkidenConfig};
+//! use std::time::Duration;
+//!
+//! let config = EkidenConfig::production()?
+//!     .with_backup_url("https://api-eu.ekiden.fi/api/v1")?
+//!     .with_failback_interval(Duration::from_secs(30));
+//! let client = EkidenClient::new(config)?;
+//! for endpoint in client.endpoint_health() {
+//!     println!("{} active={} failures={}", endpoint.url, endpoint.active, endpoint.consecutive_failures);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Intents are never repeated after a timeout, since the first attempt may
+//! have reached the gateway.
+
+use crate::clock::Clock;
+use crate::config::EkidenConfig;
+use crate::error::EkidenError;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use url::Url;
+
+/// Default time on a backup before the primary is tried again
+pub const DEFAULT_FAILBACK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Health of one endpoint as seen by the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointHealth {
+    /// API base URL
+    pub url: Url,
+    /// Whether requests currently go to this endpoint
+    pub active: bool,
+    /// Failures since the last successful request
+    pub consecutive_failures: u32,
+    /// When the endpoint last failed
+    pub last_failure: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct State {
+    active: usize,
+    switched_at: Instant,
+    failures: Vec<(u32, Option<Instant>)>,
+}
+
+/// Ordered API endpoints with the currently active one
+#[derive(Debug)]
+pub struct Endpoints {
+    base_urls: Vec<Url>,
+    ws_urls: Vec<Url>,
+    failback_interval: Duration,
+    clock: Arc<dyn Clock>,
+    state: Mutex<State>,
+}
+
+impl Endpoints {
+    /// Create endpoints from the config's base URL followed by its backups
+    ///
+    /// Backups whose WebSocket URL cannot be derived use the config's `ws_url`.
+    pub fn new(config: &EkidenConfig, clock: Arc<dyn Clock>) -> Self {
+        let mut base_urls = vec![config.base_url.clone()];
+        let mut ws_urls = vec![config.ws_url.clone()];
+        for backup in &config.backup_urls {
+            base_urls.push(backup.clone());
+            ws_urls.push(
+                EkidenConfig::derive_ws_url(backup).unwrap_or_else(|_| config.ws_url.clone()),
+            );
+        }
+        let now = clock.instant();
+        Self {
+            state: Mutex::new(State {
+                active: 0,
+                switched_at: now,
+                failures: vec![(0, None); base_urls.len()],
+            }),
+            base_urls,
+            ws_urls,
+            failback_interval: config.failback_interval,
+            clock,
+        }
+    }
+
+    /// Get the number of endpoints
+    pub fn len(&self) -> usize {
+        self.base_urls.len()
+    }
+
+    /// Check if there are no endpoints; never true
+    pub fn is_empty(&self) -> bool {
+        self.base_urls.is_empty()
+    }
+
+    /// Get the index of the endpoint to use, falling back to the primary when due
+    pub fn active(&self) -> usize {
+        let now = self.clock.instant();
+        let mut state = self.state.lock().unwrap();
+        if state.active != 0
+            && now.saturating_duration_since(state.switched_at) >= self.failback_interval
+        {
+            info!("Trying primary endpoint {} again", self.base_urls[0]);
+            state.active = 0;
+            state.switched_at = now;
+        }
+        state.active
+    }
+
+    /// Get the API URL for `path` on an endpoint
+    pub fn api_url(&self, index: usize, path: &str) -> String {
+        EkidenConfig::join_api_path(&self.base_urls[index], path)
+    }
+
+    /// Get the WebSocket URL of an endpoint
+    pub fn websocket_url(&self, index: usize) -> &Url {
+        &self.ws_urls[index]
+    }
+
+    /// Record a successful request to an endpoint
+    pub fn report_success(&self, index: usize) {
+        self.state.lock().unwrap().failures[index].0 = 0;
+    }
+
+    /// Record a failed connection to an endpoint, moving on from it if active
+    pub fn report_failure(&self, index: usize) {
+        let now = self.clock.instant();
+        let mut state = self.state.lock().unwrap();
+        let failures = &mut state.failures[index];
+        failures.0 += 1;
+        failures.1 = Some(now);
+        if state.active == index && self.base_urls.len() > 1 {
+            state.active = (index + 1) % self.base_urls.len();
+            state.switched_at = now;
+            warn!(
+                "Endpoint {} failed, switching to {}",
+                self.base_urls[index], self.base_urls[state.active]
+            );
+        }
+    }
+
+    /// Get the health of every endpoint, primary first
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        let state = self.state.lock().unwrap();
+        self.base_urls
+            .iter()
+            .zip(&state.failures)
+            .enumerate()
+            .map(|(index, (url, (failures, last_failure)))| EndpointHealth {
+                url: url.clone(),
+                active: index == state.active,
+                consecutive_failures: *failures,
+                last_failure: *last_failure,
+            })
+            .collect()
+    }
+}
+
+/// Whether a transport error means the request may be retried on another endpoint
+///
+/// Connection failures never reached the gateway. Timeouts only qualify for
+/// methods that are safe to repeat.
+pub(crate) fn should_fail_over(error: &EkidenError, method: &reqwest::Method) -> bool {
+    match error {
+        EkidenError::Http(e) if e.is_connect() => true,
+        EkidenError::Http(e) if e.is_timeout() => method.is_safe(),
+        EkidenError::Timeout => method.is_safe(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn test_fails_over_and_back() {
+        let clock = ManualClock::default();
+        let config = EkidenConfig::new("https://primary.example.com/api/v1")
+            .unwrap()
+            .with_backup_url("https://backup.example.com/api/v1")
+            .unwrap()
+            .with_failback_interval(Duration::from_secs(60));
+        let endpoints = Endpoints::new(&config, Arc::new(clock.clone()));
+        assert_eq!(endpoints.len(), 2);
+        assert_eq!(
+            endpoints.websocket_url(1).as_str(),
+            "wss://backup.example.com/ws"
+        );
+
+        assert_eq!(endpoints.active(), 0);
+        endpoints.report_failure(0);
+        assert_eq!(endpoints.active(), 1);
+        assert_eq!(
+            endpoints.api_url(1, "orders"),
+            "https://backup.example.com/api/v1/orders"
+        );
+        let health = endpoints.health();
+        assert_eq!(health[0].consecutive_failures, 1);
+        assert!(health[1].active);
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(endpoints.active(), 0);
+        endpoints.report_success(0);
+        assert_eq!(endpoints.health()[0].consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_timeouts_fail_over_only_when_safe() {
+        assert!(should_fail_over(
+            &EkidenError::Timeout,
+            &reqwest::Method::GET
+        ));
+        assert!(!should_fail_over(
+            &EkidenError::Timeout,
+            &reqwest::Method::POST
+        ));
+        assert!(!should_fail_over(
+            &EkidenError::api(503, "maintenance".to_string()),
+            &reqwest::Method::GET
+        ));
+    }
+}
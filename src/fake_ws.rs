@@ -0,0 +1,798 @@
+//! Scriptable WebSocket server for subscription tests.
+//!
+//! Enabled with the `mock` feature. [`FakeWsServer`] speaks the
+//! [`WsRequest`]/[`WsResponse`] protocol over a local tokio-tungstenite
+//! listener. Every connection answers pings and (un)subscriptions on its own;
+//! on top of that each new connection runs the next queued [`Script`], which
+//! can push events, wait for subscriptions, pause, or drop the connection, so
+//! reconnection, sequence gaps and local orderbook handling can be tested
+//! without a gateway:
+//!
+//! ```rust,no_run
+//! # async fn run(snapshot: ekiden_rust_sdk::WsEvent, update: ekiden_rust_sdk::WsEvent) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::fake_ws::{FakeWsServer, Script};
+//! use ekiden_rust_sdk::ws::WebSocketClient;
+//!
+//! let server = FakeWsServer::start().await?;
+//! let channel = "orderbook/0x1111111111111111111111111111111111111111";
+//! server
+//!     .push_script(
+//!         Script::new()
+//!             .wait_for_subscribe(channel)
+//!             .event(channel, snapshot)
+//!             .event(channel, update)
+//!             .disconnect(),
+//!     )
+//!     .await;
+//!
+//! let mut ws = WebSocketClient::new(server.url());
+//! ws.connect().await?;
+//! let mut events = ws.subscribe(channel).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Result;
+use crate::types::{WsEvent, WsRequest, WsResponse};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tracing::debug;
+use url::Url;
+
+/// A single scripted action
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Send a protocol message
+    Send(WsResponse),
+    /// Send a raw text frame, e.g. malformed JSON
+    Raw(String),
+    /// Wait until the client has subscribed to a channel
+    WaitForSubscribe(String),
+    /// Keep serving requests for a while before the next step
+    Sleep(Duration),
+    /// Send a close frame and end the connection
+    Close,
+    /// Drop the TCP connection without a close frame
+    Disconnect,
+}
+
+/// Steps run in order on one connection
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    steps: Vec<Step>,
+}
+
+impl Script {
+    /// Create an empty script
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a step
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Send an event on a channel
+    pub fn event<S: Into<String>>(self, channel: S, event: WsEvent) -> Self {
+        self.step(Step::Send(WsResponse::Event {
+            channel: channel.into(),
+            seq: None,
+            data: event,
+        }))
+    }
+
+    /// Send several events on a channel
+    pub fn events<S: Into<String>, I: IntoIterator<Item = WsEvent>>(
+        self,
+        channel: S,
+        events: I,
+    ) -> Self {
+        let channel = channel.into();
+        events
+            .into_iter()
+            .fold(self, |script, event| script.event(channel.clone(), event))
+    }
+
+    /// Send a protocol error message
+    pub fn error<S: Into<String>>(self, message: S) -> Self {
+        self.step(Step::Send(WsResponse::Error {
+            message: message.into(),
+            id: None,
+        }))
+    }
+
+    /// Send a raw text frame
+    pub fn raw<S: Into<String>>(self, text: S) -> Self {
+        self.step(Step::Raw(text.into()))
+    }
+
+    /// Wait until the client subscribes to a channel
+    pub fn wait_for_subscribe<S: Into<String>>(self, channel: S) -> Self {
+        self.step(Step::WaitForSubscribe(channel.into()))
+    }
+
+    /// Pause before the next step
+    pub fn sleep(self, duration: Duration) -> Self {
+        self.step(Step::Sleep(duration))
+    }
+
+    /// Close the connection cleanly
+    pub fn close(self) -> Self {
+        self.step(Step::Close)
+    }
+
+    /// Drop the connection abruptly
+    pub fn disconnect(self) -> Self {
+        self.step(Step::Disconnect)
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    scripts: Mutex<VecDeque<Script>>,
+    requests: Mutex<Vec<WsRequest>>,
+    rejected: Mutex<HashMap<String, String>>,
+    connections: AtomicUsize,
+    events: broadcast::Sender<(String, WsEvent)>,
+    tasks: std::sync::Mutex<Vec<JoinHandle<()>>>,
+}
+
+/// A local WebSocket server running scripted sessions
+#[derive(Debug)]
+pub struct FakeWsServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+}
+
+impl FakeWsServer {
+    /// Bind to an ephemeral local port and start accepting connections
+    pub async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (events, _) = broadcast::channel(1000);
+        let shared = Arc::new(Shared {
+            scripts: Mutex::new(VecDeque::new()),
+            requests: Mutex::new(Vec::new()),
+            rejected: Mutex::new(HashMap::new()),
+            connections: AtomicUsize::new(0),
+            events,
+            tasks: std::sync::Mutex::new(Vec::new()),
+        });
+
+        let accept = tokio::spawn(accept_loop(listener, shared.clone()));
+        shared.tasks.lock().unwrap().push(accept);
+        Ok(Self { addr, shared })
+    }
+
+    /// Get the bound address
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Get the WebSocket URL
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("ws://{}/ws", self.addr)).expect("valid local URL")
+    }
+
+    /// Queue a script for the next connection that has none
+    pub async fn push_script(&self, script: Script) {
+        self.shared.scripts.lock().await.push_back(script);
+    }
+
+    /// Answer subscriptions to a channel with an error instead of an ack
+    pub async fn reject<S: Into<String>, M: Into<String>>(&self, channel: S, message: M) {
+        self.shared
+            .rejected
+            .lock()
+            .await
+            .insert(channel.into(), message.into());
+    }
+
+    /// Send an event to every live connection subscribed to the channel
+    pub fn push_event<S: Into<String>>(&self, channel: S, event: WsEvent) {
+        let _ = self.shared.events.send((channel.into(), event));
+    }
+
+    /// Get every request received so far, across connections
+    pub async fn requests(&self) -> Vec<WsRequest> {
+        self.shared.requests.lock().await.clone()
+    }
+
+    /// Get the number of connections accepted so far
+    pub fn connections(&self) -> usize {
+        self.shared.connections.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for FakeWsServer {
+    fn drop(&mut self) {
+        for task in self.shared.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+}
+
+async fn accept_loop(listener: TcpListener, shared: Arc<Shared>) {
+    while let Ok((stream, peer)) = listener.accept().await {
+        debug!("Fake WebSocket server accepted {}", peer);
+        let session = tokio::spawn(run_session(stream, shared.clone()));
+        shared.tasks.lock().unwrap().push(session);
+    }
+}
+
+async fn run_session(stream: TcpStream, shared: Arc<Shared>) {
+    let ws = match tokio_tungstenite::accept_async(stream).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            debug!("Fake WebSocket handshake failed: {}", e);
+            return;
+        }
+    };
+    shared.connections.fetch_add(1, Ordering::SeqCst);
+    let script = shared.scripts.lock().await.pop_front().unwrap_or_default();
+    let events = shared.events.subscribe();
+    let (sink, stream) = ws.split();
+    let mut session = Session {
+        sink,
+        stream,
+        events,
+        subscribed: HashSet::new(),
+        shared,
+    };
+
+    for step in script.steps {
+        let open = match step {
+            Step::Send(response) => session.send(&response).await,
+            Step::Raw(text) => session.sink.send(Message::Text(text.into())).await.is_ok(),
+            Step::WaitForSubscribe(channel) => {
+                let mut open = true;
+                while open && !session.subscribed.contains(&channel) {
+                    open = session.serve_once().await;
+                }
+                open
+            }
+            Step::Sleep(duration) => {
+                let deadline = tokio::time::sleep(duration);
+                tokio::pin!(deadline);
+                loop {
+                    tokio::select! {
+                        _ = &mut deadline => break true,
+                        open = session.serve_once() => if !open { break false },
+                    }
+                }
+            }
+            Step::Close => {
+                let _ = session.sink.send(Message::Close(None)).await;
+                false
+            }
+            Step::Disconnect => false,
+        };
+        if !open {
+            return;
+        }
+    }
+
+    while session.serve_once().await {}
+}
+
+type ServerSink = SplitSink<WebSocketStream<TcpStream>, Message>;
+type ServerStream = SplitStream<WebSocketStream<TcpStream>>;
+
+struct Session {
+    sink: ServerSink,
+    stream: ServerStream,
+    events: broadcast::Receiver<(String, WsEvent)>,
+    subscribed: HashSet<String>,
+    shared: Arc<Shared>,
+}
+
+impl Session {
+    async fn send(&mut self, response: &WsResponse) -> bool {
+        let text = match serde_json::to_string(response) {
+            Ok(text) => text,
+            Err(_) => return true,
+        };
+        self.sink.send(Message::Text(text.into())).await.is_ok()
+    }
+
+    /// Handle one incoming frame or pushed event; false once the connection is gone
+    async fn serve_once(&mut self) -> bool {
+        tokio::select! {
+            frame = self.stream.next() => match frame {
+                Some(Ok(Message::Text(text))) => self.handle_request(text.as_str()).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => false,
+                Some(Ok(_)) => true,
+            },
+            event = self.events.recv() => match event {
+                Ok((channel, data)) if self.subscribed.contains(&channel) => {
+                    self.send(&WsResponse::Event {
+                        channel,
+                        seq: None,
+                        data,
+                    })
+                    .await
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => true,
+                Err(broadcast::error::RecvError::Closed) => false,
+            },
+        }
+    }
+
+    async fn handle_request(&mut self, text: &str) -> bool {
+        let request: WsRequest = match serde_json::from_str(text) {
+            Ok(request) => request,
+            Err(e) => {
+                return self
+                    .send(&WsResponse::Error {
+                        message: format!("Invalid request: {}", e),
+                        id: None,
+                    })
+                    .await;
+            }
+        };
+        self.shared.requests.lock().await.push(request.clone());
+
+        let response = match request {
+            WsRequest::Ping { id } => WsResponse::Pong { id },
+            WsRequest::Subscribe { channel, id } => {
+                match self.shared.rejected.lock().await.get(&channel) {
+                    Some(message) => WsResponse::Error {
+                        message: message.clone(),
+                        id,
+                    },
+                    None => {
+                        self.subscribed.insert(channel.clone());
+                        WsResponse::Subscribed { channel, id }
+                    }
+                }
+            }
+            WsRequest::Unsubscribe { channel, id } => {
+                self.subscribed.remove(&channel);
+                WsResponse::Unsubscribed { channel, id }
+            }
+        };
+        self.send(&response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EkidenError;
+    use crate::types::OrderbookLevel;
+    use crate::ws::{
+        channels, Channel, ConnectionStatus, LagPolicy, ReconnectConfig, SnapshotSource,
+        SubscriptionOptions, WebSocketClient,
+    };
+
+    const CHANNEL: &str = "orderbook/0x1111111111111111111111111111111111111111";
+
+    fn snapshot(price: u64) -> WsEvent {
+        WsEvent::OrderbookSnapshot {
+            market_addr: "0x1111111111111111111111111111111111111111".to_string(),
+            bids: vec![OrderbookLevel { price, size: 10 }],
+            asks: vec![],
+            timestamp: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_script_runs_after_subscribe() {
+        let server = FakeWsServer::start().await.unwrap();
+        server
+            .push_script(
+                Script::new()
+                    .wait_for_subscribe(CHANNEL)
+                    .events(CHANNEL, [snapshot(100), snapshot(101)]),
+            )
+            .await;
+
+        let mut ws = WebSocketClient::new(server.url());
+        ws.connect().await.unwrap();
+        let mut events = ws.subscribe(CHANNEL).await.unwrap();
+
+        for price in [100, 101] {
+            match events.recv().await.unwrap() {
+                WsEvent::OrderbookSnapshot { bids, .. } => assert_eq!(bids[0].price, price),
+                other => panic!("unexpected event {:?}", other),
+            }
+        }
+
+        server.push_event(CHANNEL, snapshot(102));
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            WsEvent::OrderbookSnapshot { .. }
+        ));
+        assert_eq!(server.requests().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_then_reconnect() {
+        let server = FakeWsServer::start().await.unwrap();
+        server
+            .push_script(Script::new().wait_for_subscribe(CHANNEL).disconnect())
+            .await;
+
+        let mut ws = WebSocketClient::new(server.url());
+        ws.connect().await.unwrap();
+        let _events = ws.subscribe(CHANNEL).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while ws.connection_status().await == ConnectionStatus::Connected {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        ws.connect().await.unwrap();
+        ws.ping().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !matches!(server.requests().await.last(), Some(WsRequest::Ping { .. })) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(server.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_and_resubscribes() {
+        let server = FakeWsServer::start().await.unwrap();
+        server
+            .push_script(Script::new().wait_for_subscribe(CHANNEL).disconnect())
+            .await;
+        server
+            .push_script(
+                Script::new()
+                    .wait_for_subscribe(CHANNEL)
+                    .event(CHANNEL, snapshot(200)),
+            )
+            .await;
+
+        let mut ws = WebSocketClient::new(server.url()).with_reconnect(Some(
+            ReconnectConfig::new().with_initial_delay(Duration::from_millis(10)),
+        ));
+        ws.connect().await.unwrap();
+        let mut events = ws.subscribe(CHANNEL).await.unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            event,
+            WsEvent::OrderbookSnapshot { ref bids, .. } if bids[0].price == 200
+        ));
+        assert_eq!(server.connections(), 2);
+        assert_eq!(ws.connection_status().await, ConnectionStatus::Connected);
+        assert!(ws.is_subscribed(CHANNEL).await);
+
+        ws.disconnect().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(server.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sequence_gap_triggers_resync() {
+        let sequenced = |seq, price| {
+            Step::Send(WsResponse::Event {
+                channel: CHANNEL.to_string(),
+                seq: Some(seq),
+                data: snapshot(price),
+            })
+        };
+        let server = FakeWsServer::start().await.unwrap();
+        server
+            .push_script(
+                Script::new()
+                    .wait_for_subscribe(CHANNEL)
+                    .step(sequenced(1, 100))
+                    .step(sequenced(1, 100))
+                    .step(sequenced(3, 102)),
+            )
+            .await;
+
+        let mut ws = WebSocketClient::new(server.url());
+        ws.connect().await.unwrap();
+        let mut events = ws.subscribe(CHANNEL).await.unwrap();
+
+        async fn next(events: &mut broadcast::Receiver<WsEvent>) -> WsEvent {
+            tokio::time::timeout(Duration::from_secs(5), events.recv())
+                .await
+                .unwrap()
+                .unwrap()
+        }
+        assert!(matches!(
+            next(&mut events).await,
+            WsEvent::OrderbookSnapshot { .. }
+        ));
+        match next(&mut events).await {
+            WsEvent::Resync {
+                channel,
+                expected,
+                received,
+            } => {
+                assert_eq!(channel, CHANNEL);
+                assert_eq!((expected, received), (2, 3));
+            }
+            other => panic!("expected resync, got {:?}", other),
+        }
+        assert!(matches!(
+            next(&mut events).await,
+            WsEvent::OrderbookSnapshot { ref bids, .. } if bids[0].price == 102
+        ));
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while server.requests().await.len() < 3 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let requests = server.requests().await;
+        assert!(matches!(
+            &requests[1..],
+            [WsRequest::Unsubscribe { .. }, WsRequest::Subscribe { .. }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_introspection_and_unsubscribe_all() {
+        let market = "0x1111111111111111111111111111111111111111";
+        let candles = channels::candles(market, "1m");
+        let server = FakeWsServer::start().await.unwrap();
+        let mut ws = WebSocketClient::new(server.url());
+        ws.connect().await.unwrap();
+
+        let first = ws.subscribe(CHANNEL).await.unwrap();
+        let _second = first.resubscribe();
+        let blocking = SubscriptionOptions::new().with_lag_policy(LagPolicy::Block);
+        let stream = ws.subscribe_with(&candles, blocking).await.unwrap();
+        assert_eq!(ws.subscriber_count(CHANNEL).await, 2);
+        assert_eq!(ws.subscriber_count(&candles).await, 1);
+
+        let subscriptions = ws.subscriptions().await;
+        assert_eq!(subscriptions.len(), 2);
+        assert_eq!(
+            subscriptions[0].kind,
+            Some(Channel::Candles(market.to_string(), "1m".to_string()))
+        );
+        assert_eq!(
+            subscriptions[1].kind,
+            Some(Channel::Orderbook(market.to_string()))
+        );
+        assert!(subscriptions[1].last_activity.is_some());
+
+        drop(stream);
+        assert_eq!(ws.subscriber_count(&candles).await, 0);
+
+        ws.unsubscribe_all().await.unwrap();
+        assert!(ws.active_subscriptions().await.is_empty());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let unsubscribed = server
+            .requests()
+            .await
+            .into_iter()
+            .filter(|request| matches!(request, WsRequest::Unsubscribe { .. }))
+            .count();
+        assert_eq!(unsubscribed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_shared_subscription_released_with_last_receiver() {
+        let server = FakeWsServer::start().await.unwrap();
+        let mut ws = WebSocketClient::new(server.url());
+        ws.connect().await.unwrap();
+
+        let first = ws.subscribe(CHANNEL).await.unwrap();
+        let mut second = ws.subscribe(CHANNEL).await.unwrap();
+        assert_eq!(ws.subscriber_count(CHANNEL).await, 2);
+        let subscribed = server
+            .requests()
+            .await
+            .into_iter()
+            .filter(|request| matches!(request, WsRequest::Subscribe { .. }))
+            .count();
+        assert_eq!(subscribed, 1);
+
+        // The remaining receiver keeps the channel alive
+        drop(first);
+        server.push_event(CHANNEL, snapshot(100));
+        let event = tokio::time::timeout(Duration::from_secs(5), second.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(event, WsEvent::OrderbookSnapshot { .. }));
+        assert!(ws.is_subscribed(CHANNEL).await);
+
+        drop(second);
+        server.push_event(CHANNEL, snapshot(101));
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while ws.is_subscribed(CHANNEL).await {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(server
+            .requests()
+            .await
+            .iter()
+            .any(|request| matches!(request, WsRequest::Unsubscribe { .. })));
+        ws.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resume_replays_updates_after_fetched_snapshot() {
+        #[derive(Debug)]
+        struct SlowSnapshots;
+
+        impl SnapshotSource for SlowSnapshots {
+            fn orderbook_snapshot<'a>(
+                &'a self,
+                _market_addr: &'a str,
+            ) -> futures_util::future::BoxFuture<'a, Result<WsEvent>> {
+                Box::pin(async {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    Ok(WsEvent::OrderbookSnapshot {
+                        market_addr: "0x1111111111111111111111111111111111111111".to_string(),
+                        bids: vec![OrderbookLevel {
+                            price: 100,
+                            size: 10,
+                        }],
+                        asks: vec![],
+                        timestamp: 10,
+                    })
+                })
+            }
+        }
+
+        let update = |timestamp| WsEvent::OrderbookUpdate {
+            market_addr: "0x1111111111111111111111111111111111111111".to_string(),
+            bids: vec![OrderbookLevel {
+                price: 100,
+                size: timestamp,
+            }],
+            asks: vec![],
+            timestamp,
+        };
+        let server = FakeWsServer::start().await.unwrap();
+        server
+            .push_script(Script::new().wait_for_subscribe(CHANNEL).disconnect())
+            .await;
+        server
+            .push_script(
+                Script::new()
+                    .wait_for_subscribe(CHANNEL)
+                    .events(CHANNEL, [update(9), update(11)]),
+            )
+            .await;
+
+        let mut ws = WebSocketClient::new(server.url())
+            .with_reconnect(Some(
+                ReconnectConfig::new().with_initial_delay(Duration::from_millis(10)),
+            ))
+            .with_snapshot_source(Arc::new(SlowSnapshots));
+        ws.connect().await.unwrap();
+        let mut events = ws.subscribe(CHANNEL).await.unwrap();
+
+        async fn next(events: &mut broadcast::Receiver<WsEvent>) -> WsEvent {
+            tokio::time::timeout(Duration::from_secs(5), events.recv())
+                .await
+                .unwrap()
+                .unwrap()
+        }
+        assert!(matches!(
+            next(&mut events).await,
+            WsEvent::OrderbookSnapshot { timestamp: 10, .. }
+        ));
+        assert!(matches!(
+            next(&mut events).await,
+            WsEvent::OrderbookUpdate { timestamp: 11, .. }
+        ));
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resume_retries_failed_snapshot_fetch() {
+        #[derive(Debug, Default)]
+        struct FlakySnapshots(std::sync::atomic::AtomicUsize);
+
+        impl SnapshotSource for FlakySnapshots {
+            fn orderbook_snapshot<'a>(
+                &'a self,
+                _market_addr: &'a str,
+            ) -> futures_util::future::BoxFuture<'a, Result<WsEvent>> {
+                Box::pin(async {
+                    if self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                        return Err(EkidenError::general("unavailable"));
+                    }
+                    Ok(snapshot(100))
+                })
+            }
+        }
+
+        let server = FakeWsServer::start().await.unwrap();
+        server
+            .push_script(Script::new().wait_for_subscribe(CHANNEL).disconnect())
+            .await;
+        let source = Arc::new(FlakySnapshots::default());
+        let mut ws = WebSocketClient::new(server.url())
+            .with_reconnect(Some(
+                ReconnectConfig::new().with_initial_delay(Duration::from_millis(10)),
+            ))
+            .with_snapshot_source(source.clone());
+        ws.connect().await.unwrap();
+        let mut events = ws.subscribe(CHANNEL).await.unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(event, WsEvent::OrderbookSnapshot { .. }));
+        assert_eq!(source.0.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_quiet_channel_released_after_receivers_drop() {
+        let server = FakeWsServer::start().await.unwrap();
+        let clock = crate::clock::ManualClock::default();
+        let mut ws = WebSocketClient::new(server.url()).with_clock(Arc::new(clock.clone()));
+        ws.connect().await.unwrap();
+        drop(ws.subscribe(CHANNEL).await.unwrap());
+
+        while clock.pending_sleeps() == 0 {
+            tokio::task::yield_now().await;
+        }
+        clock.advance(Duration::from_secs(5));
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !server
+                .requests()
+                .await
+                .iter()
+                .any(|request| matches!(request, WsRequest::Unsubscribe { .. }))
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(!ws.is_subscribed(CHANNEL).await);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_waits_for_ack() {
+        let server = FakeWsServer::start().await.unwrap();
+        server.reject("trades/0xdead", "unknown market").await;
+        let mut ws = WebSocketClient::new(server.url());
+        ws.connect().await.unwrap();
+
+        ws.subscribe(CHANNEL).await.unwrap();
+        assert!(matches!(
+            server.requests().await.as_slice(),
+            [WsRequest::Subscribe { id: Some(_), .. }]
+        ));
+
+        match ws.subscribe("trades/0xdead").await {
+            Err(EkidenError::WebSocket(message)) => assert!(message.contains("unknown market")),
+            other => panic!("expected rejection, got {:?}", other),
+        }
+        assert!(!ws.is_subscribed("trades/0xdead").await);
+        assert!(ws.is_subscribed(CHANNEL).await);
+    }
+}
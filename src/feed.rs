@@ -0,0 +1,890 @@
+//! Market and account data that keeps flowing while the WebSocket is down.
+//!
+//! [`DataFeed`] subscribes over the client's WebSocket when it can and falls
+//! back to polling the REST API while the socket is unavailable, emitting the
+//! same [`WsEvent`]s either way so strategy code does not care about the
+//! source:
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient) {
+//! use ekiden_rust_sdk::feed::{DataFeed, DataFeedConfig};
+//!
+//! let market = "0x1111111111111111111111111111111111111111";
+//! let mut feed = DataFeed::new(client, DataFeedConfig::default())
+//!     .orderbook(market)
+//!     .trades(market)
+//!     .start();
+//!
+//! while let Some(event) = feed.recv().await {
+//!     println!("{:?} via {:?}", event, feed.source());
+//! }
+//! # }
+//! ```
+//!
+//! While polling, orderbooks arrive as periodic snapshots, trades newer than
+//! the last one seen are replayed from recent fills, and candles are emitted
+//! when they change. For a followed user, positions and balances of the
+//! authenticated user are emitted when they change, along with the user's
+//! orders and new fills on followed markets and markets with a position.
+//! REST requests are spaced by [`DataFeedConfig::request_interval`] so
+//! polling stays within rate limits. The socket is retried every
+//! [`DataFeedConfig::ws_retry_interval`]. Delays follow the client's
+//! [`clock`](EkidenClient::clock).
+
+use crate::client::EkidenClient;
+use crate::clock::Clock;
+use crate::error::{EkidenError, Result};
+use crate::types::{FillResponse, ListOrdersParams, OrderResponse, Pagination, WsEvent};
+use crate::utils::format;
+use crate::ws::channels;
+use futures_util::TryStreamExt;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Default interval between REST polls while the socket is down
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Default interval between WebSocket reconnection attempts
+pub const DEFAULT_WS_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Default minimum time between REST requests while polling
+pub const DEFAULT_REQUEST_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Number of recent fills fetched per poll
+const FILLS_PER_POLL: u32 = 100;
+
+/// Number of recent candles fetched per poll, the current one and the one it replaced
+const CANDLES_PER_POLL: u32 = 2;
+
+/// Configuration for a [`DataFeed`]
+#[derive(Debug, Clone)]
+pub struct DataFeedConfig {
+    /// Interval between REST polls while the socket is down
+    pub poll_interval: Duration,
+    /// Interval between WebSocket reconnection attempts while polling
+    pub ws_retry_interval: Duration,
+    /// Minimum time between REST requests while polling
+    pub request_interval: Duration,
+    /// Orderbook levels per side fetched when polling
+    pub orderbook_depth: Option<u32>,
+    /// Events buffered for a slow consumer
+    pub buffer: usize,
+}
+
+impl Default for DataFeedConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            ws_retry_interval: DEFAULT_WS_RETRY_INTERVAL,
+            request_interval: DEFAULT_REQUEST_INTERVAL,
+            orderbook_depth: None,
+            buffer: 1024,
+        }
+    }
+}
+
+impl DataFeedConfig {
+    /// Set the interval between REST polls
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Set the interval between WebSocket reconnection attempts
+    pub fn with_ws_retry_interval(mut self, interval: Duration) -> Self {
+        self.ws_retry_interval = interval;
+        self
+    }
+
+    /// Set the minimum time between REST requests while polling
+    pub fn with_request_interval(mut self, interval: Duration) -> Self {
+        self.request_interval = interval;
+        self
+    }
+
+    /// Set the orderbook depth fetched when polling
+    pub fn with_orderbook_depth(mut self, depth: u32) -> Self {
+        self.orderbook_depth = Some(depth);
+        self
+    }
+
+    /// Set how many events are buffered for a slow consumer
+    pub fn with_buffer(mut self, buffer: usize) -> Self {
+        self.buffer = buffer.max(1);
+        self
+    }
+}
+
+/// Where a feed is currently getting its events from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedSource {
+    /// Live WebSocket subscriptions
+    WebSocket,
+    /// REST polling fallback
+    Polling,
+}
+
+#[derive(Debug, Clone)]
+enum Subscription {
+    Orderbook(String),
+    Trades(String),
+    Candles(String, String),
+    User(String),
+}
+
+impl Subscription {
+    fn channel(&self) -> String {
+        match self {
+            Subscription::Orderbook(market_addr) => channels::orderbook(market_addr),
+            Subscription::Trades(market_addr) => channels::trades(market_addr),
+            Subscription::Candles(market_addr, interval) => {
+                channels::candles(market_addr, interval)
+            }
+            Subscription::User(user_addr) => channels::user(user_addr),
+        }
+    }
+
+    async fn subscribe(&self, client: &EkidenClient) -> Result<broadcast::Receiver<WsEvent>> {
+        match self {
+            Subscription::Orderbook(market_addr) => client.subscribe_orderbook(market_addr).await,
+            Subscription::Trades(market_addr) => client.subscribe_trades(market_addr).await,
+            Subscription::Candles(market_addr, interval) => {
+                client.subscribe_candles(market_addr, interval).await
+            }
+            Subscription::User(user_addr) => client.subscribe_user(user_addr).await,
+        }
+    }
+}
+
+/// WebSocket-first data feed with a REST polling fallback
+pub struct DataFeed {
+    client: EkidenClient,
+    config: DataFeedConfig,
+    subscriptions: Vec<Subscription>,
+}
+
+impl DataFeed {
+    /// Create a feed; the WebSocket is connected on start if needed
+    pub fn new(client: EkidenClient, config: DataFeedConfig) -> Self {
+        Self {
+            client,
+            config,
+            subscriptions: Vec::new(),
+        }
+    }
+
+    /// Follow the orderbook of a market
+    pub fn orderbook(mut self, market_addr: &str) -> Self {
+        self.subscriptions
+            .push(Subscription::Orderbook(market_addr.to_string()));
+        self
+    }
+
+    /// Follow public trades of a market
+    pub fn trades(mut self, market_addr: &str) -> Self {
+        self.subscriptions
+            .push(Subscription::Trades(market_addr.to_string()));
+        self
+    }
+
+    /// Follow candles of a market at an interval such as `"1m"`
+    pub fn candles(mut self, market_addr: &str, interval: &str) -> Self {
+        self.subscriptions.push(Subscription::Candles(
+            market_addr.to_string(),
+            interval.to_string(),
+        ));
+        self
+    }
+
+    /// Follow orders, fills, positions and balances of a user
+    ///
+    /// Polling can only fetch positions and balances of the authenticated
+    /// user. Orders and fills are polled on the markets the feed follows and
+    /// those the user has a position in; the first poll emits the user's
+    /// current orders.
+    pub fn user(mut self, user_addr: &str) -> Self {
+        self.subscriptions
+            .push(Subscription::User(user_addr.to_string()));
+        self
+    }
+
+    /// Start the feed in the background
+    pub fn start(self) -> FeedReceiver {
+        let (events_tx, events) = mpsc::channel(self.config.buffer.max(1));
+        let (source_tx, source) = watch::channel(FeedSource::Polling);
+        let client = self.client.clone();
+        let clock = client.clock();
+        let runner = Runner {
+            next_request: clock.instant(),
+            clock,
+            client: self.client,
+            config: self.config,
+            subscriptions: self.subscriptions,
+            events: events_tx,
+            source: source_tx,
+            last_trade: HashMap::new(),
+            last_fill: HashMap::new(),
+            open_orders: HashMap::new(),
+            last_state: HashMap::new(),
+        };
+        FeedReceiver {
+            events,
+            source,
+            task: client.spawn_job(runner.run()),
+        }
+    }
+}
+
+/// Receiving end of a started [`DataFeed`]; the feed stops when this is dropped
+pub struct FeedReceiver {
+    events: mpsc::Receiver<WsEvent>,
+    source: watch::Receiver<FeedSource>,
+    task: JoinHandle<()>,
+}
+
+impl FeedReceiver {
+    /// Receive the next event from whichever source is active
+    pub async fn recv(&mut self) -> Option<WsEvent> {
+        self.events.recv().await
+    }
+
+    /// Get the source events are currently coming from
+    pub fn source(&self) -> FeedSource {
+        *self.source.borrow()
+    }
+
+    /// Wait until the source changes and return the new one
+    pub async fn source_changed(&mut self) -> Option<FeedSource> {
+        self.source.changed().await.ok()?;
+        Some(*self.source.borrow_and_update())
+    }
+}
+
+impl Drop for FeedReceiver {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Runner {
+    client: EkidenClient,
+    config: DataFeedConfig,
+    subscriptions: Vec<Subscription>,
+    events: mpsc::Sender<WsEvent>,
+    source: watch::Sender<FeedSource>,
+    /// Newest trade `(timestamp, seq)` seen per market
+    last_trade: HashMap<String, (u64, u64)>,
+    /// Newest fill `(timestamp, seq)` seen per user and market
+    last_fill: HashMap<String, (u64, u64)>,
+    /// Orders last seen open per user and market, to close those that drop out
+    open_orders: HashMap<String, HashMap<String, OrderResponse>>,
+    /// Last emitted order, position, balance or candle, to emit only changes when polling
+    last_state: HashMap<String, serde_json::Value>,
+    /// Earliest time the next REST request may be sent
+    next_request: Instant,
+    /// The client's clock, for poll, health check and pacing delays
+    clock: Arc<dyn Clock>,
+}
+
+impl Runner {
+    async fn run(mut self) {
+        loop {
+            if let Err(e) = self.stream_ws().await {
+                warn!("Data feed WebSocket unavailable, polling REST: {}", e);
+            }
+            if self.events.is_closed() || !self.poll_until_retry().await {
+                return;
+            }
+        }
+    }
+
+    /// Forward WebSocket events until the socket fails or the consumer goes away
+    async fn stream_ws(&mut self) -> Result<()> {
+        if !self.client.is_websocket_connected().await {
+            self.client.connect_websocket().await?;
+        }
+        let mut receivers = Vec::new();
+        for subscription in &self.subscriptions {
+            receivers.push(subscription.subscribe(&self.client).await?);
+        }
+        self.source.send_replace(FeedSource::WebSocket);
+
+        let (merged_tx, mut merged) = mpsc::channel(self.config.buffer.max(1));
+        let forwarders: Vec<_> = receivers
+            .into_iter()
+            .zip(&self.subscriptions)
+            .map(|(receiver, subscription)| {
+                tokio::spawn(forward(subscription.channel(), receiver, merged_tx.clone()))
+            })
+            .collect();
+        drop(merged_tx);
+
+        let mut health = self.clock.sleep(self.config.poll_interval);
+        let result = loop {
+            tokio::select! {
+                event = merged.recv() => match event {
+                    Some(event) => {
+                        if !self.emit(event).await {
+                            break Ok(());
+                        }
+                    }
+                    None => break Err(EkidenError::ConnectionClosed),
+                },
+                _ = &mut health => {
+                    if !self.client.is_websocket_connected().await {
+                        break Err(EkidenError::ConnectionClosed);
+                    }
+                    health = self.clock.sleep(self.config.poll_interval);
+                }
+            }
+        };
+        for forwarder in forwarders {
+            forwarder.abort();
+        }
+        result
+    }
+
+    /// Poll REST until it is time to retry the socket; `false` once the consumer is gone
+    async fn poll_until_retry(&mut self) -> bool {
+        self.source.send_replace(FeedSource::Polling);
+        let retry_at = self.clock.instant() + self.config.ws_retry_interval;
+        let mut next_poll = self.clock.instant();
+        while self.clock.instant() < retry_at {
+            let wait = next_poll.saturating_duration_since(self.clock.instant());
+            self.clock.sleep(wait).await;
+            next_poll += self.config.poll_interval;
+            for event in self.poll().await {
+                if !self.emit(event).await {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Fetch one round of events over REST
+    async fn poll(&mut self) -> Vec<WsEvent> {
+        let mut events = Vec::new();
+        for subscription in self.subscriptions.clone() {
+            let result = match &subscription {
+                Subscription::Orderbook(market_addr) => {
+                    self.pace().await;
+                    self.client
+                        .get_orderbook(market_addr, self.config.orderbook_depth)
+                        .await
+                        .map(|book| vec![book.into()])
+                }
+                Subscription::Trades(market_addr) => {
+                    self.pace().await;
+                    self.client
+                        .get_recent_fills(market_addr, Some(FILLS_PER_POLL))
+                        .await
+                        .map(|fills| self.new_trades(market_addr, fills))
+                }
+                Subscription::Candles(market_addr, interval) => {
+                    self.pace().await;
+                    self.client
+                        .get_recent_candles(market_addr, interval, Some(CANDLES_PER_POLL))
+                        .await
+                        .map(|mut candles| {
+                            candles.sort_by_key(|candle| candle.timestamp);
+                            self.changed(
+                                candles.into_iter().map(|candle| WsEvent::Candle { candle }),
+                            )
+                        })
+                }
+                Subscription::User(user_addr) => self.poll_user(user_addr).await,
+            };
+            match result {
+                Ok(polled) => events.extend(polled),
+                Err(e) => debug!("Polling {} failed: {}", subscription.channel(), e),
+            }
+        }
+        events
+    }
+
+    /// Turn fills newer than the last seen trade into trade events
+    ///
+    /// Fills are ordered by `(timestamp, seq)`, so fills sharing the last
+    /// trade's timestamp are not lost.
+    fn new_trades(&mut self, market_addr: &str, mut fills: Vec<FillResponse>) -> Vec<WsEvent> {
+        fills.sort_by_key(|fill| (fill.timestamp, fill.seq));
+        let key = address_key(market_addr);
+        let newest = fills
+            .last()
+            .map_or((0, 0), |fill| (fill.timestamp, fill.seq));
+        let Some(&last) = self.last_trade.get(&key) else {
+            // Nothing seen yet: start from the newest fill rather than replay history
+            self.last_trade.insert(key, newest);
+            return Vec::new();
+        };
+        self.last_trade.insert(key, last.max(newest));
+        fills
+            .into_iter()
+            .filter(|fill| (fill.timestamp, fill.seq) > last)
+            .map(|fill| WsEvent::Trade {
+                market_addr: fill.market_addr,
+                price: fill.price,
+                size: fill.size,
+                side: fill.side,
+                timestamp: fill.timestamp,
+            })
+            .collect()
+    }
+
+    /// Turn fills of a user newer than the last one seen into user fill events
+    fn new_user_fills(
+        &mut self,
+        user_addr: &str,
+        market_addr: &str,
+        mut fills: Vec<FillResponse>,
+    ) -> Vec<WsEvent> {
+        fills.sort_by_key(|fill| (fill.timestamp, fill.seq));
+        let key = fill_key(user_addr, market_addr);
+        let Some(&last) = self.last_fill.get(&key) else {
+            let newest = fills
+                .last()
+                .map_or((0, 0), |fill| (fill.timestamp, fill.seq));
+            self.last_fill.insert(key, newest);
+            return Vec::new();
+        };
+        fills
+            .into_iter()
+            .filter(|fill| (fill.timestamp, fill.seq) > last)
+            .filter(|fill| {
+                address_key(&fill.taker_addr) == user_addr
+                    || address_key(&fill.maker_addr) == user_addr
+            })
+            .map(|fill| WsEvent::UserFill { fill })
+            .collect()
+    }
+
+    /// Fetch the authenticated user's positions and balances, and a user's
+    /// orders and fills, keeping only changes
+    ///
+    /// An order seen open that is no longer listed is emitted once more with
+    /// a `closed` status.
+    async fn poll_user(&mut self, user_addr: &str) -> Result<Vec<WsEvent>> {
+        self.pace().await;
+        let positions = self.client.get_all_user_positions().await?;
+        self.pace().await;
+        let vaults = self.client.get_all_user_vaults().await?;
+
+        let mut markets = self.followed_markets();
+        for position in &positions {
+            let market_addr = address_key(&position.market_addr);
+            if !markets.contains(&market_addr) {
+                markets.push(market_addr);
+            }
+        }
+        let mut events: Vec<WsEvent> = positions
+            .into_iter()
+            .map(|position| WsEvent::PositionUpdate { position })
+            .chain(
+                vaults
+                    .into_iter()
+                    .map(|vault| WsEvent::BalanceUpdate { vault }),
+            )
+            .collect();
+
+        let user_addr = address_key(user_addr);
+        for market_addr in markets {
+            self.pace().await;
+            let orders: Vec<OrderResponse> = self
+                .client
+                .get_orders_stream(ListOrdersParams {
+                    market_addr: market_addr.clone(),
+                    side: None,
+                    pagination: Pagination::default(),
+                })
+                .try_filter(|order| {
+                    futures_util::future::ready(address_key(&order.user_addr) == user_addr)
+                })
+                .try_collect()
+                .await?;
+            let previous = self
+                .open_orders
+                .get(&fill_key(&user_addr, &market_addr))
+                .cloned()
+                .unwrap_or_default();
+            for (sid, mut order) in previous {
+                if !orders.iter().any(|listed| listed.sid == sid) {
+                    order.status = "closed".to_string();
+                    events.push(WsEvent::OrderUpdate { order });
+                }
+            }
+            events.extend(
+                orders
+                    .into_iter()
+                    .map(|order| WsEvent::OrderUpdate { order }),
+            );
+            self.pace().await;
+            let fills = self
+                .client
+                .get_recent_fills(&market_addr, Some(FILLS_PER_POLL))
+                .await?;
+            events.extend(self.new_user_fills(&user_addr, &market_addr, fills));
+        }
+        Ok(self.changed(events))
+    }
+
+    /// Markets followed by orderbook, trades or candles subscriptions
+    fn followed_markets(&self) -> Vec<String> {
+        let mut markets = Vec::new();
+        for subscription in &self.subscriptions {
+            let market_addr = match subscription {
+                Subscription::Orderbook(market_addr)
+                | Subscription::Trades(market_addr)
+                | Subscription::Candles(market_addr, _) => address_key(market_addr),
+                Subscription::User(_) => continue,
+            };
+            if !markets.contains(&market_addr) {
+                markets.push(market_addr);
+            }
+        }
+        markets
+    }
+
+    /// Keep events whose state differs from the last one emitted
+    ///
+    /// Candles older than the last one emitted for their market and interval
+    /// are dropped.
+    fn changed(&self, events: impl IntoIterator<Item = WsEvent>) -> Vec<WsEvent> {
+        events
+            .into_iter()
+            .filter(|event| {
+                let Some(last) = state_key(event).and_then(|key| self.last_state.get(&key)) else {
+                    return true;
+                };
+                if let WsEvent::Candle { candle } = event {
+                    if candle.timestamp < last["candle"]["timestamp"].as_u64().unwrap_or(0) {
+                        return false;
+                    }
+                }
+                serde_json::to_value(event).ok().as_ref() != Some(last)
+            })
+            .collect()
+    }
+
+    /// Wait until the next REST request may be sent
+    async fn pace(&mut self) {
+        let wait = self
+            .next_request
+            .saturating_duration_since(self.clock.instant());
+        self.clock.sleep(wait).await;
+        self.next_request = self.clock.instant() + self.config.request_interval;
+    }
+
+    /// Record what an event tells us and pass it on; `false` once the consumer is gone
+    async fn emit(&mut self, event: WsEvent) -> bool {
+        match &event {
+            WsEvent::Trade {
+                market_addr,
+                timestamp,
+                ..
+            } => {
+                // Socket trades carry no sequence number, so a newer one marks
+                // its whole timestamp as seen
+                let last = self.last_trade.entry(address_key(market_addr)).or_default();
+                if *timestamp > last.0 {
+                    *last = (*timestamp, u64::MAX);
+                }
+            }
+            WsEvent::UserFill { fill } => {
+                for user_addr in [&fill.taker_addr, &fill.maker_addr] {
+                    let last = self
+                        .last_fill
+                        .entry(fill_key(&address_key(user_addr), &fill.market_addr))
+                        .or_default();
+                    *last = (*last).max((fill.timestamp, fill.seq));
+                }
+            }
+            WsEvent::OrderUpdate { order } => {
+                let open = self
+                    .open_orders
+                    .entry(fill_key(&address_key(&order.user_addr), &order.market_addr))
+                    .or_default();
+                if order.is_open() {
+                    open.insert(order.sid.clone(), order.clone());
+                } else {
+                    open.remove(&order.sid);
+                }
+                if let (Some(key), Ok(value)) = (state_key(&event), serde_json::to_value(&event)) {
+                    self.last_state.insert(key, value);
+                }
+            }
+            WsEvent::PositionUpdate { .. }
+            | WsEvent::BalanceUpdate { .. }
+            | WsEvent::Candle { .. } => {
+                if let (Some(key), Ok(value)) = (state_key(&event), serde_json::to_value(&event)) {
+                    self.last_state.insert(key, value);
+                }
+            }
+            _ => {}
+        }
+        self.events.send(event).await.is_ok()
+    }
+}
+
+/// Forward a channel's events until it closes or the feed stops
+async fn forward(
+    channel: String,
+    mut receiver: broadcast::Receiver<WsEvent>,
+    sender: mpsc::Sender<WsEvent>,
+) {
+    loop {
+        match receiver.recv().await {
+            Ok(event) => {
+                if sender.send(event).await.is_err() {
+                    return;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                warn!("Data feed lagged on {}, missed {} events", channel, missed);
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+fn address_key(address: &str) -> String {
+    format::normalize_address(address).unwrap_or_else(|_| address.to_string())
+}
+
+fn fill_key(user_addr: &str, market_addr: &str) -> String {
+    format!("{}/{}", user_addr, address_key(market_addr))
+}
+
+fn state_key(event: &WsEvent) -> Option<String> {
+    match event {
+        WsEvent::OrderUpdate { order } => Some(format!("order/{}", order.sid)),
+        WsEvent::Candle { candle } => {
+            Some(channels::candles(&candle.market_addr, &candle.interval))
+        }
+        WsEvent::PositionUpdate { position } => {
+            Some(format!("position/{}", address_key(&position.market_addr)))
+        }
+        WsEvent::BalanceUpdate { vault } => {
+            Some(format!("vault/{}", address_key(&vault.vault_addr)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{self, MockGateway};
+    use crate::types::{CandleResponse, OrderbookLevel, OrderbookResponse};
+
+    const MARKET: &str = "0x1111111111111111111111111111111111111111";
+
+    fn fast() -> DataFeedConfig {
+        DataFeedConfig::default()
+            .with_poll_interval(Duration::from_millis(20))
+            .with_ws_retry_interval(Duration::from_secs(60))
+    }
+
+    fn fill(seq: u64, timestamp: u64) -> FillResponse {
+        FillResponse {
+            sid: format!("{:016x}", seq),
+            price: 100,
+            size: 1,
+            side: "buy".to_string(),
+            taker_addr: String::new(),
+            maker_addr: String::new(),
+            market_addr: MARKET.to_string(),
+            seq,
+            timestamp,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_polls_rest_when_socket_is_down() {
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(mock::market(MARKET, "BTC-USD")).await;
+        gateway
+            .set_orderbook(OrderbookResponse {
+                market_addr: MARKET.to_string(),
+                bids: vec![OrderbookLevel { price: 99, size: 5 }],
+                asks: vec![OrderbookLevel {
+                    price: 101,
+                    size: 5,
+                }],
+                timestamp: 1,
+            })
+            .await;
+        gateway.add_fill(fill(1, 1_000)).await;
+
+        // A client pointed at a closed port can never open its socket
+        let config = gateway
+            .config()
+            .unwrap()
+            .with_ws_url("ws://127.0.0.1:9/ws")
+            .unwrap();
+        let client = EkidenClient::new(config).unwrap();
+        let mut feed = DataFeed::new(client, fast())
+            .orderbook(MARKET)
+            .trades(MARKET)
+            .start();
+
+        let event = feed.recv().await.unwrap();
+        assert!(
+            matches!(event, WsEvent::OrderbookSnapshot { ref bids, .. } if bids[0].price == 99)
+        );
+        assert_eq!(feed.source(), FeedSource::Polling);
+
+        // Only fills after the first poll are replayed as trades, including
+        // one sharing the last timestamp
+        gateway.add_fill(fill(2, 1_000)).await;
+        let trade = loop {
+            match feed.recv().await.unwrap() {
+                WsEvent::Trade { timestamp, .. } => break timestamp,
+                _ => continue,
+            }
+        };
+        assert_eq!(trade, 1_000);
+    }
+
+    fn candle(timestamp: u64, close: u64) -> CandleResponse {
+        CandleResponse {
+            market_addr: MARKET.to_string(),
+            timestamp,
+            open: 100,
+            high: close.max(100),
+            low: close.min(100),
+            close,
+            volume: 1,
+            interval: "1m".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_polls_only_changed_candles() {
+        let gateway = MockGateway::start().await.unwrap();
+        gateway
+            .set_response("GET", "candles", 200, &vec![candle(60_000, 101)])
+            .await;
+        let config = gateway
+            .config()
+            .unwrap()
+            .with_ws_url("ws://127.0.0.1:9/ws")
+            .unwrap();
+        let client = EkidenClient::new(config).unwrap();
+        let mut feed = DataFeed::new(client, fast()).candles(MARKET, "1m").start();
+
+        let first = feed.recv().await.unwrap();
+        assert!(matches!(first, WsEvent::Candle { ref candle } if candle.close == 101));
+
+        // An unchanged candle is not repeated; the closed one and its successor are
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(feed.events.try_recv().is_err());
+        gateway
+            .set_response(
+                "GET",
+                "candles",
+                200,
+                &vec![candle(60_000, 102), candle(120_000, 102)],
+            )
+            .await;
+        let mut closes = Vec::new();
+        while closes.len() < 2 {
+            if let WsEvent::Candle { candle } = feed.recv().await.unwrap() {
+                closes.push((candle.timestamp, candle.close));
+            }
+        }
+        assert_eq!(closes, vec![(60_000, 102), (120_000, 102)]);
+    }
+
+    #[tokio::test]
+    async fn test_polled_user_orders_close_when_they_drop_out() {
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(mock::market(MARKET, "BTC-USD")).await;
+        gateway
+            .set_response("GET", "user/vaults", 200, &Vec::<serde_json::Value>::new())
+            .await;
+        let config = gateway
+            .config()
+            .unwrap()
+            .with_ws_url("ws://127.0.0.1:9/ws")
+            .unwrap();
+        let client = crate::EkidenClientBuilder::new()
+            .config(config)
+            .private_key(format!("0x{}", hex::encode([7u8; 32])))
+            .build()
+            .await
+            .unwrap();
+        client.authorize().await.unwrap();
+        let user_addr = client.address().await.unwrap().unwrap();
+        let order = OrderResponse {
+            sid: "resting".to_string(),
+            side: "buy".to_string(),
+            size: 1,
+            price: 100,
+            leverage: 1,
+            order_type: "limit".to_string(),
+            status: "open".to_string(),
+            user_addr: user_addr.clone(),
+            market_addr: MARKET.to_string(),
+            seq: 1,
+            timestamp: 0,
+            client_order_id: None,
+            time_in_force: None,
+            post_only: None,
+            reduce_only: None,
+        };
+        gateway
+            .set_response("GET", "orders", 200, &vec![order])
+            .await;
+        let mut feed = DataFeed::new(client, fast())
+            .trades(MARKET)
+            .user(&user_addr)
+            .start();
+
+        async fn next_order(feed: &mut FeedReceiver) -> OrderResponse {
+            loop {
+                if let WsEvent::OrderUpdate { order } = feed.recv().await.unwrap() {
+                    return order;
+                }
+            }
+        }
+        assert_eq!(next_order(&mut feed).await.status, "open");
+        gateway
+            .set_response("GET", "orders", 200, &Vec::<OrderResponse>::new())
+            .await;
+        let closed = next_order(&mut feed).await;
+        assert_eq!(
+            (closed.sid.as_str(), closed.status.as_str()),
+            ("resting", "closed")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prefers_websocket() {
+        let gateway = MockGateway::start().await.unwrap();
+        let client = gateway.client().await.unwrap();
+        let mut feed = DataFeed::new(client, fast()).trades(MARKET).start();
+
+        assert_eq!(feed.source_changed().await, Some(FeedSource::WebSocket));
+        let trade = WsEvent::Trade {
+            market_addr: MARKET.to_string(),
+            price: 100,
+            size: 1,
+            side: "sell".to_string(),
+            timestamp: 5,
+        };
+        let event = loop {
+            gateway.push_event(&channels::trades(MARKET), trade.clone());
+            match tokio::time::timeout(Duration::from_millis(50), feed.recv()).await {
+                Ok(event) => break event.unwrap(),
+                Err(_) => continue,
+            }
+        };
+        assert!(matches!(event, WsEvent::Trade { timestamp: 5, .. }));
+        assert_eq!(feed.source(), FeedSource::WebSocket);
+    }
+}
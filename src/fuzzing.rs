@@ -0,0 +1,56 @@
+//! Entry points for the cargo-fuzz targets in `fuzz/`.
+//!
+//! Enabled with the `fuzzing` feature. Each function takes arbitrary bytes,
+//! runs them through a parser that consumes untrusted network data and
+//! panics only if an invariant is violated, never on malformed input.
+
+use crate::codec::WireFormat;
+use crate::error::EkidenError;
+use crate::types::{MarketResponse, WsEvent, WsResponse};
+
+/// Parse a WebSocket frame; accepted frames must survive a round trip
+pub fn ws_response(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(response) = WsResponse::parse(text) {
+        let encoded = serde_json::to_string(&response).expect("parsed frame re-encodes");
+        WsResponse::parse(&encoded).expect("re-encoded frame parses");
+    }
+}
+
+/// Parse a bare WebSocket event payload
+pub fn ws_event(data: &[u8]) {
+    if let Ok(event) = serde_json::from_slice::<WsEvent>(data) {
+        let encoded = serde_json::to_vec(&event).expect("parsed event re-encodes");
+        serde_json::from_slice::<WsEvent>(&encoded).expect("re-encoded event parses");
+    }
+}
+
+/// Build an error from a response body; the first two bytes are the status
+pub fn error_body(data: &[u8]) {
+    let (status, body) = match data {
+        [hi, lo, body @ ..] => (u16::from_be_bytes([*hi, *lo]), body),
+        _ => (500, data),
+    };
+    match EkidenError::from_response_body(status, body) {
+        EkidenError::Api { status: parsed, .. } => assert_eq!(parsed, status),
+        EkidenError::InsufficientMargin { .. }
+        | EkidenError::OrderRejected { .. }
+        | EkidenError::PositionNotFound { .. }
+        | EkidenError::MarketClosed { .. } => {}
+        other => panic!("unexpected error kind {:?}", other),
+    }
+}
+
+/// Decode a REST body in every supported wire format
+pub fn response_body(data: &[u8]) {
+    for content_type in [
+        "application/json",
+        "application/msgpack",
+        "application/cbor",
+    ] {
+        let format = WireFormat::from_content_type(Some(content_type));
+        let _ = format.decode::<Vec<MarketResponse>>(data);
+    }
+}
@@ -0,0 +1,182 @@
+//! Golden fixtures of captured gateway payloads.
+//!
+//! Real responses live as JSON files under `tests/fixtures/gateway`, named
+//! `<kind>_<version>.json` (e.g. `market_v2.json`). [`assert_deserializes`]
+//! checks that a fixture still parses into the SDK type and that no field of
+//! the payload is silently ignored, so schema changes between gateway
+//! versions fail loudly:
+//!
+//! ```rust,no_run
+//! use ekiden_rust_sdk::golden::assert_deserializes;
+//! use ekiden_rust_sdk::MarketResponse;
+//!
+//! let market: MarketResponse = assert_deserializes("market_v2.json");
+//! assert!(!market.symbol.is_empty());
+//! ```
+//!
+//! The directory is resolved from `EKIDEN_FIXTURES_DIR`, falling back to
+//! `tests/fixtures/gateway` under the crate being tested, so downstream crates
+//! can keep their own corpus. See `tests/fixtures/gateway/README.md` for how
+//! to contribute captured payloads.
+
+use crate::error::{EkidenError, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Environment variable overriding the fixtures directory
+pub const FIXTURES_DIR_ENV: &str = "EKIDEN_FIXTURES_DIR";
+
+/// Get the directory fixtures are loaded from
+pub fn fixtures_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var(FIXTURES_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+    let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    Path::new(&root)
+        .join("tests")
+        .join("fixtures")
+        .join("gateway")
+}
+
+/// Load a fixture as raw JSON
+pub fn load(name: &str) -> Result<Value> {
+    let path = fixtures_dir().join(name);
+    let text = std::fs::read_to_string(&path).map_err(|e| {
+        EkidenError::general(format!("Failed to read fixture {}: {}", path.display(), e))
+    })?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+/// Deserialize a payload, returning it with the paths of fields `T` ignored
+pub fn deserialize_strict<T: Serialize + DeserializeOwned>(
+    payload: &Value,
+) -> Result<(T, Vec<String>)> {
+    let value: T = serde_json::from_value(payload.clone())?;
+    let reserialized = serde_json::to_value(&value)?;
+    let mut dropped = Vec::new();
+    collect_dropped(payload, &reserialized, "$", &mut dropped);
+    Ok((value, dropped))
+}
+
+/// Assert a fixture parses into `T` without ignoring any field
+///
+/// Panics with the fixture name and the offending fields on failure.
+pub fn assert_deserializes<T: Serialize + DeserializeOwned>(name: &str) -> T {
+    let payload = load(name).unwrap_or_else(|e| panic!("{}", e));
+    match deserialize_strict::<T>(&payload) {
+        Ok((value, dropped)) if dropped.is_empty() => value,
+        Ok((_, dropped)) => panic!(
+            "Fixture {} has fields not modelled by {}: {}",
+            name,
+            std::any::type_name::<T>(),
+            dropped.join(", ")
+        ),
+        Err(e) => panic!(
+            "Fixture {} no longer deserializes into {}: {}",
+            name,
+            std::any::type_name::<T>(),
+            e
+        ),
+    }
+}
+
+fn collect_dropped(original: &Value, parsed: &Value, path: &str, dropped: &mut Vec<String>) {
+    match (original, parsed) {
+        (Value::Object(original), Value::Object(parsed)) => {
+            for (key, value) in original {
+                let child = format!("{}.{}", path, key);
+                match parsed.get(key) {
+                    Some(parsed) => collect_dropped(value, parsed, &child, dropped),
+                    None if value.is_null() => {}
+                    None => dropped.push(child),
+                }
+            }
+        }
+        (Value::Array(original), Value::Array(parsed)) => {
+            for (i, (value, parsed)) in original.iter().zip(parsed).enumerate() {
+                collect_dropped(value, parsed, &format!("{}[{}]", path, i), dropped);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::*;
+
+    /// Check one fixture against the type its name prefix maps to
+    fn check(name: &str, payload: &Value) -> Result<Vec<String>> {
+        fn strict<T: Serialize + DeserializeOwned>(payload: &Value) -> Result<Vec<String>> {
+            if payload.is_array() {
+                deserialize_strict::<Vec<T>>(payload).map(|(_, dropped)| dropped)
+            } else {
+                deserialize_strict::<T>(payload).map(|(_, dropped)| dropped)
+            }
+        }
+
+        let kind = name.rsplit_once('_').map_or(name, |(kind, _)| kind);
+        match kind {
+            "market" => strict::<MarketResponse>(payload),
+            "order" => strict::<OrderResponse>(payload),
+            "orderbook" => strict::<OrderbookResponse>(payload),
+            "fill" => strict::<FillResponse>(payload),
+            "position" => strict::<PositionResponse>(payload),
+            "portfolio" => strict::<PortfolioResponse>(payload),
+            "vault" => strict::<VaultResponse>(payload),
+            "intent" => strict::<SendIntentResponse>(payload),
+            "candle" => strict::<CandleResponse>(payload),
+            "funding_rate" => strict::<FundingRateResponse>(payload),
+            "deposit" => strict::<DepositResponse>(payload),
+            "withdraw" => strict::<WithdrawResponse>(payload),
+            "leverage" => strict::<LeverageResponse>(payload),
+            "announcement" => strict::<AnnouncementResponse>(payload),
+            "account" => strict::<AccountInfoResponse>(payload),
+            kind if kind.starts_with("ws") => strict::<WsResponse>(payload),
+            _ => Err(EkidenError::general(format!(
+                "No type registered for fixture prefix {:?}",
+                kind
+            ))),
+        }
+    }
+
+    #[test]
+    fn test_fixture_corpus() {
+        let mut failures = Vec::new();
+        let mut checked = 0;
+        for entry in std::fs::read_dir(fixtures_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let stem = name.trim_end_matches(".json");
+            let result = load(&name).and_then(|payload| check(stem, &payload));
+            match result {
+                Ok(dropped) if dropped.is_empty() => checked += 1,
+                Ok(dropped) => {
+                    failures.push(format!("{}: unmodelled {}", name, dropped.join(", ")))
+                }
+                Err(e) => failures.push(format!("{}: {}", name, e)),
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn test_assert_deserializes() {
+        let market: MarketResponse = assert_deserializes("market_v2.json");
+        assert_eq!(market.symbol, "BTC-USD");
+    }
+
+    #[test]
+    fn test_reports_unmodelled_fields() {
+        let payload = serde_json::json!({ "price": 1, "size": 2, "count": 3 });
+        let (_, dropped) = deserialize_strict::<OrderbookLevel>(&payload).unwrap();
+        assert_eq!(dropped, vec!["$.count"]);
+    }
+}
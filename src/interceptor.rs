@@ -0,0 +1,160 @@
+//! Hooks around every REST request.
+//!
+//! A [`RequestInterceptor`] registered on the client sees each request before
+//! it reaches the transport and each outcome afterwards. Interceptors can add
+//! headers, reject requests, time calls or write audit logs without wrapping
+//! the transport:
+//!
+//! ```rust,no_run
+//! # async fn run() -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::interceptor::{RequestContext, RequestInterceptor};
+//! use ekiden_rust_sdk::transport::{HttpRequest, HttpResponse};
+//! use ekiden_rust_sdk::EkidenClientBuilder;
+//!
+//! #[derive(Debug)]
+//! struct Audit;
+//!
+//! impl RequestInterceptor for Audit {
+//!     fn after_response(
+//!         &self,
+//!         request: &HttpRequest,
+//!         response: &ekiden_rust_sdk::Result<HttpResponse>,
+//!         context: &RequestContext,
+//!     ) {
+//!         let status = response.as_ref().map(|r| r.status).ok();
+//!         println!("{} {} -> {:?} in {:?}", request.method, context.path, status, context.elapsed());
+//!     }
+//! }
+//!
+//! let client = EkidenClientBuilder::new()
+//!     .production()?
+//!     .interceptor(Audit)
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Hooks run synchronously on the calling task in registration order, and
+//! `after_response` in reverse order, so they should hand off anything slow.
+
+use crate::error::Result;
+use crate::transport::{HttpRequest, HttpResponse};
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What the client knows about a request besides its HTTP form
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct RequestContext {
+    /// API path relative to the base URL, e.g. `user/intent`
+    pub path: String,
+    /// Whether the request carries authentication headers
+    pub auth_required: bool,
+    /// When the request was handed to the interceptors
+    pub started_at: Instant,
+}
+
+impl RequestContext {
+    pub(crate) fn new(path: &str, auth_required: bool) -> Self {
+        Self {
+            path: path.to_string(),
+            auth_required,
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Time since the request started
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+}
+
+/// Observes and adjusts REST requests made by the client
+pub trait RequestInterceptor: Send + Sync + Debug {
+    /// Inspect or modify a request before it is sent
+    ///
+    /// Returning an error aborts the request with that error.
+    fn before_request(&self, _request: &mut HttpRequest, _context: &RequestContext) -> Result<()> {
+        Ok(())
+    }
+
+    /// Observe the transport's outcome for a request
+    ///
+    /// Called for every request that was sent, including failed ones, before
+    /// the response status is turned into an error.
+    fn after_response(
+        &self,
+        _request: &HttpRequest,
+        _response: &Result<HttpResponse>,
+        _context: &RequestContext,
+    ) {
+    }
+}
+
+/// Adds fixed headers to every request
+#[derive(Debug, Clone, Default)]
+pub struct HeaderInjector {
+    headers: Vec<(String, String)>,
+}
+
+impl HeaderInjector {
+    /// Create an injector with no headers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a header, replacing any value the request already has for it
+    pub fn with_header<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+impl RequestInterceptor for HeaderInjector {
+    fn before_request(&self, request: &mut HttpRequest, _context: &RequestContext) -> Result<()> {
+        for (name, value) in &self.headers {
+            request
+                .headers
+                .retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+            request.headers.push((name.clone(), value.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// Interceptors registered on a client, in registration order
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Interceptors(Vec<Arc<dyn RequestInterceptor>>);
+
+impl Interceptors {
+    pub(crate) fn push(&mut self, interceptor: Arc<dyn RequestInterceptor>) {
+        self.0.push(interceptor);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn before_request(
+        &self,
+        request: &mut HttpRequest,
+        context: &RequestContext,
+    ) -> Result<()> {
+        self.0
+            .iter()
+            .try_for_each(|interceptor| interceptor.before_request(request, context))
+    }
+
+    pub(crate) fn after_response(
+        &self,
+        request: &HttpRequest,
+        response: &Result<HttpResponse>,
+        context: &RequestContext,
+    ) {
+        for interceptor in self.0.iter().rev() {
+            interceptor.after_response(request, response, context);
+        }
+    }
+}
@@ -1,16 +1,79 @@
+pub mod analytics;
 pub mod auth;
+pub mod batch;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod books;
+pub mod bracket;
+pub mod cache;
+#[cfg(feature = "chaos")]
+pub mod chaos;
 pub mod client;
+pub mod clock;
+pub mod cloid;
+pub mod codec;
 pub mod config;
 pub mod error;
+pub mod failover;
+#[cfg(feature = "mock")]
+pub mod fake_ws;
+pub mod feed;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+pub mod golden;
+pub mod interceptor;
+pub mod logging;
+pub mod metrics;
+pub mod mm;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod notify;
+pub mod orderbook;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod pool;
+pub mod ratelimit;
+pub mod rebalance;
+#[cfg(feature = "recorder")]
+pub mod recorder;
+pub mod risk;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(any(test, feature = "proptest"))]
+pub mod strategies;
+pub mod streams;
+#[cfg(test)]
+mod test_utils;
+pub mod throttle;
+pub mod tls;
+pub mod tracker;
+pub mod transfer;
+pub mod transport;
+pub mod triggers;
 pub mod types;
+pub mod unified;
 pub mod utils;
+#[cfg(feature = "vcr")]
+pub mod vcr;
+pub mod watchdog;
 pub mod ws;
 
 // Re-export main types for convenience
 pub use auth::Auth;
-pub use client::{EkidenClient, EkidenClientBuilder};
-pub use config::EkidenConfig;
-pub use error::{EkidenError, Result};
+pub use client::{BulkOutcome, EkidenClient, EkidenClientBuilder, ErrorContext};
+pub use clock::Clock;
+pub use cloid::{ClientOrder, ClientOrderIds};
+pub use codec::WireFormat;
+pub use config::{EkidenConfig, ProxyConfig};
+pub use error::{
+    ApiErrorBody, ApiErrorCode, CryptoError, EkidenError, ErrorKind, RateLimitScope, Result,
+};
+pub use interceptor::RequestInterceptor;
+pub use metrics::MetricsRecorder;
+pub use orderbook::Orderbook;
+pub use ratelimit::RateLimiterConfig;
+pub use tls::TlsConfig;
+pub use tokio_util::sync::CancellationToken;
 pub use types::*;
 pub use utils::{Crypto, KeyPair};
 
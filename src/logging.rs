@@ -0,0 +1,174 @@
+//! Request and response logging for [`EkidenConfig::enable_logging`].
+//!
+//! With logging enabled every REST exchange is logged at info level under the
+//! `ekiden_rust_sdk::http` target: method, URL, status, duration and the body
+//! truncated to [`MAX_BODY_CHARS`]. Credential headers and secret JSON fields
+//! such as signatures and tokens are replaced by their fingerprints:
+//!
+//! ```rust,no_run
+//! # async fn run() -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::{EkidenClient, EkidenConfig};
+//!
+//! let config = EkidenConfig::production()?.with_logging(true);
+//! let client = EkidenClient::new(config)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`EkidenConfig::enable_logging`]: crate::EkidenConfig::enable_logging
+
+use crate::error::Result;
+use crate::transport::{HttpRequest, HttpResponse};
+use crate::utils::Redacted;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Longest body logged before it is truncated
+pub const MAX_BODY_CHARS: usize = 1024;
+
+/// Headers whose values are never logged
+const SECRET_HEADERS: &[&str] = &["authorization", "cookie", "set-cookie", "x-api-key"];
+
+/// JSON fields whose values are never logged
+const SECRET_FIELDS: &[&str] = &[
+    "signature",
+    "private_key",
+    "secret",
+    "token",
+    "access_token",
+    "refresh_token",
+];
+
+/// Format headers for logging, redacting credentials
+pub fn redact_headers(headers: &[(String, String)]) -> String {
+    let headers: Vec<String> = headers
+        .iter()
+        .map(|(name, value)| {
+            if SECRET_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+                format!("{}: {:?}", name, Redacted(value))
+            } else {
+                format!("{}: {}", name, value)
+            }
+        })
+        .collect();
+    format!("[{}]", headers.join(", "))
+}
+
+/// Format a body for logging, redacting secret fields and truncating it
+///
+/// Bodies that are not JSON are logged by size only.
+pub fn redact_body(body: &[u8]) -> String {
+    if body.is_empty() {
+        return String::new();
+    }
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return format!("<{} bytes>", body.len());
+    };
+    redact_value(&mut value);
+    truncate(value.to_string())
+}
+
+fn redact_value(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if SECRET_FIELDS.contains(&key.as_str()) {
+                    if let Some(secret) = value.as_str() {
+                        *value = format!("{:?}", Redacted(secret)).into();
+                        continue;
+                    }
+                }
+                redact_value(value);
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+fn truncate(mut body: String) -> String {
+    if let Some((index, _)) = body.char_indices().nth(MAX_BODY_CHARS) {
+        let total = body.len();
+        body.truncate(index);
+        body.push_str(&format!("... ({} bytes)", total));
+    }
+    body
+}
+
+/// Log a request before it is sent
+pub(crate) fn log_request(request: &HttpRequest) {
+    info!(
+        target: "ekiden_rust_sdk::http",
+        method = %request.method,
+        url = %request.url,
+        headers = %redact_headers(&request.headers),
+        body = %request.body.as_deref().map(redact_body).unwrap_or_default(),
+        "HTTP request"
+    );
+}
+
+/// Log the outcome of a request
+pub(crate) fn log_response(
+    request: &HttpRequest,
+    response: &Result<HttpResponse>,
+    duration: Duration,
+) {
+    match response {
+        Ok(response) => info!(
+            target: "ekiden_rust_sdk::http",
+            method = %request.method,
+            url = %request.url,
+            status = response.status,
+            duration_ms = duration.as_millis() as u64,
+            body = %redact_body(&response.body),
+            "HTTP response"
+        ),
+        Err(error) => warn!(
+            target: "ekiden_rust_sdk::http",
+            method = %request.method,
+            url = %request.url,
+            duration_ms = duration.as_millis() as u64,
+            error = %error,
+            "HTTP request failed"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_secrets() {
+        let headers = vec![
+            (
+                "Authorization".to_string(),
+                "Bearer secret-token".to_string(),
+            ),
+            ("Accept".to_string(), "application/json".to_string()),
+        ];
+        let logged = redact_headers(&headers);
+        assert!(!logged.contains("secret-token"));
+        assert!(logged.contains("Authorization: <redacted "));
+        assert!(logged.contains("Accept: application/json"));
+
+        let body = serde_json::json!({
+            "actions": [{ "type": "cancel_order", "data": { "sid": "7" } }],
+            "signature": "0xdeadbeef",
+            "nested": { "token": "abc" },
+        });
+        let logged = redact_body(body.to_string().as_bytes());
+        assert!(!logged.contains("deadbeef"));
+        assert!(!logged.contains("\"abc\""));
+        assert!(logged.contains("cancel_order"));
+        assert_eq!(redact_body(&[0xff, 0x00]), "<2 bytes>");
+    }
+
+    #[test]
+    fn test_truncates_long_bodies() {
+        let body = serde_json::json!({ "data": "x".repeat(5000) });
+        let logged = redact_body(body.to_string().as_bytes());
+        assert!(logged.len() < 1100);
+        assert!(logged.ends_with("(5011 bytes)"));
+    }
+}
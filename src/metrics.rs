@@ -0,0 +1,459 @@
+//! Metrics hooks for REST requests and WebSocket traffic.
+//!
+//! A [`MetricsRecorder`] registered on the client is called once per REST
+//! request with its endpoint, status, latency and payload sizes, once per
+//! WebSocket message, and on every WebSocket connection attempt. Implement it
+//! to feed any metrics library, or use [`PrometheusRecorder`], which keeps
+//! counters and latency histograms in memory and renders them in the
+//! Prometheus text format:
+//!
+//! ```rust,no_run
+//! # async fn run() -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::metrics::PrometheusRecorder;
+//! use ekiden_rust_sdk::EkidenClientBuilder;
+//! use std::sync::Arc;
+//!
+//! let recorder = Arc::new(PrometheusRecorder::new());
+//! let client = EkidenClientBuilder::new()
+//!     .production()?
+//!     .metrics(recorder.clone())
+//!     .build()
+//!     .await?;
+//! client.get_markets(Default::default()).await?;
+//!
+//! // Serve this on /metrics
+//! let body = recorder.render();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Recorders run synchronously on the task making the request or reading the
+//! socket, so they should only update counters and hand off anything slow.
+
+use crate::error::ErrorKind;
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// One completed REST request
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct RequestMetrics {
+    /// API route relative to the base URL, e.g. `user/intent`
+    ///
+    /// Ids in the path are replaced by placeholders, e.g. `user/intent/{seq}`,
+    /// see [`route_template`].
+    pub endpoint: String,
+    pub method: reqwest::Method,
+    /// HTTP status, if a response was received
+    pub status: Option<u16>,
+    /// Category of the transport failure, if no response was received
+    pub error: Option<ErrorKind>,
+    /// Time from handing the request to the transport until its response
+    pub latency: Duration,
+    /// Size of the request body in bytes
+    pub request_bytes: usize,
+    /// Size of the response body in bytes, as received
+    pub response_bytes: usize,
+}
+
+/// Direction of a WebSocket message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl Direction {
+    /// Stable snake_case name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Inbound => "inbound",
+            Direction::Outbound => "outbound",
+        }
+    }
+}
+
+/// One WebSocket message sent or received
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct WsMessageMetrics {
+    pub direction: Direction,
+    /// Channel the message belongs to, if it names one
+    pub channel: Option<String>,
+    /// Size of the message payload in bytes
+    pub bytes: usize,
+}
+
+/// One WebSocket connection attempt
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct WsConnectMetrics {
+    /// Whether the client had connected before
+    pub reconnect: bool,
+    /// Category of the failure, if the attempt failed
+    pub error: Option<ErrorKind>,
+    /// Time the attempt took
+    pub latency: Duration,
+}
+
+/// Receives measurements from the client
+pub trait MetricsRecorder: Send + Sync + Debug {
+    /// Record a REST request that reached the transport
+    fn record_request(&self, _metrics: &RequestMetrics) {}
+
+    /// Record a WebSocket message
+    fn record_ws_message(&self, _metrics: &WsMessageMetrics) {}
+
+    /// Record a WebSocket connection attempt
+    fn record_ws_connect(&self, _metrics: &WsConnectMetrics) {}
+}
+
+/// Upper bounds of the request latency histogram buckets, in seconds
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    /// Requests by endpoint, method and status or error kind
+    requests: BTreeMap<(String, String, String), u64>,
+    latency: BTreeMap<String, Histogram>,
+    request_bytes: BTreeMap<String, u64>,
+    response_bytes: BTreeMap<String, u64>,
+    ws_messages: BTreeMap<(Direction, String), u64>,
+    ws_bytes: BTreeMap<Direction, u64>,
+    ws_connects: BTreeMap<&'static str, u64>,
+    ws_reconnects: u64,
+}
+
+/// In-memory recorder rendering the Prometheus text exposition format
+#[derive(Debug, Default)]
+pub struct PrometheusRecorder {
+    registry: Mutex<Registry>,
+}
+
+impl PrometheusRecorder {
+    /// Create a recorder with no samples
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render every metric in the Prometheus text format
+    pub fn render(&self) -> String {
+        let registry = self.registry.lock().unwrap();
+        let mut out = MetricWriter::default();
+
+        out.family(
+            "ekiden_client_requests_total",
+            "REST requests by endpoint, method and status",
+            "counter",
+        );
+        for ((endpoint, method, status), count) in &registry.requests {
+            out.sample(
+                "ekiden_client_requests_total",
+                &[
+                    ("endpoint", endpoint),
+                    ("method", method),
+                    ("status", status),
+                ],
+                *count as f64,
+            );
+        }
+
+        out.family(
+            "ekiden_client_request_duration_seconds",
+            "REST request latency",
+            "histogram",
+        );
+        for (endpoint, histogram) in &registry.latency {
+            for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+                out.sample(
+                    "ekiden_client_request_duration_seconds_bucket",
+                    &[("endpoint", endpoint), ("le", &bound.to_string())],
+                    *count as f64,
+                );
+            }
+            out.sample(
+                "ekiden_client_request_duration_seconds_bucket",
+                &[("endpoint", endpoint), ("le", "+Inf")],
+                histogram.count as f64,
+            );
+            out.sample(
+                "ekiden_client_request_duration_seconds_sum",
+                &[("endpoint", endpoint)],
+                histogram.sum,
+            );
+            out.sample(
+                "ekiden_client_request_duration_seconds_count",
+                &[("endpoint", endpoint)],
+                histogram.count as f64,
+            );
+        }
+
+        out.family(
+            "ekiden_client_request_bytes_total",
+            "REST request body bytes sent",
+            "counter",
+        );
+        for (endpoint, bytes) in &registry.request_bytes {
+            out.sample(
+                "ekiden_client_request_bytes_total",
+                &[("endpoint", endpoint)],
+                *bytes as f64,
+            );
+        }
+        out.family(
+            "ekiden_client_response_bytes_total",
+            "REST response body bytes received",
+            "counter",
+        );
+        for (endpoint, bytes) in &registry.response_bytes {
+            out.sample(
+                "ekiden_client_response_bytes_total",
+                &[("endpoint", endpoint)],
+                *bytes as f64,
+            );
+        }
+
+        out.family(
+            "ekiden_client_ws_messages_total",
+            "WebSocket messages by direction and channel",
+            "counter",
+        );
+        for ((direction, channel), count) in &registry.ws_messages {
+            out.sample(
+                "ekiden_client_ws_messages_total",
+                &[("direction", direction.as_str()), ("channel", channel)],
+                *count as f64,
+            );
+        }
+        out.family(
+            "ekiden_client_ws_bytes_total",
+            "WebSocket payload bytes by direction",
+            "counter",
+        );
+        for (direction, bytes) in &registry.ws_bytes {
+            out.sample(
+                "ekiden_client_ws_bytes_total",
+                &[("direction", direction.as_str())],
+                *bytes as f64,
+            );
+        }
+        out.family(
+            "ekiden_client_ws_connects_total",
+            "WebSocket connection attempts by outcome",
+            "counter",
+        );
+        for (outcome, count) in &registry.ws_connects {
+            out.sample(
+                "ekiden_client_ws_connects_total",
+                &[("outcome", outcome)],
+                *count as f64,
+            );
+        }
+        out.family(
+            "ekiden_client_ws_reconnects_total",
+            "WebSocket connection attempts after the first",
+            "counter",
+        );
+        out.sample(
+            "ekiden_client_ws_reconnects_total",
+            &[],
+            registry.ws_reconnects as f64,
+        );
+
+        out.0
+    }
+}
+
+impl MetricsRecorder for PrometheusRecorder {
+    fn record_request(&self, metrics: &RequestMetrics) {
+        let status = match (metrics.status, metrics.error) {
+            (Some(status), _) => status.to_string(),
+            (None, Some(kind)) => kind.as_str().to_string(),
+            (None, None) => "unknown".to_string(),
+        };
+        let endpoint = &metrics.endpoint;
+        let mut registry = self.registry.lock().unwrap();
+        *registry
+            .requests
+            .entry((endpoint.clone(), metrics.method.to_string(), status))
+            .or_default() += 1;
+        registry
+            .latency
+            .entry(endpoint.clone())
+            .or_default()
+            .observe(metrics.latency.as_secs_f64());
+        *registry.request_bytes.entry(endpoint.clone()).or_default() +=
+            metrics.request_bytes as u64;
+        *registry.response_bytes.entry(endpoint.clone()).or_default() +=
+            metrics.response_bytes as u64;
+    }
+
+    fn record_ws_message(&self, metrics: &WsMessageMetrics) {
+        let channel = metrics.channel.clone().unwrap_or_default();
+        let mut registry = self.registry.lock().unwrap();
+        *registry
+            .ws_messages
+            .entry((metrics.direction, channel))
+            .or_default() += 1;
+        *registry.ws_bytes.entry(metrics.direction).or_default() += metrics.bytes as u64;
+    }
+
+    fn record_ws_connect(&self, metrics: &WsConnectMetrics) {
+        let outcome = if metrics.error.is_some() {
+            "error"
+        } else {
+            "success"
+        };
+        let mut registry = self.registry.lock().unwrap();
+        *registry.ws_connects.entry(outcome).or_default() += 1;
+        if metrics.reconnect {
+            registry.ws_reconnects += 1;
+        }
+    }
+}
+
+#[derive(Default)]
+struct MetricWriter(String);
+
+impl MetricWriter {
+    fn family(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.0, "# HELP {} {}", name, help);
+        let _ = writeln!(self.0, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.0.push_str(name);
+        if !labels.is_empty() {
+            self.0.push('{');
+            for (i, (key, value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.0.push(',');
+                }
+                let _ = write!(self.0, "{}=\"{}\"", key, escape_label(value));
+            }
+            self.0.push('}');
+        }
+        let _ = writeln!(self.0, " {}", value);
+    }
+}
+
+/// Replace the ids in an API path with `{id}`
+///
+/// Segments that are numbers, `0x`-prefixed hex or UUIDs count as ids, so
+/// that labels keyed by route do not grow with every order or intent.
+/// Placeholders already in the path are kept.
+pub fn route_template(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            let hex = segment
+                .strip_prefix("0x")
+                .is_some_and(|hex| !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit()));
+            let number = !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit());
+            let uuid =
+                segment.len() == 36 && segment.bytes().all(|b| b == b'-' || b.is_ascii_hexdigit());
+            if hex || number || uuid {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_template_hides_ids() {
+        assert_eq!(route_template("market_info"), "market_info");
+        assert_eq!(route_template("user/intent/{seq}"), "user/intent/{seq}");
+        assert_eq!(route_template("user/intent/42"), "user/intent/{id}");
+        assert_eq!(
+            route_template("vaults/0x1111111111111111111111111111111111111111/history"),
+            "vaults/{id}/history"
+        );
+        assert_eq!(
+            route_template("orders/5f0c8e9a-7d3b-4c1e-9a2f-0b6d8e4c2a11"),
+            "orders/{id}"
+        );
+    }
+
+    #[test]
+    fn test_prometheus_rendering() {
+        let recorder = PrometheusRecorder::new();
+        recorder.record_request(&RequestMetrics {
+            endpoint: "market_info".to_string(),
+            method: reqwest::Method::GET,
+            status: Some(200),
+            error: None,
+            latency: Duration::from_millis(30),
+            request_bytes: 0,
+            response_bytes: 512,
+        });
+        recorder.record_request(&RequestMetrics {
+            endpoint: "market_info".to_string(),
+            method: reqwest::Method::GET,
+            status: None,
+            error: Some(ErrorKind::Timeout),
+            latency: Duration::from_secs(20),
+            request_bytes: 0,
+            response_bytes: 0,
+        });
+        recorder.record_ws_message(&WsMessageMetrics {
+            direction: Direction::Inbound,
+            channel: Some("trades/0x1".to_string()),
+            bytes: 64,
+        });
+        recorder.record_ws_connect(&WsConnectMetrics {
+            reconnect: true,
+            error: None,
+            latency: Duration::from_millis(5),
+        });
+
+        let text = recorder.render();
+        for line in [
+            r#"ekiden_client_requests_total{endpoint="market_info",method="GET",status="200"} 1"#,
+            r#"ekiden_client_requests_total{endpoint="market_info",method="GET",status="timeout"} 1"#,
+            r#"ekiden_client_request_duration_seconds_bucket{endpoint="market_info",le="0.05"} 1"#,
+            r#"ekiden_client_request_duration_seconds_bucket{endpoint="market_info",le="+Inf"} 2"#,
+            r#"ekiden_client_response_bytes_total{endpoint="market_info"} 512"#,
+            r#"ekiden_client_ws_messages_total{direction="inbound",channel="trades/0x1"} 1"#,
+            r#"ekiden_client_ws_connects_total{outcome="success"} 1"#,
+            "ekiden_client_ws_reconnects_total 1",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {}", line);
+        }
+    }
+}
@@ -0,0 +1,484 @@
+//! Reference market-making framework.
+//!
+//! [`Quoter`] keeps a ladder of two-sided quotes around the mid of a local
+//! [`Orderbook`], skews them by inventory and replaces them through a single
+//! batched intent whenever the mid moves far enough. Quote placement is driven
+//! by a [`QuoteModel`], so strategies can swap in their own pricing while
+//! reusing the order management loop.
+
+use crate::client::EkidenClient;
+use crate::error::{EkidenError, Result};
+use crate::orderbook::Orderbook;
+use crate::types::{ActionPayload, OrderSide, OrderType, WsEvent};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
+
+/// Configuration for a [`Quoter`]
+#[derive(Debug, Clone)]
+pub struct QuoterConfig {
+    /// Market to quote
+    pub market_addr: String,
+    /// User address used to follow position updates for inventory
+    pub user_addr: Option<String>,
+    /// Distance between the best bid and best ask quotes, in basis points
+    pub spread_bps: f64,
+    /// Number of quote levels on each side
+    pub levels: usize,
+    /// Distance between consecutive levels, in basis points
+    pub level_spacing_bps: f64,
+    /// Size quoted on each level, in base units
+    pub level_size: u64,
+    /// Absolute inventory at which quoting on the increasing side stops
+    pub max_inventory: u64,
+    /// Shift of the quote center at full inventory, in basis points
+    pub skew_bps: f64,
+    /// Minimum mid move before quotes are replaced, in basis points
+    pub refresh_threshold_bps: f64,
+    /// Interval for checking the WebSocket connection, measured on the client's clock
+    pub heartbeat_interval: Duration,
+}
+
+impl QuoterConfig {
+    /// Create a configuration with conservative defaults for a market
+    pub fn new<S: Into<String>>(market_addr: S, level_size: u64) -> Self {
+        Self {
+            market_addr: market_addr.into(),
+            user_addr: None,
+            spread_bps: 20.0,
+            levels: 1,
+            level_spacing_bps: 10.0,
+            level_size,
+            max_inventory: level_size.saturating_mul(10),
+            skew_bps: 10.0,
+            refresh_threshold_bps: 5.0,
+            heartbeat_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Follow position updates for this user to track inventory
+    pub fn with_user_addr<S: Into<String>>(mut self, user_addr: S) -> Self {
+        self.user_addr = Some(user_addr.into());
+        self
+    }
+
+    /// Set the spread between the best quotes
+    pub fn with_spread_bps(mut self, spread_bps: f64) -> Self {
+        self.spread_bps = spread_bps;
+        self
+    }
+
+    /// Set the ladder shape
+    pub fn with_levels(mut self, levels: usize, level_spacing_bps: f64) -> Self {
+        self.levels = levels;
+        self.level_spacing_bps = level_spacing_bps;
+        self
+    }
+
+    /// Set the inventory limit and the skew applied at that limit
+    pub fn with_inventory_skew(mut self, max_inventory: u64, skew_bps: f64) -> Self {
+        self.max_inventory = max_inventory;
+        self.skew_bps = skew_bps;
+        self
+    }
+
+    /// Set the mid move that triggers a quote refresh
+    pub fn with_refresh_threshold_bps(mut self, refresh_threshold_bps: f64) -> Self {
+        self.refresh_threshold_bps = refresh_threshold_bps;
+        self
+    }
+}
+
+/// A single quote to be placed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub side: OrderSide,
+    pub price: u64,
+    pub size: u64,
+}
+
+/// Two-sided quotes computed around a reference price
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuoteSet {
+    /// Price the ladder was built around
+    pub reference_price: f64,
+    /// Bids, best first
+    pub bids: Vec<Quote>,
+    /// Asks, best first
+    pub asks: Vec<Quote>,
+}
+
+impl QuoteSet {
+    /// Iterate over all quotes, bids first
+    pub fn iter(&self) -> impl Iterator<Item = &Quote> {
+        self.bids.iter().chain(self.asks.iter())
+    }
+
+    /// Check if the set contains no quotes
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+}
+
+/// Pricing model used by the [`Quoter`]
+pub trait QuoteModel: Send + Sync {
+    /// Compute quotes for the current book and signed inventory
+    fn quotes(&self, book: &Orderbook, inventory: i64, config: &QuoterConfig) -> Option<QuoteSet>;
+}
+
+/// Default model: symmetric ladder around the mid, shifted against inventory
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InventorySkewModel;
+
+impl QuoteModel for InventorySkewModel {
+    fn quotes(&self, book: &Orderbook, inventory: i64, config: &QuoterConfig) -> Option<QuoteSet> {
+        let mid = book.mid_price()?;
+        let max_inventory = config.max_inventory.max(1) as f64;
+        let ratio = (inventory as f64 / max_inventory).clamp(-1.0, 1.0);
+        let center = mid * (1.0 - ratio * config.skew_bps / 10_000.0);
+        let half_spread = config.spread_bps / 2.0;
+
+        let mut quotes = QuoteSet {
+            reference_price: center,
+            ..Default::default()
+        };
+
+        for level in 0..config.levels {
+            let offset_bps = half_spread + level as f64 * config.level_spacing_bps;
+            let offset = center * offset_bps / 10_000.0;
+
+            if inventory < config.max_inventory as i64 {
+                let price = (center - offset).floor();
+                if price >= 1.0 {
+                    quotes.bids.push(Quote {
+                        side: OrderSide::Buy,
+                        price: price as u64,
+                        size: config.level_size,
+                    });
+                }
+            }
+
+            if inventory > -(config.max_inventory as i64) {
+                quotes.asks.push(Quote {
+                    side: OrderSide::Sell,
+                    price: (center + offset).ceil() as u64,
+                    size: config.level_size,
+                });
+            }
+        }
+
+        Some(quotes)
+    }
+}
+
+/// Two-sided quoting engine driven by the local orderbook
+pub struct Quoter {
+    client: EkidenClient,
+    config: QuoterConfig,
+    model: Box<dyn QuoteModel>,
+    book: Orderbook,
+    inventory: i64,
+    live_orders: Vec<String>,
+    last_reference: Option<f64>,
+}
+
+impl Quoter {
+    /// Create a quoter using the [`InventorySkewModel`]
+    pub fn new(client: EkidenClient, config: QuoterConfig) -> Self {
+        let book = Orderbook::new(config.market_addr.clone());
+        Self {
+            client,
+            config,
+            model: Box::new(InventorySkewModel),
+            book,
+            inventory: 0,
+            live_orders: Vec::new(),
+            last_reference: None,
+        }
+    }
+
+    /// Replace the pricing model
+    pub fn with_model<M: QuoteModel + 'static>(mut self, model: M) -> Self {
+        self.model = Box::new(model);
+        self
+    }
+
+    /// Get the quoter configuration
+    pub fn config(&self) -> &QuoterConfig {
+        &self.config
+    }
+
+    /// Get the local orderbook
+    pub fn orderbook(&self) -> &Orderbook {
+        &self.book
+    }
+
+    /// Get the current signed inventory (positive when long)
+    pub fn inventory(&self) -> i64 {
+        self.inventory
+    }
+
+    /// Override the current inventory
+    pub fn set_inventory(&mut self, inventory: i64) {
+        self.inventory = inventory;
+    }
+
+    /// Get the sids of quotes currently resting on the book
+    pub fn live_orders(&self) -> &[String] {
+        &self.live_orders
+    }
+
+    /// Compute the quotes the model wants for the current state
+    pub fn compute_quotes(&self) -> Option<QuoteSet> {
+        self.model.quotes(&self.book, self.inventory, &self.config)
+    }
+
+    /// Apply a WebSocket event to the local state.
+    ///
+    /// Returns `true` if the orderbook or inventory changed.
+    pub fn on_event(&mut self, event: &WsEvent) -> bool {
+        match event {
+            WsEvent::PositionUpdate { position }
+                if position.market_addr == self.config.market_addr =>
+            {
+                self.inventory = position.signed_size();
+                true
+            }
+            _ => self.book.apply_event(event),
+        }
+    }
+
+    /// Check whether the reference price moved enough to requote
+    pub fn needs_refresh(&self, quotes: &QuoteSet) -> bool {
+        match self.last_reference {
+            None => true,
+            Some(last) => {
+                let moved_bps = (quotes.reference_price - last).abs() / last * 10_000.0;
+                moved_bps >= self.config.refresh_threshold_bps
+            }
+        }
+    }
+
+    /// Cancel live quotes and place fresh ones in a single intent
+    pub async fn refresh(&mut self) -> Result<()> {
+        let Some(quotes) = self.compute_quotes() else {
+            return Ok(());
+        };
+
+        let market_addr = self.config.market_addr.as_str();
+        let mut actions: Vec<ActionPayload> = self
+            .live_orders
+            .iter()
+            .map(|sid| ActionPayload::cancel_order(market_addr, sid))
+            .collect();
+        actions.extend(quotes.iter().map(|quote| {
+            ActionPayload::place_order(
+                market_addr,
+                quote.side,
+                OrderType::Limit,
+                quote.price,
+                quote.size,
+            )
+        }));
+
+        if actions.is_empty() {
+            return Ok(());
+        }
+
+        let cancelled = self.live_orders.len();
+        let response = self.client.send_actions(actions).await?;
+
+        // Quotes whose cancel failed are still resting and stay tracked
+        let (cancels, places) = response
+            .outputs
+            .split_at(cancelled.min(response.outputs.len()));
+        let mut live_orders = Vec::new();
+        for (index, sid) in self.live_orders.iter().enumerate() {
+            let error = cancels
+                .get(index)
+                .map_or(Some("no output"), |output| output.error());
+            if let Some(error) = error {
+                warn!(
+                    "Failed to cancel quote {} on {}: {}",
+                    sid, market_addr, error
+                );
+                live_orders.push(sid.clone());
+            }
+        }
+        live_orders.extend(
+            places
+                .iter()
+                .filter(|output| output.action_type == "place_order")
+                .filter_map(|output| output.sid().map(str::to_string)),
+        );
+        self.live_orders = live_orders;
+        self.last_reference = Some(quotes.reference_price);
+
+        debug!(
+            "Requoted {} around {:.2} with {} live orders",
+            market_addr,
+            quotes.reference_price,
+            self.live_orders.len()
+        );
+        Ok(())
+    }
+
+    /// Cancel all live quotes
+    pub async fn cancel_all(&mut self) -> Result<()> {
+        if self.live_orders.is_empty() {
+            return Ok(());
+        }
+
+        let market_addr = self.config.market_addr.as_str();
+        let actions = self
+            .live_orders
+            .iter()
+            .map(|sid| ActionPayload::cancel_order(market_addr, sid))
+            .collect();
+        self.client.send_actions(actions).await?;
+
+        info!(
+            "Cancelled {} quotes on {}",
+            self.live_orders.len(),
+            market_addr
+        );
+        self.live_orders.clear();
+        self.last_reference = None;
+        Ok(())
+    }
+
+    /// Run the quoting loop until the WebSocket connection drops.
+    ///
+    /// The WebSocket must already be connected. On disconnect all live quotes
+    /// are cancelled through the REST API before returning.
+    pub async fn run(&mut self) -> Result<()> {
+        let mut book_rx = self
+            .client
+            .subscribe_orderbook(&self.config.market_addr)
+            .await?;
+        let mut user_rx = match &self.config.user_addr {
+            Some(user_addr) => Some(self.client.subscribe_user(user_addr).await?),
+            None => None,
+        };
+        let clock = self.client.clock();
+        let mut heartbeat = clock.sleep(self.config.heartbeat_interval);
+
+        let result = loop {
+            let event = tokio::select! {
+                event = book_rx.recv() => event,
+                event = async {
+                    match user_rx.as_mut() {
+                        Some(rx) => rx.recv().await,
+                        None => std::future::pending().await,
+                    }
+                } => event,
+                _ = &mut heartbeat => {
+                    heartbeat = clock.sleep(self.config.heartbeat_interval);
+                    if !self.client.is_websocket_connected().await {
+                        break Err(EkidenError::ConnectionClosed);
+                    }
+                    continue;
+                }
+            };
+
+            match event {
+                Ok(event) => {
+                    if !self.on_event(&event) {
+                        continue;
+                    }
+                    let Some(quotes) = self.compute_quotes() else {
+                        continue;
+                    };
+                    if self.needs_refresh(&quotes) {
+                        if let Err(e) = self.refresh().await {
+                            warn!("Failed to refresh quotes: {}", e);
+                        }
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("Quoter lagged behind by {} events", missed);
+                }
+                Err(RecvError::Closed) => break Err(EkidenError::ConnectionClosed),
+            }
+        };
+
+        warn!("Quoter stopping, cancelling live quotes");
+        self.cancel_all().await?;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OrderbookLevel;
+
+    fn book(bid: u64, ask: u64) -> Orderbook {
+        let mut book = Orderbook::new("0x1");
+        book.apply_snapshot(
+            &[OrderbookLevel {
+                price: bid,
+                size: 1,
+            }],
+            &[OrderbookLevel {
+                price: ask,
+                size: 1,
+            }],
+            0,
+        );
+        book
+    }
+
+    #[test]
+    fn test_symmetric_quotes_without_inventory() {
+        let config = QuoterConfig::new("0x1", 10)
+            .with_spread_bps(20.0)
+            .with_levels(2, 10.0);
+        let quotes = InventorySkewModel
+            .quotes(&book(9_990, 10_010), 0, &config)
+            .unwrap();
+
+        assert_eq!(quotes.reference_price, 10_000.0);
+        assert_eq!(quotes.bids.len(), 2);
+        assert_eq!(quotes.asks.len(), 2);
+        assert_eq!(quotes.bids[0].price, 9_990);
+        assert_eq!(quotes.asks[0].price, 10_010);
+        assert_eq!(quotes.bids[1].price, 9_980);
+        assert_eq!(quotes.asks[1].price, 10_020);
+    }
+
+    #[test]
+    fn test_long_inventory_skews_quotes_down() {
+        let config = QuoterConfig::new("0x1", 10).with_inventory_skew(100, 50.0);
+        let quotes = InventorySkewModel
+            .quotes(&book(9_990, 10_010), 50, &config)
+            .unwrap();
+
+        assert!(quotes.reference_price < 10_000.0);
+        assert!(quotes.asks[0].price < 10_010);
+    }
+
+    #[test]
+    fn test_max_inventory_stops_one_side() {
+        let config = QuoterConfig::new("0x1", 10).with_inventory_skew(100, 50.0);
+        let quotes = InventorySkewModel
+            .quotes(&book(9_990, 10_010), 100, &config)
+            .unwrap();
+        assert!(quotes.bids.is_empty());
+        assert!(!quotes.asks.is_empty());
+
+        let quotes = InventorySkewModel
+            .quotes(&book(9_990, 10_010), -100, &config)
+            .unwrap();
+        assert!(!quotes.bids.is_empty());
+        assert!(quotes.asks.is_empty());
+    }
+
+    #[test]
+    fn test_no_quotes_without_two_sided_book() {
+        let config = QuoterConfig::new("0x1", 10);
+        assert!(InventorySkewModel
+            .quotes(&Orderbook::new("0x1"), 0, &config)
+            .is_none());
+    }
+}
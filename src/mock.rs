@@ -0,0 +1,1726 @@
+//! In-process mock of the Ekiden Gateway for integration tests.
+//!
+//! Enabled with the `mock` feature. [`MockGateway::start`] serves the REST
+//! and WebSocket contract on an ephemeral localhost port: `authorize` issues a
+//! token for any well-formed key, `market_info`, `orderbook`, `orders`, `fills`, positions
+//! and the portfolio are served from scripted state, and `user/intent`
+//! checks the signature of authorized users and places and cancels orders.
+//! `user/intent/simulate` matches orders against the seeded orderbook, and
+//! `user/intent/{seq}` reports executed intents as final. Scenarios are scripted by seeding state, queuing
+//! canned or failing responses, and pushing WebSocket events:
+//!
+//! ```rust,no_run
+//! # async fn run() -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::mock::{self, MockGateway};
+//!
+//! let gateway = MockGateway::start().await?;
+//! gateway
+//!     .add_market(mock::market("0x1111111111111111111111111111111111111111", "BTC-USD"))
+//!     .await;
+//! gateway.fail_next("market_info", 503, "maintenance").await;
+//!
+//! let client = gateway.client().await?;
+//! assert!(client.get_market_by_symbol("BTC-USD").await.is_err());
+//! assert!(client.get_market_by_symbol("BTC-USD").await?.is_some());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::auth;
+use crate::client::{EkidenClient, EkidenClientBuilder};
+use crate::config::EkidenConfig;
+use crate::error::Result;
+use crate::types::{
+    ActionPayload, AuthorizeParams, AuthorizeResponse, FillResponse, IntentOutput,
+    IntentSimulation, MarketResponse, OrderResponse, OrderbookResponse, PortfolioResponse,
+    PortfolioSummary, PositionResponse, SendIntentParams, SendIntentResponse, SimulatedFill,
+    SimulatedOutput, WsEvent, WsRequest, WsResponse,
+};
+use crate::utils::Crypto;
+use axum::body::Bytes;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{any, get};
+use axum::{Json, Router};
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::task::JoinHandle;
+
+/// A request received by the mock, for assertions
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedRequest {
+    pub method: String,
+    /// Path below `/api/v1/`
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub body: Option<Value>,
+}
+
+/// A canned response for a method and path
+#[derive(Debug, Clone)]
+struct Canned {
+    status: u16,
+    body: Value,
+}
+
+#[derive(Default)]
+struct MockData {
+    markets: Vec<MarketResponse>,
+    orders: Vec<OrderResponse>,
+    fills: Vec<FillResponse>,
+    orderbooks: HashMap<String, OrderbookResponse>,
+    positions: HashMap<String, Vec<PositionResponse>>,
+    portfolios: HashMap<String, PortfolioResponse>,
+    /// Issued token to user address
+    tokens: HashMap<String, String>,
+    /// Public key of each authorized user address
+    public_keys: HashMap<String, String>,
+    /// One-shot responses served before the built-in handlers
+    queued: HashMap<(String, String), VecDeque<Canned>>,
+    /// Responses served for every matching request
+    fixed: HashMap<(String, String), Canned>,
+    /// Events sent to a connection right after it subscribes to a channel
+    on_subscribe: HashMap<String, Vec<WsEvent>>,
+    requests: Vec<ReceivedRequest>,
+    intents: Vec<SendIntentParams>,
+    /// Response of every executed intent, by seq
+    intent_results: HashMap<u64, SendIntentResponse>,
+    next_seq: u64,
+    /// Nonce the next intent must carry, if it carries one
+    next_nonce: u64,
+}
+
+struct MockState {
+    data: Mutex<MockData>,
+    events: broadcast::Sender<(String, WsEvent)>,
+    disconnect: broadcast::Sender<()>,
+}
+
+impl MockState {
+    fn publish(&self, channel: String, event: WsEvent) {
+        let _ = self.events.send((channel, event));
+    }
+}
+
+/// Build a market with typical decimals and margin settings
+pub fn market(addr: &str, symbol: &str) -> MarketResponse {
+    MarketResponse {
+        addr: addr.to_string(),
+        symbol: symbol.to_string(),
+        base_addr: String::new(),
+        base_decimals: 8,
+        quote_addr: String::new(),
+        quote_decimals: 6,
+        min_order_size: 1_000,
+        max_leverage: 20,
+        initial_margin_ratio: 0.05,
+        maintenance_margin_ratio: 0.03,
+        mark_price: 0,
+        oracle_price: 0,
+        open_interest: 0,
+        funding_index: 0,
+        funding_epoch: 0,
+        root: String::new(),
+        epoch: 0,
+        created_at: String::new(),
+        updated_at: String::new(),
+    }
+}
+
+/// A running mock gateway; the server stops when this is dropped
+pub struct MockGateway {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    task: JoinHandle<()>,
+}
+
+impl std::fmt::Debug for MockGateway {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockGateway")
+            .field("addr", &self.addr)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for MockGateway {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl MockGateway {
+    /// Start the mock on an ephemeral localhost port
+    pub async fn start() -> Result<Self> {
+        let (events, _) = broadcast::channel(1024);
+        let (disconnect, _) = broadcast::channel(1);
+        let state = Arc::new(MockState {
+            data: Mutex::new(MockData::default()),
+            events,
+            disconnect,
+        });
+
+        let router = Router::new()
+            .route("/ws", get(ws_handler))
+            .route("/api/v1/*path", any(rest_handler))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+        let addr = listener.local_addr()?;
+        let task = tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+
+        Ok(Self { addr, state, task })
+    }
+
+    /// Address the mock listens on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// REST base URL, e.g. `http://127.0.0.1:PORT/api/v1`
+    pub fn base_url(&self) -> String {
+        format!("http://{}/api/v1", self.addr)
+    }
+
+    /// WebSocket URL
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}/ws", self.addr)
+    }
+
+    /// Client configuration pointing at the mock
+    pub fn config(&self) -> Result<EkidenConfig> {
+        EkidenConfig::new(self.base_url())?.with_ws_url(self.ws_url())
+    }
+
+    /// Build a client for the mock with a fresh random key
+    pub async fn client(&self) -> Result<EkidenClient> {
+        let private_key = format!("0x{}", hex::encode(rand::random::<[u8; 32]>()));
+        EkidenClientBuilder::new()
+            .config(self.config()?)
+            .private_key(private_key)
+            .build()
+            .await
+    }
+
+    // ===== Scripting =====
+
+    /// Add a market served by `market_info`
+    pub async fn add_market(&self, market: MarketResponse) {
+        self.state.data.lock().await.markets.push(market);
+    }
+
+    /// Add a resting order served by `orders`
+    pub async fn add_order(&self, order: OrderResponse) {
+        self.state.data.lock().await.orders.push(order);
+    }
+
+    /// Add a fill served by `fills`
+    pub async fn add_fill(&self, fill: FillResponse) {
+        self.state.data.lock().await.fills.push(fill);
+    }
+
+    /// Set the orderbook snapshot served by `orderbook` for its market
+    pub async fn set_orderbook(&self, orderbook: OrderbookResponse) {
+        self.state
+            .data
+            .lock()
+            .await
+            .orderbooks
+            .insert(orderbook.market_addr.clone(), orderbook);
+    }
+
+    /// Set the positions served to a user
+    pub async fn set_positions(&self, user_addr: &str, positions: Vec<PositionResponse>) {
+        self.state
+            .data
+            .lock()
+            .await
+            .positions
+            .insert(user_addr.to_string(), positions);
+    }
+
+    /// Set the portfolio served to a user
+    pub async fn set_portfolio(&self, user_addr: &str, portfolio: PortfolioResponse) {
+        self.state
+            .data
+            .lock()
+            .await
+            .portfolios
+            .insert(user_addr.to_string(), portfolio);
+    }
+
+    /// Serve `body` with `status` for the next request to `method path`
+    ///
+    /// `path` is relative to `/api/v1/`, e.g. `"orders"`. Queued responses are
+    /// served in order before any other handling.
+    pub async fn enqueue_response<T: Serialize>(
+        &self,
+        method: &str,
+        path: &str,
+        status: u16,
+        body: &T,
+    ) {
+        let body = serde_json::to_value(body).unwrap_or(Value::Null);
+        self.state
+            .data
+            .lock()
+            .await
+            .queued
+            .entry((method.to_ascii_uppercase(), path.to_string()))
+            .or_default()
+            .push_back(Canned { status, body });
+    }
+
+    /// Set the nonce the next intent must carry, as if another session had sent intents
+    pub async fn set_intent_nonce(&self, nonce: u64) {
+        self.state.data.lock().await.next_nonce = nonce;
+    }
+
+    /// Fail the next request to `path`, whatever its method
+    pub async fn fail_next(&self, path: &str, status: u16, message: &str) {
+        self.enqueue_response("*", path, status, &json!({ "error": message }))
+            .await;
+    }
+
+    /// Serve `body` for every request to `method path`, e.g. for endpoints
+    /// the mock does not model such as `candles`
+    pub async fn set_response<T: Serialize>(
+        &self,
+        method: &str,
+        path: &str,
+        status: u16,
+        body: &T,
+    ) {
+        let body = serde_json::to_value(body).unwrap_or(Value::Null);
+        self.state.data.lock().await.fixed.insert(
+            (method.to_ascii_uppercase(), path.to_string()),
+            Canned { status, body },
+        );
+    }
+
+    /// Send `events` to each connection as soon as it subscribes to `channel`
+    pub async fn on_subscribe(&self, channel: &str, events: Vec<WsEvent>) {
+        self.state
+            .data
+            .lock()
+            .await
+            .on_subscribe
+            .insert(channel.to_string(), events);
+    }
+
+    /// Send an event to every connection subscribed to `channel`
+    pub fn push_event(&self, channel: &str, event: WsEvent) {
+        self.state.publish(channel.to_string(), event);
+    }
+
+    /// Close every open WebSocket connection
+    pub fn disconnect_all(&self) {
+        let _ = self.state.disconnect.send(());
+    }
+
+    // ===== Inspection =====
+
+    /// REST requests received so far
+    pub async fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.data.lock().await.requests.clone()
+    }
+
+    /// Intents received so far
+    pub async fn intents(&self) -> Vec<SendIntentParams> {
+        self.state.data.lock().await.intents.clone()
+    }
+
+    /// Orders currently known to the mock, including cancelled ones
+    pub async fn orders(&self) -> Vec<OrderResponse> {
+        self.state.data.lock().await.orders.clone()
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn reply(status: u16, body: Value) -> Response {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, Json(body)).into_response()
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+async fn rest_handler(
+    State(state): State<Arc<MockState>>,
+    method: Method,
+    Path(path): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let body: Option<Value> = if body.is_empty() {
+        None
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(body) => Some(body),
+            Err(e) => return error(StatusCode::BAD_REQUEST, &e.to_string()),
+        }
+    };
+
+    let mut data = state.data.lock().await;
+    data.requests.push(ReceivedRequest {
+        method: method.to_string(),
+        path: path.clone(),
+        query: query.clone(),
+        body: body.clone(),
+    });
+
+    for key in [
+        (method.to_string(), path.clone()),
+        ("*".to_string(), path.clone()),
+    ] {
+        if let Some(canned) = data.queued.get_mut(&key).and_then(|q| q.pop_front()) {
+            return reply(canned.status, canned.body);
+        }
+    }
+    if let Some(canned) = data.fixed.get(&(method.to_string(), path.clone())) {
+        return reply(canned.status, canned.body.clone());
+    }
+
+    let user = bearer(&headers).and_then(|token| data.tokens.get(token).cloned());
+    let filter = |key: &str, value: &str| query.get(key).map_or(true, |v| v == value);
+
+    match (method.as_str(), path.as_str()) {
+        ("POST", "authorize") => {
+            let Some(params) = body.and_then(|b| serde_json::from_value::<AuthorizeParams>(b).ok())
+            else {
+                return error(StatusCode::BAD_REQUEST, "Invalid authorize params");
+            };
+            let Ok(user_addr) = Crypto::generate_address_from_public_key(&params.public_key) else {
+                return error(StatusCode::UNAUTHORIZED, "Invalid public key");
+            };
+            let token = format!("mock-{}", hex::encode(rand::random::<[u8; 16]>()));
+            data.public_keys
+                .insert(user_addr.clone(), params.public_key);
+            data.tokens.insert(token.clone(), user_addr);
+            Json(AuthorizeResponse { token }).into_response()
+        }
+        ("GET", "market_info") => {
+            let markets: Vec<_> = data
+                .markets
+                .iter()
+                .filter(|m| filter("market_addr", &m.addr) && filter("symbol", &m.symbol))
+                .cloned()
+                .collect();
+            Json(markets).into_response()
+        }
+        ("GET", "orders") => {
+            let orders: Vec<_> = data
+                .orders
+                .iter()
+                .filter(|o| {
+                    filter("market_addr", &o.market_addr)
+                        && query
+                            .get("side")
+                            .map_or(true, |side| side.eq_ignore_ascii_case(&o.side))
+                })
+                .cloned()
+                .collect();
+            Json(orders).into_response()
+        }
+        ("GET", "fills") => {
+            let fills: Vec<_> = data
+                .fills
+                .iter()
+                .filter(|f| filter("market_addr", &f.market_addr))
+                .cloned()
+                .collect();
+            Json(fills).into_response()
+        }
+        ("GET", "orderbook") => {
+            let book = query
+                .get("market_addr")
+                .and_then(|market_addr| data.orderbooks.get(market_addr));
+            match book {
+                Some(book) => {
+                    let depth = query
+                        .get("depth")
+                        .and_then(|d| d.parse().ok())
+                        .unwrap_or(usize::MAX);
+                    let mut book = book.clone();
+                    book.bids.truncate(depth);
+                    book.asks.truncate(depth);
+                    Json(book).into_response()
+                }
+                None => error(StatusCode::NOT_FOUND, "Unknown market"),
+            }
+        }
+        (_, p) if p.starts_with("user/") && user.is_none() => {
+            error(StatusCode::UNAUTHORIZED, "Missing or invalid token")
+        }
+        ("GET", "user/positions") => {
+            let user = user.unwrap_or_default();
+            let positions: Vec<_> = data
+                .positions
+                .get(&user)
+                .into_iter()
+                .flatten()
+                .filter(|p| filter("market_addr", &p.market_addr))
+                .cloned()
+                .collect();
+            Json(positions).into_response()
+        }
+        ("GET", "user/portfolio") => {
+            let user = user.unwrap_or_default();
+            let portfolio =
+                data.portfolios
+                    .get(&user)
+                    .cloned()
+                    .unwrap_or_else(|| PortfolioResponse {
+                        summary: PortfolioSummary {
+                            total_value: 0,
+                            available_balance: 0,
+                            locked_balance: 0,
+                            unrealized_pnl: 0,
+                            margin_used: 0,
+                            margin_available: 0,
+                        },
+                        positions: Vec::new(),
+                        vaults: Vec::new(),
+                    });
+            Json(portfolio).into_response()
+        }
+        ("GET", "user/nonce") => Json(json!({ "nonce": data.next_nonce })).into_response(),
+        ("POST", "user/intent") => {
+            let Some(intent) =
+                body.and_then(|b| serde_json::from_value::<SendIntentParams>(b).ok())
+            else {
+                return error(StatusCode::BAD_REQUEST, "Invalid intent");
+            };
+            if !signed_by(&data, user.as_deref(), &intent) {
+                return error(StatusCode::UNAUTHORIZED, "Invalid intent signature");
+            }
+            if let Some(nonce) = intent.nonce {
+                if nonce != data.next_nonce {
+                    let body = json!({
+                        "code": "invalid_nonce",
+                        "message": format!("Expected nonce {}", data.next_nonce),
+                    });
+                    return (StatusCode::BAD_REQUEST, Json(body)).into_response();
+                }
+                data.next_nonce += 1;
+            }
+            let user = user.unwrap_or_default();
+            data.next_seq += 1;
+            let seq = data.next_seq;
+            let outputs = intent
+                .actions
+                .iter()
+                .enumerate()
+                .map(|(i, action)| execute(&state, &mut data, &user, seq, i, action))
+                .collect();
+            data.intents.push(intent);
+            let response = SendIntentResponse {
+                seq,
+                status: "success".to_string(),
+                outputs,
+            };
+            data.intent_results.insert(seq, response.clone());
+            Json(response).into_response()
+        }
+        ("GET", intent_path) if intent_path.starts_with("user/intent/") => {
+            let result = intent_path["user/intent/".len()..]
+                .parse::<u64>()
+                .ok()
+                .and_then(|seq| data.intent_results.get(&seq));
+            match result {
+                Some(result) => Json(result.clone()).into_response(),
+                None => error(StatusCode::NOT_FOUND, "Unknown intent"),
+            }
+        }
+        ("POST", "user/intent/simulate") => {
+            let Some(intent) =
+                body.and_then(|b| serde_json::from_value::<SendIntentParams>(b).ok())
+            else {
+                return error(StatusCode::BAD_REQUEST, "Invalid intent");
+            };
+            if !signed_by(&data, user.as_deref(), &intent) {
+                return error(StatusCode::UNAUTHORIZED, "Invalid intent signature");
+            }
+            let outputs: Vec<_> = intent
+                .actions
+                .iter()
+                .map(|action| simulate(&data, action))
+                .collect();
+            Json(IntentSimulation {
+                accepted: outputs.iter().all(|output| output.error.is_none()),
+                outputs,
+                total_fee: 0,
+                margin_required: 0,
+            })
+            .into_response()
+        }
+        _ => error(
+            StatusCode::NOT_FOUND,
+            &format!("Not mocked: {} {}", method, path),
+        ),
+    }
+}
+
+/// Whether an intent is signed by the user's key; intents of unknown users pass
+fn signed_by(data: &MockData, user: Option<&str>, intent: &SendIntentParams) -> bool {
+    match user.and_then(|user| data.public_keys.get(user)) {
+        Some(public_key) => auth::verify_intent(intent, public_key).unwrap_or(false),
+        None => true,
+    }
+}
+
+/// Match a `place_order` action against the seeded orderbook, without fees or margin
+fn simulate(data: &MockData, action: &ActionPayload) -> SimulatedOutput {
+    let mut output = SimulatedOutput {
+        action_type: action.action_type.clone(),
+        ..SimulatedOutput::default()
+    };
+    if action.action_type != "place_order" {
+        return output;
+    }
+    let field = |key: &str| match action.data.get(key) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => n.to_string(),
+        _ => String::new(),
+    };
+    let number = |key: &str| field(key).parse::<u64>().unwrap_or(0);
+    let market = field("order_type") == "market";
+    let buy = field("side") == "buy";
+    let (price, mut remaining) = (number("price"), number("size"));
+
+    let mut levels = data
+        .orderbooks
+        .get(&field("market_addr"))
+        .map(|book| {
+            if buy {
+                book.asks.clone()
+            } else {
+                book.bids.clone()
+            }
+        })
+        .unwrap_or_default();
+    levels.sort_by_key(|level| level.price);
+    if !buy {
+        levels.reverse();
+    }
+    for level in levels {
+        let crosses = if buy {
+            level.price <= price
+        } else {
+            level.price >= price
+        };
+        if remaining == 0 || !(market || crosses) {
+            break;
+        }
+        let size = remaining.min(level.size);
+        remaining -= size;
+        output.fills.push(SimulatedFill {
+            price: level.price,
+            size,
+            fee: 0,
+        });
+    }
+    if market && remaining > 0 {
+        output.error = Some("Insufficient liquidity".to_string());
+    }
+    output
+}
+
+/// Apply one intent action to the mock state
+fn execute(
+    state: &MockState,
+    data: &mut MockData,
+    user: &str,
+    seq: u64,
+    index: usize,
+    action: &ActionPayload,
+) -> IntentOutput {
+    let field = |key: &str| {
+        action.data.get(key).and_then(|v| match v {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        })
+    };
+    let number = |key: &str| field(key).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+    let flag = |key: &str| action.data.get(key).and_then(Value::as_bool) == Some(true);
+
+    let result = match action.action_type.as_str() {
+        "place_order" => {
+            let order = OrderResponse {
+                sid: format!("mock-{}-{}", seq, index),
+                side: field("side").unwrap_or_default(),
+                size: number("size"),
+                price: number("price"),
+                leverage: number("leverage").max(1),
+                order_type: field("order_type").unwrap_or_default(),
+                status: "placed".to_string(),
+                user_addr: user.to_string(),
+                market_addr: field("market_addr").unwrap_or_default(),
+                seq,
+                timestamp: now_millis(),
+                client_order_id: field("client_order_id"),
+                time_in_force: field("time_in_force")
+                    .and_then(|v| serde_json::from_value(Value::String(v)).ok()),
+                post_only: Some(flag("post_only")),
+                reduce_only: Some(flag("reduce_only")),
+            };
+            let sid = order.sid.clone();
+            data.orders.push(order.clone());
+            state.publish(format!("user/{}", user), WsEvent::OrderUpdate { order });
+            json!({ "sid": sid })
+        }
+        "place_trigger_order" => {
+            // Trigger orders rest at their trigger price until the test settles them
+            let order = OrderResponse {
+                sid: format!("mock-{}-{}", seq, index),
+                side: field("side").unwrap_or_default(),
+                size: number("size"),
+                price: number("trigger_price"),
+                leverage: 1,
+                order_type: field("kind").unwrap_or_default(),
+                status: "placed".to_string(),
+                user_addr: user.to_string(),
+                market_addr: field("market_addr").unwrap_or_default(),
+                seq,
+                timestamp: now_millis(),
+                client_order_id: None,
+                time_in_force: None,
+                post_only: Some(false),
+                reduce_only: Some(flag("reduce_only")),
+            };
+            let sid = order.sid.clone();
+            data.orders.push(order.clone());
+            state.publish(format!("user/{}", user), WsEvent::OrderUpdate { order });
+            json!({ "sid": sid })
+        }
+        "cancel_order" => {
+            let sid = field("sid").unwrap_or_default();
+            match data.orders.iter_mut().find(|o| o.sid == sid) {
+                Some(order) => {
+                    order.status = "cancelled".to_string();
+                    let order = order.clone();
+                    state.publish(format!("user/{}", user), WsEvent::OrderUpdate { order });
+                    json!({ "sid": sid })
+                }
+                None => json!({ "error": format!("Unknown order {}", sid) }),
+            }
+        }
+        "amend_order" => {
+            let sid = field("sid").unwrap_or_default();
+            match data
+                .orders
+                .iter_mut()
+                .find(|o| o.sid == sid && o.status == "placed")
+            {
+                Some(order) => {
+                    order.price = number("price");
+                    order.size = number("size");
+                    let order = order.clone();
+                    state.publish(format!("user/{}", user), WsEvent::OrderUpdate { order });
+                    json!({ "sid": sid })
+                }
+                None => json!({ "error": format!("Unknown order {}", sid) }),
+            }
+        }
+        _ => json!({}),
+    };
+
+    IntentOutput {
+        action_type: action.action_type.clone(),
+        result,
+    }
+}
+
+async fn ws_handler(State(state): State<Arc<MockState>>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(state, socket))
+}
+
+async fn handle_socket(state: Arc<MockState>, socket: WebSocket) {
+    let (mut sink, mut stream) = socket.split();
+    let (out_tx, mut out_rx) = mpsc::channel::<WsResponse>(1024);
+    let mut disconnect = state.disconnect.subscribe();
+
+    let writer = tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                response = out_rx.recv() => {
+                    let Some(response) = response else { break };
+                    let Ok(text) = serde_json::to_string(&response) else {
+                        continue;
+                    };
+                    if sink.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                _ = disconnect.recv() => {
+                    let _ = sink.send(Message::Close(None)).await;
+                    break;
+                }
+            }
+        }
+    });
+
+    let subscribed: Arc<std::sync::Mutex<HashSet<String>>> = Arc::default();
+    let forwarder = {
+        let mut events = state.events.subscribe();
+        let subscribed = subscribed.clone();
+        let out_tx = out_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                let (channel, data) = match events.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let wanted = subscribed
+                    .lock()
+                    .map(|s| s.contains(&channel))
+                    .unwrap_or(false);
+                if wanted
+                    && out_tx
+                        .send(WsResponse::Event {
+                            channel,
+                            seq: None,
+                            data,
+                        })
+                        .await
+                        .is_err()
+                {
+                    break;
+                }
+            }
+        })
+    };
+
+    while let Some(Ok(message)) = stream.next().await {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let mut replay = Vec::new();
+        let response = match serde_json::from_str::<WsRequest>(&text) {
+            Ok(WsRequest::Ping { id }) => WsResponse::Pong { id },
+            Ok(WsRequest::Subscribe { channel, id }) => {
+                if let Ok(mut subscribed) = subscribed.lock() {
+                    subscribed.insert(channel.clone());
+                }
+                if let Some(events) = state.data.lock().await.on_subscribe.get(&channel) {
+                    replay = events.clone();
+                }
+                WsResponse::Subscribed { channel, id }
+            }
+            Ok(WsRequest::Unsubscribe { channel, id }) => {
+                if let Ok(mut subscribed) = subscribed.lock() {
+                    subscribed.remove(&channel);
+                }
+                WsResponse::Unsubscribed { channel, id }
+            }
+            Err(e) => WsResponse::Error {
+                message: format!("Invalid request: {}", e),
+                id: None,
+            },
+        };
+
+        let channel = match &response {
+            WsResponse::Subscribed { channel, .. } => Some(channel.clone()),
+            _ => None,
+        };
+        if out_tx.send(response).await.is_err() {
+            break;
+        }
+        if let Some(channel) = channel {
+            for data in replay {
+                let event = WsResponse::Event {
+                    channel: channel.clone(),
+                    seq: None,
+                    data,
+                };
+                if out_tx.send(event).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    forwarder.abort();
+    writer.abort();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OrderSide, OrderType, RequestOptions, RetryPolicy};
+    use crate::OrderbookLevel;
+    use std::time::Duration;
+
+    const MARKET: &str = "0x1111111111111111111111111111111111111111";
+
+    #[tokio::test]
+    async fn test_authorize_and_place_order() {
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(market(MARKET, "BTC-USD")).await;
+
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+
+        let found = client.get_market_by_symbol("BTC-USD").await.unwrap();
+        assert_eq!(found.unwrap().addr, MARKET);
+
+        let response = client
+            .send_actions(vec![ActionPayload::place_order(
+                MARKET,
+                OrderSide::Buy,
+                OrderType::Limit,
+                65_000_000_000,
+                100_000,
+            )])
+            .await
+            .unwrap();
+        let sid = response.outputs[0].sid().unwrap().to_string();
+
+        let orders = gateway.orders().await;
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].sid, sid);
+        assert_eq!(orders[0].price, 65_000_000_000);
+        assert_eq!(gateway.intents().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_intent_signature_is_checked() {
+        let gateway = MockGateway::start().await.unwrap();
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+
+        client
+            .send_actions(vec![ActionPayload::place_order(
+                MARKET,
+                OrderSide::Sell,
+                OrderType::Limit,
+                66_000_000_000,
+                100_000,
+            )])
+            .await
+            .unwrap();
+
+        // A signature over other actions is rejected
+        let forged = SendIntentParams {
+            actions: vec![ActionPayload::place_order(
+                MARKET,
+                OrderSide::Sell,
+                OrderType::Limit,
+                1,
+                100_000,
+            )],
+            signature: gateway.intents().await[0].signature.clone(),
+            nonce: None,
+        };
+        let result = client.send_intent(forged).await;
+        assert!(matches!(
+            result,
+            Err(crate::EkidenError::Api { status: 401, .. })
+        ));
+        assert_eq!(gateway.intents().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_wait_intent_polls_until_final() {
+        let gateway = MockGateway::start().await.unwrap();
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+
+        let sent = client
+            .send_actions(vec![ActionPayload::place_order(
+                MARKET,
+                OrderSide::Buy,
+                OrderType::Limit,
+                64_000_000_000,
+                100_000,
+            )])
+            .await
+            .unwrap();
+        let path = format!("user/intent/{}", sent.seq);
+        let pending = SendIntentResponse {
+            status: "pending".to_string(),
+            outputs: Vec::new(),
+            ..sent.clone()
+        };
+        gateway.enqueue_response("GET", &path, 200, &pending).await;
+
+        let done = client
+            .wait_intent(sent.seq, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(done.is_success());
+        assert_eq!(done.outputs[0].sid(), sent.outputs[0].sid());
+        let polls = gateway.requests().await;
+        assert_eq!(polls.iter().filter(|r| r.path == path).count(), 2);
+
+        // Still pending when the timeout passes
+        gateway.set_response("GET", &path, 200, &pending).await;
+        let result = client
+            .wait_intent(sent.seq, Duration::from_millis(100))
+            .await;
+        assert!(matches!(result, Err(crate::EkidenError::Timeout)));
+
+        let failed = SendIntentResponse {
+            status: "failed".to_string(),
+            ..sent
+        };
+        gateway.set_response("GET", &path, 200, &failed).await;
+        let result = client.wait_intent(failed.seq, Duration::from_secs(5)).await;
+        assert!(matches!(
+            result,
+            Err(crate::EkidenError::OrderRejected { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_simulate_intent_does_not_execute() {
+        let gateway = MockGateway::start().await.unwrap();
+        gateway
+            .set_orderbook(OrderbookResponse {
+                market_addr: MARKET.to_string(),
+                bids: vec![],
+                asks: vec![
+                    OrderbookLevel {
+                        price: 64_010_000_000,
+                        size: 100_000,
+                    },
+                    OrderbookLevel {
+                        price: 64_000_000_000,
+                        size: 50_000,
+                    },
+                ],
+                timestamp: 0,
+            })
+            .await;
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+
+        let order = |order_type, size| {
+            ActionPayload::place_order(MARKET, OrderSide::Buy, order_type, 64_005_000_000, size)
+        };
+        let simulation = client
+            .simulate_actions(vec![order(OrderType::Limit, 80_000)])
+            .await
+            .unwrap()
+            .into_result()
+            .unwrap();
+        let output = &simulation.outputs[0];
+        assert_eq!(output.filled_size(), 50_000);
+        assert_eq!(output.average_price(), Some(64_000_000_000));
+
+        let simulation = client
+            .simulate_actions(vec![order(OrderType::Market, 200_000)])
+            .await
+            .unwrap();
+        assert!(!simulation.accepted);
+        assert_eq!(simulation.errors(), vec!["Insufficient liquidity"]);
+        assert!(matches!(
+            simulation.into_result(),
+            Err(crate::EkidenError::OrderRejected { .. })
+        ));
+
+        assert!(gateway.orders().await.is_empty());
+        assert!(gateway.intents().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_place_limit_and_market_orders() {
+        let gateway = MockGateway::start().await.unwrap();
+        let mut btc = market(MARKET, "BTC-USD");
+        btc.mark_price = 64_000_000_000;
+        gateway.add_market(btc).await;
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+
+        let options = crate::OrderOptions::new().with_leverage(5);
+        let placed = client
+            .place_limit_order(MARKET, OrderSide::Buy, 63_000_000_000, 100_000, options)
+            .await
+            .unwrap();
+        assert_eq!(placed.order_type, OrderType::Limit);
+        assert_eq!(gateway.orders().await[0].sid, placed.sid);
+        let intents = gateway.intents().await;
+        let types: Vec<&str> = intents[0]
+            .actions
+            .iter()
+            .map(|action| action.action_type.as_str())
+            .collect();
+        assert_eq!(types, vec!["set_leverage", "place_order"]);
+
+        // Market sells may fill down to the mark price less the slippage
+        let placed = client
+            .place_market_order(MARKET, OrderSide::Sell, 100_000, Default::default())
+            .await
+            .unwrap();
+        assert_eq!(placed.price, 63_680_000_000);
+        assert_eq!(gateway.orders().await[1].price, placed.price);
+        assert!(matches!(
+            client
+                .place_market_order(
+                    MARKET,
+                    OrderSide::Sell,
+                    100_000,
+                    crate::OrderOptions::new().with_slippage_bps(10_001),
+                )
+                .await,
+            Err(crate::EkidenError::Validation(_))
+        ));
+        assert!(matches!(
+            client
+                .place_limit_order(MARKET, OrderSide::Buy, 0, 100_000, Default::default())
+                .await,
+            Err(crate::EkidenError::Validation(_))
+        ));
+
+        // Flags are sent with the order; post-only market orders never reach the gateway
+        let options = crate::OrderOptions::new().with_post_only(true);
+        client
+            .place_limit_order(
+                MARKET,
+                OrderSide::Buy,
+                63_000_000_000,
+                100_000,
+                options.clone(),
+            )
+            .await
+            .unwrap();
+        let intents = gateway.intents().await;
+        assert_eq!(intents.last().unwrap().actions[0].data["post_only"], true);
+        assert!(matches!(
+            client
+                .place_market_order(MARKET, OrderSide::Buy, 100_000, options)
+                .await,
+            Err(crate::EkidenError::Validation(_))
+        ));
+        assert_eq!(gateway.intents().await.len(), intents.len());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_orders() {
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(market(MARKET, "BTC-USD")).await;
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+
+        let mut sids = Vec::new();
+        for price in [1, 2, 3] {
+            let placed = client
+                .place_limit_order(MARKET, OrderSide::Buy, price, 1_000, Default::default())
+                .await
+                .unwrap();
+            sids.push(placed.sid);
+        }
+
+        client.cancel_order(MARKET, &sids[0]).await.unwrap();
+        assert!(matches!(
+            client.cancel_order(MARKET, "missing").await,
+            Err(crate::EkidenError::OrderRejected { .. })
+        ));
+        assert_eq!(client.get_open_orders(Some(MARKET)).await.unwrap().len(), 2);
+
+        let outcome = client.cancel_all_orders_best_effort(None).await.unwrap();
+        assert!(outcome.is_complete());
+        assert_eq!(outcome.succeeded, sids[1..].to_vec());
+        assert!(client.get_open_orders(None).await.unwrap().is_empty());
+        assert!(client.cancel_all_orders(None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_client_order_ids_follow_order_updates() {
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(market(MARKET, "BTC-USD")).await;
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+        client.connect_websocket().await.unwrap();
+        let user_addr = client.address().await.unwrap().unwrap();
+        client.track_client_orders(&user_addr).await.unwrap();
+
+        let options = crate::OrderOptions::new().with_client_order_id("grid-1");
+        let placed = client
+            .place_limit_order(MARKET, OrderSide::Buy, 100, 1_000, options.clone())
+            .await
+            .unwrap();
+        assert_eq!(placed.client_order_id.as_deref(), Some("grid-1"));
+        assert_eq!(
+            gateway.orders().await[0].client_order_id.as_deref(),
+            Some("grid-1")
+        );
+        assert_eq!(
+            client.client_orders().sid("grid-1"),
+            Some(placed.sid.clone())
+        );
+        assert!(client
+            .place_limit_order(MARKET, OrderSide::Buy, 100, 1_000, options)
+            .await
+            .is_err());
+
+        let mut filled = gateway.orders().await.remove(0);
+        filled.status = "filled".to_string();
+        gateway.push_event(
+            &format!("user/{}", user_addr),
+            WsEvent::OrderUpdate { order: filled },
+        );
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.client_orders().get("grid-1").unwrap().is_open() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_order_tracker_follows_lifecycle() {
+        use crate::tracker::{OrderEvent, OrderTracker, OrderTrackerConfig};
+
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(market(MARKET, "BTC-USD")).await;
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+        let resting = client
+            .place_limit_order(MARKET, OrderSide::Buy, 100, 1_000, Default::default())
+            .await
+            .unwrap();
+
+        let config = OrderTrackerConfig::default().with_market(MARKET);
+        let tracker = OrderTracker::start(client.clone(), config).await.unwrap();
+        assert_eq!(tracker.open_orders().len(), 1);
+        let cancels = Arc::new(std::sync::Mutex::new(Vec::new()));
+        tracker.on_event({
+            let cancels = cancels.clone();
+            move |event| {
+                if let OrderEvent::Cancelled(order) = event {
+                    cancels.lock().unwrap().push(order.sid.clone());
+                }
+            }
+        });
+
+        let mut filled = gateway.orders().await.remove(0);
+        filled.status = "filled".to_string();
+        filled.seq += 1;
+        let user_addr = client.address().await.unwrap().unwrap();
+        gateway.push_event(
+            &format!("user/{}", user_addr),
+            WsEvent::OrderUpdate { order: filled },
+        );
+        let order = tokio::time::timeout(Duration::from_secs(5), tracker.wait_filled(&resting.sid))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(order.status, "filled");
+
+        let other = client
+            .place_limit_order(MARKET, OrderSide::Sell, 200, 1_000, Default::default())
+            .await
+            .unwrap();
+        client.cancel_order(MARKET, &other.sid).await.unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(5), tracker.wait_filled(&other.sid))
+            .await
+            .unwrap();
+        assert!(matches!(
+            result,
+            Err(crate::EkidenError::OrderRejected { .. })
+        ));
+        assert_eq!(*cancels.lock().unwrap(), [other.sid]);
+        assert!(tracker.open_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_order_tracker_reconciles_unseen_fill() {
+        use crate::tracker::{OrderTracker, OrderTrackerConfig};
+
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(market(MARKET, "BTC-USD")).await;
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+        let config =
+            OrderTrackerConfig::default().with_reconcile_interval(Duration::from_millis(50));
+        let tracker = OrderTracker::start(client.clone(), config).await.unwrap();
+        tracker.track_market(MARKET).unwrap();
+
+        // The order filled without the tracker seeing any update for it
+        let user_addr = client.address().await.unwrap().unwrap();
+        gateway
+            .add_order(OrderResponse {
+                sid: "lost".to_string(),
+                side: "buy".to_string(),
+                size: 1_000,
+                price: 100,
+                leverage: 1,
+                order_type: "limit".to_string(),
+                status: "filled".to_string(),
+                user_addr,
+                market_addr: MARKET.to_string(),
+                seq: 1,
+                timestamp: 0,
+                client_order_id: None,
+                time_in_force: None,
+                post_only: None,
+                reduce_only: None,
+            })
+            .await;
+        let order = tokio::time::timeout(Duration::from_secs(5), tracker.wait_filled("lost"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(order.status, "filled");
+    }
+
+    #[tokio::test]
+    async fn test_trigger_monitor_fires_locally() {
+        use crate::triggers::{TriggerMode, TriggerMonitor, TriggerPlacement};
+        use crate::types::{TickerResponse, TriggerOrder};
+
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(market(MARKET, "BTC-USD")).await;
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+
+        // The gateway turns the native trigger down, so the monitor holds it
+        gateway
+            .enqueue_response(
+                "POST",
+                "user/intent",
+                200,
+                &json!({
+                    "seq": 99,
+                    "status": "rejected",
+                    "outputs": [{
+                        "action_type": "place_trigger_order",
+                        "result": { "error": "Unknown action place_trigger_order" }
+                    }]
+                }),
+            )
+            .await;
+        let monitor = TriggerMonitor::new(client.clone(), TriggerMode::Auto);
+        let (fired_tx, mut fired) = mpsc::unbounded_channel();
+        monitor.on_fired(move |id, result| {
+            let _ = fired_tx.send((id.to_string(), result.as_ref().map(|o| o.price).ok()));
+        });
+        let stop = TriggerOrder::stop_market(MARKET, OrderSide::Sell, 1_000, 90).with_price(80);
+        let TriggerPlacement::Local(id) = monitor.place(stop).await.unwrap() else {
+            panic!("expected a local trigger");
+        };
+
+        let ticker = |mark_price| WsEvent::Ticker {
+            ticker: TickerResponse {
+                market_addr: MARKET.to_string(),
+                mark_price,
+                oracle_price: 0,
+                volume_24h: 0,
+                funding_rate: 0.0,
+                next_funding_time: 0,
+                timestamp: 0,
+            },
+        };
+        let channel = crate::ws::channels::ticker(MARKET);
+        gateway.push_event(&channel, ticker(95));
+        gateway.push_event(
+            &crate::ws::channels::trades(MARKET),
+            WsEvent::Trade {
+                market_addr: MARKET.to_string(),
+                price: 85,
+                size: 1,
+                side: "sell".to_string(),
+                timestamp: 0,
+            },
+        );
+        // A transient failure holds the order again until the next price
+        gateway.fail_next("user/intent", 503, "overloaded").await;
+        gateway.push_event(&channel, ticker(89));
+        gateway.push_event(&channel, ticker(88));
+
+        let (fired_id, price) = tokio::time::timeout(Duration::from_secs(5), fired.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((fired_id, price), (id, Some(80)));
+        assert!(monitor.pending().is_empty());
+        let intents = gateway.intents().await;
+        assert_eq!(
+            intents.last().unwrap().actions[0].data["time_in_force"],
+            "ioc"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bracket_cancels_sibling_exit() {
+        use crate::bracket::{BracketOrder, BracketStatus};
+        use crate::tracker::{OrderTracker, OrderTrackerConfig};
+
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(market(MARKET, "BTC-USD")).await;
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+        let tracker = OrderTracker::start(client.clone(), OrderTrackerConfig::default())
+            .await
+            .unwrap();
+        let user_channel = format!("user/{}", client.address().await.unwrap().unwrap());
+        let fill = |sid: String| {
+            let gateway = &gateway;
+            let user_channel = &user_channel;
+            async move {
+                let mut order = gateway
+                    .orders()
+                    .await
+                    .into_iter()
+                    .find(|order| order.sid == sid)
+                    .unwrap();
+                order.status = "filled".to_string();
+                order.seq += 1;
+                gateway.push_event(user_channel, WsEvent::OrderUpdate { order });
+            }
+        };
+
+        let bracket = BracketOrder::limit(MARKET, OrderSide::Buy, 1_000, 100)
+            .with_take_profit(110)
+            .with_stop_loss(90)
+            .submit(&client, Arc::new(tracker))
+            .await
+            .unwrap();
+        assert_eq!(bracket.status(), BracketStatus::Entering);
+        fill(bracket.legs().entry).await;
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while bracket.status() != BracketStatus::Protected {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let legs = bracket.legs();
+        fill(legs.take_profit.unwrap()).await;
+
+        let status = tokio::time::timeout(Duration::from_secs(5), bracket.wait())
+            .await
+            .unwrap();
+        assert_eq!(status, BracketStatus::TookProfit);
+        let stop_loss = legs.stop_loss.unwrap();
+        let orders = gateway.orders().await;
+        let stop = orders.iter().find(|order| order.sid == stop_loss).unwrap();
+        assert_eq!(
+            (stop.order_type.as_str(), stop.status.as_str()),
+            ("stop_market", "cancelled")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bracket_protects_partial_entry() {
+        use crate::bracket::{BracketOrder, BracketStatus};
+        use crate::tracker::{OrderTracker, OrderTrackerConfig};
+
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(market(MARKET, "BTC-USD")).await;
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+        let tracker = OrderTracker::start(client.clone(), OrderTrackerConfig::default())
+            .await
+            .unwrap();
+        let user_channel = format!("user/{}", client.address().await.unwrap().unwrap());
+
+        let bracket = BracketOrder::limit(MARKET, OrderSide::Buy, 1_000, 100)
+            .with_take_profit(110)
+            .with_stop_loss(90)
+            .submit(&client, Arc::new(tracker))
+            .await
+            .unwrap();
+        let mut entry = gateway.orders().await.remove(0);
+        for status in ["partially_filled", "cancelled"] {
+            entry.status = status.to_string();
+            entry.size = 400;
+            entry.seq += 1;
+            let order = entry.clone();
+            gateway.push_event(&user_channel, WsEvent::OrderUpdate { order });
+        }
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while bracket.status() != BracketStatus::Protected {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let legs = bracket.legs();
+        let orders = gateway.orders().await;
+        for sid in [legs.take_profit.unwrap(), legs.stop_loss.unwrap()] {
+            let exit = orders.iter().find(|order| order.sid == sid).unwrap();
+            assert_eq!(exit.size, 600);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_intent_nonces_do_not_collide() {
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(market(MARKET, "BTC-USD")).await;
+        let private_key = format!("0x{}", hex::encode(rand::random::<[u8; 32]>()));
+        let client = EkidenClientBuilder::new()
+            .config(gateway.config().unwrap().with_intent_nonces())
+            .private_key(private_key)
+            .build()
+            .await
+            .unwrap();
+        client.authorize().await.unwrap();
+
+        let orders = (1..=5).map(|price| {
+            client.place_limit_order(MARKET, OrderSide::Buy, price, 1_000, Default::default())
+        });
+        for result in futures_util::future::join_all(orders).await {
+            result.unwrap();
+        }
+
+        // Another session moved the nonce on
+        gateway.set_intent_nonce(42).await;
+        client
+            .place_limit_order(MARKET, OrderSide::Buy, 6, 1_000, Default::default())
+            .await
+            .unwrap();
+        let nonces: Vec<_> = gateway
+            .intents()
+            .await
+            .iter()
+            .map(|intent| intent.nonce.unwrap())
+            .collect();
+        assert_eq!(nonces, [0, 1, 2, 3, 4, 42]);
+    }
+
+    #[tokio::test]
+    async fn test_nonced_intent_is_not_retried() {
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(market(MARKET, "BTC-USD")).await;
+        let private_key = format!("0x{}", hex::encode(rand::random::<[u8; 32]>()));
+        let client = EkidenClientBuilder::new()
+            .config(gateway.config().unwrap().with_intent_nonces())
+            .private_key(private_key)
+            .build()
+            .await
+            .unwrap()
+            .with_options(RequestOptions::new().with_retry(RetryPolicy::new(2, Duration::ZERO)));
+        client.authorize().await.unwrap();
+
+        // The outcome of a 503 is unknown, so the intent must not be sent again
+        gateway.fail_next("user/intent", 503, "overloaded").await;
+        let result = client
+            .place_limit_order(MARKET, OrderSide::Buy, 1, 1_000, Default::default())
+            .await;
+        assert!(matches!(
+            result,
+            Err(crate::EkidenError::Api { status: 503, .. })
+        ));
+        let posts = gateway
+            .requests()
+            .await
+            .into_iter()
+            .filter(|r| r.method == "POST" && r.path == "user/intent")
+            .count();
+        assert_eq!(posts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_amend_order_falls_back_to_cancel_replace() {
+        let gateway = MockGateway::start().await.unwrap();
+        gateway.add_market(market(MARKET, "BTC-USD")).await;
+        let client = gateway.client().await.unwrap();
+        client.authorize().await.unwrap();
+        let options = crate::OrderOptions::new().with_post_only(true);
+        let placed = client
+            .place_limit_order(MARKET, OrderSide::Sell, 100, 1_000, options)
+            .await
+            .unwrap();
+
+        let amended = client
+            .amend_order(MARKET, &placed.sid, 101, 2_000)
+            .await
+            .unwrap();
+        assert!(!amended.replaced);
+        assert_eq!(amended.sid, placed.sid);
+        assert_eq!(gateway.orders().await[0].price, 101);
+
+        // A rejection without the unsupported_action code is an ordinary failure
+        gateway
+            .enqueue_response(
+                "POST",
+                "user/intent",
+                200,
+                &json!({
+                    "seq": 98,
+                    "status": "rejected",
+                    "outputs": [{
+                        "action_type": "amend_order",
+                        "result": { "error": "unsupported tick size" }
+                    }]
+                }),
+            )
+            .await;
+        assert!(matches!(
+            client.amend_order(MARKET, &placed.sid, 101, 2_000).await,
+            Err(crate::EkidenError::OrderRejected { .. })
+        ));
+        assert_eq!(gateway.orders().await[0].sid, placed.sid);
+
+        // A gateway without amends gets a cancel and a replacement instead
+        gateway
+            .enqueue_response(
+                "POST",
+                "user/intent",
+                200,
+                &json!({
+                    "seq": 99,
+                    "status": "rejected",
+                    "outputs": [{
+                        "action_type": "amend_order",
+                        "result": {
+                            "code": "unsupported_action",
+                            "error": "unsupported action amend_order"
+                        }
+                    }]
+                }),
+            )
+            .await;
+        let replaced = client
+            .amend_order(MARKET, &placed.sid, 102, 2_000)
+            .await
+            .unwrap();
+        assert!(replaced.replaced);
+        assert_ne!(replaced.sid, placed.sid);
+        let open = client.get_open_orders(Some(MARKET)).await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(
+            (open[0].sid.as_str(), open[0].price),
+            (replaced.sid.as_str(), 102)
+        );
+        assert_eq!(open[0].post_only, Some(true));
+
+        client
+            .amend_order(MARKET, &replaced.sid, 103, 2_000)
+            .await
+            .unwrap();
+        let intents = gateway.intents().await;
+        let last = intents.last().unwrap();
+        assert_eq!(last.actions[0].action_type, "cancel_order");
+    }
+
+    #[tokio::test]
+    async fn test_scripted_failure_and_auth() {
+        let gateway = MockGateway::start().await.unwrap();
+        let client = gateway.client().await.unwrap();
+
+        // User endpoints need a token
+        assert!(client
+            .request_json("user/portfolio", crate::RequestConfig::get())
+            .await
+            .is_err());
+
+        gateway.fail_next("market_info", 503, "maintenance").await;
+        let err = client.get_market_by_symbol("BTC-USD").await.unwrap_err();
+        assert!(matches!(err, crate::EkidenError::Api { status: 503, .. }));
+        assert!(client
+            .get_market_by_symbol("BTC-USD")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subscription_receives_scripted_events() {
+        let gateway = MockGateway::start().await.unwrap();
+        let snapshot = WsEvent::OrderbookSnapshot {
+            market_addr: MARKET.to_string(),
+            bids: vec![OrderbookLevel {
+                price: 100,
+                size: 5,
+            }],
+            asks: Vec::new(),
+            timestamp: 1,
+        };
+        gateway
+            .on_subscribe(&format!("orderbook/{}", MARKET), vec![snapshot])
+            .await;
+
+        let client = gateway.client().await.unwrap();
+        client.connect_websocket().await.unwrap();
+        let mut receiver = client.subscribe_orderbook(MARKET).await.unwrap();
+
+        let first = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(first, WsEvent::OrderbookSnapshot { .. }));
+
+        gateway.push_event(
+            &format!("orderbook/{}", MARKET),
+            WsEvent::Trade {
+                market_addr: MARKET.to_string(),
+                price: 100,
+                size: 1,
+                side: "buy".to_string(),
+                timestamp: 2,
+            },
+        );
+        let second = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(second, WsEvent::Trade { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_short_form_channels_are_normalized() {
+        let gateway = MockGateway::start().await.unwrap();
+        let client = gateway.client().await.unwrap();
+        client.connect_websocket().await.unwrap();
+        let channel = crate::ws::channels::trades("0x1");
+
+        let mut receiver = client
+            .subscribe_with("trades/0x1", Default::default())
+            .await
+            .unwrap();
+        assert_eq!(receiver.channel(), channel);
+        assert_eq!(client.subscriber_count("trades/0x01").await, 1);
+        gateway.push_event(
+            &channel,
+            WsEvent::Trade {
+                market_addr: "0x1".to_string(),
+                price: 100,
+                size: 1,
+                side: "buy".to_string(),
+                timestamp: 1,
+            },
+        );
+        let event = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(event, WsEvent::Trade { .. }));
+
+        client.unsubscribe("trades/0x1").await.unwrap();
+        assert!(client.active_subscriptions().await.is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+//! Discord backend posting notifications to a channel webhook.
+
+use super::{Notification, NotificationKind, NotifierBackend};
+use crate::error::{EkidenError, Result};
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use std::time::Duration;
+
+/// Discord caps embed descriptions at this many characters
+const MAX_DESCRIPTION_LEN: usize = 4096;
+
+/// Delivers notifications to a Discord channel webhook
+#[derive(Clone)]
+pub struct DiscordBackend {
+    webhook_url: String,
+    username: Option<String>,
+    http_client: reqwest::Client,
+}
+
+impl std::fmt::Debug for DiscordBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiscordBackend")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DiscordBackend {
+    /// Create a backend for a channel webhook URL
+    /// (`https://discord.com/api/webhooks/{id}/{token}`)
+    pub fn new<S: Into<String>>(webhook_url: S) -> Self {
+        Self {
+            webhook_url: webhook_url.into(),
+            username: None,
+            http_client: reqwest::Client::builder()
+                .use_rustls_tls()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Override the webhook's display name
+    pub fn with_username<S: Into<String>>(mut self, username: S) -> Self {
+        self.username = Some(username.into());
+        self
+    }
+
+    async fn post(&self, notification: &Notification) -> Result<()> {
+        let mut payload = build_payload(notification);
+        if let Some(username) = &self.username {
+            payload["username"] = json!(username);
+        }
+
+        let response = self
+            .http_client
+            .post(&self.webhook_url)
+            .json(&payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            let text = response.text().await.unwrap_or_default();
+            Err(EkidenError::api(status.as_u16(), text))
+        }
+    }
+}
+
+impl NotifierBackend for DiscordBackend {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.post(notification))
+    }
+}
+
+/// Build a webhook payload with one embed, colored by kind
+fn build_payload(notification: &Notification) -> serde_json::Value {
+    let (title, color) = match notification.kind {
+        NotificationKind::Fill => ("Fill", 0x2ecc71),
+        NotificationKind::LiquidationRisk => ("Liquidation risk", 0xe74c3c),
+        NotificationKind::AuthFailure => ("Auth failure", 0xe67e22),
+        NotificationKind::WsDisconnect => ("WebSocket disconnect", 0x95a5a6),
+    };
+    let description: String = notification
+        .message
+        .chars()
+        .take(MAX_DESCRIPTION_LEN)
+        .collect();
+
+    json!({
+        "embeds": [{
+            "title": title,
+            "description": description,
+            "color": color,
+            "footer": { "text": "Ekiden" },
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_payload() {
+        let notification = Notification::new(
+            NotificationKind::Fill,
+            "x".repeat(MAX_DESCRIPTION_LEN + 10),
+            serde_json::Value::Null,
+        );
+        let payload = build_payload(&notification);
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["title"], "Fill");
+        assert_eq!(embed["color"], 0x2ecc71);
+        assert_eq!(
+            embed["description"].as_str().unwrap().len(),
+            MAX_DESCRIPTION_LEN
+        );
+    }
+}
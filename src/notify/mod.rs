@@ -0,0 +1,503 @@
+//! Push notifications for trading events.
+//!
+//! A [`Notifier`] fans [`Notification`]s out to one or more
+//! [`NotifierBackend`]s, each optionally restricted to certain
+//! [`NotificationKind`]s, retrying failed deliveries with exponential backoff.
+//! [`Notifier::watch`] derives fill, liquidation-risk and WebSocket-disconnect
+//! notifications from a user's event stream; auth failures are reported with
+//! [`Notifier::report_error`].
+
+#[cfg(feature = "discord")]
+pub mod discord;
+#[cfg(feature = "telegram")]
+pub mod telegram;
+pub mod webhook;
+
+#[cfg(feature = "discord")]
+pub use discord::DiscordBackend;
+#[cfg(feature = "telegram")]
+pub use telegram::TelegramBackend;
+pub use webhook::WebhookBackend;
+
+use crate::client::{EkidenClient, ErrorContext};
+use crate::clock::{self, Clock};
+use crate::error::{EkidenError, ErrorKind, Result};
+use crate::types::{OrderResponse, OrderState, PositionResponse, WsEvent};
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Kinds of events a notifier can report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    Fill,
+    LiquidationRisk,
+    AuthFailure,
+    WsDisconnect,
+}
+
+/// A single notification payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    /// Unix time in milliseconds
+    pub timestamp: u64,
+    /// Human-readable summary
+    pub message: String,
+    /// Structured details, e.g. the order or position that triggered it
+    pub data: serde_json::Value,
+}
+
+impl Notification {
+    /// Create a notification stamped with the current time
+    pub fn new<S: Into<String>>(
+        kind: NotificationKind,
+        message: S,
+        data: serde_json::Value,
+    ) -> Self {
+        Self {
+            kind,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            message: message.into(),
+            data,
+        }
+    }
+
+    /// Create a fill notification from a filled or partially filled order
+    pub fn fill(order: &OrderResponse) -> Self {
+        Self::new(
+            NotificationKind::Fill,
+            format!(
+                "Order {} {} {} @ {} on {} is {}",
+                order.sid, order.side, order.size, order.price, order.market_addr, order.status
+            ),
+            serde_json::to_value(order).unwrap_or_default(),
+        )
+    }
+
+    /// Create a liquidation-risk notification for a position
+    pub fn liquidation_risk(position: &PositionResponse, distance: f64) -> Self {
+        Self::new(
+            NotificationKind::LiquidationRisk,
+            format!(
+                "{} position on {} is {:.2}% from liquidation (mark {}, liquidation {})",
+                position.side,
+                position.market_addr,
+                distance * 100.0,
+                position.mark_price,
+                position.liquidation_price
+            ),
+            serde_json::to_value(position).unwrap_or_default(),
+        )
+    }
+
+    /// Create an auth-failure notification
+    pub fn auth_failure(error: &EkidenError) -> Self {
+        Self::new(
+            NotificationKind::AuthFailure,
+            format!("Authentication failed: {}", error),
+            serde_json::Value::Null,
+        )
+    }
+
+    /// Create a WebSocket-disconnect notification
+    pub fn ws_disconnect() -> Self {
+        Self::new(
+            NotificationKind::WsDisconnect,
+            "WebSocket disconnected",
+            serde_json::Value::Null,
+        )
+    }
+}
+
+/// Delivery channel for notifications
+pub trait NotifierBackend: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    /// Deliver a notification once; the notifier handles retries
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Retry policy for failed deliveries
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each further retry
+    pub initial_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Settings for [`Notifier::watch`]
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    /// Alert when mark price is within this fraction of the liquidation price
+    pub liquidation_buffer: f64,
+    /// How often the WebSocket connection is checked
+    pub heartbeat_interval: Duration,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            liquidation_buffer: 0.05,
+            heartbeat_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+struct Route {
+    backend: Arc<dyn NotifierBackend>,
+    kinds: HashSet<NotificationKind>,
+}
+
+/// Fans notifications out to backends with retry
+#[derive(Clone)]
+pub struct Notifier {
+    routes: Vec<Arc<Route>>,
+    retry: RetryPolicy,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self {
+            routes: Vec::new(),
+            retry: RetryPolicy::default(),
+            clock: clock::system(),
+        }
+    }
+}
+
+impl Notifier {
+    /// Create a notifier without backends
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a backend receiving the given kinds; an empty slice means all kinds
+    pub fn with_backend<B>(mut self, backend: B, kinds: &[NotificationKind]) -> Self
+    where
+        B: NotifierBackend + 'static,
+    {
+        self.routes.push(Arc::new(Route {
+            backend: Arc::new(backend),
+            kinds: kinds.iter().copied().collect(),
+        }));
+        self
+    }
+
+    /// Set the retry policy
+    pub fn with_retry(mut self, max_attempts: u32, initial_delay: Duration) -> Self {
+        self.retry = RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial_delay,
+        };
+        self
+    }
+
+    /// Use a custom clock for retry delays
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Deliver a notification to every matching backend
+    ///
+    /// All backends are attempted; the first delivery error is returned.
+    pub async fn notify(&self, notification: &Notification) -> Result<()> {
+        let mut first_error = None;
+        for route in &self.routes {
+            if !route.kinds.is_empty() && !route.kinds.contains(&notification.kind) {
+                continue;
+            }
+            if let Err(e) = self.deliver(route.backend.as_ref(), notification).await {
+                warn!(
+                    "Notifier backend {} failed to deliver {:?}: {}",
+                    route.backend.name(),
+                    notification.kind,
+                    e
+                );
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    async fn deliver(
+        &self,
+        backend: &dyn NotifierBackend,
+        notification: &Notification,
+    ) -> Result<()> {
+        let mut delay = self.retry.initial_delay;
+        let mut attempt = 1;
+        loop {
+            match backend.send(notification).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= self.retry.max_attempts || !e.is_retryable() => return Err(e),
+                Err(e) => {
+                    warn!(
+                        "Notifier backend {} attempt {} failed: {}",
+                        backend.name(),
+                        attempt,
+                        e
+                    );
+                    self.clock.sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Report an error, notifying if it is an authentication failure
+    pub async fn report_error(&self, error: &EkidenError) -> Result<()> {
+        if error.kind() == ErrorKind::Auth {
+            self.notify(&Notification::auth_failure(error)).await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Build an error hook that reports client errors through this notifier
+    ///
+    /// Register it with [`EkidenClient::on_error`]; notifications are sent on
+    /// a spawned task so the failing call is not delayed.
+    pub fn error_hook(&self) -> impl Fn(&EkidenError, &ErrorContext) + Send + Sync + 'static {
+        let notifier = self.clone();
+        move |error, _context| {
+            if error.kind() == ErrorKind::Auth {
+                let notifier = notifier.clone();
+                let notification = Notification::auth_failure(error);
+                tokio::spawn(async move {
+                    if let Err(e) = notifier.notify(&notification).await {
+                        warn!("Failed to report client error: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Watch a user's events until the stream closes
+    ///
+    /// Notifies on filled orders, positions within `liquidation_buffer` of
+    /// their liquidation price (once per market until they recover) and loss of
+    /// the WebSocket connection.
+    pub async fn watch(
+        &self,
+        client: &EkidenClient,
+        user_addr: &str,
+        config: WatchConfig,
+    ) -> Result<()> {
+        let mut events = client.subscribe_user(user_addr).await?;
+        let mut heartbeat = tokio::time::interval(config.heartbeat_interval);
+        let mut at_risk: HashSet<String> = HashSet::new();
+        let mut connected = true;
+
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(WsEvent::OrderUpdate { order }) if is_fill(&order.status) => {
+                        let _ = self.notify(&Notification::fill(&order)).await;
+                    }
+                    Ok(WsEvent::PositionUpdate { position }) => {
+                        match liquidation_distance(&position) {
+                            Some(distance) if distance <= config.liquidation_buffer => {
+                                if at_risk.insert(position.market_addr.clone()) {
+                                    let notification = Notification::liquidation_risk(&position, distance);
+                                    let _ = self.notify(&notification).await;
+                                }
+                            }
+                            _ => {
+                                at_risk.remove(&position.market_addr);
+                            }
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("Notifier lagged, missed {} events", missed);
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                        if connected {
+                            let _ = self.notify(&Notification::ws_disconnect()).await;
+                        }
+                        return Ok(());
+                    }
+                },
+                _ = heartbeat.tick() => {
+                    let now_connected = client.is_websocket_connected().await;
+                    if connected && !now_connected {
+                        let _ = self.notify(&Notification::ws_disconnect()).await;
+                    }
+                    connected = now_connected;
+                }
+            }
+        }
+    }
+}
+
+fn is_fill(status: &str) -> bool {
+    matches!(
+        OrderState::parse(status),
+        OrderState::Filled | OrderState::PartiallyFilled
+    )
+}
+
+/// Relative distance between mark and liquidation price, `None` without a position
+fn liquidation_distance(position: &PositionResponse) -> Option<f64> {
+    if position.size == 0 || position.mark_price == 0 || position.liquidation_price == 0 {
+        return None;
+    }
+    Some(
+        position.mark_price.abs_diff(position.liquidation_price) as f64
+            / position.mark_price as f64,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    struct FlakyBackend {
+        failures: u32,
+        calls: Arc<AtomicU32>,
+    }
+
+    impl NotifierBackend for FlakyBackend {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn send<'a>(&'a self, _notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move {
+                let call = self.calls.fetch_add(1, Ordering::SeqCst);
+                if call < self.failures {
+                    Err(EkidenError::network("unavailable"))
+                } else {
+                    Ok(())
+                }
+            })
+        }
+    }
+
+    fn flaky(failures: u32) -> (FlakyBackend, Arc<AtomicU32>) {
+        let calls = Arc::new(AtomicU32::new(0));
+        (
+            FlakyBackend {
+                failures,
+                calls: calls.clone(),
+            },
+            calls,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_delivered() {
+        let (backend, calls) = flaky(2);
+        let notifier = Notifier::new()
+            .with_backend(backend, &[])
+            .with_retry(3, Duration::from_millis(1));
+
+        notifier
+            .notify(&Notification::ws_disconnect())
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let (backend, calls) = flaky(5);
+        let notifier = Notifier::new()
+            .with_backend(backend, &[])
+            .with_retry(2, Duration::from_millis(1));
+
+        assert!(notifier
+            .notify(&Notification::ws_disconnect())
+            .await
+            .is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_backoff_uses_clock() {
+        let clock = crate::clock::ManualClock::default();
+        let (backend, calls) = flaky(2);
+        let notifier = Notifier::new()
+            .with_backend(backend, &[])
+            .with_retry(3, Duration::from_secs(60))
+            .with_clock(Arc::new(clock.clone()));
+
+        let task =
+            tokio::spawn(async move { notifier.notify(&Notification::ws_disconnect()).await });
+        while clock.pending_sleeps() == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        clock.advance(Duration::from_secs(60));
+        while calls.load(Ordering::SeqCst) < 2 || clock.pending_sleeps() == 0 {
+            tokio::task::yield_now().await;
+        }
+        clock.advance(Duration::from_secs(120));
+        task.await.unwrap().unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_routes_by_kind() {
+        let (backend, calls) = flaky(0);
+        let notifier = Notifier::new().with_backend(backend, &[NotificationKind::Fill]);
+
+        notifier
+            .notify(&Notification::ws_disconnect())
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        notifier
+            .report_error(&EkidenError::auth("expired"))
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_liquidation_distance() {
+        let position: PositionResponse = serde_json::from_value(serde_json::json!({
+            "market_addr": "0x1",
+            "user_addr": "0x2",
+            "side": "long",
+            "size": 10,
+            "entry_price": 100,
+            "mark_price": 100,
+            "unrealized_pnl": 0,
+            "margin": 10,
+            "leverage": 10,
+            "liquidation_price": 96,
+            "created_at": "",
+            "updated_at": ""
+        }))
+        .unwrap();
+
+        let distance = liquidation_distance(&position).unwrap();
+        assert!((distance - 0.04).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,120 @@
+//! Telegram backend sending notifications through the Bot API.
+
+use super::{Notification, NotificationKind, NotifierBackend};
+use crate::error::{EkidenError, Result};
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use std::time::Duration;
+
+const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
+/// Delivers notifications to a Telegram chat via a bot
+#[derive(Clone)]
+pub struct TelegramBackend {
+    api_url: String,
+    bot_token: String,
+    chat_id: String,
+    http_client: reqwest::Client,
+}
+
+impl std::fmt::Debug for TelegramBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TelegramBackend")
+            .field("api_url", &self.api_url)
+            .field("chat_id", &self.chat_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TelegramBackend {
+    /// Create a backend posting as the bot with `bot_token` to `chat_id`
+    ///
+    /// `chat_id` is a numeric chat id or an `@channelusername`.
+    pub fn new<T: Into<String>, C: Into<String>>(bot_token: T, chat_id: C) -> Self {
+        Self {
+            api_url: DEFAULT_API_URL.to_string(),
+            bot_token: bot_token.into(),
+            chat_id: chat_id.into(),
+            http_client: reqwest::Client::builder()
+                .use_rustls_tls()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Override the Bot API base URL, e.g. for a local Bot API server
+    pub fn with_api_url<S: Into<String>>(mut self, api_url: S) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    async fn post(&self, notification: &Notification) -> Result<()> {
+        let url = format!("{}/bot{}/sendMessage", self.api_url, self.bot_token);
+        let response = self
+            .http_client
+            .post(&url)
+            .json(&json!({
+                "chat_id": self.chat_id,
+                "text": format_message(notification),
+                "disable_web_page_preview": true,
+            }))
+            .send()
+            .await
+            // The URL carries the bot token
+            .map_err(|e| EkidenError::from(e.without_url()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            let text = response.text().await.unwrap_or_default();
+            Err(EkidenError::api(status.as_u16(), text))
+        }
+    }
+}
+
+impl NotifierBackend for TelegramBackend {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.post(notification))
+    }
+}
+
+/// Render a notification as plain chat text
+pub(crate) fn format_message(notification: &Notification) -> String {
+    let title = match notification.kind {
+        NotificationKind::Fill => "Fill",
+        NotificationKind::LiquidationRisk => "Liquidation risk",
+        NotificationKind::AuthFailure => "Auth failure",
+        NotificationKind::WsDisconnect => "WebSocket disconnect",
+    };
+    format!("[Ekiden] {}\n{}", title, notification.message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_message() {
+        let notification = Notification::new(
+            NotificationKind::LiquidationRisk,
+            "LONG position is close to liquidation",
+            serde_json::Value::Null,
+        );
+        assert_eq!(
+            format_message(&notification),
+            "[Ekiden] Liquidation risk\nLONG position is close to liquidation"
+        );
+    }
+
+    #[test]
+    fn test_debug_hides_token() {
+        let backend = TelegramBackend::new("123:secret", "@desk");
+        assert!(!format!("{:?}", backend).contains("secret"));
+    }
+}
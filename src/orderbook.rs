@@ -0,0 +1,490 @@
+use crate::types::{OrderSide, OrderbookLevel, WsEvent};
+use crate::ws::channels;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// Microstructure metrics refreshed after every snapshot or update
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderbookMetrics {
+    /// Top-of-book queue imbalance in `[-1, 1]`, positive when bids dominate
+    pub imbalance: Option<f64>,
+    /// Size-weighted mid of the best bid and ask
+    pub microprice: Option<f64>,
+    /// Cumulative `(bps, bid_size, ask_size)` within each configured band of the mid
+    pub depth_within: Vec<(f64, u64, u64)>,
+}
+
+/// Expected outcome of sweeping the book with an aggressive order
+#[derive(Debug, Clone, PartialEq)]
+pub struct FillEstimate {
+    /// Size that the visible book can fill
+    pub filled_size: u64,
+    /// Size-weighted average fill price
+    pub avg_price: f64,
+    /// Price of the last level touched
+    pub worst_price: u64,
+    /// Cost versus the mid in basis points, positive when worse than mid
+    pub slippage_bps: Option<f64>,
+    /// Number of price levels consumed
+    pub levels: usize,
+}
+
+impl FillEstimate {
+    /// Check if the requested size can be filled completely
+    pub fn is_complete(&self, size: u64) -> bool {
+        self.filled_size >= size
+    }
+}
+
+/// Local L2 orderbook maintained from WebSocket snapshots and updates
+#[derive(Debug, Clone, Default)]
+pub struct Orderbook {
+    market_addr: String,
+    bids: BTreeMap<Reverse<u64>, u64>,
+    asks: BTreeMap<u64, u64>,
+    bid_volume: u64,
+    ask_volume: u64,
+    depth_bands_bps: Vec<f64>,
+    metrics: OrderbookMetrics,
+    timestamp: u64,
+    awaiting_snapshot: bool,
+}
+
+impl Orderbook {
+    /// Create an empty orderbook for a market
+    pub fn new<S: Into<String>>(market_addr: S) -> Self {
+        Self {
+            market_addr: market_addr.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Track cumulative depth within these distances of the mid, in basis points
+    pub fn with_depth_bands(mut self, bands_bps: &[f64]) -> Self {
+        self.depth_bands_bps = bands_bps.to_vec();
+        self.refresh_metrics();
+        self
+    }
+
+    /// Get the market address
+    pub fn market_addr(&self) -> &str {
+        &self.market_addr
+    }
+
+    /// Get the timestamp of the last applied snapshot or update
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Check if updates are being ignored until the next snapshot
+    ///
+    /// Set by a [`WsEvent::Resync`] on the market's orderbook channel.
+    pub fn is_awaiting_snapshot(&self) -> bool {
+        self.awaiting_snapshot
+    }
+
+    /// Check if both sides of the book are empty
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+
+    /// Replace the whole book with a snapshot
+    pub fn apply_snapshot(
+        &mut self,
+        bids: &[OrderbookLevel],
+        asks: &[OrderbookLevel],
+        timestamp: u64,
+    ) {
+        self.awaiting_snapshot = false;
+        self.bids.clear();
+        self.asks.clear();
+        self.bid_volume = 0;
+        self.ask_volume = 0;
+        self.apply_update(bids, asks, timestamp);
+    }
+
+    /// Apply an incremental update; a level with zero size removes the price
+    pub fn apply_update(
+        &mut self,
+        bids: &[OrderbookLevel],
+        asks: &[OrderbookLevel],
+        timestamp: u64,
+    ) {
+        for level in bids {
+            let previous = if level.size == 0 {
+                self.bids.remove(&Reverse(level.price))
+            } else {
+                self.bids.insert(Reverse(level.price), level.size)
+            };
+            self.bid_volume = self.bid_volume - previous.unwrap_or(0) + level.size;
+        }
+        for level in asks {
+            let previous = if level.size == 0 {
+                self.asks.remove(&level.price)
+            } else {
+                self.asks.insert(level.price, level.size)
+            };
+            self.ask_volume = self.ask_volume - previous.unwrap_or(0) + level.size;
+        }
+        self.timestamp = timestamp;
+        self.refresh_metrics();
+    }
+
+    /// Apply an orderbook event for this market.
+    ///
+    /// Returns `true` if the event changed the book.
+    pub fn apply_event(&mut self, event: &WsEvent) -> bool {
+        match event {
+            WsEvent::OrderbookSnapshot {
+                market_addr,
+                bids,
+                asks,
+                timestamp,
+            } if market_addr == &self.market_addr => {
+                self.apply_snapshot(bids, asks, *timestamp);
+                true
+            }
+            WsEvent::OrderbookUpdate {
+                market_addr,
+                bids,
+                asks,
+                timestamp,
+            } if market_addr == &self.market_addr && !self.awaiting_snapshot => {
+                self.apply_update(bids, asks, *timestamp);
+                true
+            }
+            WsEvent::Resync { channel, .. }
+                if *channel == channels::orderbook(&self.market_addr) =>
+            {
+                self.awaiting_snapshot = true;
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Get the best (highest) bid
+    pub fn best_bid(&self) -> Option<OrderbookLevel> {
+        self.bids.iter().next().map(|(price, size)| OrderbookLevel {
+            price: price.0,
+            size: *size,
+        })
+    }
+
+    /// Get the best (lowest) ask
+    pub fn best_ask(&self) -> Option<OrderbookLevel> {
+        self.asks.iter().next().map(|(price, size)| OrderbookLevel {
+            price: *price,
+            size: *size,
+        })
+    }
+
+    /// Get the mid price between the best bid and ask
+    pub fn mid_price(&self) -> Option<f64> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+        Some((bid.price as f64 + ask.price as f64) / 2.0)
+    }
+
+    /// Get the spread between the best ask and bid
+    pub fn spread(&self) -> Option<u64> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+        Some(ask.price.saturating_sub(bid.price))
+    }
+
+    /// Iterate bids from best to worst
+    pub fn bids(&self) -> impl Iterator<Item = OrderbookLevel> + '_ {
+        self.bids.iter().map(|(price, size)| OrderbookLevel {
+            price: price.0,
+            size: *size,
+        })
+    }
+
+    /// Iterate asks from best to worst
+    pub fn asks(&self) -> impl Iterator<Item = OrderbookLevel> + '_ {
+        self.asks.iter().map(|(price, size)| OrderbookLevel {
+            price: *price,
+            size: *size,
+        })
+    }
+
+    /// Get the total resting size on the bid side
+    pub fn bid_volume(&self) -> u64 {
+        self.bid_volume
+    }
+
+    /// Get the total resting size on the ask side
+    pub fn ask_volume(&self) -> u64 {
+        self.ask_volume
+    }
+
+    /// Get the metrics computed after the last snapshot or update
+    pub fn metrics(&self) -> &OrderbookMetrics {
+        &self.metrics
+    }
+
+    /// Get the top-of-book queue imbalance in `[-1, 1]`
+    pub fn imbalance(&self) -> Option<f64> {
+        let bid = self.best_bid()?.size as f64;
+        let ask = self.best_ask()?.size as f64;
+        if bid + ask == 0.0 {
+            return None;
+        }
+        Some((bid - ask) / (bid + ask))
+    }
+
+    /// Get the microprice, the mid weighted by the opposite side's queue size
+    pub fn microprice(&self) -> Option<f64> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+        let total = bid.size as f64 + ask.size as f64;
+        if total == 0.0 {
+            return self.mid_price();
+        }
+        Some((bid.price as f64 * ask.size as f64 + ask.price as f64 * bid.size as f64) / total)
+    }
+
+    /// Get the cumulative `(bid_size, ask_size)` resting within `bps` of the mid
+    pub fn depth_within_bps(&self, bps: f64) -> Option<(u64, u64)> {
+        let mid = self.mid_price()?;
+        let distance = mid * bps / 10_000.0;
+        let lower = (mid - distance).max(0.0).ceil() as u64;
+        let upper = (mid + distance).floor() as u64;
+
+        let bid_size = self
+            .bids
+            .range(..=Reverse(lower))
+            .map(|(_, size)| *size)
+            .sum();
+        let ask_size = self.asks.range(..=upper).map(|(_, size)| *size).sum();
+        Some((bid_size, ask_size))
+    }
+
+    /// Estimate the average price and slippage of an aggressive order of `size`.
+    ///
+    /// A buy walks the asks and a sell walks the bids. Returns `None` if the
+    /// opposite side is empty or `size` is zero; `filled_size` is lower than
+    /// `size` when the visible book is too thin.
+    pub fn estimate_fill(&self, side: OrderSide, size: u64) -> Option<FillEstimate> {
+        if size == 0 {
+            return None;
+        }
+
+        let levels: Box<dyn Iterator<Item = OrderbookLevel> + '_> = match side {
+            OrderSide::Buy => Box::new(self.asks()),
+            OrderSide::Sell => Box::new(self.bids()),
+        };
+
+        let mut remaining = size;
+        let mut notional = 0u128;
+        let mut worst_price = None;
+        let mut consumed = 0;
+        for level in levels {
+            if remaining == 0 {
+                break;
+            }
+            let take = remaining.min(level.size);
+            notional += take as u128 * level.price as u128;
+            remaining -= take;
+            worst_price = Some(level.price);
+            consumed += 1;
+        }
+
+        let worst_price = worst_price?;
+        let filled_size = size - remaining;
+        let avg_price = notional as f64 / filled_size as f64;
+        let slippage_bps = self.mid_price().map(|mid| {
+            let diff = match side {
+                OrderSide::Buy => avg_price - mid,
+                OrderSide::Sell => mid - avg_price,
+            };
+            diff / mid * 10_000.0
+        });
+
+        Some(FillEstimate {
+            filled_size,
+            avg_price,
+            worst_price,
+            slippage_bps,
+            levels: consumed,
+        })
+    }
+
+    fn refresh_metrics(&mut self) {
+        self.metrics = OrderbookMetrics {
+            imbalance: self.imbalance(),
+            microprice: self.microprice(),
+            depth_within: self
+                .depth_bands_bps
+                .iter()
+                .filter_map(|bps| {
+                    self.depth_within_bps(*bps)
+                        .map(|(bid, ask)| (*bps, bid, ask))
+                })
+                .collect(),
+        };
+    }
+
+    /// Get the top `levels` levels of each side as `(bids, asks)`
+    pub fn depth(&self, levels: usize) -> (Vec<OrderbookLevel>, Vec<OrderbookLevel>) {
+        (
+            self.bids().take(levels).collect(),
+            self.asks().take(levels).collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: u64, size: u64) -> OrderbookLevel {
+        OrderbookLevel { price, size }
+    }
+
+    #[test]
+    fn test_snapshot_and_best_levels() {
+        let mut book = Orderbook::new("0x1");
+        book.apply_snapshot(
+            &[level(99, 5), level(100, 2)],
+            &[level(102, 1), level(101, 3)],
+            1,
+        );
+
+        assert_eq!(book.best_bid(), Some(level(100, 2)));
+        assert_eq!(book.best_ask(), Some(level(101, 3)));
+        assert_eq!(book.mid_price(), Some(100.5));
+        assert_eq!(book.spread(), Some(1));
+    }
+
+    #[test]
+    fn test_update_removes_empty_levels() {
+        let mut book = Orderbook::new("0x1");
+        book.apply_snapshot(&[level(100, 2)], &[level(101, 3)], 1);
+        book.apply_update(&[level(100, 0), level(98, 4)], &[], 2);
+
+        assert_eq!(book.best_bid(), Some(level(98, 4)));
+        assert_eq!(book.timestamp(), 2);
+    }
+
+    #[test]
+    fn test_volume_is_tracked_incrementally() {
+        let mut book = Orderbook::new("0x1");
+        book.apply_snapshot(&[level(100, 2), level(99, 3)], &[level(101, 4)], 1);
+        assert_eq!(book.bid_volume(), 5);
+        assert_eq!(book.ask_volume(), 4);
+
+        book.apply_update(&[level(100, 0), level(99, 1)], &[level(102, 6)], 2);
+        assert_eq!(book.bid_volume(), 1);
+        assert_eq!(book.ask_volume(), 10);
+    }
+
+    #[test]
+    fn test_imbalance_and_microprice() {
+        let mut book = Orderbook::new("0x1");
+        book.apply_snapshot(&[level(100, 3)], &[level(102, 1)], 1);
+
+        assert_eq!(book.imbalance(), Some(0.5));
+        // Heavier bid queue pulls the microprice towards the ask
+        assert_eq!(book.microprice(), Some(101.5));
+    }
+
+    #[test]
+    fn test_depth_bands_refresh_on_update() {
+        let mut book = Orderbook::new("0x1").with_depth_bands(&[100.0]);
+        book.apply_snapshot(
+            &[level(10_000, 1), level(9_950, 2), level(9_800, 5)],
+            &[level(10_010, 1), level(10_100, 7)],
+            1,
+        );
+
+        // Mid is 10_005, so the 1% band spans [9_905, 10_105]
+        assert_eq!(book.depth_within_bps(100.0), Some((3, 8)));
+        assert_eq!(book.metrics().depth_within, vec![(100.0, 3, 8)]);
+
+        book.apply_update(&[level(9_950, 0)], &[], 2);
+        assert_eq!(book.metrics().depth_within, vec![(100.0, 1, 8)]);
+    }
+
+    #[test]
+    fn test_estimate_fill_walks_levels() {
+        let mut book = Orderbook::new("0x1");
+        book.apply_snapshot(
+            &[level(99, 5), level(98, 5)],
+            &[level(101, 2), level(103, 2)],
+            1,
+        );
+
+        let buy = book.estimate_fill(OrderSide::Buy, 3).unwrap();
+        assert_eq!(buy.filled_size, 3);
+        assert_eq!(buy.worst_price, 103);
+        assert_eq!(buy.levels, 2);
+        assert!((buy.avg_price - 305.0 / 3.0).abs() < 1e-9);
+        assert!((buy.slippage_bps.unwrap() - (305.0 / 3.0 - 100.0) * 100.0).abs() < 1e-6);
+
+        let sell = book.estimate_fill(OrderSide::Sell, 5).unwrap();
+        assert_eq!(sell.avg_price, 99.0);
+        assert_eq!(sell.slippage_bps, Some(100.0));
+    }
+
+    #[test]
+    fn test_estimate_fill_reports_thin_book() {
+        let mut book = Orderbook::new("0x1");
+        book.apply_snapshot(&[], &[level(101, 2)], 1);
+
+        let buy = book.estimate_fill(OrderSide::Buy, 5).unwrap();
+        assert_eq!(buy.filled_size, 2);
+        assert!(!buy.is_complete(5));
+        assert_eq!(buy.slippage_bps, None);
+        assert!(book.estimate_fill(OrderSide::Sell, 1).is_none());
+    }
+
+    #[test]
+    fn test_apply_event_ignores_other_markets() {
+        let mut book = Orderbook::new("0x1");
+        let event = WsEvent::OrderbookSnapshot {
+            market_addr: "0x2".to_string(),
+            bids: vec![level(100, 1)],
+            asks: vec![],
+            timestamp: 1,
+        };
+
+        assert!(!book.apply_event(&event));
+        assert!(book.is_empty());
+    }
+
+    #[test]
+    fn test_resync_holds_updates_until_snapshot() {
+        let market = "0x1111111111111111111111111111111111111111";
+        let mut book = Orderbook::new(market);
+        book.apply_snapshot(&[level(100, 2)], &[level(101, 3)], 1);
+
+        let resync = WsEvent::Resync {
+            channel: channels::orderbook(market),
+            expected: 5,
+            received: 7,
+        };
+        assert!(!book.apply_event(&resync));
+        assert!(book.is_awaiting_snapshot());
+
+        let update = WsEvent::OrderbookUpdate {
+            market_addr: market.to_string(),
+            bids: vec![level(100, 0)],
+            asks: vec![],
+            timestamp: 2,
+        };
+        assert!(!book.apply_event(&update));
+        assert_eq!(book.best_bid(), Some(level(100, 2)));
+
+        let snapshot = WsEvent::OrderbookSnapshot {
+            market_addr: market.to_string(),
+            bids: vec![level(99, 1)],
+            asks: vec![],
+            timestamp: 3,
+        };
+        assert!(book.apply_event(&snapshot));
+        assert!(!book.is_awaiting_snapshot());
+        assert!(book.apply_event(&update));
+    }
+}
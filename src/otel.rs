@@ -0,0 +1,75 @@
+//! OpenTelemetry context propagation.
+//!
+//! REST requests run inside `ekiden.request` spans and WebSocket messages
+//! inside `ekiden.ws_message` spans. With the `otel` feature, each request also
+//! carries the trace context of its span in headers such as `traceparent`, so
+//! gateway traces join the caller's. Install a `tracing-opentelemetry` layer
+//! and a text map propagator to export them:
+//!
+//! ```rust,no_run
+//! use opentelemetry_sdk::propagation::TraceContextPropagator;
+//!
+//! opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+//! ```
+//!
+//! Without a propagator installed no headers are added.
+
+use opentelemetry::propagation::Injector;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Writes propagation fields into request headers
+struct HeaderCarrier<'a>(&'a mut Vec<(String, String)>);
+
+impl Injector for HeaderCarrier<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(key));
+        self.0.push((key.to_string(), value));
+    }
+}
+
+/// Add the trace context of the current span to `headers`
+pub fn inject_context(headers: &mut Vec<(String, String)>) {
+    let context = tracing::Span::current().context();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderCarrier(headers))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::propagation::TextMapPropagator;
+    use std::collections::HashMap;
+
+    #[derive(Debug)]
+    struct Fixed;
+
+    impl TextMapPropagator for Fixed {
+        fn inject_context(&self, _cx: &opentelemetry::Context, injector: &mut dyn Injector) {
+            injector.set("traceparent", "00-trace-span-01".to_string());
+        }
+
+        fn extract_with_context(
+            &self,
+            cx: &opentelemetry::Context,
+            _extractor: &dyn opentelemetry::propagation::Extractor,
+        ) -> opentelemetry::Context {
+            cx.clone()
+        }
+
+        fn fields(&self) -> opentelemetry::propagation::text_map_propagator::FieldIter<'_> {
+            opentelemetry::propagation::text_map_propagator::FieldIter::new(&[])
+        }
+    }
+
+    #[test]
+    fn test_inject_replaces_existing_header() {
+        opentelemetry::global::set_text_map_propagator(Fixed);
+        let mut headers = vec![("TraceParent".to_string(), "stale".to_string())];
+        inject_context(&mut headers);
+        let headers: HashMap<_, _> = headers.into_iter().collect();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers["traceparent"], "00-trace-span-01");
+    }
+}
@@ -0,0 +1,414 @@
+//! WebSocket connections shared out by channel.
+//!
+//! One socket is read by one task, which becomes the bottleneck when a
+//! client follows hundreds of orderbooks. A [`WsPool`] spreads channels over
+//! several [`WebSocketClient`]s. Every channel is assigned to a shard by
+//! rendezvous hashing, so the same channel always lands on the same
+//! connection and changing the shard count moves as few channels as possible.
+//!
+//! [`EkidenClient`](crate::EkidenClient) routes its `subscribe_*` methods
+//! through a pool sized by [`EkidenConfig::ws_connections`]:
+//!
+//! ```rust,no_run
+//! # async fn run(markets: Vec<String>) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::{EkidenClient, EkidenConfig};
+//!
+//! let config = EkidenConfig::production()?.with_ws_connections(4);
+//! let client = EkidenClient::new(config)?;
+//! client.connect_websocket().await?;
+//! for market in &markets {
+//!     let mut book = client.subscribe_orderbook(market).await?;
+//!     tokio::spawn(async move { while let Ok(_event) = book.recv().await {} });
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`EkidenConfig::ws_connections`]: crate::EkidenConfig::ws_connections
+
+use crate::error::{EkidenError, Result};
+use crate::types::WsEvent;
+use crate::ws::{
+    channels, ConnectionStatus, EventStream, SubscriptionInfo, SubscriptionOptions, WebSocketClient,
+};
+use futures_util::future::select_all;
+use std::collections::HashMap;
+use std::time::Instant;
+use tokio::sync::{broadcast, watch};
+
+/// WebSocket connections with channels spread across them
+#[derive(Debug)]
+pub struct WsPool {
+    shards: Vec<WebSocketClient>,
+}
+
+impl WsPool {
+    /// Create a pool over the given connections
+    pub fn new(shards: Vec<WebSocketClient>) -> Result<Self> {
+        if shards.is_empty() {
+            return Err(EkidenError::validation(
+                "A WebSocket pool needs at least one connection",
+            ));
+        }
+        Ok(Self { shards })
+    }
+
+    /// Get the number of connections
+    pub fn len(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Check if the pool has no connections; never true
+    pub fn is_empty(&self) -> bool {
+        self.shards.is_empty()
+    }
+
+    /// Get the connections
+    pub fn shards(&self) -> &[WebSocketClient] {
+        &self.shards
+    }
+
+    /// Get the connections for reconfiguring or connecting them one by one
+    pub fn shards_mut(&mut self) -> &mut [WebSocketClient] {
+        &mut self.shards
+    }
+
+    /// Get the index of the connection carrying a channel
+    pub fn shard_for(&self, channel: &str) -> usize {
+        let channel = &channels::normalize(channel);
+        (0..self.shards.len())
+            .max_by_key(|&shard| weight(channel, shard))
+            .unwrap_or(0)
+    }
+
+    /// Get the connection carrying a channel
+    pub fn shard(&self, channel: &str) -> &WebSocketClient {
+        &self.shards[self.shard_for(channel)]
+    }
+
+    /// Connect every connection, stopping at the first failure
+    pub async fn connect(&mut self) -> Result<()> {
+        for shard in &mut self.shards {
+            shard.connect().await?;
+        }
+        Ok(())
+    }
+
+    /// Disconnect every connection
+    pub async fn disconnect(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        for shard in &mut self.shards {
+            if let Err(e) = shard.disconnect().await {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// Shut down every connection; see [`WebSocketClient::shutdown`]
+    pub async fn shutdown(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        for shard in &mut self.shards {
+            if let Err(e) = shard.shutdown().await {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// Get the status of the pool as a whole
+    ///
+    /// The pool is connected only when every connection is; otherwise the
+    /// worst status wins, from `Failed` down to `Disconnected`.
+    pub async fn connection_status(&self) -> ConnectionStatus {
+        let mut statuses = Vec::with_capacity(self.shards.len());
+        for shard in &self.shards {
+            statuses.push(shard.connection_status().await);
+        }
+        combine(statuses.into_iter())
+    }
+
+    /// Watch status transitions of the pool as a whole
+    ///
+    /// With several connections a task merges their statuses until the
+    /// receiver is dropped, so this must be called within a Tokio runtime.
+    pub fn status_stream(&self) -> watch::Receiver<ConnectionStatus> {
+        if let [shard] = self.shards.as_slice() {
+            return shard.status_stream();
+        }
+        let mut receivers: Vec<_> = self
+            .shards
+            .iter()
+            .map(WebSocketClient::status_stream)
+            .collect();
+        let (tx, rx) = watch::channel(combine(
+            receivers
+                .iter_mut()
+                .map(|rx| rx.borrow_and_update().clone()),
+        ));
+        tokio::spawn(async move {
+            loop {
+                let changed = select_all(receivers.iter_mut().map(|rx| Box::pin(rx.changed())));
+                let result = tokio::select! {
+                    _ = tx.closed() => return,
+                    (result, _, _) = changed => result,
+                };
+                if result.is_err() {
+                    return;
+                }
+                let status = combine(
+                    receivers
+                        .iter_mut()
+                        .map(|rx| rx.borrow_and_update().clone()),
+                );
+                tx.send_if_modified(|current| {
+                    let modified = *current != status;
+                    *current = status;
+                    modified
+                });
+            }
+        });
+        rx
+    }
+
+    /// Check if every connection is connected
+    pub async fn is_connected(&self) -> bool {
+        self.connection_status().await == ConnectionStatus::Connected
+    }
+
+    /// Get the round-trip stats of the connection with the highest p99
+    pub fn latency(&self) -> Option<LatencyStats> {
+        self.shards
+            .iter()
+            .filter_map(WebSocketClient::latency)
+            .max_by_key(|stats| stats.p99)
+    }
+
+    /// Subscribe to a channel on its connection
+    pub async fn subscribe(&self, channel: &str) -> Result<broadcast::Receiver<WsEvent>> {
+        self.shard(channel).subscribe(channel).await
+    }
+
+    /// Subscribe to a channel on its connection with a chosen buffer and lag policy
+    pub async fn subscribe_with(
+        &self,
+        channel: &str,
+        options: SubscriptionOptions,
+    ) -> Result<EventStream> {
+        self.shard(channel).subscribe_with(channel, options).await
+    }
+
+    /// Subscribe to orderbook updates for a market
+    pub async fn subscribe_orderbook(
+        &self,
+        market_addr: &str,
+    ) -> Result<broadcast::Receiver<WsEvent>> {
+        self.subscribe(&channels::orderbook(market_addr)).await
+    }
+
+    /// Subscribe to trade updates for a market
+    pub async fn subscribe_trades(
+        &self,
+        market_addr: &str,
+    ) -> Result<broadcast::Receiver<WsEvent>> {
+        self.subscribe(&channels::trades(market_addr)).await
+    }
+
+    /// Subscribe to live candle updates for a market and interval
+    pub async fn subscribe_candles(
+        &self,
+        market_addr: &str,
+        interval: &str,
+    ) -> Result<broadcast::Receiver<WsEvent>> {
+        self.subscribe(&channels::candles(market_addr, interval))
+            .await
+    }
+
+    /// Subscribe to mark price, oracle price, volume and funding updates for a market
+    pub async fn subscribe_ticker(
+        &self,
+        market_addr: &str,
+    ) -> Result<broadcast::Receiver<WsEvent>> {
+        self.subscribe(&channels::ticker(market_addr)).await
+    }
+
+    /// Subscribe to liquidations on a market
+    pub async fn subscribe_liquidations(
+        &self,
+        market_addr: &str,
+    ) -> Result<broadcast::Receiver<WsEvent>> {
+        self.subscribe(&channels::liquidations(market_addr)).await
+    }
+
+    /// Subscribe to user-specific updates (orders, positions, balances)
+    pub async fn subscribe_user(&self, user_addr: &str) -> Result<broadcast::Receiver<WsEvent>> {
+        self.subscribe(&channels::user(user_addr)).await
+    }
+
+    /// Subscribe to gateway announcements
+    pub async fn subscribe_announcements(&self) -> Result<broadcast::Receiver<WsEvent>> {
+        self.subscribe(&channels::announcements()).await
+    }
+
+    /// Repeat the subscription request for a channel, keeping its receivers
+    pub async fn resubscribe(&self, channel: &str) -> Result<()> {
+        self.shard(channel).resubscribe(channel).await
+    }
+
+    /// Unsubscribe from a channel
+    pub async fn unsubscribe(&self, channel: &str) -> Result<()> {
+        self.shard(channel).unsubscribe(channel).await
+    }
+
+    /// Unsubscribe from every channel on every connection
+    ///
+    /// All channels are forgotten even if a request fails; the first failure
+    /// is returned.
+    pub async fn unsubscribe_all(&self) -> Result<()> {
+        let mut result = Ok(());
+        for shard in &self.shards {
+            if let Err(e) = shard.unsubscribe_all().await {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    /// Get all active subscriptions, sorted
+    pub async fn active_subscriptions(&self) -> Vec<String> {
+        let mut channels = Vec::new();
+        for shard in &self.shards {
+            channels.extend(shard.active_subscriptions().await);
+        }
+        channels.sort_unstable();
+        channels
+    }
+
+    /// Check if subscribed to a channel
+    pub async fn is_subscribed(&self, channel: &str) -> bool {
+        self.shard(channel).is_subscribed(channel).await
+    }
+
+    /// Get the number of receivers attached to a channel
+    pub async fn subscriber_count(&self, channel: &str) -> usize {
+        self.shard(channel).subscriber_count(channel).await
+    }
+
+    /// Get every active subscription with its parsed channel, sorted by channel
+    pub async fn subscriptions(&self) -> Vec<SubscriptionInfo> {
+        let mut subscriptions = Vec::new();
+        for shard in &self.shards {
+            subscriptions.extend(shard.subscriptions().await);
+        }
+        subscriptions.sort_unstable_by(|a, b| a.channel.cmp(&b.channel));
+        subscriptions
+    }
+
+    /// Get the time of the last event per subscribed channel
+    pub async fn channel_activity(&self) -> HashMap<String, Instant> {
+        let mut activity = HashMap::new();
+        for shard in &self.shards {
+            activity.extend(shard.channel_activity().await);
+        }
+        activity
+    }
+}
+
+/// Score of a shard for a channel; the highest score wins
+fn weight(channel: &str, shard: usize) -> u64 {
+    // FNV-1a over the channel and shard, then a finalizer to spread the bits
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in channel.bytes().chain((shard as u64).to_le_bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^ (hash >> 33)
+}
+
+/// Status of several connections taken together
+fn combine(statuses: impl Iterator<Item = ConnectionStatus>) -> ConnectionStatus {
+    fn rank(status: &ConnectionStatus) -> u8 {
+        match status {
+            ConnectionStatus::Connected => 0,
+            ConnectionStatus::Disconnected => 1,
+            ConnectionStatus::Connecting => 2,
+            ConnectionStatus::Reconnecting => 3,
+            ConnectionStatus::Failed(_) => 4,
+        }
+    }
+    statuses
+        .max_by_key(rank)
+        .unwrap_or(ConnectionStatus::Disconnected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    fn pool(size: usize) -> WsPool {
+        let url = Url::parse("ws://localhost:3010/ws").unwrap();
+        WsPool::new(
+            (0..size)
+                .map(|_| WebSocketClient::new(url.clone()))
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_assignment_is_consistent_and_spread() {
+        let channels: Vec<String> = (0..400)
+            .map(|i| channels::orderbook(&format!("0x{:040x}", i)))
+            .collect();
+        let four = pool(4);
+        let mut counts = [0; 4];
+        for channel in &channels {
+            let shard = four.shard_for(channel);
+            assert_eq!(shard, four.shard_for(channel));
+            counts[shard] += 1;
+        }
+        assert!(counts.iter().all(|&count| count > 50), "{:?}", counts);
+
+        // Growing the pool only moves channels onto the new connection
+        let five = pool(5);
+        for channel in &channels {
+            let shard = five.shard_for(channel);
+            assert!(shard == 4 || shard == four.shard_for(channel));
+        }
+
+        assert!(WsPool::new(Vec::new()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_status_is_worst_of_connections() {
+        let pool = pool(2);
+        assert_eq!(
+            pool.connection_status().await,
+            ConnectionStatus::Disconnected
+        );
+        assert_eq!(
+            combine(
+                [
+                    ConnectionStatus::Connected,
+                    ConnectionStatus::Failed("refused".to_string()),
+                ]
+                .into_iter()
+            ),
+            ConnectionStatus::Failed("refused".to_string())
+        );
+        assert_eq!(
+            combine([ConnectionStatus::Connected, ConnectionStatus::Connected].into_iter()),
+            ConnectionStatus::Connected
+        );
+        assert!(!pool.is_connected().await);
+        assert_eq!(
+            *pool.status_stream().borrow(),
+            ConnectionStatus::Disconnected
+        );
+    }
+}
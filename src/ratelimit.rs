@@ -0,0 +1,284 @@
+//! Client-side rate limiting for REST requests.
+//!
+//! With a [`RateLimiterConfig`] on [`EkidenConfig`](crate::EkidenConfig), the
+//! client draws a token from a global bucket and from the bucket of the
+//! request's [`EndpointClass`] before every call. When a bucket is empty the
+//! call waits for it to refill instead of running into a 429, which keeps long
+//! backfills of candles or fills going:
+//!
+//! ```rust,no_run
+//! # async fn run() -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::ratelimit::{EndpointClass, RateLimiterConfig};
+//! use ekiden_rust_sdk::{EkidenClient, EkidenConfig};
+//!
+//! let limits = RateLimiterConfig::new()
+//!     .with_global(20, 10.0)?
+//!     .with_class(EndpointClass::MarketData, 10, 5.0)?;
+//! let client = EkidenClient::new(EkidenConfig::production()?.with_rate_limiter(limits))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::clock::Clock;
+use crate::error::{EkidenError, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Group of endpoints sharing a rate limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndpointClass {
+    /// Public market data: markets, orderbooks, candles, fills
+    MarketData,
+    /// Authenticated account reads and settings
+    Account,
+    /// Intent submission
+    Trading,
+}
+
+impl EndpointClass {
+    /// Classify a request by its API path and whether it is authenticated
+    pub fn classify(path: &str, auth_required: bool) -> Self {
+        match path.trim_matches('/') {
+            "user/intent" => EndpointClass::Trading,
+            _ if auth_required => EndpointClass::Account,
+            _ => EndpointClass::MarketData,
+        }
+    }
+}
+
+/// Size and refill rate of a token bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketConfig {
+    /// Requests that may be made at once after a quiet period
+    pub burst: u32,
+    /// Tokens added per second
+    pub per_second: f64,
+}
+
+impl BucketConfig {
+    /// Create a bucket, rejecting one that would never hand out a token
+    pub fn new(burst: u32, per_second: f64) -> Result<Self> {
+        if burst == 0 {
+            return Err(EkidenError::validation(
+                "Rate limit burst must be greater than zero",
+            ));
+        }
+        if per_second.is_nan() || per_second <= 0.0 {
+            return Err(EkidenError::validation(
+                "Rate limit refill rate must be greater than zero",
+            ));
+        }
+        Ok(Self { burst, per_second })
+    }
+}
+
+/// Buckets applied to REST requests
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimiterConfig {
+    /// Bucket shared by every request
+    pub global: Option<BucketConfig>,
+    /// Buckets per endpoint class
+    pub classes: HashMap<EndpointClass, BucketConfig>,
+}
+
+impl RateLimiterConfig {
+    /// Create a configuration with no buckets
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit all requests together
+    ///
+    /// Fails if `burst` is zero or `per_second` is not positive.
+    pub fn with_global(mut self, burst: u32, per_second: f64) -> Result<Self> {
+        self.global = Some(BucketConfig::new(burst, per_second)?);
+        Ok(self)
+    }
+
+    /// Limit requests of one endpoint class
+    ///
+    /// Fails if `burst` is zero or `per_second` is not positive.
+    pub fn with_class(mut self, class: EndpointClass, burst: u32, per_second: f64) -> Result<Self> {
+        self.classes
+            .insert(class, BucketConfig::new(burst, per_second)?);
+        Ok(self)
+    }
+}
+
+/// A token bucket refilled continuously
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    config: BucketConfig,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket
+    pub fn new(config: BucketConfig, now: Instant) -> Self {
+        Self {
+            config,
+            tokens: config.burst as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.config.per_second).min(self.config.burst as f64);
+        self.updated = now;
+    }
+
+    /// Time until a token is available at `now`
+    pub fn wait_time(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+        Duration::try_from_secs_f64((1.0 - self.tokens) / self.config.per_second)
+            .unwrap_or(Duration::from_secs(1))
+    }
+
+    /// Take a token if one is available at `now`
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        if self.wait_time(now).is_zero() {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    global: Option<TokenBucket>,
+    classes: HashMap<EndpointClass, TokenBucket>,
+}
+
+/// Makes requests wait for tokens from the configured buckets
+#[derive(Debug)]
+pub struct RateLimiter {
+    buckets: Mutex<Buckets>,
+    clock: Arc<dyn Clock>,
+}
+
+impl RateLimiter {
+    /// Create a limiter with full buckets
+    pub fn new(config: &RateLimiterConfig, clock: Arc<dyn Clock>) -> Self {
+        let now = clock.instant();
+        Self {
+            buckets: Mutex::new(Buckets {
+                global: config.global.map(|bucket| TokenBucket::new(bucket, now)),
+                classes: config
+                    .classes
+                    .iter()
+                    .map(|(class, bucket)| (*class, TokenBucket::new(*bucket, now)))
+                    .collect(),
+            }),
+            clock,
+        }
+    }
+
+    /// Wait until both the global and the class bucket have a token, then take them
+    pub async fn acquire(&self, class: EndpointClass) {
+        loop {
+            let wait = {
+                let mut buckets = self.buckets.lock().unwrap();
+                let now = self.clock.instant();
+                let Buckets { global, classes } = &mut *buckets;
+                let wait = global
+                    .iter_mut()
+                    .chain(classes.get_mut(&class))
+                    .map(|bucket| bucket.wait_time(now))
+                    .max()
+                    .unwrap_or_default();
+                if wait.is_zero() {
+                    for bucket in global.iter_mut().chain(classes.get_mut(&class)) {
+                        bucket.try_acquire(now);
+                    }
+                    return;
+                }
+                wait
+            };
+            debug!("Rate limited {:?} request, waiting {:?}", class, wait);
+            self.clock.sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            EndpointClass::classify("candles", false),
+            EndpointClass::MarketData
+        );
+        assert_eq!(
+            EndpointClass::classify("user/portfolio", true),
+            EndpointClass::Account
+        );
+        assert_eq!(
+            EndpointClass::classify("/user/intent", true),
+            EndpointClass::Trading
+        );
+    }
+
+    #[test]
+    fn test_rejects_buckets_that_never_refill() {
+        assert!(RateLimiterConfig::new().with_global(0, 10.0).is_err());
+        assert!(RateLimiterConfig::new()
+            .with_class(EndpointClass::Trading, 5, 0.0)
+            .is_err());
+        assert!(BucketConfig::new(5, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_bucket_refills() {
+        let now = ManualClock::default().instant();
+        let config = BucketConfig {
+            burst: 2,
+            per_second: 4.0,
+        };
+        let mut bucket = TokenBucket::new(config, now);
+        assert!(bucket.try_acquire(now));
+        assert!(bucket.try_acquire(now));
+        assert!(!bucket.try_acquire(now));
+        assert_eq!(bucket.wait_time(now), Duration::from_millis(250));
+        assert!(bucket.try_acquire(now + Duration::from_millis(250)));
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_refill() {
+        let clock = ManualClock::default();
+        let config = RateLimiterConfig::new()
+            .with_global(10, 10.0)
+            .unwrap()
+            .with_class(EndpointClass::MarketData, 1, 1.0)
+            .unwrap();
+        let limiter = Arc::new(RateLimiter::new(&config, Arc::new(clock.clone())));
+
+        limiter.acquire(EndpointClass::MarketData).await;
+        // Other classes only draw from the global bucket
+        limiter.acquire(EndpointClass::Account).await;
+
+        let waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire(EndpointClass::MarketData).await }
+        });
+        while clock.pending_sleeps() == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(!waiting.is_finished());
+
+        clock.advance(Duration::from_secs(1));
+        waiting.await.unwrap();
+    }
+}
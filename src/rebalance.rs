@@ -0,0 +1,458 @@
+//! Portfolio rebalancing across markets.
+//!
+//! A [`Rebalancer`] holds a target exposure per market as a signed fraction of
+//! account equity, compares it with the current portfolio and builds the
+//! orders needed to close the gap. Orders below a market's minimum size or
+//! within the configured tolerance are skipped, and targets are scaled down
+//! when they would need more initial margin than allowed:
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::rebalance::Rebalancer;
+//!
+//! let rebalancer = Rebalancer::new(client)
+//!     .with_target("0x1111111111111111111111111111111111111111", 0.5)
+//!     .with_target("0x2222222222222222222222222222222222222222", -0.25)
+//!     .with_dry_run(true);
+//!
+//! let outcome = rebalancer.rebalance().await?;
+//! for order in &outcome.plan.orders {
+//!     println!("{:?} {} on {}", order.side, order.size, order.symbol);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Exposure is measured as `size * mark_price / 10^base_decimals`, in the same
+//! quote units as the portfolio's total value.
+
+use crate::client::EkidenClient;
+use crate::error::Result;
+use crate::types::{
+    ActionPayload, ListMarketsParams, MarketResponse, OrderSide, OrderType, PortfolioResponse,
+    SendIntentResponse,
+};
+use std::collections::HashMap;
+use tracing::{debug, info};
+
+/// Default share of equity that targets may commit as initial margin
+pub const DEFAULT_MAX_MARGIN_USAGE: f64 = 0.8;
+
+/// An order needed to move a market towards its target
+#[derive(Debug, Clone, PartialEq)]
+pub struct RebalanceOrder {
+    pub market_addr: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    /// Order size, in base units
+    pub size: u64,
+    /// Reference price the order was sized at
+    pub price: u64,
+    /// Signed position before the order
+    pub current_size: i64,
+    /// Signed position the order moves towards
+    pub target_size: i64,
+}
+
+impl RebalanceOrder {
+    /// Whether the order shrinks the absolute position
+    pub fn is_reducing(&self) -> bool {
+        self.target_size.unsigned_abs() < self.current_size.unsigned_abs()
+            && self.target_size.signum() * self.current_size.signum() >= 0
+    }
+}
+
+/// Why a market was left untouched
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// The market is not listed by the gateway
+    UnknownMarket,
+    /// The market has no mark price to size the order with
+    NoPrice,
+    /// The difference is smaller than the market's minimum order size
+    BelowMinSize { size: u64, min_order_size: u64 },
+    /// The difference is within the configured tolerance
+    WithinTolerance,
+}
+
+/// Orders computed for a rebalance
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RebalancePlan {
+    /// Account equity the targets were applied to, in quote units
+    pub equity: u64,
+    /// Factor applied to all targets to stay within the margin limit
+    pub scale: f64,
+    /// Orders to send, position reductions first
+    pub orders: Vec<RebalanceOrder>,
+    /// Markets that need no order, with the reason
+    pub skipped: Vec<(String, SkipReason)>,
+}
+
+impl RebalancePlan {
+    /// Check if the portfolio is already on target
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    /// Build the `place_order` actions for the plan
+    pub fn actions(&self, order_type: OrderType) -> Vec<ActionPayload> {
+        self.orders
+            .iter()
+            .map(|order| {
+                ActionPayload::place_order(
+                    &order.market_addr,
+                    order.side,
+                    order_type,
+                    order.price,
+                    order.size,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Result of [`Rebalancer::rebalance`]
+#[derive(Debug, Clone)]
+pub struct RebalanceOutcome {
+    pub plan: RebalancePlan,
+    /// Gateway response, or `None` in dry-run mode or when nothing was sent
+    pub response: Option<SendIntentResponse>,
+}
+
+/// Moves a portfolio towards target exposures per market
+#[derive(Debug, Clone)]
+pub struct Rebalancer {
+    client: EkidenClient,
+    targets: HashMap<String, f64>,
+    tolerance_bps: f64,
+    max_margin_usage: f64,
+    close_untargeted: bool,
+    order_type: OrderType,
+    dry_run: bool,
+}
+
+impl Rebalancer {
+    /// Create a rebalancer with no targets
+    pub fn new(client: EkidenClient) -> Self {
+        Self {
+            client,
+            targets: HashMap::new(),
+            tolerance_bps: 0.0,
+            max_margin_usage: DEFAULT_MAX_MARGIN_USAGE,
+            close_untargeted: false,
+            order_type: OrderType::Market,
+            dry_run: false,
+        }
+    }
+
+    /// Set the target exposure of a market as a fraction of equity
+    ///
+    /// Negative weights target a short position.
+    pub fn with_target<S: Into<String>>(mut self, market_addr: S, weight: f64) -> Self {
+        self.targets
+            .insert(market_addr.into().to_lowercase(), weight);
+        self
+    }
+
+    /// Skip markets whose exposure differs from the target by less than this
+    pub fn with_tolerance_bps(mut self, tolerance_bps: f64) -> Self {
+        self.tolerance_bps = tolerance_bps;
+        self
+    }
+
+    /// Set the share of equity that targets may commit as initial margin
+    pub fn with_max_margin_usage(mut self, max_margin_usage: f64) -> Self {
+        self.max_margin_usage = max_margin_usage;
+        self
+    }
+
+    /// Close positions in markets without a target
+    pub fn with_close_untargeted(mut self, close_untargeted: bool) -> Self {
+        self.close_untargeted = close_untargeted;
+        self
+    }
+
+    /// Set the order type used for rebalancing orders
+    pub fn with_order_type(mut self, order_type: OrderType) -> Self {
+        self.order_type = order_type;
+        self
+    }
+
+    /// Compute orders without sending them
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Fetch the portfolio and markets and compute the required orders
+    pub async fn plan(&self) -> Result<RebalancePlan> {
+        let portfolio = self.client.get_user_portfolio().await?;
+        let markets = self
+            .client
+            .get_markets(ListMarketsParams::default())
+            .await?;
+        Ok(self.compute(&portfolio, &markets))
+    }
+
+    /// Compute the required orders and send them as one batched intent
+    pub async fn rebalance(&self) -> Result<RebalanceOutcome> {
+        let plan = self.plan().await?;
+        if self.dry_run || plan.is_empty() {
+            debug!(
+                "Rebalance plan has {} orders, not sending",
+                plan.orders.len()
+            );
+            return Ok(RebalanceOutcome {
+                plan,
+                response: None,
+            });
+        }
+
+        let response = self
+            .client
+            .send_actions(plan.actions(self.order_type))
+            .await?;
+        info!("Rebalanced with {} orders", plan.orders.len());
+        Ok(RebalanceOutcome {
+            plan,
+            response: Some(response),
+        })
+    }
+
+    /// Compute the orders that move a portfolio towards the targets
+    pub fn compute(
+        &self,
+        portfolio: &PortfolioResponse,
+        markets: &[MarketResponse],
+    ) -> RebalancePlan {
+        let equity = portfolio.summary.total_value;
+        let markets: HashMap<String, &MarketResponse> = markets
+            .iter()
+            .map(|market| (market.addr.to_lowercase(), market))
+            .collect();
+        let positions: HashMap<String, i64> = portfolio
+            .positions
+            .iter()
+            .map(|position| (position.market_addr.to_lowercase(), position.signed_size()))
+            .collect();
+
+        let mut targets = self.targets.clone();
+        if self.close_untargeted {
+            for market_addr in positions.keys() {
+                targets.entry(market_addr.clone()).or_insert(0.0);
+            }
+        }
+
+        let required_margin: f64 = targets
+            .iter()
+            .filter_map(|(addr, weight)| {
+                markets
+                    .get(addr)
+                    .map(|market| weight.abs() * market.initial_margin_ratio)
+            })
+            .sum();
+        let scale = if required_margin > self.max_margin_usage {
+            self.max_margin_usage / required_margin
+        } else {
+            1.0
+        };
+
+        let mut market_addrs: Vec<&String> = targets.keys().collect();
+        market_addrs.sort();
+
+        let mut plan = RebalancePlan {
+            equity,
+            scale,
+            ..Default::default()
+        };
+        for market_addr in market_addrs {
+            let Some(market) = markets.get(market_addr) else {
+                plan.skipped
+                    .push((market_addr.clone(), SkipReason::UnknownMarket));
+                continue;
+            };
+            if market.mark_price == 0 {
+                plan.skipped
+                    .push((market_addr.clone(), SkipReason::NoPrice));
+                continue;
+            }
+
+            let unit = 10f64.powi(market.base_decimals as i32);
+            let target_value = targets[market_addr] * scale * equity as f64;
+            let target_size = (target_value * unit / market.mark_price as f64).round() as i64;
+            let current_size = positions.get(market_addr).copied().unwrap_or(0);
+            let delta = target_size - current_size;
+            let size = delta.unsigned_abs();
+
+            let delta_value = size as f64 * market.mark_price as f64 / unit;
+            if size == 0 || delta_value < equity as f64 * self.tolerance_bps / 10_000.0 {
+                plan.skipped
+                    .push((market_addr.clone(), SkipReason::WithinTolerance));
+                continue;
+            }
+            if size < market.min_order_size {
+                plan.skipped.push((
+                    market_addr.clone(),
+                    SkipReason::BelowMinSize {
+                        size,
+                        min_order_size: market.min_order_size,
+                    },
+                ));
+                continue;
+            }
+
+            plan.orders.push(RebalanceOrder {
+                market_addr: market.addr.clone(),
+                symbol: market.symbol.clone(),
+                side: if delta > 0 {
+                    OrderSide::Buy
+                } else {
+                    OrderSide::Sell
+                },
+                size,
+                price: market.mark_price,
+                current_size,
+                target_size,
+            });
+        }
+
+        // Free margin before adding exposure elsewhere
+        plan.orders.sort_by_key(|order| !order.is_reducing());
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PortfolioPosition, PortfolioSummary};
+
+    const BTC: &str = "0x1111111111111111111111111111111111111111";
+    const ETH: &str = "0x2222222222222222222222222222222222222222";
+
+    fn market(addr: &str, symbol: &str, mark_price: u64, min_order_size: u64) -> MarketResponse {
+        MarketResponse {
+            addr: addr.to_string(),
+            symbol: symbol.to_string(),
+            base_decimals: 2,
+            min_order_size,
+            max_leverage: 10,
+            initial_margin_ratio: 0.1,
+            maintenance_margin_ratio: 0.05,
+            mark_price,
+            oracle_price: mark_price,
+            ..crate::test_utils::market()
+        }
+    }
+
+    fn portfolio(total_value: u64, positions: &[(&str, &str, u64)]) -> PortfolioResponse {
+        PortfolioResponse {
+            summary: PortfolioSummary {
+                total_value,
+                available_balance: total_value,
+                locked_balance: 0,
+                unrealized_pnl: 0,
+                margin_used: 0,
+                margin_available: total_value,
+            },
+            positions: positions
+                .iter()
+                .map(|(market_addr, side, size)| PortfolioPosition {
+                    market_addr: market_addr.to_string(),
+                    symbol: String::new(),
+                    side: side.to_string(),
+                    size: *size,
+                    entry_price: 0,
+                    mark_price: 0,
+                    unrealized_pnl: 0,
+                    margin: 0,
+                    leverage: 1,
+                })
+                .collect(),
+            vaults: Vec::new(),
+        }
+    }
+
+    async fn rebalancer() -> Rebalancer {
+        let client = crate::EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        Rebalancer::new(client)
+    }
+
+    #[tokio::test]
+    async fn test_orders_move_towards_targets_reductions_first() {
+        let rebalancer = rebalancer()
+            .await
+            .with_target(BTC, 0.5)
+            .with_target(ETH, -0.25);
+        let markets = [
+            market(BTC, "BTC-PERP", 1_000, 1),
+            market(ETH, "ETH-PERP", 100, 1),
+        ];
+        // 1.00 BTC long at 1000 = 1000 of exposure, target 500
+        let plan = rebalancer.compute(&portfolio(1_000, &[(BTC, "long", 100)]), &markets);
+
+        assert_eq!(plan.scale, 1.0);
+        assert_eq!(plan.orders.len(), 2);
+        assert_eq!(plan.orders[0].market_addr, BTC);
+        assert_eq!(plan.orders[0].side, OrderSide::Sell);
+        assert_eq!(plan.orders[0].size, 50);
+        assert_eq!(plan.orders[1].market_addr, ETH);
+        assert_eq!(plan.orders[1].side, OrderSide::Sell);
+        assert_eq!(plan.orders[1].size, 250);
+        assert_eq!(plan.orders[1].target_size, -250);
+    }
+
+    #[tokio::test]
+    async fn test_skips_small_and_unknown_markets() {
+        let rebalancer = rebalancer()
+            .await
+            .with_target(BTC, 0.5)
+            .with_target("0x9999999999999999999999999999999999999999", 0.1);
+        let markets = [market(BTC, "BTC-PERP", 1_000, 10)];
+        let plan = rebalancer.compute(&portfolio(1_000, &[(BTC, "long", 45)]), &markets);
+
+        assert!(plan.is_empty());
+        assert!(plan.skipped.contains(&(
+            BTC.to_string(),
+            SkipReason::BelowMinSize {
+                size: 5,
+                min_order_size: 10
+            }
+        )));
+        assert!(plan
+            .skipped
+            .iter()
+            .any(|(_, reason)| *reason == SkipReason::UnknownMarket));
+    }
+
+    #[tokio::test]
+    async fn test_scales_targets_to_margin_limit() {
+        // 20x gross exposure at 10% initial margin needs twice the equity
+        let rebalancer = rebalancer()
+            .await
+            .with_target(BTC, 20.0)
+            .with_max_margin_usage(1.0);
+        let markets = [market(BTC, "BTC-PERP", 1_000, 1)];
+        let plan = rebalancer.compute(&portfolio(1_000, &[]), &markets);
+
+        assert_eq!(plan.scale, 0.5);
+        assert_eq!(plan.orders[0].size, 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_closes_untargeted_positions() {
+        let rebalancer = rebalancer().await.with_close_untargeted(true);
+        let markets = [market(ETH, "ETH-PERP", 100, 1)];
+        let plan = rebalancer.compute(&portfolio(1_000, &[(ETH, "short", 30)]), &markets);
+
+        assert_eq!(plan.orders.len(), 1);
+        assert_eq!(plan.orders[0].side, OrderSide::Buy);
+        assert_eq!(plan.orders[0].size, 30);
+        assert!(plan.orders[0].is_reducing());
+    }
+}
@@ -0,0 +1,631 @@
+//! Market data recorder persisting WebSocket events into SQLite.
+//!
+//! Enabled with the `recorder` feature. Events from the selected channels are
+//! batched in memory and written in a single transaction per batch on a
+//! dedicated thread. Database files are rotated by row count and/or age, so
+//! long captures produce a series of manageable files.
+
+use crate::client::EkidenClient;
+use crate::error::{EkidenError, Result};
+use crate::types::{FundingRateResponse, OrderbookLevel, WsEvent};
+use crate::ws::channels;
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc as std_mpsc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{error, info, warn};
+
+/// Configuration for a [`Recorder`]
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    /// Database path; rotated files get a timestamp suffix before the extension
+    pub path: PathBuf,
+    /// Number of records written per transaction
+    pub batch_size: usize,
+    /// Maximum time a record waits in memory before being written
+    pub flush_interval: Duration,
+    /// Start a new file after this many rows
+    pub rotate_after_rows: Option<u64>,
+    /// Start a new file after this much time
+    pub rotate_interval: Option<Duration>,
+    /// How often funding rates are polled for recorded markets
+    pub funding_poll_interval: Duration,
+}
+
+impl RecorderConfig {
+    /// Create a configuration writing to `path`
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            batch_size: 500,
+            flush_interval: Duration::from_secs(1),
+            rotate_after_rows: None,
+            rotate_interval: None,
+            funding_poll_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Set the batch size and flush interval
+    pub fn with_batching(mut self, batch_size: usize, flush_interval: Duration) -> Self {
+        self.batch_size = batch_size.max(1);
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Rotate files after a number of rows
+    pub fn with_rotate_after_rows(mut self, rows: u64) -> Self {
+        self.rotate_after_rows = Some(rows);
+        self
+    }
+
+    /// Rotate files after a time interval
+    pub fn with_rotate_interval(mut self, interval: Duration) -> Self {
+        self.rotate_interval = Some(interval);
+        self
+    }
+}
+
+/// A single row to persist
+#[derive(Debug, Clone)]
+pub enum Record {
+    Trade {
+        market_addr: String,
+        price: u64,
+        size: u64,
+        side: String,
+        timestamp: u64,
+    },
+    Book {
+        market_addr: String,
+        kind: &'static str,
+        bids: Vec<OrderbookLevel>,
+        asks: Vec<OrderbookLevel>,
+        timestamp: u64,
+    },
+    Funding(FundingRateResponse),
+    Event {
+        channel: String,
+        event_type: String,
+        payload: String,
+        received_at: u64,
+    },
+}
+
+impl Record {
+    /// Convert an event received on `channel` into a record
+    pub fn from_event(channel: &str, event: WsEvent) -> Result<Self> {
+        Ok(match event {
+            WsEvent::Trade {
+                market_addr,
+                price,
+                size,
+                side,
+                timestamp,
+            } => Record::Trade {
+                market_addr,
+                price,
+                size,
+                side,
+                timestamp,
+            },
+            WsEvent::OrderbookSnapshot {
+                market_addr,
+                bids,
+                asks,
+                timestamp,
+            } => Record::Book {
+                market_addr,
+                kind: "snapshot",
+                bids,
+                asks,
+                timestamp,
+            },
+            WsEvent::OrderbookUpdate {
+                market_addr,
+                bids,
+                asks,
+                timestamp,
+            } => Record::Book {
+                market_addr,
+                kind: "update",
+                bids,
+                asks,
+                timestamp,
+            },
+            event => {
+                let payload = serde_json::to_value(&event)?;
+                let event_type = payload
+                    .get("type")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or("unknown")
+                    .to_string();
+                Record::Event {
+                    channel: channel.to_string(),
+                    event_type,
+                    payload: payload.to_string(),
+                    received_at: now_millis(),
+                }
+            }
+        })
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn sqlite_error(e: rusqlite::Error) -> EkidenError {
+    EkidenError::general(format!("SQLite error: {}", e))
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS trades (
+        market_addr TEXT NOT NULL,
+        price INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        side TEXT NOT NULL,
+        timestamp INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS orderbook (
+        market_addr TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        bids TEXT NOT NULL,
+        asks TEXT NOT NULL,
+        timestamp INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS funding (
+        market_addr TEXT NOT NULL,
+        funding_rate REAL NOT NULL,
+        funding_index INTEGER NOT NULL,
+        funding_epoch INTEGER NOT NULL,
+        next_funding_time INTEGER NOT NULL,
+        timestamp INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        channel TEXT NOT NULL,
+        event_type TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        received_at INTEGER NOT NULL
+    );
+";
+
+/// Batched SQLite writer with file rotation
+#[derive(Debug)]
+pub struct SqliteWriter {
+    config: RecorderConfig,
+    conn: Connection,
+    current_path: PathBuf,
+    rows: u64,
+    opened_at: Instant,
+    sequence: u32,
+}
+
+impl SqliteWriter {
+    /// Open the configured database and create the schema
+    pub fn open(config: RecorderConfig) -> Result<Self> {
+        let current_path = Self::file_path(&config, 0);
+        let conn = Self::connect(&current_path)?;
+        Ok(Self {
+            config,
+            conn,
+            current_path,
+            rows: 0,
+            opened_at: Instant::now(),
+            sequence: 0,
+        })
+    }
+
+    /// Get the path of the file currently written
+    pub fn current_path(&self) -> &Path {
+        &self.current_path
+    }
+
+    fn rotates(config: &RecorderConfig) -> bool {
+        config.rotate_after_rows.is_some() || config.rotate_interval.is_some()
+    }
+
+    fn file_path(config: &RecorderConfig, sequence: u32) -> PathBuf {
+        if !Self::rotates(config) {
+            return config.path.clone();
+        }
+        let stem = config
+            .path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("ekiden");
+        let file_name = match config.path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => format!("{}-{}-{}.{}", stem, now_millis(), sequence, ext),
+            None => format!("{}-{}-{}", stem, now_millis(), sequence),
+        };
+        config.path.with_file_name(file_name)
+    }
+
+    fn connect(path: &Path) -> Result<Connection> {
+        let conn = Connection::open(path).map_err(sqlite_error)?;
+        conn.execute_batch(SCHEMA).map_err(sqlite_error)?;
+        Ok(conn)
+    }
+
+    fn rotate_if_needed(&mut self) -> Result<()> {
+        let by_rows = self
+            .config
+            .rotate_after_rows
+            .is_some_and(|max| self.rows >= max);
+        let by_age = self
+            .config
+            .rotate_interval
+            .is_some_and(|max| self.opened_at.elapsed() >= max);
+        if !(by_rows || by_age) {
+            return Ok(());
+        }
+
+        self.sequence += 1;
+        let path = Self::file_path(&self.config, self.sequence);
+        self.conn = Self::connect(&path)?;
+        info!("Recorder rotated to {}", path.display());
+        self.current_path = path;
+        self.rows = 0;
+        self.opened_at = Instant::now();
+        Ok(())
+    }
+
+    /// Write a batch of records in one transaction
+    pub fn write(&mut self, records: &[Record]) -> Result<()> {
+        self.rotate_if_needed()?;
+
+        let tx = self.conn.transaction().map_err(sqlite_error)?;
+        for record in records {
+            match record {
+                Record::Trade {
+                    market_addr,
+                    price,
+                    size,
+                    side,
+                    timestamp,
+                } => tx.execute(
+                    "INSERT INTO trades (market_addr, price, size, side, timestamp) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![market_addr, *price as i64, *size as i64, side, *timestamp as i64],
+                ),
+                Record::Book {
+                    market_addr,
+                    kind,
+                    bids,
+                    asks,
+                    timestamp,
+                } => tx.execute(
+                    "INSERT INTO orderbook (market_addr, kind, bids, asks, timestamp) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        market_addr,
+                        kind,
+                        serde_json::to_string(bids)?,
+                        serde_json::to_string(asks)?,
+                        *timestamp as i64
+                    ],
+                ),
+                Record::Funding(rate) => tx.execute(
+                    "INSERT INTO funding (market_addr, funding_rate, funding_index, funding_epoch, next_funding_time, timestamp) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        rate.market_addr,
+                        rate.funding_rate,
+                        rate.funding_index as i64,
+                        rate.funding_epoch as i64,
+                        rate.next_funding_time as i64,
+                        rate.timestamp as i64
+                    ],
+                ),
+                Record::Event {
+                    channel,
+                    event_type,
+                    payload,
+                    received_at,
+                } => tx.execute(
+                    "INSERT INTO events (channel, event_type, payload, received_at) VALUES (?1, ?2, ?3, ?4)",
+                    params![channel, event_type, payload, *received_at as i64],
+                ),
+            }
+            .map_err(sqlite_error)?;
+        }
+        tx.commit().map_err(sqlite_error)?;
+
+        self.rows += records.len() as u64;
+        Ok(())
+    }
+}
+
+/// Subscribes to channels and persists their events into SQLite
+pub struct Recorder {
+    client: EkidenClient,
+    config: RecorderConfig,
+    subscriptions: Vec<Subscription>,
+    funding_markets: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Subscription {
+    Orderbook(String),
+    Trades(String),
+    User(String),
+}
+
+impl Subscription {
+    fn channel(&self) -> String {
+        match self {
+            Subscription::Orderbook(market_addr) => channels::orderbook(market_addr),
+            Subscription::Trades(market_addr) => channels::trades(market_addr),
+            Subscription::User(user_addr) => channels::user(user_addr),
+        }
+    }
+
+    async fn subscribe(&self, client: &EkidenClient) -> Result<broadcast::Receiver<WsEvent>> {
+        match self {
+            Subscription::Orderbook(market_addr) => client.subscribe_orderbook(market_addr).await,
+            Subscription::Trades(market_addr) => client.subscribe_trades(market_addr).await,
+            Subscription::User(user_addr) => client.subscribe_user(user_addr).await,
+        }
+    }
+}
+
+impl Recorder {
+    /// Create a recorder; the client's WebSocket must be connected before [`Recorder::run`]
+    pub fn new(client: EkidenClient, config: RecorderConfig) -> Self {
+        Self {
+            client,
+            config,
+            subscriptions: Vec::new(),
+            funding_markets: Vec::new(),
+        }
+    }
+
+    /// Record orderbook snapshots and updates for a market
+    pub fn orderbook(mut self, market_addr: &str) -> Self {
+        self.subscriptions
+            .push(Subscription::Orderbook(market_addr.to_string()));
+        self
+    }
+
+    /// Record public trades for a market
+    pub fn trades(mut self, market_addr: &str) -> Self {
+        self.subscriptions
+            .push(Subscription::Trades(market_addr.to_string()));
+        self
+    }
+
+    /// Record user events (orders, positions, balances) for a user
+    pub fn user(mut self, user_addr: &str) -> Self {
+        self.subscriptions
+            .push(Subscription::User(user_addr.to_string()));
+        self
+    }
+
+    /// Record funding rates for a market by polling the REST API
+    pub fn funding(mut self, market_addr: &str) -> Self {
+        self.funding_markets.push(market_addr.to_string());
+        self
+    }
+
+    /// Run until all subscriptions close, flushing pending records on exit
+    ///
+    /// A failed database write stops the recorder and is returned.
+    pub async fn run(self) -> Result<()> {
+        let (event_tx, mut event_rx) = mpsc::channel::<(String, WsEvent)>(10_000);
+        for subscription in &self.subscriptions {
+            let receiver = subscription.subscribe(&self.client).await?;
+            tokio::spawn(forward(subscription.channel(), receiver, event_tx.clone()));
+        }
+        drop(event_tx);
+
+        let mut writer = WriterThread::spawn(self.config.clone())?;
+        let mut batch = Vec::with_capacity(self.config.batch_size);
+        let mut flush = tokio::time::interval(self.config.flush_interval);
+        let mut funding = tokio::time::interval(self.config.funding_poll_interval);
+        let poll_funding = !self.funding_markets.is_empty();
+        let mut events_open = !self.subscriptions.is_empty();
+
+        while events_open || poll_funding {
+            tokio::select! {
+                event = event_rx.recv(), if events_open => match event {
+                    Some((channel, event)) => match Record::from_event(&channel, event) {
+                        Ok(record) => batch.push(record),
+                        Err(e) => warn!("Failed to convert event on {}: {}", channel, e),
+                    },
+                    None => events_open = false,
+                },
+                _ = funding.tick(), if poll_funding => {
+                    for market_addr in &self.funding_markets {
+                        match self.client.get_current_funding_rate(market_addr).await {
+                            Ok(Some(rate)) => batch.push(Record::Funding(rate)),
+                            Ok(None) => {}
+                            Err(e) => warn!("Failed to poll funding for {}: {}", market_addr, e),
+                        }
+                    }
+                }
+                _ = flush.tick() => {
+                    if !batch.is_empty() {
+                        writer.send(&mut batch)?;
+                    }
+                    continue;
+                }
+            }
+
+            if batch.len() >= self.config.batch_size {
+                writer.send(&mut batch)?;
+            }
+        }
+
+        if !batch.is_empty() {
+            writer.send(&mut batch)?;
+        }
+        writer.finish()
+    }
+}
+
+async fn forward(
+    channel: String,
+    mut receiver: broadcast::Receiver<WsEvent>,
+    sender: mpsc::Sender<(String, WsEvent)>,
+) {
+    loop {
+        match receiver.recv().await {
+            Ok(event) => {
+                if sender.send((channel.clone(), event)).await.is_err() {
+                    break;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                warn!("Recorder lagged on {}, missed {} events", channel, missed);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// A [`SqliteWriter`] on its own thread, fed batches over a channel
+struct WriterThread {
+    sender: std_mpsc::Sender<Vec<Record>>,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl WriterThread {
+    fn spawn(config: RecorderConfig) -> Result<Self> {
+        let mut writer = SqliteWriter::open(config)?;
+        let (sender, receiver) = std_mpsc::channel::<Vec<Record>>();
+        let handle = std::thread::spawn(move || {
+            for batch in receiver {
+                if let Err(e) = writer.write(&batch) {
+                    error!("Failed to write {} records: {}", batch.len(), e);
+                    return Err(e);
+                }
+            }
+            Ok(())
+        });
+        Ok(Self {
+            sender,
+            handle: Some(handle),
+        })
+    }
+
+    /// Hand a batch to the thread, or get the error that stopped it
+    fn send(&mut self, batch: &mut Vec<Record>) -> Result<()> {
+        if self.sender.send(std::mem::take(batch)).is_ok() {
+            return Ok(());
+        }
+        Self::join(self.handle.take())?;
+        Err(EkidenError::general("Recorder writer stopped"))
+    }
+
+    /// Wait for queued batches to be written
+    fn finish(self) -> Result<()> {
+        let Self { sender, handle } = self;
+        drop(sender);
+        Self::join(handle)
+    }
+
+    fn join(handle: Option<JoinHandle<Result<()>>>) -> Result<()> {
+        match handle {
+            Some(handle) => handle
+                .join()
+                .map_err(|_| EkidenError::general("Recorder writer thread panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ekiden-recorder-{}-{}.db", name, now_millis()))
+    }
+
+    fn trade() -> Record {
+        Record::from_event(
+            "trades/0x1",
+            WsEvent::Trade {
+                market_addr: "0x1".to_string(),
+                price: 100,
+                size: 2,
+                side: "buy".to_string(),
+                timestamp: 1,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_writes_batches() {
+        let path = temp_path("batch");
+        let mut writer = SqliteWriter::open(RecorderConfig::new(&path)).unwrap();
+        writer.write(&[trade(), trade()]).unwrap();
+
+        let count: i64 = writer
+            .conn
+            .query_row("SELECT COUNT(*) FROM trades", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_rotates_by_rows() {
+        let path = temp_path("rotate");
+        let config = RecorderConfig::new(&path).with_rotate_after_rows(2);
+        let mut writer = SqliteWriter::open(config).unwrap();
+
+        writer.write(&[trade(), trade()]).unwrap();
+        let first = writer.current_path().to_path_buf();
+        writer.write(&[trade()]).unwrap();
+
+        assert_ne!(first, writer.current_path());
+        let _ = std::fs::remove_file(first);
+        let _ = std::fs::remove_file(writer.current_path());
+    }
+
+    #[test]
+    fn test_writer_thread_returns_write_errors() {
+        let dir = temp_path("failing");
+        std::fs::create_dir(&dir).unwrap();
+        let config = RecorderConfig::new(dir.join("capture.db")).with_rotate_after_rows(1);
+        let mut writer = WriterThread::spawn(config).unwrap();
+
+        writer.send(&mut vec![trade()]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        // Writing into the removed directory fails, which stops the thread
+        let sent = writer.send(&mut vec![trade()]);
+
+        let err = sent.and(writer.finish()).unwrap_err();
+        assert!(err.to_string().contains("SQLite error"), "{}", err);
+    }
+
+    #[test]
+    fn test_user_events_are_stored_generically() {
+        let record = Record::from_event(
+            "user/0x1",
+            serde_json::from_value(serde_json::json!({
+                "type": "balance_update",
+                "vault": {
+                    "vault_addr": "0x2",
+                    "user_addr": "0x1",
+                    "asset_addr": "0x3",
+                    "balance": 10,
+                    "locked_balance": 0,
+                    "available_balance": 10,
+                    "created_at": "",
+                    "updated_at": ""
+                }
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            record,
+            Record::Event { ref event_type, .. } if event_type == "balance_update"
+        ));
+    }
+}
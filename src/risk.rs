@@ -0,0 +1,636 @@
+//! Pre-trade risk checks around the trading API.
+//!
+//! [`RiskGuard`] wraps an [`EkidenClient`] and validates every intent against
+//! configurable [`RiskLimits`] before it reaches the gateway. Order-level
+//! breaches reject the intent, while a breach of the daily loss limit pauses
+//! all submissions until [`RiskGuard::resume`] is called.
+//!
+//! Regular and trigger orders are checked against every order limit, with
+//! trigger orders counted as if they fill. Amends carry no side, so they are
+//! only checked against the notional limit and do not move projected
+//! positions. Market addresses are normalized before limits are looked up.
+
+use crate::client::EkidenClient;
+use crate::clock::{self, Clock};
+use crate::error::{EkidenError, Result};
+use crate::types::{ActionPayload, OrderSide, SendIntentParams, SendIntentResponse, WsEvent};
+use crate::utils::format;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tracing::warn;
+
+/// Callback invoked whenever a risk limit is breached
+pub type BreachCallback = Arc<dyn Fn(&RiskBreach) + Send + Sync>;
+
+/// Limits enforced by a [`RiskGuard`]
+#[derive(Debug, Clone, Default)]
+pub struct RiskLimits {
+    /// Maximum absolute position per market, in base units
+    pub max_position: HashMap<String, u64>,
+    /// Maximum absolute position for markets without a specific limit
+    pub default_max_position: Option<u64>,
+    /// Maximum notional (price * size) of a single order, in quote units
+    pub max_order_notional: Option<u128>,
+    /// Maximum loss of account value within a UTC day, in quote units
+    pub max_daily_loss: Option<u64>,
+    /// Maximum number of orders placed within `order_rate_window`
+    pub max_orders_per_window: Option<u32>,
+    /// Window used for the order rate cap
+    pub order_rate_window: Duration,
+}
+
+impl RiskLimits {
+    /// Create limits with nothing enforced
+    pub fn new() -> Self {
+        Self {
+            order_rate_window: Duration::from_secs(1),
+            ..Default::default()
+        }
+    }
+
+    /// Set the maximum absolute position for a market
+    pub fn with_max_position<S: Into<String>>(mut self, market_addr: S, max: u64) -> Self {
+        self.max_position.insert(market_addr.into(), max);
+        self
+    }
+
+    /// Set the maximum absolute position for all other markets
+    pub fn with_default_max_position(mut self, max: u64) -> Self {
+        self.default_max_position = Some(max);
+        self
+    }
+
+    /// Set the maximum notional per order
+    pub fn with_max_order_notional(mut self, max: u128) -> Self {
+        self.max_order_notional = Some(max);
+        self
+    }
+
+    /// Set the maximum daily loss
+    pub fn with_max_daily_loss(mut self, max: u64) -> Self {
+        self.max_daily_loss = Some(max);
+        self
+    }
+
+    /// Set the order rate cap
+    pub fn with_order_rate(mut self, max_orders: u32, window: Duration) -> Self {
+        self.max_orders_per_window = Some(max_orders);
+        self.order_rate_window = window;
+        self
+    }
+
+    /// Get the limit for a market, matching addresses in any case or form
+    fn position_limit(&self, market_addr: &str) -> Option<u64> {
+        let market_addr = market_key(market_addr);
+        self.max_position
+            .iter()
+            .find(|(addr, _)| market_key(addr) == market_addr)
+            .map(|(_, max)| *max)
+            .or(self.default_max_position)
+    }
+}
+
+/// A breached risk limit
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskBreach {
+    /// The order would take the position beyond its limit
+    PositionLimit {
+        market_addr: String,
+        projected: i64,
+        limit: u64,
+    },
+    /// The order notional exceeds the per-order limit
+    OrderNotional {
+        market_addr: String,
+        notional: u128,
+        limit: u128,
+    },
+    /// The account lost more than allowed today; submissions are paused
+    DailyLoss { loss: u64, limit: u64 },
+    /// Too many orders were placed within the rate window
+    OrderRate {
+        count: usize,
+        limit: u32,
+        window: Duration,
+    },
+    /// The order is too large to project onto a signed position
+    OrderSize { market_addr: String, size: u64 },
+}
+
+impl fmt::Display for RiskBreach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskBreach::PositionLimit {
+                market_addr,
+                projected,
+                limit,
+            } => write!(
+                f,
+                "position {} on {} exceeds limit {}",
+                projected, market_addr, limit
+            ),
+            RiskBreach::OrderNotional {
+                market_addr,
+                notional,
+                limit,
+            } => write!(
+                f,
+                "order notional {} on {} exceeds limit {}",
+                notional, market_addr, limit
+            ),
+            RiskBreach::DailyLoss { loss, limit } => {
+                write!(f, "daily loss {} exceeds limit {}", loss, limit)
+            }
+            RiskBreach::OrderRate {
+                count,
+                limit,
+                window,
+            } => write!(
+                f,
+                "{} orders within {:?} exceeds limit {}",
+                count, window, limit
+            ),
+            RiskBreach::OrderSize { market_addr, size } => {
+                write!(f, "order size {} on {} is out of range", size, market_addr)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RiskState {
+    positions: HashMap<String, i64>,
+    order_times: VecDeque<Instant>,
+    day: u64,
+    day_start_value: Option<u64>,
+    paused: Option<RiskBreach>,
+}
+
+/// An order extracted from a `place_order`, `place_trigger_order` or
+/// `amend_order` action
+struct OrderIntent {
+    /// Normalized market address
+    market_addr: String,
+    /// `None` for an amend, which keeps the side of the order it changes
+    side: Option<OrderSide>,
+    /// Limit price, or the trigger price of a trigger order without one
+    price: Option<u64>,
+    size: u64,
+}
+
+impl OrderIntent {
+    fn from_action(action: &ActionPayload) -> Option<Self> {
+        let data = &action.data;
+        let side = match action.action_type.as_str() {
+            "place_order" | "place_trigger_order" => {
+                Some(serde_json::from_value(data.get("side")?.clone()).ok()?)
+            }
+            "amend_order" => None,
+            _ => return None,
+        };
+        Some(Self {
+            market_addr: market_key(data.get("market_addr")?.as_str()?),
+            side,
+            price: data
+                .get("price")
+                .or_else(|| data.get("trigger_price"))
+                .and_then(value_to_u64),
+            size: data.get("size").and_then(value_to_u64)?,
+        })
+    }
+}
+
+/// Key positions and limits by the normalized address, or the lowercased
+/// input if it is not a valid address
+fn market_key(market_addr: &str) -> String {
+    format::normalize_address(market_addr).unwrap_or_else(|_| market_addr.to_ascii_lowercase())
+}
+
+fn value_to_u64(value: &serde_json::Value) -> Option<u64> {
+    match value {
+        serde_json::Value::String(s) => s.parse().ok(),
+        value => value.as_u64(),
+    }
+}
+
+fn utc_day(clock: &dyn Clock) -> u64 {
+    clock
+        .now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or_default()
+}
+
+/// Risk-checking wrapper around the trading API
+#[derive(Clone)]
+pub struct RiskGuard {
+    client: EkidenClient,
+    limits: RiskLimits,
+    state: Arc<Mutex<RiskState>>,
+    callbacks: Vec<BreachCallback>,
+    clock: Arc<dyn Clock>,
+}
+
+impl RiskGuard {
+    /// Create a new risk guard
+    pub fn new(client: EkidenClient, limits: RiskLimits) -> Self {
+        let clock = clock::system();
+        Self {
+            client,
+            limits,
+            state: Arc::new(Mutex::new(RiskState {
+                day: utc_day(clock.as_ref()),
+                ..Default::default()
+            })),
+            callbacks: Vec::new(),
+            clock,
+        }
+    }
+
+    /// Use a custom clock for the daily loss reset and order rate window
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.state.lock().unwrap().day = utc_day(clock.as_ref());
+        self.clock = clock;
+        self
+    }
+
+    /// Register a callback invoked on every breach, e.g. for alerting
+    pub fn on_breach<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&RiskBreach) + Send + Sync + 'static,
+    {
+        self.callbacks.push(Arc::new(callback));
+        self
+    }
+
+    /// Get the configured limits
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    /// Get the wrapped client
+    pub fn client(&self) -> &EkidenClient {
+        &self.client
+    }
+
+    /// Get the breach that paused submissions, if any
+    pub fn paused(&self) -> Option<RiskBreach> {
+        self.state.lock().unwrap().paused.clone()
+    }
+
+    /// Check if submissions are paused
+    pub fn is_paused(&self) -> bool {
+        self.paused().is_some()
+    }
+
+    /// Resume submissions after a pause
+    pub fn resume(&self) {
+        self.state.lock().unwrap().paused = None;
+    }
+
+    /// Get the tracked signed position for a market
+    pub fn position(&self, market_addr: &str) -> i64 {
+        self.state
+            .lock()
+            .unwrap()
+            .positions
+            .get(&market_key(market_addr))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Set the tracked signed position for a market
+    pub fn set_position<S: Into<String>>(&self, market_addr: S, position: i64) {
+        self.state
+            .lock()
+            .unwrap()
+            .positions
+            .insert(market_key(&market_addr.into()), position);
+    }
+
+    /// Update the account value, pausing submissions if the daily loss limit is hit
+    pub fn update_account_value(&self, value: u64) {
+        let breach = {
+            let mut state = self.state.lock().unwrap();
+            let today = utc_day(self.clock.as_ref());
+            if state.day != today || state.day_start_value.is_none() {
+                state.day = today;
+                state.day_start_value = Some(value);
+            }
+
+            let start = state.day_start_value.unwrap_or(value);
+            let loss = start.saturating_sub(value);
+            match self.limits.max_daily_loss {
+                Some(limit) if loss > limit => {
+                    let breach = RiskBreach::DailyLoss { loss, limit };
+                    state.paused = Some(breach.clone());
+                    Some(breach)
+                }
+                _ => None,
+            }
+        };
+
+        if let Some(breach) = breach {
+            self.notify(&breach);
+        }
+    }
+
+    /// Apply a WebSocket event to the tracked positions
+    pub fn apply_event(&self, event: &WsEvent) {
+        if let WsEvent::PositionUpdate { position } = event {
+            self.set_position(position.market_addr.clone(), position.signed_size());
+        }
+    }
+
+    /// Refresh positions and account value from the REST API
+    pub async fn refresh(&self) -> Result<()> {
+        let portfolio = self.client.get_user_portfolio().await?;
+        {
+            let mut state = self.state.lock().unwrap();
+            state.positions = portfolio
+                .positions
+                .iter()
+                .map(|position| (market_key(&position.market_addr), position.signed_size()))
+                .collect();
+        }
+        self.update_account_value(portfolio.summary.total_value);
+        Ok(())
+    }
+
+    /// Validate a batch of actions against the limits without sending them
+    pub fn check_actions(&self, actions: &[ActionPayload]) -> Result<()> {
+        let breach = self.evaluate(actions, false);
+        match breach {
+            Some(breach) => {
+                self.notify(&breach);
+                Err(EkidenError::RiskLimit(breach.to_string()))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Check and send a signed intent
+    pub async fn send_intent(&self, params: SendIntentParams) -> Result<SendIntentResponse> {
+        self.admit(&params.actions)?;
+        self.client.send_intent(params).await
+    }
+
+    /// Check, sign and send a batch of actions as one intent
+    pub async fn send_actions(&self, actions: Vec<ActionPayload>) -> Result<SendIntentResponse> {
+        self.admit(&actions)?;
+        self.client.send_actions(actions).await
+    }
+
+    fn admit(&self, actions: &[ActionPayload]) -> Result<()> {
+        match self.evaluate(actions, true) {
+            Some(breach) => {
+                warn!("Rejected intent: {}", breach);
+                self.notify(&breach);
+                Err(EkidenError::RiskLimit(breach.to_string()))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Evaluate the actions, recording them against the rate cap if `record` is set
+    fn evaluate(&self, actions: &[ActionPayload], record: bool) -> Option<RiskBreach> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(breach) = &state.paused {
+            return Some(breach.clone());
+        }
+
+        let orders: Vec<OrderIntent> = actions
+            .iter()
+            .filter_map(OrderIntent::from_action)
+            .collect();
+        let mut projected = state.positions.clone();
+
+        for order in &orders {
+            if let (Some(limit), Some(price)) = (self.limits.max_order_notional, order.price) {
+                let notional = price as u128 * order.size as u128;
+                if notional > limit {
+                    return Some(RiskBreach::OrderNotional {
+                        market_addr: order.market_addr.clone(),
+                        notional,
+                        limit,
+                    });
+                }
+            }
+
+            let Some(side) = order.side else {
+                continue;
+            };
+            let position = projected.entry(order.market_addr.clone()).or_default();
+            let before = *position;
+            let after = i64::try_from(order.size).ok().and_then(|size| match side {
+                OrderSide::Buy => before.checked_add(size),
+                OrderSide::Sell => before.checked_sub(size),
+            });
+            let Some(after) = after else {
+                return Some(RiskBreach::OrderSize {
+                    market_addr: order.market_addr.clone(),
+                    size: order.size,
+                });
+            };
+            *position = after;
+            // An order that shrinks the position without flipping it is
+            // allowed even over the limit
+            let reduces = position.signum() == before.signum()
+                && position.unsigned_abs() < before.unsigned_abs();
+            if let Some(limit) = self.limits.position_limit(&order.market_addr) {
+                if position.unsigned_abs() > limit && !reduces {
+                    return Some(RiskBreach::PositionLimit {
+                        market_addr: order.market_addr.clone(),
+                        projected: *position,
+                        limit,
+                    });
+                }
+            }
+        }
+
+        if let Some(limit) = self.limits.max_orders_per_window {
+            let now = self.clock.instant();
+            let window = self.limits.order_rate_window;
+            while let Some(front) = state.order_times.front() {
+                if now.duration_since(*front) > window {
+                    state.order_times.pop_front();
+                } else {
+                    break;
+                }
+            }
+
+            // Amends change resting orders rather than placing new ones
+            let placed = orders.iter().filter(|order| order.side.is_some()).count();
+            let count = state.order_times.len() + placed;
+            if count > limit as usize {
+                return Some(RiskBreach::OrderRate {
+                    count,
+                    limit,
+                    window,
+                });
+            }
+            if record {
+                state
+                    .order_times
+                    .extend(std::iter::repeat(now).take(placed));
+            }
+        }
+
+        None
+    }
+
+    fn notify(&self, breach: &RiskBreach) {
+        for callback in &self.callbacks {
+            callback(breach);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::types::{IntentAction, OrderType, TriggerOrder};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn guard(limits: RiskLimits) -> RiskGuard {
+        RiskGuard::new(EkidenClient::default_config().unwrap(), limits)
+    }
+
+    fn buy(size: u64, price: u64) -> ActionPayload {
+        ActionPayload::place_order("0x1", OrderSide::Buy, OrderType::Limit, price, size)
+    }
+
+    #[test]
+    fn test_position_limit() {
+        let guard = guard(RiskLimits::new().with_max_position("0x1", 10));
+        assert!(guard.check_actions(&[buy(10, 1)]).is_ok());
+        assert!(guard.check_actions(&[buy(11, 1)]).is_err());
+
+        guard.set_position("0x1", -5);
+        assert!(guard.check_actions(&[buy(15, 1)]).is_ok());
+
+        // Over the limit, orders that reduce the position still pass
+        guard.set_position("0x1", -20);
+        assert!(guard.check_actions(&[buy(5, 1)]).is_ok());
+        assert!(guard.check_actions(&[buy(20, 1)]).is_ok());
+        assert!(guard.check_actions(&[buy(35, 1)]).is_err());
+    }
+
+    #[test]
+    fn test_limits_match_addresses_in_any_case() {
+        let upper = "0xABCDEF0123456789ABCDEF0123456789ABCDEF01";
+        let guard = guard(RiskLimits::new().with_max_position(upper, 10));
+        let lower = upper.to_ascii_lowercase();
+        let order = ActionPayload::place_order(&lower, OrderSide::Buy, OrderType::Limit, 1, 11);
+        assert!(guard.check_actions(&[order]).is_err());
+
+        guard.set_position(upper, 5);
+        assert_eq!(guard.position(&lower), 5);
+    }
+
+    #[test]
+    fn test_trigger_orders_and_amends_are_checked() {
+        let guard = guard(
+            RiskLimits::new()
+                .with_max_position("0x1", 10)
+                .with_max_order_notional(1_000),
+        );
+        let stop = |size| -> ActionPayload {
+            IntentAction::PlaceTriggerOrder(TriggerOrder::stop_market(
+                "0x1",
+                OrderSide::Sell,
+                size,
+                100,
+            ))
+            .into()
+        };
+        assert!(guard.check_actions(&[stop(10)]).is_ok());
+        // The trigger price stands in for the missing limit price
+        assert!(guard.check_actions(&[stop(11)]).is_err());
+
+        let amend =
+            |price, size| ActionPayload::from(IntentAction::amend_order("0x1", "7", price, size));
+        assert!(guard.check_actions(&[amend(100, 10)]).is_ok());
+        assert!(guard.check_actions(&[amend(101, 10)]).is_err());
+        // Without a side, an amend does not move the projected position
+        assert!(guard.check_actions(&[amend(1, 20), buy(10, 1)]).is_ok());
+    }
+
+    #[test]
+    fn test_oversized_orders_are_rejected() {
+        let guard = guard(RiskLimits::new());
+        assert!(guard.check_actions(&[buy(u64::MAX, 1)]).is_err());
+
+        guard.set_position("0x1", i64::MAX);
+        assert!(guard.check_actions(&[buy(1, 1)]).is_err());
+    }
+
+    #[test]
+    fn test_order_notional_limit() {
+        let guard = guard(RiskLimits::new().with_max_order_notional(1_000));
+        assert!(guard.check_actions(&[buy(10, 100)]).is_ok());
+
+        let err = guard.check_actions(&[buy(10, 101)]).unwrap_err();
+        assert!(matches!(err, EkidenError::RiskLimit(_)));
+    }
+
+    #[test]
+    fn test_order_rate_limit() {
+        let guard = guard(RiskLimits::new().with_order_rate(2, Duration::from_secs(60)));
+        assert!(guard.admit(&[buy(1, 1)]).is_ok());
+        assert!(guard.admit(&[buy(1, 1)]).is_ok());
+        assert!(guard.admit(&[buy(1, 1)]).is_err());
+    }
+
+    #[test]
+    fn test_order_rate_window_slides() {
+        let clock = ManualClock::default();
+        let guard = guard(RiskLimits::new().with_order_rate(2, Duration::from_secs(60)))
+            .with_clock(Arc::new(clock.clone()));
+        assert!(guard.admit(&[buy(1, 1)]).is_ok());
+        clock.advance(Duration::from_secs(30));
+        assert!(guard.admit(&[buy(1, 1)]).is_ok());
+        assert!(guard.admit(&[buy(1, 1)]).is_err());
+
+        clock.advance(Duration::from_secs(31));
+        assert!(guard.admit(&[buy(1, 1)]).is_ok());
+        assert!(guard.admit(&[buy(1, 1)]).is_err());
+    }
+
+    #[test]
+    fn test_daily_loss_resets_at_utc_midnight() {
+        let clock = ManualClock::from_unix_millis(86_400_000 - 1_000);
+        let guard =
+            guard(RiskLimits::new().with_max_daily_loss(100)).with_clock(Arc::new(clock.clone()));
+        guard.update_account_value(1_000);
+        clock.advance(Duration::from_secs(2));
+        guard.update_account_value(850);
+        assert!(!guard.is_paused());
+        guard.update_account_value(700);
+        assert!(guard.is_paused());
+    }
+
+    #[test]
+    fn test_daily_loss_pauses_and_notifies() {
+        let breaches = Arc::new(AtomicUsize::new(0));
+        let counter = breaches.clone();
+        let guard = guard(RiskLimits::new().with_max_daily_loss(100)).on_breach(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        guard.update_account_value(1_000);
+        guard.update_account_value(950);
+        assert!(!guard.is_paused());
+
+        guard.update_account_value(850);
+        assert!(guard.is_paused());
+        assert!(guard.check_actions(&[buy(1, 1)]).is_err());
+        assert_eq!(breaches.load(Ordering::SeqCst), 2);
+
+        guard.resume();
+        assert!(guard.check_actions(&[buy(1, 1)]).is_ok());
+    }
+}
@@ -0,0 +1,486 @@
+//! Local caching proxy for the gateway's REST and WebSocket APIs.
+//!
+//! Enabled with the `server` feature. A single [`EkidenClient`] (and its one
+//! upstream WebSocket connection) is shared by every local process talking to
+//! the proxy. REST calls under `/api/v1/` are forwarded with the client's
+//! credentials, market metadata is cached, and `/ws` speaks the gateway's
+//! subscribe/unsubscribe protocol while multiplexing upstream channels.
+
+use crate::client::EkidenClient;
+use crate::error::{EkidenError, Result};
+use crate::orderbook::Orderbook;
+use crate::types::{RequestConfig, WsEvent, WsRequest, WsResponse};
+use crate::ws::channels;
+use axum::body::Bytes;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{any, get};
+use axum::{Json, Router};
+use futures_util::{SinkExt, StreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Path of the cached market metadata endpoint
+const MARKETS_PATH: &str = "market_info";
+
+/// Configuration for a [`ProxyServer`]
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    /// Local address to listen on
+    pub bind_addr: SocketAddr,
+    /// How long market metadata responses are served from cache
+    pub market_cache_ttl: Duration,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: SocketAddr::from(([127, 0, 0, 1], 3011)),
+            market_cache_ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ProxyConfig {
+    /// Set the local listen address
+    pub fn with_bind_addr(mut self, bind_addr: SocketAddr) -> Self {
+        self.bind_addr = bind_addr;
+        self
+    }
+
+    /// Set the market metadata cache TTL
+    pub fn with_market_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.market_cache_ttl = ttl;
+        self
+    }
+}
+
+/// One upstream channel shared by all local subscribers
+struct Upstream {
+    receiver: broadcast::Receiver<WsEvent>,
+    book: Option<Arc<StdMutex<Orderbook>>>,
+    pump: Option<JoinHandle<()>>,
+    /// Local subscriptions sharing the channel
+    subscribers: usize,
+}
+
+impl Drop for Upstream {
+    fn drop(&mut self) {
+        if let Some(pump) = &self.pump {
+            pump.abort();
+        }
+    }
+}
+
+struct ProxyState {
+    client: EkidenClient,
+    config: ProxyConfig,
+    market_cache: RwLock<HashMap<String, (Instant, serde_json::Value)>>,
+    upstream: Mutex<HashMap<String, Upstream>>,
+}
+
+impl ProxyState {
+    /// Get a local receiver for a channel, subscribing upstream on first use
+    ///
+    /// Orderbook channels also return the book maintained by the proxy, so
+    /// late joiners do not wait for the next upstream snapshot. Each call must
+    /// be paired with a [`release`](Self::release).
+    async fn subscribe(
+        &self,
+        channel: &str,
+    ) -> Result<(
+        broadcast::Receiver<WsEvent>,
+        Option<Arc<StdMutex<Orderbook>>>,
+    )> {
+        let channel = channels::normalize(channel);
+        let mut upstream = self.upstream.lock().await;
+        if !upstream.contains_key(&channel) {
+            let entry = self.open_upstream(&channel).await?;
+            upstream.insert(channel.clone(), entry);
+        }
+        let entry = upstream
+            .get_mut(&channel)
+            .expect("upstream channel was just opened");
+        entry.subscribers += 1;
+        Ok((entry.receiver.resubscribe(), entry.book.clone()))
+    }
+
+    /// Drop a local subscription, closing the upstream channel after the last one
+    async fn release(&self, channel: &str) {
+        let channel = channels::normalize(channel);
+        let mut upstream = self.upstream.lock().await;
+        let Some(entry) = upstream.get_mut(&channel) else {
+            return;
+        };
+        entry.subscribers = entry.subscribers.saturating_sub(1);
+        if entry.subscribers > 0 {
+            return;
+        }
+        if let Some(mut entry) = upstream.remove(&channel) {
+            // Wait for the pump to drop its receiver before counting receivers
+            if let Some(pump) = entry.pump.take() {
+                pump.abort();
+                let _ = pump.await;
+            }
+        }
+        // Leave the channel alone if the client has other receivers on it
+        if self.client.subscriber_count(&channel).await == 0 {
+            if let Err(e) = self.client.unsubscribe(&channel).await {
+                warn!(
+                    "Proxy failed to unsubscribe upstream from {}: {}",
+                    channel, e
+                );
+            }
+        }
+        info!("Proxy released upstream channel {}", channel);
+    }
+
+    async fn open_upstream(&self, channel: &str) -> Result<Upstream> {
+        let receiver = match channel.split_once('/') {
+            Some(("orderbook", market_addr)) => {
+                self.client.subscribe_orderbook(market_addr).await?
+            }
+            Some(("trades", market_addr)) => self.client.subscribe_trades(market_addr).await?,
+            Some(("user", user_addr)) => self.client.subscribe_user(user_addr).await?,
+            _ => {
+                return Err(EkidenError::validation(format!(
+                    "Unknown channel: {}",
+                    channel
+                )))
+            }
+        };
+        info!("Proxy subscribed upstream to {}", channel);
+
+        let mut entry = Upstream {
+            receiver,
+            book: None,
+            pump: None,
+            subscribers: 0,
+        };
+        if let Some(("orderbook", market_addr)) = channel.split_once('/') {
+            let book = Arc::new(StdMutex::new(Orderbook::new(market_addr)));
+            let mut events = entry.receiver.resubscribe();
+            let pump_book = book.clone();
+            entry.pump = Some(tokio::spawn(async move {
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            pump_book
+                                .lock()
+                                .unwrap_or_else(|e| e.into_inner())
+                                .apply_event(&event);
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            }));
+            entry.book = Some(book);
+        }
+        Ok(entry)
+    }
+}
+
+/// Local proxy sharing one authenticated client between many processes
+pub struct ProxyServer {
+    state: Arc<ProxyState>,
+}
+
+impl ProxyServer {
+    /// Create a proxy; connect the client's WebSocket first to serve `/ws`
+    pub fn new(client: EkidenClient, config: ProxyConfig) -> Self {
+        Self {
+            state: Arc::new(ProxyState {
+                client,
+                config,
+                market_cache: RwLock::new(HashMap::new()),
+                upstream: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Build the router, e.g. to mount it inside a larger application
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/ws", get(ws_handler))
+            .route("/api/v1/*path", any(rest_handler))
+            .with_state(self.state.clone())
+    }
+
+    /// Listen on the configured address until the task is cancelled
+    pub async fn serve(self) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(self.state.config.bind_addr).await?;
+        info!("Proxy listening on {}", self.state.config.bind_addr);
+        axum::serve(listener, self.router()).await?;
+        Ok(())
+    }
+}
+
+/// Cache key for a request: the path plus its query sorted by key
+fn cache_key(path: &str, query: &HashMap<String, String>) -> String {
+    let sorted: BTreeMap<_, _> = query.iter().collect();
+    let query = sorted
+        .into_iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{}?{}", path, query)
+}
+
+/// HTTP status to report for an upstream error
+fn error_status(error: &EkidenError) -> StatusCode {
+    match error {
+        EkidenError::Api { status, .. } => {
+            StatusCode::from_u16(*status).unwrap_or(StatusCode::BAD_GATEWAY)
+        }
+        EkidenError::Validation(_) | EkidenError::Json(_) => StatusCode::BAD_REQUEST,
+        EkidenError::Auth(_) => StatusCode::UNAUTHORIZED,
+        EkidenError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        EkidenError::RateLimit { .. } => StatusCode::TOO_MANY_REQUESTS,
+        EkidenError::PositionNotFound { .. } => StatusCode::NOT_FOUND,
+        EkidenError::InsufficientMargin { .. }
+        | EkidenError::OrderRejected { .. }
+        | EkidenError::MarketClosed { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        _ => StatusCode::BAD_GATEWAY,
+    }
+}
+
+fn error_response(error: EkidenError) -> Response {
+    let status = error_status(&error);
+    let retry_after = error.retry_after();
+    let message = match error {
+        EkidenError::Api { message, .. } => message,
+        other => other.to_string(),
+    };
+    let mut response = (status, Json(serde_json::json!({ "error": message }))).into_response();
+    if let Some(delay) = retry_after {
+        let secs = delay.as_secs() + u64::from(delay.subsec_nanos() > 0);
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    }
+    response
+}
+
+async fn rest_handler(
+    State(state): State<Arc<ProxyState>>,
+    method: Method,
+    Path(path): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    body: Bytes,
+) -> Response {
+    let cacheable = method == Method::GET && path == MARKETS_PATH;
+    let key = cache_key(&path, &query);
+    if cacheable {
+        if let Some((fetched, value)) = state.market_cache.read().await.get(&key) {
+            if fetched.elapsed() < state.config.market_cache_ttl {
+                debug!("Serving {} from cache", key);
+                return Json(value.clone()).into_response();
+            }
+        }
+    }
+
+    let mut config = RequestConfig {
+        method: reqwest::Method::from_bytes(method.as_str().as_bytes())
+            .unwrap_or(reqwest::Method::GET),
+        query: (!query.is_empty()).then_some(query),
+        ..Default::default()
+    };
+    if !body.is_empty() {
+        match serde_json::from_slice(&body) {
+            Ok(body) => config.body = Some(body),
+            Err(e) => return error_response(e.into()),
+        }
+    }
+    if state.client.is_authenticated().await {
+        config = config.with_auth();
+    }
+
+    match state.client.request_json(&path, config).await {
+        Ok(value) => {
+            if cacheable {
+                state
+                    .market_cache
+                    .write()
+                    .await
+                    .insert(key, (Instant::now(), value.clone()));
+            }
+            Json(value).into_response()
+        }
+        Err(e) => {
+            warn!("Proxied request to {} failed: {}", path, e);
+            error_response(e)
+        }
+    }
+}
+
+async fn ws_handler(State(state): State<Arc<ProxyState>>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(state, socket))
+}
+
+async fn handle_socket(state: Arc<ProxyState>, socket: WebSocket) {
+    let (mut sink, mut stream) = socket.split();
+    let (out_tx, mut out_rx) = mpsc::channel::<WsResponse>(1024);
+
+    let writer = tokio::spawn(async move {
+        while let Some(response) = out_rx.recv().await {
+            let Ok(text) = serde_json::to_string(&response) else {
+                continue;
+            };
+            if sink.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut forwards: HashMap<String, JoinHandle<()>> = HashMap::new();
+    while let Some(Ok(message)) = stream.next().await {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let response = match serde_json::from_str::<WsRequest>(&text) {
+            Ok(WsRequest::Ping { id }) => WsResponse::Pong { id },
+            Ok(WsRequest::Subscribe { channel, id }) => match state.subscribe(&channel).await {
+                Ok((receiver, book)) => {
+                    if let Some(previous) = forwards.remove(&channel) {
+                        previous.abort();
+                        let _ = previous.await;
+                        state.release(&channel).await;
+                    }
+                    // Ack, then the book as it stands after the ack, then live
+                    // events. The receiver predates the snapshot, so nothing is
+                    // missed; levels are absolute, so repeats are harmless.
+                    let ack = WsResponse::Subscribed {
+                        channel: channel.clone(),
+                        id,
+                    };
+                    if out_tx.send(ack).await.is_err() {
+                        state.release(&channel).await;
+                        break;
+                    }
+                    if let Some(snapshot) = book.as_deref().and_then(book_snapshot) {
+                        let _ = out_tx
+                            .send(WsResponse::Event {
+                                channel: channel.clone(),
+                                seq: None,
+                                data: snapshot,
+                            })
+                            .await;
+                    }
+                    let task = tokio::spawn(forward(channel.clone(), receiver, out_tx.clone()));
+                    forwards.insert(channel, task);
+                    continue;
+                }
+                Err(e) => WsResponse::Error {
+                    message: e.to_string(),
+                    id,
+                },
+            },
+            Ok(WsRequest::Unsubscribe { channel, id }) => {
+                if let Some(task) = forwards.remove(&channel) {
+                    task.abort();
+                    let _ = task.await;
+                    state.release(&channel).await;
+                }
+                WsResponse::Unsubscribed { channel, id }
+            }
+            Err(e) => WsResponse::Error {
+                message: format!("Invalid request: {}", e),
+                id: None,
+            },
+        };
+        if out_tx.send(response).await.is_err() {
+            break;
+        }
+    }
+
+    for (channel, task) in forwards {
+        task.abort();
+        let _ = task.await;
+        state.release(&channel).await;
+    }
+    writer.abort();
+}
+
+/// Snapshot of a book maintained by the proxy, if it has any levels
+fn book_snapshot(book: &StdMutex<Orderbook>) -> Option<WsEvent> {
+    let book = book.lock().unwrap_or_else(|e| e.into_inner());
+    (!book.is_empty()).then(|| WsEvent::OrderbookSnapshot {
+        market_addr: book.market_addr().to_string(),
+        bids: book.bids().collect(),
+        asks: book.asks().collect(),
+        timestamp: book.timestamp(),
+    })
+}
+
+async fn forward(
+    channel: String,
+    mut receiver: broadcast::Receiver<WsEvent>,
+    out: mpsc::Sender<WsResponse>,
+) {
+    loop {
+        match receiver.recv().await {
+            Ok(data) => {
+                let response = WsResponse::Event {
+                    channel: channel.clone(),
+                    seq: None,
+                    data,
+                };
+                if out.send(response).await.is_err() {
+                    break;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                warn!(
+                    "Proxy client lagged on {}, missed {} events",
+                    channel, missed
+                );
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key_sorts_query() {
+        let mut a = HashMap::new();
+        a.insert("symbol".to_string(), "BTC".to_string());
+        a.insert("limit".to_string(), "10".to_string());
+
+        assert_eq!(
+            cache_key("market_info", &a),
+            "market_info?limit=10&symbol=BTC"
+        );
+        assert_eq!(cache_key("market_info", &HashMap::new()), "market_info?");
+    }
+
+    #[test]
+    fn test_error_status() {
+        assert_eq!(
+            error_status(&EkidenError::api(404, "missing".to_string())),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            error_status(&EkidenError::validation("bad")),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            error_status(&EkidenError::network("down")),
+            StatusCode::BAD_GATEWAY
+        );
+    }
+}
@@ -0,0 +1,390 @@
+//! Proptest strategies for the core SDK types.
+//!
+//! Enabled with the `proptest` feature. The strategies produce values shaped
+//! like real gateway data (hex addresses, integer prices and sizes, known
+//! status strings) so downstream crates can property-test their own handling
+//! code. The main types also implement [`Arbitrary`], so `any::<WsEvent>()`
+//! works directly:
+//!
+//! ```rust,ignore
+//! use ekiden_rust_sdk::types::WsEvent;
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn book_never_panics(event in any::<WsEvent>()) {
+//!         my_bot.on_event(&event);
+//!     }
+//! }
+//! ```
+
+use crate::types::{
+    AnnouncementKind, AnnouncementResponse, FillResponse, OrderResponse, OrderSide, OrderType,
+    OrderbookLevel, PositionResponse, VaultResponse, WsEvent, WsResponse,
+};
+use proptest::arbitrary::Arbitrary;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+/// A `0x`-prefixed 20-byte hex address
+pub fn address() -> impl Strategy<Value = String> {
+    "0x[0-9a-f]{40}"
+}
+
+/// A price in quote units
+pub fn price() -> impl Strategy<Value = u64> {
+    1u64..=1_000_000_000_000
+}
+
+/// A size in base units
+pub fn size() -> impl Strategy<Value = u64> {
+    1u64..=1_000_000_000
+}
+
+/// A Unix timestamp in milliseconds
+pub fn timestamp() -> impl Strategy<Value = u64> {
+    1_600_000_000_000u64..=2_000_000_000_000
+}
+
+/// An RFC 3339 date-time string
+pub fn datetime() -> impl Strategy<Value = String> {
+    "20[2-3][0-9]-(0[1-9]|1[0-2])-(0[1-9]|1[0-9]|2[0-8])T([01][0-9]|2[0-3]):[0-5][0-9]:[0-5][0-9]Z"
+}
+
+/// A side string as sent by the gateway
+pub fn side() -> impl Strategy<Value = String> {
+    prop_oneof![Just("buy".to_string()), Just("sell".to_string())]
+}
+
+/// A position side string as sent by the gateway
+pub fn position_side() -> impl Strategy<Value = String> {
+    prop_oneof![Just("long".to_string()), Just("short".to_string())]
+}
+
+/// An order status string as sent by the gateway
+pub fn order_status() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just("open".to_string()),
+        Just("partially_filled".to_string()),
+        Just("filled".to_string()),
+        Just("cancelled".to_string()),
+    ]
+}
+
+/// A typed order side
+pub fn order_side() -> impl Strategy<Value = OrderSide> {
+    prop_oneof![Just(OrderSide::Buy), Just(OrderSide::Sell)]
+}
+
+/// A typed order type
+pub fn order_type() -> impl Strategy<Value = OrderType> {
+    prop_oneof![Just(OrderType::Market), Just(OrderType::Limit)]
+}
+
+/// An order as returned by the orders endpoint
+pub fn order_response() -> impl Strategy<Value = OrderResponse> {
+    (
+        "[0-9a-f]{16}",
+        side(),
+        size(),
+        price(),
+        1u64..=100,
+        prop_oneof![Just("limit".to_string()), Just("market".to_string())],
+        order_status(),
+        address(),
+        address(),
+        any::<u64>(),
+        timestamp(),
+    )
+        .prop_map(
+            |(sid, side, size, price, leverage, order_type, status, user, market, seq, ts)| {
+                OrderResponse {
+                    sid,
+                    side,
+                    size,
+                    price,
+                    leverage,
+                    order_type,
+                    status,
+                    user_addr: user,
+                    market_addr: market,
+                    seq,
+                    timestamp: ts,
+                    client_order_id: None,
+                    time_in_force: None,
+                    post_only: None,
+                    reduce_only: None,
+                }
+            },
+        )
+}
+
+/// A fill as returned by the fills endpoint
+pub fn fill_response() -> impl Strategy<Value = FillResponse> {
+    (
+        "[0-9a-f]{16}",
+        price(),
+        size(),
+        side(),
+        address(),
+        address(),
+        address(),
+        any::<u64>(),
+        timestamp(),
+    )
+        .prop_map(
+            |(sid, price, size, side, taker, maker, market, seq, timestamp)| FillResponse {
+                sid,
+                price,
+                size,
+                side,
+                taker_addr: taker,
+                maker_addr: maker,
+                market_addr: market,
+                seq,
+                timestamp,
+            },
+        )
+}
+
+/// A position as returned by the positions endpoint
+pub fn position_response() -> impl Strategy<Value = PositionResponse> {
+    (
+        (address(), address(), position_side(), size()),
+        (price(), price(), any::<i64>(), price(), 1u64..=100, price()),
+        (datetime(), datetime()),
+    )
+        .prop_map(
+            |(
+                (market_addr, user_addr, side, size),
+                (entry_price, mark_price, unrealized_pnl, margin, leverage, liquidation_price),
+                (created_at, updated_at),
+            )| PositionResponse {
+                market_addr,
+                user_addr,
+                side,
+                size,
+                entry_price,
+                mark_price,
+                unrealized_pnl,
+                margin,
+                leverage,
+                liquidation_price,
+                created_at,
+                updated_at,
+            },
+        )
+}
+
+/// A vault balance
+pub fn vault_response() -> impl Strategy<Value = VaultResponse> {
+    (
+        address(),
+        address(),
+        address(),
+        0u64..=1_000_000_000_000,
+        0u64..=1_000_000_000_000,
+        datetime(),
+        datetime(),
+    )
+        .prop_map(
+            |(vault_addr, user_addr, asset_addr, available, locked, created_at, updated_at)| {
+                VaultResponse {
+                    vault_addr,
+                    user_addr,
+                    asset_addr,
+                    balance: available + locked,
+                    locked_balance: locked,
+                    available_balance: available,
+                    created_at,
+                    updated_at,
+                }
+            },
+        )
+}
+
+/// A known announcement kind
+pub fn announcement_kind() -> impl Strategy<Value = AnnouncementKind> {
+    prop_oneof![
+        Just(AnnouncementKind::System),
+        Just(AnnouncementKind::Listing),
+        Just(AnnouncementKind::Delisting),
+        Just(AnnouncementKind::Maintenance),
+    ]
+}
+
+/// A gateway announcement
+pub fn announcement_response() -> impl Strategy<Value = AnnouncementResponse> {
+    (
+        "[0-9a-f]{16}",
+        announcement_kind(),
+        "[ -~]{1,40}",
+        "[ -~]{0,80}",
+        vec(address(), 0..3),
+        proptest::option::of((timestamp(), 0u64..=86_400_000)),
+        datetime(),
+    )
+        .prop_map(
+            |(id, kind, title, message, market_addrs, window, created_at)| AnnouncementResponse {
+                id,
+                kind,
+                title,
+                message,
+                market_addrs,
+                starts_at: window.map(|(start, _)| start),
+                ends_at: window.map(|(start, length)| start + length),
+                created_at,
+            },
+        )
+}
+
+/// A single orderbook price level
+pub fn orderbook_level() -> impl Strategy<Value = OrderbookLevel> {
+    (price(), size()).prop_map(|(price, size)| OrderbookLevel { price, size })
+}
+
+/// Orderbook levels with distinct prices, in no particular order
+pub fn orderbook_levels() -> impl Strategy<Value = Vec<OrderbookLevel>> {
+    proptest::collection::btree_map(price(), size(), 0..20).prop_map(|levels| {
+        levels
+            .into_iter()
+            .map(|(price, size)| OrderbookLevel { price, size })
+            .collect()
+    })
+}
+
+/// A WebSocket event of any kind
+pub fn ws_event() -> impl Strategy<Value = WsEvent> {
+    prop_oneof![
+        (
+            address(),
+            orderbook_levels(),
+            orderbook_levels(),
+            timestamp()
+        )
+            .prop_map(
+                |(market_addr, bids, asks, timestamp)| WsEvent::OrderbookSnapshot {
+                    market_addr,
+                    bids,
+                    asks,
+                    timestamp,
+                }
+            ),
+        (
+            address(),
+            vec(orderbook_level(), 0..10),
+            vec(orderbook_level(), 0..10),
+            timestamp()
+        )
+            .prop_map(
+                |(market_addr, bids, asks, timestamp)| WsEvent::OrderbookUpdate {
+                    market_addr,
+                    bids,
+                    asks,
+                    timestamp,
+                }
+            ),
+        (address(), price(), size(), side(), timestamp()).prop_map(
+            |(market_addr, price, size, side, timestamp)| WsEvent::Trade {
+                market_addr,
+                price,
+                size,
+                side,
+                timestamp,
+            }
+        ),
+        order_response().prop_map(|order| WsEvent::OrderUpdate { order }),
+        position_response().prop_map(|position| WsEvent::PositionUpdate { position }),
+        vault_response().prop_map(|vault| WsEvent::BalanceUpdate { vault }),
+        announcement_response().prop_map(|announcement| WsEvent::Announcement { announcement }),
+    ]
+}
+
+/// A server-to-client WebSocket message
+pub fn ws_response() -> impl Strategy<Value = WsResponse> {
+    let channel = "[a-z_]{1,12}/0x[0-9a-f]{40}";
+    prop_oneof![
+        proptest::option::of(any::<u64>()).prop_map(|id| WsResponse::Pong { id }),
+        (channel, proptest::option::of(any::<u64>()))
+            .prop_map(|(channel, id)| WsResponse::Subscribed { channel, id }),
+        (channel, proptest::option::of(any::<u64>()))
+            .prop_map(|(channel, id)| WsResponse::Unsubscribed { channel, id }),
+        (channel, proptest::option::of(any::<u64>()), ws_event())
+            .prop_map(|(channel, seq, data)| WsResponse::Event { channel, seq, data }),
+        ("[ -~]{0,40}", proptest::option::of(any::<u64>()))
+            .prop_map(|(message, id)| WsResponse::Error { message, id }),
+    ]
+}
+
+macro_rules! impl_arbitrary {
+    ($($ty:ty => $strategy:ident),* $(,)?) => {
+        $(
+            impl Arbitrary for $ty {
+                type Parameters = ();
+                type Strategy = BoxedStrategy<Self>;
+
+                fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+                    $strategy().boxed()
+                }
+            }
+        )*
+    };
+}
+
+impl_arbitrary! {
+    OrderSide => order_side,
+    OrderType => order_type,
+    OrderResponse => order_response,
+    FillResponse => fill_response,
+    PositionResponse => position_response,
+    VaultResponse => vault_response,
+    AnnouncementKind => announcement_kind,
+    AnnouncementResponse => announcement_response,
+    OrderbookLevel => orderbook_level,
+    WsEvent => ws_event,
+    WsResponse => ws_response,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    fn round_trips<T: Serialize + DeserializeOwned>(value: &T) -> bool {
+        let json = serde_json::to_value(value).unwrap();
+        let decoded: T = serde_json::from_value(json.clone()).unwrap();
+        serde_json::to_value(&decoded).unwrap() == json
+    }
+
+    proptest! {
+        #[test]
+        fn test_orders_round_trip(order in any::<OrderResponse>()) {
+            prop_assert!(round_trips(&order));
+        }
+
+        #[test]
+        fn test_fills_round_trip(fill in any::<FillResponse>()) {
+            prop_assert!(round_trips(&fill));
+        }
+
+        #[test]
+        fn test_positions_round_trip(position in any::<PositionResponse>()) {
+            prop_assert!(round_trips(&position));
+            prop_assert_eq!(position.signed_size().unsigned_abs(), position.size);
+        }
+
+        #[test]
+        fn test_ws_messages_round_trip(response in any::<WsResponse>()) {
+            let text = serde_json::to_string(&response).unwrap();
+            let decoded: WsResponse = serde_json::from_str(&text).unwrap();
+            prop_assert_eq!(serde_json::to_string(&decoded).unwrap(), text);
+        }
+
+        #[test]
+        fn test_typed_enums_round_trip(side in any::<OrderSide>(), kind in any::<OrderType>()) {
+            prop_assert!(round_trips(&side));
+            prop_assert!(round_trips(&kind));
+        }
+    }
+}
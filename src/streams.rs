@@ -0,0 +1,452 @@
+//! Typed subscription streams.
+//!
+//! Channel subscriptions deliver [`WsEvent`]s, most variants of which never
+//! appear on a given channel. The streams here keep only the events that
+//! belong to their channel and map them into channel-specific types:
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::streams::OrderbookEvent;
+//!
+//! let mut book = client
+//!     .subscribe_orderbook_stream("0x1111111111111111111111111111111111111111")
+//!     .await?;
+//! loop {
+//!     match book.recv().await? {
+//!         OrderbookEvent::Snapshot(data) => println!("snapshot with {} bids", data.bids.len()),
+//!         OrderbookEvent::Update(data) => println!("update at {}", data.timestamp),
+//!         OrderbookEvent::Resync { .. } => println!("events lost, snapshot requested"),
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! A [`MultiStream`] merges several subscriptions into one stream of
+//! `(Channel, WsEvent)` pairs, polling the channels in turn so a busy one
+//! cannot starve the others.
+
+use crate::error::{EkidenError, Result};
+use crate::types::{
+    FillResponse, OrderResponse, OrderbookLevel, PositionResponse, VaultResponse, WsEvent,
+};
+use crate::ws::{Channel, EventStream};
+use futures_util::future::select_all;
+use std::marker::PhantomData;
+
+/// An event type that can be taken from a channel's [`WsEvent`]s
+pub trait ChannelEvent: Sized {
+    /// Map an event, or `None` if it does not belong to this channel type
+    fn from_event(event: WsEvent) -> Option<Self>;
+}
+
+/// Bids and asks of an orderbook snapshot or update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderbookData {
+    pub market_addr: String,
+    pub bids: Vec<OrderbookLevel>,
+    pub asks: Vec<OrderbookLevel>,
+    pub timestamp: u64,
+}
+
+/// Event on an orderbook channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderbookEvent {
+    /// The whole book
+    Snapshot(OrderbookData),
+    /// Changed levels; a level with zero size removes the price
+    Update(OrderbookData),
+    /// Events were lost; rebuild the book from the next snapshot
+    Resync { expected: u64, received: u64 },
+}
+
+impl ChannelEvent for OrderbookEvent {
+    fn from_event(event: WsEvent) -> Option<Self> {
+        match event {
+            WsEvent::OrderbookSnapshot {
+                market_addr,
+                bids,
+                asks,
+                timestamp,
+            } => Some(OrderbookEvent::Snapshot(OrderbookData {
+                market_addr,
+                bids,
+                asks,
+                timestamp,
+            })),
+            WsEvent::OrderbookUpdate {
+                market_addr,
+                bids,
+                asks,
+                timestamp,
+            } => Some(OrderbookEvent::Update(OrderbookData {
+                market_addr,
+                bids,
+                asks,
+                timestamp,
+            })),
+            WsEvent::Resync {
+                expected, received, ..
+            } => Some(OrderbookEvent::Resync { expected, received }),
+            _ => None,
+        }
+    }
+}
+
+/// A trade on a trades channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TradeEvent {
+    pub market_addr: String,
+    pub price: u64,
+    pub size: u64,
+    pub side: String,
+    pub timestamp: u64,
+}
+
+impl ChannelEvent for TradeEvent {
+    fn from_event(event: WsEvent) -> Option<Self> {
+        match event {
+            WsEvent::Trade {
+                market_addr,
+                price,
+                size,
+                side,
+                timestamp,
+            } => Some(TradeEvent {
+                market_addr,
+                price,
+                size,
+                side,
+                timestamp,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Event on a user channel
+#[derive(Debug, Clone)]
+pub enum UserEvent {
+    Order(OrderResponse),
+    Position(PositionResponse),
+    Balance(VaultResponse),
+    Fill(FillResponse),
+    /// Events were lost; refetch orders, positions and balances over REST
+    Resync {
+        expected: u64,
+        received: u64,
+    },
+}
+
+impl ChannelEvent for UserEvent {
+    fn from_event(event: WsEvent) -> Option<Self> {
+        match event {
+            WsEvent::OrderUpdate { order } => Some(UserEvent::Order(order)),
+            WsEvent::PositionUpdate { position } => Some(UserEvent::Position(position)),
+            WsEvent::BalanceUpdate { vault } => Some(UserEvent::Balance(vault)),
+            WsEvent::UserFill { fill } => Some(UserEvent::Fill(fill)),
+            WsEvent::Resync {
+                expected, received, ..
+            } => Some(UserEvent::Resync { expected, received }),
+            _ => None,
+        }
+    }
+}
+
+/// Fill event on a user channel
+#[derive(Debug, Clone)]
+pub enum FillEvent {
+    Fill(FillResponse),
+    /// Events were lost; refetch fills over REST
+    Resync {
+        expected: u64,
+        received: u64,
+    },
+}
+
+impl ChannelEvent for FillEvent {
+    fn from_event(event: WsEvent) -> Option<Self> {
+        match event {
+            WsEvent::UserFill { fill } => Some(FillEvent::Fill(fill)),
+            WsEvent::Resync {
+                expected, received, ..
+            } => Some(FillEvent::Resync { expected, received }),
+            _ => None,
+        }
+    }
+}
+
+/// An [`EventStream`] that yields only one channel type's events
+pub struct TypedStream<T> {
+    inner: EventStream,
+    _event: PhantomData<fn() -> T>,
+}
+
+/// Stream of an orderbook channel
+pub type OrderbookStream = TypedStream<OrderbookEvent>;
+/// Stream of a trades channel; resyncs are not reported since trades are not rebuilt
+pub type TradeStream = TypedStream<TradeEvent>;
+/// Stream of a user channel
+pub type UserEventStream = TypedStream<UserEvent>;
+/// Stream of a user channel's fills only
+pub type FillStream = TypedStream<FillEvent>;
+
+impl<T: ChannelEvent> TypedStream<T> {
+    pub fn new(inner: EventStream) -> Self {
+        Self {
+            inner,
+            _event: PhantomData,
+        }
+    }
+
+    /// Get the channel name
+    pub fn channel(&self) -> &str {
+        self.inner.channel()
+    }
+
+    /// Total number of events dropped because this stream lagged
+    pub fn missed(&self) -> u64 {
+        self.inner.missed()
+    }
+
+    /// Receive the next event of this channel type
+    ///
+    /// Lags are reported like [`EventStream::recv`].
+    pub async fn recv(&mut self) -> Result<T> {
+        loop {
+            if let Some(event) = T::from_event(self.inner.recv().await?) {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Try to receive an event of this channel type without blocking
+    pub fn try_recv(&mut self) -> Result<T> {
+        loop {
+            if let Some(event) = T::from_event(self.inner.try_recv()?) {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Get the underlying untyped stream
+    pub fn into_inner(self) -> EventStream {
+        self.inner
+    }
+}
+
+impl<T> std::fmt::Debug for TypedStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedStream")
+            .field("channel", &self.inner.channel())
+            .field("missed", &self.inner.missed())
+            .finish()
+    }
+}
+
+/// Events of several channels merged into one stream
+///
+/// Channels are polled round-robin, starting after the one that produced the
+/// last event. A channel whose subscription closes is dropped from the set.
+pub struct MultiStream {
+    streams: Vec<(Channel, EventStream)>,
+    next: usize,
+}
+
+impl MultiStream {
+    pub fn new(streams: Vec<(Channel, EventStream)>) -> Self {
+        Self { streams, next: 0 }
+    }
+
+    /// Get the channels still open
+    pub fn channels(&self) -> impl Iterator<Item = &Channel> {
+        self.streams.iter().map(|(channel, _)| channel)
+    }
+
+    /// Get the number of channels still open
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// Check if every channel has closed
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Receive the next event from any channel
+    ///
+    /// Lags are reported like [`EventStream::recv`]. Returns
+    /// [`EkidenError::ConnectionClosed`] once every channel has closed.
+    pub async fn recv(&mut self) -> Result<(Channel, WsEvent)> {
+        loop {
+            if self.streams.is_empty() {
+                return Err(EkidenError::ConnectionClosed);
+            }
+            let len = self.streams.len();
+            let start = self.next % len;
+            let (result, index) = {
+                let (head, tail) = self.streams.split_at_mut(start);
+                let receivers = tail
+                    .iter_mut()
+                    .chain(head.iter_mut())
+                    .map(|(_, stream)| Box::pin(stream.recv()));
+                let (result, index, _) = select_all(receivers).await;
+                (result, (start + index) % len)
+            };
+            self.next = index + 1;
+            match result {
+                Ok(event) => return Ok((self.streams[index].0.clone(), event)),
+                Err(EkidenError::ConnectionClosed) => {
+                    self.streams.remove(index);
+                    self.next = index;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for MultiStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiStream")
+            .field("channels", &self.channels().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::channels;
+    use tokio::sync::broadcast;
+
+    fn trade(timestamp: u64) -> WsEvent {
+        WsEvent::Trade {
+            market_addr: MARKET.to_string(),
+            price: 100,
+            size: 1,
+            side: "buy".to_string(),
+            timestamp,
+        }
+    }
+
+    const MARKET: &str = "0x1111111111111111111111111111111111111111";
+
+    #[tokio::test]
+    async fn test_streams_keep_their_channel_events() {
+        let (tx, rx) = broadcast::channel(16);
+        let mut trades = TradeStream::new(EventStream::new(rx, channels::trades(MARKET)));
+        let mut book = OrderbookStream::new(EventStream::new(
+            tx.subscribe(),
+            channels::orderbook(MARKET),
+        ));
+
+        tx.send(WsEvent::OrderbookSnapshot {
+            market_addr: MARKET.to_string(),
+            bids: vec![OrderbookLevel {
+                price: 100,
+                size: 1,
+            }],
+            asks: vec![],
+            timestamp: 1,
+        })
+        .unwrap();
+        tx.send(WsEvent::Trade {
+            market_addr: MARKET.to_string(),
+            price: 100,
+            size: 2,
+            side: "buy".to_string(),
+            timestamp: 2,
+        })
+        .unwrap();
+
+        let trade = trades.recv().await.unwrap();
+        assert_eq!(trade.size, 2);
+        assert!(trades.try_recv().is_err());
+
+        match book.recv().await.unwrap() {
+            OrderbookEvent::Snapshot(data) => assert_eq!(data.bids[0].price, 100),
+            other => panic!("expected snapshot, got {:?}", other),
+        }
+        assert!(book.try_recv().is_err());
+        assert_eq!(book.channel(), channels::orderbook(MARKET));
+    }
+
+    #[tokio::test]
+    async fn test_fill_stream_skips_other_user_events() {
+        let (tx, rx) = broadcast::channel(16);
+        let mut fills = FillStream::new(EventStream::new(rx, channels::user(MARKET)));
+
+        tx.send(WsEvent::BalanceUpdate {
+            vault: VaultResponse {
+                vault_addr: MARKET.to_string(),
+                user_addr: MARKET.to_string(),
+                asset_addr: MARKET.to_string(),
+                balance: 10,
+                locked_balance: 0,
+                available_balance: 10,
+                created_at: String::new(),
+                updated_at: String::new(),
+            },
+        })
+        .unwrap();
+        tx.send(WsEvent::UserFill {
+            fill: FillResponse {
+                sid: "1a30".to_string(),
+                price: 100,
+                size: 2,
+                side: "buy".to_string(),
+                taker_addr: MARKET.to_string(),
+                maker_addr: MARKET.to_string(),
+                market_addr: MARKET.to_string(),
+                seq: 7,
+                timestamp: 3,
+            },
+        })
+        .unwrap();
+
+        match fills.recv().await.unwrap() {
+            FillEvent::Fill(fill) => assert_eq!(fill.seq, 7),
+            other => panic!("expected fill, got {:?}", other),
+        }
+        assert!(fills.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_multi_stream_polls_channels_in_turn() {
+        let (trades_tx, trades_rx) = broadcast::channel(16);
+        let (user_tx, user_rx) = broadcast::channel(16);
+        let trades = Channel::Trades(MARKET.to_string());
+        let user = Channel::User(MARKET.to_string());
+        let mut merged = MultiStream::new(vec![
+            (trades.clone(), EventStream::new(trades_rx, trades.name())),
+            (user.clone(), EventStream::new(user_rx, user.name())),
+        ]);
+
+        for timestamp in 0..3 {
+            trades_tx.send(trade(timestamp)).unwrap();
+            user_tx.send(trade(timestamp)).unwrap();
+        }
+        let mut order = Vec::new();
+        for _ in 0..4 {
+            order.push(merged.recv().await.unwrap().0);
+        }
+        assert_eq!(
+            order,
+            vec![trades.clone(), user.clone(), trades.clone(), user.clone()]
+        );
+
+        // A closed channel is dropped once drained, the rest keep flowing
+        drop(trades_tx);
+        assert_eq!(merged.recv().await.unwrap().0, trades);
+        assert_eq!(merged.recv().await.unwrap().0, user);
+        user_tx.send(trade(3)).unwrap();
+        assert_eq!(merged.recv().await.unwrap().0, user);
+        assert_eq!(merged.len(), 1);
+        drop(user_tx);
+        assert!(matches!(
+            merged.recv().await,
+            Err(EkidenError::ConnectionClosed)
+        ));
+        assert!(merged.is_empty());
+    }
+}
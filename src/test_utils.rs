@@ -0,0 +1,30 @@
+//! Fixtures shared by unit tests.
+
+use crate::types::MarketResponse;
+
+/// Build a `BTC-USD` market at `0x1` with 8 base and 6 quote decimals
+///
+/// Tests override the fields they depend on with struct update syntax.
+pub(crate) fn market() -> MarketResponse {
+    MarketResponse {
+        addr: "0x1".to_string(),
+        symbol: "BTC-USD".to_string(),
+        base_addr: "0x2".to_string(),
+        base_decimals: 8,
+        quote_addr: "0x3".to_string(),
+        quote_decimals: 6,
+        min_order_size: 1,
+        max_leverage: 20,
+        initial_margin_ratio: 0.05,
+        maintenance_margin_ratio: 0.03,
+        mark_price: 0,
+        oracle_price: 0,
+        open_interest: 0,
+        funding_index: 0,
+        funding_epoch: 0,
+        root: String::new(),
+        epoch: 0,
+        created_at: String::new(),
+        updated_at: String::new(),
+    }
+}
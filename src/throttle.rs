@@ -0,0 +1,616 @@
+//! Rate-limited submission queue for intents.
+//!
+//! [`OrderThrottle`] sits in front of [`EkidenClient::send_actions`] and keeps
+//! the number of actions sent within a window under global and per-market
+//! limits. Submissions that would exceed a limit wait in a queue, where
+//! cancel-only submissions go first. A queued cancel/replace for a market is
+//! superseded by a newer one for the same market: its cancels move into the
+//! newer submission and its placements are dropped.
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::throttle::{OrderThrottle, QueueOutcome, ThrottleConfig};
+//! use ekiden_rust_sdk::{ActionPayload, OrderSide, OrderType};
+//! use std::time::Duration;
+//!
+//! let config = ThrottleConfig::new()
+//!     .with_global_limit(20, Duration::from_secs(1))
+//!     .with_default_market_limit(5, Duration::from_secs(1));
+//! let queue = OrderThrottle::new(client, config).start();
+//!
+//! let market = "0x1111111111111111111111111111111111111111";
+//! let actions = vec![
+//!     ActionPayload::cancel_order(market, "sid-1"),
+//!     ActionPayload::place_order(market, OrderSide::Buy, OrderType::Limit, 64_000, 10),
+//! ];
+//! match queue.submit(actions).await? {
+//!     QueueOutcome::Sent(response) => println!("Sent as intent {}", response.seq),
+//!     QueueOutcome::Superseded => println!("Replaced by a newer requote"),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Each submission is sent as one intent, so its actions stay atomic. When the
+//! gateway answers with a rate limit that carries a retry delay, the queue
+//! holds everything back for that long.
+
+use crate::client::EkidenClient;
+use crate::clock::{self, Clock};
+use crate::error::{EkidenError, Result};
+use crate::types::{ActionPayload, SendIntentResponse};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Notify};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Default number of submissions that may wait in the queue
+pub const DEFAULT_MAX_QUEUE: usize = 1_000;
+
+/// Maximum number of actions within a sliding window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionRate {
+    pub max_actions: u32,
+    pub window: Duration,
+}
+
+/// Limits enforced by an [`OrderThrottle`]
+#[derive(Debug, Clone)]
+pub struct ThrottleConfig {
+    /// Limit across all markets
+    pub global: Option<ActionRate>,
+    /// Limits for specific markets
+    pub markets: HashMap<String, ActionRate>,
+    /// Limit for markets without a specific one
+    pub default_market: Option<ActionRate>,
+    /// Maximum number of waiting submissions
+    pub max_queue: usize,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThrottleConfig {
+    /// Create a configuration with no limits
+    pub fn new() -> Self {
+        Self {
+            global: None,
+            markets: HashMap::new(),
+            default_market: None,
+            max_queue: DEFAULT_MAX_QUEUE,
+        }
+    }
+
+    /// Limit the actions sent across all markets
+    pub fn with_global_limit(mut self, max_actions: u32, window: Duration) -> Self {
+        self.global = Some(ActionRate {
+            max_actions,
+            window,
+        });
+        self
+    }
+
+    /// Limit the actions sent for one market
+    pub fn with_market_limit<S: Into<String>>(
+        mut self,
+        market_addr: S,
+        max_actions: u32,
+        window: Duration,
+    ) -> Self {
+        self.markets.insert(
+            market_addr.into().to_lowercase(),
+            ActionRate {
+                max_actions,
+                window,
+            },
+        );
+        self
+    }
+
+    /// Limit the actions sent for every market without a specific limit
+    pub fn with_default_market_limit(mut self, max_actions: u32, window: Duration) -> Self {
+        self.default_market = Some(ActionRate {
+            max_actions,
+            window,
+        });
+        self
+    }
+
+    /// Set the maximum number of waiting submissions
+    pub fn with_max_queue(mut self, max_queue: usize) -> Self {
+        self.max_queue = max_queue;
+        self
+    }
+
+    fn market_rate(&self, market_addr: &str) -> Option<ActionRate> {
+        self.markets
+            .get(market_addr)
+            .copied()
+            .or(self.default_market)
+    }
+}
+
+/// How a queued submission ended
+#[derive(Debug, Clone)]
+pub enum QueueOutcome {
+    /// The submission was sent as one intent
+    Sent(SendIntentResponse),
+    /// A newer cancel/replace for the same market took its place
+    Superseded,
+}
+
+/// Sliding window of send times, one entry per action
+#[derive(Debug)]
+struct Window {
+    rate: ActionRate,
+    sent: VecDeque<Instant>,
+}
+
+impl Window {
+    fn new(rate: ActionRate) -> Self {
+        Self {
+            rate,
+            sent: VecDeque::new(),
+        }
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some(front) = self.sent.front() {
+            if now.saturating_duration_since(*front) >= self.rate.window {
+                self.sent.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Whether `count` more actions fit; an empty window admits any batch
+    fn fits(&self, count: usize) -> bool {
+        self.sent.is_empty() || self.sent.len() + count <= self.rate.max_actions as usize
+    }
+
+    fn record(&mut self, now: Instant, count: usize) {
+        self.sent.extend(std::iter::repeat_n(now, count));
+    }
+
+    fn frees_at(&self) -> Option<Instant> {
+        self.sent.front().map(|sent| *sent + self.rate.window)
+    }
+}
+
+fn market_of(action: &ActionPayload) -> Option<String> {
+    action
+        .data
+        .get("market_addr")?
+        .as_str()
+        .map(str::to_lowercase)
+}
+
+fn is_cancel(action: &ActionPayload) -> bool {
+    action.action_type == "cancel_order"
+}
+
+fn is_place(action: &ActionPayload) -> bool {
+    action.action_type == "place_order"
+}
+
+/// Drop repeated cancels of the same order, keeping the first
+fn dedupe_cancels(actions: Vec<ActionPayload>) -> Vec<ActionPayload> {
+    let mut seen = HashSet::new();
+    actions
+        .into_iter()
+        .filter(|action| {
+            !is_cancel(action)
+                || match action.data.get("sid").and_then(|sid| sid.as_str()) {
+                    Some(sid) => seen.insert(sid.to_string()),
+                    None => true,
+                }
+        })
+        .collect()
+}
+
+struct Pending {
+    actions: Vec<ActionPayload>,
+    /// Actions per market
+    markets: HashMap<String, usize>,
+    cancel_only: bool,
+    /// Market this submission requotes, if it only cancels and places there
+    replaces: Option<String>,
+    reply: oneshot::Sender<Result<QueueOutcome>>,
+}
+
+impl Pending {
+    fn new(actions: Vec<ActionPayload>, reply: oneshot::Sender<Result<QueueOutcome>>) -> Self {
+        let actions = dedupe_cancels(actions);
+        let mut markets = HashMap::new();
+        for market in actions.iter().filter_map(market_of) {
+            *markets.entry(market).or_default() += 1;
+        }
+        let cancel_only = actions.iter().all(is_cancel);
+        let replaces = match markets.keys().next() {
+            Some(market)
+                if markets.len() == 1
+                    && actions.iter().any(is_place)
+                    && actions.iter().all(|a| is_cancel(a) || is_place(a))
+                    && actions.iter().all(|a| market_of(a).is_some()) =>
+            {
+                Some(market.clone())
+            }
+            _ => None,
+        };
+        Self {
+            actions,
+            markets,
+            cancel_only,
+            replaces,
+            reply,
+        }
+    }
+
+    fn shares_market(&self, other: &Pending) -> bool {
+        self.markets.keys().any(|m| other.markets.contains_key(m))
+    }
+}
+
+enum Next {
+    Send(Pending),
+    Wait(Instant),
+    Idle,
+}
+
+struct QueueState {
+    config: ThrottleConfig,
+    queue: VecDeque<Pending>,
+    global: Option<Window>,
+    markets: HashMap<String, Window>,
+    paused_until: Option<Instant>,
+}
+
+impl QueueState {
+    fn new(config: ThrottleConfig) -> Self {
+        Self {
+            global: config.global.map(Window::new),
+            config,
+            queue: VecDeque::new(),
+            markets: HashMap::new(),
+            paused_until: None,
+        }
+    }
+
+    fn enqueue(&mut self, mut pending: Pending) -> Result<()> {
+        if let Some(market) = pending.replaces.clone() {
+            let mut actions = Vec::new();
+            let mut index = 0;
+            while index < self.queue.len() {
+                if self.queue[index].replaces.as_ref() != Some(&market) {
+                    index += 1;
+                    continue;
+                }
+                if let Some(old) = self.queue.remove(index) {
+                    actions.extend(old.actions.into_iter().filter(is_cancel));
+                    let _ = old.reply.send(Ok(QueueOutcome::Superseded));
+                }
+            }
+            if !actions.is_empty() {
+                debug!("Coalesced queued requotes on {}", market);
+                actions.append(&mut pending.actions);
+                pending = Pending::new(actions, pending.reply);
+            }
+        }
+
+        if self.queue.len() >= self.config.max_queue {
+            return Err(EkidenError::general("Submission queue is full"));
+        }
+        self.queue.push_back(pending);
+        Ok(())
+    }
+
+    fn fits(&self, index: usize) -> bool {
+        let pending = &self.queue[index];
+        if let Some(global) = &self.global {
+            if !global.fits(pending.actions.len()) {
+                return false;
+            }
+        }
+        pending
+            .markets
+            .iter()
+            .all(|(market, count)| match self.markets.get(market) {
+                Some(window) => window.fits(*count),
+                None => true,
+            })
+    }
+
+    fn record(&mut self, pending: &Pending, now: Instant) {
+        if let Some(global) = &mut self.global {
+            global.record(now, pending.actions.len());
+        }
+        for (market, count) in &pending.markets {
+            if let Some(rate) = self.config.market_rate(market) {
+                self.markets
+                    .entry(market.clone())
+                    .or_insert_with(|| Window::new(rate))
+                    .record(now, *count);
+            }
+        }
+    }
+
+    /// Pick the next submission allowed at `now`, cancels first
+    fn next(&mut self, now: Instant) -> Next {
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return Next::Wait(until);
+            }
+            self.paused_until = None;
+        }
+        if self.queue.is_empty() {
+            return Next::Idle;
+        }
+
+        if let Some(global) = &mut self.global {
+            global.prune(now);
+        }
+        for window in self.markets.values_mut() {
+            window.prune(now);
+        }
+
+        let (cancels, others): (Vec<usize>, Vec<usize>) =
+            (0..self.queue.len()).partition(|&index| self.queue[index].cancel_only);
+        for index in cancels.into_iter().chain(others) {
+            // Placements keep their order within a market
+            let pending = &self.queue[index];
+            let blocked = !pending.cancel_only
+                && self
+                    .queue
+                    .iter()
+                    .take(index)
+                    .any(|earlier| earlier.shares_market(pending));
+            if blocked || !self.fits(index) {
+                continue;
+            }
+            if let Some(pending) = self.queue.remove(index) {
+                self.record(&pending, now);
+                return Next::Send(pending);
+            }
+        }
+
+        let frees_at = self
+            .global
+            .iter()
+            .chain(self.markets.values())
+            .filter_map(Window::frees_at)
+            .min();
+        Next::Wait(frees_at.unwrap_or(now))
+    }
+}
+
+/// Rate limiter and queue in front of the intent API
+#[derive(Debug, Clone)]
+pub struct OrderThrottle {
+    client: EkidenClient,
+    config: ThrottleConfig,
+    clock: Arc<dyn Clock>,
+}
+
+impl OrderThrottle {
+    /// Create a throttle for a client's submissions
+    pub fn new(client: EkidenClient, config: ThrottleConfig) -> Self {
+        Self {
+            client,
+            config,
+            clock: clock::system(),
+        }
+    }
+
+    /// Use a custom clock for the rate windows
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Start sending queued submissions in the background
+    pub fn start(self) -> SubmissionQueue {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(QueueState::new(self.config)),
+            wake: Notify::new(),
+        });
+        let client = self.client.clone();
+        let dispatcher = Dispatcher {
+            client: self.client,
+            clock: self.clock,
+            shared: shared.clone(),
+        };
+        SubmissionQueue {
+            shared,
+            task: client.spawn_job(dispatcher.run()),
+        }
+    }
+}
+
+struct Shared {
+    state: Mutex<QueueState>,
+    wake: Notify,
+}
+
+/// Handle for submitting through a running [`OrderThrottle`]; stops it when dropped
+pub struct SubmissionQueue {
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+impl std::fmt::Debug for SubmissionQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubmissionQueue")
+            .field("pending", &self.pending())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SubmissionQueue {
+    /// Queue actions to be signed and sent as one intent, and wait for the outcome
+    pub async fn submit(&self, actions: Vec<ActionPayload>) -> Result<QueueOutcome> {
+        if actions.is_empty() {
+            return Err(EkidenError::validation("No actions to submit"));
+        }
+
+        let (reply, outcome) = oneshot::channel();
+        self.shared
+            .state
+            .lock()
+            .unwrap()
+            .enqueue(Pending::new(actions, reply))?;
+        self.shared.wake.notify_one();
+
+        outcome
+            .await
+            .map_err(|_| EkidenError::general("Submission queue stopped"))?
+    }
+
+    /// Number of submissions waiting to be sent
+    pub fn pending(&self) -> usize {
+        self.shared.state.lock().unwrap().queue.len()
+    }
+}
+
+impl Drop for SubmissionQueue {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Dispatcher {
+    client: EkidenClient,
+    clock: Arc<dyn Clock>,
+    shared: Arc<Shared>,
+}
+
+impl Dispatcher {
+    async fn run(self) {
+        loop {
+            let now = self.clock.instant();
+            let next = self.shared.state.lock().unwrap().next(now);
+            match next {
+                Next::Send(pending) => self.send(pending).await,
+                Next::Wait(until) => {
+                    let delay = until.saturating_duration_since(now);
+                    tokio::select! {
+                        _ = self.clock.sleep(delay) => {}
+                        _ = self.shared.wake.notified() => {}
+                    }
+                }
+                Next::Idle => self.shared.wake.notified().await,
+            }
+        }
+    }
+
+    async fn send(&self, pending: Pending) {
+        let result = self.client.send_actions(pending.actions).await;
+        if let Err(e) = &result {
+            if let Some(retry_after) = e.retry_after() {
+                warn!(
+                    "Gateway rate limit hit, holding submissions for {:?}",
+                    retry_after
+                );
+                self.shared.state.lock().unwrap().paused_until =
+                    Some(self.clock.instant() + retry_after);
+            }
+        }
+        let _ = pending.reply.send(result.map(QueueOutcome::Sent));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OrderSide, OrderType};
+
+    const A: &str = "0x1111111111111111111111111111111111111111";
+    const B: &str = "0x2222222222222222222222222222222222222222";
+
+    fn place(market: &str) -> ActionPayload {
+        ActionPayload::place_order(market, OrderSide::Buy, OrderType::Limit, 100, 1)
+    }
+
+    fn cancel(market: &str, sid: &str) -> ActionPayload {
+        ActionPayload::cancel_order(market, sid)
+    }
+
+    fn enqueue(
+        state: &mut QueueState,
+        actions: Vec<ActionPayload>,
+    ) -> oneshot::Receiver<Result<QueueOutcome>> {
+        let (reply, outcome) = oneshot::channel();
+        state.enqueue(Pending::new(actions, reply)).unwrap();
+        outcome
+    }
+
+    fn sent(next: Next) -> Vec<ActionPayload> {
+        match next {
+            Next::Send(pending) => pending.actions,
+            _ => panic!("expected a submission to be sent"),
+        }
+    }
+
+    #[test]
+    fn test_global_limit_defers_submissions() {
+        let now = Instant::now();
+        let config = ThrottleConfig::new().with_global_limit(2, Duration::from_secs(1));
+        let mut state = QueueState::new(config);
+        for _ in 0..3 {
+            enqueue(&mut state, vec![place(A)]);
+        }
+
+        sent(state.next(now));
+        sent(state.next(now));
+        assert!(matches!(state.next(now), Next::Wait(at) if at == now + Duration::from_secs(1)));
+        sent(state.next(now + Duration::from_secs(1)));
+        assert!(matches!(state.next(now), Next::Idle));
+    }
+
+    #[test]
+    fn test_cancels_jump_the_queue() {
+        let now = Instant::now();
+        let config = ThrottleConfig::new().with_global_limit(1, Duration::from_secs(1));
+        let mut state = QueueState::new(config);
+        enqueue(&mut state, vec![place(A)]);
+        enqueue(&mut state, vec![place(B)]);
+        enqueue(&mut state, vec![cancel(B, "s1")]);
+
+        assert!(is_cancel(&sent(state.next(now))[0]));
+        let later = now + Duration::from_secs(1);
+        assert_eq!(market_of(&sent(state.next(later))[0]).as_deref(), Some(A));
+    }
+
+    #[test]
+    fn test_market_limit_keeps_order_within_market() {
+        let now = Instant::now();
+        let config = ThrottleConfig::new().with_market_limit(A, 1, Duration::from_secs(1));
+        let mut state = QueueState::new(config);
+        enqueue(&mut state, vec![place(A)]);
+        enqueue(&mut state, vec![place(A), place(A)]);
+        enqueue(&mut state, vec![place(B)]);
+
+        assert_eq!(sent(state.next(now)).len(), 1);
+        assert_eq!(market_of(&sent(state.next(now))[0]).as_deref(), Some(B));
+        assert!(matches!(state.next(now), Next::Wait(_)));
+    }
+
+    #[test]
+    fn test_requotes_coalesce() {
+        let mut state = QueueState::new(ThrottleConfig::new());
+        let mut first = enqueue(&mut state, vec![cancel(A, "s1"), place(A)]);
+        enqueue(&mut state, vec![cancel(A, "s1"), cancel(A, "s2"), place(A)]);
+        enqueue(&mut state, vec![cancel(B, "s3"), place(B)]);
+
+        assert!(matches!(first.try_recv(), Ok(Ok(QueueOutcome::Superseded))));
+        assert_eq!(state.queue.len(), 2);
+        let actions = &state.queue[0].actions;
+        assert_eq!(actions.len(), 3);
+        assert_eq!(actions.iter().filter(|a| is_cancel(a)).count(), 2);
+    }
+}
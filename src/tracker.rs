@@ -0,0 +1,499 @@
+//! Lifecycle of the authenticated user's orders.
+//!
+//! [`OrderTracker`] follows the user channel's order updates and fills, and
+//! reconciles with the REST API every
+//! [`OrderTrackerConfig::reconcile_interval`] and after lost events, so its
+//! view of open orders stays correct even when updates are missed:
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::tracker::{OrderEvent, OrderTracker, OrderTrackerConfig};
+//! use ekiden_rust_sdk::{OrderOptions, OrderSide};
+//!
+//! let market = "0x1111111111111111111111111111111111111111";
+//! let tracker = OrderTracker::start(client.clone(), OrderTrackerConfig::default()).await?;
+//! tracker.on_event(|event| {
+//!     if let OrderEvent::PartiallyFilled(order) = event {
+//!         println!("{} has {} left", order.sid, order.size);
+//!     }
+//! });
+//!
+//! let order = client
+//!     .place_limit_order(market, OrderSide::Buy, 63_000_000_000, 100_000, OrderOptions::new())
+//!     .await?;
+//! let filled = tracker.wait_filled(&order.sid).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Reconciliation fetches the user's orders on the markets of tracked orders
+//! and on [`OrderTrackerConfig::with_market`] and [`OrderTracker::track_market`]
+//! markets. Orders closed before the tracker saw them are skipped, unless
+//! they are being waited for. An order that was open but is no longer listed
+//! is reported closed with the status `"unknown"`, since whether it filled
+//! cannot be told.
+
+use crate::client::EkidenClient;
+use crate::error::{EkidenError, Result};
+use crate::streams::{UserEvent, UserEventStream};
+use crate::types::{FillResponse, ListOrdersParams, OrderResponse, OrderState, Pagination};
+use crate::utils::format;
+use futures_util::StreamExt;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Default interval between REST reconciliations
+pub const DEFAULT_RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Status given to open orders that reconciliation no longer finds
+const MISSING_STATUS: &str = "unknown";
+
+/// Configuration for an [`OrderTracker`]
+#[derive(Debug, Clone)]
+pub struct OrderTrackerConfig {
+    /// Interval between REST reconciliations
+    pub reconcile_interval: Duration,
+    /// Markets reconciled even without tracked orders
+    pub markets: Vec<String>,
+}
+
+impl Default for OrderTrackerConfig {
+    fn default() -> Self {
+        Self {
+            reconcile_interval: DEFAULT_RECONCILE_INTERVAL,
+            markets: Vec::new(),
+        }
+    }
+}
+
+impl OrderTrackerConfig {
+    /// Set the interval between REST reconciliations
+    pub fn with_reconcile_interval(mut self, interval: Duration) -> Self {
+        self.reconcile_interval = interval;
+        self
+    }
+
+    /// Load and reconcile a market's orders from the start
+    pub fn with_market(mut self, market_addr: &str) -> Self {
+        self.markets.push(market_addr.to_string());
+        self
+    }
+}
+
+/// Change in the state of one of the user's orders
+#[derive(Debug, Clone)]
+pub enum OrderEvent {
+    /// An order was first seen open
+    Opened(OrderResponse),
+    /// An order filled in part and is still open
+    PartiallyFilled(OrderResponse),
+    Filled(OrderResponse),
+    Cancelled(OrderResponse),
+    /// An order closed for another reason, such as rejection or expiry
+    Closed(OrderResponse),
+    /// A fill on the user channel
+    Fill(FillResponse),
+}
+
+impl OrderEvent {
+    /// Classify an order that is new or whose status changed
+    fn from_order(order: OrderResponse) -> Self {
+        match order.state() {
+            OrderState::Open => OrderEvent::Opened(order),
+            OrderState::PartiallyFilled => OrderEvent::PartiallyFilled(order),
+            OrderState::Filled => OrderEvent::Filled(order),
+            OrderState::Cancelled => OrderEvent::Cancelled(order),
+            OrderState::Closed => OrderEvent::Closed(order),
+        }
+    }
+}
+
+/// Callback invoked with every [`OrderEvent`]
+pub type OrderCallback = Arc<dyn Fn(&OrderEvent) + Send + Sync>;
+
+struct Shared {
+    /// Latest state of every order seen, open or closed, by sid
+    orders: Mutex<HashMap<String, OrderResponse>>,
+    callbacks: Mutex<Vec<OrderCallback>>,
+    /// Bumped whenever an order changes
+    changes: watch::Sender<u64>,
+    /// Markets reconciled even without tracked orders
+    markets: Mutex<BTreeSet<String>>,
+    /// Number of waiters of each sid
+    awaited: Mutex<HashMap<String, usize>>,
+}
+
+impl Shared {
+    fn new(markets: BTreeSet<String>) -> Self {
+        Self {
+            orders: Mutex::new(HashMap::new()),
+            callbacks: Mutex::new(Vec::new()),
+            changes: watch::channel(0).0,
+            markets: Mutex::new(markets),
+            awaited: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn orders(&self) -> std::sync::MutexGuard<'_, HashMap<String, OrderResponse>> {
+        self.orders.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn awaited(&self) -> std::sync::MutexGuard<'_, HashMap<String, usize>> {
+        self.awaited.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record an order's state, notifying if it is new or changed
+    fn apply(&self, order: OrderResponse) {
+        {
+            let mut orders = self.orders();
+            if let Some(known) = orders.get(&order.sid) {
+                let stale = order.seq < known.seq;
+                let unchanged = known.status == order.status && known.size == order.size;
+                // A closed order does not reopen
+                if stale || unchanged || !known.is_open() {
+                    return;
+                }
+            }
+            orders.insert(order.sid.clone(), order.clone());
+        }
+        self.changes.send_modify(|version| *version += 1);
+        self.emit(&OrderEvent::from_order(order));
+    }
+
+    fn emit(&self, event: &OrderEvent) {
+        let callbacks = self
+            .callbacks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        for callback in callbacks {
+            callback(event);
+        }
+    }
+}
+
+/// Registration of a waiter for a sid, removed on drop
+struct Awaiting<'a> {
+    shared: &'a Shared,
+    sid: &'a str,
+}
+
+impl<'a> Awaiting<'a> {
+    fn new(shared: &'a Shared, sid: &'a str) -> Self {
+        *shared.awaited().entry(sid.to_string()).or_default() += 1;
+        Self { shared, sid }
+    }
+}
+
+impl Drop for Awaiting<'_> {
+    fn drop(&mut self) {
+        let mut awaited = self.shared.awaited();
+        if let Some(waiters) = awaited.get_mut(self.sid) {
+            *waiters -= 1;
+            if *waiters == 0 {
+                awaited.remove(self.sid);
+            }
+        }
+    }
+}
+
+/// Authoritative view of the user's orders, kept up to date in the background
+///
+/// The tracker stops when it is dropped or the client shuts down.
+pub struct OrderTracker {
+    client: EkidenClient,
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+impl std::fmt::Debug for OrderTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OrderTracker")
+            .field("orders", &self.shared.orders().len())
+            .finish()
+    }
+}
+
+impl OrderTracker {
+    /// Subscribe to the authenticated user's channel, load the configured
+    /// markets' orders and start tracking
+    pub async fn start(client: EkidenClient, config: OrderTrackerConfig) -> Result<Self> {
+        let user_addr = client
+            .address()
+            .await?
+            .ok_or_else(|| EkidenError::auth("A key pair is required to track orders"))?;
+        if !client.is_websocket_connected().await {
+            client.connect_websocket().await?;
+        }
+        let stream = client.subscribe_user_stream(&user_addr).await?;
+        let markets = config
+            .markets
+            .iter()
+            .map(|market_addr| format::normalize_address(market_addr))
+            .collect::<Result<_>>()?;
+
+        let shared = Arc::new(Shared::new(markets));
+        let runner = Runner {
+            client: client.clone(),
+            shared: shared.clone(),
+            user_addr,
+            reconcile_interval: config.reconcile_interval,
+        };
+        runner.reconcile().await?;
+        let task = client.spawn_job(runner.run(stream));
+        Ok(Self {
+            client,
+            shared,
+            task,
+        })
+    }
+
+    /// Call `callback` with every order event from now on
+    pub fn on_event<F>(&self, callback: F)
+    where
+        F: Fn(&OrderEvent) + Send + Sync + 'static,
+    {
+        self.shared
+            .callbacks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Arc::new(callback));
+    }
+
+    /// Reconcile a market's orders from now on, e.g. before placing an
+    /// order on it that will be waited for
+    pub fn track_market(&self, market_addr: &str) -> Result<()> {
+        let market_addr = format::normalize_address(market_addr)?;
+        self.shared
+            .markets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(market_addr);
+        Ok(())
+    }
+
+    /// Get the latest state of an order
+    pub fn order(&self, sid: &str) -> Option<OrderResponse> {
+        self.shared.orders().get(sid).cloned()
+    }
+
+    /// Get the orders currently open
+    pub fn open_orders(&self) -> Vec<OrderResponse> {
+        self.shared
+            .orders()
+            .values()
+            .filter(|order| order.is_open())
+            .cloned()
+            .collect()
+    }
+
+    /// Wait until an order is filled
+    ///
+    /// Fails with [`EkidenError::OrderRejected`] if the order closes any
+    /// other way, and with [`EkidenError::General`] if it stopped being
+    /// listed without the tracker learning how it closed.
+    pub async fn wait_filled(&self, sid: &str) -> Result<OrderResponse> {
+        let order = self.wait_closed(sid).await?;
+        if order.status.eq_ignore_ascii_case("filled") {
+            Ok(order)
+        } else if is_missing(&order) {
+            Err(EkidenError::general(format!(
+                "Order {} is no longer listed; whether it filled is unknown",
+                sid
+            )))
+        } else {
+            Err(EkidenError::OrderRejected {
+                reason: format!("Order {} closed as {}", sid, order.status),
+            })
+        }
+    }
+
+    /// Wait until an order is filled, cancelled or otherwise closed
+    ///
+    /// Orders not seen yet are waited for as well; reconciliation picks them
+    /// up, closed or not, on markets it reconciles.
+    pub async fn wait_closed(&self, sid: &str) -> Result<OrderResponse> {
+        let _awaiting = Awaiting::new(&self.shared, sid);
+        let mut changes = self.shared.changes.subscribe();
+        let shutdown = self.client.cancellation_token();
+        loop {
+            if let Some(order) = self.order(sid).filter(|order| !order.is_open()) {
+                return Ok(order);
+            }
+            tokio::select! {
+                changed = changes.changed() => {
+                    changed.map_err(|_| EkidenError::ConnectionClosed)?;
+                }
+                _ = shutdown.cancelled() => return Err(EkidenError::Cancelled),
+            }
+        }
+    }
+}
+
+impl Drop for OrderTracker {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Whether reconciliation closed an order because it was no longer listed
+pub(crate) fn is_missing(order: &OrderResponse) -> bool {
+    order.status == MISSING_STATUS
+}
+
+struct Runner {
+    client: EkidenClient,
+    shared: Arc<Shared>,
+    user_addr: String,
+    reconcile_interval: Duration,
+}
+
+impl Runner {
+    async fn run(self, stream: UserEventStream) {
+        let mut stream = Some(stream);
+        let mut reconcile = tokio::time::interval(self.reconcile_interval);
+        reconcile.tick().await;
+        loop {
+            let next = async {
+                match stream.as_mut() {
+                    Some(stream) => stream.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+            let event = tokio::select! {
+                event = next => Some(event),
+                _ = reconcile.tick() => None,
+            };
+            match event {
+                Some(Ok(UserEvent::Order(order))) => self.shared.apply(order),
+                Some(Ok(UserEvent::Fill(fill))) => self.shared.emit(&OrderEvent::Fill(fill)),
+                Some(Ok(UserEvent::Resync { .. })) | None => self.reconcile_logged().await,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    warn!("Order updates stopped, reconciling over REST only: {}", e);
+                    stream = None;
+                }
+            }
+        }
+    }
+
+    async fn reconcile_logged(&self) {
+        if let Err(e) = self.reconcile().await {
+            warn!("Failed to reconcile orders: {}", e);
+        }
+    }
+
+    /// Refetch the user's orders on every market of interest
+    async fn reconcile(&self) -> Result<()> {
+        let mut markets = self
+            .shared
+            .markets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let open: Vec<OrderResponse> = {
+            let orders = self.shared.orders();
+            orders.values().filter(|o| o.is_open()).cloned().collect()
+        };
+        markets.extend(open.iter().map(|order| order.market_addr.clone()));
+
+        for market_addr in markets {
+            let listed: Vec<Result<OrderResponse>> = self
+                .client
+                .get_orders_stream(ListOrdersParams {
+                    market_addr: market_addr.clone(),
+                    side: None,
+                    pagination: Pagination::default(),
+                })
+                .collect()
+                .await;
+            let mut seen = HashSet::new();
+            for order in listed {
+                let order = order?;
+                let owned = format::normalize_address(&order.user_addr)
+                    .map_or(false, |owner| owner == self.user_addr);
+                // Skip the history of orders closed before tracking started,
+                // unless someone waits for them
+                let known = self.shared.orders().contains_key(&order.sid);
+                let awaited = self.shared.awaited().contains_key(&order.sid);
+                if owned && (known || awaited || order.is_open()) {
+                    seen.insert(order.sid.clone());
+                    self.shared.apply(order);
+                }
+            }
+            for order in open.iter().filter(|o| o.market_addr == market_addr) {
+                if !seen.contains(&order.sid) {
+                    debug!("Order {} is no longer listed, marking it closed", order.sid);
+                    self.shared.apply(OrderResponse {
+                        status: MISSING_STATUS.to_string(),
+                        ..order.clone()
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(sid: &str, status: &str, size: u64, seq: u64) -> OrderResponse {
+        OrderResponse {
+            sid: sid.to_string(),
+            side: "buy".to_string(),
+            size,
+            price: 100,
+            leverage: 1,
+            order_type: "limit".to_string(),
+            status: status.to_string(),
+            user_addr: "0x1".to_string(),
+            market_addr: "0x2".to_string(),
+            seq,
+            timestamp: 0,
+            client_order_id: None,
+            time_in_force: None,
+            post_only: None,
+            reduce_only: None,
+        }
+    }
+
+    #[test]
+    fn test_apply_emits_only_changes() {
+        let shared = Shared::new(BTreeSet::new());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        shared.callbacks.lock().unwrap().push(Arc::new({
+            let events = events.clone();
+            move |event: &OrderEvent| {
+                let name = match event {
+                    OrderEvent::Opened(_) => "opened",
+                    OrderEvent::PartiallyFilled(_) => "partial",
+                    OrderEvent::Filled(_) => "filled",
+                    OrderEvent::Cancelled(_) => "cancelled",
+                    OrderEvent::Closed(_) => "closed",
+                    OrderEvent::Fill(_) => "fill",
+                };
+                events.lock().unwrap().push(name);
+            }
+        }));
+
+        shared.apply(order("a", "placed", 10, 1));
+        shared.apply(order("a", "placed", 10, 1));
+        shared.apply(order("a", "partially_filled", 4, 2));
+        shared.apply(order("a", "placed", 10, 1));
+        shared.apply(order("a", "filled", 0, 3));
+        shared.apply(order("a", "cancelled", 0, 4));
+        shared.apply(order("b", "rejected", 10, 5));
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            ["opened", "partial", "filled", "closed"]
+        );
+        assert_eq!(*shared.changes.borrow(), 4);
+    }
+}
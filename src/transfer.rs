@@ -0,0 +1,432 @@
+//! Follow deposits and withdrawals until the funds are usable.
+//!
+//! A transfer goes through the chain, then the gateway's indexer, then the
+//! vault. [`TransferTracker`] polls each stage and reports progress as a
+//! stream of [`TransferStatus`] values, or waits for the outcome:
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::transfer::{Transfer, TransferStatus, TransferTracker};
+//! use std::time::Duration;
+//!
+//! let tracker = TransferTracker::new(client).with_timeout(Duration::from_secs(300));
+//! let transfer = Transfer::deposit("0xabc...", "0x1111111111111111111111111111111111111111");
+//! match tracker.wait(&transfer).await? {
+//!     TransferStatus::Completed => println!("Funds available"),
+//!     status => println!("Deposit ended as {:?}", status),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Without a [`VaultContract`] the chain stage is skipped and progress starts
+//! when the gateway indexes the transfer. A deposit only completes once the
+//! user's vault holds at least its amount, which needs an authorized client.
+
+use crate::aptos::vault::{TransactionStatus, VaultContract};
+use crate::client::EkidenClient;
+use crate::clock::{self, Clock};
+use crate::error::{EkidenError, Result};
+use crate::types::{
+    DepositResponse, ListDepositsParams, ListVaultsParams, ListWithdrawsParams, Pagination,
+    TransferState,
+};
+use crate::utils::format;
+use futures_util::stream::{BoxStream, Stream};
+use futures_util::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default interval between status checks
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Default time allowed for a transfer to complete
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Direction of a transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferKind {
+    Deposit,
+    Withdrawal,
+}
+
+/// A submitted deposit or withdrawal to follow
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub kind: TransferKind,
+    /// Hash of the submitted transaction
+    pub tx_hash: String,
+    /// User the transfer belongs to
+    pub user_addr: String,
+}
+
+impl Transfer {
+    /// Follow a deposit
+    pub fn deposit(tx_hash: &str, user_addr: &str) -> Self {
+        Self {
+            kind: TransferKind::Deposit,
+            tx_hash: tx_hash.to_string(),
+            user_addr: user_addr.to_string(),
+        }
+    }
+
+    /// Follow a withdrawal
+    pub fn withdrawal(tx_hash: &str, user_addr: &str) -> Self {
+        Self {
+            kind: TransferKind::Withdrawal,
+            tx_hash: tx_hash.to_string(),
+            user_addr: user_addr.to_string(),
+        }
+    }
+}
+
+/// Progress of a transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferStatus {
+    /// Not yet seen on chain or by the gateway
+    Pending,
+    /// The transaction succeeded on chain
+    Confirmed,
+    /// The gateway indexed the transfer but has not settled it
+    Indexed { version: u64, status: String },
+    /// The vault balance reflects the transfer
+    Completed,
+    /// The transfer failed on chain or was rejected by the gateway
+    Failed(String),
+}
+
+impl TransferStatus {
+    /// Whether the transfer can no longer change
+    pub fn is_final(&self) -> bool {
+        matches!(self, TransferStatus::Completed | TransferStatus::Failed(_))
+    }
+
+    /// Classify a gateway deposit or withdrawal status
+    fn from_gateway(version: u64, state: TransferState, status: &str) -> Self {
+        match state {
+            TransferState::Completed => TransferStatus::Completed,
+            TransferState::Failed => TransferStatus::Failed(status.to_string()),
+            TransferState::Pending => TransferStatus::Indexed {
+                version,
+                status: status.to_string(),
+            },
+        }
+    }
+}
+
+/// Follows transfers through the chain, the gateway and the vault
+#[derive(Clone)]
+pub struct TransferTracker {
+    client: EkidenClient,
+    vault: Option<Arc<VaultContract>>,
+    poll_interval: Duration,
+    timeout: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for TransferTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransferTracker")
+            .field(
+                "vault",
+                &self.vault.as_ref().map(|v| v.contract_addr.to_string()),
+            )
+            .field("poll_interval", &self.poll_interval)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TransferTracker {
+    /// Create a tracker that follows transfers through the gateway
+    pub fn new(client: EkidenClient) -> Self {
+        Self {
+            client,
+            vault: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+            clock: clock::system(),
+        }
+    }
+
+    /// Also confirm transactions on chain through the vault contract's node
+    pub fn with_vault(mut self, vault: Arc<VaultContract>) -> Self {
+        self.vault = Some(vault);
+        self
+    }
+
+    /// Set the interval between status checks
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Set the time allowed for a transfer to complete
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Use a custom clock for polling and the timeout
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Stream each new status of a transfer
+    ///
+    /// The stream ends after a final status, or with
+    /// [`EkidenError::Timeout`] if none is reached in time.
+    pub fn track(&self, transfer: &Transfer) -> BoxStream<'static, Result<TransferStatus>> {
+        let tracker = self.clone();
+        let transfer = transfer.clone();
+        async_stream::stream! {
+            let deadline = tracker.clock.instant() + tracker.timeout;
+            let mut last = None;
+            loop {
+                let status = tracker.check(&transfer, last.as_ref()).await;
+                if last.as_ref() != Some(&status) {
+                    last = Some(status.clone());
+                    yield Ok(status.clone());
+                }
+                if status.is_final() {
+                    break;
+                }
+                if tracker.clock.instant() >= deadline {
+                    yield Err(EkidenError::Timeout);
+                    break;
+                }
+                tracker.clock.sleep(tracker.poll_interval).await;
+            }
+        }
+        .boxed()
+    }
+
+    /// Wait for a transfer to reach a final status
+    pub async fn wait(&self, transfer: &Transfer) -> Result<TransferStatus> {
+        let mut statuses = self.track(transfer);
+        let mut last = TransferStatus::Pending;
+        while let Some(status) = statuses.next().await {
+            last = status?;
+        }
+        Ok(last)
+    }
+
+    /// Check the current status of a transfer once
+    async fn check(&self, transfer: &Transfer, last: Option<&TransferStatus>) -> TransferStatus {
+        match self.gateway_status(transfer).await {
+            Ok(Some(status)) => return status,
+            Ok(None) => {}
+            Err(e) => tracing::debug!("Failed to look up transfer {}: {}", transfer.tx_hash, e),
+        }
+
+        match (&self.vault, last) {
+            (_, Some(TransferStatus::Confirmed)) => TransferStatus::Confirmed,
+            (Some(vault), _) => match vault.transaction_status(&transfer.tx_hash).await {
+                TransactionStatus::Confirmed => TransferStatus::Confirmed,
+                TransactionStatus::Failed(vm_status) => TransferStatus::Failed(vm_status),
+                TransactionStatus::Pending => TransferStatus::Pending,
+            },
+            (None, _) => TransferStatus::Pending,
+        }
+    }
+
+    /// Find the transfer in the gateway's deposit or withdrawal history
+    async fn gateway_status(&self, transfer: &Transfer) -> Result<Option<TransferStatus>> {
+        let user_addr = Some(format::normalize_address(&transfer.user_addr)?);
+        let matches = |tx_hash: &str| tx_hash.eq_ignore_ascii_case(&transfer.tx_hash);
+        match transfer.kind {
+            TransferKind::Deposit => {
+                let deposits = self.client.get_deposits_stream(ListDepositsParams {
+                    user_addr,
+                    vault_addr: None,
+                    asset_addr: None,
+                    start_version: None,
+                    end_version: None,
+                    pagination: Pagination::default(),
+                });
+                let Some(deposit) = find(deposits, |d| matches(&d.tx_hash)).await? else {
+                    return Ok(None);
+                };
+                let state = match deposit.state() {
+                    TransferState::Completed if !self.credited(&deposit).await => {
+                        TransferState::Pending
+                    }
+                    state => state,
+                };
+                Ok(Some(TransferStatus::from_gateway(
+                    deposit.version,
+                    state,
+                    &deposit.status,
+                )))
+            }
+            TransferKind::Withdrawal => {
+                let withdrawals = self.client.get_withdrawals_stream(ListWithdrawsParams {
+                    user_addr,
+                    vault_addr: None,
+                    asset_addr: None,
+                    start_version: None,
+                    end_version: None,
+                    pagination: Pagination::default(),
+                });
+                let found = find(withdrawals, |w| matches(&w.tx_hash)).await?;
+                Ok(found.map(|w| TransferStatus::from_gateway(w.version, w.state(), &w.status)))
+            }
+        }
+    }
+
+    /// Whether the user's vault holds a settled deposit's amount
+    async fn credited(&self, deposit: &DepositResponse) -> bool {
+        let vaults = self.client.get_user_vaults_stream(ListVaultsParams {
+            pagination: Pagination::default(),
+        });
+        let vault = find(vaults, |v| {
+            v.vault_addr.eq_ignore_ascii_case(&deposit.vault_addr)
+                && v.asset_addr.eq_ignore_ascii_case(&deposit.asset_addr)
+        })
+        .await;
+        match vault {
+            Ok(vault) => vault.is_some_and(|v| v.balance >= deposit.amount),
+            Err(e) => {
+                tracing::debug!("Failed to check the vault for {}: {}", deposit.tx_hash, e);
+                false
+            }
+        }
+    }
+}
+
+/// Find the first item of a paginated history matching `pred`
+async fn find<T>(
+    items: impl Stream<Item = Result<T>>,
+    pred: impl Fn(&T) -> bool,
+) -> Result<Option<T>> {
+    let mut items = std::pin::pin!(items);
+    while let Some(item) = items.next().await {
+        let item = item?;
+        if pred(&item) {
+            return Ok(Some(item));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+    use futures_util::future::BoxFuture;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const USER: &str = "0x2222222222222222222222222222222222222222";
+
+    /// Gateway whose deposit history progresses by one step per request
+    ///
+    /// The vault holds `credit` once the deposit completes.
+    #[derive(Debug, Default)]
+    struct DepositHistory {
+        calls: AtomicUsize,
+        credit: u64,
+    }
+
+    impl HttpTransport for DepositHistory {
+        fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+            let body = if request.url.path().ends_with("user/vaults") {
+                serde_json::json!([{
+                    "vault_addr": "0x3",
+                    "user_addr": USER,
+                    "asset_addr": "0x4",
+                    "balance": self.credit,
+                    "locked_balance": 0,
+                    "available_balance": self.credit,
+                    "created_at": "",
+                    "updated_at": "",
+                }])
+            } else {
+                match self.calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => serde_json::json!([]),
+                    1 | 2 => serde_json::json!([deposit("pending")]),
+                    _ => serde_json::json!([deposit("completed")]),
+                }
+            };
+            Box::pin(async move {
+                Ok(HttpResponse {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: serde_json::to_vec(&body)?,
+                })
+            })
+        }
+    }
+
+    fn deposit(status: &str) -> serde_json::Value {
+        serde_json::json!({
+            "user_addr": USER,
+            "vault_addr": "0x3",
+            "asset_addr": "0x4",
+            "amount": 1000,
+            "tx_hash": "0xABC",
+            "version": 42,
+            "timestamp": 1,
+            "status": status,
+        })
+    }
+
+    async fn tracker(transport: DepositHistory) -> TransferTracker {
+        let client = crate::EkidenClientBuilder::new()
+            .local()
+            .unwrap()
+            .transport(Arc::new(transport))
+            .token("token")
+            .build()
+            .await
+            .unwrap();
+        TransferTracker::new(client).with_poll_interval(Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn test_tracks_deposit_until_credited() {
+        let tracker = tracker(DepositHistory {
+            credit: 1000,
+            ..Default::default()
+        })
+        .await;
+        let statuses: Vec<_> = tracker
+            .track(&Transfer::deposit("0xabc", USER))
+            .map(|status| status.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(
+            statuses,
+            vec![
+                TransferStatus::Pending,
+                TransferStatus::Indexed {
+                    version: 42,
+                    status: "pending".to_string()
+                },
+                TransferStatus::Completed,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_the_vault_credit() {
+        let tracker = tracker(DepositHistory {
+            credit: 999,
+            ..Default::default()
+        })
+        .await
+        .with_timeout(Duration::from_millis(50));
+        let result = tracker.wait(&Transfer::deposit("0xabc", USER)).await;
+        assert!(matches!(result, Err(EkidenError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn test_times_out() {
+        let tracker = tracker(DepositHistory::default())
+            .await
+            .with_timeout(Duration::ZERO);
+        let result = tracker.wait(&Transfer::deposit("0xabc", USER)).await;
+        assert!(matches!(result, Err(EkidenError::Timeout)));
+    }
+}
@@ -0,0 +1,430 @@
+//! Pluggable HTTP and WebSocket transports.
+//!
+//! [`EkidenClient`](crate::EkidenClient) sends REST requests through an
+//! [`HttpTransport`] and opens WebSocket connections through a
+//! [`WsConnector`]. The defaults use reqwest and tokio-tungstenite; tests can
+//! swap in implementations that record, replay or fake traffic, such as
+//! [`MockTransport`], which answers from canned responses without a network:
+//!
+//! ```rust,no_run
+//! # async fn run() -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::transport::MockTransport;
+//! use ekiden_rust_sdk::EkidenClientBuilder;
+//! use std::sync::Arc;
+//!
+//! let transport = Arc::new(MockTransport::new());
+//! transport.respond_json("market_info", &serde_json::json!([]))?;
+//! transport.respond_once_status("market_info", 503, "maintenance");
+//!
+//! let client = EkidenClientBuilder::new()
+//!     .local()?
+//!     .transport(transport.clone())
+//!     .build()
+//!     .await?;
+//! assert!(client.get_markets(Default::default()).await.is_err());
+//! assert!(client.get_markets(Default::default()).await?.is_empty());
+//! assert_eq!(transport.requests().len(), 2);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::config::EkidenConfig;
+use crate::error::{EkidenError, Result};
+use crate::tls::TlsConfig;
+use futures_util::future::BoxFuture;
+use futures_util::stream::BoxStream;
+use futures_util::{Sink, SinkExt, StreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, connect_async_tls_with_config};
+use url::Url;
+
+/// An HTTP request as handed to a transport
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: reqwest::Method,
+    pub url: Url,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+    /// Timeout for this request instead of the transport's default
+    pub timeout: Option<Duration>,
+}
+
+/// An HTTP response as returned by a transport
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Create a JSON response, failing if `body` does not serialize
+    pub fn json<T: serde::Serialize + ?Sized>(status: u16, body: &T) -> Result<Self> {
+        Ok(Self::json_bytes(status, serde_json::to_vec(body)?))
+    }
+
+    /// Create a JSON response from a value, which always serializes
+    fn json_value(status: u16, body: serde_json::Value) -> Self {
+        Self::json_bytes(status, body.to_string().into_bytes())
+    }
+
+    fn json_bytes(status: u16, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body,
+        }
+    }
+
+    /// Get a header value by case-insensitive name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the status is 2xx
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Sends HTTP requests for the client
+pub trait HttpTransport: Send + Sync + Debug {
+    /// Send a request and return the complete response
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>>;
+}
+
+/// Default transport backed by reqwest
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// Create a transport using the config's timeout, user agent, proxy, TLS and
+    /// compression settings
+    pub fn new(config: &EkidenConfig) -> Result<Self> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .user_agent(&config.user_agent)
+            .gzip(config.compression)
+            .brotli(config.compression);
+        builder = match &config.tls {
+            Some(tls) => tls.apply(builder)?,
+            None => builder.use_rustls_tls(),
+        };
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(proxy.to_reqwest()?);
+        }
+        Ok(Self {
+            client: builder.build()?,
+        })
+    }
+
+    /// Wrap an existing reqwest client
+    pub fn from_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            let mut builder = self.client.request(request.method, request.url);
+            for (key, value) in &request.headers {
+                builder = builder.header(key, value);
+            }
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+            if let Some(timeout) = request.timeout {
+                builder = builder.timeout(timeout);
+            }
+
+            let response = builder.send().await?;
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(key, value)| {
+                    value
+                        .to_str()
+                        .ok()
+                        .map(|v| (key.as_str().to_string(), v.to_string()))
+                })
+                .collect();
+            let body = response.bytes().await?.to_vec();
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct MockRoutes {
+    once: HashMap<String, VecDeque<HttpResponse>>,
+    always: HashMap<String, HttpResponse>,
+    requests: Vec<HttpRequest>,
+}
+
+/// Transport answering from canned responses keyed by API path
+///
+/// Paths are relative to the API base path, `/api/v1` by default, e.g.
+/// `market_info` or `user/intent`, and must match exactly. One-shot
+/// responses are used first, in order, then the standing response for the
+/// path; requests without either get a 404.
+#[derive(Debug)]
+pub struct MockTransport {
+    routes: Mutex<MockRoutes>,
+    base_path: String,
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self {
+            routes: Mutex::default(),
+            base_path: "/api/v1".to_string(),
+        }
+    }
+}
+
+impl MockTransport {
+    /// Create a transport with no responses
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve paths relative to `base_path` instead of `/api/v1`
+    pub fn with_base_path(mut self, base_path: &str) -> Self {
+        self.base_path = format!("/{}", base_path.trim_matches('/'));
+        self
+    }
+
+    /// Answer every request to `path` with `response`
+    pub fn respond(&self, path: &str, response: HttpResponse) {
+        let mut routes = self.routes.lock().unwrap();
+        routes.always.insert(Self::key(path), response);
+    }
+
+    /// Answer every request to `path` with a 200 JSON body
+    pub fn respond_json<T: serde::Serialize + ?Sized>(&self, path: &str, body: &T) -> Result<()> {
+        self.respond(path, HttpResponse::json(200, body)?);
+        Ok(())
+    }
+
+    /// Answer the next request to `path` with `response`
+    pub fn respond_once(&self, path: &str, response: HttpResponse) {
+        let mut routes = self.routes.lock().unwrap();
+        routes
+            .once
+            .entry(Self::key(path))
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Fail the next request to `path` with a status and gateway error message
+    pub fn respond_once_status(&self, path: &str, status: u16, message: &str) {
+        self.respond_once(
+            path,
+            HttpResponse::json_value(status, serde_json::json!({ "message": message })),
+        );
+    }
+
+    /// Get the requests received so far
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.routes.lock().unwrap().requests.clone()
+    }
+
+    /// Get the requests made to `path`
+    pub fn requests_to(&self, path: &str) -> Vec<HttpRequest> {
+        let key = Self::key(path);
+        self.requests()
+            .into_iter()
+            .filter(|request| self.relative(&request.url) == key)
+            .collect()
+    }
+
+    fn key(path: &str) -> String {
+        path.trim_matches('/').to_string()
+    }
+
+    /// Path of a request URL relative to the base path
+    fn relative(&self, url: &Url) -> String {
+        let path = url.path();
+        let path = path
+            .strip_prefix(&self.base_path)
+            .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+            .unwrap_or(path);
+        Self::key(path)
+    }
+
+    fn answer(&self, request: HttpRequest) -> HttpResponse {
+        let key = self.relative(&request.url);
+        let mut routes = self.routes.lock().unwrap();
+        routes.requests.push(request);
+
+        let once = routes
+            .once
+            .get_mut(&key)
+            .and_then(|queue| queue.pop_front());
+        let response = once.or_else(|| routes.always.get(&key).cloned());
+        response.unwrap_or_else(|| {
+            HttpResponse::json_value(
+                404,
+                serde_json::json!({ "message": format!("no mock response for {}", key) }),
+            )
+        })
+    }
+}
+
+impl HttpTransport for MockTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        let response = self.answer(request);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// Outgoing half of a WebSocket connection
+pub type WsFrameSink = Pin<Box<dyn Sink<Message, Error = EkidenError> + Send>>;
+
+/// Incoming half of a WebSocket connection
+pub type WsFrameStream = BoxStream<'static, Result<Message>>;
+
+/// Opens WebSocket connections for the client
+pub trait WsConnector: Send + Sync + Debug {
+    /// Connect to `url` and return the split connection
+    fn connect<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, Result<(WsFrameSink, WsFrameStream)>>;
+}
+
+/// Default connector backed by tokio-tungstenite
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TungsteniteConnector;
+
+impl WsConnector for TungsteniteConnector {
+    fn connect<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, Result<(WsFrameSink, WsFrameStream)>> {
+        Box::pin(async move {
+            let (ws_stream, _) = connect_async(url.as_str())
+                .await
+                .map_err(|e| EkidenError::WebSocket(format!("Failed to connect: {}", e)))?;
+            let (sink, stream) = ws_stream.split();
+            let sink: WsFrameSink = Box::pin(sink.sink_map_err(EkidenError::from));
+            let stream: WsFrameStream = stream.map(|m| m.map_err(EkidenError::from)).boxed();
+            Ok((sink, stream))
+        })
+    }
+}
+
+/// Connector backed by tokio-tungstenite with custom TLS settings
+#[derive(Clone)]
+pub struct TlsTungsteniteConnector {
+    connector: tokio_tungstenite::Connector,
+}
+
+impl Debug for TlsTungsteniteConnector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsTungsteniteConnector")
+            .finish_non_exhaustive()
+    }
+}
+
+impl TlsTungsteniteConnector {
+    /// Create a connector with the roots and client identity of `tls`
+    pub fn new(tls: &TlsConfig) -> Result<Self> {
+        Ok(Self {
+            connector: tls.ws_connector()?,
+        })
+    }
+}
+
+impl WsConnector for TlsTungsteniteConnector {
+    fn connect<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, Result<(WsFrameSink, WsFrameStream)>> {
+        Box::pin(async move {
+            let (ws_stream, _) = connect_async_tls_with_config(
+                url.as_str(),
+                None,
+                false,
+                Some(self.connector.clone()),
+            )
+            .await
+            .map_err(|e| EkidenError::WebSocket(format!("Failed to connect: {}", e)))?;
+            let (sink, stream) = ws_stream.split();
+            let sink: WsFrameSink = Box::pin(sink.sink_map_err(EkidenError::from));
+            let stream: WsFrameStream = stream.map(|m| m.map_err(EkidenError::from)).boxed();
+            Ok((sink, stream))
+        })
+    }
+}
+
+/// Get the default WebSocket connector for a configuration
+pub(crate) fn default_ws_connector(config: &EkidenConfig) -> Result<Arc<dyn WsConnector>> {
+    Ok(match &config.tls {
+        Some(tls) => Arc::new(TlsTungsteniteConnector::new(tls)?),
+        None => Arc::new(TungsteniteConnector),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_header_lookup() {
+        let response = HttpResponse {
+            status: 204,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: Vec::new(),
+        };
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        assert_eq!(response.header("accept"), None);
+        assert!(response.is_success());
+    }
+
+    fn get(path: &str) -> HttpRequest {
+        HttpRequest {
+            method: reqwest::Method::GET,
+            url: Url::parse(&format!("http://localhost:3010/api/v1/{}", path)).unwrap(),
+            headers: Vec::new(),
+            body: None,
+            timeout: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport_routes_by_path() {
+        let transport = MockTransport::new();
+        transport
+            .respond_json("user/orders", &serde_json::json!([]))
+            .unwrap();
+        transport
+            .respond_json("orders", &serde_json::json!([1]))
+            .unwrap();
+        transport.respond_once_status("orders", 503, "maintenance");
+
+        // A one-shot response for `orders` is not used up by `user/orders`
+        assert_eq!(
+            transport.send(get("user/orders")).await.unwrap().body,
+            b"[]"
+        );
+        assert_eq!(transport.send(get("orders")).await.unwrap().status, 503);
+        assert_eq!(transport.send(get("orders")).await.unwrap().body, b"[1]");
+        assert_eq!(
+            transport.send(get("user/orders")).await.unwrap().body,
+            b"[]"
+        );
+        assert_eq!(transport.send(get("fills")).await.unwrap().status, 404);
+
+        assert_eq!(transport.requests().len(), 5);
+        assert_eq!(transport.requests_to("user/orders").len(), 2);
+        assert_eq!(transport.requests_to("orders").len(), 2);
+    }
+}
@@ -0,0 +1,339 @@
+//! Trigger orders fired from the client.
+//!
+//! [`EkidenClient::place_trigger_order`] leaves stops and take-profits to the
+//! gateway. Where the gateway has no native triggers, [`TriggerMonitor`]
+//! holds the orders locally, watches the market's ticker and trades, and
+//! places each order once its trigger price is crossed:
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::triggers::{TriggerMode, TriggerMonitor};
+//! use ekiden_rust_sdk::{OrderSide, TriggerOrder};
+//!
+//! let market = "0x1111111111111111111111111111111111111111";
+//! let monitor = TriggerMonitor::new(client, TriggerMode::Auto);
+//! monitor.on_fired(|id, result| match result {
+//!     Ok(order) => println!("{} fired as {}", id, order.sid),
+//!     Err(e) => eprintln!("{} failed: {}", id, e),
+//! });
+//!
+//! let stop = TriggerOrder::stop_market(market, OrderSide::Sell, 100_000, 60_000_000_000)
+//!     .with_reduce_only(true);
+//! monitor.place(stop).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Local triggers only fire while the monitor is alive and the socket
+//! delivers prices; they are lost when the process exits.
+
+use crate::client::{action_unsupported, EkidenClient};
+use crate::error::{EkidenError, Result};
+use crate::types::{
+    OrderOptions, PlacedOrder, PlacedTrigger, TimeInForce, TriggerOrder, TriggerOrderKind,
+    TriggerType, WsEvent,
+};
+use crate::utils::format;
+use crate::ws::Channel;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Where a [`TriggerMonitor`] keeps trigger orders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerMode {
+    /// Place on the gateway, and monitor locally once it rejects trigger orders
+    Auto,
+    /// Always monitor locally
+    Local,
+}
+
+/// Where a trigger order was placed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriggerPlacement {
+    /// Held by the gateway
+    Native(PlacedTrigger),
+    /// Held by the monitor under a local id
+    Local(String),
+}
+
+/// Callback invoked with the local id and outcome of every fired order
+///
+/// Orders that fail to fire with a retryable error are held again and fire
+/// on the next price instead. If a market's price feed fails, the callback
+/// gets an error for each of its orders; they stay pending, and are watched
+/// again once another order is placed on the market.
+pub type FiredCallback = Arc<dyn Fn(&str, &Result<PlacedOrder>) + Send + Sync>;
+
+#[derive(Default)]
+struct Shared {
+    /// Local orders waiting for their trigger, by id
+    pending: Mutex<BTreeMap<String, TriggerOrder>>,
+    callbacks: Mutex<Vec<FiredCallback>>,
+    /// Price watchers by market
+    watchers: Mutex<HashMap<String, JoinHandle<()>>>,
+    next_id: AtomicU64,
+    /// Set once the gateway rejects trigger orders
+    native_unsupported: AtomicBool,
+}
+
+impl Shared {
+    fn pending(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, TriggerOrder>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Remove and return the orders of a market set off by `price`
+    fn take_triggered(
+        &self,
+        market_addr: &str,
+        trigger_type: TriggerType,
+        price: u64,
+    ) -> Vec<(String, TriggerOrder)> {
+        let mut pending = self.pending();
+        let ids: Vec<String> = pending
+            .iter()
+            .filter(|(_, order)| {
+                order.market_addr == market_addr
+                    && order.trigger_type == trigger_type
+                    && order.is_triggered(price)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.into_iter()
+            .filter_map(|id| pending.remove(&id).map(|order| (id, order)))
+            .collect()
+    }
+
+    /// Call every callback with an order's outcome
+    fn notify(&self, id: &str, result: &Result<PlacedOrder>) {
+        let callbacks = self
+            .callbacks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        for callback in callbacks {
+            callback(id, result);
+        }
+    }
+
+    /// Report to the callbacks that a market's orders are no longer watched
+    fn watch_failed(&self, market_addr: &str, error: &EkidenError) {
+        let ids: Vec<String> = self
+            .pending()
+            .iter()
+            .filter(|(_, order)| order.market_addr == market_addr)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in ids {
+            let error = EkidenError::general(format!(
+                "Stopped watching {} for triggers: {}",
+                market_addr, error
+            ));
+            self.notify(&id, &Err(error));
+        }
+    }
+}
+
+/// Holds trigger orders the gateway cannot, and fires them from the client
+///
+/// Price watchers stop when the monitor is dropped or the client shuts down.
+pub struct TriggerMonitor {
+    client: EkidenClient,
+    mode: TriggerMode,
+    shared: Arc<Shared>,
+}
+
+impl std::fmt::Debug for TriggerMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TriggerMonitor")
+            .field("mode", &self.mode)
+            .field("pending", &self.shared.pending().len())
+            .finish()
+    }
+}
+
+impl TriggerMonitor {
+    pub fn new(client: EkidenClient, mode: TriggerMode) -> Self {
+        Self {
+            client,
+            mode,
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Call `callback` with every order the monitor fires from now on
+    pub fn on_fired<F>(&self, callback: F)
+    where
+        F: Fn(&str, &Result<PlacedOrder>) + Send + Sync + 'static,
+    {
+        self.shared
+            .callbacks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Arc::new(callback));
+    }
+
+    /// Place a trigger order on the gateway or hold it locally, per the mode
+    pub async fn place(&self, order: TriggerOrder) -> Result<TriggerPlacement> {
+        order.validate()?;
+        let order = TriggerOrder {
+            market_addr: format::normalize_address(&order.market_addr)?,
+            ..order
+        };
+        if self.mode == TriggerMode::Auto && !self.shared.native_unsupported.load(Ordering::Relaxed)
+        {
+            match self.client.place_trigger_order(order.clone()).await {
+                Err(e) if action_unsupported(&e) => {
+                    debug!("Gateway does not support trigger orders, monitoring locally");
+                    self.shared
+                        .native_unsupported
+                        .store(true, Ordering::Relaxed);
+                }
+                result => return result.map(TriggerPlacement::Native),
+            }
+        }
+        self.hold(order).await.map(TriggerPlacement::Local)
+    }
+
+    /// Cancel a local trigger order; returns false if it already fired or is unknown
+    pub fn cancel(&self, id: &str) -> bool {
+        self.shared.pending().remove(id).is_some()
+    }
+
+    /// Get the local trigger orders still waiting, by id
+    pub fn pending(&self) -> Vec<(String, TriggerOrder)> {
+        self.shared
+            .pending()
+            .iter()
+            .map(|(id, order)| (id.clone(), order.clone()))
+            .collect()
+    }
+
+    /// Keep an order locally and make sure its market's prices are watched
+    async fn hold(&self, order: TriggerOrder) -> Result<String> {
+        let market_addr = order.market_addr.clone();
+        let watching = self
+            .shared
+            .watchers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&market_addr)
+            .is_some_and(|watcher| !watcher.is_finished());
+        if !watching {
+            let watcher = self.watch(&market_addr).await?;
+            let replaced = self
+                .shared
+                .watchers
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(market_addr, watcher);
+            if let Some(replaced) = replaced {
+                replaced.abort();
+            }
+        }
+
+        let id = format!(
+            "trigger-{}",
+            self.shared.next_id.fetch_add(1, Ordering::Relaxed) + 1
+        );
+        self.shared.pending().insert(id.clone(), order);
+        Ok(id)
+    }
+
+    /// Subscribe to a market's prices and fire its orders as they trigger
+    async fn watch(&self, market_addr: &str) -> Result<JoinHandle<()>> {
+        if !self.client.is_websocket_connected().await {
+            self.client.connect_websocket().await?;
+        }
+        let mut stream = self
+            .client
+            .subscribe_many(&[
+                Channel::Ticker(market_addr.to_string()),
+                Channel::Trades(market_addr.to_string()),
+            ])
+            .await?;
+        let client = self.client.clone();
+        let shared = self.shared.clone();
+        let market_addr = market_addr.to_string();
+        Ok(self.client.spawn_job(async move {
+            loop {
+                let prices = match stream.recv().await {
+                    Ok((_, WsEvent::Ticker { ticker })) => vec![
+                        (TriggerType::Mark, ticker.mark_price),
+                        (TriggerType::Oracle, ticker.oracle_price),
+                    ],
+                    Ok((_, WsEvent::Trade { price, .. })) => vec![(TriggerType::Last, price)],
+                    Ok(_) => continue,
+                    // Later prices still set off the orders the missed ones would have
+                    Err(EkidenError::Lagged { .. }) => continue,
+                    Err(e) => {
+                        warn!("Stopped watching {} for triggers: {}", market_addr, e);
+                        shared.watch_failed(&market_addr, &e);
+                        return;
+                    }
+                };
+                // A zero price is one the gateway does not know yet
+                for (trigger_type, price) in prices.into_iter().filter(|(_, price)| *price > 0) {
+                    for (id, order) in shared.take_triggered(&market_addr, trigger_type, price) {
+                        debug!("Trigger {} set off at {}", id, price);
+                        match fire(&client, &order).await {
+                            Err(e) if e.is_retryable() => {
+                                warn!("Trigger {} failed to fire, holding it again: {}", id, e);
+                                shared.pending().insert(id, order);
+                            }
+                            result => shared.notify(&id, &result),
+                        }
+                    }
+                }
+            }
+        }))
+    }
+}
+
+impl Drop for TriggerMonitor {
+    fn drop(&mut self) {
+        let watchers = self
+            .shared
+            .watchers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        for watcher in watchers.values() {
+            watcher.abort();
+        }
+    }
+}
+
+/// Place the order a trigger stands for
+async fn fire(client: &EkidenClient, order: &TriggerOrder) -> Result<PlacedOrder> {
+    let options = OrderOptions::new().with_reduce_only(order.reduce_only);
+    match (order.kind, order.price) {
+        (TriggerOrderKind::StopLimit, Some(price)) => {
+            client
+                .place_limit_order(&order.market_addr, order.side, price, order.size, options)
+                .await
+        }
+        (TriggerOrderKind::StopLimit, None) => Err(EkidenError::validation(
+            "Stop-limit orders need a limit price",
+        )),
+        // A worst price turns the market order into an immediate-or-cancel limit
+        (_, Some(worst_price)) => {
+            let options = options.with_time_in_force(TimeInForce::Ioc);
+            client
+                .place_limit_order(
+                    &order.market_addr,
+                    order.side,
+                    worst_price,
+                    order.size,
+                    options,
+                )
+                .await
+        }
+        (_, None) => {
+            client
+                .place_market_order(&order.market_addr, order.side, order.size, options)
+                .await
+        }
+    }
+}
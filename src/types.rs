@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
 
 // ===== Common Pagination =====
 
@@ -40,6 +41,25 @@ impl Pagination {
             page_size: Some(page_size),
         }
     }
+
+    /// Move to the page after one that returned `received` items
+    ///
+    /// Returns `false` when that page was the last one: it was empty or
+    /// shorter than the requested page size.
+    pub fn advance(&mut self, received: usize) -> bool {
+        let requested = match self.page {
+            Some(_) => self.page_size,
+            None => self.limit,
+        };
+        if received == 0 || requested.is_some_and(|requested| received < requested as usize) {
+            return false;
+        }
+        match self.page {
+            Some(page) => self.page = Some(page + 1),
+            None => self.offset = Some(self.offset.unwrap_or(0) + received as u32),
+        }
+        true
+    }
 }
 
 // ===== Authentication Types =====
@@ -59,6 +79,8 @@ pub struct AuthorizeResponse {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketResponse {
+    #[serde(default)]
+    pub addr: String,
     pub symbol: String,
     pub base_addr: String,
     pub base_decimals: u8,
@@ -97,6 +119,32 @@ impl Default for ListMarketsParams {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderbookResponse {
+    pub market_addr: String,
+    pub bids: Vec<OrderbookLevel>,
+    pub asks: Vec<OrderbookLevel>,
+    pub timestamp: u64,
+}
+
+impl From<OrderbookResponse> for WsEvent {
+    fn from(book: OrderbookResponse) -> Self {
+        WsEvent::OrderbookSnapshot {
+            market_addr: book.market_addr,
+            bids: book.bids,
+            asks: book.asks,
+            timestamp: book.timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetOrderbookParams {
+    pub market_addr: String,
+    /// Number of levels per side; the gateway default when unset
+    pub depth: Option<u32>,
+}
+
 // ===== Order Types =====
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -113,6 +161,63 @@ pub struct OrderResponse {
     pub market_addr: String,
     pub seq: u64,
     pub timestamp: u64,
+    /// Client order id the order was placed with, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_order_id: Option<String>,
+    /// Time in force, if the gateway reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_in_force: Option<TimeInForce>,
+    /// Whether the order may only add liquidity, if the gateway reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_only: Option<bool>,
+    /// Whether the order may only reduce a position, if the gateway reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+}
+
+impl OrderResponse {
+    /// Classify the order's status
+    pub fn state(&self) -> OrderState {
+        OrderState::parse(&self.status)
+    }
+
+    /// Check if the order can still fill or be cancelled
+    pub fn is_open(&self) -> bool {
+        self.state().is_open()
+    }
+}
+
+/// Lifecycle state of an order, as reported by the gateway
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderState {
+    /// Resting or waiting to rest, nothing filled yet
+    Open,
+    /// Partly filled and still resting
+    PartiallyFilled,
+    /// Completely filled
+    Filled,
+    /// Cancelled before filling completely
+    Cancelled,
+    /// Closed otherwise, e.g. expired or rejected, or a status the SDK does not know
+    Closed,
+}
+
+impl OrderState {
+    /// Classify a gateway status, case-insensitively
+    pub fn parse(status: &str) -> Self {
+        match status.to_ascii_lowercase().as_str() {
+            "open" | "new" | "placed" | "pending" => OrderState::Open,
+            "partially_filled" | "partial" => OrderState::PartiallyFilled,
+            "filled" => OrderState::Filled,
+            "cancelled" | "canceled" => OrderState::Cancelled,
+            _ => OrderState::Closed,
+        }
+    }
+
+    /// Check if an order in this state can still fill or be cancelled
+    pub fn is_open(self) -> bool {
+        matches!(self, OrderState::Open | OrderState::PartiallyFilled)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -123,20 +228,183 @@ pub struct ListOrdersParams {
     pub pagination: Pagination,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum OrderSide {
     Buy,
     Sell,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum OrderType {
     Market,
     Limit,
 }
 
+/// How long an order may rest on the book
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeInForce {
+    /// Good till cancelled
+    #[default]
+    Gtc,
+    /// Immediate or cancel: fill what is possible now, cancel the rest
+    Ioc,
+    /// Fill or kill: fill the whole size now or cancel
+    Fok,
+}
+
+/// What a trigger order does once its trigger price is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerOrderKind {
+    /// Place a market order when the price moves against the order's side
+    StopMarket,
+    /// Place a limit order when the price moves against the order's side
+    StopLimit,
+    /// Place a market order when the price moves in the order's favour
+    TakeProfit,
+}
+
+/// Price a trigger order is compared against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TriggerType {
+    #[default]
+    Mark,
+    /// Price of the last trade
+    Last,
+    Oracle,
+}
+
+/// An order placed once a market price crosses `trigger_price`
+///
+/// Buy stops trigger when the price rises to the trigger price and sell stops
+/// when it falls to it; take-profits trigger the other way round.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerOrder {
+    pub market_addr: String,
+    pub side: OrderSide,
+    pub kind: TriggerOrderKind,
+    pub trigger_price: u64,
+    pub trigger_type: TriggerType,
+    /// Limit price of a stop-limit order, or the worst fill price of the others
+    pub price: Option<u64>,
+    pub size: u64,
+    /// Only reduce an existing position
+    pub reduce_only: bool,
+}
+
+impl TriggerOrder {
+    fn new(
+        market_addr: &str,
+        side: OrderSide,
+        kind: TriggerOrderKind,
+        size: u64,
+        trigger_price: u64,
+    ) -> Self {
+        Self {
+            market_addr: market_addr.to_string(),
+            side,
+            kind,
+            trigger_price,
+            trigger_type: TriggerType::Mark,
+            price: None,
+            size,
+            reduce_only: false,
+        }
+    }
+
+    /// Create a stop that sends a market order
+    pub fn stop_market(market_addr: &str, side: OrderSide, size: u64, trigger_price: u64) -> Self {
+        Self::new(
+            market_addr,
+            side,
+            TriggerOrderKind::StopMarket,
+            size,
+            trigger_price,
+        )
+    }
+
+    /// Create a stop that sends a limit order at `price`
+    pub fn stop_limit(
+        market_addr: &str,
+        side: OrderSide,
+        size: u64,
+        trigger_price: u64,
+        price: u64,
+    ) -> Self {
+        Self::new(
+            market_addr,
+            side,
+            TriggerOrderKind::StopLimit,
+            size,
+            trigger_price,
+        )
+        .with_price(price)
+    }
+
+    /// Create a take-profit that sends a market order
+    pub fn take_profit(market_addr: &str, side: OrderSide, size: u64, trigger_price: u64) -> Self {
+        Self::new(
+            market_addr,
+            side,
+            TriggerOrderKind::TakeProfit,
+            size,
+            trigger_price,
+        )
+    }
+
+    /// Set the price the trigger is compared against
+    pub fn with_trigger_type(mut self, trigger_type: TriggerType) -> Self {
+        self.trigger_type = trigger_type;
+        self
+    }
+
+    /// Set the limit price, or the worst fill price of a market kind
+    pub fn with_price(mut self, price: u64) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    /// Make the triggered order reduce-only
+    pub fn with_reduce_only(mut self, reduce_only: bool) -> Self {
+        self.reduce_only = reduce_only;
+        self
+    }
+
+    /// Check if `price` of the trigger type sets the order off
+    pub fn is_triggered(&self, price: u64) -> bool {
+        let rising = match self.kind {
+            TriggerOrderKind::StopMarket | TriggerOrderKind::StopLimit => {
+                self.side == OrderSide::Buy
+            }
+            TriggerOrderKind::TakeProfit => self.side == OrderSide::Sell,
+        };
+        if rising {
+            price >= self.trigger_price
+        } else {
+            price <= self.trigger_price
+        }
+    }
+
+    /// Check the order can be placed
+    pub fn validate(&self) -> std::result::Result<(), crate::error::EkidenError> {
+        if self.size == 0 || self.trigger_price == 0 {
+            return Err(crate::error::EkidenError::validation(
+                "Trigger order size and trigger price must be greater than zero",
+            ));
+        }
+        if self.kind == TriggerOrderKind::StopLimit && self.price.unwrap_or(0) == 0 {
+            return Err(crate::error::EkidenError::validation(
+                "Stop-limit orders need a limit price",
+            ));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderStatus {
     pub status: String,
@@ -200,6 +468,13 @@ pub struct PositionResponse {
     pub updated_at: String,
 }
 
+impl PositionResponse {
+    /// Get the position size signed by side (negative when short)
+    pub fn signed_size(&self) -> i64 {
+        signed_size(&self.side, self.size)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListPositionsParams {
     pub market_addr: Option<String>,
@@ -257,6 +532,21 @@ pub struct PortfolioPosition {
     pub leverage: u64,
 }
 
+impl PortfolioPosition {
+    /// Get the position size signed by side (negative when short)
+    pub fn signed_size(&self) -> i64 {
+        signed_size(&self.side, self.size)
+    }
+}
+
+fn signed_size(side: &str, size: u64) -> i64 {
+    let size = size as i64;
+    match side.to_lowercase().as_str() {
+        "short" | "sell" => -size,
+        _ => size,
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortfolioVault {
     pub vault_addr: String,
@@ -268,12 +558,70 @@ pub struct PortfolioVault {
     pub usd_value: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountInfoResponse {
+    pub user_addr: String,
+    /// Account tier, e.g. `standard` or `vip1`
+    pub tier: String,
+    pub fees: FeeTier,
+    pub referral: Option<ReferralInfo>,
+    pub api_limits: ApiLimits,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeTier {
+    pub name: String,
+    /// Maker fee in basis points; negative for a rebate
+    pub maker_fee_bps: i64,
+    /// Taker fee in basis points
+    pub taker_fee_bps: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferralInfo {
+    pub code: String,
+    pub referred_count: u64,
+    /// Lifetime referral earnings in quote units
+    pub total_earnings: u64,
+    /// Earnings not yet paid out, in quote units
+    pub pending_earnings: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiLimits {
+    pub requests_per_minute: u32,
+    pub orders_per_second: u32,
+    pub ws_connections: u32,
+    pub ws_subscriptions: u32,
+}
+
+impl ApiLimits {
+    /// Smallest spacing between REST requests that stays within the limit
+    pub fn min_request_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(60) / self.requests_per_minute.max(1)
+    }
+
+    /// Smallest spacing between orders that stays within the limit
+    pub fn min_order_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(1) / self.orders_per_second.max(1)
+    }
+}
+
 // ===== Intent Types =====
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SendIntentParams {
     pub actions: Vec<ActionPayload>,
     pub signature: String,
+    /// Per-account intent number, for gateways that require one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+}
+
+/// Next intent nonce the gateway expects from the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NonceResponse {
+    pub nonce: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -283,6 +631,117 @@ pub struct SendIntentResponse {
     pub outputs: Vec<IntentOutput>,
 }
 
+impl SendIntentResponse {
+    /// Whether the intent reached a terminal status
+    ///
+    /// Statuses not known to be terminal, such as `pending` or `accepted`,
+    /// count as not final.
+    pub fn is_final(&self) -> bool {
+        self.is_success()
+            || matches!(
+                self.status.to_ascii_lowercase().as_str(),
+                "failed" | "rejected" | "reverted" | "cancelled" | "canceled" | "expired"
+            )
+    }
+
+    /// Whether the intent was executed
+    pub fn is_success(&self) -> bool {
+        matches!(
+            self.status.to_ascii_lowercase().as_str(),
+            "success" | "executed" | "finalized"
+        )
+    }
+}
+
+/// Expected outcome of an intent, from [`simulate_intent`](crate::EkidenClient::simulate_intent)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IntentSimulation {
+    /// Whether the gateway would accept the intent
+    pub accepted: bool,
+    /// One output per action, in order
+    pub outputs: Vec<SimulatedOutput>,
+    /// Fees of all expected fills, in quote units
+    #[serde(default)]
+    pub total_fee: u64,
+    /// Margin the intent would lock, in quote units
+    #[serde(default)]
+    pub margin_required: u64,
+}
+
+impl IntentSimulation {
+    /// Get the reasons actions would be rejected
+    pub fn errors(&self) -> Vec<&str> {
+        self.outputs
+            .iter()
+            .filter_map(|output| output.error.as_deref())
+            .collect()
+    }
+
+    /// Turn a simulation the gateway would reject into an error
+    pub fn into_result(self) -> crate::error::Result<Self> {
+        if self.accepted {
+            return Ok(self);
+        }
+        let errors = self.errors();
+        let reason = if errors.is_empty() {
+            "Intent would be rejected".to_string()
+        } else {
+            errors.join("; ")
+        };
+        Err(crate::error::EkidenError::OrderRejected { reason })
+    }
+}
+
+/// Expected outcome of one action of a simulated intent
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimulatedOutput {
+    pub action_type: String,
+    /// Fills the action would get against the current book
+    #[serde(default)]
+    pub fills: Vec<SimulatedFill>,
+    /// Fees of the fills, in quote units
+    #[serde(default)]
+    pub fee: u64,
+    /// Why the action would be rejected, e.g. insufficient margin or a price band
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SimulatedOutput {
+    /// Get the size that would fill, saturating at `u64::MAX`
+    pub fn filled_size(&self) -> u64 {
+        self.fills
+            .iter()
+            .fold(0u64, |total, fill| total.saturating_add(fill.size))
+    }
+
+    /// Get the size-weighted price of the fills, if any
+    pub fn average_price(&self) -> Option<u64> {
+        let (size, notional) = self
+            .fills
+            .iter()
+            .fold((0u128, 0u128), |(size, notional), fill| {
+                (
+                    size + fill.size as u128,
+                    notional.saturating_add(fill.price as u128 * fill.size as u128),
+                )
+            });
+        if size == 0 {
+            return None;
+        }
+        Some((notional / size) as u64)
+    }
+}
+
+/// A fill expected from a simulated action
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimulatedFill {
+    pub price: u64,
+    pub size: u64,
+    #[serde(default)]
+    pub fee: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActionPayload {
     #[serde(rename = "type")]
@@ -290,12 +749,573 @@ pub struct ActionPayload {
     pub data: serde_json::Value,
 }
 
+impl ActionPayload {
+    pub fn new<S: Into<String>>(action_type: S, data: serde_json::Value) -> Self {
+        Self {
+            action_type: action_type.into(),
+            data,
+        }
+    }
+
+    /// Create a `place_order` action
+    pub fn place_order(
+        market_addr: &str,
+        side: OrderSide,
+        order_type: OrderType,
+        price: u64,
+        size: u64,
+    ) -> Self {
+        IntentAction::PlaceOrder {
+            market_addr: market_addr.to_string(),
+            side,
+            order_type,
+            price,
+            size,
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            reduce_only: false,
+            client_order_id: None,
+        }
+        .into()
+    }
+
+    /// Create a `cancel_order` action
+    pub fn cancel_order(market_addr: &str, sid: &str) -> Self {
+        IntentAction::cancel_order(market_addr, sid).into()
+    }
+}
+
+/// A typed intent action, converted to an [`ActionPayload`] when sent
+///
+/// Amounts, prices and sizes are sent as decimal strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntentAction {
+    PlaceOrder {
+        market_addr: String,
+        side: OrderSide,
+        order_type: OrderType,
+        price: u64,
+        size: u64,
+        time_in_force: TimeInForce,
+        /// Cancel instead of taking liquidity
+        post_only: bool,
+        /// Only reduce an existing position
+        reduce_only: bool,
+        /// Id chosen by the caller to find the order before its sid is known
+        client_order_id: Option<String>,
+    },
+    CancelOrder {
+        market_addr: String,
+        sid: String,
+    },
+    /// Change the price and size of an open order, keeping its sid
+    AmendOrder {
+        market_addr: String,
+        sid: String,
+        price: u64,
+        size: u64,
+    },
+    /// Withdraw an asset from the user's vault
+    Withdraw {
+        asset_addr: String,
+        amount: u64,
+    },
+    /// Add margin to a position, or remove it when `amount` is negative
+    AdjustMargin {
+        market_addr: String,
+        amount: i64,
+    },
+    SetLeverage {
+        market_addr: String,
+        leverage: u64,
+    },
+    /// Place an order held by the gateway until its trigger price is reached
+    PlaceTriggerOrder(TriggerOrder),
+}
+
+impl IntentAction {
+    /// Create a limit order
+    pub fn limit_order(market_addr: &str, side: OrderSide, price: u64, size: u64) -> Self {
+        Self::PlaceOrder {
+            market_addr: market_addr.to_string(),
+            side,
+            order_type: OrderType::Limit,
+            price,
+            size,
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            reduce_only: false,
+            client_order_id: None,
+        }
+    }
+
+    /// Create a market order that fills no worse than `worst_price`
+    pub fn market_order(market_addr: &str, side: OrderSide, size: u64, worst_price: u64) -> Self {
+        Self::PlaceOrder {
+            market_addr: market_addr.to_string(),
+            side,
+            order_type: OrderType::Market,
+            price: worst_price,
+            size,
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            reduce_only: false,
+            client_order_id: None,
+        }
+    }
+
+    /// Set an order's time in force; other actions are unchanged
+    pub fn with_time_in_force(mut self, tif: TimeInForce) -> Self {
+        if let Self::PlaceOrder { time_in_force, .. } = &mut self {
+            *time_in_force = tif;
+        }
+        self
+    }
+
+    /// Make an order post-only; other actions are unchanged
+    pub fn with_post_only(mut self, enabled: bool) -> Self {
+        if let Self::PlaceOrder { post_only, .. } = &mut self {
+            *post_only = enabled;
+        }
+        self
+    }
+
+    /// Make an order reduce-only; other actions are unchanged
+    pub fn with_reduce_only(mut self, enabled: bool) -> Self {
+        match &mut self {
+            Self::PlaceOrder { reduce_only, .. } => *reduce_only = enabled,
+            Self::PlaceTriggerOrder(order) => order.reduce_only = enabled,
+            _ => {}
+        }
+        self
+    }
+
+    /// Tag an order with a client order id; other actions are unchanged
+    pub fn with_client_order_id(mut self, id: impl Into<String>) -> Self {
+        if let Self::PlaceOrder {
+            client_order_id, ..
+        } = &mut self
+        {
+            *client_order_id = Some(id.into());
+        }
+        self
+    }
+
+    /// Cancel an order by its sid
+    pub fn cancel_order(market_addr: &str, sid: &str) -> Self {
+        Self::CancelOrder {
+            market_addr: market_addr.to_string(),
+            sid: sid.to_string(),
+        }
+    }
+
+    /// Change the price and size of an open order
+    pub fn amend_order(market_addr: &str, sid: &str, price: u64, size: u64) -> Self {
+        Self::AmendOrder {
+            market_addr: market_addr.to_string(),
+            sid: sid.to_string(),
+            price,
+            size,
+        }
+    }
+
+    /// Withdraw `amount` of an asset
+    pub fn withdraw(asset_addr: &str, amount: u64) -> Self {
+        Self::Withdraw {
+            asset_addr: asset_addr.to_string(),
+            amount,
+        }
+    }
+
+    /// Add `amount` of margin to a position, or remove it when negative
+    pub fn adjust_margin(market_addr: &str, amount: i64) -> Self {
+        Self::AdjustMargin {
+            market_addr: market_addr.to_string(),
+            amount,
+        }
+    }
+
+    /// Set the leverage used for a market
+    pub fn set_leverage(market_addr: &str, leverage: u64) -> Self {
+        Self::SetLeverage {
+            market_addr: market_addr.to_string(),
+            leverage,
+        }
+    }
+
+    /// Get the action type sent to the gateway
+    pub fn action_type(&self) -> &'static str {
+        match self {
+            Self::PlaceOrder { .. } => "place_order",
+            Self::CancelOrder { .. } => "cancel_order",
+            Self::AmendOrder { .. } => "amend_order",
+            Self::Withdraw { .. } => "withdraw",
+            Self::AdjustMargin { .. } => "adjust_margin",
+            Self::SetLeverage { .. } => "set_leverage",
+            Self::PlaceTriggerOrder(_) => "place_trigger_order",
+        }
+    }
+
+    /// Get the market the action applies to, if any
+    pub fn market_addr(&self) -> Option<&str> {
+        match self {
+            Self::PlaceOrder { market_addr, .. }
+            | Self::CancelOrder { market_addr, .. }
+            | Self::AmendOrder { market_addr, .. }
+            | Self::AdjustMargin { market_addr, .. }
+            | Self::SetLeverage { market_addr, .. } => Some(market_addr),
+            Self::PlaceTriggerOrder(order) => Some(&order.market_addr),
+            Self::Withdraw { .. } => None,
+        }
+    }
+}
+
+impl From<IntentAction> for ActionPayload {
+    fn from(action: IntentAction) -> Self {
+        let action_type = action.action_type();
+        let data = match action {
+            IntentAction::PlaceOrder {
+                market_addr,
+                side,
+                order_type,
+                price,
+                size,
+                time_in_force,
+                post_only,
+                reduce_only,
+                client_order_id,
+            } => {
+                let mut data = serde_json::json!({
+                    "market_addr": market_addr,
+                    "side": side,
+                    "size": size.to_string(),
+                    "price": price.to_string(),
+                    "order_type": order_type,
+                });
+                // Defaults are left out, as gateways without the flags expect
+                if time_in_force != TimeInForce::Gtc {
+                    data["time_in_force"] = serde_json::json!(time_in_force);
+                }
+                if post_only {
+                    data["post_only"] = true.into();
+                }
+                if reduce_only {
+                    data["reduce_only"] = true.into();
+                }
+                if let Some(id) = client_order_id {
+                    data["client_order_id"] = id.into();
+                }
+                data
+            }
+            IntentAction::CancelOrder { market_addr, sid } => serde_json::json!({
+                "market_addr": market_addr,
+                "sid": sid,
+            }),
+            IntentAction::AmendOrder {
+                market_addr,
+                sid,
+                price,
+                size,
+            } => serde_json::json!({
+                "market_addr": market_addr,
+                "sid": sid,
+                "price": price.to_string(),
+                "size": size.to_string(),
+            }),
+            IntentAction::Withdraw { asset_addr, amount } => serde_json::json!({
+                "asset_addr": asset_addr,
+                "amount": amount.to_string(),
+            }),
+            IntentAction::AdjustMargin {
+                market_addr,
+                amount,
+            } => serde_json::json!({
+                "market_addr": market_addr,
+                "amount": amount.to_string(),
+            }),
+            IntentAction::SetLeverage {
+                market_addr,
+                leverage,
+            } => serde_json::json!({
+                "market_addr": market_addr,
+                "leverage": leverage,
+            }),
+            IntentAction::PlaceTriggerOrder(order) => {
+                let mut data = serde_json::json!({
+                    "market_addr": order.market_addr,
+                    "side": order.side,
+                    "kind": order.kind,
+                    "trigger_price": order.trigger_price.to_string(),
+                    "trigger_type": order.trigger_type,
+                    "size": order.size.to_string(),
+                });
+                if let Some(price) = order.price {
+                    data["price"] = price.to_string().into();
+                }
+                if order.reduce_only {
+                    data["reduce_only"] = true.into();
+                }
+                data
+            }
+        };
+        ActionPayload::new(action_type, data)
+    }
+}
+
+impl TryFrom<&ActionPayload> for IntentAction {
+    type Error = crate::error::EkidenError;
+
+    /// Parse a payload back into a typed action
+    fn try_from(payload: &ActionPayload) -> std::result::Result<Self, Self::Error> {
+        fn field<T: std::str::FromStr + serde::de::DeserializeOwned>(
+            payload: &ActionPayload,
+            name: &str,
+        ) -> std::result::Result<T, crate::error::EkidenError> {
+            let value = payload.data.get(name).ok_or_else(|| {
+                crate::error::EkidenError::validation(format!(
+                    "{} action is missing {}",
+                    payload.action_type, name
+                ))
+            })?;
+            let parsed = match value {
+                serde_json::Value::String(s) => s.parse().ok(),
+                _ => None,
+            };
+            match parsed {
+                Some(parsed) => Ok(parsed),
+                None => serde_json::from_value(value.clone()).map_err(|_| {
+                    crate::error::EkidenError::validation(format!(
+                        "{} action has an invalid {}",
+                        payload.action_type, name
+                    ))
+                }),
+            }
+        }
+
+        fn optional<T: std::str::FromStr + serde::de::DeserializeOwned>(
+            payload: &ActionPayload,
+            name: &str,
+        ) -> std::result::Result<Option<T>, crate::error::EkidenError> {
+            match payload.data.get(name) {
+                Some(_) => field(payload, name).map(Some),
+                None => Ok(None),
+            }
+        }
+
+        match payload.action_type.as_str() {
+            "place_order" => Ok(Self::PlaceOrder {
+                market_addr: field(payload, "market_addr")?,
+                side: serde_json::from_value(payload.data["side"].clone())?,
+                order_type: serde_json::from_value(payload.data["order_type"].clone())?,
+                price: field(payload, "price")?,
+                size: field(payload, "size")?,
+                time_in_force: payload
+                    .data
+                    .get("time_in_force")
+                    .map(|tif| serde_json::from_value(tif.clone()))
+                    .transpose()?
+                    .unwrap_or_default(),
+                post_only: optional(payload, "post_only")?.unwrap_or(false),
+                reduce_only: optional(payload, "reduce_only")?.unwrap_or(false),
+                client_order_id: payload
+                    .data
+                    .get("client_order_id")
+                    .and_then(|id| id.as_str())
+                    .map(str::to_string),
+            }),
+            "cancel_order" => Ok(Self::CancelOrder {
+                market_addr: field(payload, "market_addr")?,
+                sid: field(payload, "sid")?,
+            }),
+            "amend_order" => Ok(Self::AmendOrder {
+                market_addr: field(payload, "market_addr")?,
+                sid: field(payload, "sid")?,
+                price: field(payload, "price")?,
+                size: field(payload, "size")?,
+            }),
+            "withdraw" => Ok(Self::Withdraw {
+                asset_addr: field(payload, "asset_addr")?,
+                amount: field(payload, "amount")?,
+            }),
+            "adjust_margin" => Ok(Self::AdjustMargin {
+                market_addr: field(payload, "market_addr")?,
+                amount: field(payload, "amount")?,
+            }),
+            "set_leverage" => Ok(Self::SetLeverage {
+                market_addr: field(payload, "market_addr")?,
+                leverage: field(payload, "leverage")?,
+            }),
+            "place_trigger_order" => Ok(Self::PlaceTriggerOrder(TriggerOrder {
+                market_addr: field(payload, "market_addr")?,
+                side: serde_json::from_value(payload.data["side"].clone())?,
+                kind: serde_json::from_value(payload.data["kind"].clone())?,
+                trigger_price: field(payload, "trigger_price")?,
+                trigger_type: payload
+                    .data
+                    .get("trigger_type")
+                    .map(|trigger_type| serde_json::from_value(trigger_type.clone()))
+                    .transpose()?
+                    .unwrap_or_default(),
+                price: optional(payload, "price")?,
+                size: field(payload, "size")?,
+                reduce_only: optional(payload, "reduce_only")?.unwrap_or(false),
+            })),
+            other => Err(crate::error::EkidenError::validation(format!(
+                "Unknown action type: {}",
+                other
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IntentOutput {
     pub action_type: String,
     pub result: serde_json::Value,
 }
 
+impl IntentOutput {
+    /// Get the order sid assigned by the gateway, if the output carries one
+    pub fn sid(&self) -> Option<&str> {
+        self.result.get("sid").and_then(|sid| sid.as_str())
+    }
+
+    /// Get the reason the action failed, if it did
+    pub fn error(&self) -> Option<&str> {
+        self.result.get("error").and_then(|error| error.as_str())
+    }
+
+    /// Get the gateway error code of a failed action, if it carries one
+    pub fn error_code(&self) -> Option<crate::error::ApiErrorCode> {
+        self.result
+            .get("code")
+            .and_then(|code| code.as_str())
+            .map(crate::error::ApiErrorCode::parse)
+    }
+}
+
+/// Default worst-price allowance of market orders, in basis points of the mark price
+pub const DEFAULT_SLIPPAGE_BPS: u32 = 50;
+
+/// Options of [`place_limit_order`](crate::EkidenClient::place_limit_order)
+/// and [`place_market_order`](crate::EkidenClient::place_market_order)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderOptions {
+    /// Set the market's leverage in the same intent, before the order
+    pub leverage: Option<u64>,
+    /// How far from the mark price a market order may fill, in basis points;
+    /// at most 10000
+    pub slippage_bps: u32,
+    pub time_in_force: TimeInForce,
+    /// Cancel the order rather than let it take liquidity
+    pub post_only: bool,
+    /// Only let the order reduce an existing position
+    pub reduce_only: bool,
+    /// Tag the order so it can be tracked before the gateway assigns a sid
+    pub client_order_id: Option<String>,
+}
+
+impl Default for OrderOptions {
+    fn default() -> Self {
+        Self {
+            leverage: None,
+            slippage_bps: DEFAULT_SLIPPAGE_BPS,
+            time_in_force: TimeInForce::Gtc,
+            post_only: false,
+            reduce_only: false,
+            client_order_id: None,
+        }
+    }
+}
+
+impl OrderOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the market's leverage along with the order
+    pub fn with_leverage(mut self, leverage: u64) -> Self {
+        self.leverage = Some(leverage);
+        self
+    }
+
+    /// Set the worst-price allowance of market orders
+    pub fn with_slippage_bps(mut self, slippage_bps: u32) -> Self {
+        self.slippage_bps = slippage_bps;
+        self
+    }
+
+    /// Set how long the order may rest on the book
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+
+    /// Make the order post-only
+    pub fn with_post_only(mut self, post_only: bool) -> Self {
+        self.post_only = post_only;
+        self
+    }
+
+    /// Make the order reduce-only
+    pub fn with_reduce_only(mut self, reduce_only: bool) -> Self {
+        self.reduce_only = reduce_only;
+        self
+    }
+
+    /// Tag the order with a client order id
+    pub fn with_client_order_id(mut self, id: impl Into<String>) -> Self {
+        self.client_order_id = Some(id.into());
+        self
+    }
+}
+
+/// An order accepted by the gateway
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlacedOrder {
+    /// Order id assigned by the gateway
+    pub sid: String,
+    /// Sequence number of the intent that placed the order
+    pub seq: u64,
+    pub market_addr: String,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    /// Limit price, or the worst fill price of a market order
+    pub price: u64,
+    pub size: u64,
+    pub client_order_id: Option<String>,
+    /// Status of the intent
+    pub status: String,
+}
+
+/// A trigger order accepted by the gateway
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlacedTrigger {
+    /// Order id assigned by the gateway
+    pub sid: String,
+    /// Sequence number of the intent that placed the order
+    pub seq: u64,
+    pub order: TriggerOrder,
+    /// Status of the intent
+    pub status: String,
+}
+
+/// An order after [`amend_order`](crate::EkidenClient::amend_order)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmendedOrder {
+    /// Sid of the order now resting on the book
+    pub sid: String,
+    /// Sequence number of the intent that amended the order
+    pub seq: u64,
+    pub market_addr: String,
+    pub price: u64,
+    pub size: u64,
+    /// Whether the order was cancelled and replaced, losing its queue priority
+    pub replaced: bool,
+    /// Status of the intent
+    pub status: String,
+}
+
 // ===== Deposit/Withdrawal Types =====
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -310,6 +1330,40 @@ pub struct DepositResponse {
     pub status: String,
 }
 
+impl DepositResponse {
+    /// Get the settlement state of the deposit
+    pub fn state(&self) -> TransferState {
+        TransferState::parse(&self.status)
+    }
+}
+
+/// Settlement state of a deposit or withdrawal, as reported by the gateway
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferState {
+    /// Indexed but not yet settled, or a status the SDK does not know
+    Pending,
+    /// Settled on the user's vault
+    Completed,
+    /// Failed on chain or rejected by the gateway
+    Failed,
+}
+
+impl TransferState {
+    /// Classify a gateway status, case-insensitively
+    ///
+    /// Only known settled and failed statuses are final; anything else is
+    /// still pending.
+    pub fn parse(status: &str) -> Self {
+        match status.to_ascii_lowercase().as_str() {
+            "completed" | "complete" | "credited" | "processed" | "success" | "finalized" => {
+                TransferState::Completed
+            }
+            "failed" | "rejected" | "reverted" | "cancelled" | "canceled" => TransferState::Failed,
+            _ => TransferState::Pending,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListDepositsParams {
     pub user_addr: Option<String>,
@@ -333,6 +1387,13 @@ pub struct WithdrawResponse {
     pub status: String,
 }
 
+impl WithdrawResponse {
+    /// Get the settlement state of the withdrawal
+    pub fn state(&self) -> TransferState {
+        TransferState::parse(&self.status)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListWithdrawsParams {
     pub user_addr: Option<String>,
@@ -389,32 +1450,265 @@ pub struct ListFundingRatesParams {
     pub pagination: Pagination,
 }
 
+// ===== Ticker Types =====
+
+/// Live market prices pushed on a ticker channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TickerResponse {
+    pub market_addr: String,
+    pub mark_price: u64,
+    pub oracle_price: u64,
+    /// Traded size over the last 24 hours
+    pub volume_24h: u64,
+    pub funding_rate: f64,
+    pub next_funding_time: u64,
+    pub timestamp: u64,
+}
+
+// ===== Liquidation Types =====
+
+/// A position closed by the liquidation engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidationResponse {
+    pub market_addr: String,
+    /// Owner of the liquidated position
+    pub user_addr: String,
+    /// Side of the liquidated position, "buy" for longs and "sell" for shorts
+    pub side: String,
+    pub size: u64,
+    /// Price the position was closed at
+    pub price: u64,
+    /// Price at which the position's margin is exhausted
+    pub bankruptcy_price: u64,
+    pub timestamp: u64,
+}
+
+// ===== Announcement Types =====
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnouncementKind {
+    System,
+    Listing,
+    Delisting,
+    Maintenance,
+    #[serde(other)]
+    Other,
+}
+
+impl AnnouncementKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnnouncementKind::System => "system",
+            AnnouncementKind::Listing => "listing",
+            AnnouncementKind::Delisting => "delisting",
+            AnnouncementKind::Maintenance => "maintenance",
+            AnnouncementKind::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnouncementResponse {
+    pub id: String,
+    pub kind: AnnouncementKind,
+    pub title: String,
+    pub message: String,
+    /// Markets the announcement applies to; empty means all markets
+    #[serde(default)]
+    pub market_addrs: Vec<String>,
+    /// Start of the announced window, in Unix milliseconds
+    pub starts_at: Option<u64>,
+    /// End of the announced window, in Unix milliseconds
+    pub ends_at: Option<u64>,
+    pub created_at: String,
+}
+
+impl AnnouncementResponse {
+    /// Whether the announcement applies to a market
+    pub fn affects(&self, market_addr: &str) -> bool {
+        self.market_addrs.is_empty()
+            || self
+                .market_addrs
+                .iter()
+                .any(|addr| addr.eq_ignore_ascii_case(market_addr))
+    }
+
+    /// Whether this is maintenance starting within `window_ms` of `now_ms`, or already under way
+    pub fn maintenance_within(&self, now_ms: u64, window_ms: u64) -> bool {
+        if self.kind != AnnouncementKind::Maintenance {
+            return false;
+        }
+        if self.ends_at.is_some_and(|ends_at| ends_at <= now_ms) {
+            return false;
+        }
+        self.starts_at.map_or(true, |starts_at| {
+            starts_at <= now_ms.saturating_add(window_ms)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListAnnouncementsParams {
+    pub kind: Option<AnnouncementKind>,
+    #[serde(flatten)]
+    pub pagination: Pagination,
+}
+
+// ===== Status Types =====
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceStatus {
+    #[serde(alias = "healthy", alias = "up")]
+    Ok,
+    Degraded,
+    #[serde(alias = "unhealthy")]
+    Down,
+    #[serde(other)]
+    Unknown,
+}
+
+impl ServiceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServiceStatus::Ok => "ok",
+            ServiceStatus::Degraded => "degraded",
+            ServiceStatus::Down => "down",
+            ServiceStatus::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentStatus {
+    pub status: ServiceStatus,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusResponse {
+    pub status: ServiceStatus,
+    pub version: Option<String>,
+    /// Health of gateway components, e.g. `matching_engine` or `database`
+    #[serde(default)]
+    pub components: HashMap<String, ComponentStatus>,
+}
+
+/// Result of a health check against the gateway
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    /// Round trip time of the status request
+    pub latency: Duration,
+    pub status: StatusResponse,
+    /// Whether the client's WebSocket is connected
+    pub websocket_connected: bool,
+}
+
+impl HealthReport {
+    /// Whether the gateway and every component it reports are up
+    pub fn is_ready(&self) -> bool {
+        self.status.status == ServiceStatus::Ok
+            && self
+                .status
+                .components
+                .values()
+                .all(|component| component.status == ServiceStatus::Ok)
+    }
+
+    /// Names of components that are not up, sorted
+    pub fn unhealthy_components(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .status
+            .components
+            .iter()
+            .filter(|(_, component)| component.status != ServiceStatus::Ok)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerTime {
+    /// Gateway time in milliseconds since the Unix epoch
+    #[serde(alias = "time", alias = "server_time")]
+    pub timestamp: u64,
+}
+
 // ===== WebSocket Types =====
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum WsRequest {
     #[serde(rename = "ping")]
-    Ping,
+    Ping {
+        /// Echoed in the pong
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+    },
     #[serde(rename = "subscribe")]
-    Subscribe { channel: String },
+    Subscribe {
+        channel: String,
+        /// Echoed in the acknowledgment or error
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+    },
     #[serde(rename = "unsubscribe")]
-    Unsubscribe { channel: String },
+    Unsubscribe {
+        channel: String,
+        /// Echoed in the acknowledgment or error
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum WsResponse {
     #[serde(rename = "pong")]
-    Pong,
+    Pong {
+        /// Id of the ping answered
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+    },
     #[serde(rename = "subscribed")]
-    Subscribed { channel: String },
+    Subscribed {
+        channel: String,
+        /// Id of the request acknowledged
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+    },
     #[serde(rename = "unsubscribed")]
-    Unsubscribed { channel: String },
+    Unsubscribed {
+        channel: String,
+        /// Id of the request acknowledged
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+    },
     #[serde(rename = "event")]
-    Event { channel: String, data: WsEvent },
+    Event {
+        channel: String,
+        /// Position of the event in its channel, for channels that number them
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seq: Option<u64>,
+        data: WsEvent,
+    },
     #[serde(rename = "error")]
-    Error { message: String },
+    Error {
+        message: String,
+        /// Id of the request that failed, if the error concerns one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+    },
+}
+
+impl WsResponse {
+    /// Parse a server frame, rejecting malformed input with an error
+    pub fn parse(text: &str) -> crate::error::Result<Self> {
+        Ok(serde_json::from_str(text)?)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -448,9 +1742,35 @@ pub enum WsEvent {
     PositionUpdate { position: PositionResponse },
     #[serde(rename = "balance_update")]
     BalanceUpdate { vault: VaultResponse },
+    /// A fill of one of the user's orders, sent on the user channel
+    #[serde(rename = "user_fill")]
+    UserFill { fill: FillResponse },
+    #[serde(rename = "announcement")]
+    Announcement { announcement: AnnouncementResponse },
+    /// Live update of the current candle on a candles channel
+    #[serde(rename = "candle")]
+    Candle { candle: CandleResponse },
+    /// Mark price, oracle price, volume and funding of a market
+    #[serde(rename = "ticker")]
+    Ticker { ticker: TickerResponse },
+    /// A position liquidated on a market
+    #[serde(rename = "liquidation")]
+    Liquidation { liquidation: LiquidationResponse },
+    /// Events were lost on a channel; a fresh snapshot has been requested
+    ///
+    /// Emitted by the client, never sent by the gateway. State built from the
+    /// channel should be rebuilt from the next snapshot.
+    #[serde(rename = "resync")]
+    Resync {
+        channel: String,
+        /// Sequence number that should have come next
+        expected: u64,
+        /// Sequence number that arrived instead
+        received: u64,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OrderbookLevel {
     pub price: u64,
     pub size: u64,
@@ -458,6 +1778,78 @@ pub struct OrderbookLevel {
 
 // ===== Request Configuration =====
 
+/// How often and how long to wait before retrying a failed request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further one
+    pub delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, delay: Duration) -> Self {
+        Self { max_retries, delay }
+    }
+
+    /// Never retry
+    pub fn none() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    /// Delay before retry number `retry`, counting from zero
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.delay.saturating_mul(1 << retry.min(16))
+    }
+}
+
+/// Per-call overrides of the client's request settings
+///
+/// Retries only happen for [retryable](crate::EkidenError::is_retryable)
+/// failures. A retried order submission may still have reached the gateway
+/// the first time, so enable them for intents only when that is acceptable.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestOptions {
+    /// Timeout instead of [`EkidenConfig::timeout`](crate::EkidenConfig::timeout)
+    pub timeout: Option<Duration>,
+    /// Retry policy; requests are not retried when `None`
+    pub retry: Option<RetryPolicy>,
+    /// Headers added to the request, replacing any with the same name
+    pub headers: HashMap<String, String>,
+}
+
+impl RequestOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn with_header<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.headers.insert(key.into(), value.into());
+        self
+    }
+
+    /// Combine with `overrides`, whose settings win where both have one
+    pub fn merge(&self, overrides: &RequestOptions) -> Self {
+        let mut headers = self.headers.clone();
+        headers.extend(overrides.headers.clone());
+        Self {
+            timeout: overrides.timeout.or(self.timeout),
+            retry: overrides.retry.or(self.retry),
+            headers,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RequestConfig {
     pub method: reqwest::Method,
@@ -465,6 +1857,7 @@ pub struct RequestConfig {
     pub query: Option<HashMap<String, String>>,
     pub body: Option<serde_json::Value>,
     pub auth_required: bool,
+    pub options: RequestOptions,
 }
 
 impl Default for RequestConfig {
@@ -475,6 +1868,7 @@ impl Default for RequestConfig {
             query: None,
             body: None,
             auth_required: false,
+            options: RequestOptions::default(),
         }
     }
 }
@@ -524,6 +1918,11 @@ impl RequestConfig {
         self.headers.insert(key.into(), value.into());
         self
     }
+
+    pub fn with_options(mut self, options: RequestOptions) -> Self {
+        self.options = options;
+        self
+    }
 }
 
 // ===== Utility Functions =====
@@ -612,6 +2011,19 @@ impl ToQueryParams for ListPositionsParams {
     }
 }
 
+impl ToQueryParams for GetOrderbookParams {
+    fn to_query_params(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        params.insert("market_addr".to_string(), self.market_addr.clone());
+
+        if let Some(depth) = self.depth {
+            params.insert("depth".to_string(), depth.to_string());
+        }
+
+        params
+    }
+}
+
 impl ToQueryParams for GetUserLeverageParams {
     fn to_query_params(&self) -> HashMap<String, String> {
         let mut params = HashMap::new();
@@ -710,3 +2122,15 @@ impl ToQueryParams for ListWithdrawsParams {
         params
     }
 }
+
+impl ToQueryParams for ListAnnouncementsParams {
+    fn to_query_params(&self) -> HashMap<String, String> {
+        let mut params = self.pagination.to_query_params();
+
+        if let Some(kind) = self.kind {
+            params.insert("kind".to_string(), kind.as_str().to_string());
+        }
+
+        params
+    }
+}
@@ -0,0 +1,297 @@
+//! CCXT-style unified market, order, trade and position structures.
+//!
+//! Gateway responses carry integer amounts: sizes in base units and prices in
+//! quote units per whole base token. The unified structs use decimal values
+//! (`size / 10^base_decimals`, `price / 10^quote_decimals`) and CCXT field and
+//! status names, so they serialize the way bots ported from CCXT expect.
+//! Conversions that need decimals take the market alongside the response.
+
+use crate::types::{FillResponse, MarketResponse, OrderResponse, PositionResponse};
+use serde::{Deserialize, Serialize};
+
+/// Decimal places for amounts and prices
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Precision {
+    pub amount: u8,
+    pub price: u8,
+}
+
+/// Optional lower and upper bound
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MinMax {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Trading limits for a market
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Limits {
+    pub amount: MinMax,
+    pub leverage: MinMax,
+}
+
+/// Unified market description
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnifiedMarket {
+    /// Exchange id (the market address)
+    pub id: String,
+    /// Unified symbol, e.g. `BTC/USD:USD`
+    pub symbol: String,
+    pub base: String,
+    pub quote: String,
+    pub settle: String,
+    /// Always `swap`: every Ekiden market is a perpetual
+    #[serde(rename = "type")]
+    pub market_type: String,
+    pub swap: bool,
+    pub contract: bool,
+    pub linear: bool,
+    pub active: bool,
+    pub precision: Precision,
+    pub limits: Limits,
+    pub maintenance_margin_rate: f64,
+    pub initial_margin_rate: f64,
+}
+
+/// Unified order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnifiedOrder {
+    pub id: String,
+    pub timestamp: u64,
+    pub symbol: String,
+    #[serde(rename = "type")]
+    pub order_type: String,
+    pub side: String,
+    pub price: f64,
+    pub amount: f64,
+    pub cost: f64,
+    /// One of `open`, `closed`, `canceled`, `expired` or `rejected`
+    pub status: String,
+    pub leverage: u64,
+}
+
+/// Unified trade (fill)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnifiedTrade {
+    pub id: String,
+    pub order: String,
+    pub timestamp: u64,
+    pub symbol: String,
+    pub side: String,
+    pub price: f64,
+    pub amount: f64,
+    pub cost: f64,
+}
+
+/// Unified position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnifiedPosition {
+    pub symbol: String,
+    /// `long` or `short`
+    pub side: String,
+    pub contracts: f64,
+    pub entry_price: f64,
+    pub mark_price: f64,
+    pub notional: f64,
+    pub leverage: u64,
+    pub unrealized_pnl: f64,
+    pub liquidation_price: f64,
+    pub collateral: f64,
+}
+
+/// Split an exchange symbol such as `BTC-USD` or `BTC/USD` into base and quote
+fn split_symbol(symbol: &str) -> (String, String) {
+    match symbol.split_once(['-', '/', '_']) {
+        Some((base, quote)) => (base.to_string(), quote.to_string()),
+        None => (symbol.to_string(), String::new()),
+    }
+}
+
+/// Build the unified `BASE/QUOTE:SETTLE` symbol for a market
+pub fn unified_symbol(market: &MarketResponse) -> String {
+    let (base, quote) = split_symbol(&market.symbol);
+    if quote.is_empty() {
+        return base;
+    }
+    format!("{}/{}:{}", base, quote, quote)
+}
+
+/// Map a gateway order status to the CCXT status vocabulary
+pub fn unified_status(status: &str) -> String {
+    match status.to_ascii_lowercase().as_str() {
+        "open" | "new" | "placed" | "pending" | "partially_filled" | "partial" => "open",
+        "filled" | "closed" => "closed",
+        "cancelled" | "canceled" => "canceled",
+        "expired" => "expired",
+        "rejected" | "failed" => "rejected",
+        other => return other.to_string(),
+    }
+    .to_string()
+}
+
+fn scale(value: u64, decimals: u8) -> f64 {
+    value as f64 / 10f64.powi(decimals as i32)
+}
+
+fn scale_signed(value: i64, decimals: u8) -> f64 {
+    value as f64 / 10f64.powi(decimals as i32)
+}
+
+impl From<&MarketResponse> for UnifiedMarket {
+    fn from(market: &MarketResponse) -> Self {
+        let (base, quote) = split_symbol(&market.symbol);
+        Self {
+            id: market.addr.clone(),
+            symbol: unified_symbol(market),
+            base,
+            settle: quote.clone(),
+            quote,
+            market_type: "swap".to_string(),
+            swap: true,
+            contract: true,
+            linear: true,
+            active: true,
+            precision: Precision {
+                amount: market.base_decimals,
+                price: market.quote_decimals,
+            },
+            limits: Limits {
+                amount: MinMax {
+                    min: Some(scale(market.min_order_size, market.base_decimals)),
+                    max: None,
+                },
+                leverage: MinMax {
+                    min: Some(1.0),
+                    max: Some(market.max_leverage as f64),
+                },
+            },
+            maintenance_margin_rate: market.maintenance_margin_ratio,
+            initial_margin_rate: market.initial_margin_ratio,
+        }
+    }
+}
+
+impl From<(&OrderResponse, &MarketResponse)> for UnifiedOrder {
+    fn from((order, market): (&OrderResponse, &MarketResponse)) -> Self {
+        let price = scale(order.price, market.quote_decimals);
+        let amount = scale(order.size, market.base_decimals);
+        Self {
+            id: order.sid.clone(),
+            timestamp: order.timestamp,
+            symbol: unified_symbol(market),
+            order_type: order.order_type.to_ascii_lowercase(),
+            side: order.side.to_ascii_lowercase(),
+            price,
+            amount,
+            cost: price * amount,
+            status: unified_status(&order.status),
+            leverage: order.leverage,
+        }
+    }
+}
+
+impl From<(&FillResponse, &MarketResponse)> for UnifiedTrade {
+    fn from((fill, market): (&FillResponse, &MarketResponse)) -> Self {
+        let price = scale(fill.price, market.quote_decimals);
+        let amount = scale(fill.size, market.base_decimals);
+        Self {
+            id: fill.seq.to_string(),
+            order: fill.sid.clone(),
+            timestamp: fill.timestamp,
+            symbol: unified_symbol(market),
+            side: fill.side.to_ascii_lowercase(),
+            price,
+            amount,
+            cost: price * amount,
+        }
+    }
+}
+
+impl From<(&PositionResponse, &MarketResponse)> for UnifiedPosition {
+    fn from((position, market): (&PositionResponse, &MarketResponse)) -> Self {
+        let contracts = scale(position.size, market.base_decimals);
+        let mark_price = scale(position.mark_price, market.quote_decimals);
+        Self {
+            symbol: unified_symbol(market),
+            side: if position.signed_size() < 0 {
+                "short"
+            } else {
+                "long"
+            }
+            .to_string(),
+            contracts,
+            entry_price: scale(position.entry_price, market.quote_decimals),
+            mark_price,
+            notional: contracts * mark_price,
+            leverage: position.leverage,
+            unrealized_pnl: scale_signed(position.unrealized_pnl, market.quote_decimals),
+            liquidation_price: scale(position.liquidation_price, market.quote_decimals),
+            collateral: scale(position.margin, market.quote_decimals),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market() -> MarketResponse {
+        MarketResponse {
+            base_decimals: 3,
+            quote_decimals: 2,
+            min_order_size: 10,
+            ..crate::test_utils::market()
+        }
+    }
+
+    #[test]
+    fn test_market_conversion() {
+        let unified = UnifiedMarket::from(&market());
+        assert_eq!(unified.id, "0x1");
+        assert_eq!(unified.symbol, "BTC/USD:USD");
+        assert_eq!(unified.base, "BTC");
+        assert_eq!(unified.settle, "USD");
+        assert_eq!(unified.limits.amount.min, Some(0.01));
+        assert_eq!(unified.limits.leverage.max, Some(20.0));
+
+        let json = serde_json::to_value(&unified).unwrap();
+        assert_eq!(json["type"], "swap");
+        assert!(json.get("maintenanceMarginRate").is_some());
+    }
+
+    #[test]
+    fn test_order_conversion() {
+        let order: OrderResponse = serde_json::from_value(serde_json::json!({
+            "sid": "abc",
+            "side": "BUY",
+            "size": 2500,
+            "price": 6500000,
+            "leverage": 5,
+            "type": "limit",
+            "status": "cancelled",
+            "user_addr": "0x4",
+            "market_addr": "0x1",
+            "seq": 7,
+            "timestamp": 1
+        }))
+        .unwrap();
+
+        let unified = UnifiedOrder::from((&order, &market()));
+        assert_eq!(unified.side, "buy");
+        assert_eq!(unified.price, 65_000.0);
+        assert_eq!(unified.amount, 2.5);
+        assert_eq!(unified.cost, 162_500.0);
+        assert_eq!(unified.status, "canceled");
+    }
+
+    #[test]
+    fn test_unified_status() {
+        assert_eq!(unified_status("partially_filled"), "open");
+        assert_eq!(unified_status("FILLED"), "closed");
+        assert_eq!(unified_status("something"), "something");
+    }
+}
@@ -1,4 +1,4 @@
-use crate::error::{EkidenError, Result};
+use crate::error::{CryptoError, Result};
 use aptos_crypto::{
     ed25519::{Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature},
     PrivateKey, Signature, SigningKey, Uniform, ValidCryptoMaterialStringExt,
@@ -11,32 +11,34 @@ pub struct Crypto;
 
 impl Crypto {
     /// Generate an Ed25519 signature for the given message using the private key
-    pub fn sign_message(message: &[u8], private_key: Ed25519PrivateKey) -> anyhow::Result<String> {
+    pub fn sign_message(message: &[u8], private_key: Ed25519PrivateKey) -> Result<String> {
         let signature = private_key.sign_arbitrary_message(message);
-        signature.to_encoded_string()
+        signature
+            .to_encoded_string()
+            .map_err(|e| CryptoError::Encoding(format!("signature: {}", e)).into())
     }
 
     /// Verify an Ed25519 signature
     pub fn verify_signature(message: &[u8], signature: &str, public_key: &str) -> Result<bool> {
         let signature_bytes = hex::decode(signature.strip_prefix("0x").unwrap_or(signature))
-            .map_err(|_| EkidenError::crypto("Invalid signature hex format"))?;
+            .map_err(|_| CryptoError::InvalidSignature("not valid hex".to_string()))?;
 
         let public_key_bytes = hex::decode(public_key.strip_prefix("0x").unwrap_or(public_key))
-            .map_err(|_| EkidenError::crypto("Invalid public key hex format"))?;
+            .map_err(|_| CryptoError::InvalidPublicKey("not valid hex".to_string()))?;
 
         if signature_bytes.len() != 64 {
-            return Err(EkidenError::crypto("Signature must be 64 bytes"));
+            return Err(CryptoError::InvalidSignature("must be 64 bytes".to_string()).into());
         }
 
         if public_key_bytes.len() != 32 {
-            return Err(EkidenError::crypto("Public key must be 32 bytes"));
+            return Err(CryptoError::InvalidPublicKey("must be 32 bytes".to_string()).into());
         }
 
         let signature = Ed25519Signature::try_from(signature_bytes.as_slice())
-            .map_err(|_| EkidenError::crypto("Invalid signature format"))?;
+            .map_err(|e| CryptoError::InvalidSignature(e.to_string()))?;
 
         let public_key = Ed25519PublicKey::try_from(public_key_bytes.as_slice())
-            .map_err(|_| EkidenError::crypto("Invalid public key format"))?;
+            .map_err(|e| CryptoError::InvalidPublicKey(e.to_string()))?;
 
         match signature.verify_arbitrary_msg(message, &public_key) {
             Ok(_) => Ok(true),
@@ -47,7 +49,7 @@ impl Crypto {
     /// Generate a private key from a private key hex string
     pub fn private_key_from_hex(private_key_hex: &str) -> Result<Ed25519PrivateKey> {
         let private_key = Ed25519PrivateKey::from_encoded_string(&private_key_hex)
-            .map_err(|e| EkidenError::crypto(&format!("Invalid private key hex: {}", e)))?;
+            .map_err(|e| CryptoError::InvalidPrivateKey(e.to_string()))?;
         Ok(private_key)
     }
 
@@ -61,10 +63,10 @@ impl Crypto {
     pub fn generate_address_from_public_key(public_key: &str) -> Result<String> {
         let public_key = public_key.strip_prefix("0x").unwrap_or(public_key);
         let public_key_bytes = hex::decode(public_key)
-            .map_err(|_| EkidenError::crypto("Invalid public key hex format"))?;
+            .map_err(|_| CryptoError::InvalidPublicKey("not valid hex".to_string()))?;
 
         if public_key_bytes.len() != 32 {
-            return Err(EkidenError::crypto("Public key must be 32 bytes"));
+            return Err(CryptoError::InvalidPublicKey("must be 32 bytes".to_string()).into());
         }
 
         // Hash the public key using Keccak256
@@ -89,14 +91,36 @@ impl Crypto {
         let hash = Self::keccak256(data);
         hex::encode(hash)
     }
+
+    /// Short, non-reversible fingerprint of key material, safe to log
+    pub fn fingerprint(data: &[u8]) -> String {
+        Self::keccak256_hex(data)[..8].to_string()
+    }
+}
+
+/// Debug wrapper printing a secret string as its fingerprint
+pub(crate) struct Redacted<'a>(pub &'a str);
+
+impl std::fmt::Debug for Redacted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<redacted {}>", Crypto::fingerprint(self.0.as_bytes()))
+    }
 }
 
 /// Key pair for signing operations
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct KeyPair {
     private_key: Ed25519PrivateKey,
 }
 
+impl std::fmt::Debug for KeyPair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("fingerprint", &self.fingerprint())
+            .finish_non_exhaustive()
+    }
+}
+
 impl KeyPair {
     /// Create a new key pair from a private key hex string
     pub fn from_private_key(private_key_hex: &str) -> Result<Self> {
@@ -117,14 +141,21 @@ impl KeyPair {
 
     /// Get the public key as hex string
     pub fn public_key(&self) -> String {
-        self.private_key.public_key().to_encoded_string().unwrap()
+        format!(
+            "0x{}",
+            hex::encode(self.private_key.public_key().to_bytes())
+        )
+    }
+
+    /// Get a short fingerprint of the public key for logs
+    pub fn fingerprint(&self) -> String {
+        Crypto::fingerprint(self.public_key().as_bytes())
     }
 
     /// Sign a message
     pub fn sign(&self, message: &[u8]) -> String {
         let signature = self.private_key.sign_arbitrary_message(message);
-        // hex::encode(signature.to_bytes())
-        signature.to_encoded_string().unwrap()
+        format!("0x{}", hex::encode(signature.to_bytes()))
     }
 
     /// Sign the authorization message "AUTHORIZE"
@@ -156,20 +187,18 @@ pub mod format {
         hex_str.strip_prefix("0x").unwrap_or(hex_str)
     }
 
-    /// Validate that a string is a valid hex address
-    pub fn validate_address(address: &str) -> Result<()> {
-        let address = strip_hex_prefix(address);
-
-        if address.len() != 40 {
-            return Err(EkidenError::validation(
-                "Address must be 40 hex characters (20 bytes)",
-            ));
-        }
+    /// Hex length of a 20-byte address
+    const ADDRESS_HEX_LEN: usize = 40;
 
-        hex::decode(address)
-            .map_err(|_| EkidenError::validation("Invalid hex characters in address"))?;
+    /// Hex length of a 32-byte Aptos address
+    const APTOS_ADDRESS_HEX_LEN: usize = 64;
 
-        Ok(())
+    /// Validate that a string is a valid hex address
+    ///
+    /// Accepts 20-byte (40 hex) and 32-byte Aptos (64 hex) addresses, plus
+    /// `0x`-prefixed Aptos short forms such as `0x1`.
+    pub fn validate_address(address: &str) -> Result<()> {
+        normalize_address(address).map(|_| ())
     }
 
     /// Validate that a string is a valid hex public key
@@ -205,9 +234,34 @@ pub mod format {
     }
 
     /// Normalize an address (lowercase, with 0x prefix)
+    ///
+    /// Aptos short forms are zero-padded to 32 bytes, so `0x1` becomes
+    /// `0x0000…0001`. 20-byte and full 32-byte addresses keep their length.
     pub fn normalize_address(address: &str) -> Result<String> {
-        validate_address(address)?;
-        Ok(ensure_hex_prefix(&strip_hex_prefix(address).to_lowercase()))
+        let hex_str = strip_hex_prefix(address);
+
+        if hex_str.is_empty() || hex_str.len() > APTOS_ADDRESS_HEX_LEN {
+            return Err(EkidenError::validation(
+                "Address must be 40 hex characters (20 bytes) or up to 64 (32 bytes)",
+            ));
+        }
+
+        if !hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(EkidenError::validation("Invalid hex characters in address"));
+        }
+
+        let hex_str = hex_str.to_lowercase();
+        match hex_str.len() {
+            ADDRESS_HEX_LEN | APTOS_ADDRESS_HEX_LEN => Ok(format!("0x{}", hex_str)),
+            _ if address.starts_with("0x") => Ok(format!(
+                "0x{:0>width$}",
+                hex_str,
+                width = APTOS_ADDRESS_HEX_LEN
+            )),
+            _ => Err(EkidenError::validation(
+                "Short-form addresses must start with 0x",
+            )),
+        }
     }
 
     /// Normalize a public key (lowercase, with 0x prefix)
@@ -225,11 +279,80 @@ pub mod format {
             &strip_hex_prefix(signature).to_lowercase(),
         ))
     }
+
+    /// Serialize `data` to canonical JSON
+    ///
+    /// Object keys are sorted by their UTF-8 bytes and no whitespace is
+    /// written; strings and numbers are written as by `serde_json`. The output
+    /// therefore does not depend on struct field or map insertion order.
+    pub fn canonical_json<T: serde::Serialize>(data: &T) -> Result<String> {
+        let mut out = String::new();
+        write_canonical(&serde_json::to_value(data)?, &mut out)?;
+        Ok(out)
+    }
+
+    fn write_canonical(value: &serde_json::Value, out: &mut String) -> Result<()> {
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                out.push('{');
+                for (i, (key, value)) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&serde_json::to_string(key)?);
+                    out.push(':');
+                    write_canonical(value, out)?;
+                }
+                out.push('}');
+            }
+            serde_json::Value::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_canonical(item, out)?;
+                }
+                out.push(']');
+            }
+            scalar => out.push_str(&serde_json::to_string(scalar)?),
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::EkidenError;
+
+    #[test]
+    fn test_canonical_json_sorts_keys() {
+        #[derive(serde::Serialize)]
+        struct Unordered {
+            b: u64,
+            a: Vec<serde_json::Value>,
+        }
+        let data = Unordered {
+            b: 1,
+            a: vec![serde_json::json!({ "z": "x y", "m": null, "é": 2, "A": true })],
+        };
+        assert_eq!(
+            format::canonical_json(&data).unwrap(),
+            r#"{"a":[{"A":true,"m":null,"z":"x y","é":2}],"b":1}"#
+        );
+    }
+
+    #[test]
+    fn test_key_pair_debug_is_redacted() {
+        let key_pair = KeyPair::generate();
+        let debug = format!("{:?}", key_pair);
+        let private_key = key_pair.private_key();
+        assert!(!debug.contains(format::strip_hex_prefix(&private_key)));
+        assert!(debug.contains(&key_pair.fingerprint()));
+    }
 
     #[test]
     fn test_key_pair_generation() {
@@ -265,13 +388,52 @@ mod tests {
         assert!(is_valid);
     }
 
+    #[test]
+    fn test_crypto_errors_are_typed() {
+        let key_pair = KeyPair::generate();
+        let signature =
+            Crypto::sign_message(b"payload", key_pair.get_private_key().clone()).unwrap();
+        assert_eq!(signature, key_pair.sign(b"payload"));
+
+        assert!(matches!(
+            Crypto::verify_signature(b"payload", "0x1234", &key_pair.public_key()),
+            Err(EkidenError::Crypto(CryptoError::InvalidSignature(_)))
+        ));
+        assert!(matches!(
+            Crypto::generate_address_from_public_key("0xzz"),
+            Err(EkidenError::Crypto(CryptoError::InvalidPublicKey(_)))
+        ));
+        assert!(matches!(
+            KeyPair::from_private_key("0x1234"),
+            Err(EkidenError::Crypto(CryptoError::InvalidPrivateKey(_)))
+        ));
+    }
+
     #[test]
     fn test_format_validation() {
         // Valid address
         assert!(format::validate_address("0x1234567890abcdef1234567890abcdef12345678").is_ok());
 
-        // Invalid address (too short)
-        assert!(format::validate_address("0x123").is_err());
+        // 32-byte Aptos address and its short form
+        let aptos = "0x2245549db5a39c5644f4ff69aaab297df252649b8b59e18a9130d48f01d09ea";
+        assert!(format::validate_address(aptos).is_ok());
+        assert_eq!(
+            format::normalize_address(aptos).unwrap(),
+            "0x02245549db5a39c5644f4ff69aaab297df252649b8b59e18a9130d48f01d09ea"
+        );
+        assert_eq!(
+            format::normalize_address("0x1").unwrap(),
+            format!("0x{:0>64}", "1")
+        );
+        assert_eq!(
+            format::normalize_address("1234567890ABCDEF1234567890abcdef12345678").unwrap(),
+            "0x1234567890abcdef1234567890abcdef12345678"
+        );
+
+        // Invalid address (empty, unprefixed short form, too long)
+        assert!(format::validate_address("0x").is_err());
+        assert!(format::validate_address("123").is_err());
+        assert!(format::validate_address(&format!("0x{}", "a".repeat(65))).is_err());
 
         // Invalid address (invalid hex)
         assert!(format::validate_address("0xgg34567890abcdef1234567890abcdef12345678").is_err());
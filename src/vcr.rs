@@ -0,0 +1,561 @@
+//! VCR-style recording and replay of gateway traffic.
+//!
+//! Enabled with the `vcr` feature. A [`Vcr`] in record mode forwards requests
+//! to the real transports and writes every HTTP exchange and text WebSocket
+//! frame to a JSON cassette; in replay mode it serves them back without any
+//! network access:
+//!
+//! ```rust,no_run
+//! # async fn run() -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::vcr::Vcr;
+//! use ekiden_rust_sdk::EkidenClientBuilder;
+//!
+//! // Records on the first run, replays once the cassette exists
+//! let vcr = Vcr::auto("tests/fixtures/markets.json")?;
+//! let client = EkidenClientBuilder::new()
+//!     .transport(vcr.transport())
+//!     .ws_connector(vcr.connector())
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! HTTP requests are matched by method and URL, in recorded order. Replayed
+//! WebSocket frames are released as the client sends the frames that preceded
+//! them, so subscriptions are in place before their events arrive. Request
+//! headers are never recorded, but response bodies are: cassettes may contain
+//! bearer tokens from `authorize` and should not be committed from production
+//! accounts.
+
+use crate::config::EkidenConfig;
+use crate::error::{EkidenError, Result};
+use crate::transport::{
+    HttpRequest, HttpResponse, HttpTransport, ReqwestTransport, TungsteniteConnector, WsConnector,
+    WsFrameSink, WsFrameStream,
+};
+use futures_util::future::BoxFuture;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::warn;
+use url::Url;
+
+/// A recorded HTTP request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+/// A recorded HTTP response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// UTF-8 body, or hex when `binary` is set
+    pub body: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub binary: bool,
+}
+
+/// One HTTP request and its response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// Direction of a WebSocket frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameDirection {
+    Sent,
+    Received,
+}
+
+/// A recorded text WebSocket frame
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WsFrame {
+    pub direction: FrameDirection,
+    pub text: String,
+}
+
+/// Recorded traffic as stored on disk
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    #[serde(default)]
+    pub interactions: Vec<Interaction>,
+    #[serde(default)]
+    pub ws_frames: Vec<WsFrame>,
+}
+
+impl Cassette {
+    /// Load a cassette from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Write the cassette as pretty-printed JSON, creating parent directories
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+impl From<&HttpResponse> for RecordedResponse {
+    fn from(response: &HttpResponse) -> Self {
+        let (body, binary) = match std::str::from_utf8(&response.body) {
+            Ok(text) => (text.to_string(), false),
+            Err(_) => (hex::encode(&response.body), true),
+        };
+        Self {
+            status: response.status,
+            headers: response
+                .headers
+                .iter()
+                .filter(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+                .cloned()
+                .collect(),
+            body,
+            binary,
+        }
+    }
+}
+
+impl RecordedResponse {
+    fn to_response(&self) -> Result<HttpResponse> {
+        let body = if self.binary {
+            hex::decode(&self.body)
+                .map_err(|e| EkidenError::general(format!("Invalid cassette body: {}", e)))?
+        } else {
+            self.body.clone().into_bytes()
+        };
+        Ok(HttpResponse {
+            status: self.status,
+            headers: self.headers.clone(),
+            body,
+        })
+    }
+}
+
+enum Mode {
+    Record {
+        transport: Arc<dyn HttpTransport>,
+        connector: Arc<dyn WsConnector>,
+    },
+    Replay {
+        /// Which interactions were already served
+        used: Mutex<Vec<bool>>,
+        /// Frames not yet replayed
+        frames: Mutex<VecDeque<WsFrame>>,
+        /// Signalled whenever the client sends a frame
+        sent: Notify,
+    },
+}
+
+struct Inner {
+    path: PathBuf,
+    mode: Mode,
+    cassette: Mutex<Cassette>,
+}
+
+impl Inner {
+    fn is_recording(&self) -> bool {
+        matches!(self.mode, Mode::Record { .. })
+    }
+
+    fn save(&self) -> Result<()> {
+        self.cassette
+            .lock()
+            .map_err(|_| EkidenError::general("Cassette lock poisoned"))?
+            .save(&self.path)
+    }
+
+    fn record_frame(&self, direction: FrameDirection, message: &Message) {
+        if let Message::Text(text) = message {
+            if let Ok(mut cassette) = self.cassette.lock() {
+                cassette.ws_frames.push(WsFrame {
+                    direction,
+                    text: text.to_string(),
+                });
+            }
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if self.is_recording() {
+            if let Err(e) = self.save() {
+                warn!("Failed to save cassette {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+/// Records or replays gateway traffic through a cassette file
+#[derive(Clone)]
+pub struct Vcr {
+    inner: Arc<Inner>,
+}
+
+impl std::fmt::Debug for Vcr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Vcr")
+            .field("path", &self.inner.path)
+            .field("recording", &self.inner.is_recording())
+            .finish()
+    }
+}
+
+impl Vcr {
+    /// Record through the default reqwest and tungstenite transports
+    pub fn record<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let transport = Arc::new(ReqwestTransport::new(&EkidenConfig::default())?);
+        Ok(Self::record_with(
+            path,
+            transport,
+            Arc::new(TungsteniteConnector),
+        ))
+    }
+
+    /// Record through the given transports
+    pub fn record_with<P: Into<PathBuf>>(
+        path: P,
+        transport: Arc<dyn HttpTransport>,
+        connector: Arc<dyn WsConnector>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                path: path.into(),
+                mode: Mode::Record {
+                    transport,
+                    connector,
+                },
+                cassette: Mutex::new(Cassette::default()),
+            }),
+        }
+    }
+
+    /// Replay a previously recorded cassette
+    pub fn replay<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+        let cassette = Cassette::load(&path)?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                mode: Mode::Replay {
+                    used: Mutex::new(vec![false; cassette.interactions.len()]),
+                    frames: Mutex::new(cassette.ws_frames.iter().cloned().collect()),
+                    sent: Notify::new(),
+                },
+                path,
+                cassette: Mutex::new(cassette),
+            }),
+        })
+    }
+
+    /// Replay the cassette if it exists, otherwise record it
+    pub fn auto<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+        if path.exists() {
+            Self::replay(path)
+        } else {
+            Self::record(path)
+        }
+    }
+
+    /// Whether traffic is being recorded
+    pub fn is_recording(&self) -> bool {
+        self.inner.is_recording()
+    }
+
+    /// HTTP transport to pass to [`EkidenClientBuilder::transport`](crate::EkidenClientBuilder::transport)
+    pub fn transport(&self) -> Arc<dyn HttpTransport> {
+        Arc::new(VcrTransport {
+            inner: self.inner.clone(),
+        })
+    }
+
+    /// WebSocket connector to pass to [`EkidenClientBuilder::ws_connector`](crate::EkidenClientBuilder::ws_connector)
+    pub fn connector(&self) -> Arc<dyn WsConnector> {
+        Arc::new(VcrConnector {
+            inner: self.inner.clone(),
+        })
+    }
+
+    /// Write recorded traffic now; also done when the last handle is dropped
+    pub fn save(&self) -> Result<()> {
+        if self.inner.is_recording() {
+            self.inner.save()?;
+        }
+        Ok(())
+    }
+}
+
+struct VcrTransport {
+    inner: Arc<Inner>,
+}
+
+impl std::fmt::Debug for VcrTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VcrTransport")
+            .field("path", &self.inner.path)
+            .finish()
+    }
+}
+
+impl HttpTransport for VcrTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            let recorded = RecordedRequest {
+                method: request.method.to_string(),
+                url: request.url.to_string(),
+                body: request
+                    .body
+                    .as_ref()
+                    .map(|b| String::from_utf8_lossy(b).into_owned()),
+            };
+
+            match &self.inner.mode {
+                Mode::Record { transport, .. } => {
+                    let response = transport.send(request).await?;
+                    if let Ok(mut cassette) = self.inner.cassette.lock() {
+                        cassette.interactions.push(Interaction {
+                            request: recorded,
+                            response: RecordedResponse::from(&response),
+                        });
+                    }
+                    Ok(response)
+                }
+                Mode::Replay { used, .. } => {
+                    let cassette = self
+                        .inner
+                        .cassette
+                        .lock()
+                        .map_err(|_| EkidenError::general("Cassette lock poisoned"))?;
+                    let mut used = used
+                        .lock()
+                        .map_err(|_| EkidenError::general("Cassette lock poisoned"))?;
+                    let index = cassette
+                        .interactions
+                        .iter()
+                        .zip(used.iter())
+                        .position(|(interaction, used)| {
+                            !used
+                                && interaction.request.method == recorded.method
+                                && interaction.request.url == recorded.url
+                        })
+                        .ok_or_else(|| {
+                            EkidenError::general(format!(
+                                "No recorded response for {} {}",
+                                recorded.method, recorded.url
+                            ))
+                        })?;
+                    used[index] = true;
+                    cassette.interactions[index].response.to_response()
+                }
+            }
+        })
+    }
+}
+
+struct VcrConnector {
+    inner: Arc<Inner>,
+}
+
+impl std::fmt::Debug for VcrConnector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VcrConnector")
+            .field("path", &self.inner.path)
+            .finish()
+    }
+}
+
+impl WsConnector for VcrConnector {
+    fn connect<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, Result<(WsFrameSink, WsFrameStream)>> {
+        Box::pin(async move {
+            match &self.inner.mode {
+                Mode::Record { connector, .. } => {
+                    let (sink, stream) = connector.connect(url).await?;
+
+                    let inner = self.inner.clone();
+                    let sink: WsFrameSink = Box::pin(sink.with(move |message: Message| {
+                        inner.record_frame(FrameDirection::Sent, &message);
+                        futures_util::future::ready(Ok::<_, EkidenError>(message))
+                    }));
+
+                    let inner = self.inner.clone();
+                    let stream: WsFrameStream = stream
+                        .inspect(move |message| {
+                            if let Ok(message) = message {
+                                inner.record_frame(FrameDirection::Received, message);
+                            }
+                        })
+                        .boxed();
+                    Ok((sink, stream))
+                }
+                Mode::Replay { .. } => Ok(replay_connection(self.inner.clone())),
+            }
+        })
+    }
+}
+
+/// Serve recorded frames, holding each received frame back until every
+/// frame sent before it in the recording has been sent again
+fn replay_connection(inner: Arc<Inner>) -> (WsFrameSink, WsFrameStream) {
+    let sink_inner = inner.clone();
+    let sink: WsFrameSink = Box::pin(futures_util::sink::unfold(
+        sink_inner,
+        |inner, message: Message| async move {
+            if let Mode::Replay { frames, sent, .. } = &inner.mode {
+                if let Message::Text(text) = &message {
+                    if let Ok(mut frames) = frames.lock() {
+                        if frames
+                            .front()
+                            .is_some_and(|f| f.direction == FrameDirection::Sent)
+                        {
+                            let expected = frames.pop_front().map(|f| f.text);
+                            if expected.as_deref() != Some(text.as_str()) {
+                                warn!("Replayed frame differs from recording: {}", text);
+                            }
+                        }
+                    }
+                }
+                sent.notify_one();
+            }
+            Ok::<_, EkidenError>(inner)
+        },
+    ));
+
+    let stream = async_stream::stream! {
+        let Mode::Replay { frames, sent, .. } = &inner.mode else {
+            return;
+        };
+        loop {
+            let next = frames.lock().ok().and_then(|mut frames| {
+                match frames.front() {
+                    Some(frame) if frame.direction == FrameDirection::Received => {
+                        frames.pop_front()
+                    }
+                    _ => None,
+                }
+            });
+            match next {
+                Some(frame) => yield Ok(Message::Text(frame.text.into())),
+                // Wait for the client to send the next recorded frame; once the
+                // cassette is exhausted the connection simply stays open
+                None => sent.notified().await,
+            }
+        }
+    };
+
+    (sink, stream.boxed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Fixed;
+
+    impl HttpTransport for Fixed {
+        fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+            Box::pin(async move {
+                Ok(HttpResponse {
+                    status: 200,
+                    headers: vec![("content-type".to_string(), "application/json".to_string())],
+                    body: format!("{{\"path\":\"{}\"}}", request.url.path()).into_bytes(),
+                })
+            })
+        }
+    }
+
+    fn get(url: &str) -> HttpRequest {
+        HttpRequest {
+            method: reqwest::Method::GET,
+            url: Url::parse(url).unwrap(),
+            headers: vec![("Authorization".to_string(), "Bearer secret".to_string())],
+            body: None,
+            timeout: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_http() {
+        let path = std::env::temp_dir().join(format!("ekiden-vcr-{}.json", std::process::id()));
+
+        let vcr = Vcr::record_with(&path, Arc::new(Fixed), Arc::new(TungsteniteConnector));
+        let transport = vcr.transport();
+        transport.send(get("http://gw/api/v1/a")).await.unwrap();
+        transport.send(get("http://gw/api/v1/b")).await.unwrap();
+        drop(transport);
+        drop(vcr);
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("secret"));
+
+        let transport = Vcr::replay(&path).unwrap().transport();
+        let response = transport.send(get("http://gw/api/v1/b")).await.unwrap();
+        assert_eq!(response.body, b"{\"path\":\"/api/v1/b\"}");
+        assert_eq!(response.header("Content-Type"), Some("application/json"));
+        // Each interaction is served once
+        assert!(transport.send(get("http://gw/api/v1/b")).await.is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replay_ws_waits_for_sent_frames() {
+        let path = std::env::temp_dir().join(format!("ekiden-vcr-ws-{}.json", std::process::id()));
+        Cassette {
+            interactions: Vec::new(),
+            ws_frames: vec![
+                WsFrame {
+                    direction: FrameDirection::Received,
+                    text: "hello".to_string(),
+                },
+                WsFrame {
+                    direction: FrameDirection::Sent,
+                    text: "subscribe".to_string(),
+                },
+                WsFrame {
+                    direction: FrameDirection::Received,
+                    text: "event".to_string(),
+                },
+            ],
+        }
+        .save(&path)
+        .unwrap();
+
+        let connector = Vcr::replay(&path).unwrap().connector();
+        let url = Url::parse("ws://gw/ws").unwrap();
+        let (mut sink, mut stream) = connector.connect(&url).await.unwrap();
+
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first, Message::Text("hello".into()));
+
+        let pending =
+            tokio::time::timeout(std::time::Duration::from_millis(50), stream.next()).await;
+        assert!(pending.is_err());
+
+        sink.send(Message::Text("subscribe".into())).await.unwrap();
+        let event = stream.next().await.unwrap().unwrap();
+        assert_eq!(event, Message::Text("event".into()));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,382 @@
+//! Staleness monitoring for WebSocket channels.
+//!
+//! A feed can die silently: the socket stays open and pings succeed, but one
+//! market stops publishing. [`Watchdog`] checks the last event time of every
+//! subscribed channel and emits [`WatchdogEvent::StaleData`] when a channel
+//! stays quiet beyond its threshold, optionally resubscribing it:
+//!
+//! ```rust,no_run
+//! # async fn run(client: ekiden_rust_sdk::EkidenClient) -> ekiden_rust_sdk::Result<()> {
+//! use ekiden_rust_sdk::watchdog::{Watchdog, WatchdogConfig, WatchdogEvent};
+//! use std::time::Duration;
+//!
+//! client.connect_websocket().await?;
+//! let _book = client.subscribe_orderbook("0x1111111111111111111111111111111111111111").await?;
+//!
+//! let config = WatchdogConfig::new(Duration::from_secs(5)).with_auto_resubscribe(true);
+//! let watchdog = Watchdog::new(client.clone(), config).start();
+//! let mut events = watchdog.subscribe();
+//! while let Ok(event) = events.recv().await {
+//!     if let WatchdogEvent::StaleData(stale) = event {
+//!         println!("{} quiet for {:?}, pulling quotes", stale.channel, stale.silent_for);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Checks are skipped while the WebSocket is disconnected, since every channel
+//! is quiet then and the client reconnects on its own unless
+//! [`EkidenConfig::reconnect`](crate::EkidenConfig::reconnect) is `None`.
+
+use crate::client::EkidenClient;
+use crate::clock::Clock;
+use crate::ws::channels;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Configuration for a [`Watchdog`]
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    /// Silence after which a channel is considered stale
+    pub stale_after: Duration,
+    /// Thresholds for specific channels
+    pub channel_thresholds: HashMap<String, Duration>,
+    /// Interval between checks
+    pub check_interval: Duration,
+    /// Resubscribe stale channels
+    pub auto_resubscribe: bool,
+}
+
+impl WatchdogConfig {
+    /// Create a configuration with one threshold for every channel
+    pub fn new(stale_after: Duration) -> Self {
+        Self {
+            stale_after,
+            channel_thresholds: HashMap::new(),
+            check_interval: Duration::from_secs(1),
+            auto_resubscribe: false,
+        }
+    }
+
+    /// Use a different threshold for a channel, e.g. a quiet market's trades
+    pub fn with_channel_threshold<S: Into<String>>(
+        mut self,
+        channel: S,
+        stale_after: Duration,
+    ) -> Self {
+        self.channel_thresholds.insert(channel.into(), stale_after);
+        self
+    }
+
+    /// Set the interval between checks
+    pub fn with_check_interval(mut self, check_interval: Duration) -> Self {
+        self.check_interval = check_interval;
+        self
+    }
+
+    /// Resubscribe channels when they go stale
+    pub fn with_auto_resubscribe(mut self, auto_resubscribe: bool) -> Self {
+        self.auto_resubscribe = auto_resubscribe;
+        self
+    }
+
+    /// Get the threshold for a channel
+    pub fn threshold(&self, channel: &str) -> Duration {
+        self.channel_thresholds
+            .get(channel)
+            .copied()
+            .unwrap_or(self.stale_after)
+    }
+}
+
+/// A channel that has been quiet beyond its threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaleData {
+    pub channel: String,
+    /// Market of orderbook, trades and candles channels
+    pub market_addr: Option<String>,
+    /// Time since the last event
+    pub silent_for: Duration,
+    pub threshold: Duration,
+}
+
+/// Event emitted by a [`Watchdog`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchdogEvent {
+    /// A channel went quiet; repeated every threshold while it stays quiet
+    StaleData(StaleData),
+    /// A stale channel received an event again
+    Recovered {
+        channel: String,
+        silent_for: Duration,
+    },
+    /// A stale channel was resubscribed
+    Resubscribed { channel: String },
+    /// Resubscribing a stale channel failed
+    ResubscribeFailed { channel: String, error: String },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quiet {
+    last_event: Instant,
+    alerted_at: Instant,
+}
+
+/// Turns channel activity into stale and recovery events
+#[derive(Debug, Clone)]
+pub struct StalenessTracker {
+    config: WatchdogConfig,
+    stale: HashMap<String, Quiet>,
+}
+
+impl StalenessTracker {
+    /// Create a tracker with the thresholds of `config`
+    pub fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            stale: HashMap::new(),
+        }
+    }
+
+    /// Check if a channel is currently stale
+    pub fn is_stale(&self, channel: &str) -> bool {
+        self.stale.contains_key(channel)
+    }
+
+    /// Compare last event times against the thresholds at `now`
+    pub fn check(
+        &mut self,
+        activity: &HashMap<String, Instant>,
+        now: Instant,
+    ) -> Vec<WatchdogEvent> {
+        self.stale
+            .retain(|channel, _| activity.contains_key(channel));
+
+        let mut sorted: Vec<(&String, &Instant)> = activity.iter().collect();
+        sorted.sort();
+
+        let mut events = Vec::new();
+        for (channel, &last_event) in sorted {
+            let threshold = self.config.threshold(channel);
+            let silent_for = now.saturating_duration_since(last_event);
+
+            if silent_for < threshold {
+                if let Some(quiet) = self.stale.remove(channel) {
+                    events.push(WatchdogEvent::Recovered {
+                        channel: channel.clone(),
+                        silent_for: last_event.saturating_duration_since(quiet.last_event),
+                    });
+                }
+                continue;
+            }
+
+            let due = match self.stale.get(channel) {
+                Some(quiet) => now.saturating_duration_since(quiet.alerted_at) >= threshold,
+                None => true,
+            };
+            if due {
+                self.stale.insert(
+                    channel.clone(),
+                    Quiet {
+                        last_event,
+                        alerted_at: now,
+                    },
+                );
+                events.push(WatchdogEvent::StaleData(StaleData {
+                    channel: channel.clone(),
+                    market_addr: channels::market_addr(channel).map(str::to_string),
+                    silent_for,
+                    threshold,
+                }));
+            }
+        }
+        events
+    }
+}
+
+/// Watches a client's WebSocket channels for silent failures
+#[derive(Debug)]
+pub struct Watchdog {
+    client: EkidenClient,
+    tracker: StalenessTracker,
+    clock: Arc<dyn Clock>,
+    events: broadcast::Sender<WatchdogEvent>,
+}
+
+impl Watchdog {
+    /// Create a watchdog for a client's subscriptions
+    pub fn new(client: EkidenClient, config: WatchdogConfig) -> Self {
+        let (events, _) = broadcast::channel(100);
+        Self {
+            clock: client.clock(),
+            client,
+            tracker: StalenessTracker::new(config),
+            events,
+        }
+    }
+
+    /// Use a custom clock for the check interval and staleness
+    ///
+    /// Defaults to the client's clock, which also stamps channel activity.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Subscribe to watchdog events
+    pub fn subscribe(&self) -> broadcast::Receiver<WatchdogEvent> {
+        self.events.subscribe()
+    }
+
+    /// Run checks in the background until the handle is dropped or the client shuts down
+    pub fn start(self) -> WatchdogHandle {
+        let events = self.events.clone();
+        let client = self.client.clone();
+        WatchdogHandle {
+            events,
+            task: client.spawn_job(self.run()),
+        }
+    }
+
+    /// Check every subscribed channel once
+    pub async fn check(&mut self) -> Vec<WatchdogEvent> {
+        if !self.client.is_websocket_connected().await {
+            return Vec::new();
+        }
+
+        let activity = self.client.channel_activity().await;
+        let mut events = self.tracker.check(&activity, self.clock.instant());
+        if self.tracker.config.auto_resubscribe {
+            let stale: Vec<String> = events
+                .iter()
+                .filter_map(|event| match event {
+                    WatchdogEvent::StaleData(stale) => Some(stale.channel.clone()),
+                    _ => None,
+                })
+                .collect();
+            for channel in stale {
+                events.push(match self.client.resubscribe(&channel).await {
+                    Ok(()) => WatchdogEvent::Resubscribed { channel },
+                    Err(e) => WatchdogEvent::ResubscribeFailed {
+                        channel,
+                        error: e.to_string(),
+                    },
+                });
+            }
+        }
+
+        for event in &events {
+            match event {
+                WatchdogEvent::StaleData(stale) => {
+                    warn!("No events on {} for {:?}", stale.channel, stale.silent_for)
+                }
+                event => debug!("Watchdog: {:?}", event),
+            }
+            let _ = self.events.send(event.clone());
+        }
+        events
+    }
+
+    async fn run(mut self) {
+        loop {
+            self.clock.sleep(self.tracker.config.check_interval).await;
+            self.check().await;
+        }
+    }
+}
+
+/// Handle to a running [`Watchdog`]; stops it when dropped
+#[derive(Debug)]
+pub struct WatchdogHandle {
+    events: broadcast::Sender<WatchdogEvent>,
+    task: JoinHandle<()>,
+}
+
+impl WatchdogHandle {
+    /// Subscribe to watchdog events
+    pub fn subscribe(&self) -> broadcast::Receiver<WatchdogEvent> {
+        self.events.subscribe()
+    }
+}
+
+impl Drop for WatchdogHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOK: &str = "orderbook/0x1111111111111111111111111111111111111111";
+    const TRADES: &str = "trades/0x1111111111111111111111111111111111111111";
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_flags_quiet_channel_and_recovery() {
+        let start = Instant::now();
+        let mut tracker = StalenessTracker::new(WatchdogConfig::new(secs(5)));
+        let mut activity = HashMap::from([(BOOK.to_string(), start)]);
+
+        assert!(tracker.check(&activity, start + secs(4)).is_empty());
+
+        let events = tracker.check(&activity, start + secs(6));
+        assert_eq!(
+            events,
+            vec![WatchdogEvent::StaleData(StaleData {
+                channel: BOOK.to_string(),
+                market_addr: Some("0x1111111111111111111111111111111111111111".to_string()),
+                silent_for: secs(6),
+                threshold: secs(5),
+            })]
+        );
+        assert!(tracker.is_stale(BOOK));
+
+        // Not repeated until another threshold has passed
+        assert!(tracker.check(&activity, start + secs(8)).is_empty());
+        assert_eq!(tracker.check(&activity, start + secs(11)).len(), 1);
+
+        activity.insert(BOOK.to_string(), start + secs(12));
+        assert_eq!(
+            tracker.check(&activity, start + secs(12)),
+            vec![WatchdogEvent::Recovered {
+                channel: BOOK.to_string(),
+                silent_for: secs(12),
+            }]
+        );
+        assert!(!tracker.is_stale(BOOK));
+    }
+
+    #[test]
+    fn test_per_channel_thresholds() {
+        let start = Instant::now();
+        let config = WatchdogConfig::new(secs(5)).with_channel_threshold(TRADES, secs(60));
+        let mut tracker = StalenessTracker::new(config);
+        let activity = HashMap::from([(BOOK.to_string(), start), (TRADES.to_string(), start)]);
+
+        let events = tracker.check(&activity, start + secs(10));
+        assert_eq!(events.len(), 1);
+        assert!(tracker.is_stale(BOOK));
+        assert!(!tracker.is_stale(TRADES));
+    }
+
+    #[test]
+    fn test_forgets_unsubscribed_channels() {
+        let start = Instant::now();
+        let mut tracker = StalenessTracker::new(WatchdogConfig::new(secs(5)));
+        tracker.check(&HashMap::from([(BOOK.to_string(), start)]), start + secs(6));
+        assert!(tracker.is_stale(BOOK));
+
+        assert!(tracker.check(&HashMap::new(), start + secs(7)).is_empty());
+        assert!(!tracker.is_stale(BOOK));
+    }
+}
@@ -1,29 +1,361 @@
+use crate::clock::{self, Clock};
 use crate::error::{EkidenError, Result};
+use crate::failover::Endpoints;
+use crate::metrics::{Direction, MetricsRecorder, WsConnectMetrics, WsMessageMetrics};
+use crate::transport::{TungsteniteConnector, WsConnector, WsFrameSink, WsFrameStream};
 use crate::types::*;
-use futures_util::{
-    stream::{SplitSink, SplitStream},
-    SinkExt, StreamExt,
-};
+use futures_util::future::BoxFuture;
+use futures_util::{SinkExt, StreamExt};
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::net::TcpStream;
-use tokio::sync::{broadcast, Mutex, RwLock};
-use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
-use tracing::{debug, error, info};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, oneshot, watch, Mutex, Notify, RwLock};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn, Instrument};
 use url::Url;
 
-type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
-type WsSink = SplitSink<WsStream, Message>;
-type WsReceiver = SplitStream<WsStream>;
+/// Default limit for connect, subscribe, unsubscribe, ping and disconnect
+pub const DEFAULT_CONTROL_TIMEOUT: Duration = Duration::from_secs(10);
 
-/// WebSocket client for Ekiden real-time data
+/// Default number of events buffered per subscription
+pub const DEFAULT_BUFFER_SIZE: usize = 1000;
+
+/// First delay before retrying a failed orderbook snapshot fetch
+const SNAPSHOT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Longest delay between orderbook snapshot fetch retries
+const MAX_SNAPSHOT_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Interval between sweeps unsubscribing channels whose receivers are gone
+const RELEASE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Outcome of a subscribe request still waiting for its ack, `None` until known
+type PendingSubscribe = watch::Receiver<Option<std::result::Result<(), String>>>;
+
+type Subscriptions = Arc<RwLock<HashMap<String, Vec<Subscriber>>>>;
+type Acks = Arc<std::sync::Mutex<HashMap<u64, PendingAck>>>;
+
+/// A subscription request waiting for the server's answer
 #[derive(Debug)]
+struct PendingAck {
+    channel: String,
+    done: oneshot::Sender<Result<()>>,
+}
+type SenderSlot = Arc<RwLock<Option<Arc<Mutex<WsFrameSink>>>>>;
+
+/// Automatic reconnection after the server closes or drops the connection
+///
+/// Attempts back off exponentially from `initial_delay` up to `max_delay`.
+/// After reconnecting every subscribed channel is subscribed again, so
+/// existing receivers keep getting events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectConfig {
+    /// Delay before the first attempt
+    pub initial_delay: Duration,
+    /// Longest delay between attempts
+    pub max_delay: Duration,
+    /// Attempts before giving up, unlimited when `None`
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// Create a configuration with the default delays and unlimited attempts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the delay before the first attempt
+    pub fn with_initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    /// Set the longest delay between attempts
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Give up after `max_attempts` failed attempts
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Delay before attempt number `attempt`, counting from zero
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.max_delay)
+    }
+}
+
+/// What happens when a subscriber falls a full buffer behind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LagPolicy {
+    /// Drop the oldest events and report [`EkidenError::Lagged`] once
+    #[default]
+    Error,
+    /// Drop the oldest events; the loss only shows in [`EventStream::missed`]
+    DropOldest,
+    /// Hold back the connection until the subscriber catches up
+    ///
+    /// A stalled subscriber delays every channel on the connection and can
+    /// trip the heartbeat.
+    Block,
+}
+
+/// Buffering of one subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionOptions {
+    /// Events buffered before the lag policy applies
+    pub capacity: usize,
+    pub lag_policy: LagPolicy,
+}
+
+impl Default for SubscriptionOptions {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_BUFFER_SIZE,
+            lag_policy: LagPolicy::Error,
+        }
+    }
+}
+
+impl SubscriptionOptions {
+    /// Create options with the default capacity and [`LagPolicy::Error`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of events buffered
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Set what happens when the subscriber falls behind
+    pub fn with_lag_policy(mut self, lag_policy: LagPolicy) -> Self {
+        self.lag_policy = lag_policy;
+        self
+    }
+}
+
+/// Where a channel's events are delivered
+#[derive(Debug, Clone)]
+enum Subscriber {
+    Broadcast(broadcast::Sender<WsEvent>),
+    Bounded(mpsc::Sender<WsEvent>),
+}
+
+impl Subscriber {
+    /// Number of receivers still attached
+    fn receiver_count(&self) -> usize {
+        match self {
+            Subscriber::Broadcast(sender) => sender.receiver_count(),
+            Subscriber::Bounded(sender) => usize::from(!sender.is_closed()),
+        }
+    }
+}
+
+/// Total receivers attached to a channel's subscribers
+fn receiver_count(subscribers: &[Subscriber]) -> usize {
+    subscribers.iter().map(Subscriber::receiver_count).sum()
+}
+
+/// An active subscription and its receivers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionInfo {
+    pub channel: String,
+    /// The channel parsed into its parts, `None` for one without a helper in
+    /// [`channels`]
+    pub kind: Option<Channel>,
+    /// Receivers still attached; zero means events go nowhere
+    pub subscribers: usize,
+    /// Time of the last event, or of subscribing if none arrived yet
+    pub last_activity: Option<Instant>,
+}
+
+/// Keepalive pings that detect connections dropped by intermediaries
+///
+/// A ping is sent every `interval`. If no pong arrives within `timeout` the
+/// connection is treated as dead and closed, which triggers a reconnect when
+/// one is configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatConfig {
+    /// Time between pings
+    pub interval: Duration,
+    /// How long to wait for the pong
+    pub timeout: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl HeartbeatConfig {
+    pub fn new(interval: Duration, timeout: Duration) -> Self {
+        Self { interval, timeout }
+    }
+}
+
+/// Round-trip times kept for [`WebSocketClient::latency`]
+pub const LATENCY_SAMPLES: usize = 256;
+
+/// Ping to pong round-trip times of a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// Most recent round trip
+    pub last: Duration,
+    pub avg: Duration,
+    pub p99: Duration,
+    /// Round trips the stats are computed from
+    pub samples: usize,
+}
+
+impl LatencyStats {
+    /// Compute stats from round trips in the order they were measured
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        let last = *samples.last()?;
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let total: Duration = sorted.iter().sum();
+        let p99 = sorted[(sorted.len() * 99).div_ceil(100) - 1];
+        Some(Self {
+            last,
+            avg: total / sorted.len() as u32,
+            p99,
+            samples: sorted.len(),
+        })
+    }
+}
+
+/// Outstanding pings and recent round trips
+#[derive(Debug, Default)]
+struct Latency {
+    next_id: u64,
+    /// Pings awaiting their pong, oldest first
+    pings: VecDeque<(u64, Instant)>,
+    samples: VecDeque<Duration>,
+}
+
+impl Latency {
+    /// Record a ping sent at `now`, returning the id to send with it
+    fn ping(&mut self, now: Instant) -> u64 {
+        self.next_id += 1;
+        if self.pings.len() >= LATENCY_SAMPLES {
+            self.pings.pop_front();
+        }
+        self.pings.push_back((self.next_id, now));
+        self.next_id
+    }
+
+    /// Record a pong received at `now`
+    ///
+    /// A pong echoing an id is timed against that ping, and older pings still
+    /// outstanding are dropped as lost. One without an id answers the oldest.
+    fn pong(&mut self, id: Option<u64>, now: Instant) {
+        let sent = match id {
+            Some(id) => {
+                let Some(index) = self.pings.iter().position(|(ping, _)| *ping == id) else {
+                    return;
+                };
+                let sent = self.pings[index].1;
+                self.pings.drain(..=index);
+                sent
+            }
+            None => match self.pings.pop_front() {
+                Some((_, sent)) => sent,
+                None => return,
+            },
+        };
+        if self.samples.len() >= LATENCY_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(now.saturating_duration_since(sent));
+    }
+}
+
+/// Fetches orderbook snapshots out of band, e.g. over REST
+///
+/// After a reconnect or a sequence gap, orderbook updates are held back until
+/// a snapshot arrives. Without a source that is the snapshot the server sends
+/// on subscribe; with one, whichever snapshot comes first is used. Held
+/// updates not older than the snapshot are then replayed after it.
+pub trait SnapshotSource: Send + Sync + Debug {
+    /// Fetch a market's book as a [`WsEvent::OrderbookSnapshot`]
+    fn orderbook_snapshot<'a>(&'a self, market_addr: &'a str) -> BoxFuture<'a, Result<WsEvent>>;
+}
+
+/// WebSocket client for Ekiden real-time data
 pub struct WebSocketClient {
     url: Url,
-    sender: Option<Arc<Mutex<WsSink>>>,
-    subscriptions: Arc<RwLock<HashMap<String, broadcast::Sender<WsEvent>>>>,
-    connection_status: Arc<RwLock<ConnectionStatus>>,
+    connector: Arc<dyn WsConnector>,
+    sender: SenderSlot,
+    subscriptions: Subscriptions,
+    activity: Arc<RwLock<HashMap<String, Instant>>>,
+    latency: Arc<std::sync::Mutex<Latency>>,
+    acks: Acks,
+    /// Subscribe requests awaiting their ack, for subscribers joining meanwhile
+    pending: std::sync::Mutex<HashMap<String, PendingSubscribe>>,
+    next_request_id: AtomicU64,
+    connection_status: Arc<watch::Sender<ConnectionStatus>>,
+    control_timeout: Duration,
+    shutdown: CancellationToken,
+    reader: Option<JoinHandle<()>>,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    connects: u64,
+    reconnect: Option<ReconnectConfig>,
+    heartbeat: Option<HeartbeatConfig>,
+    snapshot_source: Option<Arc<dyn SnapshotSource>>,
+    endpoints: Option<Arc<Endpoints>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for WebSocketClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketClient")
+            .field("url", &self.url)
+            .field("connector", &self.connector)
+            .field(
+                "has_sender",
+                &self
+                    .sender
+                    .try_read()
+                    .map_or(true, |sender| sender.is_some()),
+            )
+            .field("subscriptions", &self.subscriptions)
+            .field("connection_status", &*self.connection_status.borrow())
+            .field("control_timeout", &self.control_timeout)
+            .field("shutdown", &self.shutdown.is_cancelled())
+            .field("metrics", &self.metrics)
+            .field("reconnect", &self.reconnect)
+            .field("heartbeat", &self.heartbeat)
+            .field("snapshot_source", &self.snapshot_source)
+            .field("endpoints", &self.endpoints)
+            .field("clock", &self.clock)
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -35,37 +367,198 @@ pub enum ConnectionStatus {
     Failed(String),
 }
 
+impl Drop for WebSocketClient {
+    /// Abort the reader so no task outlives the client
+    fn drop(&mut self) {
+        if let Some(reader) = self.reader.take() {
+            reader.abort();
+            self.connection_status
+                .send_replace(ConnectionStatus::Disconnected);
+        }
+    }
+}
+
 impl WebSocketClient {
     /// Create a new WebSocket client
     pub fn new(url: Url) -> Self {
         Self {
             url,
-            sender: None,
+            connector: Arc::new(TungsteniteConnector),
+            sender: Arc::new(RwLock::new(None)),
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
-            connection_status: Arc::new(RwLock::new(ConnectionStatus::Disconnected)),
+            activity: Arc::new(RwLock::new(HashMap::new())),
+            latency: Default::default(),
+            acks: Default::default(),
+            pending: Default::default(),
+            next_request_id: AtomicU64::new(1),
+            connection_status: Arc::new(watch::Sender::new(ConnectionStatus::Disconnected)),
+            control_timeout: DEFAULT_CONTROL_TIMEOUT,
+            shutdown: CancellationToken::new(),
+            reader: None,
+            metrics: None,
+            connects: 0,
+            reconnect: None,
+            heartbeat: None,
+            snapshot_source: None,
+            endpoints: None,
+            clock: clock::system(),
         }
     }
 
+    /// Open connections through a custom connector
+    pub fn with_connector(mut self, connector: Arc<dyn WsConnector>) -> Self {
+        self.connector = connector;
+        self
+    }
+
+    /// Set how long control operations may take before failing with [`EkidenError::Timeout`]
+    pub fn with_control_timeout(mut self, timeout: Duration) -> Self {
+        self.control_timeout = timeout;
+        self
+    }
+
+    /// Get the URL connections are opened to
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Open later connections to a different URL
+    pub fn set_url(&mut self, url: Url) {
+        self.url = url;
+    }
+
+    /// Stop the message reader when `token` is cancelled
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.shutdown = token;
+        self
+    }
+
+    /// Report messages and connection attempts to a metrics recorder
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsRecorder>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Reconnect automatically when the connection drops, disabled when `None`
+    pub fn with_reconnect(mut self, reconnect: Option<ReconnectConfig>) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Reconnect to whichever of `endpoints` is active, reporting each attempt
+    ///
+    /// Without endpoints, reconnects go to [`url`](Self::url).
+    pub fn with_endpoints(mut self, endpoints: Arc<Endpoints>) -> Self {
+        self.endpoints = Some(endpoints);
+        self
+    }
+
+    /// Use a custom clock for channel activity and reconnect, heartbeat and
+    /// snapshot retry delays
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Fetch snapshots from `source` when resuming orderbook channels
+    pub fn with_snapshot_source(mut self, source: Arc<dyn SnapshotSource>) -> Self {
+        self.snapshot_source = Some(source);
+        self
+    }
+
+    /// Set the snapshot source used by later connections
+    pub fn set_snapshot_source(&mut self, source: Option<Arc<dyn SnapshotSource>>) {
+        self.snapshot_source = source;
+    }
+
+    /// Ping the server periodically and drop the connection when pongs stop, disabled when `None`
+    pub fn with_heartbeat(mut self, heartbeat: Option<HeartbeatConfig>) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Get the control operation timeout
+    pub fn control_timeout(&self) -> Duration {
+        self.control_timeout
+    }
+
     /// Connect to the WebSocket server
     pub async fn connect(&mut self) -> Result<()> {
+        if self.shutdown.is_cancelled() {
+            return Err(EkidenError::Cancelled);
+        }
         info!("Connecting to WebSocket: {}", self.url);
-        *self.connection_status.write().await = ConnectionStatus::Connecting;
+        self.connection_status
+            .send_replace(ConnectionStatus::Connecting);
 
-        let (ws_stream, _) = connect_async(self.url.as_str())
-            .await
-            .map_err(|e| EkidenError::WebSocket(format!("Failed to connect: {}", e)))?;
-        let (sink, stream) = ws_stream.split();
+        let started_at = Instant::now();
+        let connection =
+            tokio::time::timeout(self.control_timeout, self.connector.connect(&self.url))
+                .await
+                .map_err(|_| EkidenError::Timeout)
+                .and_then(|connection| connection);
+        if let Some(metrics) = &self.metrics {
+            metrics.record_ws_connect(&WsConnectMetrics {
+                reconnect: self.connects > 0,
+                error: connection.as_ref().err().map(EkidenError::kind),
+                latency: started_at.elapsed(),
+            });
+        }
+        self.connects += 1;
+        let (sink, stream) = match connection {
+            Ok(connection) => connection,
+            Err(e) => {
+                self.connection_status
+                    .send_replace(ConnectionStatus::Failed(e.to_string()));
+                return Err(e);
+            }
+        };
 
-        self.sender = Some(Arc::new(Mutex::new(sink)));
-        *self.connection_status.write().await = ConnectionStatus::Connected;
+        // A reader left from an earlier connection must not reconnect it
+        if let Some(reader) = self.reader.take() {
+            reader.abort();
+        }
+        *self.sender.write().await = Some(Arc::new(Mutex::new(sink)));
+        self.connection_status
+            .send_replace(ConnectionStatus::Connected);
 
         // Start the message handling loop
-        let subscriptions = self.subscriptions.clone();
-        let connection_status = self.connection_status.clone();
+        let (snapshot_tx, snapshot_rx) = mpsc::unbounded_channel();
+        let reader = Reader {
+            url: self.url.clone(),
+            connector: self.connector.clone(),
+            sender: self.sender.clone(),
+            subscriptions: self.subscriptions.clone(),
+            activity: self.activity.clone(),
+            latency: self.latency.clone(),
+            acks: self.acks.clone(),
+            connection_status: self.connection_status.clone(),
+            control_timeout: self.control_timeout,
+            metrics: self.metrics.clone(),
+            reconnect: self.reconnect,
+            heartbeat: self.heartbeat,
+            pong: Notify::new(),
+            sequences: Default::default(),
+            snapshot_source: self.snapshot_source.clone(),
+            endpoints: self.endpoints.clone(),
+            clock: self.clock.clone(),
+            resuming: Default::default(),
+            snapshot_tx,
+            snapshot_rx: Mutex::new(snapshot_rx),
+            fetches: Default::default(),
+        };
+        let shutdown = self.shutdown.clone();
 
-        tokio::spawn(async move {
-            Self::handle_messages(stream, subscriptions, connection_status).await;
-        });
+        self.reader = Some(tokio::spawn(async move {
+            let status = reader.connection_status.clone();
+            tokio::select! {
+                _ = shutdown.cancelled() => {
+                    debug!("WebSocket reader cancelled");
+                    status.send_replace(ConnectionStatus::Disconnected);
+                }
+                _ = reader.run(stream) => {}
+            }
+        }));
 
         info!("WebSocket connected successfully");
         Ok(())
@@ -73,67 +566,246 @@ impl WebSocketClient {
 
     /// Disconnect from the WebSocket server
     pub async fn disconnect(&mut self) -> Result<()> {
-        if let Some(sender) = &self.sender {
-            let mut sink = sender.lock().await;
-            let _ = sink.close().await;
+        if let Some(reader) = self.reader.take() {
+            reader.abort();
+            let _ = reader.await;
+        }
+        if let Some(sender) = self.sender.write().await.take() {
+            let close = async {
+                let mut sink = sender.lock().await;
+                let _ = sink.close().await;
+            };
+            if tokio::time::timeout(self.control_timeout, close)
+                .await
+                .is_err()
+            {
+                debug!("Timed out closing WebSocket, dropping it");
+            }
         }
 
-        self.sender = None;
-        *self.connection_status.write().await = ConnectionStatus::Disconnected;
+        self.connection_status
+            .send_replace(ConnectionStatus::Disconnected);
 
-        // Clear all subscriptions
+        // Clear all subscriptions, failing any still waiting for an ack
         self.subscriptions.write().await.clear();
+        self.activity.write().await.clear();
+        self.acks.lock().unwrap().clear();
 
         info!("WebSocket disconnected");
         Ok(())
     }
 
+    /// Cancel the reader, close the connection and wait for the reader to exit
+    ///
+    /// Writes already holding the connection finish before it is closed.
+    /// Later calls to [`connect`](Self::connect) fail with
+    /// [`EkidenError::Cancelled`].
+    pub async fn shutdown(&mut self) -> Result<()> {
+        self.shutdown.cancel();
+        self.disconnect().await
+    }
+
     /// Get the current connection status
     pub async fn connection_status(&self) -> ConnectionStatus {
-        self.connection_status.read().await.clone()
+        self.connection_status.borrow().clone()
+    }
+
+    /// Watch connection status transitions
+    ///
+    /// The receiver stays valid across disconnects and reconnects.
+    pub fn status_stream(&self) -> watch::Receiver<ConnectionStatus> {
+        self.connection_status.subscribe()
     }
 
     /// Check if the client is connected
     pub async fn is_connected(&self) -> bool {
         matches!(
-            *self.connection_status.read().await,
+            *self.connection_status.borrow(),
             ConnectionStatus::Connected
         )
     }
 
     /// Send a ping message
+    ///
+    /// The round trip to the pong is included in [`latency`](Self::latency).
     pub async fn ping(&self) -> Result<()> {
-        self.send_request(WsRequest::Ping).await
+        let id = self.latency.lock().unwrap().ping(self.clock.instant());
+        self.send_request(WsRequest::Ping { id: Some(id) }).await
+    }
+
+    /// Get round-trip stats over the last [`LATENCY_SAMPLES`] pongs, `None`
+    /// before the first
+    ///
+    /// Heartbeat pings are measured too, so a client with a heartbeat gets
+    /// fresh samples every interval.
+    pub fn latency(&self) -> Option<LatencyStats> {
+        let mut latency = self.latency.lock().unwrap();
+        LatencyStats::from_samples(latency.samples.make_contiguous())
     }
 
     /// Subscribe to a channel and receive events
+    ///
+    /// Subscribing to a channel that is already subscribed shares the
+    /// server-side subscription. The channel is unsubscribed once every
+    /// receiver has been dropped.
     pub async fn subscribe(&self, channel: &str) -> Result<broadcast::Receiver<WsEvent>> {
-        let (tx, rx) = broadcast::channel(1000);
+        let channel = &channels::normalize(channel);
+        let (tx, rx) = broadcast::channel(DEFAULT_BUFFER_SIZE);
+        self.register(channel, Subscriber::Broadcast(tx)).await?;
+        Ok(rx)
+    }
 
-        // Store the subscription
-        self.subscriptions
+    /// Subscribe to a channel with a chosen buffer size and lag policy
+    pub async fn subscribe_with(
+        &self,
+        channel: &str,
+        options: SubscriptionOptions,
+    ) -> Result<EventStream> {
+        if options.capacity == 0 {
+            return Err(EkidenError::validation(
+                "Subscription capacity must be greater than zero",
+            ));
+        }
+        let channel = &channels::normalize(channel);
+        let stream = match options.lag_policy {
+            LagPolicy::Block => {
+                let (tx, rx) = mpsc::channel(options.capacity);
+                self.register(channel, Subscriber::Bounded(tx)).await?;
+                EventStream::bounded(rx, channel.to_string())
+            }
+            lag_policy => {
+                let (tx, rx) = broadcast::channel(options.capacity);
+                self.register(channel, Subscriber::Broadcast(tx)).await?;
+                EventStream::new(rx, channel.to_string()).with_lag_policy(lag_policy)
+            }
+        };
+        Ok(stream)
+    }
+
+    /// Store a channel's subscriber, send the subscription request and wait
+    /// for the server to acknowledge it
+    ///
+    /// A channel that is already subscribed gains the subscriber without a
+    /// new request. One whose request is still awaiting its ack gains it too,
+    /// and the subscriber shares the request's outcome.
+    async fn register(&self, channel: &str, subscriber: Subscriber) -> Result<()> {
+        // Store the subscription first, the server may send events before the ack
+        let outcome = {
+            let mut subscriptions = self.subscriptions.write().await;
+            if let Some(subscribers) = subscriptions.get_mut(channel) {
+                subscribers.retain(|subscriber| subscriber.receiver_count() > 0);
+                subscribers.push(subscriber);
+                debug!("Sharing the subscription to {}", channel);
+                let pending = self
+                    .pending
+                    .lock()
+                    .unwrap()
+                    .get(channel)
+                    .filter(|pending| pending.has_changed().is_ok())
+                    .cloned();
+                drop(subscriptions);
+                return match pending {
+                    Some(pending) => Self::join_pending(pending).await,
+                    None => Ok(()),
+                };
+            }
+            subscriptions.insert(channel.to_string(), vec![subscriber]);
+            let (outcome, pending) = watch::channel(None);
+            self.pending
+                .lock()
+                .unwrap()
+                .insert(channel.to_string(), pending);
+            outcome
+        };
+
+        let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let (done, ack) = oneshot::channel();
+        self.acks.lock().unwrap().insert(
+            id,
+            PendingAck {
+                channel: channel.to_string(),
+                done,
+            },
+        );
+        let result = async {
+            self.send_request(WsRequest::Subscribe {
+                channel: channel.to_string(),
+                id: Some(id),
+            })
+            .await?;
+            match tokio::time::timeout(self.control_timeout, ack).await {
+                Ok(Ok(result)) => result,
+                Ok(Err(_)) => Err(EkidenError::ConnectionClosed),
+                Err(_) => Err(EkidenError::Timeout),
+            }
+        }
+        .await;
+        self.acks.lock().unwrap().remove(&id);
+        self.pending.lock().unwrap().remove(channel);
+
+        // Forget the channel if the request failed or was rejected
+        if let Err(e) = result {
+            self.subscriptions.write().await.remove(channel);
+            outcome.send_replace(Some(Err(e.to_string())));
+            return Err(e);
+        }
+        self.activity
             .write()
             .await
-            .insert(channel.to_string(), tx);
+            .insert(channel.to_string(), self.clock.instant());
+        outcome.send_replace(Some(Ok(())));
+
+        info!("Subscribed to channel: {}", channel);
+        Ok(())
+    }
+
+    /// Wait for the outcome of a subscribe request another caller sent
+    async fn join_pending(mut pending: PendingSubscribe) -> Result<()> {
+        match pending.wait_for(Option::is_some).await {
+            Ok(outcome) => match &*outcome {
+                Some(Err(message)) => Err(EkidenError::WebSocket(message.clone())),
+                _ => Ok(()),
+            },
+            Err(_) => Err(EkidenError::ConnectionClosed),
+        }
+    }
 
-        // Send subscription request
+    /// Repeat the subscription request for a channel, keeping its receivers
+    pub async fn resubscribe(&self, channel: &str) -> Result<()> {
+        let channel = &channels::normalize(channel);
+        if !self.is_subscribed(channel).await {
+            return Err(EkidenError::validation(format!(
+                "Not subscribed to channel: {}",
+                channel
+            )));
+        }
+
+        self.send_request(WsRequest::Unsubscribe {
+            channel: channel.to_string(),
+            id: None,
+        })
+        .await?;
         self.send_request(WsRequest::Subscribe {
             channel: channel.to_string(),
+            id: None,
         })
         .await?;
 
-        info!("Subscribed to channel: {}", channel);
-        Ok(rx)
+        info!("Resubscribed to channel: {}", channel);
+        Ok(())
     }
 
-    /// Unsubscribe from a channel
+    /// Unsubscribe from a channel, closing every receiver attached to it
     pub async fn unsubscribe(&self, channel: &str) -> Result<()> {
+        let channel = &channels::normalize(channel);
         // Remove the subscription
         self.subscriptions.write().await.remove(channel);
+        self.activity.write().await.remove(channel);
 
         // Send unsubscription request
         self.send_request(WsRequest::Unsubscribe {
             channel: channel.to_string(),
+            id: None,
         })
         .await?;
 
@@ -141,13 +813,29 @@ impl WebSocketClient {
         Ok(())
     }
 
+    /// Unsubscribe from every channel
+    ///
+    /// All channels are forgotten even if a request fails; the first failure
+    /// is returned.
+    pub async fn unsubscribe_all(&self) -> Result<()> {
+        let channels = self.active_subscriptions().await;
+        let mut result = Ok(());
+        for channel in channels {
+            if let Err(e) = self.unsubscribe(&channel).await {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
     /// Subscribe to orderbook updates for a market
     pub async fn subscribe_orderbook(
         &self,
         market_addr: &str,
     ) -> Result<broadcast::Receiver<WsEvent>> {
-        let channel = format!("orderbook/{}", market_addr);
-        self.subscribe(&channel).await
+        self.subscribe(&channels::orderbook(market_addr)).await
     }
 
     /// Subscribe to trade updates for a market
@@ -155,47 +843,261 @@ impl WebSocketClient {
         &self,
         market_addr: &str,
     ) -> Result<broadcast::Receiver<WsEvent>> {
-        let channel = format!("trades/{}", market_addr);
-        self.subscribe(&channel).await
+        self.subscribe(&channels::trades(market_addr)).await
+    }
+
+    /// Subscribe to live candle updates for a market and interval
+    pub async fn subscribe_candles(
+        &self,
+        market_addr: &str,
+        interval: &str,
+    ) -> Result<broadcast::Receiver<WsEvent>> {
+        self.subscribe(&channels::candles(market_addr, interval))
+            .await
+    }
+
+    /// Subscribe to mark price, oracle price, volume and funding updates for a market
+    pub async fn subscribe_ticker(
+        &self,
+        market_addr: &str,
+    ) -> Result<broadcast::Receiver<WsEvent>> {
+        self.subscribe(&channels::ticker(market_addr)).await
+    }
+
+    /// Subscribe to liquidations on a market
+    pub async fn subscribe_liquidations(
+        &self,
+        market_addr: &str,
+    ) -> Result<broadcast::Receiver<WsEvent>> {
+        self.subscribe(&channels::liquidations(market_addr)).await
     }
 
     /// Subscribe to user-specific updates (orders, positions, balances)
     pub async fn subscribe_user(&self, user_addr: &str) -> Result<broadcast::Receiver<WsEvent>> {
-        let channel = format!("user/{}", user_addr);
-        self.subscribe(&channel).await
+        self.subscribe(&channels::user(user_addr)).await
+    }
+
+    /// Subscribe to gateway announcements (maintenance, listings, system messages)
+    pub async fn subscribe_announcements(&self) -> Result<broadcast::Receiver<WsEvent>> {
+        self.subscribe(&channels::announcements()).await
     }
 
     /// Send a WebSocket request
     async fn send_request(&self, request: WsRequest) -> Result<()> {
         let sender = self
             .sender
-            .as_ref()
+            .read()
+            .await
+            .clone()
             .ok_or_else(|| EkidenError::network("WebSocket not connected"))?;
 
         let message = serde_json::to_string(&request)?;
-        let mut sink = sender.lock().await;
-        sink.send(Message::Text(message.into())).await?;
+        if let Some(metrics) = &self.metrics {
+            let channel = match &request {
+                WsRequest::Subscribe { channel, .. } | WsRequest::Unsubscribe { channel, .. } => {
+                    Some(channel.clone())
+                }
+                WsRequest::Ping { .. } => None,
+            };
+            metrics.record_ws_message(&WsMessageMetrics {
+                direction: Direction::Outbound,
+                channel,
+                bytes: message.len(),
+            });
+        }
+        let send = async {
+            let mut sink = sender.lock().await;
+            sink.send(Message::Text(message.into())).await
+        };
+        tokio::time::timeout(self.control_timeout, send)
+            .await
+            .map_err(|_| EkidenError::Timeout)??;
 
         debug!("Sent WebSocket request: {:?}", request);
         Ok(())
     }
 
+    /// Get all active subscriptions
+    pub async fn active_subscriptions(&self) -> Vec<String> {
+        self.subscriptions.read().await.keys().cloned().collect()
+    }
+
+    /// Check if subscribed to a specific channel
+    pub async fn is_subscribed(&self, channel: &str) -> bool {
+        self.subscriptions
+            .read()
+            .await
+            .contains_key(&channels::normalize(channel))
+    }
+
+    /// Get the number of receivers attached to a channel
+    pub async fn subscriber_count(&self, channel: &str) -> usize {
+        self.subscriptions
+            .read()
+            .await
+            .get(&channels::normalize(channel))
+            .map_or(0, |subscribers| receiver_count(subscribers))
+    }
+
+    /// Get every active subscription with its parsed channel, sorted by channel
+    pub async fn subscriptions(&self) -> Vec<SubscriptionInfo> {
+        let activity = self.activity.read().await;
+        let mut subscriptions: Vec<SubscriptionInfo> = self
+            .subscriptions
+            .read()
+            .await
+            .iter()
+            .map(|(channel, subscribers)| SubscriptionInfo {
+                channel: channel.clone(),
+                kind: channels::parse(channel),
+                subscribers: receiver_count(subscribers),
+                last_activity: activity.get(channel).copied(),
+            })
+            .collect();
+        subscriptions.sort_unstable_by(|a, b| a.channel.cmp(&b.channel));
+        subscriptions
+    }
+
+    /// Get the time of the last event per subscribed channel
+    ///
+    /// Channels that have not received an event yet report their
+    /// subscription time.
+    pub async fn channel_activity(&self) -> HashMap<String, Instant> {
+        self.activity.read().await.clone()
+    }
+}
+
+/// Message loop of one [`WebSocketClient`] connection, reconnecting if configured
+struct Reader {
+    url: Url,
+    connector: Arc<dyn WsConnector>,
+    sender: SenderSlot,
+    subscriptions: Subscriptions,
+    activity: Arc<RwLock<HashMap<String, Instant>>>,
+    latency: Arc<std::sync::Mutex<Latency>>,
+    acks: Acks,
+    connection_status: Arc<watch::Sender<ConnectionStatus>>,
+    control_timeout: Duration,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    reconnect: Option<ReconnectConfig>,
+    heartbeat: Option<HeartbeatConfig>,
+    pong: Notify,
+    /// Last sequence number seen per channel
+    sequences: std::sync::Mutex<HashMap<String, u64>>,
+    snapshot_source: Option<Arc<dyn SnapshotSource>>,
+    /// Endpoints to reconnect to instead of `url`
+    endpoints: Option<Arc<Endpoints>>,
+    clock: Arc<dyn Clock>,
+    /// Orderbook channels holding back updates until a snapshot arrives
+    resuming: Arc<std::sync::Mutex<HashMap<String, Resume>>>,
+    /// Snapshots fetched from the source, by channel
+    snapshot_tx: mpsc::UnboundedSender<(String, WsEvent)>,
+    snapshot_rx: Mutex<mpsc::UnboundedReceiver<(String, WsEvent)>>,
+    /// Snapshot fetches in flight, aborted when the reader is dropped
+    fetches: std::sync::Mutex<JoinSet<()>>,
+}
+
+/// Updates held back on an orderbook channel until a snapshot arrives
+#[derive(Debug, Default)]
+struct Resume {
+    held: VecDeque<WsEvent>,
+    /// Timestamp of the newest update dropped on overflow; older snapshots
+    /// miss it and are ignored
+    stale_before: u64,
+}
+
+/// How an event's sequence number relates to the previous one on its channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sequence {
+    InOrder,
+    Duplicate,
+    Gap { expected: u64 },
+}
+
+impl Reader {
+    async fn run(self, mut stream: WsFrameStream) {
+        loop {
+            let stale = match self.heartbeat {
+                Some(heartbeat) => tokio::select! {
+                    _ = self.handle_messages(stream) => false,
+                    _ = self.heartbeat(heartbeat) => true,
+                },
+                None => {
+                    self.handle_messages(stream).await;
+                    false
+                }
+            };
+            if stale {
+                self.connection_status
+                    .send_replace(ConnectionStatus::Failed("heartbeat timed out".to_string()));
+            }
+            *self.sender.write().await = None;
+            let Some(reconnect) = self.reconnect else {
+                self.connection_status.send_if_modified(|status| {
+                    let connected = *status == ConnectionStatus::Connected;
+                    if connected {
+                        *status = ConnectionStatus::Disconnected;
+                    }
+                    connected
+                });
+                return;
+            };
+            match self.reconnect(&reconnect).await {
+                Some(next) => stream = next,
+                None => return,
+            }
+        }
+    }
+
     /// Handle incoming WebSocket messages
-    async fn handle_messages(
-        mut stream: WsReceiver,
-        subscriptions: Arc<RwLock<HashMap<String, broadcast::Sender<WsEvent>>>>,
-        connection_status: Arc<RwLock<ConnectionStatus>>,
-    ) {
-        while let Some(message) = stream.next().await {
+    async fn handle_messages(&self, mut stream: WsFrameStream) {
+        let mut sweep = self.clock.sleep(RELEASE_INTERVAL);
+        loop {
+            let message = tokio::select! {
+                message = stream.next() => message,
+                Some((channel, snapshot)) = async { self.snapshot_rx.lock().await.recv().await } => {
+                    self.finish_resume(&channel, snapshot).await;
+                    continue;
+                }
+                _ = &mut sweep => {
+                    self.release_idle().await;
+                    sweep = self.clock.sleep(RELEASE_INTERVAL);
+                    continue;
+                }
+            };
+            let Some(message) = message else {
+                break;
+            };
             match message {
                 Ok(Message::Text(text)) => {
-                    if let Err(e) = Self::process_message(&text, &subscriptions).await {
+                    let span = tracing::debug_span!(
+                        "ekiden.ws_message",
+                        bytes = text.len(),
+                        channel = tracing::field::Empty,
+                        market_addr = tracing::field::Empty,
+                    );
+                    let result = self.process_message(&text).instrument(span.clone()).await;
+                    if let Ok(Some(channel)) = &result {
+                        span.record("channel", channel.as_str());
+                        if let Some(market_addr) = channels::market_addr(channel) {
+                            span.record("market_addr", market_addr);
+                        }
+                    }
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_ws_message(&WsMessageMetrics {
+                            direction: Direction::Inbound,
+                            channel: result.as_ref().ok().cloned().flatten(),
+                            bytes: text.len(),
+                        });
+                    }
+                    if let Err(e) = result {
                         error!("Error processing WebSocket message: {}", e);
                     }
                 }
                 Ok(Message::Close(_)) => {
                     info!("WebSocket connection closed by server");
-                    *connection_status.write().await = ConnectionStatus::Disconnected;
+                    self.connection_status
+                        .send_replace(ConnectionStatus::Disconnected);
                     break;
                 }
                 Ok(_) => {
@@ -203,57 +1105,506 @@ impl WebSocketClient {
                 }
                 Err(e) => {
                     error!("WebSocket error: {}", e);
-                    *connection_status.write().await = ConnectionStatus::Failed(e.to_string());
+                    self.connection_status
+                        .send_replace(ConnectionStatus::Failed(e.to_string()));
                     break;
                 }
             }
         }
     }
 
-    /// Process a WebSocket message
-    async fn process_message(
-        text: &str,
-        subscriptions: &Arc<RwLock<HashMap<String, broadcast::Sender<WsEvent>>>>,
-    ) -> Result<()> {
-        let response: WsResponse = serde_json::from_str(text)?;
+    /// Process a WebSocket message, returning the channel it names
+    async fn process_message(&self, text: &str) -> Result<Option<String>> {
+        let response = WsResponse::parse(text)?;
 
         match response {
-            WsResponse::Pong => {
+            WsResponse::Pong { id } => {
                 debug!("Received pong");
+                self.latency.lock().unwrap().pong(id, self.clock.instant());
+                self.pong.notify_waiters();
+                Ok(None)
             }
-            WsResponse::Subscribed { channel } => {
+            WsResponse::Subscribed { channel, id } => {
+                let channel = channels::normalize(&channel);
                 info!("Successfully subscribed to channel: {}", channel);
+                self.sequences.lock().unwrap().remove(&channel);
+                if let Some(pending) = self.take_ack(id, Some(&channel)) {
+                    let _ = pending.done.send(Ok(()));
+                }
+                Ok(Some(channel))
             }
-            WsResponse::Unsubscribed { channel } => {
+            WsResponse::Unsubscribed { channel, .. } => {
+                let channel = channels::normalize(&channel);
                 info!("Successfully unsubscribed from channel: {}", channel);
+                Ok(Some(channel))
             }
-            WsResponse::Event { channel, data } => {
+            WsResponse::Event { channel, seq, data } => {
+                let channel = channels::normalize(&channel);
                 debug!("Received event for channel {}: {:?}", channel, data);
 
-                // Forward the event to subscribers
-                let subscriptions = subscriptions.read().await;
-                if let Some(sender) = subscriptions.get(&channel) {
-                    if let Err(e) = sender.send(data) {
-                        debug!("No active receivers for channel {}: {}", channel, e);
+                match seq.map(|seq| (seq, self.track_sequence(&channel, seq))) {
+                    Some((seq, Sequence::Duplicate)) => {
+                        debug!("Dropping repeated event {} on {}", seq, channel);
+                        return Ok(Some(channel));
+                    }
+                    Some((received, Sequence::Gap { expected })) => {
+                        warn!(
+                            "Sequence gap on {}: expected {}, received {}",
+                            channel, expected, received
+                        );
+                        self.forward(
+                            &channel,
+                            WsEvent::Resync {
+                                channel: channel.clone(),
+                                expected,
+                                received,
+                            },
+                        )
+                        .await;
+                        self.begin_resume(&channel);
+                        if let Err(e) = self.request_snapshot(&channel).await {
+                            warn!("Failed to request a snapshot for {}: {}", channel, e);
+                        }
                     }
+                    _ => {}
                 }
+                self.deliver(&channel, data).await;
+                Ok(Some(channel))
             }
-            WsResponse::Error { message } => {
-                error!("WebSocket error: {}", message);
-            }
-        }
+            WsResponse::Error { message, id } => match self.take_ack(id, None) {
+                Some(pending) => {
+                    warn!("Subscription to {} rejected: {}", pending.channel, message);
+                    let _ = pending.done.send(Err(EkidenError::WebSocket(format!(
+                        "Subscription to {} rejected: {}",
+                        pending.channel, message
+                    ))));
+                    Ok(Some(pending.channel))
+                }
+                None => {
+                    error!("WebSocket error: {}", message);
+                    Ok(None)
+                }
+            },
+        }
+    }
+
+    /// Remove the pending subscription an ack or error answers
+    ///
+    /// Servers that do not echo request ids are matched by channel, oldest
+    /// request first.
+    fn take_ack(&self, id: Option<u64>, channel: Option<&str>) -> Option<PendingAck> {
+        let mut acks = self.acks.lock().unwrap();
+        let id = match (id, channel) {
+            (Some(id), _) => id,
+            (None, Some(channel)) => acks
+                .iter()
+                .filter(|(_, pending)| pending.channel == channel)
+                .map(|(id, _)| *id)
+                .min()?,
+            (None, None) => return None,
+        };
+        acks.remove(&id)
+    }
+
+    /// Forward an event, holding back orderbook updates while the channel resumes
+    ///
+    /// A snapshot from the server ends the resume. Held updates arrived
+    /// before it, so they are dropped. When the held updates overflow the
+    /// buffer they are dropped too and the resume starts over with a new
+    /// snapshot.
+    async fn deliver(&self, channel: &str, event: WsEvent) {
+        {
+            let mut resuming = self.resuming.lock().unwrap();
+            if let Some(resume) = resuming.get_mut(channel) {
+                match event {
+                    WsEvent::OrderbookUpdate { timestamp, .. } => {
+                        let overflowed = resume.held.len() >= DEFAULT_BUFFER_SIZE;
+                        if overflowed {
+                            resume.held.clear();
+                            resume.stale_before = timestamp;
+                        } else {
+                            resume.held.push_back(event);
+                        }
+                        drop(resuming);
+                        if overflowed {
+                            self.restart_resume(channel).await;
+                        }
+                        return;
+                    }
+                    WsEvent::OrderbookSnapshot { .. } => {
+                        debug!("Resumed {} from the server snapshot", channel);
+                        resuming.remove(channel);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.forward(channel, event).await;
+    }
+
+    /// Hold back an orderbook channel's updates until a snapshot arrives
+    ///
+    /// With a snapshot source the snapshot is also fetched from it.
+    fn begin_resume(&self, channel: &str) {
+        if !matches!(channels::parse(channel), Some(Channel::Orderbook(_))) {
+            return;
+        }
+        if self
+            .resuming
+            .lock()
+            .unwrap()
+            .insert(channel.to_string(), Resume::default())
+            .is_some()
+        {
+            return;
+        }
+        self.fetch_snapshot(channel);
+    }
+
+    /// Start an overflowed resume over, asking the server and the snapshot
+    /// source for a snapshot newer than the dropped updates
+    async fn restart_resume(&self, channel: &str) {
+        warn!(
+            "Held more than {} updates on {}, requesting a new snapshot",
+            DEFAULT_BUFFER_SIZE, channel
+        );
+        self.fetch_snapshot(channel);
+        if let Err(e) = self.request_snapshot(channel).await {
+            warn!("Failed to request a snapshot for {}: {}", channel, e);
+        }
+    }
+
+    /// Fetch an orderbook channel's snapshot from the snapshot source
+    ///
+    /// Failed fetches are retried with a growing delay until one succeeds or
+    /// the channel stops resuming.
+    fn fetch_snapshot(&self, channel: &str) {
+        let Some(Channel::Orderbook(market_addr)) = channels::parse(channel) else {
+            return;
+        };
+        let Some(source) = self.snapshot_source.clone() else {
+            return;
+        };
+        let snapshots = self.snapshot_tx.clone();
+        let resuming = self.resuming.clone();
+        let clock = self.clock.clone();
+        let channel = channel.to_string();
+        let mut fetches = self.fetches.lock().unwrap();
+        while fetches.try_join_next().is_some() {}
+        fetches.spawn(async move {
+            let mut delay = SNAPSHOT_RETRY_DELAY;
+            loop {
+                match source.orderbook_snapshot(&market_addr).await {
+                    Ok(snapshot) => {
+                        let _ = snapshots.send((channel, snapshot));
+                        return;
+                    }
+                    Err(e) => warn!(
+                        "Failed to fetch a snapshot for {}, retrying in {:?}: {}",
+                        channel, delay, e
+                    ),
+                }
+                clock.sleep(delay).await;
+                delay = (delay * 2).min(MAX_SNAPSHOT_RETRY_DELAY);
+                if !resuming.lock().unwrap().contains_key(&channel) {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// End a resume with a fetched snapshot, replaying the held updates after it
+    ///
+    /// Updates older than the snapshot are dropped. Levels carry absolute
+    /// sizes, so replaying one the snapshot already includes is harmless. A
+    /// snapshot older than updates dropped on overflow is ignored and the
+    /// resume keeps waiting.
+    async fn finish_resume(&self, channel: &str, snapshot: WsEvent) {
+        let since = match &snapshot {
+            WsEvent::OrderbookSnapshot { timestamp, .. } => *timestamp,
+            _ => 0,
+        };
+        let held = {
+            let mut resuming = self.resuming.lock().unwrap();
+            match resuming.get(channel) {
+                None => {
+                    debug!("Dropping fetched snapshot for {}, already resumed", channel);
+                    return;
+                }
+                Some(resume) if since < resume.stale_before => {
+                    debug!(
+                        "Dropping fetched snapshot for {}, older than dropped updates",
+                        channel
+                    );
+                    return;
+                }
+                Some(_) => resuming.remove(channel).unwrap_or_default().held,
+            }
+        };
+        debug!(
+            "Resumed {} from a fetched snapshot, replaying {} updates",
+            channel,
+            held.len()
+        );
+        self.forward(channel, snapshot).await;
+        for update in held {
+            if matches!(update, WsEvent::OrderbookUpdate { timestamp, .. } if timestamp >= since) {
+                self.forward(channel, update).await;
+            }
+        }
+    }
 
+    /// Send an event to the channel's subscribers
+    ///
+    /// Bounded subscribers are awaited without holding the subscriptions lock.
+    async fn forward(&self, channel: &str, event: WsEvent) {
+        let subscribers = self.subscriptions.read().await.get(channel).cloned();
+        let Some(subscribers) = subscribers else {
+            return;
+        };
+        let mut delivered = 0;
+        for subscriber in &subscribers {
+            let sent = match subscriber {
+                Subscriber::Broadcast(sender) => sender.send(event.clone()).is_ok(),
+                Subscriber::Bounded(sender) => sender.send(event.clone()).await.is_ok(),
+            };
+            delivered += usize::from(sent);
+        }
+        self.activity
+            .write()
+            .await
+            .insert(channel.to_string(), self.clock.instant());
+        if delivered < subscribers.len() {
+            self.release(channel).await;
+        }
+    }
+
+    /// Release every channel whose receivers are all gone, so quiet channels
+    /// are unsubscribed without waiting for an event
+    async fn release_idle(&self) {
+        let idle: Vec<String> = self
+            .subscriptions
+            .read()
+            .await
+            .iter()
+            .filter(|(_, subscribers)| receiver_count(subscribers) == 0)
+            .map(|(channel, _)| channel.clone())
+            .collect();
+        for channel in idle {
+            self.release(&channel).await;
+        }
+    }
+
+    /// Drop subscribers whose receivers are gone, unsubscribing the channel
+    /// when none remain
+    async fn release(&self, channel: &str) {
+        {
+            let mut subscriptions = self.subscriptions.write().await;
+            let Some(subscribers) = subscriptions.get_mut(channel) else {
+                return;
+            };
+            subscribers.retain(|subscriber| subscriber.receiver_count() > 0);
+            if !subscribers.is_empty() {
+                return;
+            }
+            subscriptions.remove(channel);
+        }
+        self.activity.write().await.remove(channel);
+        self.resuming.lock().unwrap().remove(channel);
+
+        debug!("Last receiver of {} dropped, unsubscribing", channel);
+        let request = WsRequest::Unsubscribe {
+            channel: channel.to_string(),
+            id: None,
+        };
+        if let Err(e) = self.send(&request).await {
+            warn!("Failed to unsubscribe from {}: {}", channel, e);
+        }
+    }
+
+    /// Record a sequence number, comparing it with the last one on the channel
+    fn track_sequence(&self, channel: &str, seq: u64) -> Sequence {
+        let mut sequences = self.sequences.lock().unwrap();
+        let previous = sequences.get(channel).copied();
+        match previous {
+            Some(last) if seq <= last => Sequence::Duplicate,
+            Some(last) if seq > last + 1 => {
+                sequences.insert(channel.to_string(), seq);
+                Sequence::Gap { expected: last + 1 }
+            }
+            _ => {
+                sequences.insert(channel.to_string(), seq);
+                Sequence::InOrder
+            }
+        }
+    }
+
+    /// Subscribe a channel again so the server sends a fresh snapshot
+    async fn request_snapshot(&self, channel: &str) -> Result<()> {
+        self.send(&WsRequest::Unsubscribe {
+            channel: channel.to_string(),
+            id: None,
+        })
+        .await?;
+        self.send(&WsRequest::Subscribe {
+            channel: channel.to_string(),
+            id: None,
+        })
+        .await
+    }
+
+    /// Send a request on the current connection
+    async fn send(&self, request: &WsRequest) -> Result<()> {
+        let sender = self
+            .sender
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| EkidenError::network("WebSocket not connected"))?;
+        let message = serde_json::to_string(request)?;
+        let bytes = message.len();
+        let send = async {
+            sender
+                .lock()
+                .await
+                .send(Message::Text(message.into()))
+                .await
+        };
+        tokio::time::timeout(self.control_timeout, send)
+            .await
+            .map_err(|_| EkidenError::Timeout)??;
+        if let Some(metrics) = &self.metrics {
+            let channel = match request {
+                WsRequest::Subscribe { channel, .. } | WsRequest::Unsubscribe { channel, .. } => {
+                    Some(channel.clone())
+                }
+                WsRequest::Ping { .. } => None,
+            };
+            metrics.record_ws_message(&WsMessageMetrics {
+                direction: Direction::Outbound,
+                channel,
+                bytes,
+            });
+        }
         Ok(())
     }
 
-    /// Get all active subscriptions
-    pub async fn active_subscriptions(&self) -> Vec<String> {
-        self.subscriptions.read().await.keys().cloned().collect()
+    /// Ping every interval until a pong is missed or the ping cannot be sent
+    async fn heartbeat(&self, heartbeat: HeartbeatConfig) {
+        loop {
+            self.clock.sleep(heartbeat.interval).await;
+            let pong = self.pong.notified();
+            tokio::pin!(pong);
+            pong.as_mut().enable();
+
+            let id = self.latency.lock().unwrap().ping(self.clock.instant());
+            if let Err(e) = self.send(&WsRequest::Ping { id: Some(id) }).await {
+                warn!("Failed to send heartbeat ping: {}", e);
+                return;
+            }
+
+            if tokio::time::timeout(heartbeat.timeout, pong).await.is_err() {
+                warn!(
+                    "No pong from {} within {:?}, dropping stale connection",
+                    self.url, heartbeat.timeout
+                );
+                return;
+            }
+        }
     }
 
-    /// Check if subscribed to a specific channel
-    pub async fn is_subscribed(&self, channel: &str) -> bool {
-        self.subscriptions.read().await.contains_key(channel)
+    /// Reconnect with backoff and replay subscriptions, `None` when giving up
+    async fn reconnect(&self, reconnect: &ReconnectConfig) -> Option<WsFrameStream> {
+        self.connection_status
+            .send_replace(ConnectionStatus::Reconnecting);
+        let mut attempt = 0;
+        loop {
+            if reconnect.max_attempts.is_some_and(|max| attempt >= max) {
+                error!(
+                    "Giving up reconnecting to {} after {} attempts",
+                    self.url, attempt
+                );
+                self.connection_status
+                    .send_replace(ConnectionStatus::Failed(format!(
+                        "gave up reconnecting after {} attempts",
+                        attempt
+                    )));
+                return None;
+            }
+            let delay = reconnect.backoff(attempt);
+            attempt += 1;
+            self.clock.sleep(delay).await;
+
+            let (endpoint, url) = match &self.endpoints {
+                Some(endpoints) => {
+                    let index = endpoints.active();
+                    (Some(index), endpoints.websocket_url(index).clone())
+                }
+                None => (None, self.url.clone()),
+            };
+            info!(
+                "Reconnecting to {} after {:?} (attempt {})",
+                url, delay, attempt
+            );
+            let started_at = Instant::now();
+            let connection =
+                tokio::time::timeout(self.control_timeout, self.connector.connect(&url))
+                    .await
+                    .map_err(|_| EkidenError::Timeout)
+                    .and_then(|connection| connection);
+            if let Some(metrics) = &self.metrics {
+                metrics.record_ws_connect(&WsConnectMetrics {
+                    reconnect: true,
+                    error: connection.as_ref().err().map(EkidenError::kind),
+                    latency: started_at.elapsed(),
+                });
+            }
+            match connection {
+                Ok((sink, stream)) => {
+                    if let (Some(endpoints), Some(index)) = (&self.endpoints, endpoint) {
+                        endpoints.report_success(index);
+                    }
+                    *self.sender.write().await = Some(Arc::new(Mutex::new(sink)));
+                    self.connection_status
+                        .send_replace(ConnectionStatus::Connected);
+                    info!("WebSocket reconnected to {}", url);
+                    self.resubscribe().await;
+                    return Some(stream);
+                }
+                Err(e) => {
+                    if let (Some(endpoints), Some(index)) = (&self.endpoints, endpoint) {
+                        endpoints.report_failure(index);
+                    }
+                    warn!("Failed to reconnect to {}: {}", url, e);
+                }
+            }
+        }
+    }
+
+    /// Subscribe every channel again on a new connection
+    ///
+    /// A failed send is only logged; the broken connection ends the message
+    /// loop and triggers another reconnect.
+    async fn resubscribe(&self) {
+        self.sequences.lock().unwrap().clear();
+        let channels: Vec<String> = self.subscriptions.read().await.keys().cloned().collect();
+        for channel in channels {
+            self.begin_resume(&channel);
+            let request = WsRequest::Subscribe {
+                channel: channel.clone(),
+                id: None,
+            };
+            match self.send(&request).await {
+                Ok(()) => {
+                    self.activity
+                        .write()
+                        .await
+                        .insert(channel.clone(), self.clock.instant());
+                    info!("Resubscribed to channel: {}", channel);
+                }
+                Err(e) => warn!("Failed to resubscribe to {}: {}", channel, e),
+            }
+        }
     }
 }
 
@@ -261,11 +1612,19 @@ impl WebSocketClient {
 #[derive(Debug)]
 pub struct WebSocketClientBuilder {
     url: Option<Url>,
+    control_timeout: Duration,
+    reconnect: Option<ReconnectConfig>,
+    heartbeat: Option<HeartbeatConfig>,
 }
 
 impl WebSocketClientBuilder {
     pub fn new() -> Self {
-        Self { url: None }
+        Self {
+            url: None,
+            control_timeout: DEFAULT_CONTROL_TIMEOUT,
+            reconnect: None,
+            heartbeat: None,
+        }
     }
 
     pub fn url<U: Into<Url>>(mut self, url: U) -> Self {
@@ -273,11 +1632,32 @@ impl WebSocketClientBuilder {
         self
     }
 
+    /// Set the timeout for control operations
+    pub fn control_timeout(mut self, timeout: Duration) -> Self {
+        self.control_timeout = timeout;
+        self
+    }
+
+    /// Reconnect automatically when the connection drops
+    pub fn reconnect(mut self, reconnect: ReconnectConfig) -> Self {
+        self.reconnect = Some(reconnect);
+        self
+    }
+
+    /// Send keepalive pings
+    pub fn heartbeat(mut self, heartbeat: HeartbeatConfig) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
     pub fn build(self) -> Result<WebSocketClient> {
         let url = self
             .url
             .ok_or_else(|| EkidenError::config("WebSocket URL is required"))?;
-        Ok(WebSocketClient::new(url))
+        Ok(WebSocketClient::new(url)
+            .with_control_timeout(self.control_timeout)
+            .with_reconnect(self.reconnect)
+            .with_heartbeat(self.heartbeat))
     }
 }
 
@@ -287,38 +1667,180 @@ impl Default for WebSocketClientBuilder {
     }
 }
 
+/// A channel to subscribe to, named by the helpers in [`channels`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Orderbook(String),
+    Trades(String),
+    /// Market address and interval such as `"1m"`
+    Candles(String, String),
+    Ticker(String),
+    Liquidations(String),
+    User(String),
+    Announcements,
+}
+
+impl Channel {
+    /// Get the channel name sent to the server
+    pub fn name(&self) -> String {
+        match self {
+            Channel::Orderbook(market_addr) => channels::orderbook(market_addr),
+            Channel::Trades(market_addr) => channels::trades(market_addr),
+            Channel::Candles(market_addr, interval) => channels::candles(market_addr, interval),
+            Channel::Ticker(market_addr) => channels::ticker(market_addr),
+            Channel::Liquidations(market_addr) => channels::liquidations(market_addr),
+            Channel::User(user_addr) => channels::user(user_addr),
+            Channel::Announcements => channels::announcements(),
+        }
+    }
+}
+
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
 /// Convenience functions for creating market-specific channels
+///
+/// Valid addresses are normalized, so `0x1` and its padded form name the same
+/// channel; anything else is used verbatim.
 pub mod channels {
+    use super::Channel;
+    use crate::utils::format;
+
+    fn addr(address: &str) -> String {
+        format::normalize_address(address).unwrap_or_else(|_| address.to_string())
+    }
+
     /// Create an orderbook channel for a market
     pub fn orderbook(market_addr: &str) -> String {
-        format!("orderbook/{}", market_addr)
+        format!("orderbook/{}", addr(market_addr))
     }
 
     /// Create a trades channel for a market
     pub fn trades(market_addr: &str) -> String {
-        format!("trades/{}", market_addr)
+        format!("trades/{}", addr(market_addr))
     }
 
     /// Create a user channel for user-specific updates
     pub fn user(user_addr: &str) -> String {
-        format!("user/{}", user_addr)
+        format!("user/{}", addr(user_addr))
+    }
+
+    /// Create the channel for gateway announcements
+    pub fn announcements() -> String {
+        "announcements".to_string()
     }
 
     /// Create a candles channel for a market and interval
     pub fn candles(market_addr: &str, interval: &str) -> String {
-        format!("candles/{}/{}", market_addr, interval)
+        format!("candles/{}/{}", addr(market_addr), interval)
+    }
+
+    /// Create a ticker channel for a market's mark price, volume and funding
+    pub fn ticker(market_addr: &str) -> String {
+        format!("ticker/{}", addr(market_addr))
+    }
+
+    /// Create a liquidations channel for a market
+    pub fn liquidations(market_addr: &str) -> String {
+        format!("liquidations/{}", addr(market_addr))
+    }
+
+    /// Get the market address of a market channel
+    pub fn market_addr(channel: &str) -> Option<&str> {
+        let mut parts = channel.split('/');
+        match parts.next()? {
+            "orderbook" | "trades" | "candles" | "ticker" | "liquidations" => parts.next(),
+            _ => None,
+        }
+    }
+
+    /// Normalize the addresses in a channel name
+    ///
+    /// Channels without a helper are returned unchanged.
+    pub fn normalize(channel: &str) -> String {
+        parse(channel).map_or_else(|| channel.to_string(), |parsed| parsed.name())
+    }
+
+    /// Parse a channel name, `None` for a channel without a helper here
+    pub fn parse(channel: &str) -> Option<Channel> {
+        let parts: Vec<&str> = channel.split('/').collect();
+        let parsed = match parts.as_slice() {
+            ["orderbook", market_addr] => Channel::Orderbook(market_addr.to_string()),
+            ["trades", market_addr] => Channel::Trades(market_addr.to_string()),
+            ["candles", market_addr, interval] => {
+                Channel::Candles(market_addr.to_string(), interval.to_string())
+            }
+            ["ticker", market_addr] => Channel::Ticker(market_addr.to_string()),
+            ["liquidations", market_addr] => Channel::Liquidations(market_addr.to_string()),
+            ["user", user_addr] => Channel::User(user_addr.to_string()),
+            ["announcements"] => Channel::Announcements,
+            _ => return None,
+        };
+        Some(parsed)
     }
 }
 
+/// Callback invoked with the channel and number of events a stream missed
+pub type LagHook = Arc<dyn Fn(&str, u64) + Send + Sync>;
+
+/// Receiving end of a subscription
+enum Source {
+    Broadcast(broadcast::Receiver<WsEvent>),
+    Bounded(mpsc::Receiver<WsEvent>),
+}
+
 /// Event stream wrapper for easier handling
 pub struct EventStream {
-    receiver: broadcast::Receiver<WsEvent>,
+    source: Source,
     channel: String,
+    missed: u64,
+    lag_policy: LagPolicy,
+    on_lag: Option<LagHook>,
 }
 
 impl EventStream {
     pub fn new(receiver: broadcast::Receiver<WsEvent>, channel: String) -> Self {
-        Self { receiver, channel }
+        Self {
+            source: Source::Broadcast(receiver),
+            channel,
+            missed: 0,
+            lag_policy: LagPolicy::Error,
+            on_lag: None,
+        }
+    }
+
+    /// Wrap a bounded receiver that holds back the sender instead of lagging
+    pub(crate) fn bounded(receiver: mpsc::Receiver<WsEvent>, channel: String) -> Self {
+        Self {
+            source: Source::Bounded(receiver),
+            channel,
+            missed: 0,
+            lag_policy: LagPolicy::Block,
+            on_lag: None,
+        }
+    }
+
+    /// Set how lags are surfaced
+    ///
+    /// Only [`SubscriptionOptions`] can make a stream block, so
+    /// [`LagPolicy::Block`] is ignored here.
+    pub fn with_lag_policy(mut self, lag_policy: LagPolicy) -> Self {
+        if matches!(self.source, Source::Broadcast(_)) && lag_policy != LagPolicy::Block {
+            self.lag_policy = lag_policy;
+        }
+        self
+    }
+
+    /// Call `hook` whenever the stream falls behind and drops events
+    pub fn with_lag_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&str, u64) + Send + Sync + 'static,
+    {
+        self.on_lag = Some(Arc::new(hook));
+        self
     }
 
     /// Get the channel name
@@ -326,23 +1848,105 @@ impl EventStream {
         &self.channel
     }
 
+    /// Get the policy applied when the stream falls behind
+    pub fn lag_policy(&self) -> LagPolicy {
+        self.lag_policy
+    }
+
+    /// Total number of events dropped because this stream lagged
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
     /// Receive the next event
+    ///
+    /// Under [`LagPolicy::Error`] returns [`EkidenError::Lagged`] once after
+    /// falling behind; the stream stays usable and the next call yields the
+    /// oldest retained event. Under [`LagPolicy::DropOldest`] that event is
+    /// returned directly.
     pub async fn recv(&mut self) -> Result<WsEvent> {
-        self.receiver.recv().await.map_err(|e| match e {
-            broadcast::error::RecvError::Closed => EkidenError::ConnectionClosed,
-            broadcast::error::RecvError::Lagged(_) => EkidenError::general("Event stream lagged"),
-        })
+        loop {
+            let received = match &mut self.source {
+                Source::Broadcast(receiver) => receiver.recv().await,
+                Source::Bounded(receiver) => {
+                    return receiver.recv().await.ok_or(EkidenError::ConnectionClosed)
+                }
+            };
+            match received {
+                Ok(event) => return Ok(event),
+                Err(broadcast::error::RecvError::Closed) => {
+                    return Err(EkidenError::ConnectionClosed)
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    if let Some(error) = self.lagged(missed) {
+                        return Err(error);
+                    }
+                }
+            }
+        }
     }
 
     /// Try to receive an event without blocking
     pub fn try_recv(&mut self) -> Result<WsEvent> {
-        self.receiver.try_recv().map_err(|e| match e {
-            broadcast::error::TryRecvError::Empty => EkidenError::general("No events available"),
-            broadcast::error::TryRecvError::Closed => EkidenError::ConnectionClosed,
-            broadcast::error::TryRecvError::Lagged(_) => {
-                EkidenError::general("Event stream lagged")
+        loop {
+            let received = match &mut self.source {
+                Source::Broadcast(receiver) => receiver.try_recv(),
+                Source::Bounded(receiver) => {
+                    return match receiver.try_recv() {
+                        Ok(event) => Ok(event),
+                        Err(mpsc::error::TryRecvError::Empty) => {
+                            Err(EkidenError::general("No events available"))
+                        }
+                        Err(mpsc::error::TryRecvError::Disconnected) => {
+                            Err(EkidenError::ConnectionClosed)
+                        }
+                    }
+                }
+            };
+            match received {
+                Ok(event) => return Ok(event),
+                Err(broadcast::error::TryRecvError::Empty) => {
+                    return Err(EkidenError::general("No events available"))
+                }
+                Err(broadcast::error::TryRecvError::Closed) => {
+                    return Err(EkidenError::ConnectionClosed)
+                }
+                Err(broadcast::error::TryRecvError::Lagged(missed)) => {
+                    if let Some(error) = self.lagged(missed) {
+                        return Err(error);
+                    }
+                }
             }
-        })
+        }
+    }
+
+    /// Record a lag and build the error reporting it, if the policy reports lags
+    fn lagged(&mut self, missed: u64) -> Option<EkidenError> {
+        self.missed += missed;
+        warn!(
+            "Event stream on {} lagged, missed {} events",
+            self.channel, missed
+        );
+        if let Some(hook) = &self.on_lag {
+            hook(&self.channel, missed);
+        }
+        match self.lag_policy {
+            LagPolicy::DropOldest => None,
+            _ => Some(EkidenError::Lagged {
+                missed,
+                channel: self.channel.clone(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventStream")
+            .field("channel", &self.channel)
+            .field("missed", &self.missed)
+            .field("lag_policy", &self.lag_policy)
+            .finish()
     }
 }
 
@@ -364,10 +1968,188 @@ mod tests {
 
     #[test]
     fn test_channel_helpers() {
-        assert_eq!(channels::orderbook("0x123"), "orderbook/0x123");
-        assert_eq!(channels::trades("0x456"), "trades/0x456");
-        assert_eq!(channels::user("0x789"), "user/0x789");
-        assert_eq!(channels::candles("0x123", "1m"), "candles/0x123/1m");
+        let market = "0x1234567890abcdef1234567890abcdef12345678";
+        assert_eq!(channels::orderbook(market), format!("orderbook/{}", market));
+        assert_eq!(channels::trades(market), format!("trades/{}", market));
+        assert_eq!(channels::user(market), format!("user/{}", market));
+        assert_eq!(channels::ticker(market), format!("ticker/{}", market));
+        assert_eq!(
+            channels::liquidations(market),
+            format!("liquidations/{}", market)
+        );
+        assert_eq!(
+            channels::candles(market, "1m"),
+            format!("candles/{}/1m", market)
+        );
+
+        // Aptos short forms are padded, unknown strings pass through
+        assert_eq!(channels::trades("0x1"), format!("trades/0x{:0>64}", "1"));
+        assert_eq!(channels::user("BTC-USD"), "user/BTC-USD");
+        assert_eq!(channels::normalize("trades/0x1"), channels::trades("0x1"));
+        assert_eq!(
+            channels::normalize("candles/0x1/1m"),
+            channels::candles("0x1", "1m")
+        );
+        assert_eq!(channels::normalize("funding/0x1"), "funding/0x1");
+
+        assert_eq!(
+            channels::market_addr(&channels::candles(market, "1m")),
+            Some(market)
+        );
+        assert_eq!(
+            channels::market_addr(&channels::ticker(market)),
+            Some(market)
+        );
+        assert_eq!(channels::market_addr(&channels::user(market)), None);
+        assert_eq!(channels::market_addr(&channels::announcements()), None);
+
+        assert_eq!(
+            channels::parse(&channels::user(market)),
+            Some(Channel::User(market.to_string()))
+        );
+        assert_eq!(
+            channels::parse(&channels::announcements()),
+            Some(Channel::Announcements)
+        );
+        assert_eq!(
+            channels::parse(&channels::ticker(market)),
+            Some(Channel::Ticker(market.to_string()))
+        );
+        assert_eq!(
+            channels::parse(&channels::liquidations(market)),
+            Some(Channel::Liquidations(market.to_string()))
+        );
+        assert_eq!(channels::parse("funding/0x1"), None);
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_reports_lag() {
+        let (tx, rx) = broadcast::channel(2);
+        let reported = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_reported = reported.clone();
+        let mut stream =
+            EventStream::new(rx, "trades/0x1".to_string()).with_lag_hook(move |channel, missed| {
+                hook_reported
+                    .lock()
+                    .unwrap()
+                    .push((channel.to_string(), missed))
+            });
+
+        for timestamp in 0..5 {
+            tx.send(WsEvent::Trade {
+                market_addr: "0x1".to_string(),
+                price: 1,
+                size: 1,
+                side: "buy".to_string(),
+                timestamp,
+            })
+            .unwrap();
+        }
+
+        match stream.recv().await {
+            Err(EkidenError::Lagged { missed, channel }) => {
+                assert_eq!(missed, 3);
+                assert_eq!(channel, "trades/0x1");
+            }
+            other => panic!("expected lag, got {:?}", other),
+        }
+        assert!(matches!(
+            stream.try_recv(),
+            Ok(WsEvent::Trade { timestamp: 3, .. })
+        ));
+        assert_eq!(stream.missed(), 3);
+        assert_eq!(
+            *reported.lock().unwrap(),
+            vec![("trades/0x1".to_string(), 3)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lag_policies() {
+        let (tx, rx) = broadcast::channel(2);
+        let mut stream =
+            EventStream::new(rx, "trades/0x1".to_string()).with_lag_policy(LagPolicy::DropOldest);
+        for timestamp in 0..5 {
+            tx.send(WsEvent::Trade {
+                market_addr: "0x1".to_string(),
+                price: 1,
+                size: 1,
+                side: "buy".to_string(),
+                timestamp,
+            })
+            .unwrap();
+        }
+        assert!(matches!(
+            stream.recv().await,
+            Ok(WsEvent::Trade { timestamp: 3, .. })
+        ));
+        assert_eq!(stream.missed(), 3);
+
+        let (tx, rx) = mpsc::channel(1);
+        let mut stream = EventStream::bounded(rx, "announcements".to_string());
+        let resync = WsEvent::Resync {
+            channel: "announcements".to_string(),
+            expected: 1,
+            received: 2,
+        };
+        tx.try_send(resync.clone()).unwrap();
+        assert!(tx.try_send(resync).is_err());
+        assert!(matches!(stream.try_recv(), Ok(WsEvent::Resync { .. })));
+        assert_eq!(stream.missed(), 0);
+        drop(tx);
+        assert!(matches!(
+            stream.recv().await,
+            Err(EkidenError::ConnectionClosed)
+        ));
+
+        let client = WebSocketClient::new(Url::parse("ws://localhost:3010/ws").unwrap());
+        let options = SubscriptionOptions::new().with_capacity(0);
+        assert!(matches!(
+            client.subscribe_with("announcements", options).await,
+            Err(EkidenError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_latency_stats() {
+        assert_eq!(LatencyStats::from_samples(&[]), None);
+        let samples: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let stats = LatencyStats::from_samples(&samples).unwrap();
+        assert_eq!(stats.last, Duration::from_millis(1));
+        assert_eq!(stats.p99, Duration::from_millis(99));
+        assert_eq!(stats.avg, Duration::from_micros(50_500));
+        assert_eq!(stats.samples, 100);
+
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut latency = Latency::default();
+        latency.pong(None, at(0));
+        assert!(latency.samples.is_empty());
+        latency.ping(at(0));
+        latency.pong(None, at(5));
+        latency.pong(None, at(6));
+        assert_eq!(latency.samples, [Duration::from_millis(5)]);
+
+        let first = latency.ping(at(10));
+        let second = latency.ping(at(20));
+        latency.pong(Some(second), at(25));
+        latency.pong(Some(first), at(30));
+        assert_eq!(
+            latency.samples,
+            [Duration::from_millis(5), Duration::from_millis(5)]
+        );
+        assert!(latency.pings.is_empty());
+    }
+
+    #[test]
+    fn test_reconnect_backoff() {
+        let reconnect = ReconnectConfig::new()
+            .with_initial_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_secs(1));
+        assert_eq!(reconnect.backoff(0), Duration::from_millis(100));
+        assert_eq!(reconnect.backoff(2), Duration::from_millis(400));
+        assert_eq!(reconnect.backoff(10), Duration::from_secs(1));
+        assert_eq!(reconnect.backoff(u32::MAX), Duration::from_secs(1));
     }
 
     #[test]
@@ -380,4 +2162,231 @@ mod tests {
 
         assert_eq!(client.url, url);
     }
+
+    /// Connector whose socket accepts the connection but never takes a frame
+    #[derive(Debug)]
+    struct WedgedConnector;
+
+    struct WedgedSink;
+
+    impl futures_util::Sink<Message> for WedgedSink {
+        type Error = EkidenError;
+
+        fn poll_ready(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<()>> {
+            std::task::Poll::Pending
+        }
+
+        fn start_send(self: std::pin::Pin<&mut Self>, _item: Message) -> Result<()> {
+            Ok(())
+        }
+
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<()>> {
+            std::task::Poll::Pending
+        }
+
+        fn poll_close(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<()>> {
+            std::task::Poll::Pending
+        }
+    }
+
+    impl WsConnector for WedgedConnector {
+        fn connect<'a>(
+            &'a self,
+            _url: &'a Url,
+        ) -> futures_util::future::BoxFuture<'a, Result<(WsFrameSink, WsFrameStream)>> {
+            Box::pin(async {
+                let sink: WsFrameSink = Box::pin(WedgedSink);
+                Ok((sink, futures_util::stream::pending().boxed()))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_control_operations_time_out() {
+        let url = Url::parse("ws://localhost:3010/ws").unwrap();
+        let mut client = WebSocketClient::new(url)
+            .with_connector(Arc::new(WedgedConnector))
+            .with_control_timeout(Duration::from_millis(50));
+        client.connect().await.unwrap();
+
+        assert!(matches!(client.ping().await, Err(EkidenError::Timeout)));
+        assert!(matches!(
+            client.subscribe("trades/0x1").await,
+            Err(EkidenError::Timeout)
+        ));
+        assert!(!client.is_subscribed("trades/0x1").await);
+        assert!(matches!(
+            client.unsubscribe("trades/0x1").await,
+            Err(EkidenError::Timeout)
+        ));
+        client.disconnect().await.unwrap();
+    }
+
+    /// Connector whose server takes every frame but never answers
+    #[derive(Debug, Default)]
+    struct SilentConnector {
+        connects: std::sync::atomic::AtomicUsize,
+    }
+
+    impl WsConnector for SilentConnector {
+        fn connect<'a>(
+            &'a self,
+            _url: &'a Url,
+        ) -> futures_util::future::BoxFuture<'a, Result<(WsFrameSink, WsFrameStream)>> {
+            self.connects
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Box::pin(async {
+                let sink: WsFrameSink =
+                    Box::pin(futures_util::sink::drain().sink_map_err(|never| match never {}));
+                Ok((sink, futures_util::stream::pending().boxed()))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missed_pong_drops_connection() {
+        let url = Url::parse("ws://localhost:3010/ws").unwrap();
+        let connector = Arc::new(SilentConnector::default());
+        let heartbeat = HeartbeatConfig::new(Duration::from_millis(20), Duration::from_millis(20));
+        let mut client = WebSocketClient::new(url.clone())
+            .with_connector(connector.clone())
+            .with_heartbeat(Some(heartbeat));
+        let mut status = client.status_stream();
+        client.connect().await.unwrap();
+        assert_eq!(*status.borrow_and_update(), ConnectionStatus::Connected);
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.is_connected().await {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            client.connection_status().await,
+            ConnectionStatus::Failed("heartbeat timed out".to_string())
+        );
+        assert!(status.has_changed().unwrap());
+        assert!(matches!(
+            *status.borrow_and_update(),
+            ConnectionStatus::Failed(_)
+        ));
+        assert!(client.ping().await.is_err());
+
+        // With reconnection enabled the stale connection is replaced
+        let connector = Arc::new(SilentConnector::default());
+        let mut client = WebSocketClient::new(url)
+            .with_connector(connector.clone())
+            .with_heartbeat(Some(heartbeat))
+            .with_reconnect(Some(
+                ReconnectConfig::new().with_initial_delay(Duration::from_millis(1)),
+            ));
+        client.connect().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while connector.connects.load(std::sync::atomic::Ordering::SeqCst) < 2 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        client.disconnect().await.unwrap();
+    }
+
+    /// Connector that records each URL and refuses the primary after the first connect
+    #[derive(Debug, Default)]
+    struct FlakyPrimaryConnector {
+        urls: std::sync::Mutex<Vec<Url>>,
+    }
+
+    impl WsConnector for FlakyPrimaryConnector {
+        fn connect<'a>(
+            &'a self,
+            url: &'a Url,
+        ) -> futures_util::future::BoxFuture<'a, Result<(WsFrameSink, WsFrameStream)>> {
+            let mut urls = self.urls.lock().unwrap();
+            urls.push(url.clone());
+            let refused = urls.len() > 1 && url.host_str() == Some("primary");
+            Box::pin(async move {
+                if refused {
+                    return Err(EkidenError::WebSocket("connection refused".to_string()));
+                }
+                let sink: WsFrameSink =
+                    Box::pin(futures_util::sink::drain().sink_map_err(|never| match never {}));
+                Ok((sink, futures_util::stream::pending().boxed()))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_fails_over_to_backup() {
+        let config = crate::EkidenConfig::new("http://primary/api/v1")
+            .unwrap()
+            .with_backup_url("http://backup/api/v1")
+            .unwrap();
+        let endpoints = Arc::new(Endpoints::new(&config, crate::clock::system()));
+        let connector = Arc::new(FlakyPrimaryConnector::default());
+        let heartbeat = HeartbeatConfig::new(Duration::from_millis(20), Duration::from_millis(20));
+        let mut client = WebSocketClient::new(endpoints.websocket_url(0).clone())
+            .with_connector(connector.clone())
+            .with_heartbeat(Some(heartbeat))
+            .with_endpoints(endpoints.clone())
+            .with_reconnect(Some(
+                ReconnectConfig::new().with_initial_delay(Duration::from_millis(1)),
+            ));
+        client.connect().await.unwrap();
+
+        // The heartbeat drops the silent connection; the primary then refuses
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !connector
+                .urls
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|url| url.host_str() == Some("backup"))
+            {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(endpoints.active(), 1);
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reader_does_not_outlive_client() {
+        let url = Url::parse("ws://localhost:3010/ws").unwrap();
+        let mut client =
+            WebSocketClient::new(url).with_connector(Arc::new(SilentConnector::default()));
+
+        // Each reader holds the subscriptions, so the count shows live readers
+        for _ in 0..5 {
+            client.connect().await.unwrap();
+            assert_eq!(Arc::strong_count(&client.subscriptions), 2);
+            client.disconnect().await.unwrap();
+            assert_eq!(Arc::strong_count(&client.subscriptions), 1);
+        }
+
+        client.connect().await.unwrap();
+        let subscriptions = Arc::downgrade(&client.subscriptions);
+        let mut status = client.status_stream();
+        drop(client);
+        assert_eq!(*status.borrow_and_update(), ConnectionStatus::Disconnected);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while subscriptions.upgrade().is_some() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
 }
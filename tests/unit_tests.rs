@@ -218,14 +218,154 @@ async fn test_error_types() {
 fn test_ws_request_serialization() {
     use ekiden_rust_sdk::WsRequest;
 
-    let ping = WsRequest::Ping;
+    let ping = WsRequest::Ping { id: Some(3) };
     let serialized = serde_json::to_string(&ping).unwrap();
     assert!(serialized.contains("ping"));
+    assert!(serialized.contains("\"id\":3"));
 
     let subscribe = WsRequest::Subscribe {
         channel: "orderbook/0x123".to_string(),
+        id: Some(7),
     };
     let serialized = serde_json::to_string(&subscribe).unwrap();
     assert!(serialized.contains("subscribe"));
     assert!(serialized.contains("orderbook/0x123"));
+    assert!(serialized.contains("\"id\":7"));
+}
+
+#[test]
+fn test_trigger_order_direction() {
+    use ekiden_rust_sdk::TriggerOrder;
+
+    let market = "0x1111111111111111111111111111111111111111";
+    let sell_stop = TriggerOrder::stop_market(market, OrderSide::Sell, 10, 100);
+    assert!(sell_stop.is_triggered(100) && sell_stop.is_triggered(99));
+    assert!(!sell_stop.is_triggered(101));
+    let buy_stop = TriggerOrder::stop_limit(market, OrderSide::Buy, 10, 100, 101);
+    assert!(buy_stop.is_triggered(101) && !buy_stop.is_triggered(99));
+    let sell_take_profit = TriggerOrder::take_profit(market, OrderSide::Sell, 10, 100);
+    assert!(sell_take_profit.is_triggered(101) && !sell_take_profit.is_triggered(99));
+
+    assert!(TriggerOrder::stop_market(market, OrderSide::Sell, 0, 100)
+        .validate()
+        .is_err());
+    let mut no_limit = buy_stop.clone();
+    no_limit.price = None;
+    assert!(matches!(
+        no_limit.validate(),
+        Err(EkidenError::Validation(_))
+    ));
+}
+
+#[test]
+fn test_intent_action_payloads() {
+    use ekiden_rust_sdk::{
+        ActionPayload, IntentAction, OrderType, TimeInForce, TriggerOrder, TriggerType,
+    };
+
+    let market = "0x1111111111111111111111111111111111111111";
+    let order = IntentAction::limit_order(market, OrderSide::Buy, 64_000, 10);
+    let payload = ActionPayload::from(order.clone());
+    assert_eq!(payload.action_type, "place_order");
+    assert_eq!(payload.data["price"], "64000");
+    assert_eq!(payload.data["order_type"], "limit");
+    assert_eq!(
+        payload.data,
+        ActionPayload::place_order(market, OrderSide::Buy, OrderType::Limit, 64_000, 10).data
+    );
+
+    // Flags are only sent when set
+    assert!(payload.data.get("time_in_force").is_none());
+    assert!(payload.data.get("post_only").is_none());
+    let flagged = IntentAction::limit_order(market, OrderSide::Sell, 64_000, 10)
+        .with_time_in_force(TimeInForce::Ioc)
+        .with_reduce_only(true);
+    let payload = ActionPayload::from(flagged.clone());
+    assert_eq!(payload.data["time_in_force"], "ioc");
+    assert_eq!(payload.data["reduce_only"], true);
+
+    let margin = IntentAction::adjust_margin(market, -500);
+    let payload = ActionPayload::from(margin.clone());
+    assert_eq!(payload.data["amount"], "-500");
+
+    // Payloads parse back into the same typed actions
+    for action in [
+        order,
+        flagged,
+        IntentAction::limit_order(market, OrderSide::Buy, 63_000, 10).with_post_only(true),
+        margin,
+        IntentAction::cancel_order(market, "1a30"),
+        IntentAction::withdraw(market, 1_000),
+        IntentAction::set_leverage(market, 5),
+        IntentAction::PlaceTriggerOrder(
+            TriggerOrder::stop_limit(market, OrderSide::Sell, 10, 60_000, 59_900)
+                .with_trigger_type(TriggerType::Oracle),
+        )
+        .with_reduce_only(true),
+    ] {
+        let payload = ActionPayload::from(action.clone());
+        assert_eq!(IntentAction::try_from(&payload).unwrap(), action);
+    }
+
+    let unknown = ActionPayload::new("transfer", serde_json::json!({}));
+    assert!(matches!(
+        IntentAction::try_from(&unknown),
+        Err(EkidenError::Validation(_))
+    ));
+}
+
+#[test]
+fn test_intent_status_is_final() {
+    use ekiden_rust_sdk::types::SendIntentResponse;
+
+    let intent = |status: &str| SendIntentResponse {
+        seq: 1,
+        status: status.to_string(),
+        outputs: Vec::new(),
+    };
+    assert!(intent("success").is_final() && intent("success").is_success());
+    assert!(intent("Rejected").is_final() && !intent("Rejected").is_success());
+    for status in ["pending", "accepted", "confirming", ""] {
+        assert!(!intent(status).is_final(), "{} is not final", status);
+    }
+}
+
+#[test]
+fn test_transfer_state() {
+    use ekiden_rust_sdk::types::TransferState;
+
+    assert_eq!(TransferState::parse("Credited"), TransferState::Completed);
+    assert_eq!(TransferState::parse("reverted"), TransferState::Failed);
+    for status in ["pending", "indexed", ""] {
+        assert_eq!(TransferState::parse(status), TransferState::Pending);
+    }
+}
+
+#[test]
+fn test_order_state() {
+    use ekiden_rust_sdk::types::OrderState;
+
+    assert_eq!(OrderState::parse("NEW"), OrderState::Open);
+    assert_eq!(OrderState::parse("partial"), OrderState::PartiallyFilled);
+    assert_eq!(OrderState::parse("canceled"), OrderState::Cancelled);
+    assert_eq!(OrderState::parse("expired"), OrderState::Closed);
+    assert!(OrderState::PartiallyFilled.is_open());
+    assert!(!OrderState::Filled.is_open());
+}
+
+#[test]
+fn test_simulated_output_sums_large_fills() {
+    use ekiden_rust_sdk::types::{SimulatedFill, SimulatedOutput};
+
+    let fill = SimulatedFill {
+        price: 7,
+        size: u64::MAX,
+        ..Default::default()
+    };
+    let output = SimulatedOutput {
+        fills: vec![fill, fill],
+        ..Default::default()
+    };
+    assert_eq!(output.filled_size(), u64::MAX);
+    assert_eq!(output.average_price(), Some(7));
 }